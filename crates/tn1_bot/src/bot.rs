@@ -0,0 +1,245 @@
+use rand::Rng;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tn1_shared::codec::*;
+use tn1_shared::protocol::*;
+use tn1_shared::transport::SecureStream;
+use crate::stats::BotStats;
+
+pub struct BotConfig {
+    pub server_address: String,
+    pub tls: Option<Arc<rustls::ClientConfig>>,
+    /// Inputs enviados por segundo (el cliente real envía uno por frame)
+    pub input_rate: f32,
+    /// Probabilidad por segundo de desconectarse y volver con `Reconnect`
+    pub reconnect_chance: f32,
+    pub codecs: Vec<WireCodec>,
+}
+
+enum SessionEnd {
+    Stopped,
+    Reconnect { session_token: Option<String> },
+}
+
+/// Loop principal de un bot: conecta, juega y se reconecta hasta que se pida parar
+pub fn run_bot(index: usize, config: Arc<BotConfig>, stats: Arc<Mutex<BotStats>>, stop: Arc<AtomicBool>) {
+    let username = format!("bot_{:04}", index);
+    let mut session_token = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        let result = run_session(&username, session_token.take(), &config, &stats, &stop);
+        {
+            let mut stats = stats.lock().unwrap();
+            stats.connected = false;
+            stats.queued = false;
+        }
+
+        match result {
+            Ok(SessionEnd::Stopped) => break,
+            Ok(SessionEnd::Reconnect { session_token: token }) => {
+                session_token = token;
+                stats.lock().unwrap().reconnects += 1;
+                thread::sleep(Duration::from_millis(rand::thread_rng().gen_range(500..2000)));
+            }
+            Err(e) => {
+                eprintln!("❌ {}: {}", username, e);
+                stats.lock().unwrap().connect_failures += 1;
+                thread::sleep(Duration::from_secs(2));
+            }
+        }
+    }
+}
+
+fn run_session(
+    username: &str,
+    session_token: Option<String>,
+    config: &BotConfig,
+    stats: &Arc<Mutex<BotStats>>,
+    stop: &AtomicBool,
+) -> std::io::Result<SessionEnd> {
+    let socket = TcpStream::connect(&config.server_address)?;
+    socket.set_nodelay(true)?;
+    let mut stream = match &config.tls {
+        Some(tls) => SecureStream::connect(socket, tls.clone())?,
+        None => SecureStream::plain(socket),
+    };
+    // Lecturas cortas para intercalar recepción y envío de inputs en un solo thread
+    stream.socket().set_read_timeout(Some(Duration::from_millis(2)))?;
+
+    let mut codec = WireCodec::Json;
+    send(&mut stream, codec, stats, &ClientMessage::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        client_name: username.to_string(),
+        codecs: config.codecs.clone(),
+    })?;
+
+    let started = Instant::now();
+    let mut frames = FrameBuffer::new();
+    let mut buffer = [0u8; 8192];
+    let mut brain = WanderBrain::new();
+    let mut sequence = 0u32;
+    let mut connected = false;
+    let mut token = session_token.clone();
+
+    let input_interval = Duration::from_secs_f32(1.0 / config.input_rate.max(1.0));
+    let mut next_input = Instant::now();
+    let mut next_second = Instant::now() + Duration::from_secs(1);
+
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            let _ = send(&mut stream, codec, stats, &ClientMessage::Disconnect);
+            let _ = stream.shutdown(Shutdown::Both);
+            return Ok(SessionEnd::Stopped);
+        }
+
+        match stream.read(&mut buffer) {
+            Ok(0) => return Err(std::io::ErrorKind::ConnectionAborted.into()),
+            Ok(n) => {
+                stats.lock().unwrap().bytes_received += n as u64;
+                frames.extend(&buffer[..n]);
+            }
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e),
+        }
+
+        while let Some(payload) = frames.next_frame().map_err(invalid_data)? {
+            let message = codec.decode::<ServerMessage>(&payload).map_err(invalid_data)?;
+            stats.lock().unwrap().messages_received += 1;
+
+            match message {
+                ServerMessage::Welcome { codec: selected, .. } => {
+                    codec = selected;
+                    let auth = match token.clone() {
+                        Some(session_token) => ClientMessage::Reconnect { session_token },
+                        None => ClientMessage::Login {
+                            username: username.to_string(),
+                            password: "bot_password".to_string(),
+                        },
+                    };
+                    send(&mut stream, codec, stats, &auth)?;
+                }
+                ServerMessage::Queued { .. } => {
+                    stats.lock().unwrap().queued = true;
+                }
+                ServerMessage::Connected { session_token, .. } => {
+                    connected = true;
+                    token = Some(session_token);
+                    let mut stats = stats.lock().unwrap();
+                    stats.connected = true;
+                    stats.queued = false;
+                }
+                ServerMessage::WorldState { tick, timestamp, .. } => {
+                    stats.lock().unwrap().record_snapshot(tick, timestamp, started.elapsed().as_secs_f64());
+                }
+                ServerMessage::Ping { timestamp } => {
+                    send(&mut stream, codec, stats, &ClientMessage::Pong { timestamp })?;
+                }
+                ServerMessage::Pong { timestamp } => {
+                    let rtt = (started.elapsed().as_secs_f64() - timestamp) as f32;
+                    stats.lock().unwrap().rtt = Some(rtt);
+                }
+                ServerMessage::ConnectionError { reason } | ServerMessage::AuthError { reason } => {
+                    return Err(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, reason));
+                }
+                _ => {}
+            }
+        }
+
+        if !connected {
+            continue;
+        }
+
+        let now = Instant::now();
+        if now >= next_input {
+            let dt = input_interval.as_secs_f32();
+            next_input += input_interval;
+            sequence += 1;
+            send(&mut stream, codec, stats, &ClientMessage::PlayerInput {
+                sequence,
+                input: brain.next_input(dt),
+            })?;
+        }
+
+        if now >= next_second {
+            next_second += Duration::from_secs(1);
+            send(&mut stream, codec, stats, &ClientMessage::Ping { timestamp: started.elapsed().as_secs_f64() })?;
+
+            if rand::thread_rng().gen::<f32>() < config.reconnect_chance {
+                send(&mut stream, codec, stats, &ClientMessage::Disconnect)?;
+                let _ = stream.shutdown(Shutdown::Both);
+                return Ok(SessionEnd::Reconnect { session_token: token });
+            }
+        }
+    }
+}
+
+fn send(
+    stream: &mut SecureStream,
+    codec: WireCodec,
+    stats: &Mutex<BotStats>,
+    message: &ClientMessage,
+) -> std::io::Result<()> {
+    let data = codec.encode_frame(message).map_err(invalid_data)?;
+    stream.write_all(&data)?;
+    stats.lock().unwrap().bytes_sent += data.len() as u64;
+    Ok(())
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
+
+/// Comportamiento scripted: camina girando poco a poco, a ratos corre y salta
+struct WanderBrain {
+    yaw: f32,
+    turn_rate: f32,
+    sprinting: bool,
+    moving: bool,
+    next_decision: f32,
+}
+
+impl WanderBrain {
+    fn new() -> Self {
+        let mut rng = rand::thread_rng();
+        Self {
+            yaw: rng.gen_range(0.0..std::f32::consts::TAU),
+            turn_rate: 0.0,
+            sprinting: false,
+            moving: true,
+            next_decision: 0.0,
+        }
+    }
+
+    fn next_input(&mut self, dt: f32) -> PlayerInput {
+        let mut rng = rand::thread_rng();
+
+        self.next_decision -= dt;
+        if self.next_decision <= 0.0 {
+            self.next_decision = rng.gen_range(1.0..4.0);
+            self.turn_rate = rng.gen_range(-1.5..1.5);
+            self.sprinting = rng.gen_bool(0.3);
+            self.moving = rng.gen_bool(0.85);
+        }
+        self.yaw = (self.yaw + self.turn_rate * dt).rem_euclid(std::f32::consts::TAU);
+
+        PlayerInput {
+            move_forward: self.moving,
+            move_backward: false,
+            move_left: false,
+            move_right: false,
+            jump: rng.gen_bool((0.5 * dt).clamp(0.0, 1.0) as f64),
+            sprint: self.sprinting && self.moving,
+            camera_yaw: self.yaw,
+            camera_pitch: 0.0,
+            flashlight: false,
+            fire: false,
+            aim: false,
+            reload: false,
+            switch_fire_mode: false,
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tn1_shared::codec::WireCodec;
+use tn1_shared::protocol::DEFAULT_PORT;
+use tn1_shared::transport;
+
+mod bot;
+mod stats;
+
+use bot::{run_bot, BotConfig};
+use stats::{print_report, BotStats};
+
+/// Opciones de línea de comandos
+struct BotArgs {
+    bots: usize,
+    server_address: String,
+    duration: Option<Duration>,
+    report_interval: Duration,
+    input_rate: f32,
+    reconnect_chance: f32,
+    spawn_interval: Duration,
+    codec: Option<WireCodec>,
+    min_connected: usize,
+}
+
+impl Default for BotArgs {
+    fn default() -> Self {
+        Self {
+            bots: 10,
+            server_address: std::env::var("TN1_SERVER_ADDR")
+                .unwrap_or_else(|_| format!("127.0.0.1:{}", DEFAULT_PORT)),
+            duration: None,
+            report_interval: Duration::from_secs(5),
+            input_rate: 60.0,
+            reconnect_chance: 0.0,
+            spawn_interval: Duration::from_millis(50),
+            codec: None,
+            min_connected: 0,
+        }
+    }
+}
+
+const USAGE: &str = "\
+Uso: tn1-bot [opciones]
+
+  --bots N              Número de jugadores simulados (10)
+  --server ADDR         Servidor (TN1_SERVER_ADDR o 127.0.0.1:7777)
+  --duration SECS       Terminar tras SECS segundos (por defecto, hasta Ctrl+C)
+  --report SECS         Intervalo entre reportes (5)
+  --input-rate HZ       Inputs por segundo y bot (60)
+  --reconnect-chance P  Probabilidad por segundo de reconectar (0.0)
+  --spawn-interval MS   Espera entre conexiones de bots (50)
+  --codec json|bincode  Forzar un codec
+  --min-connected N     Código de salida 1 si al final hay menos de N bots conectados (CI)
+
+TLS se configura igual que el cliente: TN1_TLS, TN1_TLS_PIN, TN1_TLS_PIN_FILE.";
+
+fn parse_args() -> Result<BotArgs, String> {
+    let mut args = BotArgs::default();
+    let mut iter = std::env::args().skip(1);
+
+    while let Some(flag) = iter.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{}", USAGE);
+            std::process::exit(0);
+        }
+
+        let value = iter.next().ok_or_else(|| format!("falta el valor de {}", flag))?;
+        let number = |value: &str| value.parse::<f64>().map_err(|_| format!("valor inválido para {}: {}", flag, value));
+
+        match flag.as_str() {
+            "--bots" => args.bots = number(&value)? as usize,
+            "--server" => args.server_address = value,
+            "--duration" => args.duration = Some(Duration::from_secs_f64(number(&value)?)),
+            "--report" => args.report_interval = Duration::from_secs_f64(number(&value)?.max(0.1)),
+            "--input-rate" => args.input_rate = number(&value)? as f32,
+            "--reconnect-chance" => args.reconnect_chance = number(&value)?.clamp(0.0, 1.0) as f32,
+            "--spawn-interval" => args.spawn_interval = Duration::from_millis(number(&value)? as u64),
+            "--codec" => {
+                args.codec = Some(match value.as_str() {
+                    "json" => WireCodec::Json,
+                    "bincode" => WireCodec::Bincode,
+                    other => return Err(format!("codec desconocido: {}", other)),
+                })
+            }
+            "--min-connected" => args.min_connected = number(&value)? as usize,
+            other => return Err(format!("opción desconocida: {}", other)),
+        }
+    }
+
+    Ok(args)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("❌ {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let tls = if transport::tls_enabled_from_env() {
+        let pins = transport::pins_from_env();
+        if pins.is_empty() {
+            eprintln!("❌ TLS activo pero sin pin de certificado (TN1_TLS_PIN o certs/server_cert.sha256)");
+            std::process::exit(2);
+        }
+        Some(transport::pinned_client_config(pins))
+    } else {
+        None
+    };
+
+    let config = Arc::new(BotConfig {
+        server_address: args.server_address.clone(),
+        tls,
+        input_rate: args.input_rate,
+        reconnect_chance: args.reconnect_chance,
+        codecs: args.codec.map_or_else(|| WireCodec::SUPPORTED.to_vec(), |codec| vec![codec]),
+    });
+
+    println!("🤖 Lanzando {} bots contra {}", args.bots, args.server_address);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut all_stats = Vec::with_capacity(args.bots);
+    let mut handles = Vec::with_capacity(args.bots);
+
+    for index in 0..args.bots {
+        let stats = Arc::new(Mutex::new(BotStats::default()));
+        all_stats.push(stats.clone());
+
+        let config = config.clone();
+        let stop = stop.clone();
+        handles.push(thread::spawn(move || run_bot(index, config, stats, stop)));
+
+        // Escalonar las conexiones para no saturar el accept del servidor
+        thread::sleep(args.spawn_interval);
+    }
+
+    let started = Instant::now();
+    let mut previous: Vec<BotStats> = all_stats.iter().map(|s| s.lock().unwrap().clone()).collect();
+
+    loop {
+        thread::sleep(args.report_interval);
+
+        let current: Vec<BotStats> = all_stats.iter().map(|s| s.lock().unwrap().clone()).collect();
+        print_report(&current, &previous, started.elapsed(), args.report_interval);
+        previous = current;
+
+        if args.duration.is_some_and(|duration| started.elapsed() >= duration) {
+            break;
+        }
+    }
+
+    let connected = previous.iter().filter(|s| s.connected).count();
+
+    stop.store(true, Ordering::Relaxed);
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    println!("🏁 Prueba terminada: {}/{} bots conectados al final", connected, args.bots);
+    if connected < args.min_connected {
+        eprintln!("❌ Se esperaban al menos {} bots conectados", args.min_connected);
+        std::process::exit(1);
+    }
+}
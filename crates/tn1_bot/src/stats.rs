@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+/// Métricas de un bot, compartidas con el thread que imprime los reportes
+#[derive(Default, Clone)]
+pub struct BotStats {
+    pub connected: bool,
+    pub queued: bool,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub messages_received: u64,
+    pub snapshots: u64,
+    pub reconnects: u32,
+    pub connect_failures: u32,
+    /// RTT medido con `Ping`/`Pong` propios (segundos)
+    pub rtt: Option<f32>,
+    /// Periodo de tick del servidor deducido de los snapshots (segundos)
+    pub server_tick_time: Option<f64>,
+    /// Intervalo medio entre snapshots recibidos y su desviación (segundos)
+    pub snapshot_interval: RunningAverage,
+    pub snapshot_jitter: RunningAverage,
+    /// Retraso de cada snapshot respecto al más rápido visto (reloj del servidor vs local)
+    pub snapshot_delay: RunningAverage,
+    pub last_snapshot_at: Option<Instant>,
+    pub last_snapshot_tick: Option<(u32, f64)>,
+    min_clock_offset: Option<f64>,
+}
+
+impl BotStats {
+    /// Registra la llegada de un `WorldState`
+    pub fn record_snapshot(&mut self, tick: u32, server_time: f64, local_time: f64) {
+        let now = Instant::now();
+        self.snapshots += 1;
+
+        if let Some(previous) = self.last_snapshot_at {
+            let interval = now.duration_since(previous).as_secs_f64();
+            let mean = self.snapshot_interval.mean();
+            self.snapshot_interval.add(interval);
+            if mean > 0.0 {
+                self.snapshot_jitter.add((interval - mean).abs());
+            }
+        }
+        self.last_snapshot_at = Some(now);
+
+        if let Some((previous_tick, previous_time)) = self.last_snapshot_tick {
+            if tick > previous_tick {
+                self.server_tick_time = Some((server_time - previous_time) / (tick - previous_tick) as f64);
+            }
+        }
+        self.last_snapshot_tick = Some((tick, server_time));
+
+        // Los relojes no están sincronizados: el snapshot que llegó más rápido fija la referencia
+        let offset = local_time - server_time;
+        let min_offset = self.min_clock_offset.map_or(offset, |min| min.min(offset));
+        self.min_clock_offset = Some(min_offset);
+        self.snapshot_delay.add(offset - min_offset);
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct RunningAverage {
+    total: f64,
+    count: u64,
+}
+
+impl RunningAverage {
+    pub fn add(&mut self, value: f64) {
+        self.total += value;
+        self.count += 1;
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total / self.count as f64
+        }
+    }
+}
+
+/// Resumen agregado de todos los bots en un intervalo
+pub fn print_report(stats: &[BotStats], previous: &[BotStats], elapsed: Duration, interval: Duration) {
+    let connected = stats.iter().filter(|s| s.connected).count();
+    let queued = stats.iter().filter(|s| s.queued).count();
+    let secs = interval.as_secs_f64().max(f64::EPSILON);
+
+    let sent: u64 = stats.iter().map(|s| s.bytes_sent).sum::<u64>()
+        - previous.iter().map(|s| s.bytes_sent).sum::<u64>();
+    let received: u64 = stats.iter().map(|s| s.bytes_received).sum::<u64>()
+        - previous.iter().map(|s| s.bytes_received).sum::<u64>();
+    let per_client = connected.max(1) as f64;
+
+    let rtts: Vec<f32> = stats.iter().filter_map(|s| s.rtt).collect();
+    let avg_rtt = if rtts.is_empty() { 0.0 } else { rtts.iter().sum::<f32>() / rtts.len() as f32 };
+    let max_rtt = rtts.iter().copied().fold(0.0, f32::max);
+
+    let tick_times: Vec<f64> = stats.iter().filter_map(|s| s.server_tick_time).collect();
+    let avg_tick = if tick_times.is_empty() { 0.0 } else { tick_times.iter().sum::<f64>() / tick_times.len() as f64 };
+
+    let avg = |f: fn(&BotStats) -> f64| -> f64 {
+        let values: Vec<f64> = stats.iter().filter(|s| s.snapshots > 1).map(f).collect();
+        if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 }
+    };
+
+    println!(
+        "📊 [{:>6.1}s] bots {}/{} (cola {}) | ↑ {:.1} KB/s ↓ {:.1} KB/s por cliente | RTT {:.0}ms (máx {:.0}ms) | tick servidor {:.2}ms | snapshots cada {:.1}ms ±{:.1}ms, retraso {:.1}ms | reconexiones {} fallos {}",
+        elapsed.as_secs_f64(),
+        connected,
+        stats.len(),
+        queued,
+        sent as f64 / secs / per_client / 1024.0,
+        received as f64 / secs / per_client / 1024.0,
+        avg_rtt * 1000.0,
+        max_rtt * 1000.0,
+        avg_tick * 1000.0,
+        avg(|s| s.snapshot_interval.mean()) * 1000.0,
+        avg(|s| s.snapshot_jitter.mean()) * 1000.0,
+        avg(|s| s.snapshot_delay.mean()) * 1000.0,
+        stats.iter().map(|s| s.reconnects).sum::<u32>(),
+        stats.iter().map(|s| s.connect_failures).sum::<u32>(),
+    );
+}
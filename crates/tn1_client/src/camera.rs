@@ -40,7 +40,8 @@ fn setup_camera(mut commands: Commands) {
     ));
 }
 
-fn handle_mouse_input(
+/// Yaw/pitch con el ratón capturado; también lo usa la cámara libre del visor de partidas
+pub fn handle_mouse_input(
     mut camera_query: Query<&mut PlayerCamera>,
     mut window_query: Query<&mut Window, With<PrimaryWindow>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
@@ -0,0 +1,193 @@
+//! Chat del cliente: historial de mensajes recibidos y comandos de la caja de texto.
+//!
+//! El filtrado y el reparto los hace el servidor; aquí solo se decide qué se
+//! envía, qué se muestra y a quién ignora localmente el jugador (`/mute`).
+
+use bevy::prelude::*;
+use std::collections::{HashSet, VecDeque};
+use tn1_shared::protocol::{ChatChannel, ClientMessage};
+use crate::networking::NetworkClient;
+
+/// Líneas que se guardan en el historial
+pub const CHAT_HISTORY: usize = 100;
+
+/// Resultado de interpretar lo que el jugador escribió en la caja de chat
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatCommand {
+    Say { channel: ChatChannel, text: String },
+    /// `/r`: responde al último susurro recibido
+    Reply(String),
+    JoinTeam(Option<String>),
+    /// Sintoniza la radio de voz (`None` la apaga)
+    Radio(Option<u16>),
+    Mute(String),
+    Unmute(String),
+    Help,
+}
+
+/// Interpreta una línea de la caja de chat; `None` si está vacía
+pub fn parse_chat_command(input: &str) -> Result<Option<ChatCommand>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let Some(command) = input.strip_prefix('/') else {
+        return Ok(Some(ChatCommand::Say { channel: ChatChannel::Global, text: input.to_string() }));
+    };
+
+    let (name, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let rest = rest.trim();
+    let text = |usage: &str| {
+        if rest.is_empty() { Err(format!("Uso: {}", usage)) } else { Ok(rest.to_string()) }
+    };
+
+    let command = match name.to_lowercase().as_str() {
+        "g" | "global" => ChatCommand::Say { channel: ChatChannel::Global, text: text("/g <mensaje>")? },
+        "l" | "local" | "p" => ChatCommand::Say { channel: ChatChannel::Proximity, text: text("/l <mensaje>")? },
+        "t" | "team" => ChatCommand::Say { channel: ChatChannel::Team, text: text("/team <mensaje>")? },
+        "w" | "whisper" | "msg" => {
+            let (to, message) = rest.split_once(char::is_whitespace).ok_or("Uso: /w <jugador> <mensaje>")?;
+            ChatCommand::Say {
+                channel: ChatChannel::Whisper { to: to.to_string() },
+                text: message.trim().to_string(),
+            }
+        }
+        "r" | "reply" => ChatCommand::Reply(text("/r <mensaje>")?),
+        "clan" => ChatCommand::JoinTeam(Some(rest.to_string()).filter(|team| !team.is_empty())),
+        "radio" => match rest {
+            "" | "off" => ChatCommand::Radio(None),
+            frequency => ChatCommand::Radio(Some(
+                frequency.parse().map_err(|_| "Uso: /radio <frecuencia> o /radio off".to_string())?,
+            )),
+        },
+        "mute" => ChatCommand::Mute(text("/mute <jugador>")?),
+        "unmute" => ChatCommand::Unmute(text("/unmute <jugador>")?),
+        "help" | "?" => ChatCommand::Help,
+        _ => return Err(format!("Comando desconocido: /{} (usa /help)", name)),
+    };
+    Ok(Some(command))
+}
+
+/// Línea del historial; sin canal es un aviso del sistema o del propio cliente
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatLine {
+    pub channel: Option<ChatChannel>,
+    pub sender: String,
+    pub text: String,
+}
+
+impl ChatLine {
+    /// Texto tal como se muestra en la caja de chat
+    pub fn display(&self, local_name: &str) -> String {
+        match &self.channel {
+            None => format!("* {}", self.text),
+            Some(ChatChannel::Global) => format!("[Global] {}: {}", self.sender, self.text),
+            Some(ChatChannel::Proximity) => format!("[Cerca] {}: {}", self.sender, self.text),
+            Some(ChatChannel::Team) => format!("[Equipo] {}: {}", self.sender, self.text),
+            Some(ChatChannel::Whisper { to }) if self.sender == local_name => format!("[a {}] {}", to, self.text),
+            Some(ChatChannel::Whisper { .. }) => format!("[de {}] {}", self.sender, self.text),
+        }
+    }
+}
+
+/// Historial del chat e ignorados locales
+#[derive(Resource, Default)]
+pub struct ChatHistory {
+    lines: VecDeque<ChatLine>,
+    /// Nombres en minúsculas silenciados con `/mute`
+    ignored: HashSet<String>,
+    /// Destinatario de `/r`
+    pub last_whisper_from: Option<String>,
+    /// La caja de texto tiene el foco: el input de movimiento se ignora
+    pub typing: bool,
+}
+
+impl ChatHistory {
+    pub fn lines(&self) -> impl Iterator<Item = &ChatLine> {
+        self.lines.iter()
+    }
+
+    /// Mensaje recibido del servidor; se descarta si el remitente está ignorado
+    pub fn push_message(&mut self, channel: ChatChannel, sender: String, text: String, local_name: &str) {
+        if self.is_ignored(&sender) {
+            return;
+        }
+        if matches!(channel, ChatChannel::Whisper { .. }) && sender != local_name {
+            self.last_whisper_from = Some(sender.clone());
+        }
+        self.push(ChatLine { channel: Some(channel), sender, text });
+    }
+
+    pub fn push_notice(&mut self, text: impl Into<String>) {
+        self.push(ChatLine { channel: None, sender: String::new(), text: text.into() });
+    }
+
+    pub fn is_ignored(&self, name: &str) -> bool {
+        self.ignored.contains(&name.to_lowercase())
+    }
+
+    fn push(&mut self, line: ChatLine) {
+        if self.lines.len() == CHAT_HISTORY {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// Ejecuta lo que el jugador escribió: envía al servidor o resuelve en local
+    pub fn submit(&mut self, input: &str, client: &NetworkClient) {
+        let command = match parse_chat_command(input) {
+            Ok(Some(command)) => command,
+            Ok(None) => return,
+            Err(e) => {
+                self.push_notice(e);
+                return;
+            }
+        };
+
+        let message = match command {
+            ChatCommand::Say { channel, text } => ClientMessage::Chat { channel, text },
+            ChatCommand::Reply(text) => {
+                let Some(to) = self.last_whisper_from.clone() else {
+                    self.push_notice("Nadie te ha susurrado todavía");
+                    return;
+                };
+                ClientMessage::Chat { channel: ChatChannel::Whisper { to }, text }
+            }
+            ChatCommand::JoinTeam(team) => ClientMessage::JoinTeam { team },
+            ChatCommand::Radio(frequency) => ClientMessage::TuneRadio { frequency },
+            ChatCommand::Mute(name) => {
+                self.ignored.insert(name.to_lowercase());
+                self.push_notice(format!("{} silenciado (solo para ti)", name));
+                return;
+            }
+            ChatCommand::Unmute(name) => {
+                let notice = if self.ignored.remove(&name.to_lowercase()) {
+                    format!("{} ya no está silenciado", name)
+                } else {
+                    format!("{} no estaba silenciado", name)
+                };
+                self.push_notice(notice);
+                return;
+            }
+            ChatCommand::Help => {
+                for line in [
+                    "texto o /g - chat global",
+                    "/l - jugadores cercanos",
+                    "/team - tu equipo (/clan <nombre> para unirte, /clan para salir)",
+                    "/w <jugador> - susurro, /r para responder",
+                    "/radio <frecuencia> - radio de voz (mantén B), /radio off para apagarla",
+                    "/mute y /unmute <jugador> - ignorar a alguien",
+                ] {
+                    self.push_notice(line);
+                }
+                return;
+            }
+        };
+
+        if !client.connected {
+            self.push_notice("Sin conexión con el servidor");
+        } else if let Err(e) = client.send(&message) {
+            self.push_notice(format!("No se pudo enviar: {}", e));
+        }
+    }
+}
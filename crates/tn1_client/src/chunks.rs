@@ -0,0 +1,466 @@
+//! Streaming de chunks en el cliente: pide los que faltan alrededor del jugador
+//! local, los construye poco a poco y descarta los que quedan lejos.
+//!
+//! Construir un chunk (mesh del terreno, props, contenedores) es caro, así que
+//! se spawnean como mucho `spawn_budget` por frame y se despawnean como mucho
+//! `unload_budget`: moverse rápido no provoca tirones.
+//!
+//! El terreno no viene del servidor: se genera aquí con la semilla de
+//! `WorldInfo`, con menos detalle cuanto más lejos está el chunk.
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use std::collections::{HashMap, VecDeque};
+use tn1_shared::chunk::*;
+use tn1_shared::protocol::{ClientMessage, WorldInfo};
+use tn1_shared::terrain::{lod_for_distance, TerrainGenerator, LOD_RESOLUTIONS};
+use crate::networking::NetworkClient;
+
+/// Profundidad de los faldones del terreno: tapan las grietas entre chunks con distinto LOD
+const SKIRT_DEPTH: f32 = 5.0;
+
+/// Ritmo de construcción y de peticiones
+#[derive(Resource, Debug, Clone)]
+pub struct ChunkStreamingSettings {
+    /// Chunks construidos por frame
+    pub spawn_budget: usize,
+    /// Chunks destruidos por frame
+    pub unload_budget: usize,
+    /// Segundos sin respuesta tras los que se vuelve a pedir un chunk
+    pub request_timeout: f64,
+}
+
+impl Default for ChunkStreamingSettings {
+    fn default() -> Self {
+        Self {
+            spawn_budget: 2,
+            unload_budget: 4,
+            request_timeout: 3.0,
+        }
+    }
+}
+
+pub struct ChunkStreamingPlugin;
+
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkStreamingSettings>()
+            .init_resource::<ClientChunks>()
+            .add_systems(Update, (
+                request_chunks,
+                spawn_chunks,
+                update_chunk_lods,
+                remove_fallback_ground,
+                unload_chunks,
+            ).chain());
+    }
+}
+
+/// Raíz de un chunk construido; terreno, props y contenedores cuelgan de ella
+#[derive(Component, Debug, Clone, Copy)]
+pub struct WorldChunk {
+    pub coord: ChunkCoord,
+}
+
+/// Contenedor de loot del mundo
+#[derive(Component, Debug, Clone, Copy)]
+pub struct WorldContainer {
+    pub id: u64,
+    pub kind: ContainerKind,
+}
+
+/// Mesh del terreno de un chunk y su nivel de detalle (0 = máximo)
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ChunkTerrain {
+    pub lod: usize,
+}
+
+/// Suelo provisional hasta que llega el primer chunk
+#[derive(Component)]
+pub struct FallbackGround;
+
+/// Chunks recibidos del servidor y construidos en la escena
+#[derive(Resource, Default)]
+pub struct ClientChunks {
+    world: Option<WorldInfo>,
+    terrain: Option<TerrainGenerator>,
+    /// Recibidos pero aún sin construir
+    received: VecDeque<ChunkData>,
+    spawned: HashMap<ChunkCoord, SpawnedChunk>,
+    /// Pedidos y sin respuesta, con el momento de la petición
+    requested: HashMap<ChunkCoord, f64>,
+}
+
+impl ClientChunks {
+    pub fn world(&self) -> Option<&WorldInfo> {
+        self.world.as_ref()
+    }
+
+    pub fn grid(&self) -> Option<ChunkGrid> {
+        self.world.as_ref().map(WorldInfo::grid)
+    }
+
+    pub fn set_world(&mut self, info: WorldInfo) {
+        info!("🧱 Mundo de {} m en chunks de {} m (radio de vista {})", info.world_size, info.chunk_size, info.view_radius);
+        self.terrain = Some(TerrainGenerator::new(info.seed));
+        self.world = Some(info);
+    }
+
+    /// Generador del terreno, el mismo que usa el servidor
+    pub fn terrain(&self) -> Option<TerrainGenerator> {
+        self.terrain
+    }
+
+    pub fn receive(&mut self, chunk: ChunkData) {
+        self.requested.remove(&chunk.coord);
+        self.received.push_back(chunk);
+    }
+
+    pub fn is_spawned(&self, coord: ChunkCoord) -> bool {
+        self.spawned.contains_key(&coord)
+    }
+
+    /// Chunks construidos en la escena
+    pub fn spawned(&self) -> impl Iterator<Item = ChunkCoord> + '_ {
+        self.spawned.keys().copied()
+    }
+
+    /// Nivel de detalle con el que está construido el terreno del chunk
+    pub fn lod(&self, coord: ChunkCoord) -> Option<usize> {
+        self.spawned.get(&coord).map(|chunk| chunk.lod)
+    }
+
+    /// Chunks recibidos que esperan su turno para construirse
+    pub fn pending(&self) -> usize {
+        self.received.len()
+    }
+}
+
+/// Chunk construido en la escena
+#[derive(Debug, Clone, Copy)]
+struct SpawnedChunk {
+    root: Entity,
+    revision: u32,
+    /// Hijo con el mesh del terreno (se sustituye al cambiar de LOD)
+    terrain: Entity,
+    lod: usize,
+}
+
+/// Posición del jugador local según el último snapshot
+fn local_position(client: &NetworkClient) -> Option<Vec3> {
+    let player_id = client.local_player_id?;
+    client.player_states.get(&player_id).map(|state| state.position)
+}
+
+fn request_chunks(
+    client: Res<NetworkClient>,
+    mut chunks: ResMut<ClientChunks>,
+    settings: Res<ChunkStreamingSettings>,
+    time: Res<Time>,
+) {
+    let (Some(world), Some(position)) = (chunks.world.clone(), local_position(&client)) else { return };
+    let now = time.elapsed_secs_f64();
+    chunks.requested.retain(|_, requested_at| now - *requested_at < settings.request_timeout);
+
+    let missing: Vec<ChunkCoord> = world
+        .grid()
+        .chunks_around(position, world.view_radius)
+        .into_iter()
+        .filter(|coord| {
+            !chunks.spawned.contains_key(coord)
+                && !chunks.requested.contains_key(coord)
+                && !chunks.received.iter().any(|chunk| chunk.coord == *coord)
+        })
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+
+    match client.send(&ClientMessage::RequestChunks { coords: missing.clone() }) {
+        Ok(()) => {
+            debug!("🧱 Pidiendo {} chunks", missing.len());
+            chunks.requested.extend(missing.into_iter().map(|coord| (coord, now)));
+        }
+        Err(e) => warn!("⚠️ Error pidiendo chunks: {}", e),
+    }
+}
+
+/// Meshes y materiales compartidos por todos los chunks
+struct ChunkAssets {
+    terrain: Handle<StandardMaterial>,
+    trunk: (Handle<Mesh>, Handle<StandardMaterial>),
+    canopy: (Handle<Mesh>, Handle<StandardMaterial>),
+    rock: (Handle<Mesh>, Handle<StandardMaterial>),
+    bush: (Handle<Mesh>, Handle<StandardMaterial>),
+    container: Handle<Mesh>,
+    container_materials: HashMap<ContainerKind, Handle<StandardMaterial>>,
+}
+
+impl ChunkAssets {
+    fn new(meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) -> Self {
+        let mut material = |r: f32, g: f32, b: f32| {
+            materials.add(StandardMaterial {
+                base_color: Color::srgb(r, g, b),
+                perceptual_roughness: 0.9,
+                ..default()
+            })
+        };
+        Self {
+            // El color sale de los vértices (bioma)
+            terrain: material(1.0, 1.0, 1.0),
+            trunk: (meshes.add(Cylinder::new(0.25, 3.0)), material(0.4, 0.28, 0.15)),
+            canopy: (meshes.add(Cone { radius: 1.8, height: 4.0 }), material(0.15, 0.4, 0.18)),
+            rock: (meshes.add(Sphere::new(0.8).mesh().ico(1).unwrap()), material(0.45, 0.45, 0.45)),
+            bush: (meshes.add(Sphere::new(0.7).mesh().ico(2).unwrap()), material(0.2, 0.45, 0.15)),
+            container: meshes.add(Cuboid::new(1.0, 0.6, 0.6)),
+            container_materials: HashMap::from([
+                (ContainerKind::Crate, material(0.55, 0.4, 0.2)),
+                (ContainerKind::Toolbox, material(0.7, 0.15, 0.1)),
+                (ContainerKind::MedicalBox, material(0.9, 0.9, 0.9)),
+            ]),
+        }
+    }
+}
+
+fn spawn_chunks(
+    mut commands: Commands,
+    mut chunks: ResMut<ClientChunks>,
+    client: Res<NetworkClient>,
+    settings: Res<ChunkStreamingSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<ChunkAssets>>,
+) {
+    let (Some(world), Some(terrain)) = (chunks.world.clone(), chunks.terrain) else { return };
+    let grid = world.grid();
+    let center = local_position(&client).map(|position| grid.coord_at(position));
+
+    let mut built = 0;
+    while built < settings.spawn_budget {
+        let Some(chunk) = chunks.received.pop_front() else { break };
+        // Llegó tarde y el jugador ya se alejó
+        if center.is_some_and(|center| chunk.coord.distance(center) > world.view_radius + 1) {
+            continue;
+        }
+        if let Some(spawned) = chunks.spawned.get(&chunk.coord) {
+            if spawned.revision >= chunk.revision {
+                continue;
+            }
+            commands.entity(spawned.root).despawn_recursive();
+        }
+
+        let lod = center.map_or(0, |center| lod_for_distance(chunk.coord.distance(center)));
+        let terrain_mesh = meshes.add(terrain_mesh(&terrain, &grid, chunk.coord, lod));
+        let assets = assets.get_or_insert_with(|| ChunkAssets::new(&mut meshes, &mut materials));
+        let (root, terrain) = spawn_chunk(&mut commands, assets, terrain_mesh, lod, &grid, &chunk);
+        chunks.spawned.insert(chunk.coord, SpawnedChunk { root, revision: chunk.revision, terrain, lod });
+        built += 1;
+    }
+}
+
+/// Reconstruye el terreno de los chunks cuyo LOD ya no corresponde a su distancia
+fn update_chunk_lods(
+    mut commands: Commands,
+    mut chunks: ResMut<ClientChunks>,
+    client: Res<NetworkClient>,
+    settings: Res<ChunkStreamingSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let (Some(world), Some(terrain)) = (chunks.world.clone(), chunks.terrain) else { return };
+    let Some(position) = local_position(&client) else { return };
+    let grid = world.grid();
+    let center = grid.coord_at(position);
+
+    // Primero los más cercanos: son los que más se notan
+    let mut stale: Vec<(ChunkCoord, usize)> = chunks
+        .spawned
+        .iter()
+        .map(|(coord, chunk)| (*coord, chunk.lod, lod_for_distance(coord.distance(center))))
+        .filter(|(_, current, wanted)| current != wanted)
+        .map(|(coord, _, wanted)| (coord, wanted))
+        .collect();
+    stale.sort_by_key(|(coord, _)| (coord.distance(center), *coord));
+
+    for (coord, lod) in stale.into_iter().take(settings.spawn_budget) {
+        let Some(chunk) = chunks.spawned.get_mut(&coord) else { continue };
+        // Al sustituir el mesh, el anterior se libera solo
+        let mesh = meshes.add(terrain_mesh(&terrain, &grid, coord, lod));
+        commands.entity(chunk.terrain).insert((Mesh3d(mesh), ChunkTerrain { lod }));
+        chunk.lod = lod;
+        debug!(%coord, lod, "🧱 LOD del terreno actualizado");
+    }
+}
+
+fn remove_fallback_ground(
+    mut commands: Commands,
+    chunks: Res<ClientChunks>,
+    fallback: Query<Entity, With<FallbackGround>>,
+) {
+    if chunks.spawned.is_empty() {
+        return;
+    }
+    for entity in fallback.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Construye el chunk; devuelve la raíz y el hijo con el terreno
+fn spawn_chunk(
+    commands: &mut Commands,
+    assets: &ChunkAssets,
+    terrain_mesh: Handle<Mesh>,
+    lod: usize,
+    grid: &ChunkGrid,
+    chunk: &ChunkData,
+) -> (Entity, Entity) {
+    let origin = grid.origin(chunk.coord);
+    let mut terrain = Entity::PLACEHOLDER;
+    let root = commands
+        .spawn((
+            WorldChunk { coord: chunk.coord },
+            Transform::from_translation(origin),
+            Visibility::default(),
+        ))
+        .with_children(|parent| {
+            terrain = parent
+                .spawn((
+                    Mesh3d(terrain_mesh),
+                    MeshMaterial3d(assets.terrain.clone()),
+                    Transform::default(),
+                    ChunkTerrain { lod },
+                ))
+                .id();
+
+            // Props y contenedores vienen en coordenadas del mundo
+            for prop in &chunk.props {
+                let transform = Transform::from_translation(prop.position - origin)
+                    .with_rotation(Quat::from_rotation_y(prop.rotation))
+                    .with_scale(Vec3::splat(prop.scale));
+                match prop.kind {
+                    PropKind::Tree => {
+                        parent.spawn((transform, Visibility::default())).with_children(|tree| {
+                            tree.spawn((
+                                Mesh3d(assets.trunk.0.clone()),
+                                MeshMaterial3d(assets.trunk.1.clone()),
+                                Transform::from_xyz(0.0, 1.5, 0.0),
+                            ));
+                            tree.spawn((
+                                Mesh3d(assets.canopy.0.clone()),
+                                MeshMaterial3d(assets.canopy.1.clone()),
+                                Transform::from_xyz(0.0, 4.5, 0.0),
+                            ));
+                        });
+                    }
+                    PropKind::Rock => {
+                        parent.spawn((Mesh3d(assets.rock.0.clone()), MeshMaterial3d(assets.rock.1.clone()), transform));
+                    }
+                    PropKind::Bush => {
+                        parent.spawn((Mesh3d(assets.bush.0.clone()), MeshMaterial3d(assets.bush.1.clone()), transform));
+                    }
+                }
+            }
+
+            for container in &chunk.containers {
+                parent.spawn((
+                    Mesh3d(assets.container.clone()),
+                    MeshMaterial3d(assets.container_materials[&container.kind].clone()),
+                    Transform::from_translation(container.position - origin + Vec3::Y * 0.3)
+                        .with_rotation(Quat::from_rotation_y(container.rotation)),
+                    WorldContainer { id: container.id, kind: container.kind },
+                ));
+            }
+        })
+        .id();
+    (root, terrain)
+}
+
+/// Mesh del terreno del chunk en coordenadas locales, con el detalle de `lod`.
+/// Los vértices coinciden con el heightfield del servidor en el LOD 0 y llevan
+/// el color del bioma; un faldón vertical en el borde tapa las grietas con los vecinos
+fn terrain_mesh(terrain: &TerrainGenerator, grid: &ChunkGrid, coord: ChunkCoord, lod: usize) -> Mesh {
+    let resolution = LOD_RESOLUTIONS[lod.min(LOD_RESOLUTIONS.len() - 1)];
+    let origin = grid.origin(coord);
+    let step = grid.chunk_size / (resolution - 1) as f32;
+
+    let vertices = (resolution * resolution) as usize;
+    let mut positions = Vec::with_capacity(vertices);
+    let mut normals = Vec::with_capacity(vertices);
+    let mut colors = Vec::with_capacity(vertices);
+    let mut uvs = Vec::with_capacity(vertices);
+    for row in 0..resolution {
+        for column in 0..resolution {
+            let (x, z) = (column as f32 * step, row as f32 * step);
+            let sample = terrain.sample(origin.x + x, origin.z + z);
+            let [r, g, b] = sample.biome.color();
+            positions.push([x, sample.height, z]);
+            normals.push(terrain.normal(origin.x + x, origin.z + z, step).to_array());
+            colors.push(LinearRgba::from(Color::srgb(r, g, b)).to_f32_array());
+            uvs.push([column as f32 / (resolution - 1) as f32, row as f32 / (resolution - 1) as f32]);
+        }
+    }
+
+    let mut indices = Vec::with_capacity(((resolution - 1) * (resolution - 1) * 6) as usize);
+    for row in 0..resolution - 1 {
+        for column in 0..resolution - 1 {
+            let i = row * resolution + column;
+            // Sentido antihorario visto desde arriba; la diagonal es la del heightfield
+            indices.extend_from_slice(&[i, i + resolution, i + 1, i + 1, i + resolution, i + resolution + 1]);
+        }
+    }
+
+    // Faldón: el borde recorrido en sentido antihorario visto desde arriba,
+    // duplicado SKIRT_DEPTH metros más abajo
+    let last = resolution - 1;
+    let border: Vec<u32> = (0..last)
+        .chain((0..last).map(|row| row * resolution + last))
+        .chain((0..last).map(|column| last * resolution + last - column))
+        .chain((0..last).map(|row| (last - row) * resolution))
+        .collect();
+    for (n, &top) in border.iter().enumerate() {
+        let next = border[(n + 1) % border.len()];
+        let bottom = positions.len() as u32;
+        for vertex in [top, next] {
+            let [x, y, z] = positions[vertex as usize];
+            positions.push([x, y - SKIRT_DEPTH, z]);
+            normals.push(normals[vertex as usize]);
+            colors.push(colors[vertex as usize]);
+            uvs.push(uvs[vertex as usize]);
+        }
+        // Cara hacia fuera del chunk
+        indices.extend_from_slice(&[top, next, bottom, next, bottom + 1, bottom]);
+    }
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+fn unload_chunks(
+    mut commands: Commands,
+    mut chunks: ResMut<ClientChunks>,
+    client: Res<NetworkClient>,
+    settings: Res<ChunkStreamingSettings>,
+) {
+    let Some(world) = chunks.world.clone() else { return };
+    let Some(position) = local_position(&client) else { return };
+    let center = world.grid().coord_at(position);
+
+    // Un anillo de margen para no descargar y volver a pedir en el borde
+    let far: Vec<ChunkCoord> = chunks
+        .spawned
+        .keys()
+        .filter(|coord| coord.distance(center) > world.view_radius + 1)
+        .copied()
+        .take(settings.unload_budget)
+        .collect();
+    for coord in far {
+        if let Some(chunk) = chunks.spawned.remove(&coord) {
+            commands.entity(chunk.root).despawn_recursive();
+            debug!(%coord, "🧱 Chunk descargado");
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+use tn1_shared::{protocol::DEFAULT_PORT, transport};
+
+/// Configuración de conexión del cliente (variables de entorno)
+#[derive(Resource, Debug, Clone)]
+pub struct ClientSettings {
+    pub server_address: String,
+    pub tls_enabled: bool,
+    /// Huellas SHA-256 aceptadas para el certificado del servidor
+    pub tls_pins: Vec<String>,
+}
+
+impl Default for ClientSettings {
+    fn default() -> Self {
+        Self {
+            server_address: std::env::var("TN1_SERVER_ADDR")
+                .unwrap_or_else(|_| format!("127.0.0.1:{}", DEFAULT_PORT)),
+            tls_enabled: transport::tls_enabled_from_env(),
+            // En desarrollo el servidor deja la huella de su certificado autofirmado junto al .pem
+            tls_pins: transport::pins_from_env(),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Ciclo día/noche en el cliente: sol, luna, luz ambiente y cielo según la hora
+//! que manda el servidor, y lo que la oscuridad cambia del juego.
+//!
+//! La linterna (L) viaja en el input y el servidor la replica, así que todos ven
+//! el haz; la visión nocturna (N) es solo local. De noche los jugadores lejanos
+//! se ocultan según `spotting_range` (recortado por la visibilidad del clima):
+//! la misma regla, la misma hora y el mismo clima para todos.
+
+use bevy::prelude::*;
+use tn1_shared::components::{LocalPlayer, Player, PlayerId};
+use tn1_shared::daylight::{spotting_range, Vision, WorldClock};
+use crate::chat::ChatHistory;
+use crate::networking::NetworkClient;
+use crate::weather::ClientWeather;
+
+/// Iluminancia del sol a mediodía y de la luna llena (lux)
+const SUN_ILLUMINANCE: f32 = 10_000.0;
+const MOON_ILLUMINANCE: f32 = 250.0;
+/// Brillo de la luz ambiente de día, de noche y con visión nocturna
+const DAY_AMBIENT: f32 = 200.0;
+const NIGHT_AMBIENT: f32 = 15.0;
+const NIGHT_VISION_AMBIENT: f32 = 500.0;
+
+/// Teclas del equipo nocturno
+#[derive(Resource, Debug, Clone)]
+pub struct DaylightSettings {
+    pub flashlight_key: KeyCode,
+    pub night_vision_key: KeyCode,
+}
+
+impl Default for DaylightSettings {
+    fn default() -> Self {
+        Self {
+            flashlight_key: KeyCode::KeyL,
+            night_vision_key: KeyCode::KeyN,
+        }
+    }
+}
+
+pub struct DaylightPlugin;
+
+impl Plugin for DaylightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DaylightSettings>()
+            .init_resource::<WorldTime>()
+            .init_resource::<NightGear>()
+            .init_resource::<ClearColor>()
+            .add_systems(Startup, spawn_sky)
+            .add_systems(Update, (
+                advance_world_time,
+                toggle_night_gear,
+                update_sky,
+                update_flashlights,
+                update_player_visibility,
+            ).chain());
+    }
+}
+
+/// Hora del mundo: la del último `WorldTime`, extrapolada con su `time_scale`
+#[derive(Resource, Default)]
+pub struct WorldTime {
+    clock: Option<WorldClock>,
+}
+
+impl WorldTime {
+    pub fn clock(&self) -> Option<WorldClock> {
+        self.clock
+    }
+
+    pub fn receive(&mut self, clock: WorldClock) {
+        if self.clock.is_none() {
+            info!("🌗 Hora del mundo {} (x{} tiempo real)", clock, clock.time_scale);
+        }
+        self.clock = Some(clock);
+    }
+
+    /// Oscuridad actual; antes de saber la hora se asume pleno día
+    pub fn darkness(&self) -> f32 {
+        self.clock.map_or(0.0, |clock| clock.darkness())
+    }
+}
+
+/// Linterna y gafas de visión nocturna del jugador local
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct NightGear {
+    pub flashlight: bool,
+    pub night_vision: bool,
+}
+
+impl NightGear {
+    pub fn vision(&self) -> Vision {
+        Vision { flashlight: self.flashlight, night_vision: self.night_vision }
+    }
+}
+
+/// Luz direccional que sigue a un astro
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Celestial {
+    Sun,
+    Moon,
+}
+
+/// Jugadores remotos que se ocultan de noche
+type RemotePlayers<'w, 's> =
+    Query<'w, 's, (&'static PlayerId, &'static Transform, &'static mut Visibility), (With<Player>, Without<LocalPlayer>)>;
+
+/// Haz de la linterna de un jugador (hijo de su entidad)
+#[derive(Component)]
+pub struct FlashlightBeam;
+
+fn spawn_sky(mut commands: Commands) {
+    commands.spawn((
+        DirectionalLight {
+            illuminance: SUN_ILLUMINANCE,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::default().looking_to(-WorldClock::default().sun_direction(), Vec3::Y),
+        Celestial::Sun,
+    ));
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 0.0,
+            color: Color::srgb(0.7, 0.8, 1.0),
+            ..default()
+        },
+        Transform::default().looking_to(-WorldClock::default().moon_direction(), Vec3::Y),
+        Celestial::Moon,
+    ));
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: DAY_AMBIENT,
+    });
+}
+
+fn advance_world_time(mut world_time: ResMut<WorldTime>, time: Res<Time>) {
+    if let Some(clock) = world_time.clock.as_mut() {
+        clock.advance(time.delta_secs());
+    }
+}
+
+fn toggle_night_gear(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<DaylightSettings>,
+    chat: Res<ChatHistory>,
+    mut gear: ResMut<NightGear>,
+) {
+    if chat.typing {
+        return;
+    }
+    if keyboard.just_pressed(settings.flashlight_key) {
+        gear.flashlight = !gear.flashlight;
+        info!("🔦 Linterna {}", if gear.flashlight { "encendida" } else { "apagada" });
+    }
+    if keyboard.just_pressed(settings.night_vision_key) {
+        gear.night_vision = !gear.night_vision;
+        info!("🥽 Visión nocturna {}", if gear.night_vision { "activada" } else { "desactivada" });
+    }
+}
+
+/// Sol, luna, luz ambiente y color del cielo según la hora y las nubes
+pub(crate) fn update_sky(
+    world_time: Res<WorldTime>,
+    gear: Res<NightGear>,
+    weather: Res<ClientWeather>,
+    mut ambient: ResMut<AmbientLight>,
+    mut clear_color: ResMut<ClearColor>,
+    mut lights: Query<(&Celestial, &mut DirectionalLight, &mut Transform)>,
+) {
+    let Some(clock) = world_time.clock() else { return };
+    let darkness = clock.darkness();
+    let overcast = weather.overcast();
+
+    for (celestial, mut light, mut transform) in lights.iter_mut() {
+        let (direction, illuminance) = match celestial {
+            Celestial::Sun => (clock.sun_direction(), SUN_ILLUMINANCE * clock.daylight()),
+            Celestial::Moon => (clock.moon_direction(), MOON_ILLUMINANCE * darkness),
+        };
+        *transform = Transform::default().looking_to(-direction, Vec3::Y);
+        // Las nubes tapan buena parte del sol y de la luna
+        light.illuminance = illuminance * horizon_fade(direction) * (1.0 - overcast * 0.8);
+    }
+
+    let mix = |day: Vec3, night: Vec3| day.lerp(night, darkness);
+    if gear.night_vision && darkness > 0.0 {
+        // Las gafas amplifican lo poco que hay y lo tiñen de verde
+        ambient.color = Color::srgb(0.4, 1.0, 0.4);
+        ambient.brightness = DAY_AMBIENT + (NIGHT_VISION_AMBIENT - DAY_AMBIENT) * darkness;
+        clear_color.0 = Color::srgb(0.05, 0.2 * darkness + 0.3 * (1.0 - darkness), 0.05);
+    } else {
+        let color = mix(Vec3::ONE, Vec3::new(0.5, 0.6, 1.0));
+        ambient.color = Color::srgb(color.x, color.y, color.z);
+        ambient.brightness = DAY_AMBIENT + (NIGHT_AMBIENT - DAY_AMBIENT) * darkness;
+        let sky = mix(Vec3::new(0.45, 0.65, 0.9), Vec3::new(0.02, 0.02, 0.06));
+        // Cielo encapotado: hacia un gris con el brillo que tenga
+        let sky = sky.lerp(Vec3::splat(sky.length() / 3f32.sqrt()), overcast);
+        clear_color.0 = Color::srgb(sky.x, sky.y, sky.z);
+    }
+}
+
+/// Un astro se apaga del todo al tocar el horizonte
+fn horizon_fade(direction: Vec3) -> f32 {
+    (direction.y / 0.3).clamp(0.0, 1.0)
+}
+
+/// Pone o quita el haz de la linterna a cada jugador: el local según su equipo
+/// (sin esperar al servidor), los demás según el snapshot
+fn update_flashlights(
+    mut commands: Commands,
+    client: Res<NetworkClient>,
+    gear: Res<NightGear>,
+    players: Query<(Entity, &PlayerId, Has<LocalPlayer>), With<Player>>,
+    beams: Query<(Entity, &Parent), With<FlashlightBeam>>,
+) {
+    for (entity, player_id, is_local) in players.iter() {
+        let on = if is_local {
+            gear.flashlight
+        } else {
+            client.player_states.get(player_id).is_some_and(|state| state.flashlight)
+        };
+        let beam = beams.iter().find(|(_, parent)| parent.get() == entity).map(|(beam, _)| beam);
+        match (on, beam) {
+            (true, None) => {
+                commands.entity(entity).with_children(|parent| {
+                    parent.spawn((
+                        SpotLight {
+                            intensity: 400_000.0,
+                            range: 45.0,
+                            outer_angle: 0.45,
+                            inner_angle: 0.3,
+                            shadows_enabled: false,
+                            ..default()
+                        },
+                        Transform::from_xyz(0.0, 0.4, -0.4),
+                        FlashlightBeam,
+                    ));
+                });
+            }
+            (false, Some(beam)) => commands.entity(beam).despawn_recursive(),
+            _ => {}
+        }
+    }
+}
+
+/// De noche o con mal tiempo solo se ve a quien está dentro del alcance de la vista
+fn update_player_visibility(
+    client: Res<NetworkClient>,
+    world_time: Res<WorldTime>,
+    weather: Res<ClientWeather>,
+    gear: Res<NightGear>,
+    local: Query<&Transform, With<LocalPlayer>>,
+    mut remotes: RemotePlayers,
+) {
+    let Ok(observer) = local.get_single() else { return };
+    let darkness = world_time.darkness();
+
+    for (player_id, transform, mut visibility) in remotes.iter_mut() {
+        let target_flashlight = client.player_states.get(player_id).is_some_and(|state| state.flashlight);
+        let range = spotting_range(darkness, gear.vision(), target_flashlight) * weather.visibility();
+        let visible = transform.translation.distance(observer.translation) <= range;
+        visibility.set_if_neq(if visible { Visibility::Inherited } else { Visibility::Hidden });
+    }
+}
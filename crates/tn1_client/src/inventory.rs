@@ -0,0 +1,154 @@
+//! Copia local del inventario: la manda entera el servidor al entrar y luego
+//! solo los diffs de cada acción aceptada.
+//!
+//! El cliente no aplica nada por su cuenta: pide la acción con `request` y
+//! espera la respuesta; si el servidor la rechaza se avisa por el chat. Lo
+//! mismo con los contenedores del mundo (`loot`) y con lo que hay en el suelo o
+//! en un cadáver (`request_pickup`): lo sacado llega como diffs. Las acciones
+//! de comercio (`request_trade`) usan las mismas secuencias.
+//! Nombres, pesos y demás datos de los items salen del mismo catálogo
+//! (`data/items`) que usa el servidor.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+use tn1_shared::drops::PickupAction;
+use tn1_shared::inventory::{Inventory, InventoryAction, InventoryDiff};
+use tn1_shared::inventory::ItemStack;
+use tn1_shared::items::ItemDefs;
+use tn1_shared::loot::LootAction;
+use tn1_shared::protocol::ClientMessage;
+use tn1_shared::trade::TradeAction;
+use crate::networking::NetworkClient;
+
+/// Carga el catálogo de items (`TN1_ITEMS_PATH`) al arrancar
+pub struct ItemRegistryPlugin {
+    pub path: PathBuf,
+}
+
+impl Default for ItemRegistryPlugin {
+    fn default() -> Self {
+        Self { path: std::env::var("TN1_ITEMS_PATH").unwrap_or_else(|_| "data/items".to_string()).into() }
+    }
+}
+
+impl Plugin for ItemRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        let defs = match ItemDefs::load(&self.path) {
+            Ok(defs) => {
+                info!("📦 {} tipos de item (versión {})", defs.len(), defs.version());
+                defs
+            }
+            Err(e) => {
+                error!("❌ No se pudo cargar el catálogo de items: {}", e);
+                ItemDefs::default()
+            }
+        };
+        app.insert_resource(defs);
+    }
+}
+
+/// Inventario del jugador local según el servidor
+#[derive(Resource, Default)]
+pub struct ClientInventory {
+    inventory: Option<Inventory>,
+    next_sequence: u32,
+    /// Acciones enviadas sin respuesta todavía
+    pending: Vec<u32>,
+    /// Último rechazo (secuencia y motivo)
+    last_rejection: Option<(u32, String)>,
+    /// Último contenedor abierto y lo que tenía
+    loot: Option<(u64, Vec<ItemStack>)>,
+}
+
+impl ClientInventory {
+    pub fn inventory(&self) -> Option<&Inventory> {
+        self.inventory.as_ref()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn last_rejection(&self) -> Option<&(u32, String)> {
+        self.last_rejection.as_ref()
+    }
+
+    pub fn loot(&self) -> Option<&(u64, Vec<ItemStack>)> {
+        self.loot.as_ref()
+    }
+
+    /// Envía una acción al servidor; devuelve su número de secuencia
+    pub fn request(&mut self, client: &NetworkClient, action: InventoryAction) -> u32 {
+        self.next_sequence += 1;
+        let sequence = self.next_sequence;
+        self.send(client, sequence, ClientMessage::Inventory { sequence, action });
+        sequence
+    }
+
+    /// Abre un contenedor del mundo o saca algo de él
+    pub fn request_loot(&mut self, client: &NetworkClient, action: LootAction) -> u32 {
+        self.next_sequence += 1;
+        let sequence = self.next_sequence;
+        self.send(client, sequence, ClientMessage::Loot { sequence, action });
+        sequence
+    }
+
+    /// Recoge algo del suelo o abre un cadáver o saca algo de él
+    pub fn request_pickup(&mut self, client: &NetworkClient, action: PickupAction) -> u32 {
+        self.next_sequence += 1;
+        let sequence = self.next_sequence;
+        self.send(client, sequence, ClientMessage::Pickup { sequence, action });
+        sequence
+    }
+
+    /// Propone, acepta, cambia o cancela un comercio; la respuesta llega como
+    /// `TradeWindow`/`TradeClosed` (ver `ClientTrade`) o como rechazo
+    pub fn request_trade(&mut self, client: &NetworkClient, action: TradeAction) -> u32 {
+        self.next_sequence += 1;
+        let sequence = self.next_sequence;
+        self.send(client, sequence, ClientMessage::Trade { sequence, action });
+        sequence
+    }
+
+    /// Respuesta a una acción que no toca el inventario (la ventana de comercio)
+    pub fn acknowledge(&mut self, sequence: Option<u32>) {
+        if let Some(sequence) = sequence {
+            self.pending.retain(|pending| *pending != sequence);
+        }
+    }
+
+    fn send(&mut self, client: &NetworkClient, sequence: u32, message: ClientMessage) {
+        match client.send(&message) {
+            Ok(()) => self.pending.push(sequence),
+            Err(e) => warn!("🎒 No se pudo enviar la acción de inventario: {}", e),
+        }
+    }
+
+    pub fn receive_snapshot(&mut self, inventory: Inventory) {
+        info!("🎒 Inventario recibido ({} items)", inventory.items().len());
+        self.inventory = Some(inventory);
+    }
+
+    pub fn receive_update(&mut self, sequence: Option<u32>, diffs: Vec<InventoryDiff>) {
+        if let Some(sequence) = sequence {
+            self.pending.retain(|pending| *pending != sequence);
+        }
+        let Some(inventory) = self.inventory.as_mut() else { return };
+        for diff in diffs {
+            inventory.apply_diff(diff);
+        }
+    }
+
+    pub fn receive_loot(&mut self, sequence: Option<u32>, container: u64, items: Vec<ItemStack>) {
+        if let Some(sequence) = sequence {
+            self.pending.retain(|pending| *pending != sequence);
+        }
+        self.loot = Some((container, items));
+    }
+
+    pub fn receive_rejection(&mut self, sequence: u32, reason: String) {
+        warn!("🎒 Acción de inventario {} rechazada: {}", sequence, reason);
+        self.pending.retain(|pending| *pending != sequence);
+        self.last_rejection = Some((sequence, reason));
+    }
+}
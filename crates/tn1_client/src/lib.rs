@@ -0,0 +1,25 @@
+//! Cliente de Trust-No-1.
+//!
+//! El binario `trust-no-1` arma el `App` con ventana y render; los plugins viven
+//! aquí para que los tests de integración puedan usar el networking sin GPU.
+
+pub mod camera;
+pub mod config;
+pub mod player;
+pub mod input;
+pub mod ui;
+pub mod input_sender;
+pub mod position_receiver;
+pub mod networking;
+pub mod player_tags;
+pub mod replay;
+pub mod chat;
+pub mod voice;
+pub mod chunks;
+pub mod inventory;
+pub mod world_items;
+pub mod trade;
+pub mod weapons;
+pub mod medical;
+pub mod daylight;
+pub mod weather;
@@ -2,27 +2,49 @@ use bevy::prelude::*;
 use bevy::window::{PresentMode, WindowTheme};
 use tracing::info;
 use tn1_shared::events::*;
+use tn1_shared::conditioner::{LinkConditions, NetworkConditions};
 
-mod camera;
-mod player;
-mod input;
-mod ui;
-mod input_sender;
-mod position_receiver;
-mod networking;
-mod player_tags;
-
-use camera::CameraPlugin;
-use player::PlayerPlugin;
-use input::InputPlugin;
-use ui::UIPlugin;
-use input_sender::InputSenderPlugin;
-// use position_receiver::PositionReceiverPlugin; // Deshabilitado - ahora networking maneja todo
-use networking::ClientNetworkingPlugin;
-use player_tags::PlayerTagsPlugin;
+use tn1_client::camera::CameraPlugin;
+use tn1_client::chunks::FallbackGround;
+use tn1_client::player::PlayerPlugin;
+use tn1_client::input::InputPlugin;
+use tn1_client::ui::UIPlugin;
+use tn1_client::input_sender::InputSenderPlugin;
+// use tn1_client::position_receiver::PositionReceiverPlugin; // Deshabilitado - ahora networking maneja todo
+use tn1_client::networking::ClientNetworkingPlugin;
+use tn1_client::inventory::ItemRegistryPlugin;
+use tn1_client::player_tags::PlayerTagsPlugin;
+use tn1_client::replay::{MatchRecordPlugin, MatchTimeline, ReplayViewerPlugin};
 
 fn main() {
-    App::new()
+    // `trust-no-1 --replay partida.tn1match` abre el visor en lugar de conectarse
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--replay") {
+        let Some(path) = args.get(index + 1) else {
+            eprintln!("Uso: trust-no-1 --replay <archivo.tn1match>");
+            std::process::exit(2);
+        };
+        match MatchTimeline::load(std::path::Path::new(path)) {
+            Ok(timeline) => run_replay(timeline),
+            Err(e) => {
+                eprintln!("❌ No se pudo abrir la partida: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // `--latency <ms> --jitter <ms> --loss <%> ...`: red simulada desde el arranque (ajustable con F6)
+    let conditions = match LinkConditions::from_args(&args) {
+        Ok(conditions) => conditions,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let mut app = App::new();
+    app
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
@@ -49,11 +71,54 @@ fn main() {
             InputSenderPlugin,
             // PositionReceiverPlugin, // Deshabilitado - ahora networking maneja todo
             ClientNetworkingPlugin,
+            ItemRegistryPlugin::default(),
+            MatchRecordPlugin::default(),
             PlayerTagsPlugin,
             UIPlugin,
         ))
         .insert_resource(ClearColor(Color::srgb(0.05, 0.05, 0.1)))
-        .add_systems(Startup, setup)
+        .insert_resource(NetworkConditions::new(conditions))
+        .add_systems(Startup, setup);
+
+    // Sin la feature `voice` la voz se recibe pero no suena
+    #[cfg(feature = "voice")]
+    app.add_plugins(tn1_client::voice::VoiceAudioPlugin);
+
+    app.run();
+}
+
+/// Visor de partidas grabadas: sin networking, solo la escena y la línea de tiempo
+fn run_replay(timeline: MatchTimeline) {
+    println!(
+        "🎬 Partida de {} en {} - {:.0} s, {} snapshots",
+        timeline.header.username,
+        timeline.header.server_address,
+        timeline.duration(),
+        timeline.snapshot_count()
+    );
+
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "TRUST-NO-1 - Replay".to_string(),
+                        resolution: (1280.0, 720.0).into(),
+                        present_mode: PresentMode::AutoVsync,
+                        window_theme: Some(WindowTheme::Dark),
+                        focused: true,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(ImagePlugin::default_nearest())
+        )
+        .add_plugins((
+            ReplayViewerPlugin { timeline },
+            PlayerTagsPlugin,
+        ))
+        .insert_resource(ClearColor(Color::srgb(0.05, 0.05, 0.1)))
+        .add_systems(Startup, (setup, fixed_lighting))
         .run();
 }
 
@@ -64,7 +129,7 @@ fn setup(
 ) {
     info!("Iniciando TRUST-NO-1 Cliente");
 
-    // Plano temporal para testing
+    // Suelo provisional hasta que llegan los chunks del servidor
     commands.spawn((
         Mesh3d(meshes.add(Plane3d::default().mesh().size(50.0, 50.0))),
         MeshMaterial3d(materials.add(StandardMaterial {
@@ -73,9 +138,12 @@ fn setup(
             ..default()
         })),
         Transform::from_xyz(0.0, 0.0, 0.0),
+        FallbackGround,
     ));
+}
 
-    // Luz básica
+/// Luz fija del visor de partidas (en juego la pone `DaylightPlugin` según la hora)
+fn fixed_lighting(mut commands: Commands) {
     commands.spawn((
         DirectionalLight {
             illuminance: 10000.0,
@@ -0,0 +1,36 @@
+//! Vida por zonas del jugador local, tal como la manda el servidor
+//! (`ServerMessage::BodyHealth`), para el HUD.
+
+use bevy::prelude::*;
+use tn1_shared::components::BodyZone;
+use tn1_shared::medical::BodyHealth;
+
+pub struct MedicalPlugin;
+
+impl Plugin for MedicalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClientBody>();
+    }
+}
+
+/// Último `BodyHealth` recibido
+#[derive(Resource, Default)]
+pub struct ClientBody {
+    body: Option<BodyHealth>,
+}
+
+impl ClientBody {
+    pub fn body(&self) -> Option<&BodyHealth> {
+        self.body.as_ref()
+    }
+
+    pub fn receive(&mut self, body: BodyHealth) {
+        for zone in BodyZone::ALL {
+            let was_destroyed = self.body.as_ref().is_some_and(|previous| previous.is_destroyed(zone));
+            if body.is_destroyed(zone) && !was_destroyed {
+                warn!("🦴 Zona destrozada: {}", zone.name());
+            }
+        }
+        self.body = Some(body);
+    }
+}
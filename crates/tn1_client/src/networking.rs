@@ -1,8 +1,22 @@
 use bevy::prelude::*;
-use tn1_shared::{components::*, protocol::{self, *}};
+use bevy::ecs::system::SystemParam;
+use tn1_shared::{codec::*, components::*, protocol::{self, *}, transport::{self, SecureStream}};
+use tn1_shared::conditioner::{LinkConditioner, NetworkConditions};
 use std::sync::{Arc, Mutex};
 use std::net::TcpStream;
 use std::io::{Read, Write};
+use crate::chat::ChatHistory;
+use crate::chunks::{ChunkStreamingPlugin, ClientChunks};
+use crate::config::ClientSettings;
+use crate::daylight::{DaylightPlugin, NightGear, WorldTime};
+use crate::inventory::ClientInventory;
+use crate::medical::{ClientBody, MedicalPlugin};
+use crate::replay::MatchRecorder;
+use crate::trade::{ClientTrade, TradePlugin};
+use crate::voice::{VoiceChat, VoicePlugin};
+use crate::weapons::{ClientWeapon, WeaponsPlugin};
+use crate::weather::{ClientWeather, WeatherPlugin};
+use crate::world_items::{ClientWorldItems, WorldItemsPlugin};
 use std::thread;
 use std::collections::HashMap;
 
@@ -10,13 +24,27 @@ pub struct ClientNetworkingPlugin;
 
 impl Plugin for ClientNetworkingPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(NetworkClient::new())
+        app.init_resource::<ClientSettings>()
+            .init_resource::<NetworkConditions>() // Red perfecta salvo `--latency`, `--loss`... o el panel F6
+            .insert_resource(NetworkClient::new())
             .insert_resource(InputSequence(0))
+            .init_resource::<ChatHistory>()
+            .init_resource::<ClientInventory>()
+            .add_plugins(VoicePlugin) // Captura y jitter buffer; el audio lo pone `VoiceAudioPlugin`
+            .add_plugins(ChunkStreamingPlugin)
+            .add_plugins(DaylightPlugin)
+            .add_plugins(WeatherPlugin)
+            .add_plugins(WorldItemsPlugin)
+            .add_plugins(TradePlugin)
+            .add_plugins(WeaponsPlugin)
+            .add_plugins(MedicalPlugin)
             .add_systems(Startup, connect_to_server)
             .add_systems(Update, (
                 process_server_messages,
                 send_player_input,
-            ));
+            ))
+            // En `Last` ya se ven los AppExit enviados durante el frame
+            .add_systems(Last, disconnect_on_exit);
     }
 }
 
@@ -27,7 +55,16 @@ pub struct InputSequence(pub u32);
 pub struct NetworkClient {
     pub connected: bool,
     pub local_player_id: Option<PlayerId>,
-    pub stream: Option<Arc<Mutex<TcpStream>>>,
+    /// Codec negociado en el handshake (JSON hasta recibir `Welcome`)
+    pub codec: WireCodec,
+    pub server_info: Option<ServerInfo>,
+    /// Posición en la cola de espera si el servidor está lleno
+    pub queue_position: Option<u32>,
+    pub username: String,
+    pub password: String,
+    pub stream: Option<Arc<Mutex<SecureStream>>>,
+    /// Simulador de red de salida; escribe en `stream` desde su propio thread
+    pub link: Option<LinkConditioner<Vec<u8>>>,
     pub incoming_messages: Arc<Mutex<Vec<ServerMessage>>>,
     pub player_states: HashMap<PlayerId, PlayerState>,
 }
@@ -37,45 +74,115 @@ impl NetworkClient {
         Self {
             connected: false,
             local_player_id: None,
+            codec: WireCodec::Json,
+            server_info: None,
+            queue_position: None,
+            // Credenciales por ahora hardcodeadas
+            // TODO: Agregar UI de login/registro
+            username: format!("Player{}", rand::random::<u16>()),
+            password: "temp_password".to_string(),
             stream: None,
+            link: None,
             incoming_messages: Arc::new(Mutex::new(Vec::new())),
             player_states: HashMap::new(),
         }
     }
 }
 
-fn connect_to_server(mut client: ResMut<NetworkClient>) {
-    info!("🔌 Conectando al servidor...");
+impl NetworkClient {
+    /// Envía un mensaje con el codec actual pasando por el simulador de red
+    pub fn send(&self, message: &ClientMessage) -> Result<(), std::io::Error> {
+        let Some(link) = &self.link else {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotConnected, "sin conexión"));
+        };
+        let data = self.codec.encode_frame(message)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let bytes = data.len();
+        link.send(data, bytes, message.is_droppable())
+    }
+    
+    /// Avisa al servidor y cierra el socket; el thread de recepción termina solo
+    pub fn disconnect(&mut self) {
+        // Lo que siga en vuelo se pierde al cerrar el socket, como en una red real
+        self.link = None;
+        if let Some(stream) = self.stream.take() {
+            if let Ok(mut stream_lock) = stream.lock() {
+                let _ = send_client_message(&mut stream_lock, self.codec, &ClientMessage::Disconnect);
+                let _ = stream_lock.shutdown(std::net::Shutdown::Both);
+            }
+        }
+        self.connected = false;
+        self.local_player_id = None;
+        self.queue_position = None;
+        self.player_states.clear();
+        info!("👋 Desconectado del servidor");
+    }
+}
+
+fn connect_to_server(
+    mut client: ResMut<NetworkClient>,
+    settings: Res<ClientSettings>,
+    conditions: Res<NetworkConditions>,
+) {
+    info!("🔌 Conectando al servidor {}...", settings.server_address);
     
-    match TcpStream::connect(format!("127.0.0.1:{}", DEFAULT_PORT)) {
-        Ok(stream) => {
-            stream.set_nodelay(true).ok();
+    match TcpStream::connect(&settings.server_address) {
+        Ok(socket) => {
+            socket.set_nodelay(true).ok();
+            
+            // El handshake TLS es bloqueante, antes de pasar a no-bloqueante
+            let stream = if settings.tls_enabled {
+                if settings.tls_pins.is_empty() {
+                    error!("❌ TLS activo pero sin pin de certificado (TN1_TLS_PIN o certs/server_cert.sha256)");
+                    return;
+                }
+                match SecureStream::connect(socket, transport::pinned_client_config(settings.tls_pins.clone())) {
+                    Ok(stream) => {
+                        info!("🔐 Canal TLS establecido con certificado fijado");
+                        stream
+                    }
+                    Err(e) => {
+                        error!("❌ Handshake TLS fallido: {}", e);
+                        return;
+                    }
+                }
+            } else {
+                warn!("⚠️ TLS desactivado (TN1_TLS=off) - la contraseña viaja en claro");
+                SecureStream::plain(socket)
+            };
             stream.set_nonblocking(true).ok(); // Configurar como no-bloqueante
             
             let stream_arc = Arc::new(Mutex::new(stream));
             client.stream = Some(stream_arc.clone());
+            let writer = stream_arc.clone();
+            client.link = Some(LinkConditioner::new(conditions.clone(), move |data: Vec<u8>| {
+                let mut stream_lock = writer.lock().unwrap();
+                stream_lock.write_all(&data)?;
+                stream_lock.flush()
+            }));
+            if conditions.get().is_active() {
+                info!("📶 Simulando red: {:?}", conditions.get());
+            }
             
-            // Enviar mensaje de login (por ahora hardcodeado)
-            // TODO: Agregar UI de login/registro
-            let connect_msg = ClientMessage::Login {
+            // Handshake: el login se envía al recibir `Welcome`
+            let hello_msg = ClientMessage::Hello {
                 protocol_version: PROTOCOL_VERSION,
-                username: format!("Player{}", rand::random::<u16>()),
-                password: "temp_password".to_string(),
+                client_name: client.username.clone(),
+                codecs: WireCodec::SUPPORTED.to_vec(),
             };
             
-            if let Ok(mut stream_lock) = stream_arc.lock() {
-                if let Err(e) = send_client_message(&mut *stream_lock, &connect_msg) {
-                    error!("❌ Error enviando mensaje de login: {}", e);
-                    return;
-                }
+            if let Err(e) = client.send(&hello_msg) {
+                error!("❌ Error enviando Hello: {}", e);
+                return;
             }
             
             // Thread para recibir mensajes
             let incoming = client.incoming_messages.clone();
             let stream_clone = stream_arc.clone();
+            let conditions = conditions.clone();
             
             thread::spawn(move || {
-                receive_server_messages(stream_clone, incoming);
+                receive_server_messages(stream_clone, incoming, conditions);
             });
             
             info!("✅ Conectado al servidor");
@@ -87,13 +194,21 @@ fn connect_to_server(mut client: ResMut<NetworkClient>) {
 }
 
 fn receive_server_messages(
-    stream: Arc<Mutex<TcpStream>>,
+    stream: Arc<Mutex<SecureStream>>,
     incoming: Arc<Mutex<Vec<ServerMessage>>>,
+    conditions: NetworkConditions,
 ) {
+    // Los mensajes recibidos pasan por el simulador de red antes de llegar al juego
+    let incoming = LinkConditioner::new(conditions, move |message: ServerMessage| {
+        incoming.lock().unwrap().push(message);
+        Ok(())
+    });
     let mut buffer = vec![0u8; 4096];
-    let mut message_buffer = Vec::new();
+    let mut frames = FrameBuffer::new();
+    // El servidor contesta el handshake en JSON y cambia de codec tras `Welcome`
+    let mut codec = WireCodec::Json;
     
-    loop {
+    'receive: loop {
         let mut stream_lock = match stream.lock() {
             Ok(lock) => lock,
             Err(_) => {
@@ -108,35 +223,30 @@ fn receive_server_messages(
                 break;
             }
             Ok(n) => {
-                message_buffer.extend_from_slice(&buffer[..n]);
+                frames.extend(&buffer[..n]);
                 
                 // Procesar mensajes completos
-                while message_buffer.len() >= 4 {
-                    let msg_len = u32::from_be_bytes([
-                        message_buffer[0],
-                        message_buffer[1],
-                        message_buffer[2],
-                        message_buffer[3],
-                    ]) as usize;
+                loop {
+                    let payload = match frames.next_frame() {
+                        Ok(Some(payload)) => payload,
+                        Ok(None) => break,
+                        Err(e) => {
+                            error!("❌ Frame inválido del servidor: {}", e);
+                            break 'receive;
+                        }
+                    };
                     
-                    if message_buffer.len() >= 4 + msg_len {
-                        // Mensaje completo
-                        if let Ok(json_str) = std::str::from_utf8(&message_buffer[4..4 + msg_len]) {
-                            match serde_json::from_str::<ServerMessage>(json_str) {
-                                Ok(msg) => {
-                                    if let Ok(mut incoming_lock) = incoming.lock() {
-                                        incoming_lock.push(msg);
-                                    }
-                                }
-                                Err(e) => {
-                                    warn!("⚠️ Error deserializando mensaje: {}", e);
-                                }
+                    match codec.decode::<ServerMessage>(&payload) {
+                        Ok(msg) => {
+                            if let ServerMessage::Welcome { codec: selected, .. } = &msg {
+                                codec = *selected;
                             }
+                            let droppable = msg.is_droppable();
+                            let _ = incoming.send(msg, payload.len() + 4, droppable);
+                        }
+                        Err(e) => {
+                            warn!("⚠️ Error deserializando mensaje: {}", e);
                         }
-                        
-                        message_buffer.drain(0..4 + msg_len);
-                    } else {
-                        break;
                     }
                 }
             }
@@ -158,6 +268,22 @@ fn receive_server_messages(
     info!("🔌 Thread de recepción terminado");
 }
 
+/// Lo que consume los mensajes además de los jugadores: grabación, chat, voz, chunks, inventario y armas
+#[derive(SystemParam)]
+struct MessageSinks<'w> {
+    recorder: Option<ResMut<'w, MatchRecorder>>,
+    chat: ResMut<'w, ChatHistory>,
+    voice: ResMut<'w, VoiceChat>,
+    chunks: ResMut<'w, ClientChunks>,
+    time: ResMut<'w, WorldTime>,
+    weather: ResMut<'w, ClientWeather>,
+    inventory: ResMut<'w, ClientInventory>,
+    world_items: ResMut<'w, ClientWorldItems>,
+    trade: ResMut<'w, ClientTrade>,
+    weapon: ResMut<'w, ClientWeapon>,
+    body: ResMut<'w, ClientBody>,
+}
+
 fn process_server_messages(
     mut commands: Commands,
     mut client: ResMut<NetworkClient>,
@@ -165,17 +291,55 @@ fn process_server_messages(
     mut materials: ResMut<Assets<StandardMaterial>>,
     player_query: Query<(Entity, &PlayerId)>,
     mut transform_query: Query<&mut Transform>,
+    mut sinks: MessageSinks,
 ) {
     let messages: Vec<ServerMessage> = {
         let mut incoming = client.incoming_messages.lock().unwrap();
         std::mem::take(&mut *incoming)
     };
     
+    if let Some(recorder) = sinks.recorder.as_mut() {
+        if let Err(e) = recorder.record(&messages) {
+            error!("❌ Error escribiendo la grabación, se detiene: {}", e);
+            commands.remove_resource::<MatchRecorder>();
+        }
+    }
+    
+    // Entidades creadas en este frame: los comandos aún no se aplicaron y la
+    // query no las ve, así que un segundo snapshot no debe duplicarlas
+    let mut spawned: HashMap<PlayerId, Entity> = HashMap::new();
+    
     for message in messages {
         match message {
-            ServerMessage::Connected { player_id, tick_rate, session_token, spawn_position } => {
+            ServerMessage::Welcome { codec, server_info } => {
+                info!("🤝 Servidor: {} ({}) - {}/{} jugadores - codec {:?}",
+                    server_info.server_name, server_info.map_name,
+                    server_info.player_count, server_info.max_players, codec);
+                client.codec = codec;
+                client.server_info = Some(server_info);
+                
+                let login_msg = ClientMessage::Login {
+                    username: client.username.clone(),
+                    password: client.password.clone(),
+                };
+                if let Err(e) = client.send(&login_msg) {
+                    error!("❌ Error enviando mensaje de login: {}", e);
+                }
+            }
+            
+            ServerMessage::Queued { position, queue_length } => {
+                info!("⏳ Servidor lleno - posición {}/{} en la cola", position, queue_length);
+                client.queue_position = Some(position);
+            }
+            
+            ServerMessage::ServerInfo(_) => {
+                // Solo se recibe como respuesta a StatusQuery
+            }
+            
+            ServerMessage::Connected { player_id, tick_rate, session_token, .. } => {
                 client.connected = true;
                 client.local_player_id = Some(player_id);
+                client.queue_position = None;
                 info!("🎮 Conectado como jugador {:?}", player_id);
                 info!("⚡ Tick rate del servidor: {} Hz", tick_rate);
                 info!("🔑 Token de sesión recibido: {}", session_token);
@@ -187,7 +351,7 @@ fn process_server_messages(
                 // TODO: Mostrar UI de error
             }
             
-            ServerMessage::Registered { player_id, session_token } => {
+            ServerMessage::Registered { player_id, .. } => {
                 info!("✅ Registrado exitosamente como {:?}", player_id);
                 // TODO: Cambiar a pantalla de login
             }
@@ -212,6 +376,10 @@ fn process_server_messages(
                             transform.translation = state.position;
                             transform.rotation = state.rotation;
                         }
+                    } else if let Some(&entity) = spawned.get(&state.player_id) {
+                        commands.entity(entity).insert(
+                            Transform::from_translation(state.position).with_rotation(state.rotation),
+                        );
                     } else {
                         // Crear nueva entidad
                         let is_local = Some(state.player_id) == client.local_player_id;
@@ -235,6 +403,7 @@ fn process_server_messages(
                         if is_local {
                             entity_cmds.insert(LocalPlayer);
                         }
+                        spawned.insert(state.player_id, entity_cmds.id());
                         
                         info!("🎯 Spawneado jugador {:?} (local: {})", state.player_id, is_local);
                     }
@@ -262,6 +431,7 @@ fn process_server_messages(
                     health: 100.0,
                     is_grounded: true,
                     last_input_sequence: 0,
+                    flashlight: false,
                 };
                 client.player_states.insert(player_id, state);
             }
@@ -287,6 +457,106 @@ fn process_server_messages(
             ServerMessage::Pong { .. } => {
                 // Ignorar pongs por ahora
             }
+            
+            ServerMessage::Ping { timestamp } => {
+                // Eco inmediato para que el servidor mida nuestro RTT
+                let _ = client.send(&ClientMessage::Pong { timestamp });
+            }
+            
+            ServerMessage::Chat { channel, sender, text } => {
+                sinks.chat.push_message(channel, sender, text, &client.username);
+            }
+            
+            ServerMessage::ChatNotice { text } => {
+                sinks.chat.push_notice(text);
+            }
+            
+            ServerMessage::Voice(packet) => {
+                sinks.voice.receive(packet);
+            }
+            
+            ServerMessage::RadioTuned { frequency } => {
+                sinks.voice.radio = frequency;
+                sinks.chat.push_notice(match frequency {
+                    Some(frequency) => format!("Radio sintonizada en {} (mantén B para hablar)", frequency),
+                    None => "Radio apagada".to_string(),
+                });
+            }
+            
+            ServerMessage::WorldInfo(info) => {
+                sinks.chunks.set_world(info);
+            }
+            
+            ServerMessage::Chunk(chunk) => {
+                sinks.chunks.receive(chunk);
+            }
+            
+            ServerMessage::WorldTime(clock) => {
+                sinks.time.receive(clock);
+            }
+            
+            ServerMessage::Weather(weather) => {
+                sinks.weather.receive(weather);
+            }
+            
+            ServerMessage::BodyTemperature { celsius } => {
+                sinks.weather.set_body_temperature(celsius);
+            }
+            
+            ServerMessage::Inventory(inventory) => {
+                sinks.inventory.receive_snapshot(inventory);
+            }
+            
+            ServerMessage::InventoryUpdate { sequence, diffs } => {
+                sinks.inventory.receive_update(sequence, diffs);
+            }
+            
+            ServerMessage::InventoryRejected { sequence, reason } => {
+                sinks.chat.push_notice(format!("Inventario: {}", reason));
+                sinks.inventory.receive_rejection(sequence, reason);
+            }
+            
+            ServerMessage::LootContents { sequence, container, items } => {
+                sinks.inventory.receive_loot(sequence, container, items);
+            }
+            
+            ServerMessage::WorldItems { updated, removed } => {
+                sinks.world_items.receive(updated, removed);
+            }
+            
+            ServerMessage::TradeWindow { sequence, view } => {
+                sinks.inventory.acknowledge(sequence);
+                if let Some(notice) = sinks.trade.receive_window(view) {
+                    sinks.chat.push_notice(notice);
+                }
+            }
+            
+            ServerMessage::TradeClosed { sequence, trade, completed, reason } => {
+                sinks.inventory.acknowledge(sequence);
+                sinks.chat.push_notice(format!("Comercio: {}", reason));
+                sinks.trade.receive_closed(trade, completed, reason);
+            }
+            
+            ServerMessage::WeaponState(status) => {
+                sinks.weapon.receive_status(status);
+            }
+            
+            ServerMessage::ShotFired { origin, direction, .. } => {
+                sinks.weapon.receive_shot(origin, direction);
+            }
+            
+            ServerMessage::Hit { shooter, target, damage, .. } => {
+                sinks.weapon.receive_hit(shooter, target, damage);
+            }
+            
+            ServerMessage::Kill { killer, killer_name, victim, victim_name, weapon } => {
+                sinks.chat.push_notice(format!("💀 {} ha matado a {} ({})", killer_name, victim_name, weapon));
+                sinks.weapon.receive_kill(killer, victim);
+            }
+            
+            ServerMessage::BodyHealth(body) => {
+                sinks.body.receive(body);
+            }
         }
     }
 }
@@ -296,7 +566,9 @@ fn send_player_input(
     keyboard: Res<ButtonInput<KeyCode>>,
     camera_query: Query<&crate::camera::PlayerCamera>,
     mut sequence: ResMut<InputSequence>,
-    _time: Res<Time>,
+    gear: Res<NightGear>,
+    chat: Res<ChatHistory>,
+    weapon: Res<ClientWeapon>,
 ) {
     // Solo enviar si estamos conectados y tenemos un jugador
     if !client.connected || client.local_player_id.is_none() {
@@ -308,16 +580,22 @@ fn send_player_input(
     // Incrementar secuencia
     sequence.0 += 1;
     
-    // Construir input
+    // Construir input (mientras se escribe en el chat las teclas no mueven)
+    let pressed = |key: KeyCode| !chat.typing && keyboard.pressed(key);
     let input = protocol::PlayerInput {
-        move_forward: keyboard.pressed(KeyCode::KeyW),
-        move_backward: keyboard.pressed(KeyCode::KeyS),
-        move_left: keyboard.pressed(KeyCode::KeyA),
-        move_right: keyboard.pressed(KeyCode::KeyD),
-        jump: keyboard.pressed(KeyCode::Space),
-        sprint: keyboard.pressed(KeyCode::ShiftLeft),
+        move_forward: pressed(KeyCode::KeyW),
+        move_backward: pressed(KeyCode::KeyS),
+        move_left: pressed(KeyCode::KeyA),
+        move_right: pressed(KeyCode::KeyD),
+        jump: pressed(KeyCode::Space),
+        sprint: pressed(KeyCode::ShiftLeft),
         camera_yaw: camera.yaw,
         camera_pitch: camera.pitch,
+        flashlight: gear.flashlight,
+        fire: weapon.trigger.fire,
+        aim: weapon.trigger.aim,
+        reload: weapon.trigger.reload,
+        switch_fire_mode: weapon.trigger.switch_fire_mode,
     };
     
     let message = ClientMessage::PlayerInput {
@@ -326,25 +604,22 @@ fn send_player_input(
     };
     
     // Enviar al servidor
-    if let Some(stream) = &client.stream {
-        if let Ok(mut stream_lock) = stream.lock() {
-            if let Err(e) = send_client_message(&mut *stream_lock, &message) {
-                warn!("⚠️ Error enviando input al servidor: {}", e);
-                // No rompemos la conexión por un error de envío
-            }
-        }
+    if let Err(e) = client.send(&message) {
+        warn!("⚠️ Error enviando input al servidor: {}", e);
+        // No rompemos la conexión por un error de envío
     }
 }
 
-fn send_client_message(stream: &mut TcpStream, message: &ClientMessage) -> Result<(), std::io::Error> {
-    if let Ok(json) = serde_json::to_string(message) {
-        let len = json.len() as u32;
-        let mut data = len.to_be_bytes().to_vec();
-        data.extend(json.as_bytes());
-        stream.write_all(&data)?;
-        stream.flush()?;
-        Ok(())
-    } else {
-        Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to serialize message"))
+fn disconnect_on_exit(mut exit_events: EventReader<AppExit>, mut client: ResMut<NetworkClient>) {
+    if exit_events.read().next().is_some() && client.stream.is_some() {
+        client.disconnect();
     }
+}
+
+fn send_client_message(stream: &mut SecureStream, codec: WireCodec, message: &ClientMessage) -> Result<(), std::io::Error> {
+    let data = codec.encode_frame(message)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    stream.write_all(&data)?;
+    stream.flush()?;
+    Ok(())
 }
\ No newline at end of file
@@ -1,6 +1,4 @@
 use bevy::prelude::*;
-use bevy::text::*;
-use bevy::sprite::Anchor;
 use tn1_shared::components::*;
 
 pub struct PlayerTagsPlugin;
@@ -25,12 +23,16 @@ pub struct TagText;
 #[derive(Component)]
 pub struct Billboard;
 
+/// Jugadores remotos (sin LocalPlayer) que aún no tienen tag
+type UntaggedRemotePlayers<'w, 's> = Query<'w, 's, (Entity, &'static PlayerId), (With<Player>, Without<LocalPlayer>, Without<PlayerTag>)>;
+
+/// Jugador local sin tag
+type UntaggedLocalPlayers<'w, 's> = Query<'w, 's, (Entity, &'static PlayerId), (With<LocalPlayer>, Without<PlayerTag>)>;
+
 fn create_tags_for_new_players(
     mut commands: Commands,
-    // Jugadores remotos (sin LocalPlayer)
-    remote_players: Query<(Entity, &PlayerId), (With<Player>, Without<LocalPlayer>, Without<PlayerTag>)>,
-    // Jugador local
-    local_players: Query<(Entity, &PlayerId), (With<LocalPlayer>, Without<PlayerTag>)>,
+    remote_players: UntaggedRemotePlayers,
+    local_players: UntaggedLocalPlayers,
 ) {
     // Crear tags para jugadores remotos
     for (entity, player_id) in remote_players.iter() {
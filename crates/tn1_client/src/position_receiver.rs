@@ -1,3 +1,4 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use tn1_shared::events::*;
 use tn1_shared::components::*;
@@ -26,11 +27,20 @@ pub struct LastSeenPlayers {
     pub last_update: f32,
 }
 
+/// Mallas y materiales para los jugadores remotos nuevos
+#[derive(SystemParam)]
+struct PlayerAssets<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+}
+
+/// Jugadores remotos, para limpiar los que ya no llegan
+type RemotePlayers<'w, 's> = Query<'w, 's, (Entity, &'static PlayerId), (With<Player>, Without<LocalPlayer>)>;
+
 /// Recibe las posiciones autoritativas del servidor y las aplica
 fn receive_player_positions(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: PlayerAssets,
     mut position_events: EventReader<PlayerPositionEvent>,
     mut player_query: Query<(&mut Transform, &PlayerId), With<Player>>,
     local_player_query: Query<&PlayerId, With<LocalPlayer>>,
@@ -72,8 +82,8 @@ fn receive_player_positions(
             info!("🌐 Creando jugador remoto: {:?}", position_event.player_id);
             
             // Crear representación visual del jugador remoto
-            let sphere_mesh = meshes.add(Sphere::new(0.5).mesh().ico(5).unwrap());
-            let remote_player_material = materials.add(Color::srgb(1.0, 0.3, 0.3)); // ROJO para jugadores remotos
+            let sphere_mesh = assets.meshes.add(Sphere::new(0.5).mesh().ico(5).unwrap());
+            let remote_player_material = assets.materials.add(Color::srgb(1.0, 0.3, 0.3)); // ROJO para jugadores remotos
             
             commands.spawn((
                 Mesh3d(sphere_mesh),
@@ -81,7 +91,7 @@ fn receive_player_positions(
                 Transform::from_translation(position_event.position),
                 Player,
                 position_event.player_id,
-                PlayerName(format!("Remote_{}", position_event.player_id.0.to_string()[..8].to_string())),
+                PlayerName(format!("Remote_{}", &position_event.player_id.0.to_string()[..8])),
                 Health::new(100.0),
             ));
         }
@@ -105,7 +115,7 @@ fn update_camera_from_local_player(
 /// Limpia jugadores remotos que se han desconectado
 fn cleanup_disconnected_players(
     mut commands: Commands,
-    remote_players: Query<(Entity, &PlayerId), (With<Player>, Without<LocalPlayer>)>,
+    remote_players: RemotePlayers,
     mut last_seen: ResMut<LastSeenPlayers>,
     time: Res<Time>,
 ) {
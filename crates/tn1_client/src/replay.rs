@@ -0,0 +1,615 @@
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tn1_shared::{codec::{FrameBuffer, WireCodec}, components::*, protocol::*};
+use crate::camera::{handle_mouse_input, PlayerCamera};
+use crate::config::ClientSettings;
+use crate::networking::NetworkClient;
+
+const MATCH_MAGIC: &[u8; 8] = b"TN1MATCH";
+pub const MATCH_FORMAT_VERSION: u32 = 2;
+pub const MATCH_EXTENSION: &str = "tn1match";
+
+/// Cada cuánto se vuelca el buffer al disco mientras se graba
+const FLUSH_INTERVAL_SECS: f64 = 1.0;
+
+/// Cabecera de una partida grabada por el cliente
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchHeader {
+    pub format_version: u32,
+    pub protocol_version: u32,
+    pub server_address: String,
+    pub username: String,
+    /// Segundos UNIX al empezar la grabación
+    pub recorded_at: u64,
+}
+
+/// Mensajes del servidor procesados en un frame del cliente
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MatchRecord {
+    /// Segundos desde el inicio de la grabación
+    pub time: f64,
+    pub messages: Vec<ServerMessage>,
+}
+
+// ---------------------------------------------------------------------------
+// Grabación
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct MatchRecordSettings {
+    /// Directorio (un archivo por sesión) o archivo `.tn1match`; `None` desactiva la grabación
+    pub path: Option<PathBuf>,
+}
+
+impl Default for MatchRecordSettings {
+    fn default() -> Self {
+        Self {
+            path: std::env::var("TN1_MATCH_RECORD").ok().filter(|path| !path.is_empty()).map(PathBuf::from),
+        }
+    }
+}
+
+/// Graba los `ServerMessage` recibidos; requiere `ClientNetworkingPlugin`
+#[derive(Default)]
+pub struct MatchRecordPlugin {
+    pub settings: MatchRecordSettings,
+}
+
+impl Plugin for MatchRecordPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(path) = &self.settings.path else { return };
+
+        let path = if path.extension().is_some_and(|ext| ext == MATCH_EXTENSION) {
+            path.clone()
+        } else {
+            path.join(format!("match-{}.{}", unix_now(), MATCH_EXTENSION))
+        };
+
+        app.insert_resource(PendingMatchRecording(path))
+            .add_systems(Startup, start_match_recording);
+    }
+}
+
+#[derive(Resource)]
+struct PendingMatchRecording(PathBuf);
+
+/// Escribe en disco lo que el networking saca de la cola de entrada
+#[derive(Resource)]
+pub struct MatchRecorder {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    started: Instant,
+    last_flush: f64,
+    records: u32,
+}
+
+impl MatchRecorder {
+    pub fn create(path: &Path, header: &MatchHeader) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let file = File::create(path).with_context(|| format!("creando {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(MATCH_MAGIC)?;
+        writer.write_all(&WireCodec::Bincode.encode_frame(header)?)?;
+
+        Ok(Self {
+            writer,
+            path: path.to_path_buf(),
+            started: Instant::now(),
+            last_flush: 0.0,
+            records: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn records(&self) -> u32 {
+        self.records
+    }
+
+    pub fn record(&mut self, messages: &[ServerMessage]) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        let record = MatchRecord {
+            time: self.started.elapsed().as_secs_f64(),
+            messages: messages.to_vec(),
+        };
+        self.writer.write_all(&WireCodec::Bincode.encode_frame(&record)?)?;
+        self.records += 1;
+
+        if record.time - self.last_flush >= FLUSH_INTERVAL_SECS {
+            self.writer.flush()?;
+            self.last_flush = record.time;
+        }
+        Ok(())
+    }
+}
+
+fn start_match_recording(
+    mut commands: Commands,
+    pending: Res<PendingMatchRecording>,
+    settings: Res<ClientSettings>,
+    client: Res<NetworkClient>,
+) {
+    let header = MatchHeader {
+        format_version: MATCH_FORMAT_VERSION,
+        protocol_version: PROTOCOL_VERSION,
+        server_address: settings.server_address.clone(),
+        username: client.username.clone(),
+        recorded_at: unix_now(),
+    };
+
+    match MatchRecorder::create(&pending.0, &header) {
+        Ok(recorder) => {
+            info!("🎥 Grabando partida en {}", pending.0.display());
+            commands.insert_resource(recorder);
+        }
+        Err(e) => error!("❌ No se pudo iniciar la grabación de la partida: {}", e),
+    }
+    commands.remove_resource::<PendingMatchRecording>();
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// ---------------------------------------------------------------------------
+// Archivo
+// ---------------------------------------------------------------------------
+
+/// Lee una partida grabada; un último registro cortado (cliente cerrado a la fuerza) se ignora
+pub fn read_match(path: &Path) -> Result<(MatchHeader, Vec<MatchRecord>)> {
+    let data = std::fs::read(path).with_context(|| format!("abriendo {}", path.display()))?;
+    if data.len() < MATCH_MAGIC.len() || &data[..MATCH_MAGIC.len()] != MATCH_MAGIC {
+        bail!("{} no es una partida grabada de Trust-No-1", path.display());
+    }
+
+    let mut frames = FrameBuffer::new();
+    frames.extend(&data[MATCH_MAGIC.len()..]);
+
+    let header: MatchHeader = match frames.next_frame()? {
+        Some(payload) => WireCodec::Bincode.decode(&payload)?,
+        None => bail!("la partida no tiene cabecera"),
+    };
+    if header.format_version != MATCH_FORMAT_VERSION {
+        bail!(
+            "formato de partida {} no soportado (se esperaba {})",
+            header.format_version,
+            MATCH_FORMAT_VERSION
+        );
+    }
+
+    let mut records = Vec::new();
+    while let Some(payload) = frames.next_frame()? {
+        records.push(WireCodec::Bincode.decode(&payload)?);
+    }
+    Ok((header, records))
+}
+
+// ---------------------------------------------------------------------------
+// Línea de tiempo
+// ---------------------------------------------------------------------------
+
+/// Snapshot recibido, con el tiempo relativo al primero
+#[derive(Debug, Clone)]
+struct TimelineSnapshot {
+    time: f64,
+    players: Vec<PlayerState>,
+}
+
+/// Snapshots de la partida listos para muestrear en cualquier instante
+#[derive(Debug, Clone)]
+pub struct MatchTimeline {
+    pub header: MatchHeader,
+    /// Jugador que grabó la partida
+    pub local_player: Option<PlayerId>,
+    snapshots: Vec<TimelineSnapshot>,
+    players: Vec<PlayerId>,
+}
+
+impl MatchTimeline {
+    pub fn new(header: MatchHeader, records: &[MatchRecord]) -> Self {
+        let mut local_player = None;
+        let mut snapshots: Vec<TimelineSnapshot> = Vec::new();
+        let mut players = Vec::new();
+
+        for record in records {
+            for message in &record.messages {
+                match message {
+                    ServerMessage::Connected { player_id, .. } => local_player = Some(*player_id),
+                    ServerMessage::WorldState { players: states, .. } => {
+                        for state in states {
+                            if !players.contains(&state.player_id) {
+                                players.push(state.player_id);
+                            }
+                        }
+                        // Varios snapshots en el mismo frame: solo cuenta el último
+                        match snapshots.last_mut() {
+                            Some(last) if last.time == record.time => last.players = states.clone(),
+                            _ => snapshots.push(TimelineSnapshot {
+                                time: record.time,
+                                players: states.clone(),
+                            }),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let start = snapshots.first().map(|snapshot| snapshot.time).unwrap_or(0.0);
+        for snapshot in &mut snapshots {
+            snapshot.time -= start;
+        }
+
+        Self {
+            header,
+            local_player,
+            snapshots,
+            players,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let (header, records) = read_match(path)?;
+        let timeline = Self::new(header, &records);
+        if timeline.snapshots.is_empty() {
+            bail!("{} no contiene ningún snapshot del mundo", path.display());
+        }
+        Ok(timeline)
+    }
+
+    /// Segundos entre el primer y el último snapshot
+    pub fn duration(&self) -> f64 {
+        self.snapshots.last().map(|snapshot| snapshot.time).unwrap_or(0.0)
+    }
+
+    pub fn snapshot_count(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Todos los jugadores que aparecen, en orden de aparición
+    pub fn players(&self) -> &[PlayerId] {
+        &self.players
+    }
+
+    /// Estado del mundo en `time`, interpolando entre los dos snapshots vecinos
+    pub fn sample(&self, time: f64) -> Vec<PlayerState> {
+        let next = self.snapshots.partition_point(|snapshot| snapshot.time <= time);
+        if next == 0 {
+            return self.snapshots.first().map(|s| s.players.clone()).unwrap_or_default();
+        }
+        let previous = &self.snapshots[next - 1];
+        let Some(following) = self.snapshots.get(next) else {
+            return previous.players.clone();
+        };
+
+        let alpha = ((time - previous.time) / (following.time - previous.time)) as f32;
+        previous
+            .players
+            .iter()
+            .map(|state| {
+                let Some(target) = following.players.iter().find(|p| p.player_id == state.player_id) else {
+                    return state.clone();
+                };
+                PlayerState {
+                    position: state.position.lerp(target.position, alpha),
+                    velocity: state.velocity.lerp(target.velocity, alpha),
+                    rotation: state.rotation.slerp(target.rotation, alpha),
+                    ..state.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Visor
+// ---------------------------------------------------------------------------
+
+const SPEEDS: [f32; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+const SEEK_STEP_SECS: f64 = 5.0;
+const FREE_CAMERA_SPEED: f32 = 8.0;
+const FOLLOW_DISTANCE: f32 = 4.0;
+const EYE_HEIGHT: Vec3 = Vec3::new(0.0, 1.6, 0.0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayCamera {
+    /// Cámara suelta: WASD + E/Q, ratón para mirar
+    Free,
+    /// Tercera persona detrás de un jugador
+    Follow(PlayerId),
+}
+
+/// Estado de reproducción del visor
+#[derive(Resource)]
+pub struct ReplayViewer {
+    pub timeline: MatchTimeline,
+    pub time: f64,
+    pub speed: f32,
+    pub paused: bool,
+    pub camera: ReplayCamera,
+    /// El ratón está sobre la UI: no capturar el cursor al hacer clic en la línea de tiempo
+    pointer_over_ui: bool,
+}
+
+impl ReplayViewer {
+    pub fn new(timeline: MatchTimeline) -> Self {
+        let camera = timeline.local_player.map(ReplayCamera::Follow).unwrap_or(ReplayCamera::Free);
+        Self {
+            timeline,
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+            camera,
+            pointer_over_ui: false,
+        }
+    }
+
+    pub fn seek(&mut self, time: f64) {
+        self.time = time.clamp(0.0, self.timeline.duration());
+    }
+
+    fn change_speed(&mut self, steps: isize) {
+        let current = SPEEDS.iter().position(|&s| s >= self.speed).unwrap_or(SPEEDS.len() - 1);
+        let index = (current as isize + steps).clamp(0, SPEEDS.len() as isize - 1) as usize;
+        self.speed = SPEEDS[index];
+    }
+}
+
+/// Reproduce una partida grabada con `MatchRecordPlugin`
+pub struct ReplayViewerPlugin {
+    pub timeline: MatchTimeline,
+}
+
+impl Plugin for ReplayViewerPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.insert_resource(ReplayViewer::new(self.timeline.clone()))
+            .add_systems(Startup, setup_replay_camera)
+            .add_systems(Update, (
+                render_replay_ui,
+                handle_mouse_input.run_if(|viewer: Res<ReplayViewer>| !viewer.pointer_over_ui),
+                handle_replay_keys,
+                advance_replay_clock,
+                apply_replay_snapshot,
+                update_replay_camera,
+            ).chain());
+    }
+}
+
+fn setup_replay_camera(mut commands: Commands) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 5.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+        PlayerCamera {
+            pitch: -0.3,
+            ..default()
+        },
+    ));
+}
+
+fn handle_replay_keys(
+    mut viewer: ResMut<ReplayViewer>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    player_query: Query<&PlayerId, With<Player>>,
+) {
+    if keyboard.just_pressed(KeyCode::Space) {
+        viewer.paused = !viewer.paused;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowLeft) {
+        let time = viewer.time - SEEK_STEP_SECS;
+        viewer.seek(time);
+    }
+    if keyboard.just_pressed(KeyCode::ArrowRight) {
+        let time = viewer.time + SEEK_STEP_SECS;
+        viewer.seek(time);
+    }
+    if keyboard.just_pressed(KeyCode::Home) {
+        viewer.seek(0.0);
+    }
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        viewer.change_speed(1);
+    }
+    if keyboard.just_pressed(KeyCode::ArrowDown) {
+        viewer.change_speed(-1);
+    }
+
+    // F suelta o vuelve a enganchar la cámara; Tab pasa al siguiente jugador
+    if keyboard.just_pressed(KeyCode::KeyF) {
+        viewer.camera = match viewer.camera {
+            ReplayCamera::Follow(_) => ReplayCamera::Free,
+            ReplayCamera::Free => viewer
+                .timeline
+                .local_player
+                .or_else(|| viewer.timeline.players().first().copied())
+                .map(ReplayCamera::Follow)
+                .unwrap_or(ReplayCamera::Free),
+        };
+    }
+    if keyboard.just_pressed(KeyCode::Tab) {
+        let visible: Vec<PlayerId> = viewer
+            .timeline
+            .players()
+            .iter()
+            .filter(|id| player_query.iter().any(|visible| visible == *id))
+            .copied()
+            .collect();
+        let current = match viewer.camera {
+            ReplayCamera::Follow(id) => visible.iter().position(|v| *v == id),
+            ReplayCamera::Free => None,
+        };
+        let next = current.map(|i| (i + 1) % visible.len().max(1)).unwrap_or(0);
+        if let Some(id) = visible.get(next) {
+            viewer.camera = ReplayCamera::Follow(*id);
+        }
+    }
+}
+
+fn advance_replay_clock(mut viewer: ResMut<ReplayViewer>, time: Res<Time>) {
+    if viewer.paused {
+        return;
+    }
+
+    let time = viewer.time + time.delta_secs_f64() * viewer.speed as f64;
+    viewer.seek(time);
+    if viewer.time >= viewer.timeline.duration() {
+        viewer.paused = true;
+    }
+}
+
+fn apply_replay_snapshot(
+    mut commands: Commands,
+    viewer: Res<ReplayViewer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut player_query: Query<(Entity, &PlayerId, &mut Transform), With<Player>>,
+) {
+    let states: HashMap<PlayerId, PlayerState> = viewer
+        .timeline
+        .sample(viewer.time)
+        .into_iter()
+        .map(|state| (state.player_id, state))
+        .collect();
+
+    for (entity, player_id, mut transform) in player_query.iter_mut() {
+        match states.get(player_id) {
+            Some(state) => {
+                transform.translation = state.position;
+                transform.rotation = state.rotation;
+            }
+            // Aún no entró o ya se fue en este punto de la partida
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+
+    for (player_id, state) in &states {
+        if player_query.iter().any(|(_, id, _)| id == player_id) {
+            continue;
+        }
+
+        let color = if Some(*player_id) == viewer.timeline.local_player {
+            Color::srgb(0.2, 0.6, 1.0) // Azul para quien grabó
+        } else {
+            Color::srgb(1.0, 0.2, 0.2)
+        };
+        commands.spawn((
+            Mesh3d(meshes.add(Sphere::new(0.5).mesh().ico(5).unwrap())),
+            MeshMaterial3d(materials.add(color)),
+            Transform::from_translation(state.position).with_rotation(state.rotation),
+            Player,
+            *player_id,
+        ));
+    }
+}
+
+/// Jugadores de la partida a los que puede seguir la cámara
+type FollowedPlayers<'w, 's> = Query<'w, 's, (&'static Transform, &'static PlayerId), (With<Player>, Without<PlayerCamera>)>;
+
+fn update_replay_camera(
+    viewer: Res<ReplayViewer>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut camera_query: Query<(&mut Transform, &PlayerCamera)>,
+    player_query: FollowedPlayers,
+) {
+    let Ok((mut camera_transform, camera)) = camera_query.get_single_mut() else { return };
+    let rotation = Quat::from_euler(EulerRot::YXZ, camera.yaw, camera.pitch, 0.0);
+    camera_transform.rotation = rotation;
+
+    match viewer.camera {
+        ReplayCamera::Free => {
+            let mut direction = Vec3::ZERO;
+            if keyboard.pressed(KeyCode::KeyW) { direction += *camera_transform.forward(); }
+            if keyboard.pressed(KeyCode::KeyS) { direction -= *camera_transform.forward(); }
+            if keyboard.pressed(KeyCode::KeyD) { direction += *camera_transform.right(); }
+            if keyboard.pressed(KeyCode::KeyA) { direction -= *camera_transform.right(); }
+            if keyboard.pressed(KeyCode::KeyE) { direction += Vec3::Y; }
+            if keyboard.pressed(KeyCode::KeyQ) { direction -= Vec3::Y; }
+
+            let boost = if keyboard.pressed(KeyCode::ShiftLeft) { 4.0 } else { 1.0 };
+            camera_transform.translation += direction.normalize_or_zero() * FREE_CAMERA_SPEED * boost * time.delta_secs();
+        }
+        ReplayCamera::Follow(target) => {
+            // Si el jugador no está en este momento la cámara se queda donde estaba
+            if let Some((transform, _)) = player_query.iter().find(|(_, id)| **id == target) {
+                camera_transform.translation = transform.translation + EYE_HEIGHT + rotation * Vec3::Z * FOLLOW_DISTANCE;
+            }
+        }
+    }
+}
+
+fn render_replay_ui(mut contexts: EguiContexts, mut viewer: ResMut<ReplayViewer>) {
+    let ctx = contexts.ctx_mut();
+    let duration = viewer.timeline.duration();
+
+    egui::TopBottomPanel::bottom("replay_timeline").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            let label = if viewer.paused { "▶" } else { "⏸" };
+            if ui.button(label).clicked() {
+                viewer.paused = !viewer.paused;
+            }
+            ui.label(format!("{} / {}", format_time(viewer.time), format_time(duration)));
+
+            egui::ComboBox::from_id_salt("replay_speed")
+                .selected_text(format!("x{}", viewer.speed))
+                .show_ui(ui, |ui| {
+                    for speed in SPEEDS {
+                        ui.selectable_value(&mut viewer.speed, speed, format!("x{}", speed));
+                    }
+                });
+
+            let local_player = viewer.timeline.local_player;
+            let players = viewer.timeline.players().to_vec();
+            let camera_label = |camera: ReplayCamera| match camera {
+                ReplayCamera::Free => "🎥 Cámara libre".to_string(),
+                ReplayCamera::Follow(id) if Some(id) == local_player => format!("👁 {} (grabación)", &id.0.to_string()[..8]),
+                ReplayCamera::Follow(id) => format!("👁 {}", &id.0.to_string()[..8]),
+            };
+            egui::ComboBox::from_id_salt("replay_camera")
+                .selected_text(camera_label(viewer.camera))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut viewer.camera, ReplayCamera::Free, camera_label(ReplayCamera::Free));
+                    for id in players {
+                        let camera = ReplayCamera::Follow(id);
+                        ui.selectable_value(&mut viewer.camera, camera, camera_label(camera));
+                    }
+                });
+        });
+
+        // Arrastrar la barra mueve la reproducción a cualquier punto de la partida
+        let mut time = viewer.time;
+        ui.spacing_mut().slider_width = ui.available_width();
+        if ui.add(egui::Slider::new(&mut time, 0.0..=duration).show_value(false)).changed() {
+            viewer.seek(time);
+        }
+
+        ui.colored_label(
+            egui::Color32::GRAY,
+            "Espacio: pausa · ←/→: ±5 s · ↑/↓: velocidad · Inicio: reiniciar · F: soltar/seguir cámara · Tab: siguiente jugador · WASD+E/Q: cámara libre",
+        );
+    });
+
+    viewer.pointer_over_ui = ctx.is_pointer_over_area();
+}
+
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
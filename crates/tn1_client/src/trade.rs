@@ -0,0 +1,117 @@
+//! Ventana de comercio con otro jugador según el servidor.
+//!
+//! El servidor manda la ventana entera (`TradeWindow`) cada vez que cambia y
+//! avisa al cerrarse (`TradeClosed`). Con T se propone un comercio al jugador
+//! más cercano o se acepta el que te han propuesto, con Y se confirma o se
+//! retira la confirmación y con X se cancela. Los items se ponen y se quitan
+//! con `ClientInventory::request_trade` (`Offer` / `Withdraw`).
+
+use bevy::prelude::*;
+use tn1_shared::components::PlayerId;
+use tn1_shared::trade::{TradeAction, TradePhase, TradeView};
+use crate::chat::ChatHistory;
+use crate::inventory::ClientInventory;
+use crate::networking::NetworkClient;
+
+/// Teclas del comercio y hasta dónde se busca con quién (el servidor valida
+/// con su propia distancia)
+#[derive(Resource, Debug, Clone)]
+pub struct TradeKeys {
+    pub request: KeyCode,
+    pub confirm: KeyCode,
+    pub cancel: KeyCode,
+    pub reach: f32,
+}
+
+impl Default for TradeKeys {
+    fn default() -> Self {
+        Self { request: KeyCode::KeyT, confirm: KeyCode::KeyY, cancel: KeyCode::KeyX, reach: 2.0 }
+    }
+}
+
+pub struct TradePlugin;
+
+impl Plugin for TradePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TradeKeys>()
+            .init_resource::<ClientTrade>()
+            .add_systems(Update, trade_keys);
+    }
+}
+
+/// Comercio abierto y cómo acabó el último
+#[derive(Resource, Default)]
+pub struct ClientTrade {
+    window: Option<TradeView>,
+    /// Id, si se hizo y motivo del último comercio cerrado
+    last_closed: Option<(u64, bool, String)>,
+}
+
+impl ClientTrade {
+    pub fn window(&self) -> Option<&TradeView> {
+        self.window.as_ref()
+    }
+
+    pub fn last_closed(&self) -> Option<&(u64, bool, String)> {
+        self.last_closed.as_ref()
+    }
+
+    /// Ventana nueva; devuelve el aviso para el chat si es una propuesta recibida
+    pub fn receive_window(&mut self, view: TradeView) -> Option<String> {
+        let proposed = view.phase == TradePhase::Pending && !view.requested && self.window.as_ref().is_none_or(|window| window.id != view.id);
+        let notice = proposed.then(|| format!("{} quiere comerciar contigo (T para aceptar, X para rechazar)", view.partner_name));
+        self.window = Some(view);
+        notice
+    }
+
+    pub fn receive_closed(&mut self, trade: u64, completed: bool, reason: String) {
+        info!("🤝 Comercio {} cerrado: {}", trade, reason);
+        if self.window.as_ref().is_some_and(|window| window.id == trade) {
+            self.window = None;
+        }
+        self.last_closed = Some((trade, completed, reason));
+    }
+}
+
+fn trade_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    keys: Res<TradeKeys>,
+    chat: Res<ChatHistory>,
+    client: Res<NetworkClient>,
+    trade: Res<ClientTrade>,
+    mut inventory: ResMut<ClientInventory>,
+) {
+    if chat.typing {
+        return;
+    }
+    let action = if keyboard.just_pressed(keys.request) {
+        match trade.window() {
+            Some(window) if window.phase == TradePhase::Pending && !window.requested => Some(TradeAction::Accept { trade: window.id }),
+            Some(_) => None,
+            None => nearest_player(&client, keys.reach).map(|player| TradeAction::Request { player }),
+        }
+    } else if keyboard.just_pressed(keys.confirm) {
+        trade.window().map(|window| if window.mine.confirmed { TradeAction::Unconfirm } else { TradeAction::Confirm })
+    } else if keyboard.just_pressed(keys.cancel) {
+        trade.window().map(|_| TradeAction::Cancel)
+    } else {
+        None
+    };
+    if let Some(action) = action {
+        inventory.request_trade(&client, action);
+    }
+}
+
+/// El otro jugador más cercano a menos de `reach` metros
+fn nearest_player(client: &NetworkClient, reach: f32) -> Option<PlayerId> {
+    let local = client.local_player_id?;
+    let position = client.player_states.get(&local)?.position;
+    client
+        .player_states
+        .values()
+        .filter(|state| state.player_id != local)
+        .map(|state| (state.position.distance(position), state.player_id))
+        .filter(|(distance, _)| *distance <= reach)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, player)| player)
+}
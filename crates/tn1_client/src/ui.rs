@@ -1,8 +1,17 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use bevy::window::{CursorGrabMode, PrimaryWindow};
-use tn1_shared::components::{Health, LocalPlayer, PlayerId};
+use tn1_shared::components::{BodyZone, Health, LocalPlayer, PlayerId};
+use crate::chat::{ChatHistory, ChatLine};
+use crate::daylight::{NightGear, WorldTime};
+use crate::medical::ClientBody;
 use crate::networking::NetworkClient;
+use crate::voice::VoiceChat;
+use crate::weather::{ClientWeather, Footsteps};
+use tn1_shared::protocol::ChatChannel;
+use tn1_shared::conditioner::{LinkConditions, NetworkConditions};
+use tn1_shared::voice::VoiceChannel;
+use tn1_shared::weather::HYPOTHERMIA;
 
 pub struct UIPlugin;
 
@@ -18,6 +27,9 @@ impl Plugin for UIPlugin {
                 render_instructions,
                 render_server_stats,
                 update_server_stats,
+                render_network_conditions,
+                render_chat,
+                render_voice,
             ));
     }
 }
@@ -27,6 +39,9 @@ pub struct UIState {
     pub show_debug: bool,
     pub show_inventory: bool,
     pub show_server_stats: bool,
+    pub show_network_conditions: bool,
+    /// Texto a medio escribir en la caja de chat
+    pub chat_input: String,
 }
 
 #[derive(Resource, Default)]
@@ -75,6 +90,13 @@ fn render_debug_ui(
                 ui.colored_label(egui::Color32::GREEN, "✅ Conectado al servidor");
                 ui.label("Modo: Cliente-Servidor");
                 ui.label("Puerto: 127.0.0.1:7777");
+                if let Some(info) = &client.server_info {
+                    ui.label(format!("Servidor: {} ({})", info.server_name, info.map_name));
+                    ui.label(format!("Jugadores: {}/{}", info.player_count, info.max_players));
+                    ui.label(format!("Codec: {:?}", client.codec));
+                }
+            } else if let Some(position) = client.queue_position {
+                ui.colored_label(egui::Color32::YELLOW, format!("⏳ En cola: posición {}", position));
             } else {
                 ui.colored_label(egui::Color32::RED, "❌ Desconectado");
                 ui.label("Modo: Offline (física local)");
@@ -157,9 +179,52 @@ fn render_debug_ui(
 fn render_hud(
     mut contexts: EguiContexts,
     player_query: Query<&Health, With<LocalPlayer>>,
+    world_time: Res<WorldTime>,
+    gear: Res<NightGear>,
+    weather: Res<ClientWeather>,
+    footsteps: Res<Footsteps>,
+    body: Res<ClientBody>,
 ) {
     let ctx = contexts.ctx_mut();
 
+    // Hora del mundo, equipo nocturno y clima
+    if let Some(clock) = world_time.clock() {
+        egui::Area::new("world_clock".into())
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-20.0, 20.0))
+            .show(ctx, |ui| {
+                let icon = if clock.is_night() { "🌙" } else { "☀️" };
+                let mut text = format!("{} {}", icon, clock);
+                if gear.flashlight {
+                    text.push_str("  🔦");
+                }
+                if gear.night_vision {
+                    text.push_str("  🥽");
+                }
+                ui.label(egui::RichText::new(text).color(egui::Color32::WHITE));
+                // Clima, temperatura corporal y pasos cercanos
+                if let Some(weather) = weather.weather() {
+                    ui.label(egui::RichText::new(format!(
+                        "{} {:.0} °C  💨 {:.0} m/s",
+                        weather.kind.icon(),
+                        weather.temperature,
+                        weather.wind_speed()
+                    )).color(egui::Color32::WHITE));
+                }
+                if let Some(celsius) = weather.body_temperature() {
+                    let color = if celsius < HYPOTHERMIA { egui::Color32::LIGHT_BLUE } else { egui::Color32::WHITE };
+                    let warning = if celsius < HYPOTHERMIA { " 🥶 Hipotermia" } else { "" };
+                    ui.label(egui::RichText::new(format!("🌡️ {:.1} °C{}", celsius, warning)).color(color));
+                }
+                if let Some(offset) = footsteps.nearest() {
+                    ui.label(egui::RichText::new(format!(
+                        "👣 Pasos al {} ({:.0} m)",
+                        compass(offset),
+                        offset.xz().length()
+                    )).color(egui::Color32::YELLOW));
+                }
+            });
+    }
+
     // Crosshair simple
     egui::Area::new("crosshair".into())
         .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
@@ -192,10 +257,45 @@ fn render_hud(
                         .fill(health_color)
                         .text(format!("{:.0}/{:.0}", health.current, health.max)));
                 });
+                // Zonas del cuerpo: las destrozadas en rojo, con lo que suponen
+                if let Some(body) = body.body() {
+                    for zone in BodyZone::ALL {
+                        let ratio = body.get(zone) / zone.max_health();
+                        let color = if body.is_destroyed(zone) {
+                            egui::Color32::DARK_RED
+                        } else if ratio > 0.6 {
+                            egui::Color32::GREEN
+                        } else if ratio > 0.3 {
+                            egui::Color32::YELLOW
+                        } else {
+                            egui::Color32::RED
+                        };
+                        ui.horizontal(|ui| {
+                            ui.add_sized([80.0, 14.0], egui::Label::new(zone.name()));
+                            ui.add(egui::ProgressBar::new(ratio)
+                                .desired_width(120.0)
+                                .fill(color)
+                                .text(format!("{:.0}/{:.0}", body.get(zone), zone.max_health())));
+                        });
+                    }
+                    if !body.can_sprint() {
+                        ui.colored_label(egui::Color32::RED, "🦴 Pierna rota: no puedes correr");
+                    }
+                    if body.sway() > 0.5 {
+                        ui.colored_label(egui::Color32::YELLOW, "✋ Brazos heridos: el arma tiembla");
+                    }
+                }
             });
     }
 }
 
+/// Rumbo aproximado de un desplazamiento (norte = -Z, este = +X)
+fn compass(offset: Vec3) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SO", "O", "NO"];
+    let angle = offset.x.atan2(-offset.z).to_degrees().rem_euclid(360.0);
+    POINTS[((angle + 22.5) / 45.0) as usize % 8]
+}
+
 fn render_instructions(
     mut contexts: EguiContexts,
     window_query: Query<&Window, With<PrimaryWindow>>,
@@ -235,6 +335,10 @@ fn render_instructions(
                 ui.label("• Espacio - Saltar");
                 ui.label("• F3 - Debug info");
                 ui.label("• F4 - Debug física (consola)");
+                ui.label("• F6 - Simulador de red");
+                ui.label("• Enter - Chat (/help para comandos)");
+                ui.label("• V - Hablar (push-to-talk), B - Radio (/radio <frecuencia>)");
+                ui.label("• L - Linterna, N - Visión nocturna");
                 
                 ui.separator();
                 if client.connected {
@@ -323,4 +427,144 @@ fn render_server_stats(
             ui.separator();
             ui.colored_label(egui::Color32::GRAY, "💡 Presiona F1 para ocultar");
         });
-}
\ No newline at end of file
+}
+
+fn render_network_conditions(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UIState>,
+    conditions: Option<Res<NetworkConditions>>,
+) {
+    let Some(conditions) = conditions else { return };
+    let ctx = contexts.ctx_mut();
+
+    // Toggle con F6
+    if ctx.input(|i| i.key_pressed(egui::Key::F6)) {
+        ui_state.show_network_conditions = !ui_state.show_network_conditions;
+    }
+
+    if !ui_state.show_network_conditions {
+        return;
+    }
+
+    // Se edita una copia y se publica al final: los threads de red leen en cada mensaje
+    let mut current = conditions.get();
+    egui::Window::new("📶 Condiciones de red")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (name, preset) in LinkConditions::presets() {
+                    if ui.selectable_label(current == preset, name).clicked() {
+                        current = preset;
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.add(egui::Slider::new(&mut current.latency_ms, 0..=1000).text("Latencia (ms)"));
+            ui.add(egui::Slider::new(&mut current.jitter_ms, 0..=500).text("Jitter (ms)"));
+            ui.add(egui::Slider::new(&mut current.loss_percent, 0.0..=50.0).text("Pérdida (%)"));
+            ui.add(egui::Slider::new(&mut current.duplicate_percent, 0.0..=50.0).text("Duplicados (%)"));
+            ui.add(egui::Slider::new(&mut current.reorder_percent, 0.0..=50.0).text("Desorden (%)"));
+            ui.add(egui::Slider::new(&mut current.bandwidth_kbps, 0..=10_000).text("Ancho de banda (kbit/s, 0 = sin límite)"));
+
+            ui.separator();
+            if current.is_active() {
+                ui.colored_label(egui::Color32::YELLOW, "⚠️ Simulando red degradada (en cada sentido)");
+            } else {
+                ui.colored_label(egui::Color32::GREEN, "✅ Red sin alterar");
+            }
+            ui.colored_label(egui::Color32::GRAY, "💡 Presiona F6 para ocultar");
+        });
+
+    if current != conditions.get() {
+        conditions.set(current);
+    }
+}
+
+fn render_chat(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UIState>,
+    mut chat: ResMut<ChatHistory>,
+    client: Res<NetworkClient>,
+) {
+    let ctx = contexts.ctx_mut();
+    let mut submitted = None;
+
+    egui::Window::new("💬 Chat")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(10.0, -10.0))
+        .resizable(false)
+        .default_width(380.0)
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(160.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for line in chat.lines() {
+                        ui.colored_label(chat_color(line), line.display(&client.username));
+                    }
+                });
+
+            ui.separator();
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut ui_state.chat_input)
+                    .hint_text("Enter para escribir - /help")
+                    .desired_width(f32::INFINITY),
+            );
+
+            // Enter abre la caja y, con texto escrito, lo envía; ESC la cierra
+            let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if response.lost_focus() && enter {
+                submitted = Some(std::mem::take(&mut ui_state.chat_input));
+            } else if enter && !response.has_focus() {
+                response.request_focus();
+            }
+            chat.typing = response.has_focus();
+        });
+
+    if let Some(input) = submitted {
+        chat.submit(&input, &client);
+    }
+}
+
+fn chat_color(line: &ChatLine) -> egui::Color32 {
+    match line.channel {
+        None => egui::Color32::YELLOW,
+        Some(ChatChannel::Global) => egui::Color32::WHITE,
+        Some(ChatChannel::Proximity) => egui::Color32::LIGHT_GRAY,
+        Some(ChatChannel::Team) => egui::Color32::LIGHT_GREEN,
+        Some(ChatChannel::Whisper { .. }) => egui::Color32::from_rgb(210, 160, 255),
+    }
+}
+
+/// Indicador de voz: si estamos transmitiendo y quién se oye
+fn render_voice(mut contexts: EguiContexts, voice: Res<VoiceChat>) {
+    let speakers: Vec<_> = voice.speakers().collect();
+    if voice.transmitting.is_none() && speakers.is_empty() {
+        return;
+    }
+
+    egui::Area::new("voice".into())
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -20.0))
+        .show(contexts.ctx_mut(), |ui| {
+            match voice.transmitting {
+                Some(VoiceChannel::Proximity) => {
+                    ui.colored_label(egui::Color32::LIGHT_GREEN, "🎙️ Hablando (cerca)");
+                }
+                Some(VoiceChannel::Radio { frequency }) => {
+                    ui.colored_label(egui::Color32::LIGHT_BLUE, format!("📻 Hablando por radio {}", frequency));
+                }
+                None => {}
+            }
+            if voice.transmitting.is_some() && !voice.has_input() {
+                ui.colored_label(egui::Color32::YELLOW, "⚠️ Sin micrófono (TN1_VOICE_INPUT)");
+            }
+            for (player_id, status) in speakers {
+                let channel = match status.channel {
+                    VoiceChannel::Proximity => format!("{:.0}%", status.gain * 100.0),
+                    VoiceChannel::Radio { frequency } => format!("radio {}", frequency),
+                };
+                ui.label(format!("🔊 Player {} ({})", &player_id.0.to_string()[..8], channel));
+            }
+        });
+}
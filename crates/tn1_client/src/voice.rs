@@ -0,0 +1,548 @@
+//! Voz del cliente: push-to-talk, jitter buffer por jugador y reproducción 3D.
+//!
+//! El audio sale de un `VoiceInput` (micrófono, o un WAV con `TN1_VOICE_INPUT`)
+//! y se codifica en Opus. Opus, el micrófono y la reproducción con
+//! bevy_kira_audio van en la feature `voice`; sin ella (tests, CI) la voz viaja
+//! en PCM y se decodifica igual, pero no suena.
+
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use tn1_shared::components::PlayerId;
+use tn1_shared::protocol::{ClientMessage, VoicePacket};
+use tn1_shared::voice::*;
+use crate::chat::ChatHistory;
+use crate::networking::NetworkClient;
+
+/// Frames que se acumulan antes de empezar a reproducir (60 ms de colchón)
+pub const JITTER_FRAMES: usize = 3;
+/// Con más retraso acumulado se tira lo más viejo
+const MAX_BUFFERED_FRAMES: usize = 25;
+/// Sin paquetes durante este tiempo se da por terminado el turno de palabra
+const SPEAKER_TIMEOUT: f64 = 0.5;
+
+/// Teclas y fuente de audio (variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct VoiceSettings {
+    pub push_to_talk: KeyCode,
+    /// Habla por la radio sintonizada en lugar de en proximidad
+    pub radio_key: KeyCode,
+    /// WAV que se transmite en lugar del micrófono
+    pub input_file: Option<PathBuf>,
+    pub volume: f32,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            push_to_talk: KeyCode::KeyV,
+            radio_key: KeyCode::KeyB,
+            input_file: std::env::var("TN1_VOICE_INPUT").ok().filter(|path| !path.is_empty()).map(PathBuf::from),
+            volume: std::env::var("TN1_VOICE_VOLUME")
+                .ok()
+                .and_then(|volume| volume.parse().ok())
+                .unwrap_or(1.0),
+        }
+    }
+}
+
+/// Audio decodificado listo para sonar; lo consume `VoiceAudioPlugin`
+#[derive(Event, Debug, Clone)]
+pub struct VoicePlayback {
+    pub speaker: PlayerId,
+    pub channel: VoiceChannel,
+    pub gain: f32,
+    pub position: Option<Vec3>,
+    pub samples: Vec<i16>,
+}
+
+/// Reordena los frames de un jugador y marca los que se perdieron
+#[derive(Default)]
+pub struct JitterBuffer {
+    frames: BTreeMap<u32, Vec<u8>>,
+    next: Option<u32>,
+    playing: bool,
+}
+
+impl JitterBuffer {
+    pub fn push(&mut self, sequence: u32, data: Vec<u8>) {
+        // Llegó tarde: su hueco ya se rellenó
+        if self.next.is_some_and(|next| sequence < next) {
+            return;
+        }
+        self.frames.insert(sequence, data);
+        while self.frames.len() > MAX_BUFFERED_FRAMES {
+            self.frames.pop_first();
+            self.next = self.frames.keys().next().copied();
+        }
+    }
+
+    /// Siguiente frame: `Some(None)` si se perdió, `None` si hay que esperar
+    pub fn pop(&mut self) -> Option<Option<Vec<u8>>> {
+        if !self.playing {
+            if self.frames.len() < JITTER_FRAMES {
+                return None;
+            }
+            self.playing = true;
+            self.next = self.frames.keys().next().copied();
+        }
+        if self.frames.is_empty() {
+            // Se vació: vuelve a acumular colchón antes de seguir
+            self.playing = false;
+            return None;
+        }
+        let next = self.next?;
+        self.next = Some(next.wrapping_add(1));
+        Some(self.frames.remove(&next))
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+/// Lo que se sabe de un jugador que está hablando (indicador de la UI)
+#[derive(Debug, Clone)]
+pub struct SpeakerStatus {
+    pub channel: VoiceChannel,
+    pub gain: f32,
+    pub position: Option<Vec3>,
+    pub frames_played: u32,
+}
+
+struct SpeakerStream {
+    status: SpeakerStatus,
+    buffer: JitterBuffer,
+    codec: VoiceCodec,
+    decoder: Box<dyn VoiceDecoder>,
+    /// Tiempo pendiente de reproducir (segundos)
+    clock: f32,
+    last_packet: f64,
+}
+
+/// Estado de la voz: captura, radio y jugadores que se oyen
+#[derive(Resource)]
+pub struct VoiceChat {
+    input: Option<Box<dyn VoiceInput>>,
+    encoder: Box<dyn VoiceEncoder>,
+    sequence: u32,
+    capture_clock: f32,
+    /// Canal por el que se está hablando ahora mismo
+    pub transmitting: Option<VoiceChannel>,
+    /// Frecuencia confirmada por el servidor (`/radio`)
+    pub radio: Option<u16>,
+    speakers: HashMap<PlayerId, SpeakerStream>,
+    now: f64,
+}
+
+impl VoiceChat {
+    pub fn new(settings: &VoiceSettings) -> Self {
+        let input: Option<Box<dyn VoiceInput>> = match &settings.input_file {
+            Some(path) => match WavInput::open(path, true) {
+                Ok(input) => {
+                    info!("🎙️ Voz desde {}", path.display());
+                    Some(Box::new(input))
+                }
+                Err(e) => {
+                    warn!("⚠️ No se pudo abrir {}: {}", path.display(), e);
+                    None
+                }
+            },
+            None => open_microphone(),
+        };
+        Self {
+            input,
+            encoder: new_encoder(),
+            sequence: 0,
+            capture_clock: 0.0,
+            transmitting: None,
+            radio: None,
+            speakers: HashMap::new(),
+            now: 0.0,
+        }
+    }
+
+    /// Cambia la fuente de audio (p. ej. un WAV en los tests)
+    pub fn set_input(&mut self, input: Box<dyn VoiceInput>) {
+        self.input = Some(input);
+    }
+
+    pub fn has_input(&self) -> bool {
+        self.input.is_some()
+    }
+
+    /// Jugadores que se están oyendo ahora mismo
+    pub fn speakers(&self) -> impl Iterator<Item = (&PlayerId, &SpeakerStatus)> {
+        self.speakers.iter().map(|(id, stream)| (id, &stream.status))
+    }
+
+    /// Paquete recibido del servidor
+    pub fn receive(&mut self, packet: VoicePacket) {
+        let now = self.now;
+        // Stream nuevo (o el jugador cambió de codec): decoder limpio
+        if self.speakers.get(&packet.speaker).is_none_or(|stream| stream.codec != packet.codec) {
+            let Some(decoder) = new_decoder(packet.codec) else {
+                debug!("🔇 Voz en {:?} sin decoder disponible", packet.codec);
+                return;
+            };
+            self.speakers.insert(packet.speaker, SpeakerStream {
+                status: SpeakerStatus { channel: packet.channel, gain: packet.gain, position: packet.position, frames_played: 0 },
+                buffer: JitterBuffer::default(),
+                codec: packet.codec,
+                decoder,
+                clock: 0.0,
+                last_packet: now,
+            });
+        }
+
+        let stream = self.speakers.get_mut(&packet.speaker).unwrap();
+        stream.status.channel = packet.channel;
+        stream.status.gain = packet.gain;
+        stream.status.position = packet.position;
+        stream.last_packet = now;
+        stream.buffer.push(packet.sequence, packet.data);
+    }
+}
+
+impl FromWorld for VoiceChat {
+    fn from_world(world: &mut World) -> Self {
+        Self::new(&world.get_resource_or_insert_with(VoiceSettings::default).clone())
+    }
+}
+
+/// Captura, envío y decodificación de la voz (sin audio: ver `VoiceAudioPlugin`)
+pub struct VoicePlugin;
+
+impl Plugin for VoicePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoiceSettings>()
+            .init_resource::<VoiceChat>()
+            .add_event::<VoicePlayback>()
+            .add_systems(Update, (capture_voice, decode_voice));
+    }
+}
+
+fn capture_voice(
+    client: Res<NetworkClient>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<VoiceSettings>,
+    chat: Res<ChatHistory>,
+    time: Res<Time>,
+    mut voice: ResMut<VoiceChat>,
+) {
+    let pressed = |key: KeyCode| !chat.typing && keyboard.pressed(key);
+    let channel = match voice.radio {
+        Some(frequency) if pressed(settings.radio_key) => Some(VoiceChannel::Radio { frequency }),
+        _ if pressed(settings.push_to_talk) => Some(VoiceChannel::Proximity),
+        _ => None,
+    };
+
+    voice.transmitting = channel.filter(|_| client.connected && client.local_player_id.is_some());
+    let Some(channel) = voice.transmitting else {
+        voice.capture_clock = 0.0;
+        return;
+    };
+
+    // Se envía un frame por cada 20 ms transcurridos; si la fuente va por detrás
+    // no se acumula más de un par de frames de retraso
+    voice.capture_clock = (voice.capture_clock + time.delta_secs()).min(FRAME_SECONDS * 3.0);
+    let voice = &mut *voice;
+    while voice.capture_clock >= FRAME_SECONDS {
+        let Some(frame) = voice.input.as_mut().and_then(|input| input.read_frame()) else { break };
+        voice.capture_clock -= FRAME_SECONDS;
+
+        let data = match voice.encoder.encode(&frame) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("⚠️ Error codificando voz: {}", e);
+                continue;
+            }
+        };
+        let message = ClientMessage::Voice { channel, codec: voice.encoder.codec(), sequence: voice.sequence, data };
+        voice.sequence = voice.sequence.wrapping_add(1);
+        if let Err(e) = client.send(&message) {
+            warn!("⚠️ Error enviando voz: {}", e);
+        }
+    }
+}
+
+/// Saca del jitter buffer los frames que tocan en este tick y los decodifica
+fn decode_voice(time: Res<Time>, mut voice: ResMut<VoiceChat>, mut playback: EventWriter<VoicePlayback>) {
+    voice.now = time.elapsed_secs_f64();
+    let now = voice.now;
+
+    for (speaker, stream) in voice.speakers.iter_mut() {
+        stream.clock = (stream.clock + time.delta_secs()).min(FRAME_SECONDS * MAX_BUFFERED_FRAMES as f32);
+        let mut samples = Vec::new();
+        while stream.clock >= FRAME_SECONDS {
+            let Some(packet) = stream.buffer.pop() else {
+                stream.clock = 0.0;
+                break;
+            };
+            stream.clock -= FRAME_SECONDS;
+            match stream.decoder.decode(packet.as_deref()) {
+                Ok(frame) => samples.extend(frame),
+                Err(e) => debug!("🔇 Frame de voz inválido: {}", e),
+            }
+            stream.status.frames_played += 1;
+        }
+
+        if !samples.is_empty() {
+            playback.send(VoicePlayback {
+                speaker: *speaker,
+                channel: stream.status.channel,
+                gain: stream.status.gain,
+                position: stream.status.position,
+                samples,
+            });
+        }
+    }
+
+    voice.speakers.retain(|_, stream| !stream.buffer.is_empty() || now - stream.last_packet < SPEAKER_TIMEOUT);
+}
+
+#[cfg(feature = "voice")]
+fn new_encoder() -> Box<dyn VoiceEncoder> {
+    match native::OpusEncoder::new() {
+        Ok(encoder) => Box::new(encoder),
+        Err(e) => {
+            warn!("⚠️ Opus no disponible, la voz irá sin comprimir: {}", e);
+            Box::new(Pcm16Codec)
+        }
+    }
+}
+
+#[cfg(not(feature = "voice"))]
+fn new_encoder() -> Box<dyn VoiceEncoder> {
+    Box::new(Pcm16Codec)
+}
+
+fn new_decoder(codec: VoiceCodec) -> Option<Box<dyn VoiceDecoder>> {
+    match codec {
+        VoiceCodec::Pcm16 => Some(Box::new(Pcm16Codec)),
+        #[cfg(feature = "voice")]
+        VoiceCodec::Opus => native::OpusDecoder::new().ok().map(|decoder| Box::new(decoder) as Box<dyn VoiceDecoder>),
+        #[cfg(not(feature = "voice"))]
+        VoiceCodec::Opus => None,
+    }
+}
+
+#[cfg(feature = "voice")]
+fn open_microphone() -> Option<Box<dyn VoiceInput>> {
+    match native::MicrophoneInput::open() {
+        Ok(input) => Some(Box::new(input)),
+        Err(e) => {
+            warn!("⚠️ Sin micrófono, la voz solo se escucha: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "voice"))]
+fn open_microphone() -> Option<Box<dyn VoiceInput>> {
+    None
+}
+
+#[cfg(feature = "voice")]
+pub use native::VoiceAudioPlugin;
+
+/// Opus, micrófono (cpal) y reproducción con bevy_kira_audio
+#[cfg(feature = "voice")]
+mod native {
+    use bevy::prelude::*;
+    use bevy_kira_audio::prelude::{Audio, AudioControl, AudioPlugin, AudioSource};
+    use bevy_kira_audio::prelude::{Frame, StaticSoundData, StaticSoundSettings};
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use std::collections::VecDeque;
+    use std::sync::{mpsc, Arc, Mutex};
+    use tn1_shared::voice::*;
+    use super::{VoicePlayback, VoiceSettings};
+    use crate::camera::PlayerCamera;
+
+    /// Audio capturado que se guarda como mucho (el resto se descarta)
+    const MAX_CAPTURED_SAMPLES: usize = FRAME_SAMPLES * 5;
+
+    /// `opus::Encoder` no es `Sync`; el `Mutex` lo permite en un `Resource`
+    pub struct OpusEncoder(Mutex<opus::Encoder>);
+
+    impl OpusEncoder {
+        pub fn new() -> Result<Self, VoiceError> {
+            opus::Encoder::new(SAMPLE_RATE, opus::Channels::Mono, opus::Application::Voip)
+                .map(|encoder| Self(Mutex::new(encoder)))
+                .map_err(|e| VoiceError::Codec(e.to_string()))
+        }
+    }
+
+    impl VoiceEncoder for OpusEncoder {
+        fn codec(&self) -> VoiceCodec {
+            VoiceCodec::Opus
+        }
+
+        fn encode(&mut self, frame: &[i16]) -> Result<Vec<u8>, VoiceError> {
+            self.0
+                .get_mut()
+                .unwrap()
+                .encode_vec(frame, MAX_VOICE_PACKET)
+                .map_err(|e| VoiceError::Codec(e.to_string()))
+        }
+    }
+
+    pub struct OpusDecoder(Mutex<opus::Decoder>);
+
+    impl OpusDecoder {
+        pub fn new() -> Result<Self, VoiceError> {
+            opus::Decoder::new(SAMPLE_RATE, opus::Channels::Mono)
+                .map(|decoder| Self(Mutex::new(decoder)))
+                .map_err(|e| VoiceError::Codec(e.to_string()))
+        }
+    }
+
+    impl VoiceDecoder for OpusDecoder {
+        fn decode(&mut self, packet: Option<&[u8]>) -> Result<Vec<i16>, VoiceError> {
+            let mut frame = vec![0; FRAME_SAMPLES];
+            // Con un paquete vacío Opus interpola el hueco (PLC)
+            let samples = self
+                .0
+                .get_mut()
+                .unwrap()
+                .decode(packet.unwrap_or(&[]), &mut frame, false)
+                .map_err(|e| VoiceError::Codec(e.to_string()))?;
+            frame.truncate(samples);
+            Ok(frame)
+        }
+    }
+
+    /// Micrófono por defecto del sistema, mezclado a mono y remuestreado a 48 kHz
+    pub struct MicrophoneInput {
+        captured: Arc<Mutex<VecDeque<i16>>>,
+        /// Al soltarlo termina el thread que mantiene vivo el stream
+        _stop: mpsc::Sender<()>,
+    }
+
+    impl MicrophoneInput {
+        pub fn open() -> Result<Self, VoiceError> {
+            let captured = Arc::new(Mutex::new(VecDeque::new()));
+            let (stop, stopped) = mpsc::channel::<()>();
+            let (ready, opened) = mpsc::channel();
+
+            // `cpal::Stream` no es `Send`: vive en su propio thread
+            let buffer = captured.clone();
+            std::thread::spawn(move || match open_stream(buffer) {
+                Ok(stream) => {
+                    let _ = ready.send(Ok(()));
+                    let _ = stopped.recv();
+                    drop(stream);
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                }
+            });
+
+            opened
+                .recv()
+                .map_err(|_| VoiceError::Device("el thread de captura terminó".to_string()))??;
+            info!("🎙️ Micrófono abierto");
+            Ok(Self { captured, _stop: stop })
+        }
+    }
+
+    impl VoiceInput for MicrophoneInput {
+        fn read_frame(&mut self) -> Option<Vec<i16>> {
+            let mut captured = self.captured.lock().unwrap();
+            if captured.len() < FRAME_SAMPLES {
+                return None;
+            }
+            Some(captured.drain(..FRAME_SAMPLES).collect())
+        }
+    }
+
+    fn open_stream(captured: Arc<Mutex<VecDeque<i16>>>) -> Result<cpal::Stream, VoiceError> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| VoiceError::Device("no hay micrófono".to_string()))?;
+        let config = device.default_input_config().map_err(|e| VoiceError::Device(e.to_string()))?;
+        let channels = config.channels() as usize;
+        let sample_rate = config.sample_rate().0;
+
+        let push = move |mono: Vec<i16>| {
+            let mut captured = captured.lock().unwrap();
+            captured.extend(resample(&mono, sample_rate, SAMPLE_RATE));
+            // Sin push-to-talk nadie lee: solo se guarda el audio más reciente
+            let excess = captured.len().saturating_sub(MAX_CAPTURED_SAMPLES);
+            captured.drain(..excess);
+        };
+        let on_error = |e| warn!("⚠️ Error del micrófono: {}", e);
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    push(data.chunks(channels).map(|frame| (frame.iter().sum::<f32>() / channels as f32 * i16::MAX as f32) as i16).collect())
+                },
+                on_error,
+                None,
+            ),
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config.into(),
+                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                    push(data.chunks(channels).map(|frame| (frame.iter().map(|s| *s as i32).sum::<i32>() / channels as i32) as i16).collect())
+                },
+                on_error,
+                None,
+            ),
+            format => return Err(VoiceError::Device(format!("formato de micrófono no soportado: {:?}", format))),
+        }
+        .map_err(|e| VoiceError::Device(e.to_string()))?;
+        stream.play().map_err(|e| VoiceError::Device(e.to_string()))?;
+        Ok(stream)
+    }
+
+    /// Reproduce la voz recibida con bevy_kira_audio, posicionada respecto a la cámara
+    pub struct VoiceAudioPlugin;
+
+    impl Plugin for VoiceAudioPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_plugins(AudioPlugin).add_systems(Update, play_voice.after(super::decode_voice));
+        }
+    }
+
+    fn play_voice(
+        mut playback: EventReader<VoicePlayback>,
+        audio: Res<Audio>,
+        mut sources: ResMut<Assets<AudioSource>>,
+        settings: Res<VoiceSettings>,
+        camera: Query<&GlobalTransform, With<PlayerCamera>>,
+    ) {
+        let listener = camera.get_single().ok();
+
+        for chunk in playback.read() {
+            let frames: Vec<Frame> = chunk.samples.iter().map(|sample| Frame::from_mono(*sample as f32 / i16::MAX as f32)).collect();
+            let sound = StaticSoundData {
+                sample_rate: SAMPLE_RATE,
+                frames: frames.into(),
+                settings: StaticSoundSettings::default(),
+                slice: None,
+            };
+
+            // Izquierda/derecha según hacia dónde mira la cámara; detrás suena algo más apagado
+            let mut volume = chunk.gain * settings.volume;
+            let mut panning = 0.5;
+            if let (Some(listener), Some(position)) = (listener, chunk.position) {
+                let direction = (position - listener.translation()).normalize_or_zero();
+                panning = 0.5 + 0.5 * direction.dot(*listener.right()) as f64;
+                if direction.dot(*listener.forward()) < 0.0 {
+                    volume *= 0.8;
+                }
+            }
+
+            audio
+                .play(sources.add(AudioSource { sound }))
+                .with_volume(volume as f64)
+                .with_panning(panning);
+        }
+    }
+}
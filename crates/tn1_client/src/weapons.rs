@@ -0,0 +1,206 @@
+//! Arma en la mano según el servidor, con los disparos propios predichos.
+//!
+//! Clic izquierdo dispara, clic derecho apunta, R recarga y B cambia el modo de
+//! fuego; los botones viajan en cada `PlayerInput` (`ClientWeapon::trigger`) y
+//! el servidor decide. Su respuesta (`WeaponState`) llega cada vez que cambia
+//! el arma, pero con el RTT de retraso: para que el fogonazo salga al apretar,
+//! aquí se predicen los disparos con el último estado, el modo y la cadencia, y
+//! las balas predichas se corrigen con cada estado nuevo. Los fogonazos de los
+//! demás llegan como `ShotFired`; las muertes (`Kill`) van al chat.
+
+use bevy::prelude::*;
+use tn1_shared::components::{LocalPlayer, PlayerId};
+use tn1_shared::items::{FireMode, BURST_SIZE};
+use tn1_shared::weapons::{aim_direction, muzzle_origin, WeaponPhase, WeaponStatus};
+use crate::camera::PlayerCamera;
+use crate::chat::ChatHistory;
+
+/// Segundos que dura un fogonazo
+const FLASH_SECS: f32 = 0.05;
+
+/// Distancia de los ojos a la boca del arma, para el fogonazo
+const MUZZLE_OFFSET: f32 = 0.6;
+
+/// Botones del arma
+#[derive(Resource, Debug, Clone)]
+pub struct WeaponControls {
+    pub fire: MouseButton,
+    pub aim: MouseButton,
+    pub reload: KeyCode,
+    pub fire_mode: KeyCode,
+}
+
+impl Default for WeaponControls {
+    fn default() -> Self {
+        Self { fire: MouseButton::Left, aim: MouseButton::Right, reload: KeyCode::KeyR, fire_mode: KeyCode::KeyB }
+    }
+}
+
+/// Estado de los botones tal como va en `PlayerInput`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WeaponTrigger {
+    pub fire: bool,
+    pub aim: bool,
+    pub reload: bool,
+    pub switch_fire_mode: bool,
+}
+
+pub struct WeaponsPlugin;
+
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeaponControls>()
+            .init_resource::<ClientWeapon>()
+            .add_systems(Update, (read_weapon_controls, predict_shots, spawn_muzzle_flashes, fade_muzzle_flashes).chain());
+    }
+}
+
+/// Impacto visto por este cliente: quién, a quién y cuánto
+pub type HitReport = (PlayerId, PlayerId, f32);
+
+/// Arma en la mano, predicción de disparos y lo último que ha pasado
+#[derive(Resource, Default)]
+pub struct ClientWeapon {
+    pub trigger: WeaponTrigger,
+    status: Option<WeaponStatus>,
+    /// Balas contando los disparos que el servidor aún no ha confirmado
+    predicted_ammo: u32,
+    predicted_shots: u32,
+    /// Segundo local en que la cadencia deja disparar otra vez
+    next_shot: f64,
+    burst_left: u32,
+    /// Gatillo del frame anterior, para los modos de una pulsación
+    was_firing: bool,
+    /// Fogonazos por poner: desde dónde y hacia dónde
+    flashes: Vec<(Vec3, Vec3)>,
+    last_hit: Option<HitReport>,
+    last_kill: Option<(PlayerId, PlayerId)>,
+}
+
+impl ClientWeapon {
+    pub fn status(&self) -> Option<&WeaponStatus> {
+        self.status.as_ref()
+    }
+
+    pub fn predicted_ammo(&self) -> u32 {
+        self.predicted_ammo
+    }
+
+    /// Disparos propios predichos desde que se entró
+    pub fn predicted_shots(&self) -> u32 {
+        self.predicted_shots
+    }
+
+    pub fn last_hit(&self) -> Option<&HitReport> {
+        self.last_hit.as_ref()
+    }
+
+    /// Asesino y víctima de la última muerte por disparo
+    pub fn last_kill(&self) -> Option<&(PlayerId, PlayerId)> {
+        self.last_kill.as_ref()
+    }
+
+    /// Estado autoritativo: se queda con menos balas que la predicción salvo
+    /// que haya recargado, cambiado de arma o ya no se esté disparando
+    pub fn receive_status(&mut self, status: Option<WeaponStatus>) {
+        if let Some(status) = &status {
+            let same_weapon = self.status.as_ref().is_some_and(|old| old.item == status.item && old.ammo >= status.ammo);
+            self.predicted_ammo = if same_weapon && self.trigger.fire { self.predicted_ammo.min(status.ammo) } else { status.ammo };
+        }
+        self.status = status;
+    }
+
+    pub fn receive_shot(&mut self, origin: Vec3, direction: Vec3) {
+        self.flashes.push((origin, direction));
+    }
+
+    pub fn receive_hit(&mut self, shooter: PlayerId, target: PlayerId, damage: f32) {
+        self.last_hit = Some((shooter, target, damage));
+    }
+
+    pub fn receive_kill(&mut self, killer: PlayerId, victim: PlayerId) {
+        self.last_kill = Some((killer, victim));
+    }
+}
+
+/// Un fogonazo en la escena hasta `remaining`
+#[derive(Component)]
+struct MuzzleFlash {
+    remaining: f32,
+}
+
+fn read_weapon_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    controls: Res<WeaponControls>,
+    chat: Res<ChatHistory>,
+    mut weapon: ResMut<ClientWeapon>,
+) {
+    weapon.trigger = if chat.typing {
+        WeaponTrigger::default()
+    } else {
+        WeaponTrigger {
+            fire: mouse.pressed(controls.fire),
+            aim: mouse.pressed(controls.aim),
+            reload: keyboard.pressed(controls.reload),
+            switch_fire_mode: keyboard.pressed(controls.fire_mode),
+        }
+    };
+}
+
+/// Lo mismo que decidiría el servidor con el último estado conocido
+fn predict_shots(
+    time: Res<Time>,
+    mut weapon: ResMut<ClientWeapon>,
+    local: Query<&Transform, With<LocalPlayer>>,
+    camera: Query<&PlayerCamera>,
+) {
+    let weapon = &mut *weapon;
+    let pulled = weapon.trigger.fire && !weapon.was_firing;
+    weapon.was_firing = weapon.trigger.fire;
+    let Some(status) = &weapon.status else { return };
+    let now = time.elapsed_secs_f64();
+
+    if status.mode == FireMode::Burst && pulled && weapon.burst_left == 0 {
+        weapon.burst_left = BURST_SIZE;
+    }
+    let wants = match status.mode {
+        FireMode::Semi => pulled,
+        FireMode::Burst => weapon.burst_left > 0,
+        FireMode::Auto => weapon.trigger.fire,
+    };
+    if !wants || status.phase != WeaponPhase::Ready || weapon.predicted_ammo == 0 || now < weapon.next_shot {
+        if weapon.predicted_ammo == 0 {
+            weapon.burst_left = 0;
+        }
+        return;
+    }
+    let fire_rate = status.fire_rate;
+
+    weapon.predicted_ammo -= 1;
+    weapon.predicted_shots += 1;
+    weapon.burst_left = weapon.burst_left.saturating_sub(1);
+    weapon.next_shot = weapon.next_shot.max(now - time.delta_secs_f64()) + 60.0 / fire_rate as f64;
+    if let (Ok(transform), Ok(camera)) = (local.get_single(), camera.get_single()) {
+        weapon.flashes.push((muzzle_origin(transform.translation), aim_direction(camera.yaw, camera.pitch)));
+    }
+}
+
+fn spawn_muzzle_flashes(mut commands: Commands, mut weapon: ResMut<ClientWeapon>) {
+    for (origin, direction) in weapon.flashes.drain(..) {
+        commands.spawn((
+            PointLight { intensity: 60_000.0, range: 8.0, color: Color::srgb(1.0, 0.8, 0.4), shadows_enabled: false, ..default() },
+            Transform::from_translation(origin + direction * MUZZLE_OFFSET),
+            MuzzleFlash { remaining: FLASH_SECS },
+        ));
+    }
+}
+
+fn fade_muzzle_flashes(mut commands: Commands, time: Res<Time>, mut flashes: Query<(Entity, &mut MuzzleFlash)>) {
+    for (entity, mut flash) in flashes.iter_mut() {
+        flash.remaining -= time.delta_secs();
+        if flash.remaining <= 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
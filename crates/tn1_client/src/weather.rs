@@ -0,0 +1,241 @@
+//! Clima en el cliente: niebla, lluvia y nieve según el `Weather` que manda el
+//! servidor, y lo que el clima cambia del juego.
+//!
+//! La visibilidad del clima recorta el alcance de la vista (ver `daylight`), el
+//! ruido de la lluvia y la tormenta tapa los pasos de los demás y el servidor
+//! manda la temperatura corporal propia para el HUD.
+
+use bevy::prelude::*;
+use tn1_shared::components::{LocalPlayer, PlayerId};
+use tn1_shared::weather::{footstep_range, Weather, WeatherKind};
+use crate::daylight::update_sky;
+use crate::networking::NetworkClient;
+
+/// Gotas o copos alrededor del jugador con el frente entero encima
+const PARTICLE_COUNT: usize = 400;
+/// Mitad del lado de la caja de partículas y altura desde la que caen
+const PARTICLE_BOX: f32 = 20.0;
+const PARTICLE_HEIGHT: f32 = 15.0;
+/// Distancia a la que la niebla lo tapa todo con el cielo despejado
+const CLEAR_FOG_DISTANCE: f32 = 600.0;
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClientWeather>()
+            .init_resource::<Footsteps>()
+            .add_systems(Startup, load_precipitation_assets)
+            .add_systems(Update, (
+                update_footsteps,
+                update_fog.after(update_sky),
+                update_precipitation.run_if(resource_exists::<PrecipitationAssets>),
+            ));
+    }
+}
+
+/// Último clima y temperatura corporal recibidos del servidor
+#[derive(Resource, Default)]
+pub struct ClientWeather {
+    weather: Option<Weather>,
+    body_temperature: Option<f32>,
+}
+
+impl ClientWeather {
+    pub fn weather(&self) -> Option<Weather> {
+        self.weather
+    }
+
+    pub fn receive(&mut self, weather: Weather) {
+        if self.weather.is_none_or(|current| current.kind != weather.kind) {
+            info!("{} Clima: {} ({:.0} °C)", weather.kind.icon(), weather.kind, weather.temperature);
+        }
+        self.weather = Some(weather);
+    }
+
+    pub fn body_temperature(&self) -> Option<f32> {
+        self.body_temperature
+    }
+
+    pub fn set_body_temperature(&mut self, celsius: f32) {
+        self.body_temperature = Some(celsius);
+    }
+
+    /// Fracción de la vista que deja el clima; sin datos, despejado
+    pub fn visibility(&self) -> f32 {
+        self.weather.map_or(1.0, |weather| weather.visibility())
+    }
+
+    pub fn overcast(&self) -> f32 {
+        self.weather.map_or(0.0, |weather| weather.overcast())
+    }
+}
+
+/// Jugadores remotos cuyos pasos se oyen, del más cercano al más lejano
+#[derive(Resource, Default)]
+pub struct Footsteps {
+    heard: Vec<(PlayerId, Vec3)>,
+}
+
+impl Footsteps {
+    pub fn hears(&self, player_id: PlayerId) -> bool {
+        self.heard.iter().any(|(id, _)| *id == player_id)
+    }
+
+    /// Desplazamiento hasta los pasos más cercanos
+    pub fn nearest(&self) -> Option<Vec3> {
+        self.heard.first().map(|(_, offset)| *offset)
+    }
+}
+
+/// Gota de lluvia o copo de nieve
+#[derive(Component)]
+pub struct Precipitation {
+    index: usize,
+}
+
+type Particles<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Precipitation,
+        &'static mut Transform,
+        &'static mut Visibility,
+        &'static mut Mesh3d,
+        &'static mut MeshMaterial3d<StandardMaterial>,
+    ),
+>;
+
+/// Mallas y materiales de la lluvia y la nieve
+#[derive(Resource)]
+struct PrecipitationAssets {
+    rain: (Handle<Mesh>, Handle<StandardMaterial>),
+    snow: (Handle<Mesh>, Handle<StandardMaterial>),
+}
+
+fn load_precipitation_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(PrecipitationAssets {
+        rain: (
+            meshes.add(Cuboid::new(0.02, 0.5, 0.02)),
+            materials.add(StandardMaterial { base_color: Color::srgba(0.7, 0.8, 1.0, 0.6), ..default() }),
+        ),
+        snow: (
+            meshes.add(Cuboid::new(0.08, 0.08, 0.08)),
+            materials.add(StandardMaterial { base_color: Color::WHITE, unlit: true, ..default() }),
+        ),
+    });
+}
+
+/// Se oye a quien se mueve dentro del alcance de sus pasos con el ruido que haga
+fn update_footsteps(
+    client: Res<NetworkClient>,
+    weather: Res<ClientWeather>,
+    mut footsteps: ResMut<Footsteps>,
+    local: Query<&Transform, With<LocalPlayer>>,
+    remotes: Query<(&PlayerId, &Transform), Without<LocalPlayer>>,
+) {
+    footsteps.heard.clear();
+    let Ok(listener) = local.get_single() else { return };
+    let masking = weather.weather().map_or(0.0, |weather| weather.footstep_masking());
+
+    for (player_id, transform) in remotes.iter() {
+        let Some(state) = client.player_states.get(player_id) else { continue };
+        let offset = transform.translation - listener.translation;
+        if offset.length() <= footstep_range(state.velocity.xz().length(), masking) {
+            footsteps.heard.push((*player_id, offset));
+        }
+    }
+    footsteps.heard.sort_by(|a, b| a.1.length().total_cmp(&b.1.length()));
+}
+
+/// Niebla de distancia en la cámara: se acerca con la mala visibilidad y toma
+/// el color del cielo (que ya oscurece `update_sky` según la hora y las nubes)
+fn update_fog(
+    mut commands: Commands,
+    weather: Res<ClientWeather>,
+    clear_color: Res<ClearColor>,
+    mut cameras: Query<(Entity, Option<&mut DistanceFog>), With<Camera3d>>,
+) {
+    let end = CLEAR_FOG_DISTANCE * weather.visibility();
+    let falloff = FogFalloff::Linear { start: end * 0.2, end };
+    for (entity, fog) in cameras.iter_mut() {
+        match fog {
+            Some(mut fog) => {
+                fog.color = clear_color.0;
+                fog.falloff = falloff.clone();
+            }
+            None => {
+                commands.entity(entity).insert(DistanceFog {
+                    color: clear_color.0,
+                    falloff: falloff.clone(),
+                    ..default()
+                });
+            }
+        }
+    }
+}
+
+/// Lluvia y nieve: una caja de partículas que sigue al jugador; caen con el
+/// viento y reaparecen arriba al salir de la caja
+fn update_precipitation(
+    mut commands: Commands,
+    weather: Res<ClientWeather>,
+    time: Res<Time>,
+    assets: Res<PrecipitationAssets>,
+    local: Query<&Transform, (With<LocalPlayer>, Without<Precipitation>)>,
+    mut particles: Particles,
+) {
+    let Some(weather) = weather.weather() else { return };
+    let Ok(anchor) = local.get_single() else { return };
+    let active = (weather.precipitation() * PARTICLE_COUNT as f32).round() as usize;
+    if active == 0 && particles.is_empty() {
+        return;
+    }
+
+    let snow = weather.kind == WeatherKind::Snow;
+    let (mesh, material) = if snow { &assets.snow } else { &assets.rain };
+    let fall_speed = if snow { 1.5 } else { 12.0 };
+    let velocity = Vec3::new(0.0, -fall_speed, 0.0) + weather.wind_vector() * if snow { 0.8 } else { 0.3 };
+    let respawn = |anchor: Vec3| {
+        anchor
+            + Vec3::new(
+                (rand::random::<f32>() - 0.5) * 2.0 * PARTICLE_BOX,
+                rand::random::<f32>() * PARTICLE_HEIGHT,
+                (rand::random::<f32>() - 0.5) * 2.0 * PARTICLE_BOX,
+            )
+    };
+
+    if particles.is_empty() {
+        for index in 0..PARTICLE_COUNT {
+            commands.spawn((
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material.clone()),
+                Transform::from_translation(respawn(anchor.translation)),
+                Visibility::Hidden,
+                Precipitation { index },
+            ));
+        }
+        return;
+    }
+
+    for (particle, mut transform, mut visibility, mut particle_mesh, mut particle_material) in particles.iter_mut() {
+        if particle.index >= active {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        }
+        visibility.set_if_neq(Visibility::Inherited);
+        if particle_mesh.0 != *mesh {
+            particle_mesh.0 = mesh.clone();
+            particle_material.0 = material.clone();
+        }
+        transform.translation += velocity * time.delta_secs();
+        let offset = transform.translation - anchor.translation;
+        if offset.y < -2.0 || offset.x.abs() > PARTICLE_BOX || offset.z.abs() > PARTICLE_BOX {
+            transform.translation = respawn(anchor.translation).with_y(anchor.translation.y + PARTICLE_HEIGHT);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Items del suelo y cadáveres cercanos según el servidor.
+//!
+//! El servidor manda (`WorldItems`) lo que entra en el radio del jugador o
+//! cambia y lo que sale de él; aquí se pinta una caja por item y un bulto por
+//! cadáver. Con F se recoge lo más cercano al alcance o, si es un cadáver, se
+//! abre: su contenido llega como el de cualquier contenedor (`ClientInventory::loot`).
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use tn1_shared::drops::{PickupAction, WorldItemKind, WorldItemState};
+use crate::chat::ChatHistory;
+use crate::inventory::ClientInventory;
+use crate::networking::NetworkClient;
+
+/// Tecla de recoger y hasta dónde se busca (el servidor valida con su propio alcance)
+#[derive(Resource, Debug, Clone)]
+pub struct PickupSettings {
+    pub key: KeyCode,
+    pub reach: f32,
+}
+
+impl Default for PickupSettings {
+    fn default() -> Self {
+        Self { key: KeyCode::KeyF, reach: 2.5 }
+    }
+}
+
+pub struct WorldItemsPlugin;
+
+impl Plugin for WorldItemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PickupSettings>()
+            .init_resource::<ClientWorldItems>()
+            .add_systems(Update, (sync_world_item_entities, pick_up_nearest).chain());
+    }
+}
+
+/// Items del suelo que el servidor dice que hay cerca, por id
+#[derive(Resource, Default)]
+pub struct ClientWorldItems {
+    items: HashMap<u64, WorldItemState>,
+    /// Entidades de la escena; se ponen al día en `sync_world_item_entities`
+    entities: HashMap<u64, Entity>,
+    /// Cambiados desde la última sincronización de la escena
+    dirty: Vec<u64>,
+}
+
+impl ClientWorldItems {
+    pub fn get(&self, id: u64) -> Option<&WorldItemState> {
+        self.items.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &WorldItemState> {
+        self.items.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn receive(&mut self, updated: Vec<WorldItemState>, removed: Vec<u64>) {
+        for id in removed {
+            self.items.remove(&id);
+            self.dirty.push(id);
+        }
+        for item in updated {
+            self.dirty.push(item.id);
+            self.items.insert(item.id, item);
+        }
+    }
+
+    /// Lo más cercano a `position` dentro de `reach`
+    pub fn nearest(&self, position: Vec3, reach: f32) -> Option<&WorldItemState> {
+        self.items
+            .values()
+            .map(|item| (item.position.distance(position), item))
+            .filter(|(distance, _)| *distance <= reach)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, item)| item)
+    }
+}
+
+/// Meshes y materiales compartidos
+struct WorldItemAssets {
+    item: (Handle<Mesh>, Handle<StandardMaterial>),
+    corpse: (Handle<Mesh>, Handle<StandardMaterial>),
+}
+
+fn sync_world_item_entities(
+    mut commands: Commands,
+    mut world_items: ResMut<ClientWorldItems>,
+    mut transforms: Query<&mut Transform>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<WorldItemAssets>>,
+) {
+    if world_items.dirty.is_empty() {
+        return;
+    }
+    let assets = assets.get_or_insert_with(|| WorldItemAssets {
+        item: (meshes.add(Cuboid::new(0.3, 0.15, 0.3)), materials.add(Color::srgb(0.85, 0.7, 0.2))),
+        corpse: (meshes.add(Cuboid::new(0.5, 0.3, 1.8)), materials.add(Color::srgb(0.35, 0.3, 0.25))),
+    });
+
+    let world_items = &mut *world_items;
+    for id in std::mem::take(&mut world_items.dirty) {
+        let Some(item) = world_items.items.get(&id) else {
+            if let Some(entity) = world_items.entities.remove(&id) {
+                commands.entity(entity).despawn_recursive();
+            }
+            continue;
+        };
+        let (mesh, material) = match item.kind {
+            WorldItemKind::Item { .. } => &assets.item,
+            WorldItemKind::Corpse { .. } => &assets.corpse,
+        };
+        match world_items.entities.get(&id).and_then(|entity| transforms.get_mut(*entity).ok()) {
+            Some(mut transform) => transform.translation = item.position,
+            None => {
+                let entity = commands
+                    .spawn((Mesh3d(mesh.clone()), MeshMaterial3d(material.clone()), Transform::from_translation(item.position)))
+                    .id();
+                world_items.entities.insert(id, entity);
+            }
+        }
+    }
+}
+
+fn pick_up_nearest(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<PickupSettings>,
+    chat: Res<ChatHistory>,
+    client: Res<NetworkClient>,
+    world_items: Res<ClientWorldItems>,
+    mut inventory: ResMut<ClientInventory>,
+) {
+    if chat.typing || !keyboard.just_pressed(settings.key) {
+        return;
+    }
+    let Some(position) = client.local_player_id.and_then(|id| client.player_states.get(&id)).map(|state| state.position) else {
+        return;
+    };
+    let Some(item) = world_items.nearest(position, settings.reach) else { return };
+    let action = match item.kind {
+        WorldItemKind::Item { .. } => PickupAction::Item { id: item.id },
+        WorldItemKind::Corpse { .. } => PickupAction::OpenCorpse { corpse: item.id },
+    };
+    inventory.request_pickup(&client, action);
+}
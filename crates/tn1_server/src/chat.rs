@@ -0,0 +1,368 @@
+//! Chat de texto: global, proximidad, equipo/clan y susurros.
+//!
+//! Todo mensaje pasa por los filtros del servidor (longitud, spam, palabras
+//! prohibidas, silenciados) antes de repartirse, y se registra tal como lo
+//! escribió el jugador en el log de moderación (`chat_messages` en Postgres).
+
+use bevy::prelude::*;
+use chrono::Utc;
+use std::collections::{HashMap, VecDeque};
+use tn1_shared::protocol::{ChatChannel, ServerMessage};
+use crate::config::env_or;
+use crate::database::{ChatLogEntry, PlayerStorage};
+use crate::networking::{ClientConnection, DatabaseChannel, DatabaseCommand, NetworkingSet, ServerState};
+
+/// Faltas de spam que cuentan para el silencio automático
+const STRIKE_WINDOW: f64 = 30.0;
+const MAX_TEAM_NAME: usize = 16;
+
+/// Palabras censuradas si no se define `TN1_CHAT_BANNED_WORDS`
+const DEFAULT_BANNED_WORDS: &[&str] = &[
+    "cabron", "cabrón", "gilipollas", "idiota", "imbecil", "imbécil", "mierda", "puta",
+    "bitch", "fuck", "shit",
+];
+
+/// Petición de chat recibida de un cliente; `NetworkingPlugin` la emite
+#[derive(Event, Debug, Clone)]
+pub enum ChatRequest {
+    Say { client_id: u32, channel: ChatChannel, text: String },
+    JoinTeam { client_id: u32, team: Option<String> },
+}
+
+/// Límites y filtros del chat (variables de entorno / .env)
+#[derive(Debug, Clone)]
+pub struct ChatSettings {
+    /// Caracteres por mensaje; lo que sobra se corta
+    pub max_length: usize,
+    /// Radio del chat de proximidad (metros)
+    pub proximity_radius: f32,
+    /// Mensajes permitidos por ventana de `rate_window` segundos
+    pub rate_limit: usize,
+    pub rate_window: f64,
+    /// Faltas de spam en `STRIKE_WINDOW` que provocan un silencio automático
+    pub mute_after_strikes: usize,
+    pub mute_duration: f64,
+    /// Palabras censuradas (comparación sin mayúsculas, palabra completa)
+    pub banned_words: Vec<String>,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            max_length: env_or("TN1_CHAT_MAX_LENGTH", 200),
+            proximity_radius: env_or("TN1_CHAT_PROXIMITY", 30.0),
+            rate_limit: env_or("TN1_CHAT_RATE_LIMIT", 5),
+            rate_window: env_or("TN1_CHAT_RATE_WINDOW", 5.0),
+            mute_after_strikes: 3,
+            mute_duration: env_or("TN1_CHAT_MUTE_SECS", 60.0),
+            banned_words: std::env::var("TN1_CHAT_BANNED_WORDS")
+                .map(|words| words.split(',').map(|word| word.trim().to_lowercase()).filter(|word| !word.is_empty()).collect())
+                .unwrap_or_else(|_| DEFAULT_BANNED_WORDS.iter().map(|word| word.to_string()).collect()),
+        }
+    }
+}
+
+/// Filtros, silenciados y antispam. Los tiempos son segundos de `Time` del servidor
+#[derive(Resource, Default)]
+pub struct ChatModeration {
+    pub settings: ChatSettings,
+    /// Nombre en minúsculas -> fin del silencio
+    muted: HashMap<String, f64>,
+    senders: HashMap<u32, SenderHistory>,
+}
+
+#[derive(Default)]
+struct SenderHistory {
+    recent: VecDeque<f64>,
+    strikes: VecDeque<f64>,
+    last_text: Option<(String, f64)>,
+}
+
+impl ChatModeration {
+    pub fn new(settings: ChatSettings) -> Self {
+        Self { settings, ..default() }
+    }
+
+    /// Silencia a un jugador por nombre durante `duration` segundos
+    pub fn mute(&mut self, username: &str, now: f64, duration: f64) {
+        self.muted.insert(username.to_lowercase(), now + duration);
+    }
+
+    pub fn unmute(&mut self, username: &str) {
+        self.muted.remove(&username.to_lowercase());
+    }
+
+    /// Segundos de silencio que le quedan al jugador
+    pub fn muted_for(&self, username: &str, now: f64) -> Option<f64> {
+        self.muted
+            .get(&username.to_lowercase())
+            .map(|until| until - now)
+            .filter(|remaining| *remaining > 0.0)
+    }
+
+    /// Aplica el antispam; `Err` con el motivo si el mensaje no debe salir
+    fn check_spam(&mut self, client_id: u32, text: &str, now: f64) -> Result<(), &'static str> {
+        let settings = &self.settings;
+        let history = self.senders.entry(client_id).or_default();
+        while history.recent.front().is_some_and(|sent| now - sent >= settings.rate_window) {
+            history.recent.pop_front();
+        }
+
+        let repeated = history.last_text.as_ref().is_some_and(|(last, sent)| {
+            now - sent < settings.rate_window && last.eq_ignore_ascii_case(text)
+        });
+        if history.recent.len() >= settings.rate_limit {
+            return Err("rate_limit");
+        }
+        if repeated {
+            return Err("repeated");
+        }
+
+        history.recent.push_back(now);
+        history.last_text = Some((text.to_string(), now));
+        Ok(())
+    }
+
+    /// Apunta una falta; `true` si el jugador acaba de quedar silenciado
+    fn strike(&mut self, client_id: u32, username: &str, now: f64) -> bool {
+        let history = self.senders.entry(client_id).or_default();
+        history.strikes.retain(|at| now - at < STRIKE_WINDOW);
+        history.strikes.push_back(now);
+        if history.strikes.len() < self.settings.mute_after_strikes {
+            return false;
+        }
+
+        history.strikes.clear();
+        self.mute(username, now, self.settings.mute_duration);
+        true
+    }
+}
+
+/// Filtros, silencios y reparto de mensajes de chat
+#[derive(Default)]
+pub struct ChatPlugin {
+    pub settings: ChatSettings,
+}
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChatModeration::new(self.settings.clone()))
+            .add_systems(Update, handle_chat_requests.after(NetworkingSet));
+    }
+}
+
+/// Censura las palabras prohibidas con asteriscos; `true` si cambió algo
+pub fn censor(text: &str, banned_words: &[String]) -> (String, bool) {
+    let mut result = String::with_capacity(text.len());
+    let mut word = String::new();
+    let mut filtered = false;
+
+    let mut flush = |word: &mut String, result: &mut String| {
+        if banned_words.iter().any(|banned| *banned == word.to_lowercase()) {
+            result.extend(std::iter::repeat_n('*', word.chars().count()));
+            filtered = true;
+        } else {
+            result.push_str(word);
+        }
+        word.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut result);
+            result.push(c);
+        }
+    }
+    flush(&mut word, &mut result);
+    (result, filtered)
+}
+
+fn handle_chat_requests(
+    mut requests: EventReader<ChatRequest>,
+    server_state: Res<ServerState>,
+    mut moderation: ResMut<ChatModeration>,
+    transforms: Query<&Transform>,
+    time: Res<Time>,
+    database: Option<Res<PlayerStorage>>,
+    database_channel: Res<DatabaseChannel>,
+) {
+    let now = time.elapsed_secs_f64();
+    let mut clients = server_state.clients.lock().unwrap();
+    // El historial antispam no sobrevive a la conexión (los silencios sí, por nombre)
+    moderation.senders.retain(|client_id, _| clients.contains_key(client_id));
+
+    for request in requests.read() {
+        match request.clone() {
+            ChatRequest::JoinTeam { client_id, team } => {
+                let Some(client) = clients.get_mut(&client_id).filter(|client| client.player_id.is_some()) else { continue };
+                join_team(client, team, &moderation.settings);
+            }
+            ChatRequest::Say { client_id, channel, text } => {
+                let Some(entry) = say(&mut clients, &mut moderation, &transforms, now, client_id, channel, text) else {
+                    continue;
+                };
+
+                // Solo hay worker de base de datos si hay persistencia
+                if database.is_some() {
+                    match database_channel.sender.send(DatabaseCommand::LogChatMessage(entry)) {
+                        Ok(()) => server_state.metrics.db_enqueued(),
+                        Err(e) => error!("❌ Error enviando log de chat: {}", e),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn join_team(client: &mut ClientConnection, team: Option<String>, settings: &ChatSettings) {
+    let notice = match team.map(|team| team.trim().to_string()) {
+        None => {
+            client.team = None;
+            "Has salido de tu equipo".to_string()
+        }
+        Some(team) if !valid_team_name(&team, settings) => {
+            format!("Nombre de equipo inválido (hasta {} letras, números, - o _)", MAX_TEAM_NAME)
+        }
+        Some(team) => {
+            let notice = format!("Te has unido al equipo {}", team);
+            client.team = Some(team);
+            notice
+        }
+    };
+    client.send(&ServerMessage::ChatNotice { text: notice });
+}
+
+fn valid_team_name(team: &str, settings: &ChatSettings) -> bool {
+    !team.is_empty()
+        && team.chars().count() <= MAX_TEAM_NAME
+        && team.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        && !censor(team, &settings.banned_words).1
+}
+
+/// Filtra y reparte un mensaje; devuelve la entrada del log de moderación
+fn say(
+    clients: &mut HashMap<u32, ClientConnection>,
+    moderation: &mut ChatModeration,
+    transforms: &Query<&Transform>,
+    now: f64,
+    client_id: u32,
+    channel: ChatChannel,
+    text: String,
+) -> Option<ChatLogEntry> {
+    let settings = moderation.settings.clone();
+    let sender = clients.get(&client_id)?;
+    let player_id = sender.player_id?;
+    let username = sender.player_name.clone();
+
+    // Sin caracteres de control y con longitud acotada
+    let text: String = text.trim().chars().filter(|c| !c.is_control()).take(settings.max_length).collect();
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut entry = ChatLogEntry {
+        player_id: player_id.0,
+        username: username.clone(),
+        channel: channel.kind().to_string(),
+        target: match &channel {
+            ChatChannel::Whisper { to } => Some(to.clone()),
+            ChatChannel::Team => sender.team.clone(),
+            _ => None,
+        },
+        message: text.clone(),
+        filtered: false,
+        blocked_reason: None,
+        sent_at: Utc::now(),
+    };
+
+    let notify = |clients: &mut HashMap<u32, ClientConnection>, text: String| {
+        if let Some(client) = clients.get_mut(&client_id) {
+            client.send(&ServerMessage::ChatNotice { text });
+        }
+    };
+
+    if let Some(remaining) = moderation.muted_for(&username, now) {
+        notify(clients, format!("Estás silenciado durante {:.0} s más", remaining.ceil()));
+        entry.blocked_reason = Some("muted".to_string());
+        return Some(entry);
+    }
+
+    if let Err(reason) = moderation.check_spam(client_id, &text, now) {
+        let notice = if moderation.strike(client_id, &username, now) {
+            warn!(client_id, username = %username, "🔇 Silenciado automáticamente por spam");
+            format!("Silenciado {:.0} s por spam", settings.mute_duration)
+        } else if reason == "repeated" {
+            "No repitas el mismo mensaje".to_string()
+        } else {
+            "Estás enviando mensajes demasiado rápido".to_string()
+        };
+        notify(clients, notice);
+        entry.blocked_reason = Some(reason.to_string());
+        return Some(entry);
+    }
+
+    let recipients: Vec<u32> = match &channel {
+        ChatChannel::Global => clients
+            .iter()
+            .filter(|(_, client)| client.player_id.is_some())
+            .map(|(id, _)| *id)
+            .collect(),
+        ChatChannel::Proximity => {
+            let position = |client: &ClientConnection| {
+                client.player_entity.and_then(|entity| transforms.get(entity).ok()).map(|transform| transform.translation)
+            };
+            let origin = position(sender)?;
+            clients
+                .iter()
+                .filter(|(_, client)| position(client).is_some_and(|at| at.distance(origin) <= settings.proximity_radius))
+                .map(|(id, _)| *id)
+                .collect()
+        }
+        ChatChannel::Team => {
+            let Some(team) = sender.team.clone() else {
+                notify(clients, "No estás en ningún equipo (/clan <nombre>)".to_string());
+                entry.blocked_reason = Some("no_team".to_string());
+                return Some(entry);
+            };
+            clients
+                .iter()
+                .filter(|(_, client)| client.player_id.is_some())
+                .filter(|(_, client)| client.team.as_ref().is_some_and(|other| other.eq_ignore_ascii_case(&team)))
+                .map(|(id, _)| *id)
+                .collect()
+        }
+        ChatChannel::Whisper { to } => {
+            let target = clients
+                .iter()
+                .find(|(_, client)| client.player_id.is_some() && client.player_name.eq_ignore_ascii_case(to))
+                .map(|(id, _)| *id);
+            let Some(target) = target else {
+                notify(clients, format!("{} no está conectado", to));
+                entry.blocked_reason = Some("unknown_target".to_string());
+                return Some(entry);
+            };
+            if target == client_id { vec![client_id] } else { vec![client_id, target] }
+        }
+    };
+
+    let (text, filtered) = censor(&text, &settings.banned_words);
+    entry.filtered = filtered;
+    // En susurros se usa el nombre real del destinatario, no como lo escribió el remitente
+    let channel = match channel {
+        ChatChannel::Whisper { .. } => ChatChannel::Whisper {
+            to: recipients.last().and_then(|id| clients.get(id)).map(|client| client.player_name.clone()).unwrap_or_default(),
+        },
+        channel => channel,
+    };
+
+    let message = ServerMessage::Chat { channel, sender: username, text };
+    for id in recipients {
+        if let Some(client) = clients.get_mut(&id) {
+            client.send(&message);
+        }
+    }
+    debug!(client_id, channel = entry.channel, "💬 Mensaje de chat repartido");
+    Some(entry)
+}
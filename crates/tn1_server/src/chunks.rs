@@ -0,0 +1,296 @@
+//! Chunks del mundo: carga alrededor de los jugadores y streaming bajo demanda.
+//!
+//! Cada tick se cargan (generan) los chunks dentro de `view_radius` de algún
+//! jugador y se descargan los que llevan `keep_loaded` segundos sin nadie cerca.
+//! Los clientes piden los chunks que les faltan con `RequestChunks` y reciben
+//! como mucho `send_budget` por tick para no saturar el enlace.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use tn1_shared::chunk::*;
+use tn1_shared::components::Player;
+use tn1_shared::protocol::{ServerMessage, WorldInfo};
+use tn1_shared::terrain::{Biome, TerrainGenerator};
+use crate::config::env_or;
+use crate::networking::{NetworkingSet, ServerState};
+use crate::world::WorldSettings;
+
+/// Alrededor del spawn no se genera nada para no aparecer dentro de un árbol
+const SPAWN_CLEARING: f32 = 10.0;
+
+/// Chunks pedidos por un cliente; `NetworkingPlugin` la emite
+#[derive(Event, Debug, Clone)]
+pub struct ChunkRequest {
+    pub client_id: u32,
+    pub coords: Vec<ChunkCoord>,
+}
+
+/// Radio de carga y ritmo de envío (variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct ChunkSettings {
+    /// Chunks cargados alrededor de cada jugador (1 = el suyo y los 8 vecinos)
+    pub view_radius: u32,
+    /// Segundos que un chunk sigue cargado cuando ya no hay nadie cerca
+    pub keep_loaded: f32,
+    /// Chunks enviados por tick y cliente
+    pub send_budget: usize,
+}
+
+impl Default for ChunkSettings {
+    fn default() -> Self {
+        Self {
+            view_radius: env_or("TN1_CHUNK_RADIUS", 2),
+            keep_loaded: env_or("TN1_CHUNK_KEEP_SECS", 30.0),
+            send_budget: env_or("TN1_CHUNK_SEND_BUDGET", 4),
+        }
+    }
+}
+
+/// Carga y streaming de chunks; necesita `WorldPlugin` para la rejilla
+#[derive(Default)]
+pub struct ChunkPlugin {
+    pub settings: ChunkSettings,
+}
+
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .init_resource::<LoadedChunks>()
+            .init_resource::<ChunkStreams>()
+            .add_systems(Update, (
+                load_chunks_around_players,
+                queue_chunk_requests,
+                send_chunks,
+            ).chain().after(NetworkingSet).run_if(resource_exists::<ChunkGrid>));
+    }
+}
+
+struct LoadedChunk {
+    data: ChunkData,
+    /// Última vez que un jugador estuvo dentro del radio
+    last_needed: f64,
+}
+
+/// Chunks generados y en memoria
+#[derive(Resource, Default)]
+pub struct LoadedChunks {
+    chunks: HashMap<ChunkCoord, LoadedChunk>,
+}
+
+impl LoadedChunks {
+    pub fn get(&self, coord: ChunkCoord) -> Option<&ChunkData> {
+        self.chunks.get(&coord).map(|chunk| &chunk.data)
+    }
+
+    pub fn is_loaded(&self, coord: ChunkCoord) -> bool {
+        self.chunks.contains_key(&coord)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn coords(&self) -> impl Iterator<Item = ChunkCoord> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Devuelve el chunk, generándolo si no estaba cargado
+    fn load(&mut self, grid: &ChunkGrid, seed: u64, coord: ChunkCoord, now: f64) -> &ChunkData {
+        let chunk = self.chunks.entry(coord).or_insert_with(|| {
+            debug!(%coord, "🧱 Chunk cargado");
+            LoadedChunk { data: generate_chunk(grid, seed, coord), last_needed: now }
+        });
+        chunk.last_needed = chunk.last_needed.max(now);
+        &chunk.data
+    }
+}
+
+/// Estado del streaming de cada cliente
+#[derive(Resource, Default)]
+struct ChunkStreams {
+    clients: HashMap<u32, ClientStream>,
+}
+
+#[derive(Default)]
+struct ClientStream {
+    /// Ya recibió `WorldInfo`
+    informed: bool,
+    /// Chunks pedidos pendientes de enviar, en orden de petición
+    pending: VecDeque<ChunkCoord>,
+}
+
+fn load_chunks_around_players(
+    grid: Res<ChunkGrid>,
+    world_settings: Res<WorldSettings>,
+    settings: Res<ChunkSettings>,
+    mut loaded: ResMut<LoadedChunks>,
+    players: Query<&Transform, With<Player>>,
+    server_state: Res<ServerState>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs_f64();
+    for transform in players.iter() {
+        for coord in grid.chunks_around(transform.translation, settings.view_radius) {
+            loaded.load(&grid, world_settings.seed, coord, now);
+        }
+    }
+
+    let keep_loaded = settings.keep_loaded as f64;
+    loaded.chunks.retain(|coord, chunk| {
+        let keep = now - chunk.last_needed <= keep_loaded;
+        if !keep {
+            debug!(%coord, "🧱 Chunk descargado");
+        }
+        keep
+    });
+    server_state.metrics.set_chunks_loaded(loaded.len());
+}
+
+fn queue_chunk_requests(
+    mut requests: EventReader<ChunkRequest>,
+    grid: Res<ChunkGrid>,
+    settings: Res<ChunkSettings>,
+    mut streams: ResMut<ChunkStreams>,
+    server_state: Res<ServerState>,
+    transforms: Query<&Transform>,
+) {
+    let clients = server_state.clients.lock().unwrap();
+    // Un margen de un anillo para que el cliente pueda adelantarse al moverse
+    let max_distance = settings.view_radius + 1;
+    let max_coords = (2 * max_distance as usize + 1).pow(2);
+
+    for request in requests.read() {
+        let Some(position) = clients
+            .get(&request.client_id)
+            .and_then(|client| client.player_entity)
+            .and_then(|entity| transforms.get(entity).ok())
+            .map(|transform| transform.translation)
+        else {
+            continue;
+        };
+        if request.coords.len() > max_coords {
+            warn!(client_id = request.client_id, coords = request.coords.len(), "⚠️ Petición de chunks demasiado grande");
+        }
+
+        let center = grid.coord_at(position);
+        let stream = streams.clients.entry(request.client_id).or_default();
+        for coord in request.coords.iter().take(max_coords) {
+            // Nada fuera del mundo ni lejos del jugador: no se puede explorar el mapa a distancia
+            if !grid.contains(*coord) || coord.distance(center) > max_distance {
+                debug!(client_id = request.client_id, %coord, "🧱 Chunk pedido fuera de alcance");
+                continue;
+            }
+            if !stream.pending.contains(coord) {
+                stream.pending.push_back(*coord);
+            }
+        }
+    }
+}
+
+fn send_chunks(
+    grid: Res<ChunkGrid>,
+    world_settings: Res<WorldSettings>,
+    settings: Res<ChunkSettings>,
+    mut loaded: ResMut<LoadedChunks>,
+    mut streams: ResMut<ChunkStreams>,
+    server_state: Res<ServerState>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs_f64();
+    let mut clients = server_state.clients.lock().unwrap();
+    streams.clients.retain(|client_id, _| clients.contains_key(client_id));
+
+    for (client_id, client) in clients.iter_mut() {
+        if client.player_id.is_none() {
+            continue;
+        }
+        let stream = streams.clients.entry(*client_id).or_default();
+        if !stream.informed {
+            client.send(&ServerMessage::WorldInfo(WorldInfo {
+                world_size: grid.world_size,
+                chunk_size: grid.chunk_size,
+                view_radius: settings.view_radius,
+                seed: world_settings.seed,
+            }));
+            stream.informed = true;
+        }
+
+        for _ in 0..settings.send_budget {
+            let Some(coord) = stream.pending.pop_front() else { break };
+            // Los del anillo de margen pueden no estar cargados todavía
+            let data = loaded.load(&grid, world_settings.seed, coord, now);
+            client.send(&ServerMessage::Chunk(data.clone()));
+        }
+    }
+}
+
+/// Contenido de un chunk; solo depende de la semilla y la coordenada.
+/// Todo se apoya sobre el terreno y su densidad depende del bioma
+pub fn generate_chunk(grid: &ChunkGrid, seed: u64, coord: ChunkCoord) -> ChunkData {
+    let terrain = TerrainGenerator::new(seed);
+    let mut rng = WorldRng::for_chunk(seed, coord);
+    let origin = grid.origin(coord);
+    // Densidades pensadas para chunks de 100 m; con otros tamaños se escalan por área
+    let area = (grid.chunk_size / 100.0).powi(2);
+    let random_point = |rng: &mut WorldRng| {
+        let x = origin.x + rng.range(0.0..grid.chunk_size);
+        let z = origin.z + rng.range(0.0..grid.chunk_size);
+        let sample = terrain.sample(x, z);
+        (Vec3::new(x, sample.height, z), sample.biome)
+    };
+    let away_from_spawn = |position: &Vec3| position.xz().length() > SPAWN_CLEARING;
+
+    let mut props = Vec::new();
+    for (kind, per_chunk) in [(PropKind::Tree, 30.0), (PropKind::Rock, 10.0), (PropKind::Bush, 20.0)] {
+        let count = (per_chunk * area * rng.range(0.5..1.5)).round() as usize;
+        for _ in 0..count {
+            let (position, biome) = random_point(&mut rng);
+            let rotation = rng.range(0.0..std::f32::consts::TAU);
+            let scale = rng.range(0.7..1.3);
+            let keep = rng.next_f32() < prop_density(kind, biome);
+            if keep && away_from_spawn(&position) {
+                props.push(StaticProp { kind, position, rotation, scale });
+            }
+        }
+    }
+
+    let mut containers = Vec::new();
+    let count = (2.0 * area * rng.next_f32()).round() as u16;
+    for index in 0..count {
+        let (position, biome) = random_point(&mut rng);
+        let rotation = rng.range(0.0..std::f32::consts::TAU);
+        let kind = match rng.next_u64() % 3 {
+            0 => ContainerKind::Crate,
+            1 => ContainerKind::Toolbox,
+            _ => ContainerKind::MedicalBox,
+        };
+        // Nada de loot en el fondo de un río
+        if biome != Biome::River && away_from_spawn(&position) {
+            containers.push(LootContainer { id: LootContainer::id_for(coord, index), kind, position, rotation });
+        }
+    }
+
+    ChunkData { coord, revision: 0, props, containers }
+}
+
+/// Probabilidad de conservar un objeto decorativo según el bioma donde cae
+fn prop_density(kind: PropKind, biome: Biome) -> f32 {
+    match (kind, biome) {
+        (_, Biome::River) => 0.0,
+        (PropKind::Tree, Biome::Forest) => 1.0,
+        (PropKind::Tree, Biome::Plains | Biome::Swamp) => 0.25,
+        (PropKind::Tree, Biome::Mountains) => 0.15,
+        (PropKind::Tree, Biome::Desert | Biome::Snow) => 0.0,
+        (PropKind::Rock, Biome::Mountains | Biome::Desert) => 1.0,
+        (PropKind::Rock, Biome::Snow) => 0.6,
+        (PropKind::Rock, _) => 0.2,
+        (PropKind::Bush, Biome::Swamp | Biome::Forest) => 1.0,
+        (PropKind::Bush, Biome::Plains) => 0.6,
+        (PropKind::Bush, Biome::Desert) => 0.1,
+        (PropKind::Bush, Biome::Mountains | Biome::Snow) => 0.05,
+    }
+}
@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use std::net::SocketAddr;
+use tn1_shared::protocol::DEFAULT_PORT;
+
+/// Configuración pública del servidor (leída de variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct ServerConfig {
+    pub bind_address: SocketAddr,
+    pub server_name: String,
+    pub map_name: String,
+    pub max_players: u32,
+    /// Jugadores que pueden esperar en cola cuando el servidor está lleno
+    pub max_queue: u32,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: std::env::var("TN1_BIND_ADDR")
+                .ok()
+                .and_then(|addr| addr.parse().ok())
+                .unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT))),
+            server_name: std::env::var("TN1_SERVER_NAME")
+                .unwrap_or_else(|_| "Trust-No-1 Dev".to_string()),
+            map_name: std::env::var("TN1_MAP_NAME")
+                .unwrap_or_else(|_| "dev_plane".to_string()),
+            max_players: env_or("TN1_MAX_PLAYERS", 64),
+            max_queue: env_or("TN1_MAX_QUEUE", 32),
+        }
+    }
+}
+
+/// Variable de entorno parseada, o `default` si falta o no se entiende
+pub(crate) fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
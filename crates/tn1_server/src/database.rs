@@ -7,6 +7,7 @@ use bevy::prelude::*;
 use chrono::{DateTime, Utc};
 use redis::aio::ConnectionManager;
 use sqlx::{postgres::PgPoolOptions, PgPool, Row};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
@@ -72,6 +73,116 @@ pub struct PlayerState {
     pub last_updated: DateTime<Utc>,
 }
 
+/// Mensaje de chat tal como lo escribió el jugador, para moderación
+#[derive(Debug, Clone)]
+pub struct ChatLogEntry {
+    pub player_id: Uuid,
+    pub username: String,
+    /// `global`, `proximity`, `team` o `whisper`
+    pub channel: String,
+    /// Destinatario del susurro o nombre del equipo
+    pub target: Option<String>,
+    pub message: String,
+    /// El filtro de palabras censuró parte del texto
+    pub filtered: bool,
+    /// Motivo por el que no se entregó (`muted`, `rate_limit`, `repeated`...)
+    pub blocked_reason: Option<String>,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Fila de `item_instances`: un item guardado y dónde está
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemRecord {
+    /// El `uuid` del `ItemStack`, clave primaria de la tabla
+    pub id: Uuid,
+    pub item: String,
+    pub quantity: u32,
+    pub durability: Option<f32>,
+    pub ammo: Option<u32>,
+    pub placement: ItemPlacement,
+    pub x: u8,
+    pub y: u8,
+    pub rotated: bool,
+}
+
+/// Dónde está un item guardado; en la tabla es exactamente una de las
+/// columnas `pocket`, `parent_id`, `attached_to` o `slot`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemPlacement {
+    Pocket(u8),
+    /// Dentro de la rejilla de otro item
+    Container(Uuid),
+    /// Montado como accesorio de otro item
+    Attachment(Uuid),
+    /// Ranura de equipo (`head`, `back`...)
+    Equipment(String),
+}
+
+/// Contenedor de loot guardado: fila de `loot_containers` con sus `loot_items`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LootContainerRecord {
+    /// `LootContainer::id`, el mismo en cada arranque con la misma semilla
+    pub id: u64,
+    pub table: String,
+    /// Cuándo se rellena; `None` si no se ha tocado desde el último relleno
+    pub restock_at: Option<DateTime<Utc>>,
+    pub items: Vec<LootItemRecord>,
+}
+
+/// Item suelto dentro de un contenedor de loot
+#[derive(Debug, Clone, PartialEq)]
+pub struct LootItemRecord {
+    pub id: Uuid,
+    pub item: String,
+    pub quantity: u32,
+    pub durability: Option<f32>,
+    pub ammo: Option<u32>,
+}
+
+/// Comercio hecho, para `trade_log`: los inventarios de los dos tal como
+/// quedan (se guardan en la misma transacción) y lo que cambió de manos
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeRecord {
+    pub players: [Uuid; 2],
+    pub usernames: [String; 2],
+    /// Inventario completo de cada uno; `None` si no salió de la base de datos
+    /// y no se debe guardar
+    pub inventories: [Option<Vec<ItemRecord>>; 2],
+    pub items: Vec<TradeItemRecord>,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Un item que cambió de manos, también los que iban dentro de otro
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeItemRecord {
+    pub id: Uuid,
+    pub item: String,
+    pub quantity: u32,
+    pub from: Uuid,
+    pub to: Uuid,
+}
+
+impl ItemPlacement {
+    fn columns(&self) -> (Option<i16>, Option<Uuid>, Option<Uuid>, Option<&str>) {
+        match self {
+            ItemPlacement::Pocket(index) => (Some(*index as i16), None, None, None),
+            ItemPlacement::Container(parent) => (None, Some(*parent), None, None),
+            ItemPlacement::Attachment(parent) => (None, None, Some(*parent), None),
+            ItemPlacement::Equipment(slot) => (None, None, None, Some(slot)),
+        }
+    }
+
+    fn from_columns(pocket: Option<i16>, parent: Option<Uuid>, attached_to: Option<Uuid>, slot: Option<String>) -> Option<Self> {
+        match (pocket, parent, attached_to, slot) {
+            (Some(index), None, None, None) => u8::try_from(index).ok().map(ItemPlacement::Pocket),
+            (None, Some(parent), None, None) => Some(ItemPlacement::Container(parent)),
+            (None, None, Some(parent), None) => Some(ItemPlacement::Attachment(parent)),
+            (None, None, None, Some(slot)) => Some(ItemPlacement::Equipment(slot)),
+            _ => None,
+        }
+    }
+}
+
 impl Database {
     pub async fn new(config: DatabaseConfig) -> Result<Self> {
         // Conectar a PostgreSQL
@@ -93,22 +204,28 @@ impl Database {
     }
 
     // Autenticación
-    pub async fn create_player(&self, username: &str, password: &str, email: Option<&str>) -> Result<Uuid> {
+    /// Crea la cuenta con su estado y su perfil iniciales; `None` si el nombre ya existe
+    pub async fn create_player(&self, username: &str, password: &str, email: Option<&str>) -> Result<Option<Uuid>> {
         let password_hash = hash_password(password)?;
-        
+        let mut tx = self.pg_pool.begin().await?;
+
         let row = sqlx::query(
             r#"
             INSERT INTO players (username, password_hash, email)
             VALUES ($1, $2, $3)
+            ON CONFLICT (username) DO NOTHING
             RETURNING id
             "#,
         )
         .bind(username)
         .bind(password_hash)
         .bind(email)
-        .fetch_one(&self.pg_pool)
+        .fetch_optional(&mut *tx)
         .await?;
-        
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
         let player_id: Uuid = row.get("id");
 
         // Crear estado inicial del jugador
@@ -119,7 +236,7 @@ impl Database {
             "#,
         )
         .bind(player_id)
-        .execute(&self.pg_pool)
+        .execute(&mut *tx)
         .await?;
 
         // Crear perfil inicial
@@ -131,10 +248,11 @@ impl Database {
         )
         .bind(player_id)
         .bind(username)
-        .execute(&self.pg_pool)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(player_id)
+        tx.commit().await?;
+        Ok(Some(player_id))
     }
 
     pub async fn authenticate_player(&self, username: &str, password: &str) -> Result<Option<Player>> {
@@ -182,7 +300,14 @@ impl Database {
     }
 
     // Gestión de estado del jugador
-    pub async fn save_player_state(&self, player_id: Uuid, position: Vec3, rotation: Quat) -> Result<()> {
+
+    /// Guarda posición e inventario en una sola transacción. Con `items` a
+    /// `None` el inventario guardado no se toca. Un item que aparezca en otro
+    /// jugador pasa a ser de este: el `id` es único, así que nunca hay dos
+    /// filas del mismo item.
+    pub async fn save_player_state(&self, player_id: Uuid, position: Vec3, rotation: Quat, items: Option<&[ItemRecord]>) -> Result<()> {
+        let mut tx = self.pg_pool.begin().await?;
+
         sqlx::query(
             r#"
             UPDATE player_states
@@ -200,12 +325,248 @@ impl Database {
         .bind(rotation.y)
         .bind(rotation.z)
         .bind(rotation.w)
-        .execute(&self.pg_pool)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(items) = items {
+            Self::save_items(&mut tx, player_id, items).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Deja las filas de `item_instances` del jugador como `items`
+    async fn save_items(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, player_id: Uuid, items: &[ItemRecord]) -> Result<()> {
+        // Lo que ya no lleva (tirado, gastado, entregado...)
+        let kept: Vec<Uuid> = items.iter().map(|record| record.id).collect();
+        sqlx::query(
+            r#"
+            DELETE FROM item_instances
+            WHERE owner_id = $1 AND NOT (id = ANY($2))
+            "#,
+        )
+        .bind(player_id)
+        .bind(&kept)
+        .execute(&mut **tx)
+        .await?;
+
+        for record in items {
+            let (pocket, parent_id, attached_to, slot) = record.placement.columns();
+            sqlx::query(
+                r#"
+                INSERT INTO item_instances
+                    (id, owner_id, item_key, quantity, durability, ammo,
+                     pocket, parent_id, attached_to, slot, grid_x, grid_y, rotated)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                ON CONFLICT (id) DO UPDATE
+                SET owner_id = EXCLUDED.owner_id, item_key = EXCLUDED.item_key,
+                    quantity = EXCLUDED.quantity, durability = EXCLUDED.durability, ammo = EXCLUDED.ammo,
+                    pocket = EXCLUDED.pocket, parent_id = EXCLUDED.parent_id,
+                    attached_to = EXCLUDED.attached_to, slot = EXCLUDED.slot,
+                    grid_x = EXCLUDED.grid_x, grid_y = EXCLUDED.grid_y, rotated = EXCLUDED.rotated
+                "#,
+            )
+            .bind(record.id)
+            .bind(player_id)
+            .bind(&record.item)
+            .bind(record.quantity as i32)
+            .bind(record.durability)
+            .bind(record.ammo.map(|ammo| ammo as i32))
+            .bind(pocket)
+            .bind(parent_id)
+            .bind(attached_to)
+            .bind(slot)
+            .bind(record.x as i16)
+            .bind(record.y as i16)
+            .bind(record.rotated)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Guarda un comercio hecho: los dos inventarios y su entrada en
+    /// `trade_log`, todo en una transacción
+    pub async fn save_trade(&self, trade: &TradeRecord) -> Result<()> {
+        let mut tx = self.pg_pool.begin().await?;
+
+        for (player_id, items) in trade.players.iter().zip(&trade.inventories) {
+            if let Some(items) = items {
+                Self::save_items(&mut tx, *player_id, items).await?;
+            }
+        }
+
+        let trade_id: i64 = sqlx::query(
+            r#"
+            INSERT INTO trade_log (player_a, player_b, username_a, username_b, completed_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+        )
+        .bind(trade.players[0])
+        .bind(trade.players[1])
+        .bind(&trade.usernames[0])
+        .bind(&trade.usernames[1])
+        .bind(trade.completed_at)
+        .fetch_one(&mut *tx)
+        .await?
+        .get("id");
+
+        for record in &trade.items {
+            sqlx::query(
+                r#"
+                INSERT INTO trade_log_items (trade_id, item_id, item_key, quantity, from_player, to_player)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(trade_id)
+            .bind(record.id)
+            .bind(&record.item)
+            .bind(record.quantity as i32)
+            .bind(record.from)
+            .bind(record.to)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn load_inventory(&self, player_id: Uuid) -> Result<Vec<ItemRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM item_instances
+            WHERE owner_id = $1
+            "#,
+        )
+        .bind(player_id)
+        .fetch_all(&self.pg_pool)
         .await?;
 
+        let mut records = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: Uuid = row.get("id");
+            let Some(placement) = ItemPlacement::from_columns(
+                row.get("pocket"),
+                row.get("parent_id"),
+                row.get("attached_to"),
+                row.get("slot"),
+            ) else {
+                warn!("⚠️ Item {} sin ubicación válida, se ignora", id);
+                continue;
+            };
+            records.push(ItemRecord {
+                id,
+                item: row.get("item_key"),
+                quantity: row.get::<i32, _>("quantity").max(0) as u32,
+                durability: row.get("durability"),
+                ammo: row.get::<Option<i32>, _>("ammo").map(|ammo| ammo.max(0) as u32),
+                placement,
+                x: row.get::<i16, _>("grid_x").clamp(0, u8::MAX as i16) as u8,
+                y: row.get::<i16, _>("grid_y").clamp(0, u8::MAX as i16) as u8,
+                rotated: row.get("rotated"),
+            });
+        }
+        Ok(records)
+    }
+
+    /// Guarda el estado de los contenedores dados en una transacción; los items
+    /// que ya no están en un contenedor se borran
+    pub async fn save_loot_containers(&self, containers: &[LootContainerRecord]) -> Result<()> {
+        let mut tx = self.pg_pool.begin().await?;
+
+        for container in containers {
+            sqlx::query(
+                r#"
+                INSERT INTO loot_containers (id, table_key, restock_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (id) DO UPDATE
+                SET table_key = EXCLUDED.table_key, restock_at = EXCLUDED.restock_at
+                "#,
+            )
+            .bind(container.id as i64)
+            .bind(&container.table)
+            .bind(container.restock_at)
+            .execute(&mut *tx)
+            .await?;
+
+            let kept: Vec<Uuid> = container.items.iter().map(|record| record.id).collect();
+            sqlx::query(
+                r#"
+                DELETE FROM loot_items
+                WHERE container_id = $1 AND NOT (id = ANY($2))
+                "#,
+            )
+            .bind(container.id as i64)
+            .bind(&kept)
+            .execute(&mut *tx)
+            .await?;
+
+            for record in &container.items {
+                sqlx::query(
+                    r#"
+                    INSERT INTO loot_items (id, container_id, item_key, quantity, durability, ammo)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    ON CONFLICT (id) DO UPDATE
+                    SET container_id = EXCLUDED.container_id, item_key = EXCLUDED.item_key,
+                        quantity = EXCLUDED.quantity, durability = EXCLUDED.durability, ammo = EXCLUDED.ammo
+                    "#,
+                )
+                .bind(record.id)
+                .bind(container.id as i64)
+                .bind(&record.item)
+                .bind(record.quantity as i32)
+                .bind(record.durability)
+                .bind(record.ammo.map(|ammo| ammo as i32))
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 
+    pub async fn load_loot_containers(&self) -> Result<Vec<LootContainerRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM loot_containers
+            "#,
+        )
+        .fetch_all(&self.pg_pool)
+        .await?;
+
+        let mut containers: HashMap<u64, LootContainerRecord> = rows
+            .into_iter()
+            .map(|row| {
+                let id = row.get::<i64, _>("id") as u64;
+                (id, LootContainerRecord { id, table: row.get("table_key"), restock_at: row.get("restock_at"), items: Vec::new() })
+            })
+            .collect();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM loot_items
+            "#,
+        )
+        .fetch_all(&self.pg_pool)
+        .await?;
+
+        for row in rows {
+            let Some(container) = containers.get_mut(&(row.get::<i64, _>("container_id") as u64)) else { continue };
+            container.items.push(LootItemRecord {
+                id: row.get("id"),
+                item: row.get("item_key"),
+                quantity: row.get::<i32, _>("quantity").max(0) as u32,
+                durability: row.get("durability"),
+                ammo: row.get::<Option<i32>, _>("ammo").map(|ammo| ammo.max(0) as u32),
+            });
+        }
+        Ok(containers.into_values().collect())
+    }
+
     pub async fn load_player_state(&self, player_id: Uuid) -> Result<Option<PlayerState>> {
         let row = sqlx::query(
             r#"
@@ -332,6 +693,272 @@ impl Database {
 
         Ok(())
     }
+
+    // Chat
+    pub async fn log_chat_message(&self, entry: &ChatLogEntry) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO chat_messages (player_id, username, channel, target, message, filtered, blocked_reason, sent_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(entry.player_id)
+        .bind(&entry.username)
+        .bind(&entry.channel)
+        .bind(&entry.target)
+        .bind(&entry.message)
+        .bind(entry.filtered)
+        .bind(&entry.blocked_reason)
+        .bind(entry.sent_at)
+        .execute(&self.pg_pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Persistencia que usa el loop del servidor. Las llamadas son bloqueantes:
+/// se hacen desde el worker de base de datos, nunca desde un sistema de Bevy.
+pub trait PlayerStore: Send + Sync + 'static {
+    fn authenticate_player(&self, username: &str, password: &str) -> Result<Option<Player>>;
+    /// Crea la cuenta; `None` si el nombre ya está cogido
+    fn create_player(&self, username: &str, password: &str) -> Result<Option<Uuid>>;
+    /// Posición e inventario juntos: o se guarda todo o nada
+    fn save_player_state(&self, player_id: Uuid, position: Vec3, rotation: Quat, items: Option<&[ItemRecord]>) -> Result<()>;
+    fn load_player_state(&self, player_id: Uuid) -> Result<Option<PlayerState>>;
+    fn load_inventory(&self, player_id: Uuid) -> Result<Vec<ItemRecord>>;
+    fn save_loot_containers(&self, containers: &[LootContainerRecord]) -> Result<()>;
+    fn load_loot_containers(&self) -> Result<Vec<LootContainerRecord>>;
+    fn log_chat_message(&self, entry: &ChatLogEntry) -> Result<()>;
+    /// Los dos inventarios y la entrada del registro juntos: o todo o nada
+    fn save_trade(&self, trade: &TradeRecord) -> Result<()>;
+}
+
+/// Backend de persistencia activo (Postgres en producción, en memoria en los tests)
+#[derive(Resource, Clone)]
+pub struct PlayerStorage(pub Arc<dyn PlayerStore>);
+
+/// Postgres + Redis ejecutados sobre el runtime de Tokio del plugin
+pub struct PostgresStore {
+    pub database: Database,
+    pub runtime: tokio::runtime::Handle,
+}
+
+impl PlayerStore for PostgresStore {
+    fn authenticate_player(&self, username: &str, password: &str) -> Result<Option<Player>> {
+        self.runtime.block_on(self.database.authenticate_player(username, password))
+    }
+
+    fn create_player(&self, username: &str, password: &str) -> Result<Option<Uuid>> {
+        self.runtime.block_on(self.database.create_player(username, password, None))
+    }
+
+    fn save_player_state(&self, player_id: Uuid, position: Vec3, rotation: Quat, items: Option<&[ItemRecord]>) -> Result<()> {
+        self.runtime.block_on(self.database.save_player_state(player_id, position, rotation, items))
+    }
+
+    fn load_player_state(&self, player_id: Uuid) -> Result<Option<PlayerState>> {
+        self.runtime.block_on(self.database.load_player_state(player_id))
+    }
+
+    fn load_inventory(&self, player_id: Uuid) -> Result<Vec<ItemRecord>> {
+        self.runtime.block_on(self.database.load_inventory(player_id))
+    }
+
+    fn save_loot_containers(&self, containers: &[LootContainerRecord]) -> Result<()> {
+        self.runtime.block_on(self.database.save_loot_containers(containers))
+    }
+
+    fn load_loot_containers(&self) -> Result<Vec<LootContainerRecord>> {
+        self.runtime.block_on(self.database.load_loot_containers())
+    }
+
+    fn log_chat_message(&self, entry: &ChatLogEntry) -> Result<()> {
+        self.runtime.block_on(self.database.log_chat_message(entry))
+    }
+
+    fn save_trade(&self, trade: &TradeRecord) -> Result<()> {
+        self.runtime.block_on(self.database.save_trade(trade))
+    }
+}
+
+/// Backend en memoria para tests y servidores locales sin Postgres
+#[derive(Default)]
+pub struct InMemoryStore {
+    accounts: std::sync::Mutex<HashMap<String, Player>>,
+    states: std::sync::Mutex<HashMap<Uuid, PlayerState>>,
+    /// Items por `id`, con su dueño (como la clave primaria de `item_instances`)
+    items: std::sync::Mutex<HashMap<Uuid, (Uuid, ItemRecord)>>,
+    loot: std::sync::Mutex<HashMap<u64, LootContainerRecord>>,
+    chat: std::sync::Mutex<Vec<ChatLogEntry>>,
+    trades: std::sync::Mutex<Vec<TradeRecord>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Jugadores con estado guardado
+    pub fn saved_players(&self) -> Vec<Uuid> {
+        self.states.lock().unwrap().keys().copied().collect()
+    }
+
+    /// Contenedores de loot guardados
+    pub fn loot_containers(&self) -> Vec<LootContainerRecord> {
+        self.loot.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Mensajes de chat registrados, en orden
+    pub fn chat_log(&self) -> Vec<ChatLogEntry> {
+        self.chat.lock().unwrap().clone()
+    }
+
+    /// Comercios registrados, en orden
+    pub fn trade_log(&self) -> Vec<TradeRecord> {
+        self.trades.lock().unwrap().clone()
+    }
+}
+
+/// Como `Database::save_items`, sobre los items en memoria
+fn save_items(saved_items: &mut HashMap<Uuid, (Uuid, ItemRecord)>, player_id: Uuid, items: &[ItemRecord]) {
+    saved_items.retain(|id, (owner, _)| *owner != player_id || items.iter().any(|record| record.id == *id));
+    for record in items {
+        saved_items.insert(record.id, (player_id, record.clone()));
+    }
+}
+
+/// `UNIQUE (owner_id, slot)` de `item_instances`, comprobado como en el commit
+fn check_slots(saved_items: &HashMap<Uuid, (Uuid, ItemRecord)>) -> Result<()> {
+    let mut taken = HashSet::new();
+    for (owner, record) in saved_items.values() {
+        if let ItemPlacement::Equipment(slot) = &record.placement {
+            if !taken.insert((*owner, slot.as_str())) {
+                anyhow::bail!("Dos items en el slot {} de {}", slot, owner);
+            }
+        }
+    }
+    Ok(())
+}
+
+impl PlayerStore for InMemoryStore {
+    fn authenticate_player(&self, username: &str, password: &str) -> Result<Option<Player>> {
+        let mut accounts = self.accounts.lock().unwrap();
+        let Some(player) = accounts.get_mut(username).filter(|player| !player.is_banned) else {
+            return Ok(None);
+        };
+
+        if !verify_password(password, &player.password_hash)? {
+            return Ok(None);
+        }
+        player.last_login = Some(Utc::now());
+        Ok(Some(player.clone()))
+    }
+
+    /// Con los mismos valores por defecto que `schema.sql`
+    fn create_player(&self, username: &str, password: &str) -> Result<Option<Uuid>> {
+        let mut accounts = self.accounts.lock().unwrap();
+        if accounts.contains_key(username) {
+            return Ok(None);
+        }
+        let player = Player {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            password_hash: hash_password(password)?,
+            email: None,
+            created_at: Utc::now(),
+            last_login: None,
+            is_banned: false,
+            ban_reason: None,
+            ban_until: None,
+        };
+        let player_id = player.id;
+        accounts.insert(username.to_string(), player);
+        Ok(Some(player_id))
+    }
+
+    fn save_player_state(&self, player_id: Uuid, position: Vec3, rotation: Quat, items: Option<&[ItemRecord]>) -> Result<()> {
+        // Con los dos locks a la vez, como la transacción de Postgres
+        let mut states = self.states.lock().unwrap();
+        let mut saved_items = self.items.lock().unwrap();
+        if let Some(items) = items {
+            // Sobre una copia: si falla la restricción no se guarda nada
+            let mut updated = saved_items.clone();
+            save_items(&mut updated, player_id, items);
+            check_slots(&updated)?;
+            *saved_items = updated;
+        }
+
+        // A diferencia del UPDATE de Postgres, crea la fila si no existe
+        let state = states.entry(player_id).or_insert_with(|| PlayerState {
+            player_id,
+            position_x: 0.0,
+            position_y: 10.0,
+            position_z: 0.0,
+            rotation_x: 0.0,
+            rotation_y: 0.0,
+            rotation_z: 0.0,
+            rotation_w: 1.0,
+            health: 100.0,
+            hunger: 100.0,
+            thirst: 100.0,
+            stamina: 100.0,
+            is_alive: true,
+            is_online: false,
+            last_updated: Utc::now(),
+        });
+
+        state.position_x = position.x;
+        state.position_y = position.y;
+        state.position_z = position.z;
+        state.rotation_x = rotation.x;
+        state.rotation_y = rotation.y;
+        state.rotation_z = rotation.z;
+        state.rotation_w = rotation.w;
+        state.last_updated = Utc::now();
+        Ok(())
+    }
+
+    fn load_player_state(&self, player_id: Uuid) -> Result<Option<PlayerState>> {
+        Ok(self.states.lock().unwrap().get(&player_id).cloned())
+    }
+
+    fn load_inventory(&self, player_id: Uuid) -> Result<Vec<ItemRecord>> {
+        let items = self.items.lock().unwrap();
+        Ok(items.values().filter(|(owner, _)| *owner == player_id).map(|(_, record)| record.clone()).collect())
+    }
+
+    fn save_loot_containers(&self, containers: &[LootContainerRecord]) -> Result<()> {
+        let mut loot = self.loot.lock().unwrap();
+        for container in containers {
+            loot.insert(container.id, container.clone());
+        }
+        Ok(())
+    }
+
+    fn load_loot_containers(&self) -> Result<Vec<LootContainerRecord>> {
+        Ok(self.loot.lock().unwrap().values().cloned().collect())
+    }
+
+    fn log_chat_message(&self, entry: &ChatLogEntry) -> Result<()> {
+        self.chat.lock().unwrap().push(entry.clone());
+        Ok(())
+    }
+
+    fn save_trade(&self, trade: &TradeRecord) -> Result<()> {
+        let mut saved_items = self.items.lock().unwrap();
+        let mut trades = self.trades.lock().unwrap();
+        let mut updated = saved_items.clone();
+        for (player_id, items) in trade.players.iter().zip(&trade.inventories) {
+            if let Some(items) = items {
+                save_items(&mut updated, *player_id, items);
+            }
+        }
+        check_slots(&updated)?;
+        *saved_items = updated;
+        trades.push(trade.clone());
+        Ok(())
+    }
 }
 
 // Funciones auxiliares
@@ -390,6 +1017,10 @@ impl Plugin for DatabasePlugin {
         
         // Si la DB se inicializó correctamente, la añadimos como recurso
         if let Some(db) = database {
+            app.insert_resource(PlayerStorage(Arc::new(PostgresStore {
+                database: db.clone(),
+                runtime: runtime.handle().clone(),
+            })));
             app.insert_resource(db);
             app.insert_resource(TokioRuntime(runtime));
         }
@@ -0,0 +1,640 @@
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tn1_shared::{chunk::ChunkGrid, codec::WireCodec, components::*, events::*, protocol::*};
+use crate::config::ServerConfig;
+use crate::lag_compensation::LagCompensationPlugin;
+use crate::networking::{ClientConnection, DatabaseChannel, NetworkingPlugin, ResolvedAccount, ServerState};
+use crate::physics::ServerPhysicsPlugin;
+use crate::systems::SystemsPlugin;
+use crate::tls::ServerTls;
+use crate::world::{WorldPlugin, WorldSettings, WorldTerrain};
+
+const DEMO_MAGIC: &[u8; 8] = b"TN1DEMO\0";
+pub const DEMO_FORMAT_VERSION: u32 = 6;
+pub const DEMO_EXTENSION: &str = "tn1demo";
+
+/// Tolerancia al comparar keyframes: la re-simulación debería ser bit a bit idéntica
+const KEYFRAME_TOLERANCE: f32 = 1e-4;
+
+/// Cabecera de la demo: lo necesario para re-simular con las mismas reglas
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DemoHeader {
+    pub format_version: u32,
+    pub protocol_version: u32,
+    pub tick_rate: u32,
+    pub server_name: String,
+    pub map_name: String,
+    pub max_players: u32,
+    pub max_queue: u32,
+    pub keyframe_interval: u32,
+    pub recorded_at: String,
+    /// Rejilla del mundo al grabar (sus límites frenan a los jugadores); `None` sin `WorldPlugin`
+    pub world: Option<ChunkGrid>,
+    /// Semilla del terreno (sus alturas deciden dónde pisan los jugadores)
+    pub world_seed: u64,
+}
+
+/// Un tick del servidor: su delta, los mensajes procesados y, cada tanto, un keyframe
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DemoFrame {
+    pub delta: Duration,
+    pub messages: Vec<(u32, ClientMessage)>,
+    /// Logins resueltos en el tick; sin base de datos al re-simular, entran cuando entraron
+    pub accounts: Vec<ResolvedAccount>,
+    /// Jugadores que el equipo o las heridas frenan al acabar el tick; el resto corre normal
+    pub movement: Vec<MovementLimits>,
+    pub keyframe: Option<Vec<KeyframePlayer>>,
+}
+
+/// Lo que deja correr el controlador a un cliente. Lo calculan el inventario y el
+/// daño por zonas, que no se re-simulan (dependen de la base de datos y de eventos
+/// que no llegan por red), así que se graba tal cual
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MovementLimits {
+    pub client_id: u32,
+    pub speed_multiplier: f32,
+    pub can_sprint: bool,
+}
+
+/// Estado de un jugador en un keyframe, identificado por cliente (los `PlayerId` cambian al re-simular)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KeyframePlayer {
+    pub client_id: u32,
+    pub state: PlayerState,
+}
+
+// ---------------------------------------------------------------------------
+// Grabación
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct DemoRecordSettings {
+    /// Directorio (se crea un archivo por sesión) o archivo `.tn1demo`; `None` desactiva la grabación
+    pub path: Option<PathBuf>,
+    /// Ticks entre keyframes
+    pub keyframe_interval: u32,
+}
+
+impl Default for DemoRecordSettings {
+    fn default() -> Self {
+        Self {
+            path: std::env::var("TN1_DEMO_RECORD").ok().filter(|path| !path.is_empty()).map(PathBuf::from),
+            keyframe_interval: std::env::var("TN1_DEMO_KEYFRAME_TICKS")
+                .ok()
+                .and_then(|ticks| ticks.parse().ok())
+                .unwrap_or(60),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DemoRecordPlugin {
+    pub settings: DemoRecordSettings,
+}
+
+impl Plugin for DemoRecordPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(path) = &self.settings.path else { return };
+
+        let path = if path.extension().is_some_and(|ext| ext == DEMO_EXTENSION) {
+            path.clone()
+        } else {
+            path.join(format!("{}.{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"), DEMO_EXTENSION))
+        };
+
+        app.insert_resource(PendingDemoRecording {
+            path,
+            keyframe_interval: self.settings.keyframe_interval.max(1),
+        })
+        .add_systems(Startup, start_demo_recording)
+        .add_systems(Last, write_demo_frame);
+    }
+}
+
+#[derive(Resource)]
+struct PendingDemoRecording {
+    path: PathBuf,
+    keyframe_interval: u32,
+}
+
+/// Graba los mensajes que procesa el servidor tick a tick
+#[derive(Resource)]
+pub struct DemoRecorder {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    keyframe_interval: u32,
+    frame: u32,
+    pending: Vec<(u32, ClientMessage)>,
+    pending_accounts: Vec<ResolvedAccount>,
+}
+
+impl DemoRecorder {
+    pub fn create(path: &Path, header: &DemoHeader) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let file = File::create(path).with_context(|| format!("creando {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(DEMO_MAGIC)?;
+        write_record(&mut writer, header)?;
+
+        Ok(Self {
+            writer,
+            path: path.to_path_buf(),
+            keyframe_interval: header.keyframe_interval.max(1),
+            frame: 0,
+            pending: Vec::new(),
+            pending_accounts: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Ticks grabados hasta ahora
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Mensajes que el servidor acaba de sacar de la cola de entrada
+    pub fn record_messages(&mut self, messages: &[(u32, ClientMessage)]) {
+        // La voz no afecta a la simulación y ocuparía casi todo el archivo
+        self.pending.extend(messages.iter().filter(|(_, message)| !matches!(message, ClientMessage::Voice { .. })).cloned());
+    }
+
+    /// Logins que el servidor acaba de resolver
+    pub fn record_accounts(&mut self, accounts: &[ResolvedAccount]) {
+        self.pending_accounts.extend(accounts.iter().cloned());
+    }
+
+    fn wants_keyframe(&self) -> bool {
+        self.frame.is_multiple_of(self.keyframe_interval)
+    }
+
+    fn finish_frame(&mut self, delta: Duration, movement: Vec<MovementLimits>, keyframe: Option<Vec<KeyframePlayer>>) -> Result<()> {
+        let is_keyframe = keyframe.is_some();
+        let frame = DemoFrame {
+            delta,
+            messages: std::mem::take(&mut self.pending),
+            accounts: std::mem::take(&mut self.pending_accounts),
+            movement,
+            keyframe,
+        };
+        write_record(&mut self.writer, &frame)?;
+        self.frame += 1;
+
+        // Si el servidor muere se pierde como mucho lo grabado desde el último keyframe
+        if is_keyframe {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn start_demo_recording(
+    mut commands: Commands,
+    pending: Res<PendingDemoRecording>,
+    config: Res<ServerConfig>,
+    grid: Option<Res<ChunkGrid>>,
+    terrain: Option<Res<WorldTerrain>>,
+) {
+    let header = DemoHeader {
+        format_version: DEMO_FORMAT_VERSION,
+        protocol_version: PROTOCOL_VERSION,
+        tick_rate: TICK_RATE,
+        server_name: config.server_name.clone(),
+        map_name: config.map_name.clone(),
+        max_players: config.max_players,
+        max_queue: config.max_queue,
+        keyframe_interval: pending.keyframe_interval,
+        recorded_at: chrono::Utc::now().to_rfc3339(),
+        world: grid.map(|grid| *grid),
+        world_seed: terrain.map_or(0, |terrain| terrain.generator.seed),
+    };
+
+    match DemoRecorder::create(&pending.path, &header) {
+        Ok(recorder) => {
+            info!("🎥 Grabando demo en {}", pending.path.display());
+            commands.insert_resource(recorder);
+        }
+        Err(e) => error!("❌ No se pudo iniciar la grabación de demo: {}", e),
+    }
+    commands.remove_resource::<PendingDemoRecording>();
+}
+
+fn write_demo_frame(
+    mut commands: Commands,
+    recorder: Option<ResMut<DemoRecorder>>,
+    server_state: Res<ServerState>,
+    player_query: Query<(&Transform, &PlayerController, &PlayerId, &Health), With<Player>>,
+    time: Res<Time>,
+) {
+    let Some(mut recorder) = recorder else { return };
+
+    let movement = movement_limits(&server_state, &player_query);
+    let keyframe = recorder
+        .wants_keyframe()
+        .then(|| snapshot_players(&server_state, &player_query));
+
+    if let Err(e) = recorder.finish_frame(time.delta(), movement, keyframe) {
+        error!("❌ Error escribiendo la demo, grabación detenida: {}", e);
+        commands.remove_resource::<DemoRecorder>();
+    }
+}
+
+/// Estado de los jugadores autenticados ordenado por cliente
+fn snapshot_players(
+    server_state: &ServerState,
+    player_query: &Query<(&Transform, &PlayerController, &PlayerId, &Health), With<Player>>,
+) -> Vec<KeyframePlayer> {
+    let clients = server_state.clients.lock().unwrap();
+    let mut players: Vec<KeyframePlayer> = clients
+        .iter()
+        .filter_map(|(&client_id, client)| {
+            let (transform, controller, player_id, health) = player_query.get(client.player_entity?).ok()?;
+            Some(KeyframePlayer {
+                client_id,
+                state: PlayerState {
+                    player_id: *player_id,
+                    position: transform.translation,
+                    velocity: controller.velocity,
+                    rotation: transform.rotation,
+                    health: health.current,
+                    is_grounded: controller.is_grounded,
+                    last_input_sequence: 0,
+                    flashlight: controller.flashlight,
+                },
+            })
+        })
+        .collect();
+    players.sort_by_key(|player| player.client_id);
+    players
+}
+
+/// Límites de movimiento de quien no corre a velocidad normal, ordenados por cliente
+fn movement_limits(
+    server_state: &ServerState,
+    player_query: &Query<(&Transform, &PlayerController, &PlayerId, &Health), With<Player>>,
+) -> Vec<MovementLimits> {
+    let clients = server_state.clients.lock().unwrap();
+    let mut limits: Vec<MovementLimits> = clients
+        .iter()
+        .filter_map(|(&client_id, client)| {
+            let (_, controller, _, _) = player_query.get(client.player_entity?).ok()?;
+            let limited = controller.speed_multiplier != 1.0 || !controller.can_sprint;
+            limited.then_some(MovementLimits {
+                client_id,
+                speed_multiplier: controller.speed_multiplier,
+                can_sprint: controller.can_sprint,
+            })
+        })
+        .collect();
+    limits.sort_by_key(|limits| limits.client_id);
+    limits
+}
+
+// ---------------------------------------------------------------------------
+// Archivo
+// ---------------------------------------------------------------------------
+
+fn write_record<T: Serialize>(writer: &mut impl Write, value: &T) -> Result<()> {
+    let payload = bincode::serialize(value)?;
+    writer.write_all(&(payload.len() as u32).to_be_bytes())?;
+    writer.write_all(&payload)?;
+    Ok(())
+}
+
+/// `Ok(None)` al final del archivo; un registro cortado (servidor caído) también se trata como final
+fn read_record<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    let mut payload = vec![0u8; u32::from_be_bytes(len) as usize];
+    match reader.read_exact(&mut payload) {
+        Ok(()) => Ok(Some(bincode::deserialize(&payload)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            warn!("⚠️ Demo truncada: se ignora el último tick incompleto");
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Lee una demo completa
+pub fn read_demo(path: &Path) -> Result<(DemoHeader, Vec<DemoFrame>)> {
+    let file = File::open(path).with_context(|| format!("abriendo {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).context("archivo demasiado corto")?;
+    if &magic != DEMO_MAGIC {
+        bail!("{} no es una demo de Trust-No-1", path.display());
+    }
+
+    let header: DemoHeader = read_record(&mut reader)?.context("la demo no tiene cabecera")?;
+    if header.format_version != DEMO_FORMAT_VERSION {
+        bail!(
+            "formato de demo {} no soportado (se esperaba {})",
+            header.format_version,
+            DEMO_FORMAT_VERSION
+        );
+    }
+
+    let mut frames = Vec::new();
+    while let Some(frame) = read_record::<DemoFrame>(&mut reader)? {
+        frames.push(frame);
+    }
+    Ok((header, frames))
+}
+
+/// Escribe una demo completa (recortes, o demos editadas para aislar un bug)
+pub fn write_demo(path: &Path, header: &DemoHeader, frames: &[DemoFrame]) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path).with_context(|| format!("creando {}", path.display()))?);
+    writer.write_all(DEMO_MAGIC)?;
+    write_record(&mut writer, header)?;
+    for frame in frames {
+        write_record(&mut writer, frame)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Reproducción
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct DemoPlaybackSettings {
+    pub path: PathBuf,
+    /// Detenerse tras este tick e imprimir el estado de todos los jugadores
+    pub stop_at: Option<u32>,
+}
+
+/// Jugador cuyo estado re-simulado no coincide con el keyframe grabado
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub frame: u32,
+    pub client_id: u32,
+    pub expected: PlayerState,
+    pub actual: Option<PlayerState>,
+}
+
+/// Demo cargada y progreso de la re-simulación
+#[derive(Resource)]
+pub struct DemoPlayback {
+    pub header: DemoHeader,
+    frames: Vec<DemoFrame>,
+    pub frame: usize,
+    pub stop_at: Option<u32>,
+    pub keyframes_checked: u32,
+    pub divergences: Vec<Divergence>,
+    pub finished: bool,
+    started: Instant,
+}
+
+impl DemoPlayback {
+    pub fn new(header: DemoHeader, frames: Vec<DemoFrame>, stop_at: Option<u32>) -> Self {
+        Self {
+            header,
+            frames,
+            frame: 0,
+            stop_at,
+            keyframes_checked: 0,
+            divergences: Vec::new(),
+            finished: false,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+pub struct DemoPlaybackPlugin {
+    pub settings: DemoPlaybackSettings,
+}
+
+impl Plugin for DemoPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        let (header, frames) = read_demo(&self.settings.path)
+            .expect("No se pudo leer la demo");
+
+        if header.protocol_version != PROTOCOL_VERSION {
+            warn!(
+                "⚠️ Demo grabada con protocolo {} (actual {}): puede no reproducirse igual",
+                header.protocol_version, PROTOCOL_VERSION
+            );
+        }
+        info!(
+            "🎬 Demo {}: {} ticks, grabada {} en {} ({})",
+            self.settings.path.display(),
+            frames.len(),
+            header.recorded_at,
+            header.server_name,
+            header.map_name
+        );
+
+        let first_delta = frames.first().map_or(Duration::ZERO, |frame| frame.delta);
+        // Mismos límites y terreno que al grabar (`WorldPlugin` no pisa estos ajustes)
+        if let Some(grid) = header.world {
+            app.insert_resource(WorldSettings {
+                world_size: grid.world_size,
+                chunk_size: grid.chunk_size,
+                seed: header.world_seed,
+                ..default()
+            });
+        }
+        app.insert_resource(ServerConfig {
+            bind_address: std::net::SocketAddr::from(([127, 0, 0, 1], 0)),
+            server_name: header.server_name.clone(),
+            map_name: header.map_name.clone(),
+            max_players: header.max_players,
+            max_queue: header.max_queue,
+        })
+        .insert_resource(ServerTls(None))
+        // Cada tick re-simulado avanza exactamente lo mismo que al grabar
+        .insert_resource(TimeUpdateStrategy::ManualDuration(first_delta))
+        .insert_resource(DemoPlayback::new(header, frames, self.settings.stop_at))
+        .add_systems(PreUpdate, (inject_demo_messages, apply_demo_movement).chain())
+        .add_systems(Last, advance_demo_playback);
+    }
+}
+
+/// Servidor sin red que re-simula una demo tan rápido como puede
+pub fn build_replay_app(settings: DemoPlaybackSettings) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_event::<PlayerInputEvent>()
+        .add_event::<PlayerPositionEvent>()
+        .add_event::<PlayerSpawnEvent>()
+        .add_event::<PlayerDespawnEvent>()
+        .add_plugins((
+            DemoPlaybackPlugin { settings },
+            ServerPhysicsPlugin,
+            NetworkingPlugin,
+            LagCompensationPlugin,
+            SystemsPlugin,
+        ));
+    // Sin mundo si se grabó sin él: sus límites cambian la simulación
+    if app.world().resource::<DemoPlayback>().header.world.is_some() {
+        app.add_plugins(WorldPlugin);
+    }
+    app
+}
+
+/// Mete en la cola de entrada los mensajes y los logins grabados para este tick
+fn inject_demo_messages(playback: Res<DemoPlayback>, server_state: Res<ServerState>, database_channel: Res<DatabaseChannel>) {
+    if playback.finished {
+        return;
+    }
+    let Some(frame) = playback.frames.get(playback.frame) else { return };
+
+    {
+        // El handshake no se graba: los clientes aparecen con su primer mensaje
+        let mut clients = server_state.clients.lock().unwrap();
+        for (client_id, message) in &frame.messages {
+            if matches!(message, ClientMessage::Disconnect) {
+                continue;
+            }
+            clients.entry(*client_id).or_insert_with(|| ClientConnection {
+                stream: None,
+                codec: WireCodec::default(),
+                player_entity: None,
+                player_id: None,
+                player_name: format!("demo_{}", client_id),
+                authenticating: false,
+                team: None,
+                radio: None,
+                last_ping: Instant::now(),
+                rtt: 0.0,
+                metrics: server_state.metrics.clone(),
+                link: None,
+            });
+        }
+    }
+
+    server_state.incoming_messages.lock().unwrap().extend(frame.messages.iter().cloned());
+    database_channel.accounts.lock().unwrap().extend(frame.accounts.iter().cloned());
+}
+
+/// Devuelve a cada jugador lo que le dejaba correr el equipo al acabar el tick anterior
+fn apply_demo_movement(
+    playback: Res<DemoPlayback>,
+    server_state: Res<ServerState>,
+    mut controllers: Query<&mut PlayerController, With<Player>>,
+) {
+    if playback.finished {
+        return;
+    }
+    let Some(previous) = playback.frame.checked_sub(1).and_then(|index| playback.frames.get(index)) else { return };
+
+    let clients = server_state.clients.lock().unwrap();
+    for (client_id, client) in clients.iter() {
+        let Some(mut controller) = client.player_entity.and_then(|entity| controllers.get_mut(entity).ok()) else { continue };
+        let limits = previous.movement.iter().find(|limits| limits.client_id == *client_id);
+        controller.speed_multiplier = limits.map_or(1.0, |limits| limits.speed_multiplier);
+        controller.can_sprint = limits.is_none_or(|limits| limits.can_sprint);
+    }
+}
+
+fn advance_demo_playback(
+    mut playback: ResMut<DemoPlayback>,
+    server_state: Res<ServerState>,
+    player_query: Query<(&Transform, &PlayerController, &PlayerId, &Health), With<Player>>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if playback.finished {
+        return;
+    }
+
+    let index = playback.frame;
+    if index >= playback.frames.len() {
+        finish_playback(&mut playback, &mut exit);
+        return;
+    }
+    let frame_number = index as u32;
+    let actual = snapshot_players(&server_state, &player_query);
+
+    if let Some(expected) = playback.frames[index].keyframe.clone() {
+        playback.keyframes_checked += 1;
+        for expected in expected {
+            let found = actual.iter().find(|player| player.client_id == expected.client_id);
+            if !found.is_some_and(|player| states_match(&player.state, &expected.state)) {
+                if playback.divergences.is_empty() {
+                    warn!(
+                        "⚠️ Primera divergencia en tick {} (cliente {}): grabado {:?}, re-simulado {:?}",
+                        frame_number,
+                        expected.client_id,
+                        expected.state.position,
+                        found.map(|player| player.state.position)
+                    );
+                }
+                playback.divergences.push(Divergence {
+                    frame: frame_number,
+                    client_id: expected.client_id,
+                    expected: expected.state,
+                    actual: found.map(|player| player.state.clone()),
+                });
+            }
+        }
+    }
+
+    if playback.stop_at == Some(frame_number) {
+        println!("⏸️ Tick {}:", frame_number);
+        for player in &actual {
+            println!(
+                "   cliente {} - pos {:?} vel {:?} suelo {} vida {}",
+                player.client_id, player.state.position, player.state.velocity,
+                player.state.is_grounded, player.state.health
+            );
+        }
+        finish_playback(&mut playback, &mut exit);
+        return;
+    }
+
+    playback.frame += 1;
+    match playback.frames.get(playback.frame) {
+        Some(next) => *time_strategy = TimeUpdateStrategy::ManualDuration(next.delta),
+        None => finish_playback(&mut playback, &mut exit),
+    }
+}
+
+fn states_match(actual: &PlayerState, expected: &PlayerState) -> bool {
+    actual.position.distance(expected.position) <= KEYFRAME_TOLERANCE
+        && actual.velocity.distance(expected.velocity) <= KEYFRAME_TOLERANCE
+        && actual.is_grounded == expected.is_grounded
+        && actual.flashlight == expected.flashlight
+        && (actual.health - expected.health).abs() <= KEYFRAME_TOLERANCE
+}
+
+fn finish_playback(playback: &mut DemoPlayback, exit: &mut EventWriter<AppExit>) {
+    playback.finished = true;
+    println!(
+        "🏁 Demo reproducida: {}/{} ticks en {:.2}s, {} keyframes verificados, {} divergencias",
+        (playback.frame + 1).min(playback.frames.len()),
+        playback.frames.len(),
+        playback.started.elapsed().as_secs_f32(),
+        playback.keyframes_checked,
+        playback.divergences.len()
+    );
+
+    exit.send(if playback.divergences.is_empty() {
+        AppExit::Success
+    } else {
+        AppExit::from_code(1)
+    });
+}
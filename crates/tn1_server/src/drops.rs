@@ -0,0 +1,483 @@
+//! Items sueltos en el mundo y cadáveres.
+//!
+//! Lo que se tira (`ItemDropped`) sale lanzado a un lado, cae con
+//! gravedad y rebota hasta quedarse quieto en el suelo. Al morir un jugador
+//! (`Health` a 0) todo lo que llevaba pasa a un cadáver en el sitio, que se
+//! registra como un contenedor. Ambos desaparecen al acabar su tiempo de vida
+//! y, si hay demasiados items sueltos, se van primero los más viejos.
+//!
+//! Cada cliente recibe (`WorldItems`) solo lo que hay a `view_distance` de él.
+//! Para recoger algo (`ClientMessage::Pickup`) hay que estar a `reach` metros y
+//! verlo desde los ojos: ni el terreno ni árboles o rocas pueden taparlo. Los
+//! items del suelo y los cadáveres no se guardan en la base de datos.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use tn1_shared::chunk::{ChunkCoord, ChunkGrid, StaticProp};
+use tn1_shared::components::{Health, Player, PlayerId};
+use tn1_shared::drops::*;
+use tn1_shared::inventory::{Inventory, InventoryDiff, ItemStack};
+use tn1_shared::items::ItemDefs;
+use tn1_shared::protocol::ServerMessage;
+use crate::chunks::LoadedChunks;
+use crate::config::env_or;
+use crate::inventory::ItemDropped;
+use crate::networking::{NetworkingSet, ServerState};
+use crate::physics::{ground_height, world_half_size};
+use crate::world::WorldTerrain;
+
+/// Altura desde la que cae lo que se tira (las manos)
+const DROP_HEIGHT: f32 = 1.0;
+
+/// Velocidad (m/s) con la que sale lanzado lo que se tira
+const TOSS_SPEED: f32 = 1.5;
+
+/// Parte de la velocidad vertical que conserva al rebotar
+const BOUNCE: f32 = 0.3;
+
+/// Parte de la velocidad horizontal que conserva al tocar el suelo
+const GROUND_FRICTION: f32 = 0.5;
+
+/// Por debajo de esta velocidad al tocar el suelo se queda quieto
+const REST_SPEED: f32 = 0.5;
+
+/// Acción sobre algo del suelo recibida de un cliente; `NetworkingPlugin` la emite
+#[derive(Event, Debug, Clone)]
+pub struct PickupRequest {
+    pub client_id: u32,
+    pub sequence: u32,
+    pub action: PickupAction,
+}
+
+/// Un jugador ha muerto: lo que lleva pasa a un cadáver
+#[derive(Event, Debug, Clone)]
+pub struct PlayerDied {
+    pub player_id: PlayerId,
+}
+
+/// Ya tiene cadáver; se quita si vuelve a tener vida
+#[derive(Component, Debug, Default)]
+pub struct Dead;
+
+/// Alcance, radio de replicación y limpieza (variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct DropSettings {
+    /// Distancia máxima (metros) desde los ojos a lo que se recoge
+    pub reach: f32,
+    /// Radio (metros) en el que un cliente recibe los items del suelo
+    pub view_distance: f32,
+    /// Segundos que dura un item suelto
+    pub item_lifetime: f32,
+    /// Segundos que dura un cadáver
+    pub corpse_lifetime: f32,
+    /// Items sueltos a la vez en todo el mundo
+    pub max_items: usize,
+}
+
+impl Default for DropSettings {
+    fn default() -> Self {
+        Self {
+            reach: env_or("TN1_PICKUP_REACH", 2.5),
+            view_distance: env_or("TN1_DROP_VIEW_DISTANCE", 60.0),
+            item_lifetime: env_or("TN1_DROP_LIFETIME_SECS", 600.0),
+            corpse_lifetime: env_or("TN1_CORPSE_LIFETIME_SECS", 1800.0),
+            max_items: env_or("TN1_DROP_MAX", 500),
+        }
+    }
+}
+
+/// Algo en el suelo; la posición es la de su `Transform`
+#[derive(Component, Debug, Clone)]
+pub struct WorldItem {
+    pub id: u64,
+    pub contents: WorldItemContents,
+    pub velocity: Vec3,
+    pub resting: bool,
+    /// Segundo del servidor en que desaparece
+    pub expires_at: f64,
+    /// Sube cuando cambia lo que ven los clientes
+    revision: u32,
+}
+
+#[derive(Debug, Clone)]
+pub enum WorldItemContents {
+    Stack(ItemStack),
+    Corpse { name: String, items: Vec<ItemStack> },
+}
+
+impl WorldItem {
+    pub fn is_corpse(&self) -> bool {
+        matches!(self.contents, WorldItemContents::Corpse { .. })
+    }
+
+    /// Lo que ve un cliente
+    pub fn state(&self, position: Vec3) -> WorldItemState {
+        let kind = match &self.contents {
+            WorldItemContents::Stack(stack) => WorldItemKind::Item { item: stack.item.clone(), quantity: stack.quantity },
+            WorldItemContents::Corpse { name, .. } => WorldItemKind::Corpse { name: name.clone() },
+        };
+        WorldItemState { id: self.id, position, resting: self.resting, kind }
+    }
+}
+
+/// Próximo id de `WorldItem`
+#[derive(Resource, Debug)]
+struct WorldItemIds {
+    next: u64,
+}
+
+impl Default for WorldItemIds {
+    fn default() -> Self {
+        Self { next: 1 }
+    }
+}
+
+impl WorldItemIds {
+    fn allocate(&mut self) -> u64 {
+        let id = self.next;
+        self.next += 1;
+        id
+    }
+}
+
+/// Lo que ya sabe cada cliente: id y revisión de cada item que tiene cerca
+#[derive(Resource, Default)]
+struct WorldItemViews {
+    clients: HashMap<u32, HashMap<u64, u32>>,
+}
+
+/// Items del suelo y cadáveres; necesita `InventoryPlugin`. Con `WorldPlugin`
+/// y `ChunkPlugin` caen sobre el terreno y la vista la tapan sus props
+#[derive(Default)]
+pub struct DropsPlugin {
+    pub settings: DropSettings,
+}
+
+impl Plugin for DropsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .init_resource::<WorldItemIds>()
+            .init_resource::<WorldItemViews>()
+            .add_event::<PlayerDied>()
+            .add_systems(Update, (
+                detect_deaths,
+                spawn_dropped_items,
+                spawn_corpses,
+                settle_world_items,
+                expire_world_items,
+                handle_pickup_requests,
+                replicate_world_items,
+            ).chain().after(NetworkingSet));
+    }
+}
+
+/// Jugadores que se han quedado sin vida y aún no tienen cadáver
+fn detect_deaths(
+    mut commands: Commands,
+    players: Query<(Entity, &PlayerId, &Health, Has<Dead>), With<Player>>,
+    mut deaths: EventWriter<PlayerDied>,
+) {
+    for (entity, player_id, health, dead) in players.iter() {
+        match (health.current <= 0.0, dead) {
+            (true, false) => {
+                commands.entity(entity).insert(Dead);
+                deaths.send(PlayerDied { player_id: *player_id });
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<Dead>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn spawn_dropped_items(
+    mut commands: Commands,
+    mut dropped: EventReader<ItemDropped>,
+    defs: Res<ItemDefs>,
+    settings: Res<DropSettings>,
+    mut ids: ResMut<WorldItemIds>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs_f64();
+    for drop in dropped.read() {
+        // Lo que ya no está en el catálogo no se podría recoger
+        if defs.get(&drop.stack.item).is_none() {
+            warn!(item = %drop.stack.item, "📦 Item de tipo desconocido tirado; se pierde");
+            continue;
+        }
+        let angle = rand::random::<f32>() * std::f32::consts::TAU;
+        let velocity = Vec3::new(angle.cos() * TOSS_SPEED, TOSS_SPEED, angle.sin() * TOSS_SPEED);
+        let item = WorldItem {
+            id: ids.allocate(),
+            contents: WorldItemContents::Stack(drop.stack.clone()),
+            velocity,
+            resting: false,
+            expires_at: now + settings.item_lifetime as f64,
+            revision: 0,
+        };
+        debug!(id = item.id, item = %drop.stack.item, player_id = %drop.player_id.0, "📦 Item tirado");
+        commands.spawn((item, Transform::from_translation(drop.position + Vec3::Y * DROP_HEIGHT)));
+    }
+}
+
+/// Vacía el inventario del muerto en un cadáver donde cayó
+fn spawn_corpses(
+    mut commands: Commands,
+    mut deaths: EventReader<PlayerDied>,
+    server_state: Res<ServerState>,
+    settings: Res<DropSettings>,
+    mut ids: ResMut<WorldItemIds>,
+    mut players: Query<(Entity, &PlayerId, &Transform, Option<&mut Inventory>), With<Player>>,
+    time: Res<Time>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    for death in deaths.read() {
+        let Some((entity, _, transform, inventory)) = players.iter_mut().find(|(_, id, ..)| **id == death.player_id) else {
+            continue;
+        };
+        let client = clients.values_mut().find(|client| client.player_entity == Some(entity));
+        let name = client.as_ref().map_or_else(|| death.player_id.0.to_string(), |client| client.player_name.clone());
+        let items = match inventory {
+            Some(mut inventory) => {
+                let items = corpse_items(&inventory);
+                *inventory = Inventory::new();
+                if let Some(client) = client {
+                    client.send(&ServerMessage::Inventory(inventory.clone()));
+                }
+                items
+            }
+            None => Vec::new(),
+        };
+
+        let corpse = WorldItem {
+            id: ids.allocate(),
+            contents: WorldItemContents::Corpse { name: name.clone(), items },
+            velocity: Vec3::ZERO,
+            resting: false,
+            expires_at: time.elapsed_secs_f64() + settings.corpse_lifetime as f64,
+            revision: 0,
+        };
+        info!(player = %name, corpse = corpse.id, "💀 Jugador muerto; su inventario queda en el cadáver");
+        commands.spawn((corpse, Transform::from_translation(transform.translation)));
+    }
+}
+
+/// Gravedad, rebotes y rozamiento hasta que se paran en el suelo
+fn settle_world_items(
+    mut items: Query<(&mut WorldItem, &mut Transform)>,
+    time: Res<Time>,
+    grid: Option<Res<ChunkGrid>>,
+    mut terrain: Option<ResMut<WorldTerrain>>,
+) {
+    let dt = time.delta().as_secs_f32();
+    let half_size = world_half_size(grid.as_deref());
+    for (mut item, mut transform) in items.iter_mut().filter(|(item, _)| !item.resting) {
+        item.velocity.y -= 9.81 * dt;
+        transform.translation += item.velocity * dt;
+        transform.translation.x = transform.translation.x.clamp(-half_size, half_size);
+        transform.translation.z = transform.translation.z.clamp(-half_size, half_size);
+
+        let ground = ground_height(terrain.as_deref_mut(), transform.translation);
+        if transform.translation.y > ground {
+            continue;
+        }
+        transform.translation.y = ground;
+        item.velocity.y = -item.velocity.y * BOUNCE;
+        item.velocity.x *= GROUND_FRICTION;
+        item.velocity.z *= GROUND_FRICTION;
+        if item.velocity.length() < REST_SPEED {
+            item.velocity = Vec3::ZERO;
+            item.resting = true;
+            item.revision += 1;
+        }
+    }
+}
+
+/// Quita lo que ha caducado y, si sobran items sueltos, los más viejos
+fn expire_world_items(
+    mut commands: Commands,
+    items: Query<(Entity, &WorldItem)>,
+    settings: Res<DropSettings>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs_f64();
+    let mut loose = Vec::new();
+    for (entity, item) in items.iter() {
+        if item.expires_at <= now {
+            debug!(id = item.id, corpse = item.is_corpse(), "📦 Item del suelo caducado");
+            commands.entity(entity).despawn();
+        } else if !item.is_corpse() {
+            loose.push((item.expires_at, entity));
+        }
+    }
+
+    if loose.len() > settings.max_items {
+        loose.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let excess = loose.len() - settings.max_items;
+        debug!(excess, "📦 Demasiados items en el suelo; se quitan los más viejos");
+        for (_, entity) in loose.into_iter().take(excess) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Comprueba distancia y línea de visión con el terreno y los props cargados
+#[derive(SystemParam)]
+struct PickupReach<'w> {
+    settings: Res<'w, DropSettings>,
+    terrain: Option<ResMut<'w, WorldTerrain>>,
+    chunks: Option<Res<'w, LoadedChunks>>,
+}
+
+impl PickupReach<'_> {
+    fn check(&mut self, eye: Vec3, target: Vec3) -> Result<(), PickupError> {
+        let target = target + Vec3::Y * ITEM_CLEARANCE;
+        if eye.distance(target) > self.settings.reach {
+            return Err(PickupError::OutOfReach);
+        }
+        let Some(terrain) = self.terrain.as_deref_mut() else {
+            return Ok(());
+        };
+        // Chunks que toca la línea: con el alcance de un jugador, como mucho cuatro
+        let grid = terrain.grid;
+        let props = self.chunks.iter().flat_map(|chunks| props_along(grid, chunks, eye, target));
+        let visible = line_of_sight(eye, target, |x, z| terrain.ground_height(Vec3::new(x, 0.0, z)), props);
+        if !visible {
+            return Err(PickupError::NotVisible);
+        }
+        Ok(())
+    }
+}
+
+/// Props de los chunks cargados en el rectángulo que cubre la línea (también
+/// los usan las balas)
+pub(crate) fn props_along(grid: ChunkGrid, chunks: &LoadedChunks, from: Vec3, to: Vec3) -> impl Iterator<Item = &StaticProp> {
+    let (a, b) = (grid.coord_at(from), grid.coord_at(to));
+    (a.x.min(b.x)..=a.x.max(b.x))
+        .flat_map(move |x| (a.z.min(b.z)..=a.z.max(b.z)).map(move |z| ChunkCoord::new(x, z)))
+        .filter_map(|coord| chunks.get(coord))
+        .flat_map(|chunk| &chunk.props)
+}
+
+/// Lo que deja una acción aceptada
+struct PickupOutcome {
+    diffs: Vec<InventoryDiff>,
+    /// Lo que le queda al cadáver, si era uno
+    corpse: Option<Vec<ItemStack>>,
+    /// El item entero pasó al inventario: se quita del suelo
+    taken: bool,
+}
+
+/// Aplica la acción sobre un item ya al alcance
+fn pick_up(action: &PickupAction, item: &mut WorldItem, inventory: &mut Inventory, defs: &ItemDefs) -> Result<PickupOutcome, PickupError> {
+    match (action, &mut item.contents) {
+        (PickupAction::Item { .. }, WorldItemContents::Stack(stack)) => {
+            let diffs = inventory.insert(defs, stack.clone())?;
+            Ok(PickupOutcome { diffs, corpse: None, taken: true })
+        }
+        (PickupAction::Item { .. }, WorldItemContents::Corpse { .. }) => Err(PickupError::Corpse),
+        (PickupAction::OpenCorpse { .. }, WorldItemContents::Corpse { items, .. }) => {
+            Ok(PickupOutcome { diffs: Vec::new(), corpse: Some(items.clone()), taken: false })
+        }
+        (PickupAction::TakeFromCorpse { item: taken, .. }, WorldItemContents::Corpse { items, .. }) => {
+            let index = items.iter().position(|stack| stack.id == *taken).ok_or(PickupError::Gone)?;
+            let diffs = inventory.insert(defs, items[index].clone())?;
+            items.remove(index);
+            Ok(PickupOutcome { diffs, corpse: Some(items.clone()), taken: false })
+        }
+        (_, WorldItemContents::Stack(_)) => Err(PickupError::NotACorpse),
+    }
+}
+
+fn handle_pickup_requests(
+    mut commands: Commands,
+    mut requests: EventReader<PickupRequest>,
+    server_state: Res<ServerState>,
+    defs: Res<ItemDefs>,
+    mut reach: PickupReach,
+    mut items: Query<(Entity, &mut WorldItem, &Transform)>,
+    mut players: Query<(&mut Inventory, &Transform), Without<WorldItem>>,
+) {
+    // Despawneados en este mismo tick: el comando aún no se ha aplicado
+    let mut picked = HashSet::new();
+    let mut clients = server_state.clients.lock().unwrap();
+    for request in requests.read() {
+        let Some(client) = clients.get_mut(&request.client_id) else { continue };
+        let Some(Ok((mut inventory, player))) = client.player_entity.map(|entity| players.get_mut(entity)) else {
+            continue;
+        };
+        let eye = player.translation + Vec3::Y * EYE_HEIGHT;
+        let id = match &request.action {
+            PickupAction::Item { id } => *id,
+            PickupAction::OpenCorpse { corpse } | PickupAction::TakeFromCorpse { corpse, .. } => *corpse,
+        };
+
+        let result = match items.iter_mut().find(|(entity, item, _)| item.id == id && !picked.contains(entity)) {
+            Some((entity, mut item, transform)) => reach
+                .check(eye, transform.translation)
+                .and_then(|()| pick_up(&request.action, &mut item, &mut inventory, &defs))
+                .inspect(|outcome| {
+                    if outcome.taken {
+                        picked.insert(entity);
+                        commands.entity(entity).despawn();
+                    }
+                }),
+            None => Err(PickupError::Gone),
+        };
+
+        match result {
+            Ok(outcome) => {
+                let opened = matches!(request.action, PickupAction::OpenCorpse { .. });
+                if !opened {
+                    info!(player = %client.player_name, ?request.action, "📦 Recogido del suelo");
+                    client.send(&ServerMessage::InventoryUpdate { sequence: Some(request.sequence), diffs: outcome.diffs });
+                }
+                if let Some(items) = outcome.corpse {
+                    client.send(&ServerMessage::LootContents { sequence: opened.then_some(request.sequence), container: id, items });
+                }
+            }
+            Err(error) => {
+                debug!(player = %client.player_name, ?request.action, %error, "📦 Recogida rechazada");
+                client.send(&ServerMessage::InventoryRejected { sequence: request.sequence, reason: error.to_string() });
+            }
+        }
+    }
+}
+
+/// Manda a cada cliente lo que ha entrado en su radio o ha cambiado y lo que ya no está
+fn replicate_world_items(
+    server_state: Res<ServerState>,
+    settings: Res<DropSettings>,
+    mut views: ResMut<WorldItemViews>,
+    items: Query<(&WorldItem, &Transform)>,
+    players: Query<&Transform, With<Player>>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    views.clients.retain(|client_id, _| clients.contains_key(client_id));
+
+    for (client_id, client) in clients.iter_mut() {
+        let Some(Ok(player)) = client.player_entity.map(|entity| players.get(entity)) else { continue };
+        let view = views.clients.entry(*client_id).or_default();
+
+        let mut updated = Vec::new();
+        let mut visible = HashSet::new();
+        for (item, transform) in items.iter() {
+            if transform.translation.distance(player.translation) > settings.view_distance {
+                continue;
+            }
+            visible.insert(item.id);
+            if view.insert(item.id, item.revision) != Some(item.revision) {
+                updated.push(item.state(transform.translation));
+            }
+        }
+        let mut removed: Vec<u64> = view.keys().filter(|id| !visible.contains(id)).copied().collect();
+        removed.sort_unstable();
+        view.retain(|id, _| visible.contains(id));
+
+        if !updated.is_empty() || !removed.is_empty() {
+            client.send(&ServerMessage::WorldItems { updated, removed });
+        }
+    }
+}
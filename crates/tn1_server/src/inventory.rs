@@ -0,0 +1,452 @@
+//! Inventarios de los jugadores, con el servidor como autoridad.
+//!
+//! Cada jugador lleva un `Inventory` (componente) que recibe entero al entrar.
+//! Las acciones del cliente llegan como `InventoryRequest`; se validan con
+//! `Inventory::apply` y se contesta con los diffs o con el motivo del rechazo.
+//! Lo que se tira sale como `ItemDropped` para quien quiera ponerlo en el mundo.
+//! Cada cambio recalcula los `GearModifiers` (carga, armadura) del jugador y su
+//! efecto en el `PlayerController`.
+//! Los tipos de item vienen de `ItemDefs` (ver `ItemRegistryPlugin`); si el
+//! catálogo cambia se adaptan todos los inventarios y se reenvían enteros.
+//!
+//! Con base de datos el inventario se pide al worker al aparecer el jugador y
+//! se guarda con su posición (`item_instances`, una fila por item). Cada item
+//! tiene un UUID permanente: al cargar se descartan los que ya lleva otro
+//! jugador conectado y al guardar no se escribe un inventario con items de otro.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use tn1_shared::components::{Player, PlayerController, PlayerId};
+use tn1_shared::inventory::*;
+use tn1_shared::medical::BodyHealth;
+use tn1_shared::protocol::ServerMessage;
+use uuid::Uuid;
+use crate::database::{ItemPlacement, ItemRecord, PlayerStorage};
+use crate::networking::{DatabaseChannel, DatabaseCommand, NetworkingSet, ServerState};
+
+/// Acción de inventario recibida de un cliente; `NetworkingPlugin` la emite
+#[derive(Event, Debug, Clone)]
+pub struct InventoryRequest {
+    pub client_id: u32,
+    pub sequence: u32,
+    pub action: InventoryAction,
+}
+
+/// Da un item nuevo a un jugador (loot, admin, tests)
+#[derive(Event, Debug, Clone)]
+pub struct GiveItem {
+    pub player_id: PlayerId,
+    pub item: String,
+    pub quantity: u32,
+}
+
+/// Un jugador tiró un item al suelo
+#[derive(Event, Debug, Clone)]
+pub struct ItemDropped {
+    pub player_id: PlayerId,
+    pub position: Vec3,
+    pub stack: ItemStack,
+}
+
+/// Carga de la base de datos el inventario guardado de un jugador y sustituye
+/// el que lleve; se emite solo al aparecer, pero sirve para restaurarlo a mano
+#[derive(Event, Debug, Clone)]
+pub struct LoadInventory {
+    pub player_id: PlayerId,
+}
+
+/// El inventario salió de la base de datos y se guarda con la posición. Si la
+/// carga falla no se pone, para no machacar lo guardado con un inventario vacío.
+#[derive(Component, Debug, Default)]
+pub struct PersistedInventory;
+
+/// Esperando al worker de base de datos
+#[derive(Component)]
+struct LoadingInventory;
+
+/// Próximo `ItemId` libre
+#[derive(Resource, Debug)]
+pub struct ItemIds {
+    next: u64,
+}
+
+impl Default for ItemIds {
+    fn default() -> Self {
+        Self { next: 1 }
+    }
+}
+
+impl ItemIds {
+    pub fn allocate(&mut self) -> ItemId {
+        let id = ItemId(self.next);
+        self.next += 1;
+        id
+    }
+}
+
+/// Inventarios autoritativos; necesita el catálogo de `ItemRegistryPlugin`
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ItemIds>()
+            .add_event::<GiveItem>()
+            .add_event::<ItemDropped>()
+            .add_event::<LoadInventory>()
+            .add_systems(Update, (
+                attach_inventories,
+                request_inventory_loads,
+                apply_loaded_inventories,
+                migrate_inventories.run_if(resource_changed::<ItemDefs>),
+                send_inventory_snapshots,
+                handle_inventory_requests,
+                give_items,
+                update_gear_modifiers,
+            ).chain().after(NetworkingSet));
+    }
+}
+
+/// Jugadores que aún no tienen inventario ni lo están esperando
+type NewPlayers<'w, 's> =
+    Query<'w, 's, (Entity, &'static PlayerId), (With<Player>, Without<Inventory>, Without<LoadingInventory>)>;
+
+/// Jugador recién aparecido: su inventario guardado o, sin base de datos, uno vacío
+fn attach_inventories(
+    mut commands: Commands,
+    storage: Option<Res<PlayerStorage>>,
+    players: NewPlayers,
+    mut loads: EventWriter<LoadInventory>,
+) {
+    for (entity, player_id) in players.iter() {
+        if storage.is_some() {
+            commands.entity(entity).insert(LoadingInventory);
+            loads.send(LoadInventory { player_id: *player_id });
+        } else {
+            commands.entity(entity).insert(Inventory::new());
+        }
+    }
+}
+
+fn request_inventory_loads(
+    mut loads: EventReader<LoadInventory>,
+    storage: Option<Res<PlayerStorage>>,
+    database_channel: Res<DatabaseChannel>,
+    server_state: Res<ServerState>,
+) {
+    for load in loads.read() {
+        if storage.is_none() {
+            warn!("🎒 Sin base de datos no se puede cargar el inventario de {}", load.player_id.0);
+            continue;
+        }
+        match database_channel.sender.send(DatabaseCommand::LoadInventory { player_id: load.player_id.0 }) {
+            Ok(()) => server_state.metrics.db_enqueued(),
+            Err(e) => error!("❌ Error enviando carga de inventario: {}", e),
+        }
+    }
+}
+
+/// Recoge lo que ha leído el worker y se lo pone a cada jugador
+fn apply_loaded_inventories(
+    mut commands: Commands,
+    database_channel: Res<DatabaseChannel>,
+    server_state: Res<ServerState>,
+    defs: Res<ItemDefs>,
+    mut ids: ResMut<ItemIds>,
+    players: Query<(Entity, &PlayerId, &Transform, Option<&Inventory>)>,
+    mut dropped: EventWriter<ItemDropped>,
+) {
+    let loaded = std::mem::take(&mut *database_channel.inventories.lock().unwrap());
+    for (player_id, result) in loaded {
+        // Si ya se ha ido no hay a quién dárselo
+        let Some((entity, player_id, transform, current)) = players.iter().find(|(_, id, ..)| id.0 == player_id) else {
+            continue;
+        };
+        let mut records = match result {
+            Ok(records) => records,
+            Err(e) => {
+                warn!(player_id = %player_id.0, "🎒 Inventario sin cargar ({}); no se guardará en esta sesión", e);
+                if current.is_none() {
+                    commands.entity(entity).remove::<LoadingInventory>().insert(Inventory::new());
+                }
+                continue;
+            }
+        };
+
+        // Lo que ya lleva otro jugador conectado no se puede tener dos veces
+        let mut online = HashSet::new();
+        for (_, _, _, inventory) in players.iter().filter(|(other, ..)| *other != entity) {
+            online.extend(inventory.into_iter().flat_map(inventory_records).map(|record| record.id));
+        }
+        let total = records.len();
+        records.retain(|record| !online.contains(&record.id));
+        if records.len() < total {
+            warn!(player_id = %player_id.0, duplicates = total - records.len(), "🎒 Items guardados que ya tiene otro jugador; se descartan");
+        }
+
+        let (inventory, migration) = restore_inventory(&records, &defs, &mut ids);
+        info!(player_id = %player_id.0, items = records.len(), moved = migration.moved, lost = migration.lost.len(), "🎒 Inventario cargado");
+        for stack in migration.lost {
+            dropped.send(ItemDropped { player_id: *player_id, position: transform.translation, stack });
+        }
+
+        // Una recarga se reenvía; el primero lo manda `send_inventory_snapshots`
+        if current.is_some() {
+            let mut clients = server_state.clients.lock().unwrap();
+            if let Some(client) = clients.values_mut().find(|client| client.player_entity == Some(entity)) {
+                client.send(&ServerMessage::Inventory(inventory.clone()));
+            }
+        }
+        commands.entity(entity).remove::<LoadingInventory>().insert((inventory, PersistedInventory));
+    }
+}
+
+type Wearers<'w, 's> = Query<'w, 's, (Entity, Ref<'static, Inventory>, Option<Ref<'static, BodyHealth>>, &'static mut PlayerController)>;
+
+/// Recalcula los `GearModifiers` de quien ha cambiado de inventario (de todos
+/// si cambia el catálogo) y se los pasa al controlador de movimiento, junto con
+/// lo que frenan las piernas rotas (`BodyHealth`)
+fn update_gear_modifiers(
+    mut commands: Commands,
+    defs: Res<ItemDefs>,
+    mut players: Wearers,
+) {
+    for (entity, inventory, body, mut controller) in players.iter_mut() {
+        if !inventory.is_changed() && !defs.is_changed() && !body.as_ref().is_some_and(|body| body.is_changed()) {
+            continue;
+        }
+        let modifiers = inventory.modifiers(&defs);
+        let (speed, sprint) = body.map_or((1.0, true), |body| (body.speed_multiplier(), body.can_sprint()));
+        controller.speed_multiplier = modifiers.speed_multiplier * speed;
+        controller.can_sprint = modifiers.can_sprint && sprint;
+        commands.entity(entity).insert(modifiers);
+    }
+}
+
+/// Filas de `item_instances` de un inventario, padres antes que hijos
+pub fn inventory_records(inventory: &Inventory) -> Vec<ItemRecord> {
+    let mut records = Vec::new();
+    for (index, pocket) in inventory.pockets.iter().enumerate() {
+        for placed in &pocket.items {
+            push_records(&placed.stack, ItemPlacement::Pocket(index as u8), (placed.x, placed.y, placed.rotated), &mut records);
+        }
+    }
+    for (slot, placed) in &inventory.equipment.slots {
+        push_records(&placed.stack, ItemPlacement::Equipment(slot.key().to_string()), (0, 0, false), &mut records);
+    }
+    records
+}
+
+fn push_records(stack: &ItemStack, placement: ItemPlacement, (x, y, rotated): (u8, u8, bool), records: &mut Vec<ItemRecord>) {
+    records.push(ItemRecord {
+        id: stack.uuid,
+        item: stack.item.clone(),
+        quantity: stack.quantity,
+        durability: stack.durability,
+        ammo: stack.ammo,
+        placement,
+        x,
+        y,
+        rotated,
+    });
+    for attachment in &stack.attachments {
+        push_records(attachment, ItemPlacement::Attachment(stack.uuid), (0, 0, false), records);
+    }
+    for inner in stack.contents.iter().flat_map(|contents| &contents.items) {
+        push_records(&inner.stack, ItemPlacement::Container(stack.uuid), (inner.x, inner.y, inner.rotated), records);
+    }
+}
+
+/// Rehace un inventario a partir de sus filas, con `ItemId` nuevos. Lo que no
+/// tiene dónde ir (padre que falta, ranura que no le vale, ciclos) busca hueco y
+/// todo se adapta al catálogo actual con `Inventory::migrate_with`.
+pub fn restore_inventory(records: &[ItemRecord], defs: &ItemDefs, ids: &mut ItemIds) -> (Inventory, Migration) {
+    let mut unique = HashSet::new();
+    let records: Vec<&ItemRecord> = records.iter().filter(|record| unique.insert(record.id)).collect();
+    let mut children: HashMap<Uuid, Vec<&ItemRecord>> = HashMap::new();
+    for record in &records {
+        if let ItemPlacement::Container(parent) | ItemPlacement::Attachment(parent) = record.placement {
+            children.entry(parent).or_default().push(record);
+        }
+    }
+
+    let mut inventory = Inventory::new();
+    let mut built = HashSet::new();
+    let mut loose = Vec::new();
+    let has_parent = |record: &ItemRecord| match record.placement {
+        ItemPlacement::Container(parent) | ItemPlacement::Attachment(parent) => unique.contains(&parent),
+        _ => false,
+    };
+    // Primero lo que cuelga de un bolsillo o de nada, luego lo que quede (ciclos)
+    let roots = records.iter().filter(|record| !has_parent(record)).chain(records.iter().filter(|record| has_parent(record)));
+    for record in roots {
+        if built.contains(&record.id) {
+            continue;
+        }
+        let stack = restore_stack(record, &children, defs, ids, &mut built, &mut loose);
+        let placed = PlacedItem { stack, x: record.x, y: record.y, rotated: record.rotated };
+        match &record.placement {
+            ItemPlacement::Pocket(index) => match inventory.pockets.get_mut(*index as usize) {
+                Some(pocket) => pocket.items.push(placed),
+                None => loose.push(placed.stack),
+            },
+            ItemPlacement::Equipment(key) => {
+                let slot = EquipmentSlot::from_key(key)
+                    .filter(|slot| !inventory.equipment.slots.contains_key(slot))
+                    .filter(|slot| defs.resolve(&record.item).is_some_and(|def| slot.accepts(def)));
+                match slot {
+                    Some(slot) => {
+                        inventory.equipment.slots.insert(slot, PlacedItem { x: 0, y: 0, rotated: false, ..placed });
+                    }
+                    None => loose.push(placed.stack),
+                }
+            }
+            _ => loose.push(placed.stack),
+        }
+    }
+
+    let migration = inventory.migrate_with(defs, loose);
+    (inventory, migration)
+}
+
+fn restore_stack(
+    record: &ItemRecord,
+    children: &HashMap<Uuid, Vec<&ItemRecord>>,
+    defs: &ItemDefs,
+    ids: &mut ItemIds,
+    built: &mut HashSet<Uuid>,
+    loose: &mut Vec<ItemStack>,
+) -> ItemStack {
+    built.insert(record.id);
+    let mut stack = ItemStack {
+        id: ids.allocate(),
+        uuid: record.id,
+        item: record.item.clone(),
+        quantity: record.quantity,
+        durability: record.durability,
+        ammo: record.ammo,
+        attachments: Vec::new(),
+        contents: defs.resolve(&record.item).and_then(|def| def.grid).map(Container::from_spec),
+    };
+    for child in children.get(&record.id).into_iter().flatten() {
+        if built.contains(&child.id) {
+            continue;
+        }
+        let inner = restore_stack(child, children, defs, ids, built, loose);
+        match (&child.placement, stack.contents.as_mut()) {
+            (ItemPlacement::Attachment(_), _) => stack.attachments.push(inner),
+            (ItemPlacement::Container(_), Some(contents)) => {
+                contents.items.push(PlacedItem { stack: inner, x: child.x, y: child.y, rotated: child.rotated });
+            }
+            _ => loose.push(inner),
+        }
+    }
+    stack
+}
+
+/// El catálogo ha cambiado: cada inventario afectado se adapta, su dueño lo
+/// recibe entero de nuevo y lo que ya no cabe cae a sus pies
+fn migrate_inventories(
+    defs: Res<ItemDefs>,
+    server_state: Res<ServerState>,
+    mut inventories: Query<(Entity, &mut Inventory, &Transform, &PlayerId)>,
+    mut dropped: EventWriter<ItemDropped>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    for (entity, mut inventory, transform, player_id) in inventories.iter_mut() {
+        let migration = inventory.migrate(&defs);
+        if migration.is_empty() {
+            continue;
+        }
+        info!(
+            player_id = %player_id.0,
+            renamed = migration.renamed,
+            clamped = migration.clamped,
+            moved = migration.moved,
+            lost = migration.lost.len(),
+            "🎒 Inventario adaptado al catálogo nuevo"
+        );
+        for stack in migration.lost {
+            dropped.send(ItemDropped { player_id: *player_id, position: transform.translation, stack });
+        }
+        if let Some(client) = clients.values_mut().find(|client| client.player_entity == Some(entity)) {
+            client.send(&ServerMessage::Inventory(inventory.clone()));
+        }
+    }
+}
+
+/// Cada cliente recibe su inventario completo una vez, al entrar
+fn send_inventory_snapshots(
+    server_state: Res<ServerState>,
+    inventories: Query<&Inventory>,
+    mut informed: Local<HashSet<u32>>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    informed.retain(|client_id| clients.contains_key(client_id));
+    for (client_id, client) in clients.iter_mut() {
+        if informed.contains(client_id) {
+            continue;
+        }
+        let Some(inventory) = client.player_entity.and_then(|entity| inventories.get(entity).ok()) else { continue };
+        client.send(&ServerMessage::Inventory(inventory.clone()));
+        informed.insert(*client_id);
+    }
+}
+
+fn handle_inventory_requests(
+    mut requests: EventReader<InventoryRequest>,
+    server_state: Res<ServerState>,
+    defs: Res<ItemDefs>,
+    mut ids: ResMut<ItemIds>,
+    mut inventories: Query<(&mut Inventory, &Transform, &PlayerId)>,
+    mut dropped: EventWriter<ItemDropped>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    for request in requests.read() {
+        let Some(client) = clients.get_mut(&request.client_id) else { continue };
+        let Some(Ok((mut inventory, transform, player_id))) = client.player_entity.map(|entity| inventories.get_mut(entity))
+        else {
+            continue;
+        };
+
+        match inventory.apply(&defs, &request.action, || ids.allocate()) {
+            Ok(outcome) => {
+                if let Some(stack) = outcome.dropped {
+                    info!(player = %client.player_name, item = %stack.item, quantity = stack.quantity, "🎒 Item tirado");
+                    dropped.send(ItemDropped { player_id: *player_id, position: transform.translation, stack });
+                }
+                client.send(&ServerMessage::InventoryUpdate { sequence: Some(request.sequence), diffs: outcome.diffs });
+            }
+            Err(error) => {
+                debug!(player = %client.player_name, ?request.action, %error, "🎒 Acción de inventario rechazada");
+                client.send(&ServerMessage::InventoryRejected { sequence: request.sequence, reason: error.to_string() });
+            }
+        }
+    }
+}
+
+fn give_items(
+    mut gifts: EventReader<GiveItem>,
+    server_state: Res<ServerState>,
+    defs: Res<ItemDefs>,
+    mut ids: ResMut<ItemIds>,
+    mut inventories: Query<(&mut Inventory, &PlayerId)>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    for gift in gifts.read() {
+        let Some((mut inventory, _)) = inventories.iter_mut().find(|(_, player_id)| **player_id == gift.player_id) else {
+            continue;
+        };
+        let Some(def) = defs.get(&gift.item) else {
+            warn!("🎒 Tipo de item desconocido: {}", gift.item);
+            continue;
+        };
+        match inventory.insert(&defs, ItemStack::new(ids.allocate(), def, gift.quantity)) {
+            Ok(diffs) => {
+                if let Some(client) = clients.values_mut().find(|client| client.player_id == Some(gift.player_id)) {
+                    client.send(&ServerMessage::InventoryUpdate { sequence: None, diffs });
+                }
+            }
+            Err(error) => debug!("🎒 No se pudo dar {} x{}: {}", gift.item, gift.quantity, error),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Catálogo de items del servidor: se carga de `TN1_ITEMS_PATH` al arrancar y,
+//! con `TN1_ITEMS_HOT_RELOAD`, se recarga al cambiar los ficheros.
+//!
+//! Un catálogo con errores no arranca el servidor; en una recarga el error se
+//! avisa y se sigue con el catálogo anterior. Al cambiar `ItemDefs` el
+//! `InventoryPlugin` adapta los inventarios (ver `Inventory::migrate`).
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tn1_shared::items::{item_files_fingerprint, ItemDefs};
+use crate::config::env_or;
+
+#[derive(Resource, Debug, Clone)]
+pub struct ItemSettings {
+    /// Fichero `.ron` o carpeta con ellos
+    pub path: PathBuf,
+    pub hot_reload: bool,
+    /// Cada cuánto se mira si los ficheros han cambiado
+    pub poll_interval: Duration,
+}
+
+impl Default for ItemSettings {
+    fn default() -> Self {
+        Self {
+            path: std::env::var("TN1_ITEMS_PATH").unwrap_or_else(|_| "data/items".to_string()).into(),
+            hot_reload: env_or("TN1_ITEMS_HOT_RELOAD", false),
+            poll_interval: Duration::from_secs_f32(env_or("TN1_ITEMS_POLL_SECS", 1.0)),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ItemRegistryPlugin {
+    pub settings: ItemSettings,
+}
+
+impl Plugin for ItemRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        let defs = ItemDefs::load(&self.settings.path)
+            .unwrap_or_else(|e| panic!("No se pudo cargar el catálogo de items: {}", e));
+        info!("📦 {} tipos de item (versión {}) de {}", defs.len(), defs.version(), self.settings.path.display());
+
+        app.insert_resource(defs)
+            .insert_resource(self.settings.clone())
+            .insert_resource(ItemFiles { fingerprint: item_files_fingerprint(&self.settings.path) });
+        if self.settings.hot_reload {
+            app.add_systems(Update, reload_item_defs);
+        }
+    }
+}
+
+/// Estado de los ficheros en la última carga
+#[derive(Resource)]
+struct ItemFiles {
+    fingerprint: Vec<(PathBuf, Option<SystemTime>, u64)>,
+}
+
+fn reload_item_defs(
+    time: Res<Time>,
+    settings: Res<ItemSettings>,
+    mut files: ResMut<ItemFiles>,
+    mut defs: ResMut<ItemDefs>,
+    mut since_check: Local<Duration>,
+) {
+    *since_check += time.delta();
+    if *since_check < settings.poll_interval {
+        return;
+    }
+    *since_check = Duration::ZERO;
+
+    let fingerprint = item_files_fingerprint(&settings.path);
+    if fingerprint == files.fingerprint {
+        return;
+    }
+    files.fingerprint = fingerprint;
+    match ItemDefs::load(&settings.path) {
+        Ok(reloaded) => {
+            info!("🔄 Catálogo de items recargado: {} tipos (versión {})", reloaded.len(), reloaded.version());
+            *defs = reloaded;
+        }
+        Err(e) => warn!("⚠️ Catálogo de items sin recargar: {}", e),
+    }
+}
@@ -0,0 +1,273 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use tn1_shared::{components::*, constants::*, medical::ZONE_HITBOXES, protocol::TICK_RATE};
+use crate::networking::ServerTick;
+
+/// Historial de hitboxes para juzgar disparos desde el punto de vista del tirador.
+///
+/// Cada tick se guarda la posición de todos los jugadores; al validar un impacto se
+/// reconstruye el mundo en el instante que el cliente estaba viendo (RTT/2 + interpolación).
+pub struct LagCompensationPlugin;
+
+impl Plugin for LagCompensationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HitboxHistory::new(LAG_COMPENSATION_HISTORY_SECS))
+            .add_systems(PostUpdate, record_hitbox_history);
+    }
+}
+
+/// Cápsula vertical que envuelve al jugador (pies en `position`)
+#[derive(Clone, Copy, Debug)]
+pub struct Hitbox {
+    pub radius: f32,
+    pub height: f32,
+}
+
+impl Default for Hitbox {
+    fn default() -> Self {
+        Self {
+            radius: PLAYER_RADIUS,
+            height: PLAYER_HEIGHT,
+        }
+    }
+}
+
+impl Hitbox {
+    /// Distancia a lo largo del rayo hasta la cápsula, si la toca.
+    /// `direction` debe estar normalizada.
+    pub fn ray_intersection(&self, position: Vec3, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        self.ray_span(position, origin, direction, max_distance).map(|(entry, _)| entry)
+    }
+
+    /// Entrada y salida del rayo por la cápsula (la entrada es 0 si el origen
+    /// está dentro). `direction` debe estar normalizada.
+    pub fn ray_span(&self, position: Vec3, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<(f32, f32)> {
+        // Segmento interior de la cápsula
+        let bottom = position + Vec3::Y * self.radius;
+        let top = position + Vec3::Y * (self.height - self.radius).max(self.radius);
+        ray_capsule(bottom, top, self.radius, origin, direction).filter(|(entry, _)| *entry <= max_distance)
+    }
+}
+
+/// Entrada y salida de un rayo por la cápsula de segmento `bottom`-`top`: la
+/// esfera centrada en el punto del segmento más cercano al rayo
+fn ray_capsule(bottom: Vec3, top: Vec3, radius: f32, origin: Vec3, direction: Vec3) -> Option<(f32, f32)> {
+    let segment = top - bottom;
+    let w0 = origin - bottom;
+    let a = direction.dot(direction);
+    let b = direction.dot(segment);
+    let c = segment.dot(segment);
+    let d = direction.dot(w0);
+    let e = segment.dot(w0);
+    let denom = a * c - b * b;
+
+    let s = if denom.abs() > f32::EPSILON {
+        ((a * e - b * d) / denom).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    ray_sphere(origin, direction, bottom + segment * s, radius)
+}
+
+fn ray_sphere(origin: Vec3, direction: Vec3, center: Vec3, radius: f32) -> Option<(f32, f32)> {
+    let offset = origin - center;
+    let b = offset.dot(direction);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let distance = -b - root;
+    if distance >= 0.0 {
+        Some((distance, -b + root))
+    } else if c <= 0.0 {
+        // El origen está dentro de la hitbox
+        Some((0.0, -b + root))
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HitboxSnapshot {
+    pub entity: Entity,
+    pub player_id: PlayerId,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub hitbox: Hitbox,
+}
+
+impl HitboxSnapshot {
+    /// Primera zona del cuerpo (`ZONE_HITBOXES`, giradas con el jugador) que
+    /// cruza el rayo, con su entrada y su salida. `direction` debe estar normalizada.
+    pub fn zone_span(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<(BodyZone, f32, f32)> {
+        // Descarte rápido con la cápsula entera
+        self.hitbox.ray_span(self.position, origin, direction, max_distance)?;
+        ZONE_HITBOXES
+            .iter()
+            .filter_map(|zone| {
+                let (bottom, top) = (self.position + self.rotation * zone.bottom, self.position + self.rotation * zone.top);
+                let (entry, exit) = ray_capsule(bottom, top, zone.radius, origin, direction)?;
+                (entry <= max_distance).then_some((zone.zone, entry, exit))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Centro de la zona a la altura de `point`, para la normal de un impacto
+    pub fn zone_axis(&self, zone: BodyZone, point: Vec3) -> Vec3 {
+        let hitbox = ZONE_HITBOXES.iter().find(|hitbox| hitbox.zone == zone).expect("todas las zonas tienen hitbox");
+        let (bottom, top) = (self.position + self.rotation * hitbox.bottom, self.position + self.rotation * hitbox.top);
+        let height = top.y - bottom.y;
+        // La cabeza es una esfera
+        if height <= f32::EPSILON {
+            return bottom;
+        }
+        bottom + (top - bottom) * ((point.y - bottom.y) / height).clamp(0.0, 1.0)
+    }
+}
+
+struct TickRecord {
+    tick: u32,
+    time: f64,
+    players: Vec<HitboxSnapshot>,
+}
+
+/// Ring buffer de hitboxes por `ServerTick`
+#[derive(Resource)]
+pub struct HitboxHistory {
+    records: VecDeque<TickRecord>,
+    capacity: usize,
+}
+
+/// Estado de los jugadores reconstruido en un instante pasado
+pub struct RewoundWorld {
+    pub tick: u32,
+    pub time: f64,
+    pub players: Vec<HitboxSnapshot>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RewindHit {
+    pub entity: Entity,
+    pub player_id: PlayerId,
+    pub distance: f32,
+    pub point: Vec3,
+}
+
+impl HitboxHistory {
+    pub fn new(seconds: f32) -> Self {
+        let capacity = (seconds * TICK_RATE as f32).ceil() as usize;
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, tick: u32, time: f64, players: Vec<HitboxSnapshot>) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(TickRecord { tick, time, players });
+    }
+
+    pub fn latest_tick(&self) -> Option<u32> {
+        self.records.back().map(|record| record.tick)
+    }
+
+    /// Instante que veía un cliente al disparar: la mitad del RTT más el retardo
+    /// de interpolación, limitado a `LAG_COMPENSATION_MAX_REWIND_SECS`.
+    pub fn estimate_view_time(now: f64, rtt: f32, interpolation_delay: f32) -> f64 {
+        let rewind = (rtt * 0.5 + interpolation_delay).clamp(0.0, LAG_COMPENSATION_MAX_REWIND_SECS);
+        now - rewind as f64
+    }
+
+    /// Reconstruye las hitboxes en `time` interpolando entre los dos ticks vecinos
+    pub fn rewind(&self, time: f64) -> Option<RewoundWorld> {
+        let newest = self.records.back()?;
+        let oldest = self.records.front()?;
+
+        // El límite de rewind se aplica también aquí por si el llamador no lo hizo
+        let earliest = newest.time - LAG_COMPENSATION_MAX_REWIND_SECS as f64;
+        let time = time.clamp(earliest.max(oldest.time), newest.time);
+
+        let after_index = self.records.iter().position(|record| record.time >= time)?;
+        let after = &self.records[after_index];
+        if after_index == 0 || after.time == time {
+            return Some(RewoundWorld {
+                tick: after.tick,
+                time: after.time,
+                players: after.players.clone(),
+            });
+        }
+
+        let before = &self.records[after_index - 1];
+        let alpha = ((time - before.time) / (after.time - before.time)) as f32;
+
+        // Solo jugadores presentes en ambos ticks
+        let players = before
+            .players
+            .iter()
+            .filter_map(|old| {
+                let new = after.players.iter().find(|p| p.player_id == old.player_id)?;
+                Some(HitboxSnapshot {
+                    position: old.position.lerp(new.position, alpha),
+                    rotation: old.rotation.slerp(new.rotation, alpha),
+                    ..*new
+                })
+            })
+            .collect();
+
+        Some(RewoundWorld {
+            tick: if alpha < 0.5 { before.tick } else { after.tick },
+            time,
+            players,
+        })
+    }
+}
+
+impl RewoundWorld {
+    /// Primer jugador que cruza el rayo, ignorando al tirador
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32, shooter: Option<PlayerId>) -> Option<RewindHit> {
+        let direction = direction.try_normalize()?;
+        self.players
+            .iter()
+            .filter(|snapshot| Some(snapshot.player_id) != shooter)
+            .filter_map(|snapshot| {
+                let distance = snapshot.hitbox.ray_intersection(snapshot.position, origin, direction, max_distance)?;
+                Some(RewindHit {
+                    entity: snapshot.entity,
+                    player_id: snapshot.player_id,
+                    distance,
+                    point: origin + direction * distance,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
+
+fn record_hitbox_history(
+    mut history: ResMut<HitboxHistory>,
+    tick: Res<ServerTick>,
+    time: Res<Time>,
+    players: Query<(Entity, &PlayerId, &Transform), With<Player>>,
+) {
+    // Un registro por tick del servidor
+    if history.latest_tick() == Some(tick.0) {
+        return;
+    }
+
+    let snapshots = players
+        .iter()
+        .map(|(entity, player_id, transform)| HitboxSnapshot {
+            entity,
+            player_id: *player_id,
+            position: transform.translation,
+            rotation: transform.rotation,
+            hitbox: Hitbox::default(),
+        })
+        .collect();
+
+    history.record(tick.0, time.elapsed_secs_f64(), snapshots);
+}
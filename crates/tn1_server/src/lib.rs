@@ -0,0 +1,26 @@
+//! Servidor dedicado de Trust-No-1.
+//!
+//! El binario `tn1-server` solo arma el `App`; los plugins viven aquí para que
+//! los tests de integración puedan levantar el servidor dentro del proceso.
+
+pub mod config;
+pub mod physics;
+pub mod world;
+pub mod weather;
+pub mod chunks;
+pub mod items;
+pub mod inventory;
+pub mod loot;
+pub mod drops;
+pub mod trade;
+pub mod weapons;
+pub mod medical;
+pub mod systems;
+pub mod networking;
+pub mod lag_compensation;
+pub mod database;
+pub mod tls;
+pub mod demo;
+pub mod metrics;
+pub mod chat;
+pub mod voice;
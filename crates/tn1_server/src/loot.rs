@@ -0,0 +1,572 @@
+//! Loot del mundo: cada contenedor de los chunks (`LootContainer`) se llena con
+//! su tabla (`LootTables`, de `TN1_LOOT_PATH`) la primera vez que se carga su chunk.
+//!
+//! Los jugadores lo abren y sacan items con `ClientMessage::Loot`, siempre a
+//! menos de `reach` metros. Al sacar el primer item empieza la cuenta atrás de
+//! la tabla; al acabar el contenedor se vuelve a tirar y lo que quedara
+//! desaparece. Ningún relleno pasa de los topes por item (`caps`), que cuentan
+//! lo que hay en contenedores y en inventarios de jugadores conectados.
+//!
+//! Con base de datos el estado de los contenedores se pide al worker al
+//! arrancar (hasta tenerlo no se llena ninguno) y los que cambian se guardan
+//! cada `save_interval`. `tn1-server --loot-stats` resume el reparto en el mapa.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+use tn1_shared::chunk::{ChunkCoord, ChunkGrid, ContainerKind, WorldRng};
+use tn1_shared::inventory::{Container, Inventory, InventoryDiff, ItemId, ItemStack};
+use tn1_shared::items::ItemDefs;
+use tn1_shared::loot::{LootAction, LootError, LootTable, LootTables};
+use tn1_shared::protocol::ServerMessage;
+use tn1_shared::terrain::TerrainGenerator;
+use crate::chunks::{generate_chunk, LoadedChunks};
+use crate::config::env_or;
+use crate::database::{LootContainerRecord, LootItemRecord, PlayerStorage};
+use crate::inventory::{inventory_records, ItemIds};
+use crate::networking::{DatabaseChannel, DatabaseCommand, NetworkingSet, ServerState};
+use crate::world::{WorldSettings, WorldTerrain};
+
+/// Acción sobre un contenedor recibida de un cliente; `NetworkingPlugin` la emite
+#[derive(Event, Debug, Clone)]
+pub struct LootRequest {
+    pub client_id: u32,
+    pub sequence: u32,
+    pub action: LootAction,
+}
+
+/// Tablas, alcance y guardado del loot (variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct LootSettings {
+    /// Fichero `.ron` o carpeta con ellos
+    pub path: PathBuf,
+    /// Distancia máxima (metros) a la que se abre un contenedor
+    pub reach: f32,
+    /// Cada cuánto se guardan los contenedores que han cambiado
+    pub save_interval: Duration,
+}
+
+impl Default for LootSettings {
+    fn default() -> Self {
+        Self {
+            path: std::env::var("TN1_LOOT_PATH").unwrap_or_else(|_| "data/loot".to_string()).into(),
+            reach: env_or("TN1_LOOT_REACH", 3.0),
+            save_interval: Duration::from_secs_f32(env_or("TN1_LOOT_SAVE_SECS", 10.0)),
+        }
+    }
+}
+
+/// Un contenedor del mundo y lo que tiene dentro
+#[derive(Debug, Clone)]
+pub struct LootSpot {
+    /// Tabla con la que se llena; vacía si ninguna encaja
+    pub table: String,
+    pub kind: ContainerKind,
+    pub position: Vec3,
+    pub items: Vec<ItemStack>,
+    /// Segundo del servidor en que se rellena; `None` si nadie lo ha tocado
+    pub restock_at: Option<f64>,
+}
+
+/// Contenedores que ya han aparecido, por `LootContainer::id`. Siguen aquí
+/// aunque su chunk se descargue, así que la cuenta atrás no se para.
+#[derive(Resource)]
+pub struct LootContainers {
+    spots: HashMap<u64, LootSpot>,
+    /// Estado guardado de los que aún no han aparecido; `None` mientras se
+    /// espera al worker
+    saved: Option<HashMap<u64, LootContainerRecord>>,
+    /// Sin base de datos, o si la carga falló, no se guarda nada
+    persist: bool,
+    /// Cambiados desde el último guardado
+    dirty: HashSet<u64>,
+    rng: WorldRng,
+}
+
+impl FromWorld for LootContainers {
+    /// Con la semilla del mundo (`TN1_WORLD_SEED` si aún no hay `WorldSettings`)
+    fn from_world(world: &mut World) -> Self {
+        let seed = world.get_resource::<WorldSettings>().map_or_else(|| WorldSettings::default().seed, |settings| settings.seed);
+        Self {
+            spots: HashMap::new(),
+            saved: None,
+            persist: false,
+            dirty: HashSet::new(),
+            // Otra secuencia que la de los chunks con la misma semilla
+            rng: WorldRng::new(seed ^ 0x1007_C0DE),
+        }
+    }
+}
+
+impl LootContainers {
+    pub fn get(&self, id: u64) -> Option<&LootSpot> {
+        self.spots.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &LootSpot)> {
+        self.spots.iter().map(|(id, spot)| (*id, spot))
+    }
+
+    pub fn len(&self) -> usize {
+        self.spots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spots.is_empty()
+    }
+
+    /// Ya se sabe qué había guardado y se pueden llenar contenedores
+    pub fn is_ready(&self) -> bool {
+        self.saved.is_some()
+    }
+}
+
+/// Loot de los contenedores del mundo; necesita los plugins de chunks,
+/// catálogo de items e inventario
+#[derive(Default)]
+pub struct LootPlugin {
+    pub settings: LootSettings,
+}
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        let defs = app.world().get_resource::<ItemDefs>().expect("LootPlugin va después de ItemRegistryPlugin");
+        let tables = LootTables::load(&self.settings.path, defs)
+            .unwrap_or_else(|e| panic!("No se pudieron cargar las tablas de loot: {}", e));
+        info!("🎲 {} tablas de loot de {}", tables.len(), self.settings.path.display());
+
+        app.insert_resource(tables)
+            .insert_resource(self.settings.clone())
+            .init_resource::<LootContainers>()
+            .add_systems(Startup, request_loot_state)
+            .add_systems(Update, (
+                apply_loaded_loot,
+                spawn_loot,
+                restock_loot,
+                handle_loot_requests,
+                save_loot,
+            ).chain().after(NetworkingSet).run_if(resource_exists::<WorldTerrain>));
+    }
+}
+
+fn request_loot_state(
+    storage: Option<Res<PlayerStorage>>,
+    database_channel: Res<DatabaseChannel>,
+    server_state: Res<ServerState>,
+    mut containers: ResMut<LootContainers>,
+) {
+    if storage.is_none() {
+        containers.saved = Some(HashMap::new());
+        return;
+    }
+    match database_channel.sender.send(DatabaseCommand::LoadLootContainers) {
+        Ok(()) => server_state.metrics.db_enqueued(),
+        Err(e) => {
+            error!("❌ Error pidiendo los contenedores de loot: {}", e);
+            containers.saved = Some(HashMap::new());
+        }
+    }
+}
+
+/// Recoge lo que ha leído el worker
+fn apply_loaded_loot(database_channel: Res<DatabaseChannel>, mut containers: ResMut<LootContainers>) {
+    if containers.is_ready() {
+        return;
+    }
+    let Some(loaded) = database_channel.loot.lock().unwrap().take() else { return };
+    match loaded {
+        Ok(records) => {
+            info!("🎲 {} contenedores de loot guardados", records.len());
+            containers.saved = Some(records.into_iter().map(|record| (record.id, record)).collect());
+            containers.persist = true;
+        }
+        Err(e) => {
+            warn!("🎲 Contenedores de loot sin cargar ({}); no se guardarán en esta sesión", e);
+            containers.saved = Some(HashMap::new());
+        }
+    }
+}
+
+/// Tablas, catálogo e ids para llenar contenedores
+#[derive(SystemParam)]
+struct LootSources<'w> {
+    tables: Res<'w, LootTables>,
+    defs: Res<'w, ItemDefs>,
+    ids: ResMut<'w, ItemIds>,
+}
+
+/// Unidades de cada item que hay ahora en contenedores e inventarios
+fn loot_tally<'a>(containers: &LootContainers, inventories: impl Iterator<Item = &'a Inventory>) -> HashMap<String, u32> {
+    let mut tally: HashMap<String, u32> = HashMap::new();
+    for stack in containers.spots.values().flat_map(|spot| &spot.items) {
+        *tally.entry(stack.item.clone()).or_default() += stack.quantity;
+    }
+    for record in inventories.flat_map(inventory_records) {
+        *tally.entry(record.item).or_default() += record.quantity;
+    }
+    tally
+}
+
+/// Tira la tabla respetando los topes y apunta lo que sale en `tally`
+fn fill(table: &LootTable, sources: &mut LootSources, rng: &mut WorldRng, tally: &mut HashMap<String, u32>) -> Vec<ItemStack> {
+    let rolled = table.roll(rng, |item| {
+        sources.tables.cap(item).map_or(u32::MAX, |cap| cap.saturating_sub(tally.get(item).copied().unwrap_or(0)))
+    });
+    let mut items = Vec::new();
+    for (item, quantity) in rolled {
+        let Some(def) = sources.defs.get(&item).filter(|_| quantity > 0) else { continue };
+        *tally.entry(item).or_default() += quantity;
+        items.push(ItemStack::new(sources.ids.allocate(), def, quantity));
+    }
+    items
+}
+
+/// Contenedores de los chunks recién cargados: su estado guardado o un relleno nuevo
+fn spawn_loot(
+    chunks: Res<LoadedChunks>,
+    terrain: Res<WorldTerrain>,
+    mut sources: LootSources,
+    mut containers: ResMut<LootContainers>,
+    inventories: Query<&Inventory>,
+    time: Res<Time>,
+) {
+    if !containers.is_ready() {
+        return;
+    }
+    let mut appeared: Vec<_> = chunks
+        .coords()
+        .filter_map(|coord| chunks.get(coord))
+        .flat_map(|chunk| &chunk.containers)
+        .filter(|container| !containers.spots.contains_key(&container.id))
+        .cloned()
+        .collect();
+    // En orden fijo: con la misma semilla, las mismas tiradas
+    appeared.sort_by_key(|container| container.id);
+    if appeared.is_empty() {
+        return;
+    }
+
+    let now = time.elapsed_secs_f64();
+    let mut tally = loot_tally(&containers, inventories.iter());
+    let containers = &mut *containers;
+    for container in appeared {
+        let biome = terrain.generator.sample(container.position.x, container.position.z).biome;
+        let saved = containers.saved.as_mut().and_then(|saved| saved.remove(&container.id));
+        let mut spot = LootSpot {
+            table: String::new(),
+            kind: container.kind,
+            position: container.position,
+            items: Vec::new(),
+            restock_at: None,
+        };
+
+        if let Some(record) = saved {
+            spot.table = match sources.tables.get(&record.table) {
+                Some(_) => record.table.clone(),
+                None => sources.tables.table_for(container.kind, biome).map(|table| table.key.clone()).unwrap_or_default(),
+            };
+            spot.items = restore_items(&record.items, &sources.defs, &mut sources.ids);
+            for stack in &spot.items {
+                *tally.entry(stack.item.clone()).or_default() += stack.quantity;
+            }
+            spot.restock_at = record.restock_at.map(|at| now + (at - Utc::now()).num_milliseconds() as f64 / 1000.0);
+        } else if let Some(table) = sources.tables.table_for(container.kind, biome).cloned() {
+            spot.table = table.key.clone();
+            spot.items = fill(&table, &mut sources, &mut containers.rng, &mut tally);
+            containers.dirty.insert(container.id);
+        }
+        debug!(container = container.id, table = %spot.table, items = spot.items.len(), "🎲 Contenedor de loot");
+        containers.spots.insert(container.id, spot);
+    }
+}
+
+/// Items guardados de un contenedor; los de tipos que ya no existen se pierden
+fn restore_items(records: &[LootItemRecord], defs: &ItemDefs, ids: &mut ItemIds) -> Vec<ItemStack> {
+    let mut items = Vec::new();
+    for record in records {
+        let Some(def) = defs.resolve(&record.item) else {
+            warn!("🎲 Item de loot de tipo desconocido: {}", record.item);
+            continue;
+        };
+        items.push(ItemStack {
+            id: ids.allocate(),
+            uuid: record.id,
+            item: def.key.clone(),
+            quantity: record.quantity.clamp(1, def.max_stack),
+            durability: record.durability,
+            ammo: record.ammo,
+            attachments: Vec::new(),
+            contents: def.grid.map(Container::from_spec),
+        });
+    }
+    items
+}
+
+/// Vuelve a tirar los contenedores cuya cuenta atrás ha terminado
+fn restock_loot(
+    mut sources: LootSources,
+    mut containers: ResMut<LootContainers>,
+    inventories: Query<&Inventory>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs_f64();
+    let due: Vec<u64> = containers
+        .spots
+        .iter()
+        .filter(|(_, spot)| spot.restock_at.is_some_and(|at| at <= now))
+        .map(|(id, _)| *id)
+        .collect();
+    if due.is_empty() {
+        return;
+    }
+
+    let mut tally = loot_tally(&containers, inventories.iter());
+    let containers = &mut *containers;
+    for id in due {
+        let Some(spot) = containers.spots.get_mut(&id) else { continue };
+        // Lo que quedaba desaparece con el relleno
+        for stack in spot.items.drain(..) {
+            if let Some(count) = tally.get_mut(&stack.item) {
+                *count = count.saturating_sub(stack.quantity);
+            }
+        }
+        spot.restock_at = None;
+        if let Some(table) = sources.tables.get(&spot.table).cloned() {
+            spot.items = fill(&table, &mut sources, &mut containers.rng, &mut tally);
+        }
+        debug!(container = id, items = spot.items.len(), "🎲 Contenedor rellenado");
+        containers.dirty.insert(id);
+    }
+}
+
+fn handle_loot_requests(
+    mut requests: EventReader<LootRequest>,
+    server_state: Res<ServerState>,
+    settings: Res<LootSettings>,
+    sources: LootSources,
+    mut containers: ResMut<LootContainers>,
+    mut inventories: Query<(&mut Inventory, &Transform)>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs_f64();
+    let mut clients = server_state.clients.lock().unwrap();
+    for request in requests.read() {
+        let Some(client) = clients.get_mut(&request.client_id) else { continue };
+        let Some(Ok((mut inventory, transform))) = client.player_entity.map(|entity| inventories.get_mut(entity)) else {
+            continue;
+        };
+
+        let (id, result) = match &request.action {
+            LootAction::Open { container } => {
+                let spot = reachable(&containers, *container, transform.translation, settings.reach);
+                (*container, spot.map(|spot| (Vec::new(), spot.items.clone())))
+            }
+            LootAction::Take { container, item } => {
+                let taken = take_loot(&mut containers, *container, *item, transform.translation, settings.reach, &mut inventory, &sources.defs);
+                if taken.is_ok() {
+                    let spot = containers.spots.get_mut(container).expect("el contenedor existe");
+                    if spot.restock_at.is_none() {
+                        spot.restock_at = sources.tables.get(&spot.table).map(|table| now + table.restock_secs as f64);
+                    }
+                    containers.dirty.insert(*container);
+                    info!(player = %client.player_name, container, item = %item, "🎲 Loot recogido");
+                }
+                (*container, taken.map(|diffs| (diffs, containers.spots[container].items.clone())))
+            }
+        };
+
+        match result {
+            Ok((diffs, items)) => {
+                let opened = matches!(request.action, LootAction::Open { .. });
+                if !opened {
+                    client.send(&ServerMessage::InventoryUpdate { sequence: Some(request.sequence), diffs });
+                }
+                client.send(&ServerMessage::LootContents { sequence: opened.then_some(request.sequence), container: id, items });
+            }
+            Err(error) => {
+                debug!(player = %client.player_name, ?request.action, %error, "🎲 Acción de loot rechazada");
+                client.send(&ServerMessage::InventoryRejected { sequence: request.sequence, reason: error.to_string() });
+            }
+        }
+    }
+}
+
+/// El contenedor, si existe y está al alcance de `from`
+fn reachable(containers: &LootContainers, id: u64, from: Vec3, reach: f32) -> Result<&LootSpot, LootError> {
+    let spot = containers.get(id).ok_or(LootError::UnknownContainer)?;
+    if spot.position.distance(from) > reach {
+        return Err(LootError::OutOfReach);
+    }
+    Ok(spot)
+}
+
+/// Pasa un item del contenedor al inventario; si no cabe se queda donde estaba
+fn take_loot(
+    containers: &mut LootContainers,
+    id: u64,
+    item: ItemId,
+    from: Vec3,
+    reach: f32,
+    inventory: &mut Inventory,
+    defs: &ItemDefs,
+) -> Result<Vec<InventoryDiff>, LootError> {
+    reachable(containers, id, from, reach)?;
+    let spot = containers.spots.get_mut(&id).ok_or(LootError::UnknownContainer)?;
+    let index = spot.items.iter().position(|stack| stack.id == item).ok_or(LootError::ItemGone)?;
+    let diffs = inventory.insert(defs, spot.items[index].clone())?;
+    spot.items.remove(index);
+    Ok(diffs)
+}
+
+fn save_loot(
+    time: Res<Time>,
+    settings: Res<LootSettings>,
+    mut containers: ResMut<LootContainers>,
+    database_channel: Res<DatabaseChannel>,
+    server_state: Res<ServerState>,
+    mut since_save: Local<Duration>,
+) {
+    *since_save += time.delta();
+    if *since_save < settings.save_interval || !containers.persist || containers.dirty.is_empty() {
+        return;
+    }
+    *since_save = Duration::ZERO;
+
+    let now = time.elapsed_secs_f64();
+    let dirty = std::mem::take(&mut containers.dirty);
+    let records: Vec<LootContainerRecord> = dirty
+        .into_iter()
+        .filter_map(|id| containers.spots.get(&id).map(|spot| (id, spot)))
+        .map(|(id, spot)| LootContainerRecord {
+            id,
+            table: spot.table.clone(),
+            restock_at: spot.restock_at.map(|at| Utc::now() + chrono::Duration::milliseconds(((at - now) * 1000.0) as i64)),
+            items: spot
+                .items
+                .iter()
+                .map(|stack| LootItemRecord {
+                    id: stack.uuid,
+                    item: stack.item.clone(),
+                    quantity: stack.quantity,
+                    durability: stack.durability,
+                    ammo: stack.ammo,
+                })
+                .collect(),
+        })
+        .collect();
+    match database_channel.sender.send(DatabaseCommand::SaveLootContainers(records)) {
+        Ok(()) => server_state.metrics.db_enqueued(),
+        Err(e) => error!("❌ Error enviando guardado de loot: {}", e),
+    }
+}
+
+/// Reparto del loot en todo el mapa: contenedores por tabla y unidades de cada
+/// item que saldrían, de media, al llenarlos todos una vez (sin topes)
+#[derive(Debug, Clone)]
+pub struct LootStats {
+    pub chunks: usize,
+    pub seed: u64,
+    /// Tiradas simuladas por tabla
+    pub samples: u32,
+    pub containers: usize,
+    /// Contenedores sin ninguna tabla que les encaje
+    pub untabled: usize,
+    /// (tabla, contenedores que la usan)
+    pub tables: Vec<(String, usize)>,
+    pub items: Vec<ItemStats>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemStats {
+    pub item: String,
+    /// Unidades esperadas con todo el mapa lleno
+    pub expected: f32,
+    /// Parte (0 a 1) de los contenedores que lo tienen
+    pub share: f32,
+    pub cap: Option<u32>,
+}
+
+/// Genera todos los chunks del mapa y simula `samples` rellenos de cada tabla
+pub fn loot_statistics(tables: &LootTables, grid: &ChunkGrid, seed: u64, samples: u32) -> LootStats {
+    let terrain = TerrainGenerator::new(seed);
+    let mut per_table: HashMap<String, usize> = HashMap::new();
+    let (mut containers, mut untabled, mut chunks) = (0, 0, 0);
+    for x in grid.coord_range() {
+        for z in grid.coord_range() {
+            chunks += 1;
+            for container in generate_chunk(grid, seed, ChunkCoord::new(x, z)).containers {
+                containers += 1;
+                let biome = terrain.sample(container.position.x, container.position.z).biome;
+                match tables.table_for(container.kind, biome) {
+                    Some(table) => *per_table.entry(table.key.clone()).or_default() += 1,
+                    None => untabled += 1,
+                }
+            }
+        }
+    }
+
+    // Por item: (unidades, contenedores con él) sumando todas las tablas
+    let samples = samples.max(1);
+    let mut totals: HashMap<String, (f64, f64)> = HashMap::new();
+    let mut rng = WorldRng::new(seed);
+    for table in tables.iter() {
+        let used = per_table.get(&table.key).copied().unwrap_or(0) as f64;
+        if used == 0.0 {
+            continue;
+        }
+        let mut units: HashMap<String, (u64, u32)> = HashMap::new();
+        for _ in 0..samples {
+            let mut seen = HashSet::new();
+            for (item, quantity) in table.roll(&mut rng, |_| u32::MAX) {
+                let entry = units.entry(item.clone()).or_default();
+                entry.0 += quantity as u64;
+                if seen.insert(item) {
+                    entry.1 += 1;
+                }
+            }
+        }
+        for (item, (quantity, present)) in units {
+            let total = totals.entry(item).or_default();
+            total.0 += quantity as f64 / samples as f64 * used;
+            total.1 += present as f64 / samples as f64 * used;
+        }
+    }
+
+    let mut items: Vec<ItemStats> = totals
+        .into_iter()
+        .map(|(item, (expected, present))| ItemStats {
+            cap: tables.cap(&item),
+            share: if containers > 0 { (present / containers as f64) as f32 } else { 0.0 },
+            expected: expected as f32,
+            item,
+        })
+        .collect();
+    items.sort_by(|a, b| b.expected.total_cmp(&a.expected).then_with(|| a.item.cmp(&b.item)));
+    let mut tables: Vec<(String, usize)> = per_table.into_iter().collect();
+    tables.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    LootStats { chunks, seed, samples, containers, untabled, tables, items }
+}
+
+impl std::fmt::Display for LootStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "🎲 Loot en {} chunks (semilla {}, {} tiradas por tabla): {} contenedores, {} sin tabla",
+            self.chunks, self.seed, self.samples, self.containers, self.untabled
+        )?;
+        writeln!(f, "{:<20} {:>12}", "tabla", "contenedores")?;
+        for (table, containers) in &self.tables {
+            writeln!(f, "{:<20} {:>12}", table, containers)?;
+        }
+        writeln!(f, "{:<20} {:>12} {:>14} {:>8}", "item", "unidades", "% contenedores", "tope")?;
+        for item in &self.items {
+            let cap = item.cap.map_or("-".to_string(), |cap| cap.to_string());
+            writeln!(f, "{:<20} {:>12.1} {:>13.1}% {:>8}", item.item, item.expected, item.share * 100.0, cap)?;
+        }
+        Ok(())
+    }
+}
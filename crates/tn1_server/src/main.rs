@@ -2,18 +2,31 @@ use bevy::prelude::*;
 use bevy::app::ScheduleRunnerPlugin;
 use std::time::Duration;
 use tn1_shared::events::*;
+use tn1_shared::chunk::ChunkGrid;
+use tn1_shared::conditioner::{LinkConditions, NetworkConditions};
+use tn1_shared::items::ItemDefs;
+use tn1_shared::loot::LootTables;
 
-mod physics;
-mod world;
-mod systems;
-mod networking;
-mod database;
-
-use physics::ServerPhysicsPlugin;
-use world::WorldPlugin;
-use systems::SystemsPlugin;
-use networking::NetworkingPlugin;
-use database::DatabasePlugin;
+use tn1_server::physics::ServerPhysicsPlugin;
+use tn1_server::world::{WorldPlugin, WorldSettings};
+use tn1_server::weather::WeatherPlugin;
+use tn1_server::chunks::ChunkPlugin;
+use tn1_server::items::{ItemRegistryPlugin, ItemSettings};
+use tn1_server::inventory::InventoryPlugin;
+use tn1_server::loot::{loot_statistics, LootPlugin, LootSettings};
+use tn1_server::drops::DropsPlugin;
+use tn1_server::trade::TradePlugin;
+use tn1_server::weapons::WeaponsPlugin;
+use tn1_server::medical::MedicalPlugin;
+use tn1_server::systems::SystemsPlugin;
+use tn1_server::networking::{NetworkingPlugin, ServerState};
+use tn1_server::lag_compensation::LagCompensationPlugin;
+use tn1_server::database::DatabasePlugin;
+use tn1_server::tls::TlsPlugin;
+use tn1_server::demo::{build_replay_app, DemoPlaybackSettings, DemoRecordPlugin};
+use tn1_server::metrics::MetricsPlugin;
+use tn1_server::chat::ChatPlugin;
+use tn1_server::voice::VoicePlugin;
 
 fn main() {
     // Cargar variables de entorno
@@ -22,13 +35,52 @@ fn main() {
     // Configurar logging
     let log_level = std::env::var("RUST_LOG")
         .unwrap_or_else(|_| "warn,tn1_server=info".to_string());
-    tracing_subscriber::fmt()
-        .with_env_filter(log_level)
-        .init();
+    // TN1_LOG_FORMAT=json: una línea JSON por evento, con los campos estructurados
+    if std::env::var("TN1_LOG_FORMAT").is_ok_and(|format| format == "json") {
+        tracing_subscriber::fmt()
+            .json()
+            .with_env_filter(log_level)
+            .init();
+    } else {
+        tracing_subscriber::fmt()
+            .with_env_filter(log_level)
+            .init();
+    }
+    
+    // `--replay <demo> [--stop-at <tick>]`: re-simula una demo sin abrir el puerto
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(path) = arg_value(&args, "--replay") {
+        let stop_at = arg_value(&args, "--stop-at").and_then(|tick| tick.parse().ok());
+        println!("🎬 Reproduciendo demo {}...", path);
+        let exit = build_replay_app(DemoPlaybackSettings { path: path.into(), stop_at }).run();
+        std::process::exit(if exit.is_success() { 0 } else { 1 });
+    }
+    
+    // `--loot-stats [--samples <n>]`: reparto del loot en el mapa, sin arrancar el servidor
+    if args.iter().any(|arg| arg == "--loot-stats") {
+        let samples = arg_value(&args, "--samples").and_then(|samples| samples.parse().ok()).unwrap_or(1000);
+        match print_loot_statistics(samples) {
+            Ok(()) => std::process::exit(0),
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    
+    // `--latency <ms> --jitter <ms> --loss <%> ...`: simula una red mala hacia todos los clientes
+    let conditions = match LinkConditions::from_args(&args) {
+        Ok(conditions) => conditions,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(2);
+        }
+    };
     
     println!("🚀 Iniciando servidor Trust-No-1...");
     
     App::new()
+        .insert_resource(NetworkConditions::new(conditions))
         .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
             Duration::from_secs_f32(1.0 / 60.0), // 60 TPS
         )))
@@ -38,11 +90,23 @@ fn main() {
         .add_event::<PlayerDespawnEvent>()
         .add_plugins((
             DatabasePlugin,
+            TlsPlugin,
             ServerPhysicsPlugin,
             NetworkingPlugin,
+            LagCompensationPlugin,
             WorldPlugin,
+            WeatherPlugin::default(),
+            ChunkPlugin::default(),
+            ItemRegistryPlugin::default(),
+            InventoryPlugin,
             SystemsPlugin,
+            DemoRecordPlugin::default(),
+            MetricsPlugin::default(),
+            ChatPlugin::default(),
+            VoicePlugin::default(),
         ))
+        // Bevy acepta como mucho 15 plugins por tupla
+        .add_plugins((LootPlugin::default(), DropsPlugin::default(), TradePlugin::default(), WeaponsPlugin::default(), MedicalPlugin))
         .add_systems(Startup, setup_server)
         .add_systems(Update, server_tick)
         .run();
@@ -53,10 +117,38 @@ fn setup_server() {
     println!("🔧 Modo: Servidor autoritativo headless");
     println!("⚡ Física: Simplificada habilitada");
     println!("🔄 TPS: 60 (Ticks por segundo)");
-    println!("🌐 Puerto: 7777 (TCP)");
-    println!("📊 Logs: Solo errores y warnings");
+    println!("🌐 Handshake: Hello/Welcome con negociación de codec");
+    println!("🔐 Transporte: TLS (rustls) salvo TN1_TLS=off");
+    println!("🧱 Mundo: chunks generados alrededor de los jugadores y enviados bajo demanda");
+    println!("🌗 Ciclo día/noche: hora del servidor replicada a los clientes (TN1_TIME_SCALE)");
+    println!("🌦️ Clima: frentes de lluvia, niebla, tormenta y nieve con viento y temperatura");
+    println!("📦 Items: catálogo en data/items/*.ron (TN1_ITEMS_PATH), recarga en caliente con TN1_ITEMS_HOT_RELOAD");
+    println!("🎲 Loot: tablas en data/loot/*.ron (TN1_LOOT_PATH), reparto con --loot-stats");
+    println!("🎒 Inventario: rejillas con rotación, montones y contenedores anidados validados por el servidor");
+    println!("💀 Suelo: items tirados con física, cadáveres con el inventario entero y recogida con línea de visión");
+    println!("🤝 Comercio: ventana de dos lados con confirmación doble, cuenta atrás y cambio atómico registrado");
+    println!("🔫 Armas: modos de fuego, cadencia, recarga y recámara; impactos con lag compensation y balas en vuelo");
+    println!("💬 Chat: global, proximidad, equipo y susurros con moderación");
+    println!("🎙️ Voz: proximidad con atenuación y radio por frecuencias");
+    println!("📊 Logs: tracing (TN1_LOG_FORMAT=json para JSON) - métricas en /metrics");
 }
 
-fn server_tick(_time: Res<Time>, _server_state: Res<crate::networking::ServerState>) {
+fn server_tick(_time: Res<Time>, _server_state: Res<ServerState>) {
     // Sin logs periódicos - servidor silencioso
-}
\ No newline at end of file
+}
+
+fn print_loot_statistics(samples: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let defs = ItemDefs::load(&ItemSettings::default().path)?;
+    let tables = LootTables::load(&LootSettings::default().path, &defs)?;
+    let world = WorldSettings::default();
+    let grid = ChunkGrid::new(world.world_size, world.chunk_size);
+    print!("{}", loot_statistics(&tables, &grid, world.seed, samples));
+    Ok(())
+}
+
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+}
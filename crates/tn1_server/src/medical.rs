@@ -0,0 +1,46 @@
+//! Salud por zonas del cuerpo con el servidor como autoridad.
+//!
+//! La vida de verdad es el `BodyHealth` de cada jugador (ver
+//! `tn1_shared::medical`); aquí se mantiene `Health` como su resumen para las
+//! snapshots, las demos y los cadáveres, y a cada jugador se le manda su
+//! `BodyHealth` cada vez que cambia para el HUD. Lo que frenan las piernas lo
+//! aplica `update_gear_modifiers` y lo que tiemblan los brazos, las armas.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use tn1_shared::components::Health;
+use tn1_shared::medical::BodyHealth;
+use tn1_shared::protocol::ServerMessage;
+use crate::networking::{NetworkingSet, ServerState};
+
+pub struct MedicalPlugin;
+
+impl Plugin for MedicalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (sync_health, send_body_health).chain().after(NetworkingSet));
+    }
+}
+
+/// `Health` sigue a `BodyHealth`: con la cabeza o el tórax a 0 se queda en 0
+fn sync_health(mut players: Query<(&BodyHealth, &mut Health), Changed<BodyHealth>>) {
+    for (body, mut health) in players.iter_mut() {
+        *health = body.health();
+    }
+}
+
+/// Cada jugador recibe su `BodyHealth` al entrar y cuando cambia
+fn send_body_health(
+    server_state: Res<ServerState>,
+    bodies: Query<&BodyHealth>,
+    mut sent: Local<HashMap<u32, BodyHealth>>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    sent.retain(|client_id, _| clients.contains_key(client_id));
+    for (client_id, client) in clients.iter_mut() {
+        let Some(body) = client.player_entity.and_then(|entity| bodies.get(entity).ok()) else { continue };
+        if sent.get(client_id) != Some(body) {
+            client.send(&ServerMessage::BodyHealth(body.clone()));
+            sent.insert(*client_id, body.clone());
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::networking::ServerState;
+
+/// Buckets de duración de tick: el presupuesto a 60 TPS es ~16.7 ms
+const TICK_BUCKETS: &[f64] = &[0.0005, 0.001, 0.002, 0.004, 0.008, 0.0167, 0.033, 0.066, 0.1];
+const DB_BUCKETS: &[f64] = &[0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+/// (dirección, tipo de mensaje) -> (mensajes, bytes)
+type MessageCounters = BTreeMap<(Direction, &'static str), (u64, u64)>;
+
+/// Dirección de un mensaje respecto al servidor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+impl Direction {
+    fn label(self) -> &'static str {
+        match self {
+            Direction::In => "in",
+            Direction::Out => "out",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.buckets.iter().zip(&mut self.counts) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, separator, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, separator, self.count);
+        let braces = |labels: &str| if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        let _ = writeln!(out, "{}_sum{} {}", name, braces(labels), self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braces(labels), self.count);
+    }
+}
+
+/// Registro de métricas del servidor, compartido entre el loop de Bevy y los threads de red
+#[derive(Debug)]
+pub struct Metrics {
+    tick_duration: Mutex<Histogram>,
+    players_connected: AtomicU64,
+    join_queue_length: AtomicU64,
+    chunks_loaded: AtomicU64,
+    messages: Mutex<MessageCounters>,
+    db_queue_depth: AtomicI64,
+    db_latency: Mutex<BTreeMap<&'static str, Histogram>>,
+    db_errors: Mutex<BTreeMap<&'static str, u64>>,
+    auth_success: AtomicU64,
+    auth_failure: AtomicU64,
+    rejected: Mutex<BTreeMap<&'static str, u64>>,
+    anticheat_flags: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            tick_duration: Mutex::new(Histogram::new(TICK_BUCKETS)),
+            players_connected: AtomicU64::new(0),
+            join_queue_length: AtomicU64::new(0),
+            chunks_loaded: AtomicU64::new(0),
+            messages: Mutex::new(BTreeMap::new()),
+            db_queue_depth: AtomicI64::new(0),
+            db_latency: Mutex::new(BTreeMap::new()),
+            db_errors: Mutex::new(BTreeMap::new()),
+            auth_success: AtomicU64::new(0),
+            auth_failure: AtomicU64::new(0),
+            rejected: Mutex::new(BTreeMap::new()),
+            anticheat_flags: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn observe_tick(&self, duration: Duration) {
+        self.tick_duration.lock().unwrap().observe(duration.as_secs_f64());
+    }
+
+    pub fn set_players(&self, players: usize, queue_length: usize) {
+        self.players_connected.store(players as u64, Ordering::Relaxed);
+        self.join_queue_length.store(queue_length as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_chunks_loaded(&self, chunks: usize) {
+        self.chunks_loaded.store(chunks as u64, Ordering::Relaxed);
+    }
+
+    /// Un frame completo (prefijo de longitud incluido) recibido o enviado
+    pub fn record_message(&self, direction: Direction, kind: &'static str, bytes: usize) {
+        let mut messages = self.messages.lock().unwrap();
+        let entry = messages.entry((direction, kind)).or_default();
+        entry.0 += 1;
+        entry.1 += bytes as u64;
+    }
+
+    pub fn db_enqueued(&self) {
+        self.db_queue_depth.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn db_dequeued(&self) {
+        self.db_queue_depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn observe_db(&self, operation: &'static str, duration: Duration, ok: bool) {
+        self.db_latency
+            .lock()
+            .unwrap()
+            .entry(operation)
+            .or_insert_with(|| Histogram::new(DB_BUCKETS))
+            .observe(duration.as_secs_f64());
+        if !ok {
+            *self.db_errors.lock().unwrap().entry(operation).or_default() += 1;
+        }
+    }
+
+    pub fn auth_succeeded(&self) {
+        self.auth_success.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn auth_failed(&self) {
+        self.auth_failure.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Conexión cerrada por el servidor antes de entrar al juego
+    pub fn connection_rejected(&self, reason: &'static str) {
+        *self.rejected.lock().unwrap().entry(reason).or_default() += 1;
+    }
+
+    pub fn anticheat_flag(&self, check: &'static str) {
+        *self.anticheat_flags.lock().unwrap().entry(check).or_default() += 1;
+    }
+
+    /// Formato de texto de Prometheus (versión 0.0.4)
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "tn1_tick_duration_seconds", "histogram", "Duración de cada tick del servidor");
+        self.tick_duration.lock().unwrap().render(&mut out, "tn1_tick_duration_seconds", "");
+
+        header(&mut out, "tn1_players_connected", "gauge", "Jugadores autenticados en el mundo");
+        let _ = writeln!(out, "tn1_players_connected {}", self.players_connected.load(Ordering::Relaxed));
+        header(&mut out, "tn1_join_queue_length", "gauge", "Clientes esperando en la cola de entrada");
+        let _ = writeln!(out, "tn1_join_queue_length {}", self.join_queue_length.load(Ordering::Relaxed));
+        header(&mut out, "tn1_chunks_loaded", "gauge", "Chunks del mundo cargados alrededor de los jugadores");
+        let _ = writeln!(out, "tn1_chunks_loaded {}", self.chunks_loaded.load(Ordering::Relaxed));
+
+        let messages = self.messages.lock().unwrap().clone();
+        header(&mut out, "tn1_messages_total", "counter", "Mensajes recibidos/enviados por tipo");
+        for ((direction, kind), (count, _)) in &messages {
+            let _ = writeln!(out, "tn1_messages_total{{direction=\"{}\",message=\"{}\"}} {}", direction.label(), kind, count);
+        }
+        header(&mut out, "tn1_message_bytes_total", "counter", "Bytes recibidos/enviados por tipo de mensaje");
+        for ((direction, kind), (_, bytes)) in &messages {
+            let _ = writeln!(out, "tn1_message_bytes_total{{direction=\"{}\",message=\"{}\"}} {}", direction.label(), kind, bytes);
+        }
+
+        header(&mut out, "tn1_db_queue_depth", "gauge", "Comandos pendientes en el worker de base de datos");
+        let _ = writeln!(out, "tn1_db_queue_depth {}", self.db_queue_depth.load(Ordering::Relaxed).max(0));
+        header(&mut out, "tn1_db_operation_duration_seconds", "histogram", "Latencia de las operaciones de base de datos");
+        for (operation, histogram) in self.db_latency.lock().unwrap().iter() {
+            histogram.render(&mut out, "tn1_db_operation_duration_seconds", &format!("operation=\"{}\"", operation));
+        }
+        header(&mut out, "tn1_db_errors_total", "counter", "Operaciones de base de datos fallidas");
+        for (operation, count) in self.db_errors.lock().unwrap().iter() {
+            let _ = writeln!(out, "tn1_db_errors_total{{operation=\"{}\"}} {}", operation, count);
+        }
+
+        header(&mut out, "tn1_auth_total", "counter", "Intentos de autenticación por resultado");
+        let _ = writeln!(out, "tn1_auth_total{{result=\"success\"}} {}", self.auth_success.load(Ordering::Relaxed));
+        let _ = writeln!(out, "tn1_auth_total{{result=\"failure\"}} {}", self.auth_failure.load(Ordering::Relaxed));
+
+        header(&mut out, "tn1_connections_rejected_total", "counter", "Conexiones rechazadas por motivo");
+        for (reason, count) in self.rejected.lock().unwrap().iter() {
+            let _ = writeln!(out, "tn1_connections_rejected_total{{reason=\"{}\"}} {}", reason, count);
+        }
+
+        header(&mut out, "tn1_anticheat_flags_total", "counter", "Correcciones del anti-cheat por comprobación");
+        for (check, count) in self.anticheat_flags.lock().unwrap().iter() {
+            let _ = writeln!(out, "tn1_anticheat_flags_total{{check=\"{}\"}} {}", check, count);
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// ---------------------------------------------------------------------------
+// Plugin y endpoint HTTP
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct MetricsSettings {
+    /// Dirección del endpoint `/metrics`; `None` solo mide sin exponer nada
+    pub address: Option<SocketAddr>,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        let address = std::env::var("TN1_METRICS_ADDR").unwrap_or_else(|_| "127.0.0.1:9100".to_string());
+        Self {
+            // `TN1_METRICS_ADDR=off` desactiva el endpoint
+            address: address.parse().ok(),
+        }
+    }
+}
+
+/// Dirección en la que escucha `/metrics` una vez arrancado
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct MetricsAddress(pub SocketAddr);
+
+#[derive(Resource)]
+struct TickStart(Instant);
+
+/// Duración de tick, jugadores y cola; expone el registro en `/metrics` si hay dirección
+#[derive(Default)]
+pub struct MetricsPlugin {
+    pub settings: MetricsSettings,
+}
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TickStart(Instant::now()))
+            .add_systems(First, start_tick_timer)
+            .add_systems(Last, finish_tick_timer);
+
+        if let Some(address) = self.settings.address {
+            app.insert_resource(PendingMetricsServer(address))
+                .add_systems(Startup, start_metrics_server);
+        }
+    }
+}
+
+#[derive(Resource)]
+struct PendingMetricsServer(SocketAddr);
+
+fn start_tick_timer(mut tick_start: ResMut<TickStart>) {
+    tick_start.0 = Instant::now();
+}
+
+fn finish_tick_timer(tick_start: Res<TickStart>, server_state: Res<ServerState>) {
+    server_state.metrics.observe_tick(tick_start.0.elapsed());
+
+    // Siempre se bloquea `clients` antes que `join_queue`
+    let clients = server_state.clients.lock().unwrap();
+    let queue = server_state.join_queue.lock().unwrap();
+    let players = clients.values().filter(|client| client.player_id.is_some()).count();
+    server_state.metrics.set_players(players, queue.len());
+}
+
+fn start_metrics_server(mut commands: Commands, pending: Res<PendingMetricsServer>, server_state: Res<ServerState>) {
+    let listener = match TcpListener::bind(pending.0) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("❌ No se pudo abrir el endpoint de métricas en {}: {}", pending.0, e);
+            return;
+        }
+    };
+    let local_addr = listener.local_addr().unwrap_or(pending.0);
+    commands.insert_resource(MetricsAddress(local_addr));
+    commands.remove_resource::<PendingMetricsServer>();
+    println!("📈 Métricas en http://{}/metrics", local_addr);
+
+    let metrics = server_state.metrics.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = serve_metrics(stream, &metrics) {
+                debug!("Petición de métricas fallida: {}", e);
+            }
+        }
+    });
+}
+
+/// HTTP mínimo: solo `GET /metrics`, una petición por conexión
+fn serve_metrics(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", metrics.render()),
+        (Some("GET"), _) => ("404 Not Found", "text/plain; charset=utf-8", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Method not allowed\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
@@ -1,12 +1,33 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use tn1_shared::{components::*, protocol::*};
-use std::net::{TcpListener, TcpStream, SocketAddr};
+use tn1_shared::{codec::*, components::*, constants::{PLAYER_MAX_HEALTH, RTT_PING_INTERVAL}, protocol::*, transport::SecureStream};
+use tn1_shared::chunk::ChunkGrid;
+use tn1_shared::conditioner::{LinkConditioner, NetworkConditions};
+use std::net::{TcpListener, TcpStream};
 use std::io::{Read, Write};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Instant;
-use crate::database::Database;
+use std::time::{Duration, Instant};
+use crate::chat::ChatRequest;
+use crate::chunks::ChunkRequest;
+use crate::inventory::{inventory_records, InventoryRequest, PersistedInventory};
+use crate::loot::LootRequest;
+use crate::drops::PickupRequest;
+use crate::trade::TradeRequest;
+use crate::weapons::WeaponInput;
+use tn1_shared::inventory::Inventory;
+use tn1_shared::medical::BodyHealth;
+use crate::voice::{VoiceFrame, VoiceRequest};
+use crate::config::ServerConfig;
+use crate::database::{ChatLogEntry, ItemRecord, LootContainerRecord, TradeRecord};
+use crate::database::{PlayerStorage, PlayerStore};
+use crate::demo::{DemoPlayback, DemoRecorder};
+use serde::{Deserialize, Serialize};
+use crate::metrics::{Direction, Metrics};
+use crate::physics::{ground_height, world_half_size, STEP_DOWN};
+use crate::world::WorldTerrain;
+use crate::tls::ServerTls;
 use std::sync::mpsc::{self, Receiver, Sender};
 
 // Estructura para comandos de base de datos
@@ -16,18 +37,69 @@ pub enum DatabaseCommand {
         player_id: uuid::Uuid,
         position: Vec3,
         rotation: Quat,
+        /// Inventario completo; `None` deja el guardado como está
+        items: Option<Vec<ItemRecord>>,
     },
+    /// El resultado vuelve por `DatabaseChannel::inventories`
+    LoadInventory {
+        player_id: uuid::Uuid,
+    },
+    SaveLootContainers(Vec<LootContainerRecord>),
+    /// El resultado vuelve por `DatabaseChannel::loot`
+    LoadLootContainers,
+    LogChatMessage(ChatLogEntry),
+    /// Comercio hecho: inventarios y registro en una transacción
+    SaveTrade(TradeRecord),
+    /// Login con contraseña: autentica la cuenta o la crea si el nombre es nuevo.
+    /// El resultado vuelve por `DatabaseChannel::accounts`
     AuthenticatePlayer {
+        client_id: u32,
         username: String,
         password: String,
-        client_id: u32,
     },
 }
 
+/// Lo que hace falta de un jugador para guardarlo
+type SavedPlayer<'a> = (&'a Transform, &'a PlayerId, Option<&'a Inventory>, Has<PersistedInventory>);
+
+/// Jugadores a los que llegan inputs y desconexiones
+type ClientPlayers<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Transform, &'static mut PlayerController, &'static PlayerId, Option<&'static Inventory>, Has<PersistedInventory>),
+>;
+
+/// Inventario leído por el worker (o el error), para el jugador dado
+pub type LoadedInventory = (uuid::Uuid, Result<Vec<ItemRecord>, String>);
+
+/// Contenedores de loot leídos por el worker (o el error)
+pub type LoadedLoot = Result<Vec<LootContainerRecord>, String>;
+
+/// Cuenta con la que entra un cliente: su id, `None` si se rechazan las
+/// credenciales, o el error de la base de datos
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResolvedAccount {
+    pub client_id: u32,
+    pub username: String,
+    pub account: Result<Option<uuid::Uuid>, String>,
+}
+
 #[derive(Resource)]
 pub struct DatabaseChannel {
     pub sender: Sender<DatabaseCommand>,
     pub receiver: Arc<Mutex<Receiver<DatabaseCommand>>>,
+    /// Inventarios cargados por el worker; los recoge `InventoryPlugin`
+    pub inventories: Arc<Mutex<Vec<LoadedInventory>>>,
+    /// Estado guardado de los contenedores de loot; lo recoge `LootPlugin`
+    pub loot: Arc<Mutex<Option<LoadedLoot>>>,
+    /// Logins resueltos (por el worker o al momento sin base de datos); los recoge `finish_joins`
+    pub accounts: Arc<Mutex<Vec<ResolvedAccount>>>,
+}
+
+impl Default for DatabaseChannel {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DatabaseChannel {
@@ -36,6 +108,9 @@ impl DatabaseChannel {
         Self {
             sender,
             receiver: Arc::new(Mutex::new(receiver)),
+            inventories: Arc::new(Mutex::new(Vec::new())),
+            loot: Arc::new(Mutex::new(None)),
+            accounts: Arc::new(Mutex::new(Vec::new())),
         }
     }
 }
@@ -44,27 +119,55 @@ pub struct NetworkingPlugin;
 
 impl Plugin for NetworkingPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ServerState::new())
+        app.init_resource::<ServerConfig>()
+            .init_resource::<NetworkConditions>() // Red perfecta salvo `--latency`, `--loss`...
+            .insert_resource(ServerState::new())
             .insert_resource(ServerTick(0))
             .insert_resource(SaveTimer(Timer::from_seconds(5.0, TimerMode::Repeating))) // Guardar cada 5 segundos
+            .insert_resource(PingTimer(Timer::from_seconds(RTT_PING_INTERVAL, TimerMode::Repeating)))
             .insert_resource(DatabaseChannel::new()) // Insertar el canal de base de datos
-            .add_systems(Startup, (start_server, start_database_worker))
+            .add_event::<ChatRequest>() // Los procesa `ChatPlugin` si está activo
+            .add_event::<VoiceRequest>() // Los procesa `VoicePlugin` si está activo
+            .add_event::<ChunkRequest>() // Los procesa `ChunkPlugin` si está activo
+            .add_event::<InventoryRequest>() // Los procesa `InventoryPlugin` si está activo
+            .add_event::<LootRequest>() // Los procesa `LootPlugin` si está activo
+            .add_event::<PickupRequest>() // Los procesa `DropsPlugin` si está activo
+            .add_event::<TradeRequest>() // Los procesa `TradePlugin` si está activo
+            .add_event::<WeaponInput>() // Los procesa `WeaponsPlugin` si está activo
+            // Al reproducir una demo los mensajes salen del archivo, no de la red
+            .add_systems(Startup, (
+                start_server.run_if(not(resource_exists::<DemoPlayback>)),
+                start_database_worker,
+            ))
             .add_systems(Update, (
                 process_client_messages,
+                process_join_queue,
+                finish_joins,
                 update_physics,
                 send_world_state,
+                send_rtt_pings,
                 save_player_positions, // Nuevo sistema
-            ).chain());
+            ).chain().in_set(NetworkingSet));
     }
 }
 
+/// Sistemas del loop de red; el resto de la simulación se ordena respecto a ellos
+/// para que un tick dé siempre el mismo resultado (necesario para re-simular demos)
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NetworkingSet;
+
 fn start_database_worker(
-    database: Option<Res<Database>>,
+    database: Option<Res<PlayerStorage>>,
     database_channel: Res<DatabaseChannel>,
+    server_state: Res<ServerState>,
 ) {
     if let Some(db) = database {
-        let db_clone = db.clone();
+        let store = db.0.clone();
         let receiver = database_channel.receiver.clone();
+        let inventories = database_channel.inventories.clone();
+        let loot = database_channel.loot.clone();
+        let accounts = database_channel.accounts.clone();
+        let metrics = server_state.metrics.clone();
         
         thread::spawn(move || {
             info!("🔄 Worker de base de datos iniciado");
@@ -73,12 +176,15 @@ fn start_database_worker(
                 if let Ok(receiver_lock) = receiver.lock() {
                     match receiver_lock.recv() {
                         Ok(cmd) => {
+                            metrics.db_dequeued();
+                            let started = Instant::now();
+                            
                             // Procesar comando de base de datos
                             match cmd {
-                                DatabaseCommand::SavePlayerPosition { player_id, position, rotation } => {
-                                    // Crear un runtime de Tokio para la operación asíncrona
-                                    let rt = tokio::runtime::Runtime::new().unwrap();
-                                    match rt.block_on(db_clone.save_player_state(player_id, position, rotation)) {
+                                DatabaseCommand::SavePlayerPosition { player_id, position, rotation, items } => {
+                                    let result = store.save_player_state(player_id, position, rotation, items.as_deref());
+                                    metrics.observe_db("save_player_state", started.elapsed(), result.is_ok());
+                                    match result {
                                         Ok(_) => {
                                             debug!("✅ Posición guardada para jugador: {:?}", player_id);
                                         }
@@ -87,21 +193,53 @@ fn start_database_worker(
                                         }
                                     }
                                 }
-                                DatabaseCommand::AuthenticatePlayer { username, password, client_id } => {
-                                    let rt = tokio::runtime::Runtime::new().unwrap();
-                                    match rt.block_on(db_clone.authenticate_player(&username, &password)) {
-                                        Ok(Some(player)) => {
-                                            info!("✅ Jugador autenticado: {} (ID: {})", username, player.id);
-                                            // TODO: Enviar resultado de vuelta al sistema de networking
-                                        }
-                                        Ok(None) => {
-                                            warn!("❌ Credenciales inválidas para: {}", username);
-                                        }
-                                        Err(e) => {
-                                            error!("❌ Error autenticando jugador: {}", e);
-                                        }
+                                DatabaseCommand::LoadInventory { player_id } => {
+                                    let result = store.load_inventory(player_id);
+                                    metrics.observe_db("load_inventory", started.elapsed(), result.is_ok());
+                                    if let Err(e) = &result {
+                                        error!("❌ Error cargando inventario de {}: {}", player_id, e);
+                                    }
+                                    inventories.lock().unwrap().push((player_id, result.map_err(|e| e.to_string())));
+                                }
+                                DatabaseCommand::SaveLootContainers(containers) => {
+                                    let result = store.save_loot_containers(&containers);
+                                    metrics.observe_db("save_loot_containers", started.elapsed(), result.is_ok());
+                                    match result {
+                                        Ok(()) => debug!("✅ {} contenedores de loot guardados", containers.len()),
+                                        Err(e) => error!("❌ Error guardando contenedores de loot: {}", e),
+                                    }
+                                }
+                                DatabaseCommand::LoadLootContainers => {
+                                    let result = store.load_loot_containers();
+                                    metrics.observe_db("load_loot_containers", started.elapsed(), result.is_ok());
+                                    if let Err(e) = &result {
+                                        error!("❌ Error cargando contenedores de loot: {}", e);
+                                    }
+                                    *loot.lock().unwrap() = Some(result.map_err(|e| e.to_string()));
+                                }
+                                DatabaseCommand::LogChatMessage(entry) => {
+                                    let result = store.log_chat_message(&entry);
+                                    metrics.observe_db("log_chat_message", started.elapsed(), result.is_ok());
+                                    if let Err(e) = result {
+                                        error!("❌ Error registrando mensaje de chat: {}", e);
                                     }
                                 }
+                                DatabaseCommand::SaveTrade(trade) => {
+                                    let result = store.save_trade(&trade);
+                                    metrics.observe_db("save_trade", started.elapsed(), result.is_ok());
+                                    match result {
+                                        Ok(()) => debug!("✅ Comercio entre {} y {} guardado", trade.usernames[0], trade.usernames[1]),
+                                        Err(e) => error!("❌ Error guardando comercio: {}", e),
+                                    }
+                                }
+                                DatabaseCommand::AuthenticatePlayer { client_id, username, password } => {
+                                    let account = resolve_account(store.as_ref(), &username, &password, &metrics);
+                                    accounts.lock().unwrap().push(ResolvedAccount {
+                                        client_id,
+                                        username,
+                                        account: account.map_err(|e| e.to_string()),
+                                    });
+                                }
                             }
                         }
                         Err(_) => {
@@ -122,239 +260,420 @@ fn start_database_worker(
 #[derive(Resource)]
 pub struct ServerTick(pub u32);
 
+/// Dirección en la que escucha el servidor una vez arrancado
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ListenAddress(pub std::net::SocketAddr);
+
 #[derive(Resource)]
 pub struct SaveTimer(pub Timer);
 
+/// Cada cuánto el servidor mide el RTT de los clientes
 #[derive(Resource)]
+pub struct PingTimer(pub Timer);
+
+/// Estado compartido con los threads de red; clonarlo solo clona los `Arc`
+#[derive(Resource, Clone)]
 pub struct ServerState {
     pub clients: Arc<Mutex<HashMap<u32, ClientConnection>>>,
     pub next_client_id: Arc<Mutex<u32>>,
     pub incoming_messages: Arc<Mutex<Vec<(u32, ClientMessage)>>>,
+    /// Clientes autenticándose mientras el servidor está lleno
+    pub join_queue: Arc<Mutex<VecDeque<PendingJoin>>>,
+    pub metrics: Arc<Metrics>,
 }
 
 pub struct ClientConnection {
-    pub stream: TcpStream,
+    /// `None` en clientes simulados por la reproducción de demos
+    pub stream: Option<SecureStream>,
+    pub codec: WireCodec,
     pub player_entity: Option<Entity>,
     pub player_id: Option<PlayerId>,
     pub player_name: String,
+    /// Login en curso (cuenta pendiente del worker): ya ocupa un hueco del servidor
+    pub authenticating: bool,
+    /// Equipo/clan elegido con `JoinTeam` (chat de equipo)
+    pub team: Option<String>,
+    /// Frecuencia de radio sintonizada con `TuneRadio`
+    pub radio: Option<u16>,
     pub last_ping: Instant,
+    /// RTT suavizado medido por el servidor (segundos), usado por la lag compensation
+    pub rtt: f32,
+    pub metrics: Arc<Metrics>,
+    /// Simulador de red de salida; los frames se escriben en `stream` desde su thread
+    pub link: Option<LinkConditioner<Vec<u8>>>,
+}
+
+impl ClientConnection {
+    /// Envía un mensaje usando el codec negociado en el handshake
+    pub fn send(&mut self, message: &ServerMessage) {
+        let Some(link) = &self.link else {
+            if let Some(stream) = self.stream.as_mut() {
+                send_message_to_stream(stream, self.codec, message, &self.metrics);
+            }
+            return;
+        };
+
+        match self.codec.encode_frame(message) {
+            Ok(data) => {
+                let bytes = data.len();
+                if link.send(data, bytes, message.is_droppable()).is_ok() {
+                    self.metrics.record_message(Direction::Out, message.kind(), bytes);
+                }
+            }
+            Err(e) => error!("❌ Error serializando mensaje: {}", e),
+        }
+    }
+
+    /// Cierra la conexión cuando ha salido lo ya enviado, que el simulador de red
+    /// puede tener aún en vuelo; lo que se envíe después se pierde
+    pub fn close(&mut self) {
+        let Some(mut stream) = self.stream.take() else { return };
+        let mut shutdown = move || {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        };
+        match self.link.take() {
+            Some(link) => link.close(shutdown),
+            None => shutdown(),
+        }
+    }
+}
+
+/// Petición de autenticación que espera un hueco libre
+pub struct PendingJoin {
+    pub client_id: u32,
+    pub username: String,
+    pub password: Option<String>,
+    pub session_token: Option<String>,
 }
 
 impl ServerState {
+    /// RTT del cliente que controla la entidad
+    pub fn rtt_for_entity(&self, entity: Entity) -> Option<f32> {
+        let clients = self.clients.lock().unwrap();
+        clients.values()
+            .find(|client| client.player_entity == Some(entity))
+            .map(|client| client.rtt)
+    }
+    
     fn new() -> Self {
         Self {
             clients: Arc::new(Mutex::new(HashMap::new())),
             next_client_id: Arc::new(Mutex::new(1)),
             incoming_messages: Arc::new(Mutex::new(Vec::new())),
+            join_queue: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: Arc::new(Metrics::default()),
         }
     }
 }
 
-fn start_server(server_state: Res<ServerState>) {
-    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT)))
+fn start_server(
+    mut commands: Commands,
+    server_state: Res<ServerState>,
+    config: Res<ServerConfig>,
+    tls: Res<ServerTls>,
+    conditions: Res<NetworkConditions>,
+) {
+    let listener = TcpListener::bind(config.bind_address)
         .expect("Failed to bind server");
+    // Con puerto 0 el sistema operativo elige uno libre (tests en paralelo)
+    let local_addr = listener.local_addr().unwrap_or(config.bind_address);
+    commands.insert_resource(ListenAddress(local_addr));
     
-    println!("🌐 Servidor autoritativo iniciado en puerto {}", DEFAULT_PORT);
+    println!("🌐 Servidor autoritativo iniciado en {}", local_addr);
+    println!("🏷️ {} - mapa {} - máx. {} jugadores", config.server_name, config.map_name, config.max_players);
     println!("📊 Tick rate: {} Hz", TICK_RATE);
     
-    let clients = server_state.clients.clone();
-    let next_id = server_state.next_client_id.clone();
-    let incoming = server_state.incoming_messages.clone();
+    let state = server_state.clone();
+    let config = Arc::new(config.clone());
+    let tls = tls.0.clone();
+    let conditions = conditions.clone();
+    if conditions.get().is_active() {
+        println!("📶 Simulando red: {:?}", conditions.get());
+    }
     
     thread::spawn(move || {
-        for stream in listener.incoming() {
-            if let Ok(stream) = stream {
-                // Configurar stream
-                stream.set_nodelay(true).ok();
-                stream.set_nonblocking(false).ok();
-                
-                // Obtener nuevo ID
-                let mut id_lock = next_id.lock().unwrap();
-                let client_id = *id_lock;
-                *id_lock += 1;
-                drop(id_lock);
-                
-                println!("🔌 Nueva conexión TCP - Cliente ID: {}", client_id);
-                
-                // Manejar cliente en thread separado
-                let clients_clone = clients.clone();
-                let incoming_clone = incoming.clone();
-                
-                thread::spawn(move || {
-                    handle_client_connection(
-                        stream,
-                        client_id,
-                        clients_clone,
-                        incoming_clone,
-                    );
-                });
-            }
+        for stream in listener.incoming().flatten() {
+            // Configurar stream
+            stream.set_nodelay(true).ok();
+            stream.set_nonblocking(false).ok();
+            
+            // Obtener nuevo ID
+            let mut id_lock = state.next_client_id.lock().unwrap();
+            let client_id = *id_lock;
+            *id_lock += 1;
+            drop(id_lock);
+            
+            info!(client_id, "🔌 Nueva conexión TCP");
+            
+            // Manejar cliente en thread separado
+            let state_clone = state.clone();
+            let config_clone = config.clone();
+            let tls_clone = tls.clone();
+            let conditions_clone = conditions.clone();
+            
+            thread::spawn(move || {
+                handle_client_connection(stream, client_id, state_clone, config_clone, tls_clone, conditions_clone);
+            });
         }
     });
 }
 
+/// Construye la información pública del servidor a partir del estado compartido
+fn build_server_info(
+    config: &ServerConfig,
+    clients: &HashMap<u32, ClientConnection>,
+    queue_length: usize,
+) -> ServerInfo {
+    ServerInfo {
+        server_name: config.server_name.clone(),
+        map_name: config.map_name.clone(),
+        player_count: clients.values().filter(|c| c.player_id.is_some()).count() as u32,
+        max_players: config.max_players,
+        queue_length: queue_length as u32,
+        tick_rate: TICK_RATE,
+        protocol_min: MIN_PROTOCOL_VERSION,
+        protocol_max: PROTOCOL_VERSION,
+        codecs: WireCodec::SUPPORTED.to_vec(),
+    }
+}
+
 fn handle_client_connection(
-    mut stream: TcpStream,
+    socket: TcpStream,
     client_id: u32,
-    clients: Arc<Mutex<HashMap<u32, ClientConnection>>>,
-    incoming: Arc<Mutex<Vec<(u32, ClientMessage)>>>,
+    state: ServerState,
+    config: Arc<ServerConfig>,
+    tls: Option<Arc<rustls::ServerConfig>>,
+    conditions: NetworkConditions,
 ) {
+    let ServerState { clients, incoming_messages, join_queue, metrics, .. } = state;
+    // Los mensajes recibidos pasan por el simulador de red antes de llegar al loop principal
+    let incoming = LinkConditioner::new(conditions.clone(), move |message: (u32, ClientMessage)| {
+        incoming_messages.lock().unwrap().push(message);
+        Ok(())
+    });
+
+    // Handshake TLS con timeout para que un cliente colgado no retenga el thread
+    socket.set_read_timeout(Some(Duration::from_secs(5))).ok();
+    let mut stream = match tls {
+        Some(tls_config) => match SecureStream::accept(socket, tls_config) {
+            Ok(stream) => stream,
+            Err(e) => {
+                metrics.connection_rejected("tls_handshake");
+                warn!(client_id, error = %e, "❌ Handshake TLS fallido");
+                return;
+            }
+        },
+        None => SecureStream::plain(socket),
+    };
+    stream.socket().set_read_timeout(None).ok();
+    
     let mut buffer = vec![0u8; 4096];
-    let mut message_buffer = Vec::new();
+    let mut frames = FrameBuffer::new();
+    // Hasta recibir `Hello` solo se habla JSON
+    let mut codec: Option<WireCodec> = None;
     
     loop {
         match stream.read(&mut buffer) {
             Ok(0) => {
                 // Cliente desconectado
-                println!("📤 Cliente {} desconectado", client_id);
-                incoming.lock().unwrap().push((client_id, ClientMessage::Disconnect));
-                clients.lock().unwrap().remove(&client_id);
+                info!(client_id, "📤 Cliente desconectado");
                 break;
             }
             Ok(n) => {
-                message_buffer.extend_from_slice(&buffer[..n]);
+                frames.extend(&buffer[..n]);
                 
                 // Procesar mensajes completos
-                while message_buffer.len() >= 4 {
-                    let msg_len = u32::from_be_bytes([
-                        message_buffer[0],
-                        message_buffer[1],
-                        message_buffer[2],
-                        message_buffer[3],
-                    ]) as usize;
+                loop {
+                    let payload = match frames.next_frame() {
+                        Ok(Some(payload)) => payload,
+                        Ok(None) => break,
+                        Err(e) => {
+                            metrics.connection_rejected("invalid_frame");
+                            warn!(client_id, error = %e, "❌ Frame inválido");
+                            close_connection(client_id, codec.is_some(), &incoming);
+                            return;
+                        }
+                    };
                     
-                    if message_buffer.len() >= 4 + msg_len {
-                        // Tenemos un mensaje completo
-                        if let Ok(json_str) = std::str::from_utf8(&message_buffer[4..4 + msg_len]) {
-                            if let Ok(msg) = serde_json::from_str::<ClientMessage>(json_str) {
-                                // Manejar mensajes de autenticación
-                                match &msg {
-                                    ClientMessage::Login { protocol_version, username, .. } => {
-                                        if *protocol_version != PROTOCOL_VERSION {
-                                            // Versión incorrecta
-                                            let error = ServerMessage::ConnectionError {
-                                                reason: format!("Versión de protocolo incorrecta. Servidor: {}, Cliente: {}", 
-                                                    PROTOCOL_VERSION, protocol_version)
-                                            };
-                                            send_message_to_stream(&mut stream, &error);
-                                            break;
-                                        }
-                                        
-                                        // Agregar cliente
-                                        let mut clients_lock = clients.lock().unwrap();
-                                        clients_lock.insert(client_id, ClientConnection {
-                                            stream: stream.try_clone().unwrap(),
-                                            player_entity: None,
-                                            player_id: None,
-                                            player_name: username.clone(),
-                                            last_ping: Instant::now(),
-                                        });
-                                        drop(clients_lock);
-                                        
-                                        println!("✅ Cliente {} conectado: {}", client_id, username);
-                                    }
-                                    ClientMessage::Register { protocol_version, username, .. } => {
-                                        if *protocol_version != PROTOCOL_VERSION {
-                                            // Versión incorrecta
-                                            let error = ServerMessage::ConnectionError {
-                                                reason: format!("Versión de protocolo incorrecta. Servidor: {}, Cliente: {}", 
-                                                    PROTOCOL_VERSION, protocol_version)
-                                            };
-                                            send_message_to_stream(&mut stream, &error);
-                                            break;
-                                        }
-                                        
-                                        // Agregar cliente
-                                        let mut clients_lock = clients.lock().unwrap();
-                                        clients_lock.insert(client_id, ClientConnection {
-                                            stream: stream.try_clone().unwrap(),
-                                            player_entity: None,
-                                            player_id: None,
-                                            player_name: username.clone(),
-                                            last_ping: Instant::now(),
-                                        });
-                                        drop(clients_lock);
-                                        
-                                        println!("✅ Cliente {} conectado: {}", client_id, username);
-                                    }
-                                    ClientMessage::Reconnect { protocol_version, .. } => {
-                                        if *protocol_version != PROTOCOL_VERSION {
-                                            // Versión incorrecta
-                                            let error = ServerMessage::ConnectionError {
-                                                reason: format!("Versión de protocolo incorrecta. Servidor: {}, Cliente: {}", 
-                                                    PROTOCOL_VERSION, protocol_version)
-                                            };
-                                            send_message_to_stream(&mut stream, &error);
-                                            break;
-                                        }
-                                        
-                                        let player_name = format!("Player_{}", client_id);
-                                        
-                                        // Agregar cliente
-                                        let mut clients_lock = clients.lock().unwrap();
-                                        clients_lock.insert(client_id, ClientConnection {
-                                            stream: stream.try_clone().unwrap(),
-                                            player_entity: None,
-                                            player_id: None,
-                                            player_name: player_name.clone(),
-                                            last_ping: Instant::now(),
-                                        });
-                                        drop(clients_lock);
-                                        
-                                        println!("✅ Cliente {} reconectado: {}", client_id, player_name);
-                                    }
-                                    _ => {}
-                                }
-                                
-                                // Agregar mensaje a la cola
-                                incoming.lock().unwrap().push((client_id, msg));
+                    let msg = match codec.unwrap_or_default().decode::<ClientMessage>(&payload) {
+                        Ok(msg) => msg,
+                        Err(e) => {
+                            metrics.record_message(Direction::In, "invalid", payload.len() + 4);
+                            warn!(client_id, error = %e, "⚠️ Mensaje ilegible");
+                            continue;
+                        }
+                    };
+                    metrics.record_message(Direction::In, msg.kind(), payload.len() + 4);
+                    
+                    if codec.is_some() {
+                        match msg {
+                            ClientMessage::Hello { .. } | ClientMessage::StatusQuery => {
+                                warn!("⚠️ Cliente {} repitió el handshake, ignorado", client_id);
+                            }
+                            // Agregar mensaje a la cola
+                            msg => {
+                                let droppable = msg.is_droppable();
+                                let _ = incoming.send((client_id, msg), payload.len() + 4, droppable);
                             }
                         }
-                        
-                        // Remover mensaje procesado del buffer
-                        message_buffer.drain(0..4 + msg_len);
-                    } else {
-                        // Mensaje incompleto, esperar más datos
-                        break;
+                        continue;
+                    }
+                    
+                    // Handshake: el primer mensaje debe ser Hello o StatusQuery
+                    let server_info = {
+                        let clients_lock = clients.lock().unwrap();
+                        let queue_length = join_queue.lock().unwrap().len();
+                        build_server_info(&config, &clients_lock, queue_length)
+                    };
+                    
+                    match msg {
+                        ClientMessage::StatusQuery => {
+                            send_message_to_stream(&mut stream, WireCodec::Json, &ServerMessage::ServerInfo(server_info), &metrics);
+                            info!(client_id, "🔎 Consulta de estado respondida");
+                            return;
+                        }
+                        ClientMessage::Hello { protocol_version, client_name, codecs } => {
+                            if !server_info.accepts_protocol(protocol_version) {
+                                let error = ServerMessage::ConnectionError {
+                                    reason: format!("Versión de protocolo incorrecta. Servidor: {}-{}, Cliente: {}", 
+                                        MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, protocol_version)
+                                };
+                                send_message_to_stream(&mut stream, WireCodec::Json, &error, &metrics);
+                                metrics.connection_rejected("protocol_version");
+                                return;
+                            }
+                            
+                            let Some(selected) = WireCodec::negotiate(&server_info.codecs, &codecs) else {
+                                let error = ServerMessage::ConnectionError {
+                                    reason: "No hay ningún codec en común con el servidor".to_string(),
+                                };
+                                send_message_to_stream(&mut stream, WireCodec::Json, &error, &metrics);
+                                metrics.connection_rejected("no_common_codec");
+                                return;
+                            };
+                            
+                            // Welcome sale antes de registrar al cliente para que nada
+                            // le llegue con el codec nuevo antes de tiempo
+                            let welcome = ServerMessage::Welcome { codec: selected, server_info };
+                            send_message_to_stream(&mut stream, WireCodec::Json, &welcome, &metrics);
+                            
+                            let mut writer = stream.try_clone().unwrap();
+                            let link = LinkConditioner::new(conditions.clone(), move |data: Vec<u8>| writer.write_all(&data));
+                            
+                            // Agregar cliente
+                            let mut clients_lock = clients.lock().unwrap();
+                            clients_lock.insert(client_id, ClientConnection {
+                                stream: Some(stream.try_clone().unwrap()),
+                                codec: selected,
+                                player_entity: None,
+                                player_id: None,
+                                player_name: client_name.clone(),
+                                authenticating: false,
+                                team: None,
+                                radio: None,
+                                last_ping: Instant::now(),
+                                rtt: 0.0,
+                                metrics: metrics.clone(),
+                                link: Some(link),
+                            });
+                            drop(clients_lock);
+                            
+                            codec = Some(selected);
+                            info!(client_id, client_name = %client_name, codec = ?selected, "🤝 Handshake completo");
+                        }
+                        _ => {
+                            let error = ServerMessage::ConnectionError {
+                                reason: "Se esperaba Hello como primer mensaje".to_string(),
+                            };
+                            send_message_to_stream(&mut stream, WireCodec::Json, &error, &metrics);
+                            metrics.connection_rejected("expected_hello");
+                            return;
+                        }
                     }
                 }
             }
             Err(e) => {
                 if e.kind() != std::io::ErrorKind::WouldBlock {
-                    println!("❌ Error leyendo de cliente {}: {}", client_id, e);
-                    incoming.lock().unwrap().push((client_id, ClientMessage::Disconnect));
-                    clients.lock().unwrap().remove(&client_id);
+                    warn!(client_id, error = %e, "❌ Error leyendo del cliente");
                     break;
                 }
             }
         }
     }
+    
+    close_connection(client_id, codec.is_some(), &incoming);
+}
+
+/// Notifica la desconexión al loop principal si el cliente llegó a registrarse.
+///
+/// El cliente se saca de `clients` al procesar el `Disconnect`: si se quitara aquí,
+/// el loop principal ya no encontraría su entidad y el jugador quedaría en el mundo.
+fn close_connection(
+    client_id: u32,
+    registered: bool,
+    incoming: &LinkConditioner<(u32, ClientMessage)>,
+) {
+    if registered {
+        // Sin descartar: llega después de lo que aún estuviera en vuelo
+        let _ = incoming.send((client_id, ClientMessage::Disconnect), 0, false);
+    }
+}
+
+/// Lo que hace falta para admitir jugadores (al llegar o al salir de la cola)
+#[derive(SystemParam)]
+struct Admission<'w> {
+    server_state: Res<'w, ServerState>,
+    config: Res<'w, ServerConfig>,
+    database: Option<Res<'w, PlayerStorage>>,
+    database_channel: Res<'w, DatabaseChannel>,
+    /// Al reproducir una demo las cuentas salen del archivo
+    playback: Option<Res<'w, DemoPlayback>>,
+}
+
+/// Peticiones que se reenvían a los plugins de cada sistema de juego
+#[derive(SystemParam)]
+struct FeatureRequests<'w> {
+    chat: EventWriter<'w, ChatRequest>,
+    voice: EventWriter<'w, VoiceRequest>,
+    chunks: EventWriter<'w, ChunkRequest>,
+    inventory: EventWriter<'w, InventoryRequest>,
+    loot: EventWriter<'w, LootRequest>,
+    pickups: EventWriter<'w, PickupRequest>,
+    trades: EventWriter<'w, TradeRequest>,
+    weapons: EventWriter<'w, WeaponInput>,
 }
 
 fn process_client_messages(
     mut commands: Commands,
-    server_state: Res<ServerState>,
-    mut player_query: Query<(&mut Transform, &mut PlayerController, &PlayerId)>,
-    database: Option<Res<Database>>,
-    database_channel: Res<DatabaseChannel>,
+    admission: Admission,
+    mut player_query: ClientPlayers,
+    time: Res<Time>,
+    recorder: Option<ResMut<DemoRecorder>>,
+    mut requests: FeatureRequests,
 ) {
+    let server_state = &admission.server_state;
     let messages = {
         let mut incoming_lock = server_state.incoming_messages.lock().unwrap();
         std::mem::take(&mut *incoming_lock)
     };
     
+    // Se graba exactamente lo que procesa este tick, en el mismo orden
+    if let Some(mut recorder) = recorder {
+        recorder.record_messages(&messages);
+    }
+
     for (client_id, message) in messages {
         match message {
             // Temporalmente manejar los tres tipos de autenticación hasta actualizar cliente
-            ClientMessage::Login { username, password, .. } => {
-                handle_auth(&mut commands, &server_state, client_id, username, Some(password), None, database.as_deref(), database_channel.sender.clone());
+            ClientMessage::Login { username, password } => {
+                request_join(&admission, PendingJoin { client_id, username, password: Some(password), session_token: None });
             }
             ClientMessage::Register { username, password, .. } => {
-                handle_auth(&mut commands, &server_state, client_id, username, Some(password), None, database.as_deref(), database_channel.sender.clone());
+                request_join(&admission, PendingJoin { client_id, username, password: Some(password), session_token: None });
             }
-            ClientMessage::Reconnect { session_token, .. } => {
+            ClientMessage::Reconnect { session_token } => {
                 let username = format!("Player_{}", client_id);
-                handle_auth(&mut commands, &server_state, client_id, username, None, Some(session_token), database.as_deref(), database_channel.sender.clone());
+                request_join(&admission, PendingJoin { client_id, username, password: None, session_token: Some(session_token) });
             }
             
             ClientMessage::PlayerInput { input, .. } => {
@@ -362,18 +681,35 @@ fn process_client_messages(
                 let clients = server_state.clients.lock().unwrap();
                 if let Some(client) = clients.get(&client_id) {
                     if let Some(entity) = client.player_entity {
-                        if let Ok((mut transform, mut controller, _)) = player_query.get_mut(entity) {
+                        if let Ok((mut transform, mut controller, ..)) = player_query.get_mut(entity) {
                             apply_player_input(&mut transform, &mut controller, &input);
                         }
+                        requests.weapons.send(WeaponInput { entity, input });
                     }
                 }
             }
             
             ClientMessage::Disconnect => {
-                // Remover jugador
+                // Siempre se bloquea `clients` antes que `join_queue`
                 let mut clients = server_state.clients.lock().unwrap();
+                
+                // Sacarlo de la cola si estaba esperando
+                let mut queue = server_state.join_queue.lock().unwrap();
+                let queued_before = queue.len();
+                queue.retain(|pending| pending.client_id != client_id);
+                let left_queue = queue.len() != queued_before;
+                
+                // Remover jugador
                 if let Some(client) = clients.remove(&client_id) {
                     if let Some(entity) = client.player_entity {
+                        // Último guardado antes de que desaparezca con su inventario
+                        if let (Some(_), Ok((transform, _, player_id, inventory, persisted))) = (&admission.database, player_query.get(entity)) {
+                            let items = inventory.filter(|_| persisted).map(inventory_records);
+                            match admission.database_channel.sender.send(save_command(transform, player_id, items)) {
+                                Ok(()) => server_state.metrics.db_enqueued(),
+                                Err(e) => error!("❌ Error enviando comando de guardado: {}", e),
+                            }
+                        }
                         commands.entity(entity).despawn();
                     }
                     
@@ -381,22 +717,148 @@ fn process_client_messages(
                         // Notificar a otros clientes
                         let leave_msg = ServerMessage::PlayerLeft { player_id };
                         for (_, other_client) in clients.iter_mut() {
-                            send_message_to_stream(&mut other_client.stream, &leave_msg);
+                            other_client.send(&leave_msg);
                         }
                     }
                     
-                    println!("👋 Cliente {} desconectado y limpiado", client_id);
+                    info!(client_id, "👋 Cliente desconectado y limpiado");
+                }
+                
+                if left_queue {
+                    notify_queue_positions(&mut clients, &queue);
                 }
             }
             
             ClientMessage::Ping { timestamp } => {
-                let clients = server_state.clients.lock().unwrap();
-                if let Some(client) = clients.get(&client_id) {
-                    let mut stream = client.stream.try_clone().unwrap();
-                    let pong = ServerMessage::Pong { timestamp };
-                    send_message_to_stream(&mut stream, &pong);
+                let mut clients = server_state.clients.lock().unwrap();
+                if let Some(client) = clients.get_mut(&client_id) {
+                    client.send(&ServerMessage::Pong { timestamp });
+                }
+            }
+            
+            ClientMessage::Pong { timestamp } => {
+                let sample = (time.elapsed_secs_f64() - timestamp).max(0.0) as f32;
+                let mut clients = server_state.clients.lock().unwrap();
+                if let Some(client) = clients.get_mut(&client_id) {
+                    // Media móvil exponencial para no reaccionar a picos aislados
+                    client.rtt = if client.rtt == 0.0 { sample } else { client.rtt * 0.8 + sample * 0.2 };
                 }
             }
+            
+            ClientMessage::Chat { channel, text } => {
+                requests.chat.send(ChatRequest::Say { client_id, channel, text });
+            }
+            
+            ClientMessage::JoinTeam { team } => {
+                requests.chat.send(ChatRequest::JoinTeam { client_id, team });
+            }
+            
+            ClientMessage::Voice { channel, codec, sequence, data } => {
+                requests.voice.send(VoiceRequest::Frame { client_id, frame: VoiceFrame { channel, codec, sequence, data } });
+            }
+            
+            ClientMessage::TuneRadio { frequency } => {
+                requests.voice.send(VoiceRequest::TuneRadio { client_id, frequency });
+            }
+            
+            ClientMessage::RequestChunks { coords } => {
+                requests.chunks.send(ChunkRequest { client_id, coords });
+            }
+            
+            ClientMessage::Inventory { sequence, action } => {
+                requests.inventory.send(InventoryRequest { client_id, sequence, action });
+            }
+            
+            ClientMessage::Loot { sequence, action } => {
+                requests.loot.send(LootRequest { client_id, sequence, action });
+            }
+            
+            ClientMessage::Pickup { sequence, action } => {
+                requests.pickups.send(PickupRequest { client_id, sequence, action });
+            }
+            
+            ClientMessage::Trade { sequence, action } => {
+                requests.trades.send(TradeRequest { client_id, sequence, action });
+            }
+            
+            // El handshake se resuelve en el thread de conexión
+            ClientMessage::Hello { .. } | ClientMessage::StatusQuery => {}
+        }
+    }
+}
+
+/// Autentica al cliente si hay hueco; si no, lo pone en cola
+fn request_join(admission: &Admission, pending: PendingJoin) {
+    let Admission { server_state, config, .. } = admission;
+    let client_id = pending.client_id;
+    let mut clients = server_state.clients.lock().unwrap();
+    let mut queue = server_state.join_queue.lock().unwrap();
+    let active_players = clients.values().filter(|c| c.player_id.is_some() || c.authenticating).count() as u32;
+    
+    if active_players < config.max_players && queue.is_empty() {
+        drop(queue);
+        drop(clients);
+        handle_auth(admission, pending);
+        return;
+    }
+    
+    let Some(client) = clients.get_mut(&client_id) else { return };
+    
+    if queue.len() as u32 >= config.max_queue {
+        server_state.metrics.connection_rejected("queue_full");
+        client.send(&ServerMessage::ConnectionError {
+            reason: "Servidor lleno y cola de espera completa".to_string(),
+        });
+        client.close();
+        return;
+    }
+    
+    queue.push_back(pending);
+    client.send(&ServerMessage::Queued {
+        position: queue.len() as u32,
+        queue_length: queue.len() as u32,
+    });
+    info!(client_id, position = queue.len(), "⏳ Servidor lleno - cliente en cola");
+}
+
+/// Admite jugadores en cola a medida que se liberan huecos
+fn process_join_queue(admission: Admission) {
+    let Admission { server_state, config, .. } = &admission;
+    let mut admitted = Vec::new();
+    {
+        let clients = server_state.clients.lock().unwrap();
+        let mut queue = server_state.join_queue.lock().unwrap();
+        let mut active_players = clients.values().filter(|c| c.player_id.is_some() || c.authenticating).count() as u32;
+        
+        while active_players < config.max_players {
+            let Some(pending) = queue.pop_front() else { break };
+            active_players += 1;
+            admitted.push(pending);
+        }
+    }
+    
+    if admitted.is_empty() {
+        return;
+    }
+    
+    for pending in admitted {
+        info!(client_id = pending.client_id, "🎟️ Cliente sale de la cola");
+        handle_auth(&admission, pending);
+    }
+    
+    let mut clients = server_state.clients.lock().unwrap();
+    let queue = server_state.join_queue.lock().unwrap();
+    notify_queue_positions(&mut clients, &queue);
+}
+
+fn notify_queue_positions(clients: &mut HashMap<u32, ClientConnection>, queue: &VecDeque<PendingJoin>) {
+    let queue_length = queue.len() as u32;
+    for (index, pending) in queue.iter().enumerate() {
+        if let Some(client) = clients.get_mut(&pending.client_id) {
+            client.send(&ServerMessage::Queued {
+                position: index as u32 + 1,
+                queue_length,
+            });
         }
     }
 }
@@ -422,7 +884,9 @@ fn apply_player_input(
     
     if movement.length() > 0.0 {
         movement = movement.normalize();
-        let speed = if input.sprint { 10.0 } else { 7.0 };
+        // El peso y el equipo (ver `GearModifiers`) frenan y pueden impedir correr
+        let speed = if input.sprint && controller.can_sprint { 10.0 } else { 7.0 };
+        let speed = speed * controller.speed_multiplier;
         controller.velocity.x = movement.x * speed;
         controller.velocity.z = movement.z * speed;
     } else {
@@ -438,13 +902,17 @@ fn apply_player_input(
     
     // Actualizar rotación del jugador
     transform.rotation = yaw_rotation;
+    controller.flashlight = input.flashlight;
 }
 
 fn update_physics(
     mut player_query: Query<(&mut Transform, &mut PlayerController), With<Player>>,
     time: Res<Time>,
+    grid: Option<Res<ChunkGrid>>,
+    mut terrain: Option<ResMut<WorldTerrain>>,
 ) {
     let dt = time.delta_secs();
+    let half_size = world_half_size(grid.as_deref());
     
     for (mut transform, mut controller) in player_query.iter_mut() {
         // Gravedad
@@ -455,18 +923,20 @@ fn update_physics(
         // Aplicar velocidad
         transform.translation += controller.velocity * dt;
         
-        // Colisión con suelo
-        if transform.translation.y <= 0.0 {
-            transform.translation.y = 0.0;
+        // Límites del mundo
+        transform.translation.x = transform.translation.x.clamp(-half_size, half_size);
+        transform.translation.z = transform.translation.z.clamp(-half_size, half_size);
+        
+        // Colisión con el terreno
+        let ground = ground_height(terrain.as_deref_mut(), transform.translation);
+        let step_down = controller.is_grounded && controller.velocity.y <= 0.0;
+        if transform.translation.y <= ground || (step_down && transform.translation.y - ground < STEP_DOWN) {
+            transform.translation.y = ground;
             controller.velocity.y = 0.0;
             controller.is_grounded = true;
         } else {
             controller.is_grounded = false;
         }
-        
-        // Límites del mundo
-        transform.translation.x = transform.translation.x.clamp(-25.0, 25.0);
-        transform.translation.z = transform.translation.z.clamp(-25.0, 25.0);
     }
 }
 
@@ -480,7 +950,7 @@ fn send_world_state(
     tick.0 += 1;
     
     // Solo enviar cada 2 ticks (30 Hz)
-    if tick.0 % 2 != 0 {
+    if !tick.0.is_multiple_of(2) {
         return;
     }
     
@@ -495,6 +965,7 @@ fn send_world_state(
             health: health.current,
             is_grounded: controller.is_grounded,
             last_input_sequence: 0, // TODO: tracking de secuencias
+            flashlight: controller.flashlight,
         });
     }
     
@@ -505,51 +976,135 @@ fn send_world_state(
     };
     
     // Enviar a todos los clientes conectados
-    let clients = server_state.clients.lock().unwrap();
-    for (_, client) in clients.iter() {
+    let mut clients = server_state.clients.lock().unwrap();
+    for (_, client) in clients.iter_mut() {
         if client.player_id.is_some() {
-            let mut stream = client.stream.try_clone().unwrap();
-            send_message_to_stream(&mut stream, &world_state);
+            client.send(&world_state);
         }
     }
 }
 
-fn send_message_to_stream(stream: &mut TcpStream, message: &ServerMessage) {
-    if let Ok(json) = serde_json::to_string(message) {
-        let len = json.len() as u32;
-        let mut data = len.to_be_bytes().to_vec();
-        data.extend(json.as_bytes());
-        let _ = stream.write_all(&data);
+fn send_rtt_pings(
+    server_state: Res<ServerState>,
+    mut ping_timer: ResMut<PingTimer>,
+    time: Res<Time>,
+) {
+    ping_timer.0.tick(time.delta());
+    if !ping_timer.0.just_finished() {
+        return;
+    }
+    
+    let ping = ServerMessage::Ping { timestamp: time.elapsed_secs_f64() };
+    let mut clients = server_state.clients.lock().unwrap();
+    for client in clients.values_mut().filter(|client| client.player_id.is_some()) {
+        client.last_ping = Instant::now();
+        client.send(&ping);
     }
 }
 
-fn handle_auth(
-    commands: &mut Commands,
-    server_state: &ServerState,
-    client_id: u32,
-    username: String,
-    password: Option<String>,
-    session_token: Option<String>,
-    database: Option<&Database>,
-    sender: Sender<DatabaseCommand>,
-) {
-    let player_id = PlayerId(uuid::Uuid::new_v4());
+fn send_message_to_stream(stream: &mut SecureStream, codec: WireCodec, message: &ServerMessage, metrics: &Metrics) {
+    match codec.encode_frame(message) {
+        Ok(data) => {
+            if stream.write_all(&data).is_ok() {
+                metrics.record_message(Direction::Out, message.kind(), data.len());
+            }
+        }
+        Err(e) => error!("❌ Error serializando mensaje: {}", e),
+    }
+}
+
+/// Resuelve la cuenta del cliente. Con base de datos lo hace el worker (argon2 y la
+/// consulta bloquean); sin ella, o al reconectar, al momento. El jugador entra en `finish_joins`
+fn handle_auth(admission: &Admission, pending: PendingJoin) {
+    let server_state = &*admission.server_state;
+    let PendingJoin { client_id, username, password, .. } = pending;
+    {
+        let mut clients = server_state.clients.lock().unwrap();
+        let Some(client) = clients.get_mut(&client_id) else { return };
+        client.authenticating = true;
+    }
+    if admission.playback.is_some() {
+        return;
+    }
+    
+    // El `PlayerId` es el id de la cuenta: con él se cargan el estado y el inventario guardados
+    let account = match (admission.database.is_some(), password) {
+        (true, Some(password)) => {
+            let command = DatabaseCommand::AuthenticatePlayer { client_id, username: username.clone(), password };
+            match admission.database_channel.sender.send(command) {
+                Ok(()) => {
+                    server_state.metrics.db_enqueued();
+                    return;
+                }
+                Err(e) => Err(e.to_string()),
+            }
+        }
+        (true, None) => {
+            // TODO: Sesiones persistentes; hasta entonces `Reconnect` entra como invitado
+            warn!(client_id, "⚠️ Reconexión sin sesión guardada - jugador temporal");
+            Ok(Some(uuid::Uuid::new_v4()))
+        }
+        (false, _) => {
+            warn!("⚠️ Autenticación mock - base de datos no disponible");
+            Ok(Some(uuid::Uuid::new_v4()))
+        }
+    };
+    admission.database_channel.accounts.lock().unwrap().push(ResolvedAccount { client_id, username, account });
+}
+
+/// Mete en el mundo a los clientes con la cuenta ya resuelta y rechaza al resto
+fn finish_joins(mut commands: Commands, admission: Admission, recorder: Option<ResMut<DemoRecorder>>) {
+    let server_state = &*admission.server_state;
+    let resolved = std::mem::take(&mut *admission.database_channel.accounts.lock().unwrap());
+    if resolved.is_empty() {
+        return;
+    }
+    
+    // Cuándo entra cada jugador depende del worker: se graba para re-simularlo igual
+    if let Some(mut recorder) = recorder {
+        recorder.record_accounts(&resolved);
+    }
+    
+    for ResolvedAccount { client_id, username, account } in resolved {
+        match account {
+            Ok(Some(account_id)) => spawn_player(&mut commands, server_state, client_id, username, PlayerId(account_id)),
+            Ok(None) => {
+                warn!(client_id, "❌ Credenciales inválidas para: {}", username);
+                refuse_auth(server_state, client_id, "Usuario o contraseña incorrectos");
+            }
+            Err(e) => {
+                error!("❌ Error autenticando jugador: {}", e);
+                refuse_auth(server_state, client_id, "Error interno de autenticación");
+            }
+        }
+    }
+}
+
+fn spawn_player(commands: &mut Commands, server_state: &ServerState, client_id: u32, username: String, player_id: PlayerId) {
     let spawn_pos = Vec3::new(0.0, 10.0, 0.0);
     
-    // Si tenemos base de datos, intentar autenticación real
-    if let Some(db) = database {
-        // TODO: Implementar autenticación asíncrona
-        // Por ahora continuamos con mock
-        info!("🔒 Usando autenticación con base de datos para: {}", username);
-    } else {
-        warn!("⚠️ Autenticación mock - base de datos no disponible");
+    {
+        let clients = server_state.clients.lock().unwrap();
+        // Se fue mientras el worker buscaba su cuenta
+        if !clients.contains_key(&client_id) {
+            return;
+        }
+        // Una cuenta, un jugador en el mundo
+        if clients.values().any(|client| client.player_id == Some(player_id)) {
+            drop(clients);
+            warn!(client_id, "❌ {} ya está conectado", username);
+            refuse_auth(server_state, client_id, "La cuenta ya está conectada");
+            return;
+        }
     }
     
     let entity = commands.spawn((
         Player,
         player_id,
         PlayerController::new(),
-        Health::new(100.0),
+        // La vida va por zonas; `Health` es su resumen
+        Health::new(PLAYER_MAX_HEALTH),
+        BodyHealth::default(),
         Transform::from_translation(spawn_pos),
         GlobalTransform::default(),
     )).id();
@@ -560,6 +1115,7 @@ fn handle_auth(
         client.player_entity = Some(entity);
         client.player_id = Some(player_id);
         client.player_name = username;
+        client.authenticating = false;
         
         // Generar token de sesión temporal
         let session_token = format!("temp_token_{}", client_id);
@@ -571,9 +1127,10 @@ fn handle_auth(
             session_token,
             spawn_position: spawn_pos,
         };
-        send_message_to_stream(&mut client.stream, &connected_msg);
+        client.send(&connected_msg);
+        server_state.metrics.auth_succeeded();
         
-        println!("🎮 Jugador autenticado - Cliente: {}, PlayerId: {:?}", client_id, player_id);
+        info!(client_id, player_id = %player_id.0, "🎮 Jugador autenticado");
         
         // Notificar a otros clientes
         let join_msg = ServerMessage::PlayerJoined {
@@ -583,19 +1140,63 @@ fn handle_auth(
         
         for (&other_id, other_client) in clients.iter_mut() {
             if other_id != client_id && other_client.player_id.is_some() {
-                send_message_to_stream(&mut other_client.stream, &join_msg);
+                other_client.send(&join_msg);
             }
         }
     }
 }
 
+/// Autentica la cuenta o, la primera vez que se entra con ese nombre, la crea.
+/// `None` si el nombre existe y la contraseña no coincide (o está baneado).
+/// Bloquea: solo desde el worker de base de datos
+fn resolve_account(store: &dyn PlayerStore, username: &str, password: &str, metrics: &Metrics) -> anyhow::Result<Option<uuid::Uuid>> {
+    let started = Instant::now();
+    let result = store.authenticate_player(username, password);
+    metrics.observe_db("authenticate_player", started.elapsed(), result.is_ok());
+    if let Some(player) = result? {
+        return Ok(Some(player.id));
+    }
+    let started = Instant::now();
+    let created = store.create_player(username, password);
+    metrics.observe_db("create_player", started.elapsed(), created.is_ok());
+    let created = created?;
+    if let Some(player_id) = created {
+        info!("🆕 Cuenta creada: {} (ID: {})", username, player_id);
+    }
+    Ok(created)
+}
+
+/// Rechaza el login, lo cuenta y cierra la conexión
+fn refuse_auth(server_state: &ServerState, client_id: u32, reason: &str) {
+    server_state.metrics.auth_failed();
+    let mut clients = server_state.clients.lock().unwrap();
+    let Some(client) = clients.get_mut(&client_id) else { return };
+    client.authenticating = false;
+    client.send(&ServerMessage::AuthError { reason: reason.to_string() });
+    client.close();
+}
+
+fn save_command(transform: &Transform, player_id: &PlayerId, items: Option<Vec<ItemRecord>>) -> DatabaseCommand {
+    DatabaseCommand::SavePlayerPosition {
+        player_id: player_id.0,
+        position: transform.translation,
+        rotation: transform.rotation,
+        items,
+    }
+}
+
 fn save_player_positions(
-    player_query: Query<(&Transform, &PlayerId), With<Player>>,
-    database: Option<Res<Database>>,
+    player_query: Query<SavedPlayer, With<Player>>,
+    database: Option<Res<PlayerStorage>>,
     mut save_timer: ResMut<SaveTimer>,
     time: Res<Time>,
     database_channel: Res<DatabaseChannel>,
+    server_state: Res<ServerState>,
 ) {
+    // Sin base de datos no hay worker que vacíe el canal
+    if database.is_none() {
+        return;
+    }
     save_timer.0.tick(time.delta());
     
     if save_timer.0.just_finished() {
@@ -603,17 +1204,24 @@ fn save_player_positions(
         if player_count > 0 {
             info!("💾 Guardando posiciones de {} jugadores", player_count);
             
-            for (transform, player_id) in player_query.iter() {
-                let cmd = DatabaseCommand::SavePlayerPosition {
-                    player_id: player_id.0,
-                    position: transform.translation,
-                    rotation: transform.rotation,
-                };
+            // Un mismo item en dos inventarios es un duplicado: solo se guarda el primero
+            let mut saved_items = HashSet::new();
+            for (transform, player_id, inventory, persisted) in player_query.iter() {
+                let mut items = inventory.filter(|_| persisted).map(inventory_records);
+                if let Some(records) = &items {
+                    if let Some(duplicate) = records.iter().find(|record| saved_items.contains(&record.id)) {
+                        error!("❌ Item {} duplicado en el inventario de {}; no se guarda su inventario", duplicate.id, player_id.0);
+                        items = None;
+                    } else {
+                        saved_items.extend(records.iter().map(|record| record.id));
+                    }
+                }
                 
-                if let Err(e) = database_channel.sender.send(cmd) {
-                    error!("❌ Error enviando comando de guardado: {}", e);
+                match database_channel.sender.send(save_command(transform, player_id, items)) {
+                    Ok(()) => server_state.metrics.db_enqueued(),
+                    Err(e) => error!("❌ Error enviando comando de guardado: {}", e),
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
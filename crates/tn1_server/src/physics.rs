@@ -1,5 +1,7 @@
 use bevy::prelude::*;
-use tn1_shared::{components::*, events::*};
+use tn1_shared::{chunk::ChunkGrid, components::*, events::*};
+use crate::networking::{NetworkingSet, ServerState};
+use crate::world::WorldTerrain;
 
 pub struct ServerPhysicsPlugin;
 
@@ -11,10 +13,26 @@ impl Plugin for ServerPhysicsPlugin {
                 update_player_physics,
                 send_position_updates,
                 validate_player_positions,
-            ));
+            ).chain().before(NetworkingSet));
     }
 }
 
+/// Mitad del lado del plano de desarrollo (50x50) cuando no hay `WorldPlugin`
+const DEV_PLANE_HALF_SIZE: f32 = 25.0;
+
+/// Los jugadores se mueven en `[-half, half]` en X y Z
+pub(crate) fn world_half_size(grid: Option<&ChunkGrid>) -> f32 {
+    grid.map_or(DEV_PLANE_HALF_SIZE, ChunkGrid::half_size)
+}
+
+/// Bajando una ladera el jugador sigue pegado al suelo si cae menos de esto por tick
+pub(crate) const STEP_DOWN: f32 = 0.5;
+
+/// Altura del suelo en la posición: el terreno procedural o el plano y = 0 sin `WorldPlugin`
+pub(crate) fn ground_height(terrain: Option<&mut WorldTerrain>, position: Vec3) -> f32 {
+    terrain.map_or(0.0, |terrain| terrain.ground_height(position))
+}
+
 fn setup_physics_world() {
     info!("Mundo físico simplificado inicializado");
     info!("Sistema de colisiones básico habilitado");
@@ -24,6 +42,7 @@ fn process_player_inputs(
     mut input_events: EventReader<PlayerInputEvent>,
     mut player_query: Query<(&mut PlayerController, &mut Transform, &PlayerId), With<Player>>,
     time: Res<Time>,
+    server_state: Res<ServerState>,
 ) {
     for event in input_events.read() {
         if let Ok((mut controller, _transform, _)) = 
@@ -97,6 +116,7 @@ fn process_player_inputs(
             
             // Anti-cheat: validar que la velocidad no sea imposible
             if controller.velocity.length() > 50.0 {
+                server_state.metrics.anticheat_flag("speed");
                 warn!("Player {:?} velocity too high: {}, clamping", event.player_id, controller.velocity.length());
                 controller.velocity = controller.velocity.normalize() * 20.0;
             }
@@ -107,7 +127,10 @@ fn process_player_inputs(
 fn update_player_physics(
     mut player_query: Query<(&mut Transform, &mut PlayerController), With<Player>>,
     time: Res<Time>,
+    grid: Option<Res<ChunkGrid>>,
+    mut terrain: Option<ResMut<WorldTerrain>>,
 ) {
+    let half_size = world_half_size(grid.as_deref());
     for (mut transform, mut player_controller) in player_query.iter_mut() {
         let dt = time.delta().as_secs_f32();
         
@@ -119,9 +142,15 @@ fn update_player_physics(
         // Actualizar posición
         transform.translation += player_controller.velocity * dt;
         
-        // Detectar colisión con el suelo (simplificado)
-        if transform.translation.y <= 0.0 {
-            transform.translation.y = 0.0;
+        // Límites del mundo
+        transform.translation.x = transform.translation.x.clamp(-half_size, half_size);
+        transform.translation.z = transform.translation.z.clamp(-half_size, half_size);
+        
+        // Detectar colisión con el suelo (heightfield del chunk)
+        let ground = ground_height(terrain.as_deref_mut(), transform.translation);
+        let step_down = player_controller.is_grounded && player_controller.velocity.y <= 0.0;
+        if transform.translation.y <= ground || (step_down && transform.translation.y - ground < STEP_DOWN) {
+            transform.translation.y = ground;
             if player_controller.velocity.y < 0.0 {
                 player_controller.velocity.y = 0.0;
                 player_controller.is_grounded = true;
@@ -129,10 +158,6 @@ fn update_player_physics(
         } else {
             player_controller.is_grounded = false;
         }
-        
-        // Límites del mundo
-        transform.translation.x = transform.translation.x.clamp(-50.0, 50.0);
-        transform.translation.z = transform.translation.z.clamp(-50.0, 50.0);
     }
 }
 
@@ -151,20 +176,25 @@ fn send_position_updates(
 
 fn validate_player_positions(
     mut player_query: Query<(&mut Transform, &PlayerId), With<Player>>,
+    server_state: Res<ServerState>,
+    grid: Option<Res<ChunkGrid>>,
 ) {
+    let half_size = world_half_size(grid.as_deref());
     for (mut transform, player_id) in player_query.iter_mut() {
         // Anti-cheat: validar que el jugador no esté fuera de los límites del mundo
         if transform.translation.y < -50.0 {
+            server_state.metrics.anticheat_flag("out_of_world");
             warn!("Player {:?} fell out of world, respawning", player_id);
             transform.translation = Vec3::new(0.0, 5.0, 0.0);
         }
         
         // Validar que no esté demasiado lejos del centro
         let distance_from_center = transform.translation.xz().length();
-        if distance_from_center > 100.0 {
+        if distance_from_center > half_size * 2.0 {
+            server_state.metrics.anticheat_flag("out_of_bounds");
             warn!("Player {:?} too far from center, teleporting back", player_id);
-            transform.translation.x = transform.translation.x.clamp(-50.0, 50.0);
-            transform.translation.z = transform.translation.z.clamp(-50.0, 50.0);
+            transform.translation.x = transform.translation.x.clamp(-half_size, half_size);
+            transform.translation.z = transform.translation.z.clamp(-half_size, half_size);
         }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tn1_shared::transport::{certificate_fingerprint, tls_enabled_from_env, TLS_SERVER_NAME};
+
+/// Configuración TLS del servidor. `None` solo si se desactivó explícitamente con `TN1_TLS=off`.
+#[derive(Resource, Clone)]
+pub struct ServerTls(pub Option<Arc<rustls::ServerConfig>>);
+
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    pub enabled: bool,
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl Default for TlsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: tls_enabled_from_env(),
+            cert_path: std::env::var("TN1_TLS_CERT")
+                .unwrap_or_else(|_| "certs/server_cert.pem".to_string())
+                .into(),
+            key_path: std::env::var("TN1_TLS_KEY")
+                .unwrap_or_else(|_| "certs/server_key.pem".to_string())
+                .into(),
+        }
+    }
+}
+
+pub struct TlsPlugin;
+
+impl Plugin for TlsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = TlsSettings::default();
+
+        if !settings.enabled {
+            warn!("⚠️ TLS desactivado (TN1_TLS=off) - credenciales y tráfico viajan en claro");
+            app.insert_resource(ServerTls(None));
+            return;
+        }
+
+        let config = load_server_config(&settings)
+            .expect("No se pudo inicializar TLS (revisa TN1_TLS_CERT / TN1_TLS_KEY)");
+        app.insert_resource(ServerTls(Some(config)));
+    }
+}
+
+/// Carga el certificado y la clave; si no existen genera un par autofirmado para desarrollo
+pub fn load_server_config(settings: &TlsSettings) -> Result<Arc<rustls::ServerConfig>> {
+    if !settings.cert_path.exists() || !settings.key_path.exists() {
+        generate_self_signed(&settings.cert_path, &settings.key_path)?;
+    }
+
+    let mut cert_reader = std::io::BufReader::new(
+        std::fs::File::open(&settings.cert_path)
+            .with_context(|| format!("abriendo {}", settings.cert_path.display()))?,
+    );
+    let certs = rustls_pemfile::certs(&mut cert_reader).collect::<Result<Vec<_>, _>>()?;
+    let leaf = certs.first().context("el archivo de certificado está vacío")?;
+    info!("🔐 TLS habilitado - huella SHA-256 del certificado: {}", certificate_fingerprint(leaf.as_ref()));
+
+    let mut key_reader = std::io::BufReader::new(
+        std::fs::File::open(&settings.key_path)
+            .with_context(|| format!("abriendo {}", settings.key_path.display()))?,
+    );
+    let key = rustls_pemfile::private_key(&mut key_reader)?
+        .context("el archivo de clave no contiene ninguna clave privada")?;
+
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+    Ok(Arc::new(config))
+}
+
+/// Genera un certificado autofirmado y deja su huella en `<cert>.sha256` para que el cliente la fije
+fn generate_self_signed(cert_path: &Path, key_path: &Path) -> Result<()> {
+    let certified = rcgen::generate_simple_self_signed(vec![
+        TLS_SERVER_NAME.to_string(),
+        "localhost".to_string(),
+    ])?;
+
+    for path in [cert_path, key_path] {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+    }
+
+    std::fs::write(cert_path, certified.cert.pem())?;
+    std::fs::write(key_path, certified.key_pair.serialize_pem())?;
+
+    let fingerprint = certificate_fingerprint(certified.cert.der());
+    std::fs::write(cert_path.with_extension("sha256"), format!("{}\n", fingerprint))?;
+
+    warn!("🔏 Certificado autofirmado de desarrollo generado en {}", cert_path.display());
+    println!("📌 Pin para el cliente (TN1_TLS_PIN): {}", fingerprint);
+    Ok(())
+}
@@ -0,0 +1,501 @@
+//! Comercio entre jugadores, con el servidor de intermediario.
+//!
+//! Un jugador propone (`TradeAction::Request`) a otro que tenga a `distance`
+//! metros y este acepta; desde ahí cada uno pone items de su inventario en su
+//! lado de la ventana. Los items no salen del inventario hasta el final: cada
+//! tick se comprueba que lo ofrecido sigue ahí igual, y si algo cambia (se tira,
+//! se parte, se mete algo dentro) la oferta se actualiza y las confirmaciones
+//! se pierden. Con las dos confirmaciones empieza una cuenta atrás de
+//! `countdown` segundos en la que no se puede tocar nada; al acabar se cambia
+//! todo sobre copias de los dos inventarios y solo si todo cabe se aplican.
+//!
+//! Con base de datos el resultado se guarda con `DatabaseCommand::SaveTrade`:
+//! los dos inventarios y la entrada de `trade_log` van en la misma transacción.
+
+use bevy::prelude::*;
+use chrono::Utc;
+use std::collections::HashMap;
+use tn1_shared::components::{Health, PlayerId};
+use tn1_shared::inventory::{Inventory, InventoryAction, InventoryError, ItemStack};
+use tn1_shared::items::ItemDefs;
+use tn1_shared::protocol::ServerMessage;
+use tn1_shared::trade::*;
+use crate::config::env_or;
+use crate::database::{PlayerStorage, TradeItemRecord, TradeRecord};
+use crate::inventory::{inventory_records, PersistedInventory};
+use crate::networking::{ClientConnection, DatabaseChannel, DatabaseCommand, NetworkingSet, ServerState};
+
+/// Acción de comercio recibida de un cliente; `NetworkingPlugin` la emite
+#[derive(Event, Debug, Clone)]
+pub struct TradeRequest {
+    pub client_id: u32,
+    pub sequence: u32,
+    pub action: TradeAction,
+}
+
+/// Distancia y tiempos del comercio (variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct TradeSettings {
+    /// Distancia máxima (metros) entre los dos durante todo el comercio
+    pub distance: f32,
+    /// Segundos entre la segunda confirmación y el cambio
+    pub countdown: f32,
+    /// Segundos que espera una propuesta a que la acepten
+    pub request_timeout: f32,
+}
+
+impl Default for TradeSettings {
+    fn default() -> Self {
+        Self {
+            distance: env_or("TN1_TRADE_DISTANCE", 2.0),
+            countdown: env_or("TN1_TRADE_COUNTDOWN_SECS", 3.0),
+            request_timeout: env_or("TN1_TRADE_REQUEST_SECS", 30.0),
+        }
+    }
+}
+
+/// Un comercio abierto; el lado 0 es quien lo propuso
+#[derive(Debug, Clone)]
+struct Trade {
+    id: u64,
+    players: [PlayerId; 2],
+    names: [String; 2],
+    /// Copia de lo ofrecido tal como está en cada inventario
+    offers: [Vec<ItemStack>; 2],
+    confirmed: [bool; 2],
+    accepted: bool,
+    /// Segundo del servidor en que caduca la propuesta si nadie la acepta
+    expires_at: f64,
+    /// Segundo en que se hace el cambio; solo con las dos confirmaciones
+    completes_at: Option<f64>,
+}
+
+impl Trade {
+    fn side(&self, player_id: PlayerId) -> Option<usize> {
+        self.players.iter().position(|player| *player == player_id)
+    }
+
+    /// Cualquier cambio en las ofertas obliga a confirmar otra vez
+    fn reset_confirmations(&mut self) {
+        self.confirmed = [false; 2];
+        self.completes_at = None;
+    }
+
+    fn view(&self, side: usize, now: f64) -> TradeView {
+        let other = 1 - side;
+        let offer = |side: usize| TradeOffer { items: self.offers[side].clone(), confirmed: self.confirmed[side] };
+        let phase = match (self.accepted, self.completes_at) {
+            (false, _) => TradePhase::Pending,
+            (true, None) => TradePhase::Open,
+            (true, Some(at)) => TradePhase::Locked { remaining: (at - now).max(0.0) as f32 },
+        };
+        TradeView {
+            id: self.id,
+            partner: self.players[other],
+            partner_name: self.names[other].clone(),
+            requested: side == 0,
+            phase,
+            mine: offer(side),
+            theirs: offer(other),
+        }
+    }
+}
+
+/// Comercios abiertos; un jugador solo puede estar en uno
+#[derive(Resource, Debug)]
+struct Trades {
+    open: HashMap<u64, Trade>,
+    next_id: u64,
+}
+
+impl Default for Trades {
+    fn default() -> Self {
+        Self { open: HashMap::new(), next_id: 1 }
+    }
+}
+
+impl Trades {
+    fn of(&self, player_id: PlayerId) -> Option<&Trade> {
+        self.open.values().find(|trade| trade.side(player_id).is_some())
+    }
+
+    fn of_mut(&mut self, player_id: PlayerId) -> Option<&mut Trade> {
+        self.open.values_mut().find(|trade| trade.side(player_id).is_some())
+    }
+}
+
+/// Comercio entre jugadores; necesita `InventoryPlugin`
+#[derive(Default)]
+pub struct TradePlugin {
+    pub settings: TradeSettings,
+}
+
+impl Plugin for TradePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .init_resource::<Trades>()
+            .add_systems(Update, (
+                handle_trade_requests,
+                watch_trades,
+                complete_trades,
+            ).chain().after(NetworkingSet));
+    }
+}
+
+/// Lo que hace falta de cada jugador para comerciar
+type Trader<'a> = (&'a PlayerId, &'a Transform, &'a Inventory, Option<&'a Health>);
+
+/// Qué ha cambiado tras una acción
+enum Change {
+    Updated(u64),
+    Closed(Trade, String),
+}
+
+fn handle_trade_requests(
+    mut requests: EventReader<TradeRequest>,
+    server_state: Res<ServerState>,
+    settings: Res<TradeSettings>,
+    time: Res<Time>,
+    mut trades: ResMut<Trades>,
+    players: Query<Trader>,
+) {
+    let now = time.elapsed_secs_f64();
+    let mut clients = server_state.clients.lock().unwrap();
+    for request in requests.read() {
+        let Some(client) = clients.get(&request.client_id) else { continue };
+        let (Some(player_id), name) = (client.player_id, client.player_name.clone()) else { continue };
+
+        let change = apply_action(&mut trades, (player_id, name), &request.action, &players, &clients, &settings, now);
+        let acting = Some((player_id, request.sequence));
+        match change {
+            Ok(Change::Updated(id)) => {
+                let trade = &trades.open[&id];
+                debug!(trade = id, player = %trade.names[trade.side(player_id).unwrap_or(0)], ?request.action, "🤝 Comercio actualizado");
+                send_views(&mut clients, trade, acting, now);
+            }
+            Ok(Change::Closed(trade, reason)) => {
+                info!(trade = trade.id, a = %trade.names[0], b = %trade.names[1], "🤝 Comercio cancelado: {}", reason);
+                send_closed(&mut clients, &trade, false, &reason, acting);
+            }
+            Err(error) => {
+                let Some(client) = clients.get_mut(&request.client_id) else { continue };
+                debug!(player = %client.player_name, ?request.action, %error, "🤝 Acción de comercio rechazada");
+                client.send(&ServerMessage::InventoryRejected { sequence: request.sequence, reason: error.to_string() });
+            }
+        }
+    }
+}
+
+/// Valida y aplica una acción del jugador (id y nombre) sobre su comercio
+fn apply_action(
+    trades: &mut Trades,
+    (player_id, name): (PlayerId, String),
+    action: &TradeAction,
+    players: &Query<Trader>,
+    clients: &HashMap<u32, ClientConnection>,
+    settings: &TradeSettings,
+    now: f64,
+) -> Result<Change, TradeError> {
+    let find = |id: PlayerId| players.iter().find(|(player, ..)| **player == id);
+    let (_, me, inventory, _) = find(player_id).ok_or(TradeError::UnknownPlayer)?;
+
+    if let TradeAction::Request { player } = action {
+        if trades.of(player_id).is_some() {
+            return Err(TradeError::Busy);
+        }
+        if *player == player_id {
+            return Err(TradeError::WithYourself);
+        }
+        let (_, other, ..) = find(*player).ok_or(TradeError::UnknownPlayer)?;
+        let other_name = clients
+            .values()
+            .find(|client| client.player_id == Some(*player))
+            .map(|client| client.player_name.clone())
+            .ok_or(TradeError::UnknownPlayer)?;
+        if trades.of(*player).is_some() {
+            return Err(TradeError::Busy);
+        }
+        if me.translation.distance(other.translation) > settings.distance {
+            return Err(TradeError::TooFar);
+        }
+        let id = trades.next_id;
+        trades.next_id += 1;
+        trades.open.insert(id, Trade {
+            id,
+            players: [player_id, *player],
+            names: [name, other_name],
+            offers: [Vec::new(), Vec::new()],
+            confirmed: [false; 2],
+            accepted: false,
+            expires_at: now + settings.request_timeout as f64,
+            completes_at: None,
+        });
+        return Ok(Change::Updated(id));
+    }
+
+    if let TradeAction::Accept { trade } = action {
+        let trade = trades
+            .open
+            .get_mut(trade)
+            .filter(|trade| !trade.accepted && trade.players[1] == player_id)
+            .ok_or(TradeError::NotInvited)?;
+        let (_, other, ..) = find(trade.players[0]).ok_or(TradeError::UnknownPlayer)?;
+        if me.translation.distance(other.translation) > settings.distance {
+            return Err(TradeError::TooFar);
+        }
+        trade.accepted = true;
+        return Ok(Change::Updated(trade.id));
+    }
+
+    let trade = trades.of_mut(player_id).ok_or(TradeError::NoTrade)?;
+    let side = trade.side(player_id).ok_or(TradeError::NoTrade)?;
+    match action {
+        TradeAction::Cancel => {
+            let id = trade.id;
+            let trade = trades.open.remove(&id).ok_or(TradeError::NoTrade)?;
+            let reason = format!("{} ha cancelado el comercio", trade.names[side]);
+            return Ok(Change::Closed(trade, reason));
+        }
+        _ if !trade.accepted => return Err(TradeError::NotAccepted),
+        TradeAction::Offer { item } => {
+            if trade.completes_at.is_some() {
+                return Err(TradeError::Locked);
+            }
+            let placed = inventory.find(*item).ok_or(InventoryError::UnknownItem(*item))?;
+            let overlaps = |offered: &ItemStack| stack_contains(offered, *item) || stack_contains(&placed.stack, offered.id);
+            if trade.offers[side].iter().any(overlaps) {
+                return Err(TradeError::AlreadyOffered);
+            }
+            trade.offers[side].push(placed.stack.clone());
+            trade.reset_confirmations();
+        }
+        TradeAction::Withdraw { item } => {
+            if trade.completes_at.is_some() {
+                return Err(TradeError::Locked);
+            }
+            let index = trade.offers[side].iter().position(|offered| offered.id == *item).ok_or(TradeError::NotOffered)?;
+            trade.offers[side].remove(index);
+            trade.reset_confirmations();
+        }
+        TradeAction::Confirm => {
+            if trade.offers.iter().all(Vec::is_empty) {
+                return Err(TradeError::Empty);
+            }
+            trade.confirmed[side] = true;
+            if trade.confirmed.iter().all(|confirmed| *confirmed) && trade.completes_at.is_none() {
+                trade.completes_at = Some(now + settings.countdown as f64);
+            }
+        }
+        TradeAction::Unconfirm => {
+            trade.confirmed[side] = false;
+            trade.completes_at = None;
+        }
+        TradeAction::Request { .. } | TradeAction::Accept { .. } => unreachable!("resueltas arriba"),
+    }
+    Ok(Change::Updated(trade.id))
+}
+
+/// Cierra los comercios que ya no pueden seguir y pone al día las ofertas con
+/// lo que hay de verdad en cada inventario
+fn watch_trades(
+    server_state: Res<ServerState>,
+    settings: Res<TradeSettings>,
+    time: Res<Time>,
+    mut trades: ResMut<Trades>,
+    players: Query<Trader>,
+) {
+    let now = time.elapsed_secs_f64();
+    let mut clients = server_state.clients.lock().unwrap();
+    let mut closed = Vec::new();
+    for trade in trades.open.values_mut() {
+        let found = trade.players.map(|id| players.iter().find(|(player, ..)| **player == id));
+        let reason = if !trade.accepted && now >= trade.expires_at {
+            Some(format!("{} no ha respondido", trade.names[1]))
+        } else if let Some(side) = found.iter().position(Option::is_none) {
+            Some(format!("{} se ha ido", trade.names[side]))
+        } else if let Some(side) = found.iter().position(|found| found.is_some_and(|(.., health)| health.is_some_and(|health| health.current <= 0.0))) {
+            Some(format!("{} ha muerto", trade.names[side]))
+        } else if let [Some((_, a, ..)), Some((_, b, ..))] = found {
+            (a.translation.distance(b.translation) > settings.distance).then(|| TradeError::TooFar.to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            closed.push((trade.id, reason));
+            continue;
+        }
+
+        let mut changed = false;
+        for (side, (_, _, inventory, _)) in found.iter().flatten().enumerate() {
+            changed |= sync_offer(&mut trade.offers[side], inventory);
+        }
+        if changed {
+            debug!(trade = trade.id, "🤝 La oferta ha cambiado en el inventario; hay que confirmar otra vez");
+            trade.reset_confirmations();
+            send_views(&mut clients, trade, None, now);
+        }
+    }
+
+    for (id, reason) in closed {
+        let Some(trade) = trades.open.remove(&id) else { continue };
+        info!(trade = id, a = %trade.names[0], b = %trade.names[1], "🤝 Comercio cancelado: {}", reason);
+        send_closed(&mut clients, &trade, false, &reason, None);
+    }
+}
+
+/// Deja la oferta como está en el inventario: quita lo que ya no está o ha
+/// acabado dentro de otro item ofrecido y copia lo que ha cambiado
+fn sync_offer(offer: &mut Vec<ItemStack>, inventory: &Inventory) -> bool {
+    let current: Vec<ItemStack> = offer
+        .iter()
+        .filter_map(|offered| inventory.find(offered.id).map(|placed| placed.stack.clone()))
+        .collect();
+    let current: Vec<ItemStack> = current
+        .iter()
+        .filter(|stack| !current.iter().any(|other| other.id != stack.id && stack_contains(other, stack.id)))
+        .cloned()
+        .collect();
+    if current == *offer {
+        return false;
+    }
+    *offer = current;
+    true
+}
+
+/// Hace los cambios cuya cuenta atrás ha terminado
+fn complete_trades(
+    server_state: Res<ServerState>,
+    time: Res<Time>,
+    defs: Res<ItemDefs>,
+    storage: Option<Res<PlayerStorage>>,
+    database_channel: Res<DatabaseChannel>,
+    mut trades: ResMut<Trades>,
+    mut players: Query<(Entity, &PlayerId, &mut Inventory, Has<PersistedInventory>)>,
+) {
+    let now = time.elapsed_secs_f64();
+    let due: Vec<u64> = trades.open.values().filter(|trade| trade.completes_at.is_some_and(|at| at <= now)).map(|trade| trade.id).collect();
+    if due.is_empty() {
+        return;
+    }
+    let mut clients = server_state.clients.lock().unwrap();
+    for id in due {
+        let Some(trade) = trades.open.remove(&id) else { continue };
+        let entities = trade.players.map(|id| players.iter().find(|(_, player, ..)| **player == id).map(|(entity, ..)| entity));
+        let [Some(a), Some(b)] = entities else { continue };
+        let Ok([(_, _, mut first, first_persisted), (_, _, mut second, second_persisted)]) = players.get_many_mut([a, b]) else {
+            continue;
+        };
+
+        let exchanged = match exchange(&trade.offers, [&first, &second], &defs) {
+            Ok(exchanged) => exchanged,
+            Err((side, error)) => {
+                let reason = format!("no se pudo hacer el cambio ({}): {}", trade.names[side], error);
+                warn!(trade = id, a = %trade.names[0], b = %trade.names[1], "🤝 Comercio fallido: {}", reason);
+                send_closed(&mut clients, &trade, false, &reason, None);
+                continue;
+            }
+        };
+        let [after_first, after_second] = exchanged;
+        *first = after_first;
+        *second = after_second;
+        for (player_id, inventory) in trade.players.iter().zip([&*first, &*second]) {
+            if let Some(client) = clients.values_mut().find(|client| client.player_id == Some(*player_id)) {
+                client.send(&ServerMessage::Inventory(inventory.clone()));
+            }
+        }
+        info!(
+            trade = id,
+            a = %trade.names[0],
+            b = %trade.names[1],
+            given = trade.offers[0].len(),
+            received = trade.offers[1].len(),
+            "🤝 Comercio hecho"
+        );
+        send_closed(&mut clients, &trade, true, "comercio hecho", None);
+
+        if storage.is_none() {
+            continue;
+        }
+        let [from, to] = trade.players.map(|player| player.0);
+        let mut items = Vec::new();
+        for stack in &trade.offers[0] {
+            moved_items(stack, from, to, &mut items);
+        }
+        for stack in &trade.offers[1] {
+            moved_items(stack, to, from, &mut items);
+        }
+        let record = TradeRecord {
+            players: [from, to],
+            usernames: trade.names.clone(),
+            inventories: [
+                first_persisted.then(|| inventory_records(&first)),
+                second_persisted.then(|| inventory_records(&second)),
+            ],
+            items,
+            completed_at: Utc::now(),
+        };
+        match database_channel.sender.send(DatabaseCommand::SaveTrade(record)) {
+            Ok(()) => server_state.metrics.db_enqueued(),
+            Err(e) => error!("❌ Error enviando comercio a la base de datos: {}", e),
+        }
+    }
+}
+
+/// Cambia las ofertas sobre copias de los dos inventarios: o sale todo bien o
+/// no se toca nada. El error dice en qué lado falló.
+fn exchange(
+    offers: &[Vec<ItemStack>; 2],
+    inventories: [&Inventory; 2],
+    defs: &ItemDefs,
+) -> Result<[Inventory; 2], (usize, InventoryError)> {
+    let mut next = inventories.map(Inventory::clone);
+    let mut given: [Vec<ItemStack>; 2] = [Vec::new(), Vec::new()];
+    for side in 0..2 {
+        for stack in &offers[side] {
+            let outcome = next[side]
+                .apply(defs, &InventoryAction::Drop { item: stack.id }, || stack.id)
+                .map_err(|error| (side, error))?;
+            given[side].extend(outcome.dropped);
+        }
+    }
+    for (side, stacks) in given.into_iter().enumerate() {
+        for stack in stacks {
+            next[1 - side].insert(defs, stack).map_err(|error| (1 - side, error))?;
+        }
+    }
+    Ok(next)
+}
+
+/// Filas del registro de un item que cambia de manos y de lo que lleva
+fn moved_items(stack: &ItemStack, from: uuid::Uuid, to: uuid::Uuid, items: &mut Vec<TradeItemRecord>) {
+    items.push(TradeItemRecord { id: stack.uuid, item: stack.item.clone(), quantity: stack.quantity, from, to });
+    for attachment in &stack.attachments {
+        moved_items(attachment, from, to, items);
+    }
+    for inner in stack.contents.iter().flat_map(|contents| &contents.items) {
+        moved_items(&inner.stack, from, to, items);
+    }
+}
+
+/// La ventana a cada uno de los dos; quien hizo la acción recibe su secuencia
+fn send_views(clients: &mut HashMap<u32, ClientConnection>, trade: &Trade, acting: Option<(PlayerId, u32)>, now: f64) {
+    for (side, player_id) in trade.players.iter().enumerate() {
+        let Some(client) = clients.values_mut().find(|client| client.player_id == Some(*player_id)) else { continue };
+        let sequence = acting.filter(|(actor, _)| actor == player_id).map(|(_, sequence)| sequence);
+        client.send(&ServerMessage::TradeWindow { sequence, view: trade.view(side, now) });
+    }
+}
+
+fn send_closed(
+    clients: &mut HashMap<u32, ClientConnection>,
+    trade: &Trade,
+    completed: bool,
+    reason: &str,
+    acting: Option<(PlayerId, u32)>,
+) {
+    for player_id in &trade.players {
+        let Some(client) = clients.values_mut().find(|client| client.player_id == Some(*player_id)) else { continue };
+        let sequence = acting.filter(|(actor, _)| actor == player_id).map(|(_, sequence)| sequence);
+        client.send(&ServerMessage::TradeClosed { sequence, trade: trade.id, completed, reason: reason.to_string() });
+    }
+}
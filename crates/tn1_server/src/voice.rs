@@ -0,0 +1,168 @@
+//! Relay de voz: proximidad con atenuación por distancia y canales de radio.
+//!
+//! El servidor no decodifica el audio. Para cada frame decide quién lo oye y
+//! con qué ganancia, y lo reenvía tal cual; el cliente lo posiciona en 3D.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use tn1_shared::protocol::{ServerMessage, VoicePacket};
+use tn1_shared::voice::{attenuation, VoiceChannel, VoiceCodec, MAX_VOICE_PACKET, RADIO_FREQUENCIES};
+use crate::chat::ChatModeration;
+use crate::config::env_or;
+use crate::networking::{ClientConnection, NetworkingSet, ServerState};
+
+/// Petición de voz recibida de un cliente; `NetworkingPlugin` la emite
+#[derive(Event, Debug, Clone)]
+pub enum VoiceRequest {
+    Frame { client_id: u32, frame: VoiceFrame },
+    TuneRadio { client_id: u32, frequency: Option<u16> },
+}
+
+/// Frame de voz tal como lo envió el cliente
+#[derive(Debug, Clone)]
+pub struct VoiceFrame {
+    pub channel: VoiceChannel,
+    pub codec: VoiceCodec,
+    pub sequence: u32,
+    pub data: Vec<u8>,
+}
+
+/// Alcance y límites de la voz (variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct VoiceSettings {
+    /// Distancia a la que deja de oírse la voz de proximidad (metros)
+    pub range: f32,
+    /// Frames por segundo y jugador; hablar sin parar son 50 (20 ms por frame)
+    pub max_frames_per_second: u32,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            range: env_or("TN1_VOICE_RANGE", 40.0),
+            max_frames_per_second: env_or("TN1_VOICE_MAX_FPS", 60),
+        }
+    }
+}
+
+/// Reenvío de voz entre jugadores
+#[derive(Default)]
+pub struct VoicePlugin {
+    pub settings: VoiceSettings,
+}
+
+impl Plugin for VoicePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .add_systems(Update, handle_voice_requests.after(NetworkingSet));
+    }
+}
+
+fn handle_voice_requests(
+    mut requests: EventReader<VoiceRequest>,
+    server_state: Res<ServerState>,
+    settings: Res<VoiceSettings>,
+    moderation: Option<Res<ChatModeration>>,
+    transforms: Query<&Transform>,
+    time: Res<Time>,
+    // client_id -> (inicio de la ventana de 1 s, frames en ella)
+    mut rate: Local<HashMap<u32, (f64, u32)>>,
+) {
+    let now = time.elapsed_secs_f64();
+    let mut clients = server_state.clients.lock().unwrap();
+    rate.retain(|client_id, _| clients.contains_key(client_id));
+
+    for request in requests.read() {
+        match request.clone() {
+            VoiceRequest::TuneRadio { client_id, frequency } => {
+                let Some(client) = clients.get_mut(&client_id).filter(|client| client.player_id.is_some()) else { continue };
+                if frequency.is_some_and(|frequency| !RADIO_FREQUENCIES.contains(&frequency)) {
+                    client.send(&ServerMessage::ChatNotice {
+                        text: format!("Frecuencia inválida ({}-{})", RADIO_FREQUENCIES.start(), RADIO_FREQUENCIES.end()),
+                    });
+                    continue;
+                }
+                client.radio = frequency;
+                client.send(&ServerMessage::RadioTuned { frequency });
+            }
+            VoiceRequest::Frame { client_id, frame } => {
+                if frame.data.is_empty() || frame.data.len() > MAX_VOICE_PACKET {
+                    debug!(client_id, bytes = frame.data.len(), "🎙️ Frame de voz descartado por tamaño");
+                    continue;
+                }
+                let Some(speaker) = clients.get(&client_id).filter(|client| client.player_id.is_some()) else { continue };
+                // Quien está silenciado en el chat tampoco puede hablar
+                if moderation.as_ref().is_some_and(|moderation| moderation.muted_for(&speaker.player_name, now).is_some()) {
+                    continue;
+                }
+
+                let (window_start, frames) = rate.entry(client_id).or_insert((now, 0));
+                if now - *window_start >= 1.0 {
+                    *window_start = now;
+                    *frames = 0;
+                }
+                *frames += 1;
+                if *frames > settings.max_frames_per_second {
+                    continue;
+                }
+
+                relay(&mut clients, &transforms, &settings, client_id, frame);
+            }
+        }
+    }
+}
+
+/// Reparte un frame a quien puede oírlo
+fn relay(
+    clients: &mut HashMap<u32, ClientConnection>,
+    transforms: &Query<&Transform>,
+    settings: &VoiceSettings,
+    client_id: u32,
+    frame: VoiceFrame,
+) {
+    let position = |client: &ClientConnection| {
+        client.player_entity.and_then(|entity| transforms.get(entity).ok()).map(|transform| transform.translation)
+    };
+    let Some(speaker) = clients.get(&client_id) else { return };
+    let Some(player_id) = speaker.player_id else { return };
+
+    // (destinatario, ganancia, posición del que habla)
+    let recipients: Vec<(u32, f32, Option<Vec3>)> = match frame.channel {
+        VoiceChannel::Proximity => {
+            let Some(origin) = position(speaker) else { return };
+            clients
+                .iter()
+                .filter(|(id, client)| **id != client_id && client.player_id.is_some())
+                .filter_map(|(id, client)| {
+                    let gain = attenuation(position(client)?.distance(origin), settings.range);
+                    (gain > 0.0).then_some((*id, gain, Some(origin)))
+                })
+                .collect()
+        }
+        VoiceChannel::Radio { frequency } => {
+            // Solo se transmite en la frecuencia que se tiene sintonizada
+            if speaker.radio != Some(frequency) {
+                return;
+            }
+            clients
+                .iter()
+                .filter(|(id, client)| **id != client_id && client.player_id.is_some() && client.radio == Some(frequency))
+                .map(|(id, _)| (*id, 1.0, None))
+                .collect()
+        }
+    };
+
+    for (id, gain, position) in recipients {
+        if let Some(client) = clients.get_mut(&id) {
+            client.send(&ServerMessage::Voice(VoicePacket {
+                speaker: player_id,
+                channel: frame.channel,
+                codec: frame.codec,
+                sequence: frame.sequence,
+                gain,
+                position,
+                data: frame.data.clone(),
+            }));
+        }
+    }
+}
@@ -0,0 +1,545 @@
+//! Armas de fuego con el servidor como autoridad.
+//!
+//! El gatillo, la mira, la recarga y el selector llegan dentro de cada
+//! `PlayerInput` (`WeaponInput`); las pulsaciones se cuentan al llegar para no
+//! perder ninguna entre ticks. Cada jugador lleva un `WeaponState` con el arma
+//! en la mano (la primera que lleve puesta en Primary, Secondary o Holster) y
+//! cada tick se decide si dispara según el modo, la cadencia y las balas.
+//! Recargar rellena el arma con munición del inventario (los cargadores no son
+//! items) y, si la recámara estaba vacía, después hay que montarla.
+//!
+//! Cada disparo sale de los ojos hacia donde mira la cámara, con una dispersión
+//! que baja al apuntar, y vuela con la balística de `tn1_shared::ballistics`
+//! (rozamiento, gravedad, el viento del clima, penetración y rebotes) contra el
+//! terreno, los árboles, las rocas y las hitboxes. Los primeros
+//! `hitscan_range` metros se adelantan en el mismo tick contra las hitboxes de
+//! `HitboxHistory` tal como las veía el tirador (lag compensation); el resto
+//! del vuelo va tick a tick contra las actuales. Las balas dan en las zonas
+//! del cuerpo (`ZONE_HITBOXES`) y les quitan `BodyHealth` según la energía que
+//! les quede y la zona; se avisa a todos (`Hit`, y `Kill` si lo matan). Los
+//! brazos heridos abren la dispersión, que sale de un `WorldRng` sembrado con
+//! la semilla del mundo.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::HashMap;
+use tn1_shared::components::{BodyZone, Health, Player, PlayerId};
+use tn1_shared::chunk::WorldRng;
+use tn1_shared::ballistics::{
+    raycast_ground, raycast_props, Atmosphere, BallisticWorld, Impact, Launch, Material, ProjectileStore, RoundStats, SurfaceHit,
+};
+use tn1_shared::constants::INTERPOLATION_DELAY;
+use tn1_shared::inventory::{Inventory, ItemId};
+use tn1_shared::items::{EquipmentSlot, FireMode, ItemDefs, BURST_SIZE};
+use tn1_shared::medical::BodyHealth;
+use tn1_shared::protocol::{PlayerInput, ServerMessage, TICK_RATE};
+use tn1_shared::weapons::*;
+use crate::chunks::LoadedChunks;
+use crate::config::env_or;
+use crate::drops::props_along;
+use crate::lag_compensation::{Hitbox, HitboxHistory, HitboxSnapshot};
+use crate::networking::{ClientConnection, NetworkingSet, ServerState};
+use crate::physics::ground_height;
+use crate::world::WorldSettings;
+use crate::weather::WorldWeather;
+use crate::world::WorldTerrain;
+
+/// Ranuras de las que se coge el arma, por orden
+const WEAPON_SLOTS: [EquipmentSlot; 3] = [EquipmentSlot::Primary, EquipmentSlot::Secondary, EquipmentSlot::Holster];
+
+/// Input de un jugador con su entidad; `NetworkingPlugin` lo emite con cada `PlayerInput`
+#[derive(Event, Debug, Clone)]
+pub struct WeaponInput {
+    pub entity: Entity,
+    pub input: PlayerInput,
+}
+
+/// Disparo hecho (la bala ya descontada) que falta resolver
+#[derive(Event, Debug, Clone)]
+pub struct Shot {
+    pub shooter: Entity,
+    pub player_id: PlayerId,
+    /// Nombre del arma, para el aviso de muerte
+    pub weapon: String,
+    pub origin: Vec3,
+    /// Con la dispersión ya aplicada
+    pub direction: Vec3,
+    pub damage: f32,
+    /// Velocidad de salida (m/s)
+    pub speed: f32,
+    /// La bala de su munición
+    pub round: RoundStats,
+    /// Instante del servidor que veía el tirador al disparar
+    pub view_time: f64,
+}
+
+/// Un disparo dio a un jugador
+#[derive(Event, Debug, Clone)]
+pub struct PlayerHit {
+    pub shooter: PlayerId,
+    pub target: PlayerId,
+    /// Lo que ha quitado, ya multiplicado por la zona
+    pub damage: f32,
+    pub point: Vec3,
+    pub zone: BodyZone,
+    pub killed: bool,
+}
+
+/// Alcance y dispersión de los disparos (variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct WeaponSettings {
+    /// Metros que se resuelven al instante con lag compensation
+    pub hitscan_range: f32,
+    /// Metros a los que desaparece una bala
+    pub max_range: f32,
+    /// Dispersión (grados) disparando desde la cadera
+    pub hip_spread: f32,
+    /// Dispersión (grados) apuntando con la mira
+    pub aim_spread: f32,
+    /// Pasos de la balística por tick
+    pub substeps: u32,
+    /// Balas en vuelo a la vez como mucho
+    pub max_projectiles: usize,
+}
+
+impl Default for WeaponSettings {
+    fn default() -> Self {
+        Self {
+            hitscan_range: env_or("TN1_HITSCAN_RANGE", 100.0),
+            max_range: env_or("TN1_WEAPON_RANGE", 800.0),
+            hip_spread: env_or("TN1_HIP_SPREAD_DEG", 3.0),
+            aim_spread: env_or("TN1_AIM_SPREAD_DEG", 0.3),
+            substeps: env_or("TN1_BALLISTIC_SUBSTEPS", 4),
+            max_projectiles: env_or("TN1_MAX_PROJECTILES", 2048),
+        }
+    }
+}
+
+/// Arma en la mano de un jugador y lo que ha pedido desde el último tick
+#[derive(Component, Debug, Default)]
+pub struct WeaponState {
+    held: Option<ItemId>,
+    /// Índice en `WeaponStats::modes`
+    mode: usize,
+    phase: WeaponPhase,
+    /// Segundo del servidor en que acaba la recarga o montar el arma
+    phase_ends: f64,
+    next_shot: f64,
+    burst_left: u32,
+    /// Veces que se apretó el gatillo desde el último tick
+    pulls: u32,
+    trigger: bool,
+    aiming: bool,
+    yaw: f32,
+    pitch: f32,
+    reload_held: bool,
+    reload_requested: bool,
+    switch_held: bool,
+    switch_requested: bool,
+    /// Lo último que se mandó al cliente
+    sent: Option<Option<WeaponStatus>>,
+}
+
+impl WeaponState {
+    /// Arma nueva en la mano: empieza lista y en su primer modo
+    fn hold(&mut self, item: Option<ItemId>) {
+        self.held = item;
+        self.mode = 0;
+        self.phase = WeaponPhase::Ready;
+        self.burst_left = 0;
+    }
+}
+
+/// Balas en vuelo, cada una con el nombre de su arma para el aviso de muerte
+#[derive(Resource)]
+struct Bullets(ProjectileStore<String>);
+
+/// Azar de la dispersión, determinista con la semilla del mundo
+#[derive(Resource)]
+struct SpreadRng(WorldRng);
+
+/// Disparos de los jugadores; necesita `InventoryPlugin` y `LagCompensationPlugin`.
+/// Con `WorldPlugin` y `ChunkPlugin` el terreno y sus props paran las balas, y
+/// con `WeatherPlugin` el viento y la temperatura cambian su vuelo
+#[derive(Default)]
+pub struct WeaponsPlugin {
+    pub settings: WeaponSettings,
+}
+
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        let bullets = ProjectileStore::new(self.settings.max_projectiles, self.settings.substeps, self.settings.max_range);
+        app.insert_resource(self.settings.clone())
+            .insert_resource(Bullets(bullets))
+            .add_event::<Shot>()
+            .add_event::<PlayerHit>()
+            .add_systems(Startup, seed_spread)
+            .add_systems(Update, (
+                attach_weapon_states,
+                read_weapon_inputs,
+                update_weapons,
+                fly_bullets,
+                resolve_shots,
+            ).chain().after(NetworkingSet));
+    }
+}
+
+/// Sin `WorldPlugin`, la semilla de `TN1_WORLD_SEED`
+fn seed_spread(mut commands: Commands, world_settings: Option<Res<WorldSettings>>) {
+    let seed = world_settings.map_or_else(|| WorldSettings::default().seed, |settings| settings.seed);
+    // Otra secuencia que la del terreno y la del clima con la misma semilla
+    commands.insert_resource(SpreadRng(WorldRng::new(seed ^ 0x5C47_7E12)));
+}
+
+fn attach_weapon_states(mut commands: Commands, players: Query<Entity, (With<Player>, Without<WeaponState>)>) {
+    for entity in players.iter() {
+        commands.entity(entity).insert(WeaponState::default());
+    }
+}
+
+/// Guarda el último estado de los botones y cuenta las pulsaciones nuevas
+fn read_weapon_inputs(mut inputs: EventReader<WeaponInput>, mut states: Query<&mut WeaponState>) {
+    for WeaponInput { entity, input } in inputs.read() {
+        let Ok(mut state) = states.get_mut(*entity) else { continue };
+        if input.fire && !state.trigger {
+            state.pulls += 1;
+        }
+        if input.reload && !state.reload_held {
+            state.reload_requested = true;
+        }
+        if input.switch_fire_mode && !state.switch_held {
+            state.switch_requested = true;
+        }
+        state.trigger = input.fire;
+        state.reload_held = input.reload;
+        state.switch_held = input.switch_fire_mode;
+        state.aiming = input.aim;
+        state.yaw = input.camera_yaw;
+        state.pitch = input.camera_pitch;
+    }
+}
+
+type Shooters<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static PlayerId, &'static Transform, &'static Health, &'static BodyHealth, &'static mut Inventory, &'static mut WeaponState),
+    With<Player>,
+>;
+
+/// Selector, recarga y gatillo de cada jugador; manda a cada uno su arma si cambió
+fn update_weapons(
+    mut players: Shooters,
+    defs: Res<ItemDefs>,
+    settings: Res<WeaponSettings>,
+    server_state: Res<ServerState>,
+    time: Res<Time>,
+    mut rng: ResMut<SpreadRng>,
+    mut shots: EventWriter<Shot>,
+) {
+    let now = time.elapsed_secs_f64();
+    let mut clients = server_state.clients.lock().unwrap();
+    for (entity, player_id, transform, health, body, mut inventory, mut state) in players.iter_mut() {
+        let state = &mut *state;
+        let mut client = clients.values_mut().find(|client| client.player_entity == Some(entity));
+        let held = WEAPON_SLOTS.iter().filter_map(|slot| inventory.equipment.get(*slot)).find_map(|placed| {
+            let def = defs.get(&placed.stack.item)?;
+            Some((placed.stack.id, placed.stack.ammo.unwrap_or(0), def, def.weapon.as_ref()?))
+        });
+        if state.held != held.map(|(item, ..)| item) {
+            state.hold(held.map(|(item, ..)| item));
+        }
+        let Some((item, mut ammo, def, stats)) = held else {
+            state.pulls = 0;
+            if state.sent != Some(None) {
+                state.sent = Some(None);
+                if let Some(client) = client.as_mut() {
+                    client.send(&ServerMessage::WeaponState(None));
+                }
+            }
+            continue;
+        };
+
+        // Los muertos no tocan el arma
+        let pulled = std::mem::take(&mut state.pulls) > 0 && health.current > 0.0;
+        let reload = std::mem::take(&mut state.reload_requested) && health.current > 0.0;
+        if std::mem::take(&mut state.switch_requested) && stats.modes.len() > 1 {
+            state.mode = (state.mode + 1) % stats.modes.len();
+            state.burst_left = 0;
+        }
+        let mode = stats.modes[state.mode.min(stats.modes.len() - 1)];
+
+        let full = stats.magazine + u32::from(ammo > 0);
+        if reload && state.phase == WeaponPhase::Ready && ammo < full && inventory.count(&stats.ammo) > 0 {
+            state.phase = WeaponPhase::Reloading { remaining: stats.reload_time };
+            state.phase_ends = now + stats.reload_time as f64;
+            state.burst_left = 0;
+        }
+
+        let mut diffs = Vec::new();
+        if state.phase != WeaponPhase::Ready && now >= state.phase_ends {
+            state.phase = match state.phase {
+                WeaponPhase::Reloading { .. } => {
+                    // Con una bala en la recámara cabe el cargador entero detrás
+                    let chambered = ammo > 0;
+                    let (taken, taken_diffs) = inventory.take(&stats.ammo, full.saturating_sub(ammo));
+                    ammo += taken;
+                    diffs.extend(taken_diffs);
+                    diffs.extend(inventory.set_ammo(item, ammo));
+                    if chambered || ammo == 0 {
+                        WeaponPhase::Ready
+                    } else {
+                        state.phase_ends = now + stats.chamber_time as f64;
+                        WeaponPhase::Chambering { remaining: stats.chamber_time }
+                    }
+                }
+                _ => WeaponPhase::Ready,
+            };
+        }
+
+        if mode == FireMode::Burst && pulled && state.burst_left == 0 {
+            state.burst_left = BURST_SIZE;
+        }
+        let wants = match mode {
+            FireMode::Semi => pulled,
+            FireMode::Burst => state.burst_left > 0,
+            FireMode::Auto => state.trigger && health.current > 0.0,
+        };
+        if wants && state.phase == WeaponPhase::Ready && ammo > 0 && now >= state.next_shot {
+            ammo -= 1;
+            diffs.extend(inventory.set_ammo(item, ammo));
+            state.burst_left = state.burst_left.saturating_sub(1);
+            // Sin perder cadencia por redondear al tick
+            state.next_shot = state.next_shot.max(now - 1.0 / TICK_RATE as f64) + 60.0 / stats.fire_rate as f64;
+            let spread = (if state.aiming { settings.aim_spread } else { settings.hip_spread }) + body.sway();
+            let rtt = client.as_ref().map_or(0.0, |client| client.rtt);
+            shots.send(Shot {
+                shooter: entity,
+                player_id: *player_id,
+                weapon: def.name.clone(),
+                origin: muzzle_origin(transform.translation),
+                direction: scatter(aim_direction(state.yaw, state.pitch), spread.to_radians(), &mut rng.0),
+                damage: stats.damage,
+                speed: stats.muzzle_velocity,
+                round: defs.get(&stats.ammo).and_then(|ammo| ammo.round).unwrap_or_default(),
+                view_time: HitboxHistory::estimate_view_time(now, rtt, INTERPOLATION_DELAY),
+            });
+        } else if ammo == 0 {
+            state.burst_left = 0;
+        }
+
+        let status = WeaponStatus {
+            item,
+            key: def.key.clone(),
+            mode,
+            fire_rate: stats.fire_rate,
+            phase: state.phase,
+            ammo,
+            chambered: ammo > 0 && !matches!(state.phase, WeaponPhase::Chambering { .. }),
+            reserve: inventory.count(&stats.ammo),
+            aiming: state.aiming,
+        };
+        let Some(client) = client else { continue };
+        if !diffs.is_empty() {
+            client.send(&ServerMessage::InventoryUpdate { sequence: None, diffs });
+        }
+        if state.sent.as_ref() != Some(&Some(status.clone())) {
+            client.send(&ServerMessage::WeaponState(Some(status.clone())));
+            state.sent = Some(Some(status));
+        }
+    }
+}
+
+/// Desvía la dirección un ángulo al azar dentro de un cono de `spread` radianes
+fn scatter(direction: Vec3, spread: f32, rng: &mut WorldRng) -> Vec3 {
+    if spread <= 0.0 {
+        return direction;
+    }
+    let (a, b) = direction.any_orthonormal_pair();
+    let angle = spread * rng.next_f32().sqrt();
+    let around = rng.range(0.0..std::f32::consts::TAU);
+    (direction * angle.cos() + (a * around.cos() + b * around.sin()) * angle.sin()).normalize()
+}
+
+/// Lo que necesitan las balas para volar
+#[derive(SystemParam)]
+struct ShotWorld<'w> {
+    settings: Res<'w, WeaponSettings>,
+    bullets: ResMut<'w, Bullets>,
+    terrain: Option<ResMut<'w, WorldTerrain>>,
+    chunks: Option<Res<'w, LoadedChunks>>,
+    weather: Option<Res<'w, WorldWeather>>,
+}
+
+impl ShotWorld<'_> {
+    fn atmosphere(&self) -> Atmosphere {
+        self.weather.as_ref().map_or_else(Atmosphere::default, |weather| Atmosphere::from_weather(&weather.weather()))
+    }
+
+    /// Todas las balas un tick, contra `players`
+    fn step(&mut self, dt: f32, players: &[HitboxSnapshot], impacts: &mut Vec<Impact<String>>) {
+        let atmosphere = self.atmosphere();
+        let mut world = BulletWorld { terrain: self.terrain.as_deref_mut(), chunks: self.chunks.as_deref(), players };
+        self.bullets.0.step(dt, &atmosphere, &mut world, impacts);
+    }
+
+    /// Dispara y adelanta la bala los primeros `hitscan_range` metros contra `players`
+    fn launch(&mut self, launch: Launch<String>, dt: f32, players: &[HitboxSnapshot], impacts: &mut Vec<Impact<String>>) {
+        let Some(handle) = self.bullets.0.launch(launch) else {
+            warn!(capacity = self.bullets.0.capacity(), "⚠️ Demasiadas balas en vuelo, se pierde un disparo");
+            return;
+        };
+        let atmosphere = self.atmosphere();
+        let range = self.settings.hitscan_range;
+        let mut world = BulletWorld { terrain: self.terrain.as_deref_mut(), chunks: self.chunks.as_deref(), players };
+        self.bullets.0.advance(handle, range, dt, &atmosphere, &mut world, impacts);
+    }
+}
+
+/// El mundo tal como lo cruzan las balas: el terreno (o el plano y = 0), los
+/// props de los chunks cargados y las hitboxes de los jugadores vivos
+struct BulletWorld<'a> {
+    terrain: Option<&'a mut WorldTerrain>,
+    chunks: Option<&'a LoadedChunks>,
+    players: &'a [HitboxSnapshot],
+}
+
+impl BallisticWorld for BulletWorld<'_> {
+    fn raycast(&mut self, from: Vec3, to: Vec3, shooter: PlayerId) -> Option<SurfaceHit> {
+        let (direction, length) = (to - from).try_normalize().map(|direction| (direction, from.distance(to)))?;
+        let terrain = &mut self.terrain;
+        let ground = raycast_ground(|x, z| ground_height(terrain.as_deref_mut(), Vec3::new(x, 0.0, z)), from, to);
+        let props = match (self.terrain.as_deref(), self.chunks) {
+            (Some(terrain), Some(chunks)) => raycast_props(props_along(terrain.grid, chunks, from, to), from, to),
+            _ => None,
+        };
+        let players = self.players.iter().filter(|snapshot| snapshot.player_id != shooter).filter_map(|snapshot| {
+            let (zone, entry, exit) = snapshot.zone_span(from, direction, length)?;
+            // Desde dentro es la bala que acaba de atravesarlo
+            if entry <= 0.0 {
+                return None;
+            }
+            let point = from + direction * entry;
+            let normal = (point - snapshot.zone_axis(zone, point)).try_normalize().unwrap_or(-direction);
+            Some(SurfaceHit { point, normal, material: Material::Flesh, thickness: exit - entry, target: Some((snapshot.entity, zone)) })
+        });
+        [ground, props].into_iter().flatten().chain(players).min_by(|a, b| from.distance_squared(a.point).total_cmp(&from.distance_squared(b.point)))
+    }
+}
+
+type Targets<'w, 's> =
+    Query<'w, 's, (Entity, &'static PlayerId, &'static Transform, &'static mut Health, &'static mut BodyHealth), With<Player>>;
+
+/// Hitboxes actuales de los jugadores vivos
+fn live_hitboxes(targets: &Targets) -> Vec<HitboxSnapshot> {
+    targets
+        .iter()
+        .filter(|(.., health, _)| health.current > 0.0)
+        .map(|(entity, player_id, transform, ..)| HitboxSnapshot {
+            entity,
+            player_id: *player_id,
+            position: transform.translation,
+            rotation: transform.rotation,
+            hitbox: Hitbox::default(),
+        })
+        .collect()
+}
+
+/// Quita vida a la zona de cada bala que dio a un jugador y avisa a todos;
+/// `Kill` solo si ese impacto lo mata
+fn apply_hits(
+    impacts: Vec<Impact<String>>,
+    targets: &mut Targets,
+    clients: &mut HashMap<u32, ClientConnection>,
+    hits: &mut EventWriter<PlayerHit>,
+) {
+    for impact in impacts {
+        let Some((entity, zone)) = impact.target else { continue };
+        let Ok((_, target, _, mut health, mut body)) = targets.get_mut(entity) else { continue };
+        let was_alive = health.current > 0.0;
+        let damage = impact.damage * zone.damage_multiplier();
+        body.damage(zone, damage);
+        // El resumen al momento, para que el siguiente disparo del tick lo vea muerto
+        *health = body.health();
+        let killed = was_alive && health.current <= 0.0;
+        let target = *target;
+
+        let name = |player_id: PlayerId| {
+            clients.values().find(|client| client.player_id == Some(player_id)).map_or_else(String::new, |client| client.player_name.clone())
+        };
+        let (shooter_name, target_name) = (name(impact.shooter), name(target));
+        debug!(shooter = %shooter_name, target = %target_name, ?zone, damage, health = health.current, "🎯 Impacto");
+        let hit = ServerMessage::Hit { shooter: impact.shooter, target, damage, point: impact.point };
+        for client in clients.values_mut().filter(|client| client.player_id.is_some()) {
+            client.send(&hit);
+        }
+        if killed {
+            info!(killer = %shooter_name, victim = %target_name, weapon = %impact.payload, "💀 Muerte por disparo");
+            let kill = ServerMessage::Kill {
+                killer: impact.shooter,
+                killer_name: shooter_name,
+                victim: target,
+                victim_name: target_name,
+                weapon: impact.payload.clone(),
+            };
+            for client in clients.values_mut().filter(|client| client.player_id.is_some()) {
+                client.send(&kill);
+            }
+        }
+        hits.send(PlayerHit { shooter: impact.shooter, target, damage, point: impact.point, zone, killed });
+    }
+}
+
+/// Dispara las balas nuevas; los primeros metros, contra el mundo que veía el tirador
+fn resolve_shots(
+    mut shots: EventReader<Shot>,
+    history: Res<HitboxHistory>,
+    mut world: ShotWorld,
+    mut targets: Targets,
+    server_state: Res<ServerState>,
+    time: Res<Time>,
+    mut hits: EventWriter<PlayerHit>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    let mut impacts = Vec::new();
+    for shot in shots.read() {
+        let fired = ServerMessage::ShotFired { shooter: shot.player_id, origin: shot.origin, direction: shot.direction };
+        for client in clients.values_mut().filter(|client| client.player_id.is_some() && client.player_entity != Some(shot.shooter)) {
+            client.send(&fired);
+        }
+
+        // Los muertos no paran balas
+        let mut players = history.rewind(shot.view_time).map_or_else(|| live_hitboxes(&targets), |rewound| rewound.players);
+        players.retain(|snapshot| targets.get(snapshot.entity).is_ok_and(|(.., health, _)| health.current > 0.0));
+        let launch = Launch {
+            payload: shot.weapon.clone(),
+            shooter: shot.player_id,
+            origin: shot.origin,
+            direction: shot.direction,
+            muzzle_velocity: shot.speed,
+            round: shot.round,
+            damage: shot.damage,
+        };
+        world.launch(launch, time.delta_secs(), &players, &mut impacts);
+        // Cada disparo ve el daño de los anteriores
+        apply_hits(std::mem::take(&mut impacts), &mut targets, &mut clients, &mut hits);
+    }
+}
+
+/// Avanza las balas en vuelo un tick contra las hitboxes actuales
+fn fly_bullets(
+    mut world: ShotWorld,
+    mut targets: Targets,
+    server_state: Res<ServerState>,
+    time: Res<Time>,
+    mut hits: EventWriter<PlayerHit>,
+) {
+    if world.bullets.0.is_empty() {
+        return;
+    }
+    let players = live_hitboxes(&targets);
+    let mut impacts = Vec::new();
+    world.step(time.delta_secs(), &players, &mut impacts);
+    if !impacts.is_empty() {
+        apply_hits(impacts, &mut targets, &mut server_state.clients.lock().unwrap(), &mut hits);
+    }
+}
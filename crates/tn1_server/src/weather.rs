@@ -0,0 +1,281 @@
+//! Clima dinámico: una máquina de estados de frentes que entran, duran un rato
+//! y se van, con viento y temperatura, replicada a los clientes.
+//!
+//! Cada frente dura entre `front_duration.0` y `front_duration.1` segundos; al
+//! acabar se elige el siguiente según `WeatherKind::transitions` y el actual se
+//! desvanece antes de que entre el nuevo, así que la visibilidad nunca salta.
+//! La temperatura sigue el ciclo del día más lo que enfríe el frente, y con ella
+//! cambia la temperatura corporal de cada jugador.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use tn1_shared::chunk::WorldRng;
+use tn1_shared::components::{Player, PlayerController};
+use tn1_shared::protocol::ServerMessage;
+use tn1_shared::weather::*;
+use crate::config::env_or;
+use crate::networking::{NetworkingSet, ServerState};
+use crate::world::WorldSettings;
+
+/// Cada cuántos segundos se reenvía el clima (intensidad, viento y temperatura cambian poco a poco)
+const WEATHER_SYNC_INTERVAL: f64 = 2.0;
+/// Grados de diferencia entre la hora más fría (3:00) y la más cálida (15:00)
+const DAILY_SWING: f32 = 12.0;
+/// m/s que puede cambiar el viento por segundo
+const WIND_CHANGE_RATE: f32 = 0.5;
+
+/// Duración de los frentes y clima de partida (variables de entorno / .env)
+#[derive(Resource, Debug, Clone)]
+pub struct WeatherSettings {
+    /// Clima al arrancar (TN1_WEATHER); `None` = despejado
+    pub start: Option<WeatherKind>,
+    /// Segundos mínimos y máximos que dura un frente
+    pub front_duration: (f32, f32),
+    /// Segundos que tarda un frente en entrar (y otros tantos en irse)
+    pub transition: f32,
+    /// Temperatura media del día (°C)
+    pub base_temperature: f32,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        Self {
+            start: std::env::var("TN1_WEATHER").ok().and_then(|kind| kind.parse().ok()),
+            front_duration: (env_or("TN1_WEATHER_MIN_SECS", 300.0), env_or("TN1_WEATHER_MAX_SECS", 900.0)),
+            transition: env_or("TN1_WEATHER_TRANSITION_SECS", 60.0),
+            base_temperature: env_or("TN1_BASE_TEMPERATURE", 12.0),
+        }
+    }
+}
+
+/// Clima del servidor; necesita `WorldPlugin` para la hora y la semilla
+#[derive(Default)]
+pub struct WeatherPlugin {
+    pub settings: WeatherSettings,
+}
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .add_systems(Startup, start_weather)
+            .add_systems(Update, (
+                update_weather,
+                update_body_temperatures,
+            ).chain().run_if(resource_exists::<WorldWeather>))
+            .add_systems(Update, (broadcast_weather, send_body_temperatures)
+                .after(update_body_temperatures)
+                .after(NetworkingSet)
+                .run_if(resource_exists::<WorldWeather>)
+                .run_if(resource_exists::<ServerState>));
+    }
+}
+
+/// Temperatura corporal de un jugador (°C)
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BodyTemperature(pub f32);
+
+impl Default for BodyTemperature {
+    fn default() -> Self {
+        Self(NORMAL_BODY_TEMPERATURE)
+    }
+}
+
+/// Estado de la máquina de clima
+#[derive(Resource)]
+pub struct WorldWeather {
+    weather: Weather,
+    /// Frente que espera a que el actual se desvanezca
+    next: Option<WeatherKind>,
+    /// Segundos que le quedan al frente actual
+    remaining: f32,
+    wind_target: Vec2,
+    rng: WorldRng,
+}
+
+impl WorldWeather {
+    pub fn new(seed: u64, kind: WeatherKind, settings: &WeatherSettings) -> Self {
+        let mut weather = Self {
+            weather: Weather { kind, intensity: 1.0, temperature: settings.base_temperature, ..default() },
+            next: None,
+            remaining: 0.0,
+            wind_target: Vec2::ZERO,
+            // Otra secuencia que la del terreno con la misma semilla
+            rng: WorldRng::new(seed ^ 0x5EA7_4E52),
+        };
+        weather.begin_front(kind, settings);
+        weather.weather.wind = weather.wind_target;
+        weather
+    }
+
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+
+    /// El frente que viene, si el actual se está yendo
+    pub fn next(&self) -> Option<WeatherKind> {
+        self.next
+    }
+
+    /// Cambia el clima al instante (admin, tests) y lo mantiene un frente entero
+    pub fn set(&mut self, kind: WeatherKind, settings: &WeatherSettings) {
+        info!("🌦️ Clima forzado: {}", kind);
+        self.next = None;
+        self.begin_front(kind, settings);
+        self.weather.intensity = 1.0;
+        self.weather.wind = self.wind_target;
+    }
+
+    fn begin_front(&mut self, kind: WeatherKind, settings: &WeatherSettings) {
+        let (min, max) = settings.front_duration;
+        self.weather.kind = kind;
+        self.remaining = if max > min { self.rng.range(min..max) } else { min };
+        let wind = kind.wind_range();
+        let speed = if wind.end > wind.start { self.rng.range(wind) } else { wind.start };
+        let angle = self.rng.range(0.0..std::f32::consts::TAU);
+        self.wind_target = Vec2::new(angle.cos(), angle.sin()) * speed;
+    }
+
+    /// Elige el siguiente frente según los pesos de transición
+    fn pick_next(&mut self) -> WeatherKind {
+        let transitions = self.weather.kind.transitions();
+        let total: f32 = transitions.iter().map(|(_, weight)| weight).sum();
+        let mut roll = self.rng.range(0.0..total);
+        for (kind, weight) in transitions {
+            if roll < *weight {
+                return *kind;
+            }
+            roll -= weight;
+        }
+        transitions[transitions.len() - 1].0
+    }
+
+    /// Avanza `seconds` segundos a la hora del mundo `hour`
+    pub fn advance(&mut self, seconds: f32, hour: f32, settings: &WeatherSettings) {
+        let ramp = if settings.transition > 0.0 { seconds / settings.transition } else { 1.0 };
+        match self.next {
+            // El frente actual se va; cuando desaparece entra el siguiente
+            Some(next) => {
+                self.weather.intensity = (self.weather.intensity - ramp).max(0.0);
+                if self.weather.intensity <= 0.0 {
+                    info!("🌦️ Entra un frente: {}", next);
+                    self.next = None;
+                    self.begin_front(next, settings);
+                }
+            }
+            None => {
+                self.weather.intensity = (self.weather.intensity + ramp).min(1.0);
+                self.remaining -= seconds;
+                if self.remaining <= 0.0 {
+                    let next = self.pick_next().for_temperature(self.air_temperature(hour, settings));
+                    debug!("🌦️ {} se va, viene {}", self.weather.kind, next);
+                    self.next = Some(next);
+                }
+            }
+        }
+
+        let wind_step = WIND_CHANGE_RATE * seconds;
+        let delta = self.wind_target - self.weather.wind;
+        self.weather.wind += if delta.length() <= wind_step { delta } else { delta.normalize() * wind_step };
+        self.weather.temperature = self.air_temperature(hour, settings)
+            - self.weather.kind.temperature_drop() * self.weather.intensity;
+    }
+
+    /// Temperatura del día a esa hora, sin el frente: mínima a las 3:00 y máxima a las 15:00
+    fn air_temperature(&self, hour: f32, settings: &WeatherSettings) -> f32 {
+        let angle = (hour - 15.0) / 24.0 * std::f32::consts::TAU;
+        settings.base_temperature + DAILY_SWING / 2.0 * angle.cos()
+    }
+}
+
+fn start_weather(mut commands: Commands, world_settings: Res<WorldSettings>, settings: Res<WeatherSettings>) {
+    let kind = settings.start.unwrap_or_default();
+    let mut weather = WorldWeather::new(world_settings.seed, kind, &settings);
+    weather.advance(0.0, world_settings.current_time, &settings);
+    info!(
+        "🌦️ Clima inicial: {} ({:.1} °C, viento {:.1} m/s)",
+        kind,
+        weather.weather.temperature,
+        weather.weather.wind_speed()
+    );
+    commands.insert_resource(weather);
+}
+
+fn update_weather(
+    mut weather: ResMut<WorldWeather>,
+    world_settings: Res<WorldSettings>,
+    settings: Res<WeatherSettings>,
+    time: Res<Time>,
+) {
+    weather.advance(time.delta_secs(), world_settings.current_time, &settings);
+}
+
+/// El frío (según la sensación térmica) baja la temperatura de los jugadores;
+/// moverse y el buen tiempo la recuperan
+fn update_body_temperatures(
+    mut commands: Commands,
+    weather: Res<WorldWeather>,
+    time: Res<Time>,
+    mut players: Query<(Entity, &PlayerController, Option<&mut BodyTemperature>), With<Player>>,
+) {
+    let weather = weather.weather();
+    for (entity, controller, body) in players.iter_mut() {
+        let Some(mut body) = body else {
+            commands.entity(entity).insert(BodyTemperature::default());
+            continue;
+        };
+        let exertion = (controller.velocity.xz().length() / controller.speed.max(1.0)).min(1.0);
+        let was_cold = body.0 < HYPOTHERMIA;
+        body.0 = update_body_temperature(&weather, body.0, exertion, time.delta_secs());
+        if !was_cold && body.0 < HYPOTHERMIA {
+            debug!("🥶 Un jugador entra en hipotermia ({:.1} °C)", body.0);
+        }
+    }
+}
+
+/// Manda el clima a quien acaba de entrar, a todos al cambiar de frente y,
+/// cada `WEATHER_SYNC_INTERVAL`, a todos
+fn broadcast_weather(
+    weather: Res<WorldWeather>,
+    server_state: Res<ServerState>,
+    time: Res<Time>,
+    mut informed: Local<HashSet<u32>>,
+    mut last_sync: Local<f64>,
+    mut last_kind: Local<Option<WeatherKind>>,
+) {
+    let now = time.elapsed_secs_f64();
+    let weather = weather.weather();
+    let sync_all = now - *last_sync >= WEATHER_SYNC_INTERVAL || *last_kind != Some(weather.kind);
+    if sync_all {
+        *last_sync = now;
+        *last_kind = Some(weather.kind);
+    }
+
+    let message = ServerMessage::Weather(weather);
+    let mut clients = server_state.clients.lock().unwrap();
+    informed.retain(|client_id| clients.contains_key(client_id));
+    for (client_id, client) in clients.iter_mut() {
+        if client.player_id.is_none() {
+            continue;
+        }
+        if informed.insert(*client_id) || sync_all {
+            client.send(&message);
+        }
+    }
+}
+
+/// Cada jugador recibe su temperatura corporal cuando cambia una décima
+fn send_body_temperatures(
+    server_state: Res<ServerState>,
+    bodies: Query<&BodyTemperature>,
+    mut sent: Local<HashMap<u32, i32>>,
+) {
+    let mut clients = server_state.clients.lock().unwrap();
+    sent.retain(|client_id, _| clients.contains_key(client_id));
+    for (client_id, client) in clients.iter_mut() {
+        let Some(body) = client.player_entity.and_then(|entity| bodies.get(entity).ok()) else { continue };
+        let tenths = (body.0 * 10.0).round() as i32;
+        if sent.insert(*client_id, tenths) != Some(tenths) {
+            client.send(&ServerMessage::BodyTemperature { celsius: tenths as f32 / 10.0 });
+        }
+    }
+}
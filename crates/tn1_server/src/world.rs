@@ -1,13 +1,30 @@
 use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use tn1_shared::chunk::{ChunkCoord, ChunkGrid};
+use tn1_shared::components::Player;
+use tn1_shared::daylight::WorldClock;
+use tn1_shared::protocol::ServerMessage;
+use tn1_shared::terrain::{Heightfield, TerrainGenerator, COLLIDER_RESOLUTION};
+use crate::config::env_or;
+use crate::networking::{NetworkingSet, ServerState};
+
+/// Cada cuántos segundos se reenvía la hora a todos para corregir la deriva
+const TIME_SYNC_INTERVAL: f64 = 10.0;
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app
-            .insert_resource(WorldSettings::default())
-            .add_systems(Startup, initialize_world)
-            .add_systems(Update, update_world_time);
+            // Se respetan los ajustes ya insertados (tests, reproducción de demos)
+            .init_resource::<WorldSettings>()
+            // Antes de `Startup` para que la rejilla exista en los sistemas de arranque
+            .add_systems(PreStartup, initialize_world)
+            .add_systems(Update, (update_world_time, unload_terrain_colliders))
+            .add_systems(Update, broadcast_world_time
+                .after(update_world_time)
+                .after(NetworkingSet)
+                .run_if(resource_exists::<ServerState>));
     }
 }
 
@@ -15,8 +32,12 @@ impl Plugin for WorldPlugin {
 pub struct WorldSettings {
     pub world_size: f32,
     pub chunk_size: f32,
+    /// Horas del mundo por hora real (TN1_TIME_SCALE)
     pub time_scale: f32,
+    /// Hora del día en `[0, 24)` (TN1_START_HOUR al arrancar)
     pub current_time: f32,
+    /// Semilla de la generación procedural (TN1_WORLD_SEED)
+    pub seed: u64,
 }
 
 impl Default for WorldSettings {
@@ -24,29 +45,107 @@ impl Default for WorldSettings {
         Self {
             world_size: 5000.0,  // 5km x 5km
             chunk_size: 100.0,   // chunks de 100x100 metros
-            time_scale: 1.0,
-            current_time: 12.0,  // Mediodía
+            time_scale: env_or("TN1_TIME_SCALE", 24.0), // un día por hora real
+            current_time: env_or("TN1_START_HOUR", 12.0), // Mediodía
+            seed: env_or("TN1_WORLD_SEED", 1),
         }
     }
 }
 
-fn initialize_world(world_settings: Res<WorldSettings>) {
+fn initialize_world(mut commands: Commands, world_settings: Res<WorldSettings>) {
+    let grid = ChunkGrid::new(world_settings.world_size, world_settings.chunk_size);
     info!(
-        "Mundo inicializado: {}x{} metros, chunks de {}m",
+        "Mundo inicializado: {}x{} metros, chunks de {}m ({}x{} chunks, semilla {})",
         world_settings.world_size,
         world_settings.world_size,
-        world_settings.chunk_size
+        world_settings.chunk_size,
+        grid.chunks_per_side(),
+        grid.chunks_per_side(),
+        world_settings.seed
     );
+    // La rejilla y el terreno son fijos durante toda la partida
+    commands.insert_resource(grid);
+    commands.insert_resource(WorldTerrain::new(grid, TerrainGenerator::new(world_settings.seed)));
+}
+
+/// Colliders de terreno (heightfields) de los chunks donde hay jugadores.
+///
+/// Se generan al primer contacto y se descartan cuando ya no hay nadie en el
+/// chunk ni en sus vecinos; como salen de la semilla, regenerarlos da lo mismo.
+#[derive(Resource)]
+pub struct WorldTerrain {
+    pub grid: ChunkGrid,
+    pub generator: TerrainGenerator,
+    colliders: HashMap<ChunkCoord, Heightfield>,
+}
+
+impl WorldTerrain {
+    pub fn new(grid: ChunkGrid, generator: TerrainGenerator) -> Self {
+        Self { grid, generator, colliders: HashMap::new() }
+    }
+
+    /// Altura del suelo bajo la posición, sobre el mismo mallado que ve el cliente
+    pub fn ground_height(&mut self, position: Vec3) -> f32 {
+        let coord = self.grid.coord_at(position);
+        let local = position - self.grid.origin(coord);
+        let (grid, generator) = (self.grid, self.generator);
+        self.colliders
+            .entry(coord)
+            .or_insert_with(|| generator.heightfield(&grid, coord, COLLIDER_RESOLUTION))
+            .height_at(local.x, local.z)
+    }
+
+    /// Chunks con collider generado
+    pub fn colliders(&self) -> impl Iterator<Item = ChunkCoord> + '_ {
+        self.colliders.keys().copied()
+    }
+}
+
+fn unload_terrain_colliders(mut terrain: ResMut<WorldTerrain>, players: Query<&Transform, With<Player>>) {
+    let grid = terrain.grid;
+    let occupied: Vec<ChunkCoord> = players.iter().map(|transform| grid.coord_at(transform.translation)).collect();
+    terrain.colliders.retain(|coord, _| occupied.iter().any(|occupied| occupied.distance(*coord) <= 1));
+}
+
+impl WorldSettings {
+    /// Reloj del mundo tal como lo ven los clientes
+    pub fn clock(&self) -> WorldClock {
+        WorldClock::new(self.current_time, self.time_scale)
+    }
 }
 
 fn update_world_time(
     mut world_settings: ResMut<WorldSettings>,
     time: Res<Time>,
 ) {
-    // Actualizar tiempo del mundo (24 horas = 1 hora real por defecto)
-    world_settings.current_time += time.delta_secs() * world_settings.time_scale / 3600.0;
-    
-    if world_settings.current_time >= 24.0 {
-        world_settings.current_time -= 24.0;
+    let mut clock = world_settings.clock();
+    clock.advance(time.delta_secs());
+    world_settings.current_time = clock.hour;
+}
+
+/// Manda la hora a quien acaba de entrar y, cada `TIME_SYNC_INTERVAL`, a todos
+fn broadcast_world_time(
+    world_settings: Res<WorldSettings>,
+    server_state: Res<ServerState>,
+    time: Res<Time>,
+    mut informed: Local<HashSet<u32>>,
+    mut last_sync: Local<f64>,
+) {
+    let now = time.elapsed_secs_f64();
+    let sync_all = now - *last_sync >= TIME_SYNC_INTERVAL;
+    if sync_all {
+        *last_sync = now;
+    }
+
+    let message = ServerMessage::WorldTime(world_settings.clock());
+    let mut clients = server_state.clients.lock().unwrap();
+    informed.retain(|client_id| clients.contains_key(client_id));
+    for (client_id, client) in clients.iter_mut() {
+        if client.player_id.is_none() {
+            continue;
+        }
+        if informed.insert(*client_id) || sync_all {
+            client.send(&message);
+        }
     }
-}
\ No newline at end of file
+}
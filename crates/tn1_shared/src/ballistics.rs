@@ -0,0 +1,565 @@
+//! Balística de las balas en vuelo (ver `docs/SYSTEMS/BALLISTICS_SYSTEM.md`).
+//!
+//! Cada bala se integra con Euler semi-implícito en `substeps` pasos fijos por
+//! tick: gravedad y rozamiento cuadrático contra el aire (que se mueve con el
+//! viento). Sin aleatoriedad, así que con los mismos disparos sale siempre lo
+//! mismo. Entre paso y paso se lanza un rayo contra el mundo (`BallisticWorld`);
+//! al tocar algo la bala rebota si entra rasante en un material duro, lo
+//! atraviesa si le queda energía para el grosor que tiene delante, o se queda
+//! dentro. El daño baja con la energía que le queda.
+//!
+//! `ProjectileStore` guarda las balas en huecos reutilizables: disparar no
+//! reserva memoria y las que se paran dejan su hueco libre.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::chunk::{PropKind, StaticProp};
+use crate::components::{BodyZone, PlayerId};
+use crate::constants::GRAVITY;
+use crate::drops::sight_blocker;
+use crate::weather::Weather;
+
+/// Julios que cuesta atravesar un metro de un material de resistencia 1
+pub const PENETRATION_COST: f32 = 1000.0;
+
+/// Por debajo de esta velocidad (m/s) la bala ya no hace nada
+pub const MIN_SPEED: f32 = 60.0;
+
+/// Velocidad mínima (m/s) para rebotar
+pub const MIN_RICOCHET_SPEED: f32 = 150.0;
+
+/// Rebotes como mucho por bala
+pub const MAX_RICOCHETS: u32 = 2;
+
+/// Segundos que vive una bala como mucho
+pub const MAX_FLIGHT_TIME: f32 = 6.0;
+
+/// Lo que se separa la bala de una superficie al salir de ella
+const SURFACE_OFFSET: f32 = 0.01;
+
+/// Superficies que se cruzan como mucho en un paso
+const MAX_CONTACTS_PER_STEP: usize = 8;
+
+/// Paso (m) al recorrer el terreno buscando el suelo
+const GROUND_STEP: f32 = 0.5;
+
+/// Tipo de munición
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AmmoKind {
+    /// Full Metal Jacket: la de uso general
+    #[default]
+    Fmj,
+    /// Punta hueca: más daño, apenas atraviesa
+    HollowPoint,
+    /// Perforante: atraviesa mucho, hace menos daño
+    ArmorPiercing,
+    /// Más lenta que el sonido
+    Subsonic,
+}
+
+/// Cómo cambia un tipo de munición a la bala (multiplicadores)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmmoProfile {
+    pub velocity: f32,
+    /// Tope de velocidad de salida (m/s)
+    pub max_velocity: f32,
+    pub damage: f32,
+    pub penetration: f32,
+    /// Sobre el ángulo de rebote de cada material
+    pub ricochet: f32,
+}
+
+impl AmmoKind {
+    pub const ALL: [AmmoKind; 4] = [AmmoKind::Fmj, AmmoKind::HollowPoint, AmmoKind::ArmorPiercing, AmmoKind::Subsonic];
+
+    pub fn profile(self) -> AmmoProfile {
+        match self {
+            AmmoKind::Fmj => AmmoProfile { velocity: 1.0, max_velocity: f32::INFINITY, damage: 1.0, penetration: 1.0, ricochet: 1.0 },
+            AmmoKind::HollowPoint => AmmoProfile { velocity: 0.95, max_velocity: f32::INFINITY, damage: 1.3, penetration: 0.4, ricochet: 0.5 },
+            AmmoKind::ArmorPiercing => AmmoProfile { velocity: 1.05, max_velocity: f32::INFINITY, damage: 0.85, penetration: 2.0, ricochet: 1.2 },
+            AmmoKind::Subsonic => AmmoProfile { velocity: 1.0, max_velocity: 320.0, damage: 0.9, penetration: 0.6, ricochet: 0.8 },
+        }
+    }
+}
+
+impl std::fmt::Display for AmmoKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmmoKind::Fmj => write!(f, "FMJ"),
+            AmmoKind::HollowPoint => write!(f, "punta hueca"),
+            AmmoKind::ArmorPiercing => write!(f, "perforante"),
+            AmmoKind::Subsonic => write!(f, "subsónica"),
+        }
+    }
+}
+
+/// La bala de una munición, tal como va en el catálogo de items
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct RoundStats {
+    pub kind: AmmoKind,
+    /// Gramos
+    pub mass: f32,
+    /// Milímetros
+    pub diameter: f32,
+    /// Coeficiente de rozamiento
+    pub drag: f32,
+}
+
+impl Default for RoundStats {
+    /// Una 9x19 FMJ
+    fn default() -> Self {
+        Self { kind: AmmoKind::Fmj, mass: 8.0, diameter: 9.0, drag: 0.3 }
+    }
+}
+
+impl RoundStats {
+    /// Aceleración de frenado por (m/s)² de velocidad relativa al aire, con densidad 1
+    fn drag_factor(&self) -> f32 {
+        let radius = self.diameter * 0.0005;
+        0.5 * self.drag * std::f32::consts::PI * radius * radius / self.kg()
+    }
+
+    fn kg(&self) -> f32 {
+        self.mass * 0.001
+    }
+}
+
+/// Material de una superficie
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Material {
+    Flesh,
+    Wood,
+    Stone,
+    Metal,
+    Soil,
+}
+
+/// Cómo para un material las balas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialProps {
+    /// Multiplica `PENETRATION_COST`
+    pub resistance: f32,
+    /// Rebota si la bala entra más rasante que esto (grados)
+    pub ricochet_angle: f32,
+    /// Parte de la velocidad que conserva al rebotar
+    pub ricochet_retention: f32,
+}
+
+impl Material {
+    pub fn props(self) -> MaterialProps {
+        match self {
+            Material::Flesh => MaterialProps { resistance: 2.5, ricochet_angle: 0.0, ricochet_retention: 0.0 },
+            Material::Wood => MaterialProps { resistance: 3.0, ricochet_angle: 10.0, ricochet_retention: 0.5 },
+            Material::Stone => MaterialProps { resistance: 8.0, ricochet_angle: 25.0, ricochet_retention: 0.6 },
+            Material::Metal => MaterialProps { resistance: 15.0, ricochet_angle: 30.0, ricochet_retention: 0.7 },
+            Material::Soil => MaterialProps { resistance: 6.0, ricochet_angle: 12.0, ricochet_retention: 0.4 },
+        }
+    }
+}
+
+/// Aire por el que vuelan las balas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Atmosphere {
+    pub gravity: Vec3,
+    /// m/s
+    pub wind: Vec3,
+    /// kg/m³
+    pub air_density: f32,
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Self::from_weather(&Weather::default())
+    }
+}
+
+impl Atmosphere {
+    pub fn from_weather(weather: &Weather) -> Self {
+        Self { gravity: Vec3::Y * GRAVITY, wind: weather.wind_vector(), air_density: weather.air_density() }
+    }
+}
+
+/// Superficie que cruza un rayo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceHit {
+    pub point: Vec3,
+    /// Hacia fuera de la superficie
+    pub normal: Vec3,
+    pub material: Material,
+    /// Metros de material a lo largo del rayo (infinito si no tiene otro lado)
+    pub thickness: f32,
+    /// Jugador alcanzado y en qué zona, si es uno
+    pub target: Option<(Entity, BodyZone)>,
+}
+
+/// Lo que pueden tocar las balas
+pub trait BallisticWorld {
+    /// Primera superficie del segmento `from` → `to`, sin contar al tirador
+    fn raycast(&mut self, from: Vec3, to: Vec3, shooter: PlayerId) -> Option<SurfaceHit>;
+}
+
+/// Suelo bajo el segmento; `ground` da la altura del terreno en (x, z)
+pub fn raycast_ground(mut ground: impl FnMut(f32, f32) -> f32, from: Vec3, to: Vec3) -> Option<SurfaceHit> {
+    let below = |ground: &mut dyn FnMut(f32, f32) -> f32, point: Vec3| point.y < ground(point.x, point.z);
+    let steps = (from.distance(to) / GROUND_STEP).ceil().max(1.0) as usize;
+    let mut previous = 0.0;
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        if !below(&mut ground, from.lerp(to, t)) {
+            previous = t;
+            continue;
+        }
+        // Afina entre la última muestra por encima y esta
+        let (mut above, mut under) = (previous, t);
+        if step > 0 {
+            for _ in 0..10 {
+                let middle = (above + under) * 0.5;
+                if below(&mut ground, from.lerp(to, middle)) {
+                    under = middle;
+                } else {
+                    above = middle;
+                }
+            }
+        }
+        let point = from.lerp(to, under);
+        let e = 0.1;
+        let dx = ground(point.x + e, point.z) - ground(point.x - e, point.z);
+        let dz = ground(point.x, point.z + e) - ground(point.x, point.z - e);
+        let normal = Vec3::new(-dx, 2.0 * e, -dz).normalize();
+        return Some(SurfaceHit { point, normal, material: Material::Soil, thickness: f32::INFINITY, target: None });
+    }
+    None
+}
+
+/// Primer prop (árbol o roca) del segmento; los arbustos no paran balas
+pub fn raycast_props<'a>(props: impl IntoIterator<Item = &'a StaticProp>, from: Vec3, to: Vec3) -> Option<SurfaceHit> {
+    let (direction, length) = (to - from).try_normalize().map(|direction| (direction, from.distance(to)))?;
+    props
+        .into_iter()
+        .filter_map(|prop| {
+            let (base, radius, height) = sight_blocker(prop)?;
+            let (entry, exit, normal) = ray_cylinder(from, direction, base, radius, height)?;
+            let material = match prop.kind {
+                PropKind::Tree | PropKind::Bush => Material::Wood,
+                PropKind::Rock => Material::Stone,
+            };
+            (entry <= length).then(|| SurfaceHit { point: from + direction * entry, normal, material, thickness: exit - entry, target: None })
+        })
+        .min_by(|a, b| from.distance_squared(a.point).total_cmp(&from.distance_squared(b.point)))
+}
+
+/// Entrada, salida y normal de un rayo contra un cilindro vertical
+fn ray_cylinder(origin: Vec3, direction: Vec3, base: Vec3, radius: f32, height: f32) -> Option<(f32, f32, Vec3)> {
+    // Tramo dentro del círculo visto desde arriba
+    let offset = origin.xz() - base.xz();
+    let flat = direction.xz();
+    let a = flat.length_squared();
+    let (mut enter, mut leave, mut normal) = if a > f32::EPSILON {
+        let b = offset.dot(flat);
+        let c = offset.length_squared() - radius * radius;
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let enter = (-b - root) / a;
+        let side = (offset + flat * enter).normalize_or_zero();
+        (enter, (-b + root) / a, Vec3::new(side.x, 0.0, side.y))
+    } else if offset.length_squared() <= radius * radius {
+        (f32::NEG_INFINITY, f32::INFINITY, Vec3::ZERO)
+    } else {
+        return None;
+    };
+
+    // Y el tramo entre la base y la tapa
+    let (bottom, top) = (base.y, base.y + height);
+    if direction.y.abs() > f32::EPSILON {
+        let (t0, t1) = ((bottom - origin.y) / direction.y, (top - origin.y) / direction.y);
+        let (near, far) = (t0.min(t1), t0.max(t1));
+        if near > enter {
+            enter = near;
+            normal = if direction.y < 0.0 { Vec3::Y } else { Vec3::NEG_Y };
+        }
+        leave = leave.min(far);
+    } else if origin.y < bottom || origin.y > top {
+        return None;
+    }
+    (enter <= leave && leave >= 0.0).then_some((enter.max(0.0), leave, normal))
+}
+
+/// Qué pasó con la bala al tocar una superficie
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImpactOutcome {
+    /// Se quedó dentro
+    Stopped,
+    /// Salió por el otro lado, por `exit`
+    Penetrated { exit: Vec3 },
+    Ricochet,
+}
+
+/// Una bala tocó algo
+#[derive(Debug, Clone, PartialEq)]
+pub struct Impact<T> {
+    pub payload: T,
+    pub shooter: PlayerId,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub material: Material,
+    pub target: Option<(Entity, BodyZone)>,
+    /// Daño según la energía que traía al llegar
+    pub damage: f32,
+    pub outcome: ImpactOutcome,
+}
+
+/// Un disparo que sale del arma
+#[derive(Debug, Clone)]
+pub struct Launch<T> {
+    pub payload: T,
+    pub shooter: PlayerId,
+    pub origin: Vec3,
+    pub direction: Vec3,
+    /// Velocidad de salida del arma (m/s), antes del tipo de munición
+    pub muzzle_velocity: f32,
+    pub round: RoundStats,
+    /// Daño del arma a la velocidad de salida, antes del tipo de munición
+    pub damage: f32,
+}
+
+/// Bala en vuelo
+#[derive(Debug, Clone)]
+pub struct Projectile<T> {
+    pub payload: T,
+    pub shooter: PlayerId,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Metros recorridos
+    pub travelled: f32,
+    /// Segundos en el aire
+    pub age: f32,
+    pub ricochets: u32,
+    mass: f32,
+    drag: f32,
+    profile: AmmoProfile,
+    damage: f32,
+    muzzle_energy: f32,
+}
+
+impl<T: Clone> Projectile<T> {
+    fn new(launch: Launch<T>) -> Self {
+        let profile = launch.round.kind.profile();
+        let speed = (launch.muzzle_velocity * profile.velocity).min(profile.max_velocity);
+        let mass = launch.round.kg();
+        Self {
+            payload: launch.payload,
+            shooter: launch.shooter,
+            position: launch.origin,
+            velocity: launch.direction.normalize_or_zero() * speed,
+            travelled: 0.0,
+            age: 0.0,
+            ricochets: 0,
+            mass,
+            drag: launch.round.drag_factor(),
+            profile,
+            damage: launch.damage * profile.damage,
+            muzzle_energy: 0.5 * mass * speed * speed,
+        }
+    }
+
+    pub fn energy(&self) -> f32 {
+        0.5 * self.mass * self.velocity.length_squared()
+    }
+
+    /// Daño que haría ahora: baja con la energía
+    pub fn damage(&self) -> f32 {
+        if self.muzzle_energy <= 0.0 {
+            return 0.0;
+        }
+        self.damage * (self.energy() / self.muzzle_energy).min(1.0)
+    }
+
+    /// Un paso de `h` segundos; `false` si la bala se acabó
+    fn step(&mut self, h: f32, atmosphere: &Atmosphere, world: &mut impl BallisticWorld, impacts: &mut Vec<Impact<T>>) -> bool {
+        let relative = self.velocity - atmosphere.wind;
+        let drag = relative * (self.drag * atmosphere.air_density * relative.length());
+        self.velocity += (atmosphere.gravity - drag) * h;
+        self.age += h;
+        let mut target = self.position + self.velocity * h;
+
+        for _ in 0..MAX_CONTACTS_PER_STEP {
+            let Some(hit) = world.raycast(self.position, target, self.shooter) else {
+                self.travelled += self.position.distance(target);
+                self.position = target;
+                return self.velocity.length() >= MIN_SPEED && self.age < MAX_FLIGHT_TIME;
+            };
+            self.travelled += self.position.distance(hit.point);
+            let direction = self.velocity.normalize_or_zero();
+            let material = hit.material.props();
+            let mut impact = Impact {
+                payload: self.payload.clone(),
+                shooter: self.shooter,
+                point: hit.point,
+                normal: hit.normal,
+                material: hit.material,
+                target: hit.target,
+                damage: self.damage(),
+                outcome: ImpactOutcome::Stopped,
+            };
+
+            // Rasante contra algo duro: rebota
+            let grazing = direction.dot(hit.normal).abs().asin().to_degrees();
+            if grazing < material.ricochet_angle * self.profile.ricochet
+                && self.velocity.length() >= MIN_RICOCHET_SPEED
+                && self.ricochets < MAX_RICOCHETS
+            {
+                impact.outcome = ImpactOutcome::Ricochet;
+                impacts.push(impact);
+                self.ricochets += 1;
+                self.velocity = self.velocity.reflect(hit.normal) * material.ricochet_retention;
+                self.position = hit.point + hit.normal * SURFACE_OFFSET;
+                return true;
+            }
+
+            // Atraviesa si le llega la energía para el grosor que tiene delante
+            let cost = material.resistance * PENETRATION_COST * hit.thickness / self.profile.penetration;
+            let energy = self.energy();
+            if cost >= energy {
+                impacts.push(impact);
+                return false;
+            }
+            let exit = hit.point + direction * (hit.thickness + SURFACE_OFFSET);
+            impact.outcome = ImpactOutcome::Penetrated { exit };
+            impacts.push(impact);
+            self.velocity *= ((energy - cost) / energy).sqrt();
+            self.travelled += hit.thickness;
+            if self.velocity.length() < MIN_SPEED {
+                return false;
+            }
+            // Sigue lo que le queda del paso desde el otro lado
+            let remaining = (target - hit.point).dot(direction) - hit.thickness;
+            self.position = exit;
+            if remaining <= 0.0 {
+                return true;
+            }
+            target = exit + direction * remaining;
+        }
+        true
+    }
+}
+
+/// Referencia a una bala del almacén
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProjectileHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// Balas en vuelo en huecos reutilizables
+#[derive(Debug, Clone)]
+pub struct ProjectileStore<T> {
+    slots: Vec<Option<Projectile<T>>>,
+    generations: Vec<u32>,
+    /// Huecos libres; se reutiliza primero el último liberado
+    free: Vec<u32>,
+    live: usize,
+    substeps: u32,
+    max_range: f32,
+}
+
+impl<T: Clone> ProjectileStore<T> {
+    /// `capacity` balas como mucho, `substeps` pasos por tick y se pierden a `max_range` metros
+    pub fn new(capacity: usize, substeps: u32, max_range: f32) -> Self {
+        Self {
+            slots: (0..capacity).map(|_| None).collect(),
+            generations: vec![0; capacity],
+            free: (0..capacity as u32).rev().collect(),
+            live: 0,
+            substeps: substeps.max(1),
+            max_range,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.live
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Dispara una bala; `None` si el almacén está lleno
+    pub fn launch(&mut self, launch: Launch<T>) -> Option<ProjectileHandle> {
+        let index = self.free.pop()?;
+        self.slots[index as usize] = Some(Projectile::new(launch));
+        self.live += 1;
+        Some(ProjectileHandle { index, generation: self.generations[index as usize] })
+    }
+
+    pub fn get(&self, handle: ProjectileHandle) -> Option<&Projectile<T>> {
+        if self.generations.get(handle.index as usize) != Some(&handle.generation) {
+            return None;
+        }
+        self.slots[handle.index as usize].as_ref()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Projectile<T>> {
+        self.slots.iter().flatten()
+    }
+
+    fn release(&mut self, index: usize) {
+        self.slots[index] = None;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(index as u32);
+        self.live -= 1;
+    }
+
+    /// Avanza un paso y libera el hueco si la bala se acabó
+    fn step_slot(&mut self, index: usize, h: f32, atmosphere: &Atmosphere, world: &mut impl BallisticWorld, impacts: &mut Vec<Impact<T>>) -> bool {
+        let Some(projectile) = self.slots[index].as_mut() else { return false };
+        let alive = projectile.step(h, atmosphere, world, impacts) && projectile.travelled < self.max_range;
+        if !alive {
+            self.release(index);
+        }
+        alive
+    }
+
+    /// Avanza todas las balas un tick de `dt` segundos
+    pub fn step(&mut self, dt: f32, atmosphere: &Atmosphere, world: &mut impl BallisticWorld, impacts: &mut Vec<Impact<T>>) {
+        if self.live == 0 {
+            return;
+        }
+        let h = dt / self.substeps as f32;
+        for index in 0..self.slots.len() {
+            for _ in 0..self.substeps {
+                if !self.step_slot(index, h, atmosphere, world, impacts) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Adelanta una bala (con los mismos pasos que `step` para un tick de `dt`)
+    /// hasta que haya recorrido `distance` metros más o se acabe
+    pub fn advance(
+        &mut self,
+        handle: ProjectileHandle,
+        distance: f32,
+        dt: f32,
+        atmosphere: &Atmosphere,
+        world: &mut impl BallisticWorld,
+        impacts: &mut Vec<Impact<T>>,
+    ) {
+        let Some(start) = self.get(handle).map(|projectile| projectile.travelled) else { return };
+        let h = dt / self.substeps as f32;
+        let index = handle.index as usize;
+        while self.slots[index].as_ref().is_some_and(|projectile| projectile.travelled - start < distance) {
+            if !self.step_slot(index, h, atmosphere, world, impacts) {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Rejilla de chunks del mundo y contenido que se transmite de cada uno.
+//!
+//! El mundo es un cuadrado centrado en el origen dividido en chunks cuadrados.
+//! El servidor genera y mantiene cargados los chunks alrededor de los jugadores;
+//! el cliente los pide bajo demanda y los construye poco a poco.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Coordenada de un chunk: (0, 0) empieza en el origen y crece hacia +X/+Z
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkCoord {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// Distancia en chunks (Chebyshev): el anillo `n` rodea al chunk a distancia `n`
+    pub fn distance(&self, other: ChunkCoord) -> u32 {
+        self.x.abs_diff(other.x).max(self.z.abs_diff(other.z))
+    }
+}
+
+impl std::fmt::Display for ChunkCoord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.z)
+    }
+}
+
+/// Geometría de la rejilla; el servidor la anuncia con `WorldInfo`
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ChunkGrid {
+    /// Lado del mundo en metros
+    pub world_size: f32,
+    /// Lado de un chunk en metros
+    pub chunk_size: f32,
+}
+
+impl ChunkGrid {
+    pub fn new(world_size: f32, chunk_size: f32) -> Self {
+        Self { world_size, chunk_size }
+    }
+
+    /// Chunks por lado del mundo
+    pub fn chunks_per_side(&self) -> i32 {
+        (self.world_size / self.chunk_size).ceil().max(1.0) as i32
+    }
+
+    /// Primera y última coordenada válidas en cada eje
+    pub fn coord_range(&self) -> std::ops::RangeInclusive<i32> {
+        let side = self.chunks_per_side();
+        let min = -(side / 2);
+        min..=min + side - 1
+    }
+
+    pub fn contains(&self, coord: ChunkCoord) -> bool {
+        let range = self.coord_range();
+        range.contains(&coord.x) && range.contains(&coord.z)
+    }
+
+    /// Mitad del lado del mundo: los jugadores se mueven en `[-half, half]`
+    pub fn half_size(&self) -> f32 {
+        self.world_size / 2.0
+    }
+
+    /// Chunk que contiene la posición (sin comprobar los límites)
+    pub fn coord_at(&self, position: Vec3) -> ChunkCoord {
+        ChunkCoord::new(
+            (position.x / self.chunk_size).floor() as i32,
+            (position.z / self.chunk_size).floor() as i32,
+        )
+    }
+
+    /// Esquina mínima (x, z) del chunk, a nivel del suelo
+    pub fn origin(&self, coord: ChunkCoord) -> Vec3 {
+        Vec3::new(coord.x as f32 * self.chunk_size, 0.0, coord.z as f32 * self.chunk_size)
+    }
+
+    pub fn center(&self, coord: ChunkCoord) -> Vec3 {
+        self.origin(coord) + Vec3::new(self.chunk_size, 0.0, self.chunk_size) / 2.0
+    }
+
+    /// Chunks válidos a `radius` o menos del que contiene `position`, del más cercano al más lejano
+    pub fn chunks_around(&self, position: Vec3, radius: u32) -> Vec<ChunkCoord> {
+        let center = self.coord_at(position);
+        let radius = radius as i32;
+        let mut coords: Vec<ChunkCoord> = (-radius..=radius)
+            .flat_map(|dz| (-radius..=radius).map(move |dx| ChunkCoord::new(center.x + dx, center.z + dz)))
+            .filter(|coord| self.contains(*coord))
+            .collect();
+        coords.sort_by(|a, b| {
+            let distance = |coord: &ChunkCoord| self.center(*coord).xz().distance_squared(position.xz());
+            distance(a).total_cmp(&distance(b)).then(a.cmp(b))
+        });
+        coords
+    }
+}
+
+/// Contenido de un chunk tal como viaja por la red. El terreno no viaja: cada
+/// lado lo genera con la semilla (ver `terrain`)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChunkData {
+    pub coord: ChunkCoord,
+    /// Sube cuando cambia el contenido; el cliente descarta versiones viejas
+    pub revision: u32,
+    pub props: Vec<StaticProp>,
+    pub containers: Vec<LootContainer>,
+}
+
+/// Objeto decorativo fijo (sin interacción)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StaticProp {
+    pub kind: PropKind,
+    pub position: Vec3,
+    /// Giro alrededor de Y (radianes)
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PropKind {
+    Tree,
+    Rock,
+    Bush,
+}
+
+/// Contenedor de loot colocado en el mundo; el id es estable entre reinicios
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LootContainer {
+    pub id: u64,
+    pub kind: ContainerKind,
+    pub position: Vec3,
+    pub rotation: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContainerKind {
+    Crate,
+    Toolbox,
+    MedicalBox,
+}
+
+impl LootContainer {
+    /// Id derivado de la posición en la rejilla: el mismo contenedor conserva su id
+    pub fn id_for(coord: ChunkCoord, index: u16) -> u64 {
+        let x = (coord.x + 0x8000) as u64 & 0xFFFF;
+        let z = (coord.z + 0x8000) as u64 & 0xFFFF;
+        (x << 32) | (z << 16) | index as u64
+    }
+}
+
+/// Generador pseudoaleatorio determinista (SplitMix64): misma semilla, mismo mundo
+#[derive(Debug, Clone)]
+pub struct WorldRng(u64);
+
+impl WorldRng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Generador propio de un chunk, independiente del orden en que se generen
+    pub fn for_chunk(seed: u64, coord: ChunkCoord) -> Self {
+        let mut rng = Self(seed ^ ((coord.x as u32 as u64) << 32 | coord.z as u32 as u64));
+        rng.next_u64();
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Número en `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, range: std::ops::Range<f32>) -> f32 {
+        range.start + self.next_f32() * (range.end - range.start)
+    }
+}
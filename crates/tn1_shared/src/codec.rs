@@ -0,0 +1,101 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Tamaño máximo de un frame (evita que un cliente nos haga reservar memoria infinita)
+pub const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// Codecs de serialización soportados sobre el framing `[u32 big-endian][payload]`.
+///
+/// El handshake (`Hello`/`Welcome`/`StatusQuery`/`ServerInfo`) viaja siempre en JSON;
+/// el codec negociado se usa a partir del mensaje siguiente a `Welcome`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WireCodec {
+    #[default]
+    Json,
+    Bincode,
+}
+
+impl WireCodec {
+    /// Codecs que este build entiende, en orden de preferencia
+    pub const SUPPORTED: [WireCodec; 2] = [WireCodec::Bincode, WireCodec::Json];
+
+    /// Elige el primer codec del servidor que también ofrece el cliente
+    pub fn negotiate(server: &[WireCodec], client: &[WireCodec]) -> Option<WireCodec> {
+        server.iter().copied().find(|codec| client.contains(codec))
+    }
+
+    /// Serializa el mensaje y le antepone la longitud
+    pub fn encode_frame<T: Serialize>(&self, message: &T) -> Result<Vec<u8>, CodecError> {
+        let payload = match self {
+            WireCodec::Json => serde_json::to_vec(message)?,
+            WireCodec::Bincode => bincode::serialize(message)?,
+        };
+
+        if payload.len() > MAX_FRAME_SIZE {
+            return Err(CodecError::FrameTooLarge(payload.len()));
+        }
+
+        let mut data = (payload.len() as u32).to_be_bytes().to_vec();
+        data.extend(payload);
+        Ok(data)
+    }
+
+    /// Deserializa el payload de un frame (sin el prefijo de longitud)
+    pub fn decode<T: DeserializeOwned>(&self, payload: &[u8]) -> Result<T, CodecError> {
+        match self {
+            WireCodec::Json => Ok(serde_json::from_slice(payload)?),
+            WireCodec::Bincode => Ok(bincode::deserialize(payload)?),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodecError {
+    #[error("JSON inválido: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("bincode inválido: {0}")]
+    Bincode(#[from] bincode::Error),
+    #[error("frame demasiado grande: {0} bytes")]
+    FrameTooLarge(usize),
+}
+
+/// Acumula bytes leídos del socket y extrae frames completos
+#[derive(Default)]
+pub struct FrameBuffer {
+    buffer: Vec<u8>,
+}
+
+impl FrameBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Devuelve el siguiente payload completo, o `None` si falta recibir datos
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, CodecError> {
+        if self.buffer.len() < 4 {
+            return Ok(None);
+        }
+
+        let msg_len = u32::from_be_bytes([
+            self.buffer[0],
+            self.buffer[1],
+            self.buffer[2],
+            self.buffer[3],
+        ]) as usize;
+
+        if msg_len > MAX_FRAME_SIZE {
+            return Err(CodecError::FrameTooLarge(msg_len));
+        }
+
+        if self.buffer.len() < 4 + msg_len {
+            return Ok(None);
+        }
+
+        let payload = self.buffer[4..4 + msg_len].to_vec();
+        self.buffer.drain(0..4 + msg_len);
+        Ok(Some(payload))
+    }
+}
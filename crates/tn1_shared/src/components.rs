@@ -16,6 +16,18 @@ impl Health {
     }
 }
 
+/// Zonas del cuerpo (ver `docs/SYSTEMS/MEDICAL_SYSTEM.md`)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BodyZone {
+    Head,
+    Thorax,
+    Stomach,
+    LeftArm,
+    RightArm,
+    LeftLeg,
+    RightLeg,
+}
+
 #[derive(Component, Serialize, Deserialize, Clone, Debug)]
 pub struct PlayerName(pub String);
 
@@ -25,12 +37,17 @@ pub struct Player;
 #[derive(Component, Default)]
 pub struct LocalPlayer;
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Clone)]
 pub struct PlayerController {
     pub velocity: Vec3,
     pub speed: f32,
     pub is_grounded: bool,
     pub jump_timer: f32,
+    /// Linterna encendida según el último input
+    pub flashlight: bool,
+    /// Lo que dejan el peso y el equipo (ver `GearModifiers`)
+    pub speed_multiplier: f32,
+    pub can_sprint: bool,
 }
 
 impl PlayerController {
@@ -40,6 +57,9 @@ impl PlayerController {
             speed: 7.0,
             is_grounded: false,
             jump_timer: 0.0,
+            flashlight: false,
+            speed_multiplier: 1.0,
+            can_sprint: true,
         }
     }
 }
\ No newline at end of file
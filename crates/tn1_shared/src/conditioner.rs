@@ -0,0 +1,311 @@
+//! Simulador de red ("link conditioner") para probar redes malas en local.
+//!
+//! Cada extremo de la conexión puede envolver lo que envía y lo que recibe en un
+//! `LinkConditioner`: los mensajes se retrasan, se pierden, se duplican o se
+//! desordenan según las `LinkConditions` compartidas, que se pueden cambiar en
+//! caliente. Con las condiciones a cero los mensajes pasan sin coste extra.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BinaryHeap;
+use std::cmp::Ordering;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Condiciones aplicadas a cada sentido de la conexión (la latencia es de ida)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct LinkConditions {
+    pub latency_ms: u32,
+    /// Variación aleatoria de ±`jitter_ms` sobre la latencia
+    pub jitter_ms: u32,
+    pub loss_percent: f32,
+    pub duplicate_percent: f32,
+    pub reorder_percent: f32,
+    /// Ancho de banda en kbit/s; 0 = sin límite
+    pub bandwidth_kbps: u32,
+}
+
+impl LinkConditions {
+    /// Sin latencia ni pérdidas: los mensajes se entregan al instante
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Presets para el panel de debug: (nombre, condiciones)
+    pub fn presets() -> [(&'static str, LinkConditions); 5] {
+        [
+            ("Perfecta", LinkConditions::default()),
+            ("Wi-Fi doméstico", LinkConditions { latency_ms: 15, jitter_ms: 5, loss_percent: 0.5, ..default() }),
+            ("4G", LinkConditions { latency_ms: 50, jitter_ms: 20, loss_percent: 1.0, reorder_percent: 1.0, ..default() }),
+            ("Wi-Fi saturado", LinkConditions { latency_ms: 80, jitter_ms: 60, loss_percent: 5.0, duplicate_percent: 1.0, reorder_percent: 5.0, bandwidth_kbps: 512 }),
+            ("Satélite", LinkConditions { latency_ms: 300, jitter_ms: 30, loss_percent: 2.0, bandwidth_kbps: 1024, ..default() }),
+        ]
+    }
+
+    /// Lee `--latency`, `--jitter`, `--loss`, `--duplicate`, `--reorder` y `--bandwidth`
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        fn flag<T: std::str::FromStr>(args: &[String], name: &str) -> Result<Option<T>, String> {
+            let Some(index) = args.iter().position(|arg| arg == name) else { return Ok(None) };
+            let value = args.get(index + 1).ok_or_else(|| format!("{} necesita un valor", name))?;
+            value.parse().map(Some).map_err(|_| format!("valor inválido para {}: {}", name, value))
+        }
+
+        let percent = |name: &str| -> Result<f32, String> {
+            let value: f32 = flag(args, name)?.unwrap_or(0.0);
+            if !(0.0..=100.0).contains(&value) {
+                return Err(format!("{} debe estar entre 0 y 100", name));
+            }
+            Ok(value)
+        };
+
+        Ok(Self {
+            latency_ms: flag(args, "--latency")?.unwrap_or(0),
+            jitter_ms: flag(args, "--jitter")?.unwrap_or(0),
+            loss_percent: percent("--loss")?,
+            duplicate_percent: percent("--duplicate")?,
+            reorder_percent: percent("--reorder")?,
+            bandwidth_kbps: flag(args, "--bandwidth")?.unwrap_or(0),
+        })
+    }
+}
+
+/// Condiciones compartidas entre el `App` (panel, CLI) y los threads de red
+#[derive(Resource, Clone, Default)]
+pub struct NetworkConditions(pub Arc<Mutex<LinkConditions>>);
+
+impl NetworkConditions {
+    pub fn new(conditions: LinkConditions) -> Self {
+        Self(Arc::new(Mutex::new(conditions)))
+    }
+
+    pub fn get(&self) -> LinkConditions {
+        *self.0.lock().unwrap()
+    }
+
+    pub fn set(&self, conditions: LinkConditions) {
+        *self.0.lock().unwrap() = conditions;
+    }
+}
+
+type Sink<T> = Box<dyn FnMut(T) -> std::io::Result<()> + Send>;
+
+struct Pending<T> {
+    deliver_at: Instant,
+    seq: u64,
+    item: T,
+}
+
+// Montículo de mínimos por (instante de entrega, orden de envío)
+impl<T> Ord for Pending<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.deliver_at, other.seq).cmp(&(self.deliver_at, self.seq))
+    }
+}
+
+impl<T> PartialOrd for Pending<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Pending<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Pending<T> {}
+
+struct QueueState<T> {
+    pending: BinaryHeap<Pending<T>>,
+    next_seq: u64,
+    /// Última entrega en orden: sin reordenamiento el jitter no adelanta mensajes
+    last_in_order: Instant,
+    /// Cuándo termina de "transmitirse" lo ya enviado (límite de ancho de banda)
+    link_free_at: Instant,
+    worker_started: bool,
+    closed: bool,
+    failed: bool,
+    /// Lo que se hace al cerrar, después de la última entrega (`close`)
+    on_closed: Option<Box<dyn FnOnce() + Send>>,
+}
+
+struct Shared<T> {
+    state: Mutex<QueueState<T>>,
+    wakeup: Condvar,
+    sink: Mutex<Sink<T>>,
+}
+
+/// Entrega mensajes a `sink` aplicando las condiciones de red.
+///
+/// Los mensajes no descartables (handshake, login, eventos) solo sufren latencia,
+/// jitter y ancho de banda; pérdida, duplicados y desorden se aplican a los que
+/// el siguiente mensaje deja obsoletos (snapshots, inputs, pings) o que toleran
+/// huecos (voz). Cada mensaje lo dice con su `is_droppable`.
+pub struct LinkConditioner<T: Clone + Send + 'static> {
+    conditions: NetworkConditions,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Clone + Send + 'static> LinkConditioner<T> {
+    pub fn new(conditions: NetworkConditions, sink: impl FnMut(T) -> std::io::Result<()> + Send + 'static) -> Self {
+        let now = Instant::now();
+        Self {
+            conditions,
+            shared: Arc::new(Shared {
+                state: Mutex::new(QueueState {
+                    pending: BinaryHeap::new(),
+                    next_seq: 0,
+                    last_in_order: now,
+                    link_free_at: now,
+                    worker_started: false,
+                    closed: false,
+                    failed: false,
+                    on_closed: None,
+                }),
+                wakeup: Condvar::new(),
+                sink: Mutex::new(Box::new(sink)),
+            }),
+        }
+    }
+
+    /// Envía `item` (de `bytes` bytes en el cable); `droppable` permite perderlo, duplicarlo o desordenarlo
+    pub fn send(&self, item: T, bytes: usize, droppable: bool) -> std::io::Result<()> {
+        let conditions = self.conditions.get();
+        let mut state = self.shared.state.lock().unwrap();
+        if state.failed {
+            return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "link conditioner cerrado"));
+        }
+
+        // Camino rápido: nada que simular y nada en vuelo que adelantar
+        if !conditions.is_active() && state.pending.is_empty() {
+            // `state` se suelta después de tomar `sink` para no adelantar al worker
+            let mut sink = self.shared.sink.lock().unwrap();
+            drop(state);
+            return sink(item);
+        }
+
+        let now = Instant::now();
+        let deliveries = schedule(&mut state, &conditions, now, bytes, droppable);
+        for deliver_at in deliveries {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.pending.push(Pending { deliver_at, seq, item: item.clone() });
+        }
+
+        if !state.worker_started && !state.pending.is_empty() {
+            state.worker_started = true;
+            let shared = self.shared.clone();
+            thread::spawn(move || deliver_pending(shared));
+        }
+        self.shared.wakeup.notify_one();
+        Ok(())
+    }
+
+    /// Mensajes esperando su instante de entrega
+    pub fn in_flight(&self) -> usize {
+        self.shared.state.lock().unwrap().pending.len()
+    }
+
+    /// Cierra el enlace y llama a `then` cuando ha salido lo que estaba en vuelo
+    /// (p. ej. para cerrar el socket sin perder el último mensaje)
+    pub fn close(self, then: impl FnOnce() + Send + 'static) {
+        self.shared.state.lock().unwrap().on_closed = Some(Box::new(then));
+    }
+}
+
+impl<T: Clone + Send + 'static> Drop for LinkConditioner<T> {
+    fn drop(&mut self) {
+        // El worker termina de entregar lo que ya estaba en vuelo
+        let mut state = self.shared.state.lock().unwrap();
+        state.closed = true;
+        if state.worker_started {
+            drop(state);
+            self.shared.wakeup.notify_one();
+        } else if let Some(on_closed) = state.on_closed.take() {
+            // Sin worker todo se entregó al enviarlo
+            drop(state);
+            on_closed();
+        }
+    }
+}
+
+/// Instantes de entrega para un mensaje: ninguno si se pierde, dos si se duplica
+fn schedule<T>(
+    state: &mut QueueState<T>,
+    conditions: &LinkConditions,
+    now: Instant,
+    bytes: usize,
+    droppable: bool,
+) -> Vec<Instant> {
+    let roll = |percent: f32| droppable && percent > 0.0 && rand::random::<f32>() * 100.0 < percent;
+
+    if roll(conditions.loss_percent) {
+        return Vec::new();
+    }
+
+    // El mensaje ocupa el enlace mientras se transmite
+    let mut sent_at = now.max(state.link_free_at);
+    if conditions.bandwidth_kbps > 0 {
+        sent_at += Duration::from_secs_f64(bytes as f64 * 8.0 / (conditions.bandwidth_kbps as f64 * 1000.0));
+    }
+    state.link_free_at = sent_at;
+
+    let jitter = conditions.jitter_ms as f64;
+    let delay_ms = (conditions.latency_ms as f64 + (rand::random::<f64>() * 2.0 - 1.0) * jitter).max(0.0);
+    let mut deliver_at = sent_at + Duration::from_secs_f64(delay_ms / 1000.0);
+
+    if roll(conditions.reorder_percent) {
+        // Llega tarde y sin respetar el orden: lo adelantan los mensajes siguientes
+        deliver_at += Duration::from_millis(conditions.jitter_ms as u64 + 10);
+    } else {
+        deliver_at = deliver_at.max(state.last_in_order);
+        state.last_in_order = deliver_at;
+    }
+
+    let mut deliveries = vec![deliver_at];
+    if roll(conditions.duplicate_percent) {
+        deliveries.push(deliver_at + Duration::from_millis(1));
+    }
+    deliveries
+}
+
+fn deliver_pending<T>(shared: Arc<Shared<T>>) {
+    let mut state = shared.state.lock().unwrap();
+    loop {
+        let now = Instant::now();
+        match state.pending.peek() {
+            None if state.closed => break,
+            None => {
+                state = shared.wakeup.wait(state).unwrap();
+            }
+            Some(next) if next.deliver_at > now => {
+                let timeout = next.deliver_at - now;
+                state = shared.wakeup.wait_timeout(state, timeout).unwrap().0;
+            }
+            Some(_) => {
+                let Some(next) = state.pending.pop() else { continue };
+                let mut sink = shared.sink.lock().unwrap();
+                drop(state);
+                let result = sink(next.item);
+                drop(sink);
+                state = shared.state.lock().unwrap();
+
+                if result.is_err() {
+                    // Conexión cerrada: lo que quedaba ya no tiene destino. Sin worker,
+                    // un `close` posterior llama a `on_closed` al momento
+                    state.failed = true;
+                    state.pending.clear();
+                    state.worker_started = false;
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(on_closed) = state.on_closed.take() {
+        drop(state);
+        on_closed();
+    }
+}
@@ -19,4 +19,10 @@ pub const CAMERA_HEIGHT_OFFSET: f32 = 1.6;
 
 // Network
 pub const SERVER_TICK_RATE: f32 = 64.0;
-pub const CLIENT_UPDATE_RATE: f32 = 60.0;
\ No newline at end of file
+pub const CLIENT_UPDATE_RATE: f32 = 60.0;
+
+// Lag compensation
+pub const INTERPOLATION_DELAY: f32 = 0.0; // El cliente pinta el último estado recibido, sin buffer de interpolación
+pub const LAG_COMPENSATION_MAX_REWIND_SECS: f32 = 0.2; // Máximo rewind permitido
+pub const LAG_COMPENSATION_HISTORY_SECS: f32 = 1.0;
+pub const RTT_PING_INTERVAL: f32 = 1.0;
\ No newline at end of file
@@ -0,0 +1,113 @@
+//! Ciclo día/noche: la hora del mundo y lo que se deriva de ella.
+//!
+//! El servidor avanza el reloj y lo envía con `WorldTime`; el cliente lo sigue
+//! extrapolando entre mensajes. La oscuridad sale de la hora por tramos lineales
+//! (sin trigonometría), así que todos los jugadores ven exactamente la misma noche
+//! y las reglas que dependen de ella (a quién se distingue y a qué distancia)
+//! dan lo mismo en cada cliente.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Amanecer: de noche cerrada a pleno día entre estas horas
+pub const DAWN: (f32, f32) = (5.0, 7.0);
+/// Anochecer: de pleno día a noche cerrada entre estas horas
+pub const DUSK: (f32, f32) = (18.5, 20.5);
+
+/// Metros a los que se distingue a un jugador a pleno día y en noche cerrada
+const DAY_SPOTTING_RANGE: f32 = 300.0;
+const NIGHT_SPOTTING_RANGE: f32 = 20.0;
+/// Una linterna encendida delata a quien la lleva desde lejos
+const FLASHLIGHT_BEACON_RANGE: f32 = 200.0;
+/// Alcance del haz de la propia linterna
+const FLASHLIGHT_BEAM_RANGE: f32 = 45.0;
+/// Las gafas de visión nocturna devuelven buena parte de la vista
+const NIGHT_VISION_RANGE: f32 = 150.0;
+
+/// Hora del mundo y velocidad a la que pasa
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct WorldClock {
+    /// Hora del día en `[0, 24)`
+    pub hour: f32,
+    /// Horas del mundo por hora real (24 = un día entero en una hora)
+    pub time_scale: f32,
+}
+
+impl WorldClock {
+    pub fn new(hour: f32, time_scale: f32) -> Self {
+        Self { hour: hour.rem_euclid(24.0), time_scale }
+    }
+
+    /// Avanza `seconds` segundos reales
+    pub fn advance(&mut self, seconds: f32) {
+        self.hour = (self.hour + seconds * self.time_scale / 3600.0).rem_euclid(24.0);
+    }
+
+    /// 1 a pleno día, 0 en noche cerrada
+    pub fn daylight(&self) -> f32 {
+        let ramp = |from: f32, to: f32| ((self.hour - from) / (to - from)).clamp(0.0, 1.0);
+        if self.hour < 12.0 {
+            ramp(DAWN.0, DAWN.1)
+        } else {
+            1.0 - ramp(DUSK.0, DUSK.1)
+        }
+    }
+
+    pub fn darkness(&self) -> f32 {
+        1.0 - self.daylight()
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.darkness() > 0.5
+    }
+
+    /// Dirección hacia el sol: sale por +X a las 6, pasa por lo alto a las 12 y
+    /// se pone por -X a las 18 (bajo el horizonte, `y < 0`)
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.hour - 6.0) / 12.0 * std::f32::consts::PI;
+        Vec3::new(angle.cos(), angle.sin(), 0.35).normalize()
+    }
+
+    /// La luna va en el lado opuesto del cielo
+    pub fn moon_direction(&self) -> Vec3 {
+        let sun = self.sun_direction();
+        Vec3::new(-sun.x, -sun.y, sun.z)
+    }
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self::new(12.0, 1.0)
+    }
+}
+
+impl std::fmt::Display for WorldClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let minutes = (self.hour * 60.0) as u32;
+        write!(f, "{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+    }
+}
+
+/// Cómo mira alguien en la oscuridad
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Vision {
+    pub flashlight: bool,
+    pub night_vision: bool,
+}
+
+/// Distancia (metros) a la que un observador distingue a otro jugador con la
+/// oscuridad dada; `target_flashlight` si el otro lleva la linterna encendida
+pub fn spotting_range(darkness: f32, observer: Vision, target_flashlight: bool) -> f32 {
+    let darkness = darkness.clamp(0.0, 1.0);
+    let mut range = DAY_SPOTTING_RANGE + (NIGHT_SPOTTING_RANGE - DAY_SPOTTING_RANGE) * darkness;
+    if observer.flashlight {
+        range = range.max(FLASHLIGHT_BEAM_RANGE);
+    }
+    if observer.night_vision {
+        range = range.max(NIGHT_VISION_RANGE);
+    }
+    if target_flashlight {
+        range = range.max(FLASHLIGHT_BEACON_RANGE);
+    }
+    range
+}
@@ -0,0 +1,119 @@
+//! Items sueltos en el mundo: lo que tiran los jugadores y sus cadáveres.
+//!
+//! El servidor es quien los mueve y decide quién los recoge; a cada cliente le
+//! llegan como `WorldItemState` los que tiene cerca. Para recoger algo hay que
+//! estar a mano y verlo: `line_of_sight` comprueba que ni el terreno ni un
+//! árbol o una roca se interponen entre los ojos del jugador y el item.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::chunk::{PropKind, StaticProp};
+use crate::constants::CAMERA_HEIGHT_OFFSET;
+use crate::inventory::{Inventory, InventoryError, ItemId, ItemStack};
+
+/// Altura de los ojos sobre los pies, desde donde se mira lo que se recoge
+pub const EYE_HEIGHT: f32 = CAMERA_HEIGHT_OFFSET;
+
+/// Lo que se mira de un item: un poco por encima de donde reposa
+pub const ITEM_CLEARANCE: f32 = 0.2;
+
+/// Distancia entre muestras del terreno a lo largo de la línea de visión
+const SIGHT_STEP: f32 = 0.5;
+
+/// El terreno tapa si sube más que esto por encima de la línea (suaviza laderas)
+const SIGHT_TOLERANCE: f32 = 0.25;
+
+/// Un item suelto o un cadáver tal como lo ve un cliente
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WorldItemState {
+    pub id: u64,
+    pub position: Vec3,
+    /// Ya está quieto en el suelo; mientras cae se manda otra vez al pararse
+    pub resting: bool,
+    pub kind: WorldItemKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum WorldItemKind {
+    /// Montón de items en el suelo
+    Item { item: String, quantity: u32 },
+    /// Cadáver de un jugador; lo que lleva se ve al abrirlo (`LootContents`)
+    Corpse { name: String },
+}
+
+/// Acción sobre algo que hay en el suelo; comparte las secuencias de `Inventory`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PickupAction {
+    /// Meter en el inventario un item suelto, entero
+    Item { id: u64 },
+    /// Mirar qué lleva un cadáver; responde `LootContents`
+    OpenCorpse { corpse: u64 },
+    /// Pasar un item de un cadáver al inventario
+    TakeFromCorpse { corpse: u64, item: ItemId },
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PickupError {
+    #[error("eso ya no está")]
+    Gone,
+    #[error("estás demasiado lejos")]
+    OutOfReach,
+    #[error("no lo ves desde ahí")]
+    NotVisible,
+    #[error("eso no es un cadáver")]
+    NotACorpse,
+    #[error("un cadáver no se coge: se registra")]
+    Corpse,
+    #[error(transparent)]
+    Inventory(#[from] InventoryError),
+}
+
+/// Todo lo que lleva un jugador (bolsillos y equipo), con lo que haya dentro de cada cosa
+pub fn corpse_items(inventory: &Inventory) -> Vec<ItemStack> {
+    inventory
+        .pockets
+        .iter()
+        .flat_map(|pocket| &pocket.items)
+        .chain(inventory.equipment.slots.values())
+        .map(|placed| placed.stack.clone())
+        .collect()
+}
+
+/// Cilindro (centro de la base, radio, alto) que tapa la vista y para las balas;
+/// los arbustos no tapan
+pub(crate) fn sight_blocker(prop: &StaticProp) -> Option<(Vec3, f32, f32)> {
+    match prop.kind {
+        PropKind::Tree => Some((prop.position, 0.25 * prop.scale, 3.0 * prop.scale)),
+        PropKind::Rock => Some((prop.position, 0.8 * prop.scale, 0.8 * prop.scale)),
+        PropKind::Bush => None,
+    }
+}
+
+/// Si se ve `to` desde `from`: `ground` da la altura del terreno en (x, z) y
+/// `props` son los objetos de los chunks por los que pasa la línea
+pub fn line_of_sight<'a>(
+    from: Vec3,
+    to: Vec3,
+    mut ground: impl FnMut(f32, f32) -> f32,
+    props: impl IntoIterator<Item = &'a StaticProp>,
+) -> bool {
+    let steps = (from.distance(to) / SIGHT_STEP).ceil() as usize;
+    for step in 1..steps {
+        let point = from.lerp(to, step as f32 / steps as f32);
+        if ground(point.x, point.z) > point.y + SIGHT_TOLERANCE {
+            return false;
+        }
+    }
+
+    let (start, end) = (from.xz(), to.xz());
+    let direction = end - start;
+    props.into_iter().filter_map(sight_blocker).all(|(base, radius, height)| {
+        // Punto de la línea más cercano al eje del cilindro, visto desde arriba
+        let length = direction.length_squared();
+        let t = if length > 0.0 { ((base.xz() - start).dot(direction) / length).clamp(0.0, 1.0) } else { 0.0 };
+        let closest = from.lerp(to, t);
+        let inside = closest.xz().distance(base.xz()) <= radius;
+        !(inside && closest.y >= base.y && closest.y <= base.y + height)
+    })
+}
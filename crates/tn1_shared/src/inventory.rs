@@ -0,0 +1,931 @@
+//! Inventario por rejilla (ver `docs/SYSTEMS/INVENTORY_SYSTEM.md`).
+//!
+//! Cada contenedor es una rejilla de celdas; cada item ocupa un rectángulo que se
+//! puede girar 90° si su tipo lo permite. Los items apilables forman montones y
+//! los que tienen rejilla propia (mochilas, riñoneras...) guardan otros dentro,
+//! con como mucho `MAX_NESTING` niveles de rejillas anidadas.
+//!
+//! El equipo puesto (`Equipment`) es parte del inventario: se equipa con
+//! `InventoryAction::Equip` y se quita moviéndolo con `Move` a una rejilla. Las
+//! rejillas de lo que se lleva puesto (mochila, rig...) cuentan como bolsillos.
+//! Peso y equipo dan los `GearModifiers` del jugador.
+//!
+//! El servidor es la autoridad: valida cada `InventoryAction` con
+//! `Inventory::apply` y responde con los `InventoryDiff` resultantes, que el
+//! cliente aplica tal cual con `Inventory::apply_diff`. Toda acción se prueba
+//! sobre una copia, así que si falla el inventario queda como estaba.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use uuid::Uuid;
+use crate::components::BodyZone;
+
+pub use crate::items::{EquipmentSlot, GridSpec, ItemDef, ItemDefs};
+
+/// Bolsillos del jugador: cuatro rejillas de 2x2
+pub const POCKET_COUNT: u8 = 4;
+pub const POCKET_SIZE: (u8, u8) = (2, 2);
+/// Niveles de rejillas dentro de rejillas (bolsillo → mochila → riñonera)
+pub const MAX_NESTING: usize = 2;
+/// Kg que se llevan sin penalización sin equipo que ayude; cada múltiplo de
+/// más es un nivel de carga (ver "Niveles de Carga" en el diseño)
+pub const BASE_CARRY_CAPACITY: f32 = 20.0;
+
+/// Identificador único de un item concreto (lo asigna el servidor)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ItemId(pub u64);
+
+impl std::fmt::Display for ItemId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Un item concreto (o un montón de items iguales)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ItemStack {
+    /// Identificador de la sesión; cambia cada vez que se carga de la base de datos
+    pub id: ItemId,
+    /// Identidad permanente del item: no puede haber dos con el mismo en el mundo
+    pub uuid: Uuid,
+    /// Clave de su `ItemDef`
+    pub item: String,
+    pub quantity: u32,
+    /// Estado de 0 a 1, para los tipos que se desgastan (armas)
+    pub durability: Option<f32>,
+    /// Balas cargadas, para las armas
+    pub ammo: Option<u32>,
+    /// Accesorios montados (miras, silenciadores...)
+    pub attachments: Vec<ItemStack>,
+    /// Lo que lleva dentro, si su tipo tiene rejilla
+    pub contents: Option<Container>,
+}
+
+impl ItemStack {
+    /// Item nuevo del tipo dado, con su rejilla vacía si la tiene
+    pub fn new(id: ItemId, def: &ItemDef, quantity: u32) -> Self {
+        Self {
+            id,
+            uuid: Uuid::new_v4(),
+            item: def.key.clone(),
+            quantity,
+            durability: def.weapon.as_ref().map(|_| 1.0),
+            ammo: def.weapon.as_ref().map(|_| 0),
+            attachments: Vec::new(),
+            contents: def.grid.map(Container::from_spec),
+        }
+    }
+
+    /// Pone el item al día con su definición actual: clave, montón, accesorios
+    /// y rejilla (que está al nivel `depth`). Lo que ya no cabe dentro sale a `displaced`
+    fn migrate(&mut self, def: &ItemDef, defs: &ItemDefs, depth: usize, migration: &mut Migration, displaced: &mut Vec<ItemStack>) {
+        if def.key != self.item {
+            self.item = def.key.clone();
+            migration.renamed += 1;
+        }
+        if self.quantity > def.max_stack {
+            self.quantity = def.max_stack;
+            migration.clamped += 1;
+        }
+        for mut attachment in std::mem::take(&mut self.attachments) {
+            match defs.resolve(&attachment.item) {
+                Some(attachment_def) => {
+                    if attachment_def.key != attachment.item {
+                        attachment.item = attachment_def.key.clone();
+                        migration.renamed += 1;
+                    }
+                    self.attachments.push(attachment);
+                }
+                None => migration.lost.push(attachment),
+            }
+        }
+        match (def.grid, self.contents.as_mut()) {
+            (Some(spec), Some(contents)) => {
+                contents.width = spec.width;
+                contents.height = spec.height;
+                contents.max_weight = spec.max_weight;
+            }
+            (Some(spec), None) => self.contents = Some(Container::from_spec(spec)),
+            (None, _) => {
+                // Ya no es un contenedor: lo de dentro busca sitio
+                if let Some(mut contents) = self.contents.take() {
+                    contents.migrate(defs, depth, migration, displaced);
+                    displaced.extend(contents.items.into_iter().map(|inner| inner.stack));
+                }
+            }
+        }
+        if let Some(contents) = self.contents.as_mut() {
+            contents.migrate(defs, depth, migration, displaced);
+        }
+    }
+
+    /// Peso total con accesorios y todo lo que lleva dentro; los tipos
+    /// desconocidos no pesan
+    pub fn weight(&self, defs: &ItemDefs) -> f32 {
+        let own = defs.get(&self.item).map_or(0.0, |def| def.weight * self.quantity as f32);
+        let attachments: f32 = self.attachments.iter().map(|attachment| attachment.weight(defs)).sum();
+        own + attachments + self.contents.as_ref().map_or(0.0, |contents| contents.weight(defs))
+    }
+}
+
+/// Item colocado en una rejilla
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlacedItem {
+    pub stack: ItemStack,
+    /// Celda de la esquina superior izquierda
+    pub x: u8,
+    pub y: u8,
+    pub rotated: bool,
+}
+
+impl PlacedItem {
+    /// Este item o uno de los que lleva dentro
+    fn find(&self, item: ItemId) -> Option<&PlacedItem> {
+        if self.stack.id == item {
+            return Some(self);
+        }
+        self.stack.contents.as_ref().and_then(|contents| contents.find(item))
+    }
+
+    fn find_mut(&mut self, item: ItemId) -> Option<&mut PlacedItem> {
+        if self.stack.id == item {
+            return Some(self);
+        }
+        self.stack.contents.as_mut().and_then(|contents| contents.find_mut(item))
+    }
+
+    fn overlaps(&self, defs: &ItemDefs, x: u8, y: u8, (width, height): (u8, u8)) -> bool {
+        // Un tipo desconocido ocupa al menos su celda
+        let (own_width, own_height) = defs.get(&self.stack.item).map_or((1, 1), |def| def.size(self.rotated));
+        let [x, y, width, height] = [x, y, width, height].map(u16::from);
+        let [own_x, own_y, own_width, own_height] = [self.x, self.y, own_width, own_height].map(u16::from);
+        x < own_x + own_width && own_x < x + width && y < own_y + own_height && own_y < y + height
+    }
+}
+
+/// Rejilla de celdas con items colocados
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Container {
+    pub width: u8,
+    pub height: u8,
+    pub max_weight: Option<f32>,
+    pub items: Vec<PlacedItem>,
+}
+
+impl Container {
+    pub fn new(width: u8, height: u8) -> Self {
+        Self { width, height, max_weight: None, items: Vec::new() }
+    }
+
+    pub fn from_spec(spec: GridSpec) -> Self {
+        Self { max_weight: spec.max_weight, ..Self::new(spec.width, spec.height) }
+    }
+
+    pub fn get(&self, item: ItemId) -> Option<&PlacedItem> {
+        self.items.iter().find(|placed| placed.stack.id == item)
+    }
+
+    /// Comprueba que un rectángulo de `size` celdas cabe en `(x, y)` sin pisar
+    /// a nadie salvo a `ignore` (el item que se está moviendo)
+    pub fn fits(&self, defs: &ItemDefs, x: u8, y: u8, size: (u8, u8), ignore: Option<ItemId>) -> Result<(), InventoryError> {
+        let (width, height) = size;
+        if x as u16 + width as u16 > self.width as u16 || y as u16 + height as u16 > self.height as u16 {
+            return Err(InventoryError::OutOfBounds);
+        }
+        match self
+            .items
+            .iter()
+            .find(|placed| Some(placed.stack.id) != ignore && placed.overlaps(defs, x, y, size))
+        {
+            Some(placed) => Err(InventoryError::Overlaps(placed.stack.id)),
+            None => Ok(()),
+        }
+    }
+
+    /// Primer hueco libre para un item del tipo dado, de arriba abajo y de
+    /// izquierda a derecha; si no cabe derecho se prueba girado
+    pub fn free_spot(&self, defs: &ItemDefs, def: &ItemDef) -> Option<(u8, u8, bool)> {
+        let rotations: &[bool] = if def.rotatable && def.width != def.height { &[false, true] } else { &[false] };
+        rotations.iter().find_map(|rotated| {
+            let size = def.size(*rotated);
+            (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .find(|(x, y)| self.fits(defs, *x, *y, size, None).is_ok())
+                .map(|(x, y)| (x, y, *rotated))
+        })
+    }
+
+    /// Peso de todo lo que hay dentro
+    pub fn weight(&self, defs: &ItemDefs) -> f32 {
+        self.items.iter().map(|placed| placed.stack.weight(defs)).sum()
+    }
+
+    /// Niveles de rejillas que cuelgan de esta (0 si no hay contenedores dentro)
+    pub fn depth(&self) -> usize {
+        self.items
+            .iter()
+            .filter_map(|placed| placed.stack.contents.as_ref())
+            .map(|contents| 1 + contents.depth())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn find(&self, item: ItemId) -> Option<&PlacedItem> {
+        self.items.iter().find_map(|placed| placed.find(item))
+    }
+
+    fn find_mut(&mut self, item: ItemId) -> Option<&mut PlacedItem> {
+        self.items.iter_mut().find_map(|placed| placed.find_mut(item))
+    }
+
+    /// Quita el item de esta rejilla o de cualquiera de las de dentro
+    fn remove(&mut self, item: ItemId) -> Option<PlacedItem> {
+        if let Some(index) = self.items.iter().position(|placed| placed.stack.id == item) {
+            return Some(self.items.remove(index));
+        }
+        self.items
+            .iter_mut()
+            .filter_map(|placed| placed.stack.contents.as_mut())
+            .find_map(|contents| contents.remove(item))
+    }
+
+    /// Rejillas de los items de dentro, recursivamente, en orden
+    fn nested_ids(&self, ids: &mut Vec<ContainerId>) {
+        for placed in &self.items {
+            if let Some(contents) = &placed.stack.contents {
+                ids.push(ContainerId::Item(placed.stack.id));
+                contents.nested_ids(ids);
+            }
+        }
+    }
+
+    /// Rehace la rejilla con el catálogo actual; lo que ya no cabe (o no cabe a
+    /// esta profundidad, o pasa del peso) sale a `displaced`
+    fn migrate(&mut self, defs: &ItemDefs, depth: usize, migration: &mut Migration, displaced: &mut Vec<ItemStack>) {
+        for mut placed in std::mem::take(&mut self.items) {
+            let Some(def) = defs.resolve(&placed.stack.item) else {
+                migration.lost.push(placed.stack);
+                continue;
+            };
+            placed.stack.migrate(def, defs, depth + 1, migration, displaced);
+
+            placed.rotated &= def.rotatable;
+            let fits = self.fits(defs, placed.x, placed.y, def.size(placed.rotated), None).is_ok();
+            let too_deep = placed.stack.contents.as_ref().is_some_and(|contents| depth + 1 + contents.depth() > MAX_NESTING);
+            if fits && !too_deep {
+                self.items.push(placed);
+            } else {
+                displaced.push(placed.stack);
+            }
+        }
+        while self.max_weight.is_some_and(|max| self.weight(defs) > max + 1e-4) {
+            let Some(placed) = self.items.pop() else { break };
+            displaced.push(placed.stack);
+        }
+    }
+
+    /// Ni esta rejilla ni las de dentro pasan de su peso máximo (el peso sube hacia fuera)
+    fn check_weights(&self, defs: &ItemDefs) -> Result<(), InventoryError> {
+        if self.max_weight.is_some_and(|max| self.weight(defs) > max + 1e-4) {
+            return Err(InventoryError::Overweight);
+        }
+        self.items
+            .iter()
+            .filter_map(|placed| placed.stack.contents.as_ref())
+            .try_for_each(|contents| contents.check_weights(defs))
+    }
+}
+
+/// Rejilla del inventario de un jugador
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContainerId {
+    /// Uno de los bolsillos, `0..POCKET_COUNT`
+    Pocket(u8),
+    /// La rejilla de un item contenedor (también si se lleva puesto)
+    Item(ItemId),
+}
+
+impl std::fmt::Display for ContainerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContainerId::Pocket(index) => write!(f, "bolsillo {}", index),
+            ContainerId::Item(item) => write!(f, "contenedor {}", item),
+        }
+    }
+}
+
+/// Destino de un item: rejilla, celda y rotación
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub container: ContainerId,
+    pub x: u8,
+    pub y: u8,
+    pub rotated: bool,
+}
+
+/// Lo que un cliente pide hacer con su inventario
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum InventoryAction {
+    /// Mover (y girar) un item a otra posición o rejilla
+    Move { item: ItemId, to: Slot },
+    /// Separar `quantity` unidades de un montón en un hueco libre
+    Split { item: ItemId, quantity: u32, to: Slot },
+    /// Pasar todo lo que quepa de un montón a otro del mismo tipo
+    Merge { item: ItemId, into: ItemId },
+    /// Tirar el item (con todo lo que lleve dentro) al suelo
+    Drop { item: ItemId },
+    /// Ponerse un item en una ranura libre; para quitárselo se mueve con `Move`
+    Equip { item: ItemId, slot: EquipmentSlot },
+}
+
+/// Cambio autoritativo que el servidor manda tras una acción válida
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum InventoryDiff {
+    /// Item nuevo o movido, con todo su contenido
+    Placed { container: ContainerId, item: PlacedItem },
+    /// Nueva cantidad de un montón
+    Quantity { item: ItemId, quantity: u32 },
+    /// El item ya no está en el inventario
+    Removed { item: ItemId },
+    /// Item puesto en una ranura, con todo su contenido
+    Equipped { slot: EquipmentSlot, item: PlacedItem },
+    /// Balas cargadas en un arma
+    Ammo { item: ItemId, ammo: u32 },
+}
+
+/// Resultado de una acción aplicada
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InventoryOutcome {
+    pub diffs: Vec<InventoryDiff>,
+    /// Lo que se tiró al suelo
+    pub dropped: Option<ItemStack>,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum InventoryError {
+    #[error("item desconocido: {0}")]
+    UnknownItem(ItemId),
+    #[error("tipo de item desconocido: {0}")]
+    UnknownDefinition(String),
+    #[error("no existe el {0}")]
+    UnknownContainer(ContainerId),
+    #[error("no cabe en el contenedor")]
+    OutOfBounds,
+    #[error("el hueco está ocupado por {0}")]
+    Overlaps(ItemId),
+    #[error("este item no se puede girar")]
+    NotRotatable,
+    #[error("este item no se apila")]
+    NotStackable,
+    #[error("solo se apilan items iguales")]
+    DifferentItems,
+    #[error("el montón ya está lleno")]
+    StackFull,
+    #[error("cantidad no válida: {0}")]
+    InvalidQuantity(u32),
+    #[error("un contenedor no puede ir dentro de sí mismo")]
+    IntoItself,
+    #[error("demasiados contenedores anidados (máximo {MAX_NESTING})")]
+    TooDeep,
+    #[error("el contenedor no aguanta tanto peso")]
+    Overweight,
+    #[error("no hay sitio en el inventario")]
+    NoSpace,
+    #[error("esto no se puede llevar en {0}")]
+    WrongSlot(EquipmentSlot),
+    #[error("ya llevas algo en {0}")]
+    SlotOccupied(EquipmentSlot),
+}
+
+/// Lo que cambió al adaptar un inventario a un catálogo nuevo
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Migration {
+    /// Items guardados con una clave antigua
+    pub renamed: usize,
+    /// Montones recortados a su nuevo `max_stack`
+    pub clamped: usize,
+    /// Items que ya no cabían donde estaban y se recolocaron
+    pub moved: usize,
+    /// Lo que ya no existe o no cabe en ningún sitio
+    pub lost: Vec<ItemStack>,
+}
+
+impl Migration {
+    pub fn is_empty(&self) -> bool {
+        self.renamed == 0 && self.clamped == 0 && self.moved == 0 && self.lost.is_empty()
+    }
+}
+
+/// Lo que lleva puesto un jugador, por ranura (siempre en `(0, 0)` sin girar)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Equipment {
+    pub slots: BTreeMap<EquipmentSlot, PlacedItem>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipmentSlot) -> Option<&PlacedItem> {
+        self.slots.get(&slot)
+    }
+
+    /// Ranura en la que se lleva puesto el item
+    pub fn slot_of(&self, item: ItemId) -> Option<EquipmentSlot> {
+        self.slots.iter().find(|(_, placed)| placed.stack.id == item).map(|(slot, _)| *slot)
+    }
+}
+
+/// Lo que el peso y el equipo cambian del jugador
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GearModifiers {
+    /// Peso total que lleva (kg)
+    pub weight: f32,
+    /// Kg que lleva sin penalización
+    pub carry_capacity: f32,
+    /// Se multiplica por la velocidad de andar y de correr
+    pub speed_multiplier: f32,
+    pub can_sprint: bool,
+    /// Mejor clase de armadura que cubre cada zona
+    pub armor: BTreeMap<BodyZone, u8>,
+}
+
+impl Default for GearModifiers {
+    fn default() -> Self {
+        Self {
+            weight: 0.0,
+            carry_capacity: BASE_CARRY_CAPACITY,
+            speed_multiplier: 1.0,
+            can_sprint: true,
+            armor: BTreeMap::new(),
+        }
+    }
+}
+
+/// Inventario de un jugador: sus bolsillos, su equipo y lo que haya dentro
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Inventory {
+    pub pockets: Vec<Container>,
+    pub equipment: Equipment,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            pockets: (0..POCKET_COUNT).map(|_| Container::new(POCKET_SIZE.0, POCKET_SIZE.1)).collect(),
+            equipment: Equipment::default(),
+        }
+    }
+
+    pub fn container(&self, id: ContainerId) -> Option<&Container> {
+        match id {
+            ContainerId::Pocket(index) => self.pockets.get(index as usize),
+            ContainerId::Item(item) => self.find(item).and_then(|placed| placed.stack.contents.as_ref()),
+        }
+    }
+
+    fn container_mut(&mut self, id: ContainerId) -> Option<&mut Container> {
+        match id {
+            ContainerId::Pocket(index) => self.pockets.get_mut(index as usize),
+            ContainerId::Item(item) => self.find_mut(item).and_then(|placed| placed.stack.contents.as_mut()),
+        }
+    }
+
+    /// Busca un item en todas las rejillas y en el equipo
+    pub fn find(&self, item: ItemId) -> Option<&PlacedItem> {
+        self.pockets
+            .iter()
+            .find_map(|pocket| pocket.find(item))
+            .or_else(|| self.equipment.slots.values().find_map(|placed| placed.find(item)))
+    }
+
+    fn find_mut(&mut self, item: ItemId) -> Option<&mut PlacedItem> {
+        let Self { pockets, equipment } = self;
+        pockets
+            .iter_mut()
+            .find_map(|pocket| pocket.find_mut(item))
+            .or_else(|| equipment.slots.values_mut().find_map(|placed| placed.find_mut(item)))
+    }
+
+    /// Rejilla en la que está el item; `None` también si lo lleva puesto
+    pub fn parent(&self, item: ItemId) -> Option<ContainerId> {
+        self.containers()
+            .into_iter()
+            .find(|id| self.container(*id).is_some_and(|container| container.get(item).is_some()))
+    }
+
+    /// Todas las rejillas: los bolsillos y, detrás de cada uno, las de dentro;
+    /// luego las del equipo puesto
+    pub fn containers(&self) -> Vec<ContainerId> {
+        let mut ids = Vec::new();
+        for (index, pocket) in self.pockets.iter().enumerate() {
+            ids.push(ContainerId::Pocket(index as u8));
+            pocket.nested_ids(&mut ids);
+        }
+        for placed in self.equipment.slots.values() {
+            if let Some(contents) = &placed.stack.contents {
+                ids.push(ContainerId::Item(placed.stack.id));
+                contents.nested_ids(&mut ids);
+            }
+        }
+        ids
+    }
+
+    /// Todos los items con la rejilla en la que están
+    pub fn items(&self) -> Vec<(ContainerId, &PlacedItem)> {
+        self.containers()
+            .into_iter()
+            .filter_map(|id| self.container(id).map(|container| (id, container)))
+            .flat_map(|(id, container)| container.items.iter().map(move |placed| (id, placed)))
+            .collect()
+    }
+
+    /// Nivel de una rejilla: 0 los bolsillos y el equipo puesto, 1 lo que va en
+    /// un bolsillo...
+    pub fn depth(&self, id: ContainerId) -> usize {
+        match id {
+            ContainerId::Pocket(_) => 0,
+            ContainerId::Item(item) => self.parent(item).map_or(0, |parent| self.depth(parent) + 1),
+        }
+    }
+
+    /// Si la rejilla `id` es la del item o está dentro de él
+    fn is_within(&self, id: ContainerId, item: ItemId) -> bool {
+        match id {
+            ContainerId::Pocket(_) => false,
+            ContainerId::Item(owner) if owner == item => true,
+            ContainerId::Item(owner) => self.parent(owner).is_some_and(|parent| self.is_within(parent, item)),
+        }
+    }
+
+    pub fn weight(&self, defs: &ItemDefs) -> f32 {
+        let worn: f32 = self.equipment.slots.values().map(|placed| placed.stack.weight(defs)).sum();
+        worn + self.pockets.iter().map(|pocket| pocket.weight(defs)).sum::<f32>()
+    }
+
+    /// Carga y protección con el equipo puesto. Cada múltiplo de la capacidad
+    /// de carga que se pasa es un nivel más lento; en el último no se corre.
+    pub fn modifiers(&self, defs: &ItemDefs) -> GearModifiers {
+        let worn: Vec<&ItemDef> = self.equipment.slots.values().filter_map(|placed| defs.get(&placed.stack.item)).collect();
+        let weight = self.weight(defs);
+        let carry_capacity = BASE_CARRY_CAPACITY + worn.iter().map(|def| def.carry_bonus).sum::<f32>();
+        let load = weight / carry_capacity;
+        let load_multiplier = match load {
+            load if load <= 1.0 => 1.0,
+            load if load <= 2.0 => 0.9,
+            load if load <= 3.0 => 0.75,
+            _ => 0.5,
+        };
+        let penalty: f32 = worn.iter().map(|def| def.speed_penalty).sum();
+
+        let mut armor = BTreeMap::new();
+        for stats in worn.iter().filter_map(|def| def.armor.as_ref()) {
+            for zone in &stats.coverage {
+                let class = armor.entry(*zone).or_insert(0);
+                *class = stats.class.max(*class);
+            }
+        }
+        GearModifiers {
+            weight,
+            carry_capacity,
+            speed_multiplier: load_multiplier * (1.0 - penalty).max(0.2),
+            can_sprint: load <= 3.0,
+            armor,
+        }
+    }
+
+    /// Valida y aplica una acción; si falla el inventario no cambia
+    pub fn apply(
+        &mut self,
+        defs: &ItemDefs,
+        action: &InventoryAction,
+        new_id: impl FnOnce() -> ItemId,
+    ) -> Result<InventoryOutcome, InventoryError> {
+        let mut next = self.clone();
+        let outcome = next.apply_unchecked(defs, action, new_id)?;
+        next.check_weights(defs)?;
+        *self = next;
+        Ok(outcome)
+    }
+
+    fn apply_unchecked(
+        &mut self,
+        defs: &ItemDefs,
+        action: &InventoryAction,
+        new_id: impl FnOnce() -> ItemId,
+    ) -> Result<InventoryOutcome, InventoryError> {
+        match action {
+            InventoryAction::Move { item, to } => {
+                let placed = self.find(*item).ok_or(InventoryError::UnknownItem(*item))?;
+                let def = defs.def(&placed.stack.item)?;
+                if to.rotated != placed.rotated && !def.rotatable {
+                    return Err(InventoryError::NotRotatable);
+                }
+                if self.is_within(to.container, *item) {
+                    return Err(InventoryError::IntoItself);
+                }
+                let container = self.container(to.container).ok_or(InventoryError::UnknownContainer(to.container))?;
+                container.fits(defs, to.x, to.y, def.size(to.rotated), Some(*item))?;
+                if let Some(contents) = &placed.stack.contents {
+                    if self.depth(to.container) + 1 + contents.depth() > MAX_NESTING {
+                        return Err(InventoryError::TooDeep);
+                    }
+                }
+
+                let mut placed = self.remove(*item).ok_or(InventoryError::UnknownItem(*item))?;
+                placed.x = to.x;
+                placed.y = to.y;
+                placed.rotated = to.rotated;
+                self.place(to.container, placed.clone())?;
+                Ok(InventoryOutcome {
+                    diffs: vec![InventoryDiff::Placed { container: to.container, item: placed }],
+                    dropped: None,
+                })
+            }
+
+            InventoryAction::Split { item, quantity, to } => {
+                let placed = self.find(*item).ok_or(InventoryError::UnknownItem(*item))?;
+                let def = defs.def(&placed.stack.item)?;
+                if def.max_stack <= 1 {
+                    return Err(InventoryError::NotStackable);
+                }
+                if *quantity == 0 || *quantity >= placed.stack.quantity {
+                    return Err(InventoryError::InvalidQuantity(*quantity));
+                }
+                if to.rotated && !def.rotatable {
+                    return Err(InventoryError::NotRotatable);
+                }
+                let container = self.container(to.container).ok_or(InventoryError::UnknownContainer(to.container))?;
+                container.fits(defs, to.x, to.y, def.size(to.rotated), None)?;
+
+                let remaining = placed.stack.quantity - quantity;
+                let split = PlacedItem {
+                    stack: ItemStack { contents: None, ..ItemStack::new(new_id(), def, *quantity) },
+                    x: to.x,
+                    y: to.y,
+                    rotated: to.rotated,
+                };
+                self.set_quantity(*item, remaining);
+                self.place(to.container, split.clone())?;
+                Ok(InventoryOutcome {
+                    diffs: vec![
+                        InventoryDiff::Quantity { item: *item, quantity: remaining },
+                        InventoryDiff::Placed { container: to.container, item: split },
+                    ],
+                    dropped: None,
+                })
+            }
+
+            InventoryAction::Merge { item, into } => {
+                if item == into {
+                    return Err(InventoryError::IntoItself);
+                }
+                let source = self.find(*item).ok_or(InventoryError::UnknownItem(*item))?;
+                let target = self.find(*into).ok_or(InventoryError::UnknownItem(*into))?;
+                if source.stack.item != target.stack.item {
+                    return Err(InventoryError::DifferentItems);
+                }
+                let def = defs.def(&target.stack.item)?;
+                if def.max_stack <= 1 {
+                    return Err(InventoryError::NotStackable);
+                }
+                if target.stack.quantity >= def.max_stack {
+                    return Err(InventoryError::StackFull);
+                }
+
+                let moved = source.stack.quantity.min(def.max_stack - target.stack.quantity);
+                let (left, merged) = (source.stack.quantity - moved, target.stack.quantity + moved);
+                let mut diffs = Vec::new();
+                if left == 0 {
+                    self.remove(*item);
+                    diffs.push(InventoryDiff::Removed { item: *item });
+                } else {
+                    self.set_quantity(*item, left);
+                    diffs.push(InventoryDiff::Quantity { item: *item, quantity: left });
+                }
+                self.set_quantity(*into, merged);
+                diffs.push(InventoryDiff::Quantity { item: *into, quantity: merged });
+                Ok(InventoryOutcome { diffs, dropped: None })
+            }
+
+            InventoryAction::Drop { item } => {
+                let placed = self.remove(*item).ok_or(InventoryError::UnknownItem(*item))?;
+                Ok(InventoryOutcome {
+                    diffs: vec![InventoryDiff::Removed { item: *item }],
+                    dropped: Some(placed.stack),
+                })
+            }
+
+            InventoryAction::Equip { item, slot } => {
+                let placed = self.find(*item).ok_or(InventoryError::UnknownItem(*item))?;
+                if !slot.accepts(defs.def(&placed.stack.item)?) {
+                    return Err(InventoryError::WrongSlot(*slot));
+                }
+                if self.equipment.slots.contains_key(slot) {
+                    return Err(InventoryError::SlotOccupied(*slot));
+                }
+
+                let mut placed = self.remove(*item).ok_or(InventoryError::UnknownItem(*item))?;
+                placed.x = 0;
+                placed.y = 0;
+                placed.rotated = false;
+                self.equipment.slots.insert(*slot, placed.clone());
+                Ok(InventoryOutcome {
+                    diffs: vec![InventoryDiff::Equipped { slot: *slot, item: placed }],
+                    dropped: None,
+                })
+            }
+        }
+    }
+
+    /// Guarda un item donde quepa: primero completa montones del mismo tipo y
+    /// el resto va al primer hueco libre (girándolo si hace falta). O entra
+    /// entero o no entra nada.
+    pub fn insert(&mut self, defs: &ItemDefs, stack: ItemStack) -> Result<Vec<InventoryDiff>, InventoryError> {
+        let mut next = self.clone();
+        let diffs = next.insert_unchecked(defs, stack)?;
+        next.check_weights(defs)?;
+        *self = next;
+        Ok(diffs)
+    }
+
+    fn insert_unchecked(&mut self, defs: &ItemDefs, mut stack: ItemStack) -> Result<Vec<InventoryDiff>, InventoryError> {
+        let def = defs.def(&stack.item)?;
+        if stack.quantity == 0 || stack.quantity > def.max_stack {
+            return Err(InventoryError::InvalidQuantity(stack.quantity));
+        }
+        let mut diffs = Vec::new();
+
+        if def.max_stack > 1 {
+            let partial: Vec<(ItemId, u32)> = self
+                .items()
+                .into_iter()
+                .filter(|(_, placed)| placed.stack.item == stack.item && placed.stack.quantity < def.max_stack)
+                .map(|(_, placed)| (placed.stack.id, placed.stack.quantity))
+                .collect();
+            for (id, quantity) in partial {
+                let moved = stack.quantity.min(def.max_stack - quantity);
+                self.set_quantity(id, quantity + moved);
+                diffs.push(InventoryDiff::Quantity { item: id, quantity: quantity + moved });
+                stack.quantity -= moved;
+                if stack.quantity == 0 {
+                    return Ok(diffs);
+                }
+            }
+        }
+
+        let inner_depth = stack.contents.as_ref().map(|contents| 1 + contents.depth());
+        let spot = self.containers().into_iter().find_map(|id| {
+            if inner_depth.is_some_and(|inner| self.depth(id) + inner > MAX_NESTING) {
+                return None;
+            }
+            self.container(id)?.free_spot(defs, def).map(|(x, y, rotated)| (id, x, y, rotated))
+        });
+        let (container, x, y, rotated) = spot.ok_or(InventoryError::NoSpace)?;
+        let placed = PlacedItem { stack, x, y, rotated };
+        self.place(container, placed.clone())?;
+        diffs.push(InventoryDiff::Placed { container, item: placed });
+        Ok(diffs)
+    }
+
+    /// Adapta el inventario a un catálogo que ha cambiado desde que se guardó:
+    /// sigue los `aliases`, recorta montones, ajusta las rejillas y recoloca lo
+    /// que ya no cabe donde estaba
+    pub fn migrate(&mut self, defs: &ItemDefs) -> Migration {
+        self.migrate_with(defs, Vec::new())
+    }
+
+    /// Como `migrate`, y además busca sitio a `loose`: items que no tienen
+    /// dónde estar (p. ej. al cargar un inventario guardado con huecos)
+    pub fn migrate_with(&mut self, defs: &ItemDefs, loose: Vec<ItemStack>) -> Migration {
+        let mut migration = Migration::default();
+        let mut displaced = Vec::new();
+        for pocket in &mut self.pockets {
+            pocket.migrate(defs, 0, &mut migration, &mut displaced);
+        }
+        for (slot, mut placed) in std::mem::take(&mut self.equipment.slots) {
+            let Some(def) = defs.resolve(&placed.stack.item) else {
+                migration.lost.push(placed.stack);
+                continue;
+            };
+            placed.stack.migrate(def, defs, 0, &mut migration, &mut displaced);
+            if slot.accepts(def) {
+                self.equipment.slots.insert(slot, placed);
+            } else {
+                displaced.push(placed.stack);
+            }
+        }
+        // Lo suelto se adapta igual, en una rejilla de paso donde no cabe nada
+        let mut scratch = Container::new(0, 0);
+        scratch.items = loose.into_iter().map(|stack| PlacedItem { stack, x: 0, y: 0, rotated: false }).collect();
+        scratch.migrate(defs, 0, &mut migration, &mut displaced);
+        for stack in displaced {
+            match self.insert(defs, stack.clone()) {
+                Ok(_) => migration.moved += 1,
+                Err(_) => migration.lost.push(stack),
+            }
+        }
+        migration
+    }
+
+    /// Unidades de un tipo de item en las rejillas (la munición de reserva)
+    pub fn count(&self, key: &str) -> u32 {
+        self.items().iter().filter(|(_, placed)| placed.stack.item == key).map(|(_, placed)| placed.stack.quantity).sum()
+    }
+
+    /// Saca hasta `quantity` unidades de un tipo, empezando por los montones
+    /// más pequeños; devuelve cuántas sacó y los cambios
+    pub fn take(&mut self, key: &str, quantity: u32) -> (u32, Vec<InventoryDiff>) {
+        let mut stacks: Vec<(ItemId, u32)> = self
+            .items()
+            .iter()
+            .filter(|(_, placed)| placed.stack.item == key)
+            .map(|(_, placed)| (placed.stack.id, placed.stack.quantity))
+            .collect();
+        stacks.sort_by_key(|(_, available)| *available);
+
+        let (mut taken, mut diffs) = (0, Vec::new());
+        for (item, available) in stacks {
+            if taken == quantity {
+                break;
+            }
+            let used = available.min(quantity - taken);
+            taken += used;
+            if used == available {
+                self.remove(item);
+                diffs.push(InventoryDiff::Removed { item });
+            } else {
+                self.set_quantity(item, available - used);
+                diffs.push(InventoryDiff::Quantity { item, quantity: available - used });
+            }
+        }
+        (taken, diffs)
+    }
+
+    /// Cambia las balas cargadas de un arma
+    pub fn set_ammo(&mut self, item: ItemId, ammo: u32) -> Option<InventoryDiff> {
+        let placed = self.find_mut(item)?;
+        placed.stack.ammo = Some(ammo);
+        Some(InventoryDiff::Ammo { item, ammo })
+    }
+
+    /// Aplica un cambio que ya validó el servidor
+    pub fn apply_diff(&mut self, diff: InventoryDiff) {
+        match diff {
+            InventoryDiff::Placed { container, item } => {
+                self.remove(item.stack.id);
+                if let Some(container) = self.container_mut(container) {
+                    container.items.push(item);
+                }
+            }
+            InventoryDiff::Quantity { item, quantity } => self.set_quantity(item, quantity),
+            InventoryDiff::Removed { item } => {
+                self.remove(item);
+            }
+            InventoryDiff::Equipped { slot, item } => {
+                self.remove(item.stack.id);
+                self.equipment.slots.insert(slot, item);
+            }
+            InventoryDiff::Ammo { item, ammo } => {
+                self.set_ammo(item, ammo);
+            }
+        }
+    }
+
+    fn remove(&mut self, item: ItemId) -> Option<PlacedItem> {
+        if let Some(slot) = self.equipment.slot_of(item) {
+            return self.equipment.slots.remove(&slot);
+        }
+        let Self { pockets, equipment } = self;
+        pockets.iter_mut().find_map(|pocket| pocket.remove(item)).or_else(|| {
+            equipment
+                .slots
+                .values_mut()
+                .filter_map(|placed| placed.stack.contents.as_mut())
+                .find_map(|contents| contents.remove(item))
+        })
+    }
+
+    fn check_weights(&self, defs: &ItemDefs) -> Result<(), InventoryError> {
+        self.pockets
+            .iter()
+            .chain(self.equipment.slots.values().filter_map(|placed| placed.stack.contents.as_ref()))
+            .try_for_each(|container| container.check_weights(defs))
+    }
+
+    fn place(&mut self, id: ContainerId, placed: PlacedItem) -> Result<(), InventoryError> {
+        self.container_mut(id).ok_or(InventoryError::UnknownContainer(id))?.items.push(placed);
+        Ok(())
+    }
+
+    fn set_quantity(&mut self, item: ItemId, quantity: u32) {
+        if let Some(placed) = self.find_mut(item) {
+            placed.stack.quantity = quantity;
+        }
+    }
+}
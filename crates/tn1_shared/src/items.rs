@@ -0,0 +1,565 @@
+//! Catálogo de tipos de item, cargado de ficheros RON (`data/items/*.ron`).
+//!
+//! Cada fichero declara su `version` y una lista de items:
+//!
+//! ```ron
+//! #![enable(implicit_some)]
+//! (
+//!     version: 1,
+//!     items: [
+//!         (key: "bandage", name: "Venda", weight: 0.05, max_stack: 5, category: Medical,
+//!          medical: (heal: 5.0, stops_bleeding: true, use_time: 3.0)),
+//!     ],
+//! )
+//! ```
+//!
+//! Los campos que faltan toman el valor de `ItemDef::default()`. Todo se valida
+//! al cargar (`ItemDefs::load`) y el primer error sale con el fichero y el item.
+//! Las claves son lo que se guarda de cada item: para renombrar una se deja la
+//! vieja en `aliases` y `Inventory::migrate` actualiza lo ya guardado.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use thiserror::Error;
+use crate::ballistics::RoundStats;
+use crate::components::BodyZone;
+use crate::inventory::InventoryError;
+
+/// Extensión de los ficheros de definiciones
+pub const ITEM_FILE_EXTENSION: &str = "ron";
+/// Lado máximo (en celdas) de un item o de una rejilla
+pub const MAX_ITEM_SIDE: u8 = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ItemCategory {
+    Weapon,
+    Ammo,
+    Medical,
+    Food,
+    Container,
+    Clothing,
+    #[default]
+    Misc,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+/// Rejilla interna de un tipo de item contenedor
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GridSpec {
+    pub width: u8,
+    pub height: u8,
+    /// Peso máximo (kg) que aguanta lo que lleva dentro
+    #[serde(default)]
+    pub max_weight: Option<f32>,
+}
+
+/// Ranura de equipo (ver `docs/SYSTEMS/INVENTORY_SYSTEM.md`)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EquipmentSlot {
+    Head,
+    Face,
+    BodyArmor,
+    Rig,
+    Backpack,
+    Primary,
+    Secondary,
+    Holster,
+    SecureContainer,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 9] = [
+        EquipmentSlot::Head,
+        EquipmentSlot::Face,
+        EquipmentSlot::BodyArmor,
+        EquipmentSlot::Rig,
+        EquipmentSlot::Backpack,
+        EquipmentSlot::Primary,
+        EquipmentSlot::Secondary,
+        EquipmentSlot::Holster,
+        EquipmentSlot::SecureContainer,
+    ];
+
+    /// Nombre estable, el que se guarda en la base de datos
+    pub fn key(self) -> &'static str {
+        match self {
+            EquipmentSlot::Head => "head",
+            EquipmentSlot::Face => "face",
+            EquipmentSlot::BodyArmor => "body_armor",
+            EquipmentSlot::Rig => "rig",
+            EquipmentSlot::Backpack => "backpack",
+            EquipmentSlot::Primary => "primary",
+            EquipmentSlot::Secondary => "secondary",
+            EquipmentSlot::Holster => "holster",
+            EquipmentSlot::SecureContainer => "secure_container",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|slot| slot.key() == key)
+    }
+
+    /// Si un item de ese tipo se puede llevar aquí; las armas de `Primary`
+    /// también van en `Secondary`
+    pub fn accepts(self, def: &ItemDef) -> bool {
+        match (self, def.slot) {
+            (EquipmentSlot::Secondary, Some(EquipmentSlot::Primary)) => true,
+            (slot, Some(own)) => slot == own,
+            (_, None) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for EquipmentSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            EquipmentSlot::Head => "cabeza",
+            EquipmentSlot::Face => "cara",
+            EquipmentSlot::BodyArmor => "chaleco",
+            EquipmentSlot::Rig => "rig",
+            EquipmentSlot::Backpack => "mochila",
+            EquipmentSlot::Primary => "arma principal",
+            EquipmentSlot::Secondary => "arma secundaria",
+            EquipmentSlot::Holster => "pistolera",
+            EquipmentSlot::SecureContainer => "contenedor seguro",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Protección de una pieza de armadura
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ArmorStats {
+    /// Clase de protección, de 1 a 6
+    pub class: u8,
+    /// Zonas que cubre
+    pub coverage: Vec<BodyZone>,
+}
+
+/// Modo de disparo de un arma
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FireMode {
+    /// Un disparo por pulsación
+    Semi,
+    /// `BURST_SIZE` disparos por pulsación
+    Burst,
+    /// Dispara mientras se mantenga el gatillo
+    Auto,
+}
+
+/// Disparos de una ráfaga
+pub const BURST_SIZE: u32 = 3;
+
+impl std::fmt::Display for FireMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FireMode::Semi => write!(f, "semiautomático"),
+            FireMode::Burst => write!(f, "ráfaga"),
+            FireMode::Auto => write!(f, "automático"),
+        }
+    }
+}
+
+/// Datos de un arma (ver `docs/SYSTEMS/WEAPONS_SYSTEM.md`)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WeaponStats {
+    /// Clave del item de munición que usa
+    pub ammo: String,
+    pub damage: f32,
+    /// Disparos por minuto
+    pub fire_rate: f32,
+    /// Velocidad de salida (m/s)
+    pub muzzle_velocity: f32,
+    pub magazine: u32,
+    /// Modos del selector; el primero es el que lleva al cogerla
+    #[serde(default = "default_fire_modes")]
+    pub modes: Vec<FireMode>,
+    /// Segundos para cambiar el cargador
+    #[serde(default = "default_reload_time")]
+    pub reload_time: f32,
+    /// Segundos para montar el arma si la recámara estaba vacía
+    #[serde(default = "default_chamber_time")]
+    pub chamber_time: f32,
+}
+
+fn default_fire_modes() -> Vec<FireMode> {
+    vec![FireMode::Semi]
+}
+
+fn default_reload_time() -> f32 {
+    2.0
+}
+
+fn default_chamber_time() -> f32 {
+    0.5
+}
+
+/// Efecto de usar un item médico
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct MedicalEffects {
+    pub heal: f32,
+    pub stops_bleeding: bool,
+    /// Segundos que se tarda en usarlo
+    pub use_time: f32,
+}
+
+/// Lo que da comer o beber un item
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Nutrition {
+    pub calories: f32,
+    /// Litros de agua
+    pub hydration: f32,
+}
+
+/// Definición de un tipo de item
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ItemDef {
+    /// Clave estable ("bandage", "ak74"...): es lo que guardan los items
+    pub key: String,
+    pub name: String,
+    /// Celdas que ocupa sin girar
+    pub width: u8,
+    pub height: u8,
+    /// Peso de una unidad (kg)
+    pub weight: f32,
+    /// Unidades por montón; 1 = no apilable
+    pub max_stack: u32,
+    /// Solo cuenta si no es cuadrado
+    pub rotatable: bool,
+    pub category: ItemCategory,
+    pub rarity: Rarity,
+    /// Rejilla propia si el item es un contenedor
+    pub grid: Option<GridSpec>,
+    pub weapon: Option<WeaponStats>,
+    /// La bala, si es munición (sin ella, una 9x19 FMJ)
+    pub round: Option<RoundStats>,
+    pub medical: Option<MedicalEffects>,
+    pub nutrition: Option<Nutrition>,
+    /// Ranura en la que se lleva puesto, si es equipo
+    pub slot: Option<EquipmentSlot>,
+    pub armor: Option<ArmorStats>,
+    /// Kg de más que se llevan sin penalización con esto puesto
+    pub carry_bonus: f32,
+    /// Parte de la velocidad (0 a 1) que quita llevarlo puesto
+    pub speed_penalty: f32,
+    /// Claves antiguas de este item
+    pub aliases: Vec<String>,
+}
+
+impl Default for ItemDef {
+    fn default() -> Self {
+        Self {
+            key: String::new(),
+            name: String::new(),
+            width: 1,
+            height: 1,
+            weight: 0.0,
+            max_stack: 1,
+            rotatable: true,
+            category: ItemCategory::default(),
+            rarity: Rarity::default(),
+            grid: None,
+            weapon: None,
+            round: None,
+            medical: None,
+            nutrition: None,
+            slot: None,
+            armor: None,
+            carry_bonus: 0.0,
+            speed_penalty: 0.0,
+            aliases: Vec::new(),
+        }
+    }
+}
+
+impl ItemDef {
+    /// Celdas (ancho, alto) que ocupa con la rotación dada
+    pub fn size(&self, rotated: bool) -> (u8, u8) {
+        if rotated {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
+    /// Primer problema de la definición, si lo hay
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_key = |key: &str| {
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        };
+        if !valid_key(&self.key) {
+            return Err("la clave solo puede tener minúsculas, dígitos y '_'".to_string());
+        }
+        if let Some(alias) = self.aliases.iter().find(|alias| !valid_key(alias)) {
+            return Err(format!("alias no válido: '{}'", alias));
+        }
+        if self.name.trim().is_empty() {
+            return Err("falta el nombre".to_string());
+        }
+        let side = 1..=MAX_ITEM_SIDE;
+        if !side.contains(&self.width) || !side.contains(&self.height) {
+            return Err(format!("tamaño {}x{} fuera de 1..={}", self.width, self.height, MAX_ITEM_SIDE));
+        }
+        if !self.weight.is_finite() || self.weight < 0.0 {
+            return Err(format!("peso no válido: {}", self.weight));
+        }
+        if self.max_stack == 0 {
+            return Err("max_stack tiene que ser al menos 1".to_string());
+        }
+
+        if let Some(grid) = &self.grid {
+            if !side.contains(&grid.width) || !side.contains(&grid.height) {
+                return Err(format!("rejilla {}x{} fuera de 1..={}", grid.width, grid.height, MAX_ITEM_SIDE));
+            }
+            if grid.max_weight.is_some_and(|max| !max.is_finite() || max <= 0.0) {
+                return Err("el peso máximo de la rejilla tiene que ser positivo".to_string());
+            }
+            if self.max_stack > 1 {
+                return Err("un contenedor no se puede apilar".to_string());
+            }
+        }
+        if (self.category == ItemCategory::Container) != self.grid.is_some() {
+            return Err("la categoría Container y la rejilla van juntas".to_string());
+        }
+
+        match (&self.weapon, self.category) {
+            (Some(weapon), ItemCategory::Weapon) => {
+                if [weapon.damage, weapon.fire_rate, weapon.muzzle_velocity].iter().any(|value| !value.is_finite() || *value <= 0.0) {
+                    return Err("daño, cadencia y velocidad de salida tienen que ser positivos".to_string());
+                }
+                if weapon.magazine == 0 {
+                    return Err("el cargador no puede estar vacío".to_string());
+                }
+                if weapon.modes.is_empty() {
+                    return Err("un arma necesita al menos un modo de disparo".to_string());
+                }
+                if [weapon.reload_time, weapon.chamber_time].iter().any(|value| !value.is_finite() || *value < 0.0) {
+                    return Err("los tiempos de recarga no pueden ser negativos".to_string());
+                }
+            }
+            (None, ItemCategory::Weapon) => return Err("un arma necesita el bloque weapon".to_string()),
+            (Some(_), _) => return Err("el bloque weapon solo vale para la categoría Weapon".to_string()),
+            (None, _) => {}
+        }
+        if let Some(round) = &self.round {
+            if self.category != ItemCategory::Ammo {
+                return Err("el bloque round solo vale para la categoría Ammo".to_string());
+            }
+            if [round.mass, round.diameter, round.drag].iter().any(|value| !value.is_finite() || *value <= 0.0) {
+                return Err("masa, calibre y rozamiento de la bala tienen que ser positivos".to_string());
+            }
+        }
+        if self.medical.is_some() != (self.category == ItemCategory::Medical) {
+            return Err("el bloque medical va con la categoría Medical".to_string());
+        }
+        if self.nutrition.is_some() != (self.category == ItemCategory::Food) {
+            return Err("el bloque nutrition va con la categoría Food".to_string());
+        }
+
+        match self.slot {
+            Some(EquipmentSlot::Primary | EquipmentSlot::Secondary | EquipmentSlot::Holster) if self.weapon.is_none() => {
+                return Err("en las ranuras de armas solo van armas".to_string());
+            }
+            Some(EquipmentSlot::Backpack | EquipmentSlot::SecureContainer) if self.grid.is_none() => {
+                return Err("mochilas y contenedores seguros necesitan rejilla".to_string());
+            }
+            _ => {}
+        }
+        if let Some(armor) = &self.armor {
+            if !(1..=6).contains(&armor.class) {
+                return Err(format!("clase de armadura {} fuera de 1..=6", armor.class));
+            }
+            if armor.coverage.is_empty() {
+                return Err("la armadura tiene que cubrir alguna zona".to_string());
+            }
+            if !matches!(self.slot, Some(EquipmentSlot::Head | EquipmentSlot::Face | EquipmentSlot::BodyArmor | EquipmentSlot::Rig)) {
+                return Err("la armadura va en cabeza, cara, chaleco o rig".to_string());
+            }
+        }
+        if !self.carry_bonus.is_finite() || self.carry_bonus < 0.0 {
+            return Err(format!("carry_bonus no válido: {}", self.carry_bonus));
+        }
+        if !(0.0..1.0).contains(&self.speed_penalty) {
+            return Err(format!("speed_penalty {} fuera de 0..1", self.speed_penalty));
+        }
+        if self.slot.is_none() && (self.armor.is_some() || self.carry_bonus > 0.0 || self.speed_penalty > 0.0) {
+            return Err("armor, carry_bonus y speed_penalty solo valen para equipo con slot".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Un fichero de definiciones tal cual se escribe
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ItemFile {
+    /// Se sube al cambiar cualquier definición del fichero
+    pub version: u32,
+    pub items: Vec<ItemDef>,
+}
+
+#[derive(Error, Debug)]
+pub enum ItemDefsError {
+    #[error("no se pudo leer {}: {source}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("{}:{error}", path.display())]
+    Parse { path: PathBuf, error: ron::error::SpannedError },
+    #[error("{}: item '{key}': {problem}", path.display())]
+    Invalid { path: PathBuf, key: String, problem: String },
+    #[error("la clave '{key}' está repetida ({} y {})", first.display(), second.display())]
+    Duplicate { key: String, first: PathBuf, second: PathBuf },
+    #[error("{}: item '{key}': {problem}", path.display())]
+    BadReference { path: PathBuf, key: String, problem: String },
+    #[error("no hay ficheros .{ITEM_FILE_EXTENSION} con items en {}", .0.display())]
+    Empty(PathBuf),
+}
+
+/// Catálogo de tipos de item
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ItemDefs {
+    defs: HashMap<String, ItemDef>,
+    /// Clave antigua → clave actual
+    aliases: HashMap<String, String>,
+    version: u32,
+}
+
+impl ItemDefs {
+    /// Catálogo sin validar (tests, herramientas)
+    pub fn new(defs: impl IntoIterator<Item = ItemDef>) -> Self {
+        let mut catalog = Self::default();
+        for def in defs {
+            catalog.insert(def);
+        }
+        catalog
+    }
+
+    pub fn insert(&mut self, def: ItemDef) {
+        for alias in &def.aliases {
+            self.aliases.insert(alias.clone(), def.key.clone());
+        }
+        self.defs.insert(def.key.clone(), def);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ItemDef> {
+        self.defs.get(key)
+    }
+
+    pub fn def(&self, key: &str) -> Result<&ItemDef, InventoryError> {
+        self.get(key).ok_or_else(|| InventoryError::UnknownDefinition(key.to_string()))
+    }
+
+    /// Definición de una clave actual o antigua
+    pub fn resolve(&self, key: &str) -> Option<&ItemDef> {
+        self.get(key).or_else(|| self.aliases.get(key).and_then(|current| self.get(current)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ItemDef> {
+        self.defs.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.defs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defs.is_empty()
+    }
+
+    /// Versión del catálogo: la más alta de sus ficheros
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Carga un fichero o todos los `.ron` de una carpeta (en orden alfabético)
+    pub fn load(path: &Path) -> Result<Self, ItemDefsError> {
+        let mut catalog = Self::default();
+        let mut origins: HashMap<String, PathBuf> = HashMap::new();
+        for file in item_files(path)? {
+            let source = std::fs::read_to_string(&file).map_err(|source| ItemDefsError::Io { path: file.clone(), source })?;
+            let parsed = Self::parse(&source, &file)?;
+            catalog.version = catalog.version.max(parsed.version);
+            for def in parsed.items {
+                for key in std::iter::once(&def.key).chain(&def.aliases) {
+                    if let Some(first) = origins.insert(key.clone(), file.clone()) {
+                        return Err(ItemDefsError::Duplicate { key: key.clone(), first, second: file.clone() });
+                    }
+                }
+                catalog.insert(def);
+            }
+        }
+        if catalog.is_empty() {
+            return Err(ItemDefsError::Empty(path.to_path_buf()));
+        }
+        catalog.check_references(&origins)?;
+        Ok(catalog)
+    }
+
+    /// Lee y valida un fichero; `path` solo se usa en los errores
+    pub fn parse(source: &str, path: &Path) -> Result<ItemFile, ItemDefsError> {
+        let mut file: ItemFile = ron::from_str(source).map_err(|error| ItemDefsError::Parse { path: path.to_path_buf(), error })?;
+        for def in &mut file.items {
+            def.validate()
+                .map_err(|problem| ItemDefsError::Invalid { path: path.to_path_buf(), key: def.key.clone(), problem })?;
+            // Girar algo cuadrado no cambia nada
+            def.rotatable &= def.width != def.height;
+        }
+        Ok(file)
+    }
+
+    /// Lo que una definición nombra de otra tiene que existir
+    fn check_references(&self, origins: &HashMap<String, PathBuf>) -> Result<(), ItemDefsError> {
+        for def in self.defs.values() {
+            let Some(weapon) = &def.weapon else { continue };
+            let problem = match self.get(&weapon.ammo) {
+                None => format!("la munición '{}' no existe", weapon.ammo),
+                Some(ammo) if ammo.category != ItemCategory::Ammo => format!("'{}' no es munición", weapon.ammo),
+                Some(_) => continue,
+            };
+            return Err(ItemDefsError::BadReference { path: origins[&def.key].clone(), key: def.key.clone(), problem });
+        }
+        Ok(())
+    }
+}
+
+/// Ficheros de definiciones bajo `path`, en orden
+pub fn item_files(path: &Path) -> Result<Vec<PathBuf>, ItemDefsError> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let entries = std::fs::read_dir(path).map_err(|source| ItemDefsError::Io { path: path.to_path_buf(), source })?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.extension().is_some_and(|extension| extension == ITEM_FILE_EXTENSION))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Huella de los ficheros (ruta, fecha de modificación y tamaño) para saber si
+/// hay que recargar; vacía si no se puede leer
+pub fn item_files_fingerprint(path: &Path) -> Vec<(PathBuf, Option<SystemTime>, u64)> {
+    item_files(path)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|file| {
+            let metadata = std::fs::metadata(&file).ok();
+            let modified = metadata.as_ref().and_then(|metadata| metadata.modified().ok());
+            let len = metadata.map_or(0, |metadata| metadata.len());
+            (file, modified, len)
+        })
+        .collect()
+}
@@ -1,8 +1,26 @@
+pub mod ballistics;
+pub mod chunk;
+pub mod codec;
+pub mod conditioner;
 pub mod components;
+pub mod daylight;
+pub mod drops;
 pub mod events;
+pub mod inventory;
+pub mod items;
+pub mod loot;
+pub mod medical;
 pub mod protocol;
 pub mod constants;
+pub mod status;
+pub mod terrain;
+pub mod trade;
+pub mod transport;
+pub mod voice;
+pub mod weapons;
+pub mod weather;
 
+pub use codec::*;
 pub use components::*;
 pub use events::*;
 pub use protocol::*;
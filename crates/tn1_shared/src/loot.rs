@@ -0,0 +1,272 @@
+//! Tablas de loot (`data/loot/*.ron`): qué aparece en los contenedores del mundo.
+//!
+//! Cada contenedor de un chunk (`LootContainer`) usa la tabla más concreta que
+//! encaje con su tipo y con el bioma donde está. De momento no hay edificios: el
+//! tipo de contenedor (caja, caja de herramientas, botiquín) hace de "edificio".
+//!
+//! ```ron
+//! (
+//!     caps: { "rifle": 6 },
+//!     tables: [
+//!         (key: "medical", containers: [MedicalBox], rolls: (1, 3), restock_secs: 900.0,
+//!          entries: [(item: "bandage", weight: 10, quantity: (1, 3))]),
+//!     ],
+//! )
+//! ```
+//!
+//! `caps` limita las unidades de un item que puede haber a la vez en
+//! contenedores e inventarios de jugadores conectados: con el tope alcanzado
+//! esa entrada deja de salir. Todo se valida contra el catálogo de items al cargar.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use crate::chunk::{ContainerKind, WorldRng};
+use crate::inventory::{InventoryError, ItemId};
+use crate::items::{item_files, ItemDefs, ItemDefsError};
+use crate::terrain::Biome;
+
+/// Tiradas como mucho por relleno de un contenedor
+pub const MAX_ROLLS: u32 = 20;
+
+/// Una posible tirada de una tabla
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LootEntry {
+    /// Clave del item
+    pub item: String,
+    /// Peso relativo frente a las demás entradas de la tabla
+    pub weight: u32,
+    /// Unidades (mínimo, máximo) de cada tirada
+    #[serde(default = "single")]
+    pub quantity: (u32, u32),
+}
+
+fn single() -> (u32, u32) {
+    (1, 1)
+}
+
+/// Qué sale en un tipo de punto de aparición
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LootTable {
+    pub key: String,
+    /// Tipos de contenedor que la usan; vacío = todos
+    #[serde(default)]
+    pub containers: Vec<ContainerKind>,
+    /// Biomas donde se usa; vacío = todos
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+    /// Tiradas (mínimo, máximo) cada vez que se llena el contenedor
+    pub rolls: (u32, u32),
+    /// Segundos desde que alguien saca el primer item hasta que se rellena
+    pub restock_secs: f32,
+    pub entries: Vec<LootEntry>,
+}
+
+impl LootTable {
+    /// Si vale para un contenedor de este tipo en este bioma
+    pub fn matches(&self, kind: ContainerKind, biome: Biome) -> bool {
+        (self.containers.is_empty() || self.containers.contains(&kind)) && (self.biomes.is_empty() || self.biomes.contains(&biome))
+    }
+
+    /// Cuánto restringe la tabla: gana la más concreta
+    fn specificity(&self) -> u8 {
+        u8::from(!self.containers.is_empty()) + u8::from(!self.biomes.is_empty())
+    }
+
+    /// Primer problema de la tabla, si lo hay
+    pub fn validate(&self, defs: &ItemDefs) -> Result<(), String> {
+        if self.key.is_empty() || !self.key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+            return Err("la clave solo puede tener minúsculas, dígitos y '_'".to_string());
+        }
+        let (min, max) = self.rolls;
+        if min > max || max == 0 || max > MAX_ROLLS {
+            return Err(format!("rolls ({}, {}) no válido (máximo {})", min, max, MAX_ROLLS));
+        }
+        if !self.restock_secs.is_finite() || self.restock_secs <= 0.0 {
+            return Err(format!("restock_secs no válido: {}", self.restock_secs));
+        }
+        if self.entries.is_empty() {
+            return Err("la tabla no tiene entradas".to_string());
+        }
+        for entry in &self.entries {
+            let Some(def) = defs.get(&entry.item) else {
+                return Err(format!("el item '{}' no existe", entry.item));
+            };
+            if entry.weight == 0 {
+                return Err(format!("'{}' tiene peso 0", entry.item));
+            }
+            let (min, max) = entry.quantity;
+            if min == 0 || min > max || max > def.max_stack {
+                return Err(format!("cantidad ({}, {}) de '{}' no válida (montón de {})", min, max, entry.item, def.max_stack));
+            }
+        }
+        Ok(())
+    }
+
+    /// Tira la tabla: (item, unidades) de cada tirada. `remaining` dice cuántas
+    /// unidades de un item caben aún bajo su tope; lo que sale en esta misma
+    /// tirada ya se descuenta aquí
+    pub fn roll(&self, rng: &mut WorldRng, remaining: impl Fn(&str) -> u32) -> Vec<(String, u32)> {
+        let (min, max) = self.rolls;
+        let rolls = min + (rng.next_u64() % (max - min + 1) as u64) as u32;
+        let mut rolled: Vec<(String, u32)> = Vec::new();
+        let left = |item: &str, rolled: &[(String, u32)]| {
+            let taken: u32 = rolled.iter().filter(|(key, _)| key == item).map(|(_, quantity)| quantity).sum();
+            remaining(item).saturating_sub(taken)
+        };
+
+        for _ in 0..rolls {
+            let available: Vec<&LootEntry> = self.entries.iter().filter(|entry| left(&entry.item, &rolled) > 0).collect();
+            let total: u64 = available.iter().map(|entry| entry.weight as u64).sum();
+            if total == 0 {
+                break;
+            }
+            let mut pick = rng.next_u64() % total;
+            let Some(entry) = available.into_iter().find(|entry| {
+                let found = pick < entry.weight as u64;
+                pick = pick.saturating_sub(entry.weight as u64);
+                found
+            }) else {
+                break;
+            };
+            let (min, max) = entry.quantity;
+            let quantity = min + (rng.next_u64() % (max - min + 1) as u64) as u32;
+            rolled.push((entry.item.clone(), quantity.min(left(&entry.item, &rolled))));
+        }
+        rolled
+    }
+}
+
+/// Un fichero de tablas tal cual se escribe
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LootFile {
+    /// Tope de unidades por item en todo el mundo
+    #[serde(default)]
+    pub caps: HashMap<String, u32>,
+    pub tables: Vec<LootTable>,
+}
+
+#[derive(Error, Debug)]
+pub enum LootTablesError {
+    #[error(transparent)]
+    Files(#[from] ItemDefsError),
+    #[error("no se pudo leer {}: {source}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("{}:{error}", path.display())]
+    Parse { path: PathBuf, error: ron::error::SpannedError },
+    #[error("{}: tabla '{key}': {problem}", path.display())]
+    Invalid { path: PathBuf, key: String, problem: String },
+    #[error("'{key}' está repetido ({} y {})", first.display(), second.display())]
+    Duplicate { key: String, first: PathBuf, second: PathBuf },
+    #[error("no hay tablas de loot en {}", .0.display())]
+    Empty(PathBuf),
+}
+
+/// Todas las tablas de loot y los topes por item
+#[derive(Resource, Debug, Clone, Default)]
+pub struct LootTables {
+    tables: Vec<LootTable>,
+    caps: HashMap<String, u32>,
+}
+
+impl LootTables {
+    /// Tablas sin validar (tests, herramientas)
+    pub fn new(tables: impl IntoIterator<Item = LootTable>, caps: HashMap<String, u32>) -> Self {
+        Self { tables: tables.into_iter().collect(), caps }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&LootTable> {
+        self.tables.iter().find(|table| table.key == key)
+    }
+
+    /// La tabla más concreta para el punto; entre iguales, la primera cargada
+    pub fn table_for(&self, kind: ContainerKind, biome: Biome) -> Option<&LootTable> {
+        self.tables
+            .iter()
+            .filter(|table| table.matches(kind, biome))
+            .fold(None, |best: Option<&LootTable>, table| match best {
+                Some(best) if best.specificity() >= table.specificity() => Some(best),
+                _ => Some(table),
+            })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LootTable> {
+        self.tables.iter()
+    }
+
+    /// Tope de unidades del item en el mundo, si tiene
+    pub fn cap(&self, item: &str) -> Option<u32> {
+        self.caps.get(item).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Carga un fichero o todos los `.ron` de una carpeta y los valida con el catálogo
+    pub fn load(path: &Path, defs: &ItemDefs) -> Result<Self, LootTablesError> {
+        let mut loaded = Self::default();
+        let mut origins: HashMap<String, PathBuf> = HashMap::new();
+        for file in item_files(path)? {
+            let source = std::fs::read_to_string(&file).map_err(|source| LootTablesError::Io { path: file.clone(), source })?;
+            let parsed = Self::parse(&source, &file, defs)?;
+            // Tablas y topes comparten espacio de nombres solo para los mensajes de error
+            let keys = parsed.tables.iter().map(|table| table.key.clone()).chain(parsed.caps.keys().map(|item| format!("caps.{}", item)));
+            for key in keys {
+                if let Some(first) = origins.insert(key.clone(), file.clone()) {
+                    return Err(LootTablesError::Duplicate { key, first, second: file.clone() });
+                }
+            }
+            loaded.tables.extend(parsed.tables);
+            loaded.caps.extend(parsed.caps);
+        }
+        if loaded.is_empty() {
+            return Err(LootTablesError::Empty(path.to_path_buf()));
+        }
+        Ok(loaded)
+    }
+
+    /// Lee y valida un fichero; `path` solo se usa en los errores
+    pub fn parse(source: &str, path: &Path, defs: &ItemDefs) -> Result<LootFile, LootTablesError> {
+        let file: LootFile = ron::from_str(source).map_err(|error| LootTablesError::Parse { path: path.to_path_buf(), error })?;
+        for table in &file.tables {
+            table.validate(defs)
+                .map_err(|problem| LootTablesError::Invalid { path: path.to_path_buf(), key: table.key.clone(), problem })?;
+        }
+        if let Some(item) = file.caps.keys().find(|item| defs.get(item).is_none()) {
+            return Err(LootTablesError::Invalid {
+                path: path.to_path_buf(),
+                key: "caps".to_string(),
+                problem: format!("el item '{}' no existe", item),
+            });
+        }
+        Ok(file)
+    }
+}
+
+/// Acción sobre un contenedor del mundo
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum LootAction {
+    /// Mirar qué tiene; responde `LootContents`
+    Open { container: u64 },
+    /// Pasar un item del contenedor al inventario
+    Take { container: u64, item: ItemId },
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LootError {
+    #[error("ese contenedor no existe")]
+    UnknownContainer,
+    #[error("estás demasiado lejos")]
+    OutOfReach,
+    #[error("ese item ya no está")]
+    ItemGone,
+    #[error(transparent)]
+    Inventory(#[from] InventoryError),
+}
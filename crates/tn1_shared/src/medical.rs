@@ -0,0 +1,197 @@
+//! Salud por zonas del cuerpo (ver `docs/SYSTEMS/MEDICAL_SYSTEM.md`).
+//!
+//! Cada jugador lleva un `BodyHealth` con la vida de sus siete zonas. Un
+//! disparo le quita vida a la zona que toca, multiplicada según la zona; lo que
+//! le da a una extremidad ya destruida se reparte entre las zonas que quedan.
+//! Con la cabeza o el tórax a 0 se muere. Una pierna rota frena y no deja
+//! correr, y los brazos heridos hacen temblar el arma. `Health` queda como
+//! resumen (el porcentaje de vida que queda, 0 si está muerto) para las
+//! snapshots, las demos y los cadáveres.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::components::{BodyZone, Health};
+use crate::constants::PLAYER_MAX_HEALTH;
+
+/// Lo que se multiplica la velocidad con una pierna rota
+pub const BROKEN_LEG_SPEED: f32 = 0.3;
+
+/// Lo que se multiplica la velocidad con las dos piernas rotas
+pub const BROKEN_LEGS_SPEED: f32 = 0.15;
+
+/// Grados de dispersión que añade un brazo destruido (proporcional al daño)
+pub const ARM_SWAY_DEG: f32 = 2.5;
+
+impl BodyZone {
+    pub const ALL: [BodyZone; 7] = [
+        BodyZone::Head,
+        BodyZone::Thorax,
+        BodyZone::Stomach,
+        BodyZone::LeftArm,
+        BodyZone::RightArm,
+        BodyZone::LeftLeg,
+        BodyZone::RightLeg,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Vida de la zona sana
+    pub fn max_health(self) -> f32 {
+        match self {
+            BodyZone::Head => 100.0,
+            BodyZone::Thorax => 150.0,
+            BodyZone::Stomach => 120.0,
+            BodyZone::LeftArm | BodyZone::RightArm => 80.0,
+            BodyZone::LeftLeg | BodyZone::RightLeg => 90.0,
+        }
+    }
+
+    /// Lo que se multiplica el daño de una bala que da aquí
+    pub fn damage_multiplier(self) -> f32 {
+        match self {
+            BodyZone::Head => 1.2,
+            BodyZone::Thorax | BodyZone::Stomach => 1.0,
+            BodyZone::LeftArm | BodyZone::RightArm | BodyZone::LeftLeg | BodyZone::RightLeg => 0.7,
+        }
+    }
+
+    /// Cabeza y tórax: a 0 se muere
+    pub fn is_critical(self) -> bool {
+        matches!(self, BodyZone::Head | BodyZone::Thorax)
+    }
+
+    pub fn is_arm(self) -> bool {
+        matches!(self, BodyZone::LeftArm | BodyZone::RightArm)
+    }
+
+    pub fn is_leg(self) -> bool {
+        matches!(self, BodyZone::LeftLeg | BodyZone::RightLeg)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BodyZone::Head => "Cabeza",
+            BodyZone::Thorax => "Tórax",
+            BodyZone::Stomach => "Estómago",
+            BodyZone::LeftArm => "Brazo izq.",
+            BodyZone::RightArm => "Brazo der.",
+            BodyZone::LeftLeg => "Pierna izq.",
+            BodyZone::RightLeg => "Pierna der.",
+        }
+    }
+}
+
+/// Cápsula vertical de una zona, relativa a los pies del jugador mirando hacia
+/// -Z (la izquierda es -X). Con `bottom == top` es una esfera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoneHitbox {
+    pub zone: BodyZone,
+    pub bottom: Vec3,
+    pub top: Vec3,
+    pub radius: f32,
+}
+
+/// Hitboxes de las zonas de un jugador de `PLAYER_HEIGHT`; los ojos quedan en
+/// el centro de la cabeza
+pub const ZONE_HITBOXES: [ZoneHitbox; 7] = [
+    ZoneHitbox { zone: BodyZone::Head, bottom: Vec3::new(0.0, 1.6, 0.0), top: Vec3::new(0.0, 1.6, 0.0), radius: 0.12 },
+    ZoneHitbox { zone: BodyZone::Thorax, bottom: Vec3::new(0.0, 1.2, 0.0), top: Vec3::new(0.0, 1.32, 0.0), radius: 0.18 },
+    ZoneHitbox { zone: BodyZone::Stomach, bottom: Vec3::new(0.0, 0.92, 0.0), top: Vec3::new(0.0, 0.98, 0.0), radius: 0.16 },
+    ZoneHitbox { zone: BodyZone::LeftArm, bottom: Vec3::new(-0.26, 0.85, 0.0), top: Vec3::new(-0.26, 1.35, 0.0), radius: 0.07 },
+    ZoneHitbox { zone: BodyZone::RightArm, bottom: Vec3::new(0.26, 0.85, 0.0), top: Vec3::new(0.26, 1.35, 0.0), radius: 0.07 },
+    ZoneHitbox { zone: BodyZone::LeftLeg, bottom: Vec3::new(-0.1, 0.1, 0.0), top: Vec3::new(-0.1, 0.72, 0.0), radius: 0.1 },
+    ZoneHitbox { zone: BodyZone::RightLeg, bottom: Vec3::new(0.1, 0.1, 0.0), top: Vec3::new(0.1, 0.72, 0.0), radius: 0.1 },
+];
+
+/// Vida de cada zona del cuerpo de un jugador
+#[derive(Component, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BodyHealth {
+    /// Por el orden de `BodyZone::ALL`
+    zones: [f32; 7],
+}
+
+impl Default for BodyHealth {
+    fn default() -> Self {
+        Self { zones: BodyZone::ALL.map(BodyZone::max_health) }
+    }
+}
+
+impl BodyHealth {
+    pub fn get(&self, zone: BodyZone) -> f32 {
+        self.zones[zone.index()]
+    }
+
+    /// Fija la vida de una zona, sin pasarse de su máximo
+    pub fn set(&mut self, zone: BodyZone, health: f32) {
+        self.zones[zone.index()] = health.clamp(0.0, zone.max_health());
+    }
+
+    pub fn is_destroyed(&self, zone: BodyZone) -> bool {
+        self.get(zone) <= 0.0
+    }
+
+    /// Cabeza o tórax destruidos
+    pub fn is_dead(&self) -> bool {
+        BodyZone::ALL.into_iter().any(|zone| zone.is_critical() && self.is_destroyed(zone))
+    }
+
+    /// Quita `amount` a una zona (ya multiplicado por la zona). Si estaba
+    /// destruida se reparte a partes iguales entre las que quedan; lo que pase
+    /// de 0 se pierde. Devuelve lo que se ha quitado en total
+    pub fn damage(&mut self, zone: BodyZone, amount: f32) -> f32 {
+        if amount <= 0.0 || self.is_dead() {
+            return 0.0;
+        }
+        if !self.is_destroyed(zone) {
+            let before = self.get(zone);
+            self.set(zone, before - amount);
+            return before - self.get(zone);
+        }
+        let alive: Vec<BodyZone> = BodyZone::ALL.into_iter().filter(|zone| !self.is_destroyed(*zone)).collect();
+        let share = amount / alive.len() as f32;
+        alive.into_iter().map(|zone| self.damage(zone, share)).sum()
+    }
+
+    pub fn total(&self) -> f32 {
+        self.zones.iter().sum()
+    }
+
+    pub fn max_total() -> f32 {
+        BodyZone::ALL.into_iter().map(BodyZone::max_health).sum()
+    }
+
+    /// Resumen para `Health`: el porcentaje de vida total, 0 si está muerto
+    pub fn health(&self) -> Health {
+        let current = if self.is_dead() { 0.0 } else { PLAYER_MAX_HEALTH * self.total() / Self::max_total() };
+        Health { current, max: PLAYER_MAX_HEALTH }
+    }
+
+    fn broken_legs(&self) -> usize {
+        BodyZone::ALL.into_iter().filter(|zone| zone.is_leg() && self.is_destroyed(*zone)).count()
+    }
+
+    /// Lo que las piernas rotas dejan de la velocidad
+    pub fn speed_multiplier(&self) -> f32 {
+        match self.broken_legs() {
+            0 => 1.0,
+            1 => BROKEN_LEG_SPEED,
+            _ => BROKEN_LEGS_SPEED,
+        }
+    }
+
+    /// Con una pierna rota no se corre
+    pub fn can_sprint(&self) -> bool {
+        self.broken_legs() == 0
+    }
+
+    /// Grados de dispersión que añaden los brazos heridos
+    pub fn sway(&self) -> f32 {
+        BodyZone::ALL
+            .into_iter()
+            .filter(|zone| zone.is_arm())
+            .map(|zone| ARM_SWAY_DEG * (1.0 - self.get(zone) / zone.max_health()))
+            .sum()
+    }
+}
@@ -1,17 +1,40 @@
 use serde::{Deserialize, Serialize};
 use bevy::prelude::*;
+use crate::chunk::{ChunkCoord, ChunkData, ChunkGrid};
+use crate::codec::WireCodec;
 use crate::components::PlayerId;
+use crate::daylight::WorldClock;
+use crate::drops::{PickupAction, WorldItemState};
+use crate::inventory::{Inventory, InventoryAction, InventoryDiff, ItemStack};
+use crate::loot::LootAction;
+use crate::medical::BodyHealth;
+use crate::trade::{TradeAction, TradeView};
+use crate::voice::{VoiceChannel, VoiceCodec};
+use crate::weapons::WeaponStatus;
+use crate::weather::Weather;
 
 pub const DEFAULT_PORT: u16 = 7777;
-pub const PROTOCOL_VERSION: u32 = 2;
+pub const PROTOCOL_VERSION: u32 = 3;
+/// Versión más antigua que el servidor sigue aceptando
+pub const MIN_PROTOCOL_VERSION: u32 = 3;
 pub const TICK_RATE: u32 = 60;
 
 /// Mensajes que el cliente envía al servidor
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ClientMessage {
+    /// Primer mensaje de toda conexión: versión y capacidades del cliente
+    Hello {
+        protocol_version: u32,
+        client_name: String,
+        codecs: Vec<WireCodec>,
+    },
+
+    /// Consulta de estado sin autenticación (server browser).
+    /// El servidor responde con `ServerInfo` y cierra la conexión.
+    StatusQuery,
+
     /// Cliente solicita conectarse (nuevo jugador)
     Register { 
-        protocol_version: u32,
         username: String,
         password: String,
         email: Option<String>,
@@ -19,14 +42,12 @@ pub enum ClientMessage {
     
     /// Cliente solicita conectarse (jugador existente)
     Login { 
-        protocol_version: u32,
         username: String,
         password: String,
     },
     
     /// Cliente se reconecta con token de sesión
     Reconnect {
-        protocol_version: u32,
         session_token: String,
     },
     
@@ -41,11 +62,61 @@ pub enum ClientMessage {
     
     /// Heartbeat/keepalive
     Ping { timestamp: f64 },
+    
+    /// Respuesta al `Ping` del servidor (eco del timestamp del servidor)
+    Pong { timestamp: f64 },
+    
+    /// Mensaje de chat; el servidor lo filtra y decide quién lo recibe
+    Chat { channel: ChatChannel, text: String },
+    
+    /// Entra en un equipo/clan para el chat de equipo (`None` lo abandona)
+    JoinTeam { team: Option<String> },
+    
+    /// Frame de voz (push-to-talk); el servidor lo reenvía sin decodificar
+    Voice {
+        channel: VoiceChannel,
+        codec: VoiceCodec,
+        sequence: u32,
+        data: Vec<u8>,
+    },
+    
+    /// Sintoniza una frecuencia de radio (`None` apaga la radio)
+    TuneRadio { frequency: Option<u16> },
+    
+    /// Pide el contenido de chunks cercanos (tras `WorldInfo` o al moverse)
+    RequestChunks { coords: Vec<ChunkCoord> },
+    
+    /// Acción sobre el propio inventario; `sequence` la identifica en la respuesta
+    Inventory { sequence: u32, action: InventoryAction },
+    
+    /// Acción sobre un contenedor del mundo; comparte las secuencias de `Inventory`
+    Loot { sequence: u32, action: LootAction },
+    
+    /// Recoger algo del suelo o de un cadáver; comparte las secuencias de `Inventory`
+    Pickup { sequence: u32, action: PickupAction },
+    
+    /// Comercio con otro jugador; comparte las secuencias de `Inventory`
+    Trade { sequence: u32, action: TradeAction },
 }
 
 /// Mensajes que el servidor envía al cliente
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ServerMessage {
+    /// Respuesta a `Hello`: codec elegido para el resto de la sesión
+    Welcome {
+        codec: WireCodec,
+        server_info: ServerInfo,
+    },
+
+    /// Respuesta a `StatusQuery`
+    ServerInfo(ServerInfo),
+
+    /// El servidor está lleno: el cliente espera en cola
+    Queued {
+        position: u32,
+        queue_length: u32,
+    },
+
     /// Confirmación de conexión con datos del jugador
     Connected {
         player_id: PlayerId,
@@ -86,8 +157,249 @@ pub enum ServerMessage {
     /// Respuesta a ping
     Pong { timestamp: f64 },
     
+    /// Ping iniciado por el servidor para medir el RTT de cada cliente
+    Ping { timestamp: f64 },
+    
     /// Error o rechazo de conexión
     ConnectionError { reason: String },
+    
+    /// Mensaje de chat ya filtrado. En susurros `channel` lleva el destinatario,
+    /// así que quien lo envió recibe también su copia
+    Chat { channel: ChatChannel, sender: String, text: String },
+    
+    /// Aviso del sistema de chat (silenciado, spam, destinatario desconocido...)
+    ChatNotice { text: String },
+    
+    /// Voz de otro jugador, ya filtrada por distancia o frecuencia
+    Voice(VoicePacket),
+    
+    /// Frecuencia de radio confirmada por el servidor
+    RadioTuned { frequency: Option<u16> },
+    
+    /// Geometría del mundo; llega una vez tras `Connected`
+    WorldInfo(WorldInfo),
+    
+    /// Contenido de un chunk pedido con `RequestChunks`
+    Chunk(ChunkData),
+    
+    /// Hora del mundo; llega al entrar y cada pocos segundos para corregir la deriva
+    WorldTime(WorldClock),
+    
+    /// Clima actual; llega al entrar, al cambiar de frente y cada pocos segundos
+    Weather(Weather),
+    
+    /// Temperatura corporal del jugador (solo a él), cuando cambia una décima
+    BodyTemperature { celsius: f32 },
+    
+    /// Inventario completo del jugador; llega al entrar
+    Inventory(Inventory),
+    
+    /// Cambios en el inventario: respuesta a la acción `sequence` o, sin ella,
+    /// cosas que hace el servidor (recoger loot, regalos de admin...)
+    InventoryUpdate { sequence: Option<u32>, diffs: Vec<InventoryDiff> },
+    
+    /// La acción `sequence` no era válida; el inventario no cambió
+    InventoryRejected { sequence: u32, reason: String },
+    
+    /// Lo que hay en un contenedor: respuesta a `LootAction::Open` (con su
+    /// secuencia) o su estado tras sacar algo
+    LootContents { sequence: Option<u32>, container: u64, items: Vec<ItemStack> },
+    
+    /// Items del suelo y cadáveres cercanos: los que aparecen en el radio o
+    /// cambian (al pararse) y los ids de los que salen de él o desaparecen.
+    /// Lo que lleva un cadáver llega como `LootContents` con su id
+    WorldItems { updated: Vec<WorldItemState>, removed: Vec<u64> },
+    
+    /// Estado de la ventana de comercio, a los dos cada vez que cambia; con la
+    /// secuencia de la acción que lo cambió para quien la hizo
+    TradeWindow { sequence: Option<u32>, view: TradeView },
+    
+    /// Se cerró el comercio: hecho (los inventarios llegan enteros) o no, y por
+    /// qué; con secuencia para quien lo canceló
+    TradeClosed { sequence: Option<u32>, trade: u64, completed: bool, reason: String },
+    
+    /// Arma en la mano del jugador (solo a él) cada vez que cambia; `None` sin arma
+    WeaponState(Option<WeaponStatus>),
+    
+    /// Disparo de otro jugador, para el fogonazo y el sonido
+    ShotFired { shooter: PlayerId, origin: Vec3, direction: Vec3 },
+    
+    /// Un disparo dio a un jugador; a todos
+    Hit { shooter: PlayerId, target: PlayerId, damage: f32, point: Vec3 },
+    
+    /// Un disparo mató a un jugador; a todos. `weapon` es el nombre del arma
+    Kill { killer: PlayerId, killer_name: String, victim: PlayerId, victim_name: String, weapon: String },
+    
+    /// Vida por zonas del jugador; solo a él, cada vez que cambia
+    BodyHealth(BodyHealth),
+}
+
+/// Parámetros del mundo que el cliente necesita para pedir chunks
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorldInfo {
+    pub world_size: f32,
+    pub chunk_size: f32,
+    /// Radio (en chunks) que el servidor mantiene cargado alrededor de cada jugador
+    pub view_radius: u32,
+    pub seed: u64,
+}
+
+impl WorldInfo {
+    pub fn grid(&self) -> ChunkGrid {
+        ChunkGrid::new(self.world_size, self.chunk_size)
+    }
+}
+
+/// Frame de voz reenviado por el servidor
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoicePacket {
+    pub speaker: PlayerId,
+    pub channel: VoiceChannel,
+    pub codec: VoiceCodec,
+    pub sequence: u32,
+    /// Atenuación por distancia ya aplicada por el servidor (0-1)
+    pub gain: f32,
+    /// Desde dónde habla, para el audio 3D (`None` por radio)
+    pub position: Option<Vec3>,
+    pub data: Vec<u8>,
+}
+
+/// Canal de un mensaje de chat
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ChatChannel {
+    /// Todos los jugadores del servidor
+    Global,
+    /// Jugadores dentro del radio de proximidad del que habla
+    Proximity,
+    /// Miembros del mismo equipo/clan
+    Team,
+    /// Mensaje privado a un jugador por nombre
+    Whisper { to: String },
+}
+
+impl ChatChannel {
+    /// Nombre del canal (logs de moderación)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ChatChannel::Global => "global",
+            ChatChannel::Proximity => "proximity",
+            ChatChannel::Team => "team",
+            ChatChannel::Whisper { .. } => "whisper",
+        }
+    }
+}
+
+impl ClientMessage {
+    /// Nombre del tipo de mensaje (etiqueta de métricas y logs)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClientMessage::Hello { .. } => "hello",
+            ClientMessage::StatusQuery => "status_query",
+            ClientMessage::Register { .. } => "register",
+            ClientMessage::Login { .. } => "login",
+            ClientMessage::Reconnect { .. } => "reconnect",
+            ClientMessage::PlayerInput { .. } => "player_input",
+            ClientMessage::Disconnect => "disconnect",
+            ClientMessage::Ping { .. } => "ping",
+            ClientMessage::Pong { .. } => "pong",
+            ClientMessage::Chat { .. } => "chat",
+            ClientMessage::JoinTeam { .. } => "join_team",
+            ClientMessage::Voice { .. } => "voice",
+            ClientMessage::TuneRadio { .. } => "tune_radio",
+            ClientMessage::RequestChunks { .. } => "request_chunks",
+            ClientMessage::Inventory { .. } => "inventory",
+            ClientMessage::Loot { .. } => "loot",
+            ClientMessage::Pickup { .. } => "pickup",
+            ClientMessage::Trade { .. } => "trade",
+        }
+    }
+
+    /// Inputs, pings y voz: el simulador de red puede perderlos (ver [`LinkConditioner`])
+    ///
+    /// [`LinkConditioner`]: crate::conditioner::LinkConditioner
+    pub fn is_droppable(&self) -> bool {
+        matches!(
+            self,
+            ClientMessage::PlayerInput { .. }
+                | ClientMessage::Ping { .. }
+                | ClientMessage::Pong { .. }
+                | ClientMessage::Voice { .. }
+        )
+    }
+}
+
+impl ServerMessage {
+    /// Nombre del tipo de mensaje (etiqueta de métricas y logs)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ServerMessage::Welcome { .. } => "welcome",
+            ServerMessage::ServerInfo(_) => "server_info",
+            ServerMessage::Queued { .. } => "queued",
+            ServerMessage::Connected { .. } => "connected",
+            ServerMessage::Registered { .. } => "registered",
+            ServerMessage::AuthError { .. } => "auth_error",
+            ServerMessage::WorldState { .. } => "world_state",
+            ServerMessage::PlayerJoined { .. } => "player_joined",
+            ServerMessage::PlayerLeft { .. } => "player_left",
+            ServerMessage::Pong { .. } => "pong",
+            ServerMessage::Ping { .. } => "ping",
+            ServerMessage::ConnectionError { .. } => "connection_error",
+            ServerMessage::Chat { .. } => "chat",
+            ServerMessage::ChatNotice { .. } => "chat_notice",
+            ServerMessage::Voice(_) => "voice",
+            ServerMessage::RadioTuned { .. } => "radio_tuned",
+            ServerMessage::WorldInfo(_) => "world_info",
+            ServerMessage::Chunk(_) => "chunk",
+            ServerMessage::WorldTime(_) => "world_time",
+            ServerMessage::Weather(_) => "weather",
+            ServerMessage::BodyTemperature { .. } => "body_temperature",
+            ServerMessage::Inventory(_) => "inventory",
+            ServerMessage::InventoryUpdate { .. } => "inventory_update",
+            ServerMessage::InventoryRejected { .. } => "inventory_rejected",
+            ServerMessage::LootContents { .. } => "loot_contents",
+            ServerMessage::WorldItems { .. } => "world_items",
+            ServerMessage::TradeWindow { .. } => "trade_window",
+            ServerMessage::TradeClosed { .. } => "trade_closed",
+            ServerMessage::WeaponState(_) => "weapon_state",
+            ServerMessage::ShotFired { .. } => "shot_fired",
+            ServerMessage::Hit { .. } => "hit",
+            ServerMessage::Kill { .. } => "kill",
+            ServerMessage::BodyHealth(_) => "body_health",
+        }
+    }
+
+    /// Snapshots del mundo, pings y voz: el simulador de red puede perderlos (ver [`LinkConditioner`])
+    ///
+    /// [`LinkConditioner`]: crate::conditioner::LinkConditioner
+    pub fn is_droppable(&self) -> bool {
+        matches!(
+            self,
+            ServerMessage::WorldState { .. }
+                | ServerMessage::Ping { .. }
+                | ServerMessage::Pong { .. }
+                | ServerMessage::Voice(_)
+        )
+    }
+}
+
+/// Información pública del servidor (handshake y server browser)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServerInfo {
+    pub server_name: String,
+    pub map_name: String,
+    pub player_count: u32,
+    pub max_players: u32,
+    pub queue_length: u32,
+    pub tick_rate: u32,
+    pub protocol_min: u32,
+    pub protocol_max: u32,
+    pub codecs: Vec<WireCodec>,
+}
+
+impl ServerInfo {
+    pub fn accepts_protocol(&self, version: u32) -> bool {
+        (self.protocol_min..=self.protocol_max).contains(&version)
+    }
 }
 
 /// Estado completo de un jugador
@@ -100,6 +412,8 @@ pub struct PlayerState {
     pub health: f32,
     pub is_grounded: bool,
     pub last_input_sequence: u32,
+    /// Linterna encendida (la ven todos)
+    pub flashlight: bool,
 }
 
 /// Input del jugador
@@ -113,4 +427,13 @@ pub struct PlayerInput {
     pub sprint: bool,
     pub camera_yaw: f32,
     pub camera_pitch: f32,
+    pub flashlight: bool,
+    /// Gatillo apretado
+    pub fire: bool,
+    /// Apuntando con la mira (menos dispersión)
+    pub aim: bool,
+    /// Cambiar el cargador (cuenta al pulsarlo)
+    pub reload: bool,
+    /// Siguiente modo del selector de fuego (cuenta al pulsarlo)
+    pub switch_fire_mode: bool,
 }
\ No newline at end of file
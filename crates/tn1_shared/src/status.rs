@@ -0,0 +1,52 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::codec::{FrameBuffer, WireCodec};
+use crate::protocol::{ClientMessage, ServerInfo, ServerMessage};
+use crate::transport::SecureStream;
+
+/// Consulta el estado de un servidor sin autenticarse (server browser).
+///
+/// Abre una conexión, envía `StatusQuery` y espera el `ServerInfo` de respuesta.
+/// `tls` debe ser `None` solo si el servidor corre con `TN1_TLS=off`.
+pub fn query_server_status(
+    address: SocketAddr,
+    timeout: Duration,
+    tls: Option<Arc<rustls::ClientConfig>>,
+) -> std::io::Result<ServerInfo> {
+    let socket = TcpStream::connect_timeout(&address, timeout)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.set_write_timeout(Some(timeout))?;
+    let mut stream = match tls {
+        Some(config) => SecureStream::connect(socket, config)?,
+        None => SecureStream::plain(socket),
+    };
+
+    let query = WireCodec::Json.encode_frame(&ClientMessage::StatusQuery).map_err(invalid_data)?;
+    stream.write_all(&query)?;
+
+    let mut buffer = [0u8; 4096];
+    let mut frames = FrameBuffer::new();
+    loop {
+        let n = stream.read(&mut buffer)?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        frames.extend(&buffer[..n]);
+
+        if let Some(payload) = frames.next_frame().map_err(invalid_data)? {
+            return match WireCodec::Json.decode::<ServerMessage>(&payload).map_err(invalid_data)? {
+                ServerMessage::ServerInfo(info) => Ok(info),
+                ServerMessage::ConnectionError { reason } => {
+                    Err(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, reason))
+                }
+                other => Err(invalid_data(format!("respuesta inesperada: {:?}", other))),
+            };
+        }
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,226 @@
+//! Terreno procedural: alturas, biomas y ríos a partir de la semilla del mundo.
+//!
+//! Servidor y cliente generan exactamente el mismo terreno: el ruido solo usa
+//! sumas, productos y `floor` en `f32` (nada de `sin`/`exp`, que cambian entre
+//! plataformas), así que el resultado es idéntico bit a bit en cualquier máquina.
+//! Por eso las alturas no viajan por la red: basta con la semilla de `WorldInfo`.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::chunk::{ChunkCoord, ChunkGrid};
+
+/// Vértices por lado de cada nivel de detalle; el LOD 0 es también el collider
+/// del servidor, así que de cerca se ve exactamente el suelo que se pisa
+pub const LOD_RESOLUTIONS: [u32; 3] = [33, 17, 9];
+pub const COLLIDER_RESOLUTION: u32 = LOD_RESOLUTIONS[0];
+
+/// Altura del lecho de los ríos (metros)
+const RIVER_BED: f32 = 6.0;
+/// Anchura relativa de los ríos en el campo de ruido
+const RIVER_WIDTH: f32 = 0.04;
+
+/// Nivel de detalle según la distancia en chunks al jugador
+pub fn lod_for_distance(distance: u32) -> usize {
+    match distance {
+        0 | 1 => 0,
+        2 => 1,
+        _ => 2,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Plains,
+    Forest,
+    Desert,
+    Swamp,
+    Mountains,
+    Snow,
+    River,
+}
+
+impl Biome {
+    /// Color del terreno (se pinta por vértice)
+    pub fn color(&self) -> [f32; 3] {
+        match self {
+            Biome::Plains => [0.42, 0.58, 0.3],
+            Biome::Forest => [0.2, 0.42, 0.18],
+            Biome::Desert => [0.8, 0.72, 0.48],
+            Biome::Swamp => [0.3, 0.36, 0.22],
+            Biome::Mountains => [0.48, 0.45, 0.42],
+            Biome::Snow => [0.92, 0.93, 0.95],
+            Biome::River => [0.25, 0.38, 0.55],
+        }
+    }
+}
+
+/// Todo lo que el generador sabe de un punto del mapa
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainSample {
+    pub height: f32,
+    pub biome: Biome,
+    /// 1 en el centro de un río, 0 fuera
+    pub river: f32,
+}
+
+/// Generador determinista del terreno de un mundo
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct TerrainGenerator {
+    pub seed: u64,
+}
+
+impl TerrainGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Altura y bioma en coordenadas del mundo
+    pub fn sample(&self, x: f32, z: f32) -> TerrainSample {
+        let continent = fbm(self.seed ^ 0x01, x / 1200.0, z / 1200.0, 4);
+        let detail = fbm(self.seed ^ 0x02, x / 180.0, z / 180.0, 4);
+        let ridges = 1.0 - fbm(self.seed ^ 0x03, x / 600.0, z / 600.0, 4).abs();
+        let mountains = smoothstep(0.15, 0.55, continent);
+        let mut height = 20.0 + continent * 25.0 + detail * 4.0 + mountains * ridges * ridges * 120.0;
+
+        // Los ríos siguen las líneas donde un campo de ruido pasa por cero
+        let river = 1.0 - smoothstep(0.0, RIVER_WIDTH, fbm(self.seed ^ 0x04, x / 900.0, z / 900.0, 3).abs());
+        height += (height.min(RIVER_BED) - height) * river;
+
+        let temperature = fbm(self.seed ^ 0x05, x / 1500.0, z / 1500.0, 3) - height / 400.0;
+        let moisture = fbm(self.seed ^ 0x06, x / 1100.0, z / 1100.0, 3) + river * 0.5;
+        let biome = if river > 0.5 {
+            Biome::River
+        } else if height > 110.0 || temperature < -0.45 {
+            Biome::Snow
+        } else if height > 70.0 {
+            Biome::Mountains
+        } else if moisture > 0.35 && height < 25.0 {
+            Biome::Swamp
+        } else if temperature > 0.25 && moisture < -0.15 {
+            Biome::Desert
+        } else if moisture > 0.0 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        };
+
+        TerrainSample { height, biome, river }
+    }
+
+    pub fn height(&self, x: f32, z: f32) -> f32 {
+        self.sample(x, z).height
+    }
+
+    /// Alturas de un chunk en una rejilla de `resolution`² vértices
+    pub fn heightfield(&self, grid: &ChunkGrid, coord: ChunkCoord, resolution: u32) -> Heightfield {
+        let resolution = resolution.max(2);
+        let origin = grid.origin(coord);
+        let step = grid.chunk_size / (resolution - 1) as f32;
+        let heights = (0..resolution)
+            .flat_map(|row| (0..resolution).map(move |column| (column, row)))
+            .map(|(column, row)| self.height(origin.x + column as f32 * step, origin.z + row as f32 * step))
+            .collect();
+        Heightfield { resolution, size: grid.chunk_size, heights }
+    }
+
+    /// Normal del terreno por diferencias centrales a `step` metros; al mirar
+    /// fuera del chunk, los bordes de chunks vecinos se iluminan igual
+    pub fn normal(&self, x: f32, z: f32, step: f32) -> Vec3 {
+        let dx = self.height(x + step, z) - self.height(x - step, z);
+        let dz = self.height(x, z + step) - self.height(x, z - step);
+        Vec3::new(-dx, 2.0 * step, -dz).normalize()
+    }
+}
+
+/// Alturas de un chunk en una rejilla regular, en coordenadas locales del chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightfield {
+    /// Vértices por lado (los bordes coinciden con los de los chunks vecinos)
+    pub resolution: u32,
+    /// Lado del chunk en metros
+    pub size: f32,
+    /// Fila a fila, de -Z a +Z y de -X a +X
+    pub heights: Vec<f32>,
+}
+
+impl Heightfield {
+    pub fn step(&self) -> f32 {
+        self.size / (self.resolution - 1) as f32
+    }
+
+    pub fn vertex(&self, column: u32, row: u32) -> f32 {
+        let last = self.resolution - 1;
+        self.heights[(row.min(last) * self.resolution + column.min(last)) as usize]
+    }
+
+    /// Altura del suelo en `(x, z)` locales, interpolada sobre los mismos
+    /// triángulos que dibuja el cliente
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let last = (self.resolution - 1) as f32;
+        let u = (x / self.step()).clamp(0.0, last);
+        let v = (z / self.step()).clamp(0.0, last);
+        let column = (u.floor() as u32).min(self.resolution - 2);
+        let row = (v.floor() as u32).min(self.resolution - 2);
+        let (u, v) = (u - column as f32, v - row as f32);
+
+        let h00 = self.vertex(column, row);
+        let h10 = self.vertex(column + 1, row);
+        let h01 = self.vertex(column, row + 1);
+        let h11 = self.vertex(column + 1, row + 1);
+        // Cada celda se parte por la diagonal (1, 0)-(0, 1)
+        if u + v <= 1.0 {
+            h00 + (h10 - h00) * u + (h01 - h00) * v
+        } else {
+            h11 + (h01 - h11) * (1.0 - u) + (h10 - h11) * (1.0 - v)
+        }
+    }
+
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Ruido fractal: `octaves` capas de ruido de gradiente, cada una al doble de frecuencia
+fn fbm(seed: u64, x: f32, z: f32, octaves: u32) -> f32 {
+    let (mut sum, mut amplitude, mut frequency, mut total) = (0.0, 1.0, 1.0, 0.0);
+    for octave in 0..octaves {
+        sum += gradient_noise(seed.wrapping_add(octave as u64), x * frequency, z * frequency) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total
+}
+
+/// Ruido de gradiente 2D en `[-1, 1]` con 8 gradientes fijos
+fn gradient_noise(seed: u64, x: f32, z: f32) -> f32 {
+    const DIAGONAL: f32 = std::f32::consts::FRAC_1_SQRT_2;
+    const GRADIENTS: [(f32, f32); 8] = [
+        (1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0),
+        (DIAGONAL, DIAGONAL), (-DIAGONAL, DIAGONAL), (DIAGONAL, -DIAGONAL), (-DIAGONAL, -DIAGONAL),
+    ];
+
+    let (x0, z0) = (x.floor(), z.floor());
+    let (fx, fz) = (x - x0, z - z0);
+    let (cx, cz) = (x0 as i32, z0 as i32);
+    let corner = |dx: i32, dz: i32| {
+        let (gx, gz) = GRADIENTS[(hash(seed, cx + dx, cz + dz) & 7) as usize];
+        gx * (fx - dx as f32) + gz * (fz - dz as f32)
+    };
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let (u, v) = (fade(fx), fade(fz));
+
+    let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * u;
+    let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * u;
+    // El máximo teórico es √2/2: se reescala a [-1, 1]
+    (top + (bottom - top) * v) * std::f32::consts::SQRT_2
+}
+
+fn hash(seed: u64, x: i32, z: i32) -> u64 {
+    let mut h = seed ^ (x as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (z as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
@@ -0,0 +1,93 @@
+//! Comercio entre jugadores: una ventana con dos ofertas que media el servidor.
+//!
+//! Uno lo pide (`Request`) y el otro lo acepta; cada uno pone y quita items de
+//! su inventario en su lado. Cualquier cambio en una oferta quita las dos
+//! confirmaciones. Con las dos puestas la ventana se bloquea y empieza una
+//! cuenta atrás: ya no se puede tocar nada, solo retirar la confirmación o
+//! cancelar, y al acabar el servidor cambia todos los items de una vez o ninguno.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::components::PlayerId;
+use crate::inventory::{InventoryError, ItemId, ItemStack};
+
+/// Acción sobre un comercio; comparte las secuencias de `Inventory`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TradeAction {
+    /// Proponer un comercio a otro jugador cercano
+    Request { player: PlayerId },
+    /// Aceptar el comercio que te han propuesto
+    Accept { trade: u64 },
+    /// Poner un item del inventario (con lo que lleve dentro) en tu lado
+    Offer { item: ItemId },
+    /// Quitarlo de tu lado
+    Withdraw { item: ItemId },
+    Confirm,
+    Unconfirm,
+    /// Rechazar una propuesta o cerrar la ventana
+    Cancel,
+}
+
+/// Un lado de la ventana
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TradeOffer {
+    pub items: Vec<ItemStack>,
+    pub confirmed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TradePhase {
+    /// Propuesto, esperando a que el otro acepte
+    Pending,
+    Open,
+    /// Los dos han confirmado; el cambio se hace en `remaining` segundos
+    Locked { remaining: f32 },
+}
+
+/// La ventana de comercio tal como la ve uno de los dos
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TradeView {
+    pub id: u64,
+    pub partner: PlayerId,
+    pub partner_name: String,
+    /// Lo propuso este jugador (si está `Pending`, el otro tiene que aceptar)
+    pub requested: bool,
+    pub phase: TradePhase,
+    pub mine: TradeOffer,
+    pub theirs: TradeOffer,
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum TradeError {
+    #[error("no estás comerciando")]
+    NoTrade,
+    #[error("ese jugador no está")]
+    UnknownPlayer,
+    #[error("no puedes comerciar contigo mismo")]
+    WithYourself,
+    #[error("ya hay un comercio abierto")]
+    Busy,
+    #[error("estáis demasiado lejos para comerciar")]
+    TooFar,
+    #[error("nadie te ha propuesto ese comercio")]
+    NotInvited,
+    #[error("el otro aún no ha aceptado")]
+    NotAccepted,
+    #[error("la oferta está bloqueada: retira la confirmación para cambiarla")]
+    Locked,
+    #[error("ese item ya está en la oferta")]
+    AlreadyOffered,
+    #[error("ese item no está en la oferta")]
+    NotOffered,
+    #[error("no hay nada que cambiar")]
+    Empty,
+    #[error(transparent)]
+    Inventory(#[from] InventoryError),
+}
+
+/// Si `item` es este stack o va dentro de él (rejilla o accesorio)
+pub fn stack_contains(stack: &ItemStack, item: ItemId) -> bool {
+    stack.id == item
+        || stack.attachments.iter().any(|attachment| stack_contains(attachment, item))
+        || stack.contents.iter().flat_map(|contents| &contents.items).any(|inner| stack_contains(&inner.stack, item))
+}
@@ -0,0 +1,302 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+
+/// Nombre de servidor usado en el handshake TLS (la identidad real la da el pin)
+pub const TLS_SERVER_NAME: &str = "tn1-server";
+
+/// Socket TCP con TLS opcional que puede clonarse para leer y escribir desde threads distintos.
+///
+/// Nadie bloquea en el socket reteniendo el lock de la sesión TLS: el thread lector
+/// espera datos sin él y quien escribe copia los registros cifrados a `outgoing`
+/// antes de mandarlos. Si no, dos extremos con el buffer de envío lleno se
+/// quedarían esperando el uno al otro (su lector necesita ese lock para avanzar).
+pub struct SecureStream {
+    socket: TcpStream,
+    session: Option<Arc<Mutex<rustls::Connection>>>,
+    /// Registros cifrados que el socket aún no ha admitido; su lock ordena a los escritores
+    outgoing: Arc<Mutex<Vec<u8>>>,
+}
+
+impl SecureStream {
+    /// Conexión sin cifrar (solo desarrollo con `TN1_TLS=off`)
+    pub fn plain(socket: TcpStream) -> Self {
+        Self { socket, session: None, outgoing: Arc::default() }
+    }
+
+    /// Lado servidor: completa el handshake TLS sobre un socket ya aceptado
+    pub fn accept(socket: TcpStream, config: Arc<rustls::ServerConfig>) -> std::io::Result<Self> {
+        let session = rustls::ServerConnection::new(config).map_err(tls_error)?;
+        Self::handshake(socket, session.into())
+    }
+
+    /// Lado cliente: completa el handshake TLS contra el servidor
+    pub fn connect(socket: TcpStream, config: Arc<rustls::ClientConfig>) -> std::io::Result<Self> {
+        let server_name = ServerName::try_from(TLS_SERVER_NAME).map_err(tls_error)?;
+        let session = rustls::ClientConnection::new(config, server_name).map_err(tls_error)?;
+        Self::handshake(socket, session.into())
+    }
+
+    fn handshake(mut socket: TcpStream, mut session: rustls::Connection) -> std::io::Result<Self> {
+        while session.is_handshaking() {
+            session.complete_io(&mut socket)?;
+        }
+        Ok(Self {
+            socket,
+            session: Some(Arc::new(Mutex::new(session))),
+            outgoing: Arc::default(),
+        })
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.session.is_some()
+    }
+
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(Self {
+            socket: self.socket.try_clone()?,
+            session: self.session.clone(),
+            outgoing: self.outgoing.clone(),
+        })
+    }
+
+    pub fn socket(&self) -> &TcpStream {
+        &self.socket
+    }
+
+    pub fn set_nodelay(&self, nodelay: bool) -> std::io::Result<()> {
+        self.socket.set_nodelay(nodelay)
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    pub fn shutdown(&mut self, how: Shutdown) -> std::io::Result<()> {
+        if let Some(session) = &self.session {
+            session.lock().unwrap().send_close_notify();
+            let _ = self.send_pending();
+        }
+        self.socket.shutdown(how)
+    }
+
+    /// Manda al socket lo que tenga pendiente la sesión, esperando si el socket es bloqueante
+    fn send_pending(&self) -> std::io::Result<()> {
+        let Some(session) = &self.session else { return Ok(()) };
+        write_records(&self.socket, session, &mut self.outgoing.lock().unwrap())
+    }
+}
+
+/// Vacía `outgoing` en el socket y repite con lo nuevo que tenga la sesión.
+/// El lock de la sesión solo se toma para copiar, nunca mientras se escribe
+fn write_records(mut socket: &TcpStream, session: &Mutex<rustls::Connection>, outgoing: &mut Vec<u8>) -> std::io::Result<()> {
+    loop {
+        while !outgoing.is_empty() {
+            match socket.write(outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    outgoing.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut session = session.lock().unwrap();
+        if !session.wants_write() {
+            return Ok(());
+        }
+        while session.wants_write() {
+            session.write_tls(outgoing)?;
+        }
+    }
+}
+
+impl Read for SecureStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some(session) = &self.session else {
+            return self.socket.read(buf);
+        };
+
+        let mut raw = [0u8; 4096];
+        loop {
+            {
+                let mut session = session.lock().unwrap();
+                match session.reader().read(buf) {
+                    Ok(n) => return Ok(n),
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e),
+                }
+            }
+
+            // Leer del socket sin retener el lock
+            let n = self.socket.read(&mut raw)?;
+            if n == 0 {
+                return Ok(0);
+            }
+
+            {
+                let mut session = session.lock().unwrap();
+                // `read_tls` solo acepta lo que cabe en su buffer (hasta un registro TLS):
+                // lo que sobra se le da en la siguiente vuelta en vez de perderlo
+                let mut pending = &raw[..n];
+                while !pending.is_empty() {
+                    if session.read_tls(&mut pending)? == 0 {
+                        break;
+                    }
+                    session.process_new_packets().map_err(tls_error)?;
+                }
+            }
+
+            // Alertas o key updates pendientes. Si hay alguien escribiendo no se le
+            // espera (puede estar bloqueado en el socket): los manda él al acabar
+            if let Ok(mut outgoing) = self.outgoing.try_lock() {
+                match write_records(&self.socket, session, &mut outgoing) {
+                    Err(e) if e.kind() != ErrorKind::WouldBlock => return Err(e),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl Write for SecureStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let Some(session) = &self.session else {
+            return self.socket.write(buf);
+        };
+
+        let n = session.lock().unwrap().writer().write(buf)?;
+        // Lo escrito ya está en la sesión: si el socket está lleno lo vacían el
+        // `flush` o la siguiente escritura. Devolver `WouldBlock` haría que quien
+        // reintenta (p. ej. `write_all`) cifrara los mismos bytes dos veces
+        match self.send_pending() {
+            Err(e) if e.kind() != ErrorKind::WouldBlock => Err(e),
+            _ => Ok(n),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.send_pending()?;
+        self.socket.flush()
+    }
+}
+
+/// Huella SHA-256 (hex en minúsculas) de un certificado DER, usada para el pinning
+pub fn certificate_fingerprint(certificate: &[u8]) -> String {
+    Sha256::digest(certificate)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Configuración TLS de cliente que solo acepta certificados cuya huella esté en `pins`.
+///
+/// No se valida la cadena de confianza: el servidor usa certificados autofirmados
+/// y el pin es la única identidad que importa.
+pub fn pinned_client_config(pins: Vec<String>) -> Arc<rustls::ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = PinnedCertVerifier {
+        pins: pins.into_iter().map(|pin| normalize_fingerprint(&pin)).collect(),
+        provider: provider.clone(),
+    };
+
+    let config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("versiones TLS por defecto soportadas por ring")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+
+    Arc::new(config)
+}
+
+/// `TN1_TLS=off` (o `0`/`false`) desactiva TLS; cualquier otro valor lo deja activo
+pub fn tls_enabled_from_env() -> bool {
+    !matches!(std::env::var("TN1_TLS").as_deref(), Ok("off") | Ok("0") | Ok("false"))
+}
+
+/// Pins configurados en `TN1_TLS_PIN` (separados por comas) o, si no hay ninguno,
+/// leídos de `TN1_TLS_PIN_FILE` (por defecto la huella que deja el servidor de desarrollo)
+pub fn pins_from_env() -> Vec<String> {
+    let mut pins: Vec<String> = std::env::var("TN1_TLS_PIN")
+        .map(|pins| pins.split(',').map(|pin| pin.trim().to_string()).filter(|pin| !pin.is_empty()).collect())
+        .unwrap_or_default();
+
+    if pins.is_empty() {
+        let pin_file = std::env::var("TN1_TLS_PIN_FILE")
+            .unwrap_or_else(|_| "certs/server_cert.sha256".to_string());
+        if let Ok(contents) = std::fs::read_to_string(&pin_file) {
+            pins.extend(contents.lines().map(str::trim).filter(|pin| !pin.is_empty()).map(String::from));
+        }
+    }
+
+    pins
+}
+
+/// Acepta huellas con o sin separadores `:` y en cualquier capitalización
+pub fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    pins: Vec<String>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let fingerprint = certificate_fingerprint(end_entity.as_ref());
+        if self.pins.contains(&fingerprint) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(format!(
+                "certificado del servidor no coincide con ningún pin (huella {})",
+                fingerprint
+            )))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn tls_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,240 @@
+//! Voz: formato de los paquetes, atenuación por distancia y fuentes de audio.
+//!
+//! El audio viaja en frames mono de 20 ms a 48 kHz. El servidor no decodifica
+//! nada: solo decide quién oye cada paquete y con qué ganancia.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+pub const SAMPLE_RATE: u32 = 48_000;
+/// Muestras por frame (20 ms a 48 kHz, mono)
+pub const FRAME_SAMPLES: usize = 960;
+pub const FRAME_SECONDS: f32 = FRAME_SAMPLES as f32 / SAMPLE_RATE as f32;
+/// Tamaño máximo de un paquete de voz (un frame PCM sin comprimir cabe)
+pub const MAX_VOICE_PACKET: usize = FRAME_SAMPLES * 2 + 64;
+/// Hasta esta distancia se oye a volumen completo
+pub const FULL_VOLUME_DISTANCE: f32 = 2.0;
+/// Frecuencias de radio válidas
+pub const RADIO_FREQUENCIES: std::ops::RangeInclusive<u16> = 1..=999;
+
+/// Por dónde se transmite la voz
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceChannel {
+    /// Se oye alrededor del que habla, atenuada con la distancia
+    Proximity,
+    /// Todos los sintonizados en la misma frecuencia, sin atenuación
+    Radio { frequency: u16 },
+}
+
+/// Formato del audio dentro del paquete
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceCodec {
+    Opus,
+    /// PCM 16 bits little-endian (clientes sin libopus, tests)
+    Pcm16,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VoiceError {
+    #[error("codec de voz: {0}")]
+    Codec(String),
+    #[error("dispositivo de audio: {0}")]
+    Device(String),
+    #[error("WAV inválido: {0}")]
+    Wav(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Ganancia según la distancia: 1 hasta `FULL_VOLUME_DISTANCE`, luego cae
+/// cuadráticamente hasta 0 en `range`
+pub fn attenuation(distance: f32, range: f32) -> f32 {
+    if distance >= range {
+        return 0.0;
+    }
+    if distance <= FULL_VOLUME_DISTANCE {
+        return 1.0;
+    }
+    let t = (distance - FULL_VOLUME_DISTANCE) / (range - FULL_VOLUME_DISTANCE);
+    (1.0 - t).powi(2)
+}
+
+pub trait VoiceEncoder: Send + Sync {
+    fn codec(&self) -> VoiceCodec;
+    /// Codifica un frame de `FRAME_SAMPLES` muestras
+    fn encode(&mut self, frame: &[i16]) -> Result<Vec<u8>, VoiceError>;
+}
+
+pub trait VoiceDecoder: Send + Sync {
+    /// `None` si el paquete se perdió: el decoder rellena el hueco
+    fn decode(&mut self, packet: Option<&[u8]>) -> Result<Vec<i16>, VoiceError>;
+}
+
+/// Sin compresión: ~770 kbit/s, solo para pruebas y clientes sin Opus
+#[derive(Default)]
+pub struct Pcm16Codec;
+
+impl VoiceEncoder for Pcm16Codec {
+    fn codec(&self) -> VoiceCodec {
+        VoiceCodec::Pcm16
+    }
+
+    fn encode(&mut self, frame: &[i16]) -> Result<Vec<u8>, VoiceError> {
+        Ok(frame.iter().flat_map(|sample| sample.to_le_bytes()).collect())
+    }
+}
+
+impl VoiceDecoder for Pcm16Codec {
+    fn decode(&mut self, packet: Option<&[u8]>) -> Result<Vec<i16>, VoiceError> {
+        let Some(packet) = packet else {
+            // Hueco: silencio
+            return Ok(vec![0; FRAME_SAMPLES]);
+        };
+        if packet.len() % 2 != 0 {
+            return Err(VoiceError::Codec(format!("PCM de longitud impar ({} bytes)", packet.len())));
+        }
+        Ok(packet.chunks_exact(2).map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]])).collect())
+    }
+}
+
+/// Origen del audio que se transmite (micrófono, archivo...)
+pub trait VoiceInput: Send + Sync {
+    /// Siguiente frame de `FRAME_SAMPLES` muestras; `None` si aún no hay audio
+    fn read_frame(&mut self) -> Option<Vec<i16>>;
+}
+
+/// Audio de un archivo WAV, para tests y bots sin micrófono
+pub struct WavInput {
+    samples: Vec<i16>,
+    cursor: usize,
+    looping: bool,
+}
+
+impl WavInput {
+    /// Carga un WAV PCM de 16 bits; se mezcla a mono y se remuestrea a 48 kHz
+    pub fn open(path: impl AsRef<Path>, looping: bool) -> Result<Self, VoiceError> {
+        let samples = read_wav(path)?;
+        Ok(Self::from_samples(samples, looping))
+    }
+
+    pub fn from_samples(samples: Vec<i16>, looping: bool) -> Self {
+        Self { samples, cursor: 0, looping }
+    }
+}
+
+impl VoiceInput for WavInput {
+    fn read_frame(&mut self) -> Option<Vec<i16>> {
+        if self.samples.is_empty() || (self.cursor >= self.samples.len() && !self.looping) {
+            return None;
+        }
+
+        let mut frame = Vec::with_capacity(FRAME_SAMPLES);
+        while frame.len() < FRAME_SAMPLES {
+            if self.cursor >= self.samples.len() {
+                if !self.looping {
+                    // Último frame incompleto: se rellena con silencio
+                    frame.resize(FRAME_SAMPLES, 0);
+                    break;
+                }
+                self.cursor = 0;
+            }
+            let take = (FRAME_SAMPLES - frame.len()).min(self.samples.len() - self.cursor);
+            frame.extend_from_slice(&self.samples[self.cursor..self.cursor + take]);
+            self.cursor += take;
+        }
+        Some(frame)
+    }
+}
+
+/// Lee un WAV PCM de 16 bits y lo deja en mono a `SAMPLE_RATE`
+pub fn read_wav(path: impl AsRef<Path>) -> Result<Vec<i16>, VoiceError> {
+    let bytes = std::fs::read(path)?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(VoiceError::Wav("falta la cabecera RIFF/WAVE".to_string()));
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let body = &bytes[offset + 8..(offset + 8 + size).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let audio_format = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let sample_rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                if audio_format != 1 || bits != 16 || channels == 0 {
+                    return Err(VoiceError::Wav(format!(
+                        "solo PCM de 16 bits (formato {}, {} bits, {} canales)",
+                        audio_format, bits, channels
+                    )));
+                }
+                // `resample` divide por ella
+                if sample_rate == 0 {
+                    return Err(VoiceError::Wav("frecuencia de muestreo 0".to_string()));
+                }
+                format = Some((channels as usize, sample_rate));
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        // Los chunks van alineados a 2 bytes
+        offset += 8 + size + size % 2;
+    }
+
+    let (Some((channels, sample_rate)), Some(data)) = (format, data) else {
+        return Err(VoiceError::Wav("faltan los chunks fmt/data".to_string()));
+    };
+
+    let mono: Vec<i16> = data
+        .chunks_exact(2 * channels)
+        .map(|frame| {
+            let sum: i32 = frame.chunks_exact(2).map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as i32).sum();
+            (sum / channels as i32) as i16
+        })
+        .collect();
+    Ok(resample(&mono, sample_rate, SAMPLE_RATE))
+}
+
+/// Escribe un WAV mono PCM de 16 bits
+pub fn write_wav(path: impl AsRef<Path>, samples: &[i16], sample_rate: u32) -> Result<(), VoiceError> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    bytes.extend(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Remuestreo lineal; suficiente para voz
+pub fn resample(samples: &[i16], from: u32, to: u32) -> Vec<i16> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let len = (samples.len() as u64 * to as u64 / from as u64) as usize;
+    let step = from as f64 / to as f64;
+    (0..len)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position as usize;
+            let next = samples[(index + 1).min(samples.len() - 1)] as f64;
+            let current = samples[index.min(samples.len() - 1)] as f64;
+            (current + (next - current) * position.fract()) as i16
+        })
+        .collect()
+}
@@ -0,0 +1,59 @@
+//! Armas de fuego: el gatillo, apuntar, recargar y el selector de fuego van
+//! en `PlayerInput`; el servidor lleva el estado del arma en la mano y se lo
+//! manda a su dueño (`WeaponStatus`) cada vez que cambia.
+//!
+//! Las balas cargadas se guardan en el propio item (`ItemStack::ammo`),
+//! contando la de la recámara: cambiar el cargador con una bala en la recámara
+//! deja `magazine + 1`; con el arma vacía deja `magazine` y hay que montarla.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::constants::CAMERA_HEIGHT_OFFSET;
+use crate::inventory::ItemId;
+use crate::items::FireMode;
+
+/// Qué está haciendo el arma
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum WeaponPhase {
+    #[default]
+    Ready,
+    /// Cambiando el cargador
+    Reloading { remaining: f32 },
+    /// Montando el arma tras meter un cargador con la recámara vacía
+    Chambering { remaining: f32 },
+}
+
+/// Arma en la mano de un jugador, tal como la ve él
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WeaponStatus {
+    pub item: ItemId,
+    /// Clave de su `ItemDef`
+    pub key: String,
+    pub mode: FireMode,
+    /// Disparos por minuto, para que el cliente prediga la cadencia
+    pub fire_rate: f32,
+    pub phase: WeaponPhase,
+    /// Balas en el arma, la de la recámara incluida
+    pub ammo: u32,
+    pub chambered: bool,
+    /// Munición de su tipo que queda en el inventario
+    pub reserve: u32,
+    pub aiming: bool,
+}
+
+impl WeaponStatus {
+    /// Si ahora mismo saldría un disparo al apretar el gatillo
+    pub fn can_fire(&self) -> bool {
+        self.phase == WeaponPhase::Ready && self.chambered
+    }
+}
+
+/// Hacia dónde mira la cámara (la misma convención que `PlayerCamera`)
+pub fn aim_direction(yaw: f32, pitch: f32) -> Vec3 {
+    Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0) * Vec3::NEG_Z
+}
+
+/// De dónde sale el disparo: los ojos de un jugador con los pies en `position`
+pub fn muzzle_origin(position: Vec3) -> Vec3 {
+    position + Vec3::Y * CAMERA_HEIGHT_OFFSET
+}
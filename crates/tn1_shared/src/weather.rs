@@ -0,0 +1,283 @@
+//! Clima dinámico: el tiempo que hace y las reglas de juego que salen de él.
+//!
+//! El servidor decide el clima (frentes que entran y salen poco a poco) y lo
+//! envía con `ServerMessage::Weather`; de aquí salen la visibilidad, lo que tapan
+//! la lluvia y la tormenta de los pasos, cómo se enfría un jugador y cuánto
+//! desvía el viento un proyectil (ver `docs/SYSTEMS/BALLISTICS_SYSTEM.md`).
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Metros a los que se oyen los pasos de alguien andando sin ruido alrededor
+const FOOTSTEP_RANGE: f32 = 20.0;
+/// Velocidad de referencia (andar) para el alcance de los pasos
+const WALK_SPEED: f32 = 7.0;
+/// Por debajo de esta velocidad no se hace ruido al moverse
+const SILENT_SPEED: f32 = 1.0;
+
+/// Temperatura corporal normal y umbral de hipotermia (°C)
+pub const NORMAL_BODY_TEMPERATURE: f32 = 37.0;
+pub const HYPOTHERMIA: f32 = 35.0;
+/// Por debajo de esta no baja: el juego no simula la muerte por frío
+const MIN_BODY_TEMPERATURE: f32 = 30.0;
+/// Sensación térmica a partir de la cual el cuerpo no pierde calor
+const COMFORT_TEMPERATURE: f32 = 15.0;
+/// °C por segundo que se pierden por cada grado de sensación bajo el confort
+const COOLING_RATE: f32 = 0.0006;
+/// °C por segundo que se recuperan a cubierto del frío
+const WARMING_RATE: f32 = 0.02;
+/// °C por segundo que aporta moverse a toda velocidad
+const EXERTION_HEAT: f32 = 0.005;
+
+/// Densidad del aire a nivel del mar y 15 °C (kg/m³)
+const SEA_LEVEL_AIR_DENSITY: f32 = 1.225;
+
+/// Tipo de clima
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Rain,
+    Fog,
+    Storm,
+    Snow,
+}
+
+impl WeatherKind {
+    pub const ALL: [WeatherKind; 5] =
+        [WeatherKind::Clear, WeatherKind::Rain, WeatherKind::Fog, WeatherKind::Storm, WeatherKind::Snow];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherKind::Clear => "clear",
+            WeatherKind::Rain => "rain",
+            WeatherKind::Fog => "fog",
+            WeatherKind::Storm => "storm",
+            WeatherKind::Snow => "snow",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            WeatherKind::Clear => "🌤️",
+            WeatherKind::Rain => "🌧️",
+            WeatherKind::Fog => "🌫️",
+            WeatherKind::Storm => "⛈️",
+            WeatherKind::Snow => "🌨️",
+        }
+    }
+
+    /// Fracción de la vista que queda con el frente entero encima
+    fn visibility(&self) -> f32 {
+        match self {
+            WeatherKind::Clear => 1.0,
+            WeatherKind::Rain => 0.6,
+            WeatherKind::Fog => 0.2,
+            WeatherKind::Storm => 0.35,
+            WeatherKind::Snow => 0.5,
+        }
+    }
+
+    /// Cuánto tapa el ruido ambiente los pasos (0 nada, 1 del todo)
+    fn noise(&self) -> f32 {
+        match self {
+            WeatherKind::Clear | WeatherKind::Fog => 0.0,
+            WeatherKind::Rain => 0.5,
+            WeatherKind::Storm => 0.8,
+            // La nieve amortigua las pisadas
+            WeatherKind::Snow => 0.3,
+        }
+    }
+
+    /// Cuánto moja (la ropa mojada enfría)
+    fn wetness(&self) -> f32 {
+        match self {
+            WeatherKind::Clear | WeatherKind::Fog => 0.0,
+            WeatherKind::Rain => 0.6,
+            WeatherKind::Storm => 1.0,
+            WeatherKind::Snow => 0.3,
+        }
+    }
+
+    /// Nubes: oscurecen el cielo y tapan el sol
+    fn cloud_cover(&self) -> f32 {
+        match self {
+            WeatherKind::Clear => 0.0,
+            WeatherKind::Rain => 0.7,
+            WeatherKind::Fog => 0.4,
+            WeatherKind::Storm => 0.9,
+            WeatherKind::Snow => 0.6,
+        }
+    }
+
+    /// Grados que baja la temperatura con el frente entero encima
+    pub fn temperature_drop(&self) -> f32 {
+        match self {
+            WeatherKind::Clear => 0.0,
+            WeatherKind::Rain => 3.0,
+            WeatherKind::Fog => 2.0,
+            WeatherKind::Storm => 6.0,
+            WeatherKind::Snow => 8.0,
+        }
+    }
+
+    /// Velocidad del viento (m/s) que trae el frente
+    pub fn wind_range(&self) -> std::ops::Range<f32> {
+        match self {
+            WeatherKind::Clear => 0.0..5.0,
+            WeatherKind::Rain => 2.0..8.0,
+            WeatherKind::Fog => 0.0..2.0,
+            WeatherKind::Storm => 10.0..20.0,
+            WeatherKind::Snow => 3.0..10.0,
+        }
+    }
+
+    /// Frentes que pueden seguir a este y su peso relativo
+    pub fn transitions(&self) -> &'static [(WeatherKind, f32)] {
+        match self {
+            WeatherKind::Clear => &[
+                (WeatherKind::Rain, 3.0),
+                (WeatherKind::Fog, 2.0),
+                (WeatherKind::Storm, 1.0),
+                (WeatherKind::Snow, 2.0),
+            ],
+            WeatherKind::Rain => &[(WeatherKind::Clear, 3.0), (WeatherKind::Storm, 2.0), (WeatherKind::Fog, 1.0)],
+            WeatherKind::Fog => &[(WeatherKind::Clear, 3.0), (WeatherKind::Rain, 2.0)],
+            WeatherKind::Storm => &[(WeatherKind::Rain, 3.0), (WeatherKind::Clear, 1.0)],
+            WeatherKind::Snow => &[(WeatherKind::Clear, 2.0), (WeatherKind::Fog, 1.0), (WeatherKind::Storm, 1.0)],
+        }
+    }
+
+    /// Lo que cae del cielo depende del frío: lluvia bajo cero es nieve y al revés
+    pub fn for_temperature(self, temperature: f32) -> Self {
+        match self {
+            WeatherKind::Rain if temperature < 1.0 => WeatherKind::Snow,
+            WeatherKind::Snow if temperature > 3.0 => WeatherKind::Rain,
+            kind => kind,
+        }
+    }
+}
+
+impl std::fmt::Display for WeatherKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for WeatherKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WeatherKind::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("clima desconocido: {}", s))
+    }
+}
+
+/// El tiempo que hace
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    /// Fuerza del frente en `[0, 1]`: sube al entrar y baja al irse
+    pub intensity: f32,
+    /// Viento en el plano XZ (m/s, hacia dónde sopla)
+    pub wind: Vec2,
+    /// Temperatura del aire (°C)
+    pub temperature: f32,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self { kind: WeatherKind::Clear, intensity: 1.0, wind: Vec2::ZERO, temperature: 15.0 }
+    }
+}
+
+impl Weather {
+    /// Efecto del frente escalado por su intensidad: de 0 (nada) a `full`
+    fn scaled(&self, full: f32) -> f32 {
+        full * self.intensity.clamp(0.0, 1.0)
+    }
+
+    /// Fracción de la distancia de visión que deja el clima (1 = despejado)
+    pub fn visibility(&self) -> f32 {
+        1.0 - self.scaled(1.0 - self.kind.visibility())
+    }
+
+    /// Cuánto tapa el ruido ambiente los pasos (0-1)
+    pub fn footstep_masking(&self) -> f32 {
+        self.scaled(self.kind.noise())
+    }
+
+    pub fn wetness(&self) -> f32 {
+        self.scaled(self.kind.wetness())
+    }
+
+    pub fn overcast(&self) -> f32 {
+        self.scaled(self.kind.cloud_cover())
+    }
+
+    /// Hay lluvia o nieve cayendo
+    pub fn precipitation(&self) -> f32 {
+        match self.kind {
+            WeatherKind::Rain | WeatherKind::Storm | WeatherKind::Snow => self.intensity.clamp(0.0, 1.0),
+            WeatherKind::Clear | WeatherKind::Fog => 0.0,
+        }
+    }
+
+    pub fn wind_speed(&self) -> f32 {
+        self.wind.length()
+    }
+
+    /// Viento en 3D (sin componente vertical)
+    pub fn wind_vector(&self) -> Vec3 {
+        Vec3::new(self.wind.x, 0.0, self.wind.y)
+    }
+
+    /// Temperatura que siente el cuerpo: el viento y la ropa mojada enfrían
+    pub fn feels_like(&self) -> f32 {
+        self.temperature - self.wind_speed() * 0.7 - self.wetness() * 5.0
+    }
+
+    /// Densidad del aire (kg/m³): el aire frío es más denso y frena más las balas
+    pub fn air_density(&self) -> f32 {
+        SEA_LEVEL_AIR_DENSITY * 288.15 / (273.15 + self.temperature)
+    }
+
+    /// Desvío lateral de un proyectil por el viento (fórmula de Didion): el
+    /// viento empuja durante el tiempo que la bala "pierde" por el rozamiento,
+    /// `time_of_flight - range / muzzle_velocity`. Sin rozamiento no hay desvío.
+    pub fn wind_drift(&self, range: f32, muzzle_velocity: f32, time_of_flight: f32) -> Vec3 {
+        let lag = (time_of_flight - range / muzzle_velocity).max(0.0);
+        self.wind_vector() * lag
+    }
+}
+
+/// Metros a los que se oyen los pasos de alguien que se mueve a `speed` m/s
+/// con el ruido ambiente dado (`Weather::footstep_masking`)
+pub fn footstep_range(speed: f32, masking: f32) -> f32 {
+    if speed < SILENT_SPEED {
+        return 0.0;
+    }
+    FOOTSTEP_RANGE * (speed / WALK_SPEED) * (1.0 - masking.clamp(0.0, 1.0))
+}
+
+/// Variación de la temperatura corporal (°C por segundo) con el clima dado;
+/// `exertion` es cuánto se mueve el jugador (0 quieto, 1 a toda velocidad)
+pub fn body_heat_rate(weather: &Weather, body_temperature: f32, exertion: f32) -> f32 {
+    let cold = COMFORT_TEMPERATURE - weather.feels_like();
+    let exertion = EXERTION_HEAT * exertion.clamp(0.0, 1.0);
+    if cold > 0.0 {
+        exertion - cold * COOLING_RATE
+    } else if body_temperature < NORMAL_BODY_TEMPERATURE {
+        WARMING_RATE + exertion
+    } else {
+        0.0
+    }
+}
+
+/// Aplica `seconds` segundos de clima a una temperatura corporal
+pub fn update_body_temperature(weather: &Weather, body_temperature: f32, exertion: f32, seconds: f32) -> f32 {
+    let next = body_temperature + body_heat_rate(weather, body_temperature, exertion) * seconds;
+    next.clamp(MIN_BODY_TEMPERATURE, NORMAL_BODY_TEMPERATURE)
+}
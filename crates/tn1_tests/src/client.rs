@@ -0,0 +1,294 @@
+use bevy::prelude::*;
+use std::net::SocketAddr;
+use tn1_client::camera::PlayerCamera;
+use tn1_client::chat::ChatHistory;
+use tn1_client::chunks::{ClientChunks, WorldContainer};
+use tn1_client::config::ClientSettings;
+use tn1_client::daylight::{FlashlightBeam, WorldTime};
+use tn1_client::inventory::ClientInventory;
+use tn1_client::medical::ClientBody;
+use tn1_client::networking::{ClientNetworkingPlugin, NetworkClient};
+use tn1_client::trade::ClientTrade;
+use tn1_client::voice::{SpeakerStatus, VoiceChat};
+use tn1_client::weapons::ClientWeapon;
+use tn1_client::weather::{ClientWeather, Footsteps, Precipitation};
+use tn1_client::world_items::ClientWorldItems;
+use tn1_shared::voice::WavInput;
+use tn1_shared::components::*;
+use tn1_shared::daylight::WorldClock;
+use tn1_shared::drops::PickupAction;
+use tn1_shared::inventory::{Inventory, InventoryAction, ItemStack};
+use tn1_shared::loot::LootAction;
+use tn1_shared::medical::BodyHealth;
+use tn1_shared::trade::{TradeAction, TradeView};
+use tn1_shared::weather::Weather;
+
+/// Cliente real (`ClientNetworkingPlugin`) sin ventana ni render
+pub struct TestClient {
+    pub app: App,
+}
+
+impl TestClient {
+    pub fn connect(address: SocketAddr, name: &str) -> Self {
+        Self::connect_with(
+            ClientSettings {
+                server_address: address.to_string(),
+                tls_enabled: false,
+                tls_pins: Vec::new(),
+            },
+            name,
+        )
+    }
+
+    pub fn connect_with(settings: ClientSettings, name: &str) -> Self {
+        Self::connect_configured(settings, name, |_| {})
+    }
+
+    /// `configure` puede añadir plugins extra (p. ej. grabación de partidas) antes del Startup
+    pub fn connect_configured(settings: ClientSettings, name: &str, configure: impl FnOnce(&mut App)) -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            // Los sistemas de networking crean meshes para los jugadores remotos
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .insert_resource(settings)
+            .add_plugins(ClientNetworkingPlugin);
+        configure(&mut app);
+
+        app.world_mut().resource_mut::<NetworkClient>().username = name.to_string();
+        // El input lee yaw/pitch de la cámara aunque no haya render
+        app.world_mut().spawn(PlayerCamera::default());
+
+        // Startup: conecta y envía Hello
+        app.update();
+
+        Self { app }
+    }
+
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    pub fn network(&self) -> &NetworkClient {
+        self.app.world().resource::<NetworkClient>()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.network().connected
+    }
+
+    pub fn player_id(&self) -> Option<PlayerId> {
+        self.network().local_player_id
+    }
+
+    /// Último estado recibido del servidor para un jugador
+    pub fn known_position(&self, player_id: PlayerId) -> Option<Vec3> {
+        self.network().player_states.get(&player_id).map(|state| state.position)
+    }
+
+    /// Posición de la entidad `LocalPlayer` que spawneó el networking
+    pub fn local_position(&mut self) -> Option<Vec3> {
+        let world = self.app.world_mut();
+        world
+            .query_filtered::<&Transform, With<LocalPlayer>>()
+            .iter(world)
+            .next()
+            .map(|transform| transform.translation)
+    }
+
+    /// Mantiene una tecla pulsada hasta `release`
+    pub fn hold(&mut self, key: KeyCode) {
+        self.app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+    }
+
+    pub fn release(&mut self, key: KeyCode) {
+        self.app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(key);
+    }
+
+    /// Pulsa y suelta una tecla en un frame (sin `InputPlugin` nadie limpia `just_pressed`)
+    pub fn tap(&mut self, key: KeyCode) {
+        self.hold(key);
+        self.update();
+        let mut keyboard = self.app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keyboard.release(key);
+        keyboard.clear_just_pressed(key);
+        keyboard.clear_just_released(key);
+    }
+
+    pub fn set_yaw(&mut self, yaw: f32) {
+        let world = self.app.world_mut();
+        for mut camera in world.query::<&mut PlayerCamera>().iter_mut(world) {
+            camera.yaw = yaw;
+        }
+    }
+
+    pub fn set_pitch(&mut self, pitch: f32) {
+        let world = self.app.world_mut();
+        for mut camera in world.query::<&mut PlayerCamera>().iter_mut(world) {
+            camera.pitch = pitch;
+        }
+    }
+
+    /// Mantiene un botón del ratón pulsado hasta `release_mouse`
+    pub fn hold_mouse(&mut self, button: MouseButton) {
+        self.app.world_mut().resource_mut::<ButtonInput<MouseButton>>().press(button);
+    }
+
+    pub fn release_mouse(&mut self, button: MouseButton) {
+        self.app.world_mut().resource_mut::<ButtonInput<MouseButton>>().release(button);
+    }
+
+    /// Escribe una línea en la caja de chat, comandos incluidos
+    pub fn chat(&mut self, input: &str) {
+        self.app.world_mut().resource_scope(|world, mut chat: Mut<ChatHistory>| {
+            chat.submit(input, world.resource::<NetworkClient>());
+        });
+    }
+
+    /// Historial del chat tal como se muestra en pantalla
+    pub fn chat_lines(&self) -> Vec<String> {
+        let username = &self.network().username;
+        self.app.world().resource::<ChatHistory>().lines().map(|line| line.display(username)).collect()
+    }
+
+    /// Lo que transmite al pulsar push-to-talk (en bucle), en lugar del micrófono
+    pub fn voice_input(&mut self, samples: Vec<i16>) {
+        self.app.world_mut().resource_mut::<VoiceChat>().set_input(Box::new(WavInput::from_samples(samples, true)));
+    }
+
+    /// Jugadores que este cliente está oyendo
+    pub fn hearing(&self) -> Vec<(PlayerId, SpeakerStatus)> {
+        self.app.world().resource::<VoiceChat>().speakers().map(|(id, status)| (*id, status.clone())).collect()
+    }
+
+    /// Hora del mundo según este cliente
+    pub fn clock(&self) -> Option<WorldClock> {
+        self.app.world().resource::<WorldTime>().clock()
+    }
+
+    /// Si el jugador remoto se dibuja (de noche depende de la distancia y las luces)
+    pub fn sees(&mut self, player_id: PlayerId) -> bool {
+        let world = self.app.world_mut();
+        world
+            .query::<(&PlayerId, &Visibility)>()
+            .iter(world)
+            .any(|(id, visibility)| *id == player_id && *visibility != Visibility::Hidden)
+    }
+
+    /// Si el jugador lleva el haz de la linterna en esta escena
+    pub fn sees_flashlight(&mut self, player_id: PlayerId) -> bool {
+        let world = self.app.world_mut();
+        let beams: Vec<Entity> =
+            world.query_filtered::<&Parent, With<FlashlightBeam>>().iter(world).map(|parent| parent.get()).collect();
+        world.query::<(Entity, &PlayerId)>().iter(world).any(|(entity, id)| *id == player_id && beams.contains(&entity))
+    }
+
+    /// Clima según este cliente
+    pub fn weather(&self) -> Option<Weather> {
+        self.app.world().resource::<ClientWeather>().weather()
+    }
+
+    /// Temperatura corporal que el servidor le ha mandado
+    pub fn body_temperature(&self) -> Option<f32> {
+        self.app.world().resource::<ClientWeather>().body_temperature()
+    }
+
+    /// Si se oyen los pasos del jugador remoto
+    pub fn hears(&self, player_id: PlayerId) -> bool {
+        self.app.world().resource::<Footsteps>().hears(player_id)
+    }
+
+    /// Gotas o copos visibles alrededor del jugador
+    pub fn precipitation(&mut self) -> usize {
+        let world = self.app.world_mut();
+        world
+            .query_filtered::<&Visibility, With<Precipitation>>()
+            .iter(world)
+            .filter(|visibility| **visibility != Visibility::Hidden)
+            .count()
+    }
+
+    /// Chunks recibidos y construidos por el streaming
+    pub fn chunks(&self) -> &ClientChunks {
+        self.app.world().resource::<ClientChunks>()
+    }
+
+    /// Contenedores de loot presentes en la escena
+    pub fn containers(&mut self) -> Vec<WorldContainer> {
+        let world = self.app.world_mut();
+        world.query::<&WorldContainer>().iter(world).copied().collect()
+    }
+
+    /// Inventario propio según el servidor
+    pub fn inventory(&self) -> Option<&Inventory> {
+        self.app.world().resource::<ClientInventory>().inventory()
+    }
+
+    /// Último rechazo del servidor a una acción de inventario
+    pub fn inventory_rejection(&self) -> Option<&(u32, String)> {
+        self.app.world().resource::<ClientInventory>().last_rejection()
+    }
+
+    /// Pide una acción de inventario; devuelve su número de secuencia
+    pub fn inventory_action(&mut self, action: InventoryAction) -> u32 {
+        self.app.world_mut().resource_scope(|world, mut inventory: Mut<ClientInventory>| {
+            inventory.request(world.resource::<NetworkClient>(), action)
+        })
+    }
+
+    /// Último contenedor abierto y lo que tenía
+    pub fn loot(&self) -> Option<&(u64, Vec<ItemStack>)> {
+        self.app.world().resource::<ClientInventory>().loot()
+    }
+
+    /// Abre un contenedor o saca algo; devuelve su número de secuencia
+    pub fn loot_action(&mut self, action: LootAction) -> u32 {
+        self.app.world_mut().resource_scope(|world, mut inventory: Mut<ClientInventory>| {
+            inventory.request_loot(world.resource::<NetworkClient>(), action)
+        })
+    }
+
+    /// Items del suelo y cadáveres que el servidor dice que hay cerca
+    pub fn world_items(&self) -> &ClientWorldItems {
+        self.app.world().resource::<ClientWorldItems>()
+    }
+
+    /// Recoge algo del suelo o de un cadáver; devuelve su número de secuencia
+    pub fn pickup(&mut self, action: PickupAction) -> u32 {
+        self.app.world_mut().resource_scope(|world, mut inventory: Mut<ClientInventory>| {
+            inventory.request_pickup(world.resource::<NetworkClient>(), action)
+        })
+    }
+
+    pub fn trade_window(&self) -> Option<&TradeView> {
+        self.app.world().resource::<ClientTrade>().window()
+    }
+
+    /// Id, si se hizo y motivo del último comercio cerrado
+    pub fn last_trade_closed(&self) -> Option<&(u64, bool, String)> {
+        self.app.world().resource::<ClientTrade>().last_closed()
+    }
+
+    /// Acción de comercio; devuelve su número de secuencia
+    pub fn trade(&mut self, action: TradeAction) -> u32 {
+        self.app.world_mut().resource_scope(|world, mut inventory: Mut<ClientInventory>| {
+            inventory.request_trade(world.resource::<NetworkClient>(), action)
+        })
+    }
+
+    /// Arma en la mano, disparos predichos y últimos impactos
+    pub fn weapon(&self) -> &ClientWeapon {
+        self.app.world().resource::<ClientWeapon>()
+    }
+
+    /// Vida por zonas que ha mandado el servidor
+    pub fn body(&self) -> Option<&BodyHealth> {
+        self.app.world().resource::<ClientBody>().body()
+    }
+
+    pub fn disconnect(&mut self) {
+        self.app.world_mut().resource_mut::<NetworkClient>().disconnect();
+    }
+}
@@ -0,0 +1,143 @@
+//! Harness de integración de Trust-No-1.
+//!
+//! Levanta el `App` del servidor (networking, física, base de datos en memoria) y
+//! clientes headless que se conectan por loopback, y los avanza tick a tick para
+//! cubrir los flujos que antes solo probaban `test_multiplayer*.sh`.
+
+use bevy::prelude::*;
+use std::thread;
+use std::time::{Duration, Instant};
+use tn1_server::config::ServerConfig;
+use tn1_server::inventory::{GiveItem, InventoryPlugin};
+use tn1_server::items::ItemRegistryPlugin;
+use tn1_server::medical::MedicalPlugin;
+use tn1_server::tls::ServerTls;
+use tn1_server::weapons::{WeaponSettings, WeaponsPlugin};
+use tn1_shared::components::PlayerId;
+use tn1_shared::inventory::{EquipmentSlot, InventoryAction, ItemId, ItemStack};
+
+mod client;
+mod raw;
+mod server;
+
+pub use client::TestClient;
+pub use raw::RawClient;
+pub use server::{collect_dropped, data_dir, item_settings, load_data, loot_settings, test_config, weapon_settings, Dropped, TestServer, TICK};
+
+/// Tiempo real máximo para esperar algo que depende de la red
+pub const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Servidor y clientes que avanzan juntos
+pub struct Harness {
+    pub server: TestServer,
+    pub clients: Vec<TestClient>,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        Self::with_config(test_config())
+    }
+
+    pub fn with_config(config: ServerConfig) -> Self {
+        Self {
+            server: TestServer::start(config),
+            clients: Vec::new(),
+        }
+    }
+
+    /// Servidor con inventario, armas y daño por zonas
+    pub fn with_weapons(settings: WeaponSettings) -> Self {
+        let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+            app.add_plugins((ItemRegistryPlugin { settings: item_settings() }, InventoryPlugin, WeaponsPlugin { settings }, MedicalPlugin));
+        });
+        Self { server, clients: Vec::new() }
+    }
+
+    /// Conecta un cliente nuevo y devuelve su índice
+    pub fn connect(&mut self, name: &str) -> usize {
+        self.clients.push(TestClient::connect(self.server.address, name));
+        self.clients.len() - 1
+    }
+
+    /// Conecta un cliente y espera a que el servidor lo admita
+    pub fn join(&mut self, name: &str) -> usize {
+        let index = self.connect(name);
+        assert!(
+            self.step_until(NETWORK_TIMEOUT, |h| h.clients[index].is_connected()),
+            "{} no llegó a conectarse",
+            name
+        );
+        index
+    }
+
+    pub fn client(&mut self, index: usize) -> &mut TestClient {
+        &mut self.clients[index]
+    }
+
+    /// Un tick del servidor y un frame de cada cliente
+    pub fn step(&mut self) {
+        self.server.update();
+        for client in &mut self.clients {
+            client.update();
+        }
+        // Deja correr a los threads de red entre ticks
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    pub fn step_for(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Avanza hasta que se cumpla la condición; `false` si se agota el tiempo
+    pub fn step_until(&mut self, timeout: Duration, mut condition: impl FnMut(&mut Self) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            self.step();
+            if condition(self) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Entra, espera a tener inventario y a estar en el suelo (se aparece a 10 m)
+    pub fn join_grounded(&mut self, name: &str) -> (usize, PlayerId) {
+        let client = self.join(name);
+        let player_id = self.clients[client].player_id().unwrap();
+        assert!(self.step_until(NETWORK_TIMEOUT, |h| {
+            h.clients[client].inventory().is_some() && h.server.player(player_id).unwrap().translation.y < 0.01
+        }));
+        (client, player_id)
+    }
+
+    /// Da un item y devuelve el stack nuevo tal como lo ve el cliente
+    pub fn give(&mut self, client: usize, player_id: PlayerId, item: &str, quantity: u32) -> ItemStack {
+        let before: Vec<ItemId> = self.clients[client].inventory().unwrap().items().iter().map(|(_, placed)| placed.stack.id).collect();
+        self.server.app.world_mut().send_event(GiveItem { player_id, item: item.to_string(), quantity });
+        assert!(self.step_until(NETWORK_TIMEOUT, |h| h.clients[client].inventory().unwrap().items().len() > before.len()));
+        let inventory = self.clients[client].inventory().unwrap();
+        inventory.items().into_iter().map(|(_, placed)| placed.stack.clone()).find(|stack| !before.contains(&stack.id)).unwrap()
+    }
+
+    /// Equipa un item y espera a que el cliente vea el hueco ocupado
+    pub fn equip(&mut self, client: usize, item: ItemId, slot: EquipmentSlot) {
+        self.clients[client].inventory_action(InventoryAction::Equip { item, slot });
+        assert!(self.step_until(NETWORK_TIMEOUT, |h| h.clients[client].inventory().unwrap().equipment.get(slot).is_some()));
+    }
+
+    /// Mueve al jugador en el servidor sin pasar por la física
+    pub fn teleport(&mut self, player_id: PlayerId, position: Vec3) {
+        let world = self.server.app.world_mut();
+        let mut players = world.query::<(&PlayerId, &mut Transform)>();
+        let (_, mut transform) = players.iter_mut(world).find(|(id, _)| **id == player_id).unwrap();
+        transform.translation = position;
+    }
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,70 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use tn1_shared::codec::*;
+use tn1_shared::protocol::*;
+
+/// Conexión de protocolo crudo para comprobar mensajes exactos (handshake, `WorldState`)
+pub struct RawClient {
+    stream: TcpStream,
+    codec: WireCodec,
+    frames: FrameBuffer,
+}
+
+impl RawClient {
+    pub fn connect(address: SocketAddr) -> std::io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            stream,
+            codec: WireCodec::Json,
+            frames: FrameBuffer::new(),
+        })
+    }
+
+    /// Codec en uso (JSON hasta recibir `Welcome`)
+    pub fn codec(&self) -> WireCodec {
+        self.codec
+    }
+
+    pub fn hello(&mut self, name: &str, codecs: Vec<WireCodec>) -> std::io::Result<()> {
+        self.send(&ClientMessage::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            client_name: name.to_string(),
+            codecs,
+        })
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> std::io::Result<()> {
+        let data = self.codec.encode_frame(message).map_err(invalid_data)?;
+        self.stream.write_all(&data)
+    }
+
+    /// Mensajes recibidos hasta ahora, sin bloquear
+    pub fn poll(&mut self) -> std::io::Result<Vec<ServerMessage>> {
+        let mut buffer = [0u8; 8192];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => self.frames.extend(&buffer[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(payload) = self.frames.next_frame().map_err(invalid_data)? {
+            let message = self.codec.decode::<ServerMessage>(&payload).map_err(invalid_data)?;
+            if let ServerMessage::Welcome { codec, .. } = &message {
+                self.codec = *codec;
+            }
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
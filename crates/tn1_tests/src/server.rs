@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tn1_server::config::ServerConfig;
+use tn1_server::database::{InMemoryStore, PlayerStorage};
+use tn1_server::inventory::ItemDropped;
+use tn1_server::items::ItemSettings;
+use tn1_server::loot::LootSettings;
+use tn1_server::lag_compensation::LagCompensationPlugin;
+use tn1_server::networking::{ListenAddress, NetworkingPlugin, ServerState, ServerTick};
+use tn1_server::physics::ServerPhysicsPlugin;
+use tn1_server::tls::ServerTls;
+use tn1_server::weapons::WeaponSettings;
+use tn1_shared::{components::*, events::*};
+
+/// Paso fijo de simulación: cada `update()` del servidor avanza exactamente un tick
+pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Configuración de pruebas: puerto libre elegido por el sistema operativo
+pub fn test_config() -> ServerConfig {
+    ServerConfig {
+        bind_address: SocketAddr::from(([127, 0, 0, 1], 0)),
+        server_name: "tn1-test".to_string(),
+        map_name: "test_plane".to_string(),
+        max_players: 8,
+        max_queue: 8,
+    }
+}
+
+/// Catálogo de items del repo (`data/items`), sin recarga en caliente
+pub fn item_settings() -> ItemSettings {
+    ItemSettings {
+        path: Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data/items"),
+        hot_reload: false,
+        poll_interval: Duration::from_secs(1),
+    }
+}
+
+/// Tablas de loot del repo (`data/loot`), guardando cada medio segundo
+pub fn loot_settings() -> LootSettings {
+    LootSettings {
+        path: Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data/loot"),
+        reach: 3.0,
+        save_interval: Duration::from_millis(500),
+    }
+}
+
+/// Carpeta temporal con los ficheros dados (y nada más), para catálogos y tablas de prueba
+pub fn data_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tn1_data_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, source) in files {
+        std::fs::write(dir.join(file), source).unwrap();
+    }
+    dir
+}
+
+/// Pasa a `load` una `data_dir` con los ficheros dados y la borra al terminar
+pub fn load_data<T>(name: &str, files: &[(&str, &str)], load: impl FnOnce(&Path) -> T) -> T {
+    let dir = data_dir(name, files);
+    let loaded = load(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    loaded
+}
+
+/// Armas sin dispersión, para poder apuntar a una zona concreta
+pub fn weapon_settings() -> WeaponSettings {
+    WeaponSettings { hitscan_range: 100.0, max_range: 800.0, hip_spread: 0.0, aim_spread: 0.0, substeps: 4, max_projectiles: 256 }
+}
+
+/// `ItemDropped` emitidos por el servidor; se llena añadiendo `collect_dropped` en `Update`
+#[derive(Resource, Default)]
+pub struct Dropped(pub Vec<ItemDropped>);
+
+pub fn collect_dropped(mut events: EventReader<ItemDropped>, mut dropped: ResMut<Dropped>) {
+    dropped.0.extend(events.read().cloned());
+}
+
+/// Servidor autoritativo con base de datos en memoria y sin TLS
+pub struct TestServer {
+    pub app: App,
+    pub address: SocketAddr,
+    pub store: Arc<InMemoryStore>,
+}
+
+impl TestServer {
+    pub fn start(config: ServerConfig) -> Self {
+        Self::start_with_tls(config, ServerTls(None))
+    }
+
+    pub fn start_with_tls(config: ServerConfig, tls: ServerTls) -> Self {
+        Self::start_with(config, tls, |_| {})
+    }
+
+    /// `configure` puede añadir plugins extra (p. ej. grabación de demos) antes del Startup
+    pub fn start_with(config: ServerConfig, tls: ServerTls, configure: impl FnOnce(&mut App)) -> Self {
+        let store = Arc::new(InMemoryStore::new());
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(TICK))
+            .add_event::<PlayerInputEvent>()
+            .add_event::<PlayerPositionEvent>()
+            .add_event::<PlayerSpawnEvent>()
+            .add_event::<PlayerDespawnEvent>()
+            .insert_resource(config)
+            .insert_resource(tls)
+            .insert_resource(PlayerStorage(store.clone()))
+            .add_plugins((
+                ServerPhysicsPlugin,
+                NetworkingPlugin,
+                LagCompensationPlugin,
+            ));
+        configure(&mut app);
+
+        // Startup: abre el listener y arranca el worker de persistencia
+        app.update();
+        let address = app.world().resource::<ListenAddress>().0;
+
+        Self { app, address, store }
+    }
+
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.app.world().resource::<ServerTick>().0
+    }
+
+    /// Jugadores simulados por el servidor
+    pub fn players(&mut self) -> Vec<(PlayerId, Transform)> {
+        let world = self.app.world_mut();
+        world
+            .query_filtered::<(&PlayerId, &Transform), With<Player>>()
+            .iter(world)
+            .map(|(player_id, transform)| (*player_id, *transform))
+            .collect()
+    }
+
+    pub fn player(&mut self, player_id: PlayerId) -> Option<Transform> {
+        self.players()
+            .into_iter()
+            .find(|(id, _)| *id == player_id)
+            .map(|(_, transform)| transform)
+    }
+
+    /// Copia de un componente del jugador en el servidor
+    pub fn component<T: Component + Clone>(&mut self, player_id: PlayerId) -> Option<T> {
+        let world = self.app.world_mut();
+        world
+            .query::<(&PlayerId, &T)>()
+            .iter(world)
+            .find(|(id, _)| **id == player_id)
+            .map(|(_, component)| component.clone())
+    }
+
+    /// Cambia un componente del jugador en el servidor (p. ej. para herirlo)
+    pub fn edit_component<T: Component>(&mut self, player_id: PlayerId, edit: impl FnOnce(&mut T)) {
+        let world = self.app.world_mut();
+        let (_, mut component) = world
+            .query::<(&PlayerId, &mut T)>()
+            .iter_mut(world)
+            .find(|(id, _)| **id == player_id)
+            .expect("el jugador no tiene ese componente");
+        edit(&mut component);
+    }
+
+    /// Clientes que completaron la autenticación
+    pub fn connected_players(&self) -> usize {
+        let state = self.app.world().resource::<ServerState>();
+        let clients = state.clients.lock().unwrap();
+        clients.values().filter(|client| client.player_id.is_some()).count()
+    }
+
+    pub fn queue_length(&self) -> usize {
+        let state = self.app.world().resource::<ServerState>();
+        let queue = state.join_queue.lock().unwrap();
+        queue.len()
+    }
+}
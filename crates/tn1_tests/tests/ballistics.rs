@@ -0,0 +1,288 @@
+use bevy::prelude::*;
+use tn1_shared::ballistics::{
+    raycast_ground, raycast_props, AmmoKind, Atmosphere, BallisticWorld, Impact, ImpactOutcome, Launch, Material, Projectile, ProjectileStore,
+    RoundStats, SurfaceHit,
+};
+use tn1_shared::chunk::{PropKind, StaticProp};
+use tn1_shared::components::PlayerId;
+use tn1_shared::items::{ItemCategory, ItemDef, ItemDefs};
+use tn1_shared::weather::Weather;
+use tn1_tests::item_settings;
+
+const DT: f32 = 1.0 / 60.0;
+
+/// Aire quieto y sin gravedad ni rozamiento, para medir una cosa cada vez
+fn vacuum() -> Atmosphere {
+    Atmosphere { gravity: Vec3::ZERO, wind: Vec3::ZERO, air_density: 0.0 }
+}
+
+/// Nada contra lo que chocar
+struct Empty;
+
+impl BallisticWorld for Empty {
+    fn raycast(&mut self, _: Vec3, _: Vec3, _: PlayerId) -> Option<SurfaceHit> {
+        None
+    }
+}
+
+/// Pared perpendicular a X entre `x` y `x + thickness`
+struct Wall {
+    x: f32,
+    thickness: f32,
+    material: Material,
+}
+
+impl BallisticWorld for Wall {
+    fn raycast(&mut self, from: Vec3, to: Vec3, _: PlayerId) -> Option<SurfaceHit> {
+        if from.x >= self.x || to.x < self.x {
+            return None;
+        }
+        let point = from.lerp(to, (self.x - from.x) / (to.x - from.x));
+        Some(SurfaceHit { point, normal: Vec3::NEG_X, material: self.material, thickness: self.thickness, target: None })
+    }
+}
+
+/// Suelo plano en y = 0 de un material sin otro lado
+struct Floor(Material);
+
+impl BallisticWorld for Floor {
+    fn raycast(&mut self, from: Vec3, to: Vec3, _: PlayerId) -> Option<SurfaceHit> {
+        let mut hit = raycast_ground(|_, _| 0.0, from, to)?;
+        hit.material = self.0;
+        Some(hit)
+    }
+}
+
+fn shooter() -> PlayerId {
+    PlayerId(uuid::Uuid::nil())
+}
+
+fn launch(direction: Vec3, muzzle_velocity: f32, round: RoundStats) -> Launch<()> {
+    Launch { payload: (), shooter: shooter(), origin: Vec3::ZERO, direction, muzzle_velocity, round, damage: 30.0 }
+}
+
+fn pistol(kind: AmmoKind) -> Launch<()> {
+    launch(Vec3::X, 360.0, RoundStats { kind, ..default() })
+}
+
+fn rifle_round() -> RoundStats {
+    RoundStats { kind: AmmoKind::Fmj, mass: 3.4, diameter: 5.6, drag: 0.25 }
+}
+
+/// Vuela `ticks` ticks y devuelve la bala (si sigue) y los impactos
+fn fly<W: BallisticWorld>(shot: Launch<()>, atmosphere: Atmosphere, world: &mut W, ticks: u32) -> (Option<Projectile<()>>, Vec<Impact<()>>) {
+    let mut store = ProjectileStore::new(4, 4, 2000.0);
+    let handle = store.launch(shot).unwrap();
+    let mut impacts = Vec::new();
+    for _ in 0..ticks {
+        store.step(DT, &atmosphere, world, &mut impacts);
+    }
+    (store.get(handle).cloned(), impacts)
+}
+
+#[test]
+fn gravity_drops_rounds_like_a_parabola() {
+    let atmosphere = Atmosphere { gravity: Vec3::Y * -9.81, ..vacuum() };
+    let (projectile, _) = fly(pistol(AmmoKind::Fmj), atmosphere, &mut Empty, 30);
+    let projectile = projectile.unwrap();
+    let t = projectile.age;
+    assert!((t - 0.5).abs() < 1e-3);
+    assert!((projectile.position.x - 180.0).abs() < 0.01, "{}", projectile.position);
+    // Euler semi-implícito con 120 pasos: muy cerca de g t² / 2
+    let expected = -0.5 * 9.81 * t * t;
+    assert!((projectile.position.y - expected).abs() < 0.02, "{} frente a {}", projectile.position.y, expected);
+}
+
+#[test]
+fn drag_slows_rounds_and_cold_air_slows_them_more() {
+    let calm = |temperature| Atmosphere { gravity: Vec3::ZERO, ..Atmosphere::from_weather(&Weather { temperature, ..default() }) };
+    let speed = |round, temperature| fly(launch(Vec3::X, 880.0, round), calm(temperature), &mut Empty, 30).0.unwrap().velocity.length();
+
+    let warm = speed(rifle_round(), 30.0);
+    let cold = speed(rifle_round(), -20.0);
+    assert!(warm < 800.0 && warm > 400.0, "{warm}");
+    assert!(cold < warm);
+
+    // Una bala más pesada con el mismo calibre frena menos
+    assert!(speed(RoundStats { mass: 8.0, ..rifle_round() }, 15.0) > speed(rifle_round(), 15.0));
+}
+
+#[test]
+fn wind_pushes_rounds_downwind() {
+    let air = Atmosphere { gravity: Vec3::ZERO, wind: Vec3::new(0.0, 0.0, 10.0), air_density: 1.225 };
+    let drifted = fly(launch(Vec3::X, 880.0, rifle_round()), air, &mut Empty, 30).0.unwrap();
+    assert!(drifted.position.z > 0.05, "{}", drifted.position);
+
+    // Sin aire no hay nada que empuje
+    let still = fly(launch(Vec3::X, 880.0, rifle_round()), Atmosphere { air_density: 0.0, ..air }, &mut Empty, 30).0.unwrap();
+    assert_eq!(still.position.z, 0.0);
+}
+
+#[test]
+fn simulation_is_deterministic() {
+    let run = || {
+        let mut store = ProjectileStore::new(64, 4, 2000.0);
+        for i in 0..50 {
+            let direction = Vec3::new(1.0, (i as f32 * 0.37).sin() * 0.2, (i as f32 * 0.91).cos() * 0.3);
+            store.launch(launch(direction, 300.0 + i as f32 * 10.0, rifle_round()));
+        }
+        let atmosphere = Atmosphere { wind: Vec3::new(3.0, 0.0, -2.0), ..Atmosphere::default() };
+        let mut impacts = Vec::new();
+        for _ in 0..90 {
+            store.step(DT, &atmosphere, &mut Floor(Material::Soil), &mut impacts);
+        }
+        let positions: Vec<Vec3> = store.iter().map(|projectile| projectile.position).collect();
+        (positions, impacts.iter().map(|impact| impact.point).collect::<Vec<_>>())
+    };
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn ammo_kinds_change_speed_and_damage() {
+    let speed = |kind| fly(pistol(kind), vacuum(), &mut Empty, 1).0.unwrap().velocity.length();
+    assert_eq!(speed(AmmoKind::Fmj), 360.0);
+    assert!(speed(AmmoKind::ArmorPiercing) > 360.0);
+    assert!(speed(AmmoKind::HollowPoint) < 360.0);
+
+    // La subsónica no pasa de la velocidad del sonido aunque el arma dé más
+    let subsonic = fly(launch(Vec3::X, 880.0, RoundStats { kind: AmmoKind::Subsonic, ..rifle_round() }), vacuum(), &mut Empty, 1);
+    assert!(subsonic.0.unwrap().velocity.length() < 343.0);
+
+    let damage = |kind| fly(pistol(kind), vacuum(), &mut Empty, 1).0.unwrap().damage();
+    assert_eq!(damage(AmmoKind::Fmj), 30.0);
+    assert!(damage(AmmoKind::HollowPoint) > damage(AmmoKind::Fmj));
+    assert!(damage(AmmoKind::ArmorPiercing) < damage(AmmoKind::Fmj));
+}
+
+#[test]
+fn penetration_depends_on_ammo_material_and_thickness() {
+    let through = |kind, material, thickness| {
+        let (projectile, impacts) = fly(pistol(kind), vacuum(), &mut Wall { x: 5.0, thickness, material }, 3);
+        assert_eq!(impacts.len(), 1);
+        let penetrated = matches!(impacts[0].outcome, ImpactOutcome::Penetrated { .. });
+        assert_eq!(penetrated, projectile.is_some());
+        if let Some(projectile) = projectile {
+            assert!(projectile.position.x > 5.0 + thickness);
+            assert!(projectile.velocity.length() < 360.0, "pierde velocidad al atravesar");
+        }
+        penetrated
+    };
+    // Una tabla de 10 cm: la FMJ pasa, la punta hueca se queda dentro
+    assert!(through(AmmoKind::Fmj, Material::Wood, 0.1));
+    assert!(!through(AmmoKind::HollowPoint, Material::Wood, 0.1));
+    // Un tronco de 30 cm solo lo pasa la perforante
+    assert!(!through(AmmoKind::Fmj, Material::Wood, 0.3));
+    assert!(through(AmmoKind::ArmorPiercing, Material::Wood, 0.3));
+    // El mismo grosor de metal no lo pasa nadie
+    assert!(!through(AmmoKind::ArmorPiercing, Material::Metal, 0.3));
+    // Y el suelo no tiene otro lado
+    assert!(!through(AmmoKind::ArmorPiercing, Material::Soil, f32::INFINITY));
+}
+
+#[test]
+fn grazing_rounds_ricochet_off_hard_surfaces() {
+    let from_above = |degrees: f32| Launch {
+        origin: Vec3::new(0.0, 1.0, 0.0),
+        ..launch(Vec3::new(degrees.to_radians().cos(), -degrees.to_radians().sin(), 0.0), 360.0, RoundStats::default())
+    };
+
+    // Rasante contra piedra: rebota hacia arriba y más lenta
+    let (projectile, impacts) = fly(from_above(5.0), vacuum(), &mut Floor(Material::Stone), 4);
+    assert_eq!(impacts[0].outcome, ImpactOutcome::Ricochet);
+    assert_eq!(impacts[0].material, Material::Stone);
+    let projectile = projectile.unwrap();
+    assert!(projectile.velocity.y > 0.0 && projectile.position.y > 0.0);
+    assert!(projectile.velocity.length() < 360.0 * 0.7);
+    assert_eq!(projectile.ricochets, 1);
+
+    // De frente se queda clavada
+    let (projectile, impacts) = fly(from_above(45.0), vacuum(), &mut Floor(Material::Stone), 4);
+    assert_eq!(impacts[0].outcome, ImpactOutcome::Stopped);
+    assert!(projectile.is_none());
+
+    // La carne no hace rebotar nada
+    let (_, impacts) = fly(from_above(5.0), vacuum(), &mut Floor(Material::Flesh), 4);
+    assert_ne!(impacts[0].outcome, ImpactOutcome::Ricochet);
+}
+
+#[test]
+fn the_store_reuses_slots_and_handles_hundreds_of_rounds() {
+    let mut store = ProjectileStore::new(500, 4, 2000.0);
+    let handles: Vec<_> = (0..500).map(|_| store.launch(pistol(AmmoKind::Fmj)).unwrap()).collect();
+    assert_eq!(store.len(), 500);
+    assert!(store.launch(pistol(AmmoKind::Fmj)).is_none(), "lleno");
+
+    // Todas contra una pared de metal a 10 m: se paran en el primer tick
+    let mut impacts = Vec::new();
+    let mut wall = Wall { x: 3.0, thickness: 1.0, material: Material::Metal };
+    store.step(DT, &vacuum(), &mut wall, &mut impacts);
+    assert_eq!(impacts.len(), 500);
+    assert!(store.is_empty());
+    assert!(handles.iter().all(|handle| store.get(*handle).is_none()), "los handles viejos no valen");
+
+    // Los huecos vuelven a estar libres
+    let again = store.launch(pistol(AmmoKind::Fmj)).unwrap();
+    assert!(store.get(again).is_some());
+    assert_eq!(store.len(), 1);
+}
+
+#[test]
+fn rounds_are_lost_past_max_range() {
+    let mut store = ProjectileStore::new(1, 4, 100.0);
+    store.launch(pistol(AmmoKind::Fmj)).unwrap();
+    let mut impacts = Vec::new();
+    for _ in 0..60 {
+        store.step(DT, &vacuum(), &mut Empty, &mut impacts);
+    }
+    assert!(store.is_empty());
+    assert!(impacts.is_empty());
+}
+
+#[test]
+fn advance_fast_forwards_one_round() {
+    let mut store = ProjectileStore::new(2, 4, 2000.0);
+    let ahead = store.launch(pistol(AmmoKind::Fmj)).unwrap();
+    let behind = store.launch(pistol(AmmoKind::Fmj)).unwrap();
+    let mut impacts = Vec::new();
+    store.advance(ahead, 50.0, DT, &vacuum(), &mut Empty, &mut impacts);
+    assert!(store.get(ahead).unwrap().travelled >= 50.0);
+    assert_eq!(store.get(behind).unwrap().travelled, 0.0);
+
+    // Y se para si encuentra algo antes
+    store.advance(behind, 50.0, DT, &vacuum(), &mut Wall { x: 10.0, thickness: 1.0, material: Material::Stone }, &mut impacts);
+    assert!(store.get(behind).is_none());
+    assert!((impacts[0].point.x - 10.0).abs() < 1e-3);
+}
+
+#[test]
+fn trees_and_rocks_stop_rounds_but_bushes_do_not() {
+    let prop = |kind| StaticProp { kind, position: Vec3::new(10.0, 0.0, 0.0), rotation: 0.0, scale: 2.0 };
+    let (from, to) = (Vec3::new(0.0, 1.0, 0.0), Vec3::new(20.0, 1.0, 0.0));
+
+    let tree = raycast_props(&[prop(PropKind::Tree)], from, to).unwrap();
+    assert_eq!(tree.material, Material::Wood);
+    assert!((tree.point.x - 9.5).abs() < 1e-3, "{}", tree.point);
+    assert!((tree.thickness - 1.0).abs() < 1e-3);
+    assert!(tree.normal.abs_diff_eq(Vec3::NEG_X, 1e-3));
+
+    assert_eq!(raycast_props(&[prop(PropKind::Rock)], from, to).unwrap().material, Material::Stone);
+    assert!(raycast_props(&[prop(PropKind::Bush)], from, to).is_none());
+    // Por encima de la roca no
+    assert!(raycast_props(&[prop(PropKind::Rock)], from + Vec3::Y * 2.0, to + Vec3::Y * 2.0).is_none());
+}
+
+#[test]
+fn shipped_ammo_describes_its_rounds() {
+    let defs = ItemDefs::load(&item_settings().path).unwrap();
+    for key in ["ammo_9mm", "ammo_545"] {
+        let round = defs.get(key).unwrap().round.unwrap();
+        assert_eq!(round.kind, AmmoKind::Fmj, "{key}");
+    }
+    assert!(defs.get("ammo_545").unwrap().round.unwrap().mass < defs.get("ammo_9mm").unwrap().round.unwrap().mass);
+
+    // Solo la munición lleva bala, y con valores positivos
+    let def = |category, round| ItemDef { key: "x".to_string(), name: "x".to_string(), category, round: Some(round), ..default() };
+    assert_eq!(def(ItemCategory::Ammo, RoundStats::default()).validate(), Ok(()));
+    assert!(def(ItemCategory::Medical, RoundStats::default()).validate().is_err());
+    assert!(def(ItemCategory::Ammo, RoundStats { mass: 0.0, ..default() }).validate().is_err());
+}
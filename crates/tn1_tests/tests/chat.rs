@@ -0,0 +1,198 @@
+use bevy::prelude::*;
+use tn1_client::chat::{parse_chat_command, ChatCommand};
+use tn1_server::chat::{censor, ChatModeration, ChatPlugin, ChatSettings};
+use tn1_server::tls::ServerTls;
+use tn1_shared::protocol::ChatChannel;
+use tn1_tests::*;
+
+fn settings() -> ChatSettings {
+    ChatSettings {
+        max_length: 50,
+        proximity_radius: 3.0,
+        rate_limit: 3,
+        rate_window: 5.0,
+        mute_after_strikes: 3,
+        mute_duration: 60.0,
+        banned_words: vec!["idiota".to_string()],
+    }
+}
+
+fn chat_harness() -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins(ChatPlugin { settings: settings() });
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+fn has_line(h: &Harness, client: usize, line: &str) -> bool {
+    h.clients[client].chat_lines().iter().any(|shown| shown == line)
+}
+
+#[test]
+fn global_chat_reaches_everyone_and_is_logged() {
+    let mut h = chat_harness();
+    let a = h.join("ana");
+    let b = h.join("bruno");
+    let c = h.join("carla");
+
+    h.client(a).chat("  hola a todos ");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        [a, b, c].iter().all(|&client| has_line(h, client, "[Global] ana: hola a todos"))
+    }));
+
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| !h.server.store.chat_log().is_empty()));
+    let log = h.server.store.chat_log();
+    assert_eq!(log[0].username, "ana");
+    assert_eq!(log[0].channel, "global");
+    assert_eq!(log[0].message, "hola a todos");
+    assert_eq!(log[0].blocked_reason, None);
+}
+
+#[test]
+fn whispers_and_team_chat_stay_private() {
+    let mut h = chat_harness();
+    let a = h.join("ana");
+    let b = h.join("bruno");
+    let c = h.join("carla");
+
+    // El nombre del destinatario no distingue mayúsculas
+    h.client(a).chat("/w BRUNO nos vemos en el faro");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        has_line(h, b, "[de ana] nos vemos en el faro") && has_line(h, a, "[a bruno] nos vemos en el faro")
+    }));
+
+    // `/r` contesta al último susurro recibido
+    h.client(b).chat("/r vale");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, a, "[de bruno] vale")));
+
+    h.client(a).chat("/clan rojo");
+    h.client(b).chat("/clan Rojo");
+    h.client(c).chat("/team ¿alguien?");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        has_line(h, a, "* Te has unido al equipo rojo") && has_line(h, b, "* Te has unido al equipo Rojo")
+    }));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, c, "* No estás en ningún equipo (/clan <nombre>)")));
+
+    h.client(a).chat("/team flanqueamos");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, b, "[Equipo] ana: flanqueamos")));
+    h.client(a).chat("/w nadie hola");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, a, "* nadie no está conectado")));
+
+    // Nada privado llegó a quien no debía
+    assert!(h.clients[c].chat_lines().iter().all(|line| !line.contains("faro") && !line.contains("flanqueamos")));
+}
+
+#[test]
+fn proximity_chat_uses_the_radius() {
+    let mut h = chat_harness();
+    let a = h.join("ana");
+    let b = h.join("bruno");
+    let c = h.join("carla");
+    let b_id = h.clients[b].player_id().unwrap();
+
+    // Bruno se aleja más que el radio de proximidad (3 m)
+    h.client(b).hold(KeyCode::KeyW);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.server.player(b_id).unwrap().translation.xz().length() > 6.0
+    }));
+    h.client(b).release(KeyCode::KeyW);
+
+    h.client(a).chat("/l ¿me oyes?");
+    h.client(b).chat("/g aquí estoy");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        has_line(h, c, "[Cerca] ana: ¿me oyes?") && has_line(h, a, "[Global] bruno: aquí estoy")
+    }));
+    h.step_for(10);
+    assert!(!has_line(&h, b, "[Cerca] ana: ¿me oyes?"));
+}
+
+#[test]
+fn profanity_is_censored_and_spammers_are_muted() {
+    let mut h = chat_harness();
+    let a = h.join("ana");
+    let b = h.join("bruno");
+
+    h.client(a).chat("eres un IDIOTA, idiotas no");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, b, "[Global] ana: eres un ******, idiotas no")));
+
+    // Límite de 3 mensajes por ventana de 5 s: el resto son faltas hasta el silencio
+    h.step_for(310);
+    for text in ["uno", "dos", "tres", "cuatro", "cinco", "seis"] {
+        h.client(a).chat(text);
+    }
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, a, "* Silenciado 60 s por spam")));
+    assert!(has_line(&h, a, "* Estás enviando mensajes demasiado rápido"));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, b, "[Global] ana: tres")));
+    assert!(!has_line(&h, b, "[Global] ana: cuatro"));
+
+    h.client(a).chat("ya me porto bien");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[a].chat_lines().iter().any(|line| line.starts_with("* Estás silenciado durante"))
+    }));
+
+    // El log de moderación guarda el texto original y el motivo del bloqueo
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.store.chat_log().len() == 8));
+    let log = h.server.store.chat_log();
+    assert_eq!(log[0].message, "eres un IDIOTA, idiotas no");
+    assert!(log[0].filtered);
+    let blocked: Vec<_> = log.iter().map(|entry| entry.blocked_reason.as_deref()).collect();
+    assert_eq!(
+        blocked,
+        [None, None, None, None, Some("rate_limit"), Some("rate_limit"), Some("rate_limit"), Some("muted")]
+    );
+
+    // Un moderador puede levantar el silencio
+    h.server.app.world_mut().resource_mut::<ChatModeration>().unmute("ANA");
+    h.step_for(400); // fuera de la ventana del antispam
+    h.client(a).chat("perdón");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, b, "[Global] ana: perdón")));
+}
+
+#[test]
+fn ignored_players_are_hidden_locally() {
+    let mut h = chat_harness();
+    let a = h.join("ana");
+    let b = h.join("bruno");
+
+    h.client(b).chat("/mute Ana");
+    h.client(a).chat("primero");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, a, "[Global] ana: primero")));
+    h.step_for(10);
+    h.client(b).chat("/unmute ana");
+    h.client(a).chat("segundo");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_line(h, b, "[Global] ana: segundo")));
+    assert!(!has_line(&h, b, "[Global] ana: primero"));
+    assert!(has_line(&h, b, "* Ana silenciado (solo para ti)"));
+}
+
+#[test]
+fn chat_commands_are_parsed() {
+    let say = |channel, text: &str| Ok(Some(ChatCommand::Say { channel, text: text.to_string() }));
+
+    assert_eq!(parse_chat_command("   "), Ok(None));
+    assert_eq!(parse_chat_command("hola"), say(ChatChannel::Global, "hola"));
+    assert_eq!(parse_chat_command("/g  hola "), say(ChatChannel::Global, "hola"));
+    assert_eq!(parse_chat_command("/l cerca"), say(ChatChannel::Proximity, "cerca"));
+    assert_eq!(parse_chat_command("/TEAM vamos"), say(ChatChannel::Team, "vamos"));
+    assert_eq!(
+        parse_chat_command("/w bruno hola qué tal"),
+        say(ChatChannel::Whisper { to: "bruno".to_string() }, "hola qué tal")
+    );
+    assert_eq!(parse_chat_command("/r ok"), Ok(Some(ChatCommand::Reply("ok".to_string()))));
+    assert_eq!(parse_chat_command("/clan rojo"), Ok(Some(ChatCommand::JoinTeam(Some("rojo".to_string())))));
+    assert_eq!(parse_chat_command("/clan"), Ok(Some(ChatCommand::JoinTeam(None))));
+    assert_eq!(parse_chat_command("/mute ana"), Ok(Some(ChatCommand::Mute("ana".to_string()))));
+    assert_eq!(parse_chat_command("/help"), Ok(Some(ChatCommand::Help)));
+
+    assert!(parse_chat_command("/w bruno").is_err());
+    assert!(parse_chat_command("/team").is_err());
+    assert!(parse_chat_command("/baila").is_err());
+}
+
+#[test]
+fn censor_only_matches_whole_words() {
+    let banned = vec!["idiota".to_string()];
+    assert_eq!(censor("Idiota!", &banned), ("******!".to_string(), true));
+    assert_eq!(censor("idiotas e idiotez", &banned), ("idiotas e idiotez".to_string(), false));
+    assert_eq!(censor("", &banned), (String::new(), false));
+}
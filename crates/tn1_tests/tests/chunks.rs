@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use tn1_client::chunks::{ChunkStreamingSettings, FallbackGround};
+use tn1_server::chunks::{generate_chunk, ChunkPlugin, ChunkSettings, LoadedChunks};
+use tn1_server::tls::ServerTls;
+use tn1_server::world::{WorldPlugin, WorldSettings};
+use tn1_shared::chunk::*;
+use tn1_shared::codec::WireCodec;
+use tn1_shared::protocol::*;
+use tn1_shared::terrain::TerrainGenerator;
+use tn1_tests::*;
+
+/// Mundo de 200 m en chunks de 10 m: se cruza un chunk en menos de 2 s andando
+fn chunk_harness() -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((
+            WorldPlugin,
+            ChunkPlugin { settings: ChunkSettings { view_radius: 1, keep_loaded: 0.5, send_budget: 4 } },
+        ))
+        .insert_resource(WorldSettings { world_size: 200.0, chunk_size: 10.0, seed: 7, ..default() });
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+/// Ordenados como `ChunkCoord` (x, luego z)
+fn around_spawn() -> Vec<ChunkCoord> {
+    (-1..=1).flat_map(|x| (-1..=1).map(move |z| ChunkCoord::new(x, z))).collect()
+}
+
+fn loaded(h: &Harness) -> Vec<ChunkCoord> {
+    let mut coords: Vec<_> = h.server.app.world().resource::<LoadedChunks>().coords().collect();
+    coords.sort();
+    coords
+}
+
+fn spawned(h: &Harness, client: usize) -> Vec<ChunkCoord> {
+    let mut coords: Vec<_> = h.clients[client].chunks().spawned().collect();
+    coords.sort();
+    coords
+}
+
+/// Camina hacia -Z hasta pasar `z`
+fn walk_north(h: &mut Harness, client: usize, z: f32) {
+    let player_id = h.clients[client].player_id().unwrap();
+    h.client(client).hold(KeyCode::KeyW);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.player(player_id).unwrap().translation.z < z));
+    h.client(client).release(KeyCode::KeyW);
+}
+
+#[test]
+fn grid_maps_positions_to_chunks() {
+    let grid = ChunkGrid::new(5000.0, 100.0);
+    assert_eq!(grid.chunks_per_side(), 50);
+    assert_eq!(grid.coord_range(), -25..=24);
+    assert_eq!(grid.coord_at(Vec3::new(-0.1, 3.0, 250.0)), ChunkCoord::new(-1, 2));
+    assert_eq!(grid.origin(ChunkCoord::new(-1, 2)), Vec3::new(-100.0, 0.0, 200.0));
+    assert!(grid.contains(ChunkCoord::new(24, -25)));
+    assert!(!grid.contains(ChunkCoord::new(25, 0)));
+
+    // Del más cercano al más lejano, y nunca fuera del mundo
+    let around = grid.chunks_around(Vec3::new(50.0, 0.0, 50.0), 1);
+    assert_eq!(around.len(), 9);
+    assert_eq!(around[0], ChunkCoord::new(0, 0));
+    assert_eq!(grid.chunks_around(Vec3::new(2490.0, 0.0, 2490.0), 1).len(), 4);
+    assert_eq!(ChunkCoord::new(0, 0).distance(ChunkCoord::new(-2, 1)), 2);
+}
+
+#[test]
+fn generation_depends_only_on_seed_and_coord() {
+    let grid = ChunkGrid::new(5000.0, 100.0);
+    let coord = ChunkCoord::new(3, -4);
+    let chunk = generate_chunk(&grid, 42, coord);
+    assert_eq!(chunk, generate_chunk(&grid, 42, coord));
+    assert_ne!(chunk.props, generate_chunk(&grid, 43, coord).props);
+    assert!(!chunk.props.is_empty());
+
+    let (min, max) = (grid.origin(coord), grid.origin(coord) + Vec3::splat(grid.chunk_size));
+    let inside = |position: Vec3| (min.x..max.x).contains(&position.x) && (min.z..max.z).contains(&position.z);
+    assert!(chunk.props.iter().all(|prop| inside(prop.position)));
+    assert!(chunk.containers.iter().all(|container| inside(container.position)));
+    // Todo apoyado sobre el terreno
+    let terrain = TerrainGenerator::new(42);
+    assert!(chunk.props.iter().all(|prop| prop.position.y == terrain.height(prop.position.x, prop.position.z)));
+
+    // Ids de contenedor únicos en todo el mundo y estables
+    assert_ne!(LootContainer::id_for(ChunkCoord::new(-1, 0), 0), LootContainer::id_for(ChunkCoord::new(0, -1), 0));
+    assert_ne!(LootContainer::id_for(coord, 0), LootContainer::id_for(coord, 1));
+
+    // Nada alrededor del spawn
+    for coord in around_spawn() {
+        let chunk = generate_chunk(&grid, 42, coord);
+        assert!(chunk.props.iter().all(|prop| prop.position.xz().length() > 10.0));
+    }
+}
+
+#[test]
+fn server_loads_chunks_around_players_and_unloads_them() {
+    let mut h = chunk_harness();
+    h.step_for(5);
+    assert!(loaded(&h).is_empty());
+
+    let a = h.join("ana");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| loaded(h) == around_spawn()));
+
+    walk_north(&mut h, a, -35.0);
+    let player_id = h.clients[a].player_id().unwrap();
+    let center = ChunkGrid::new(200.0, 10.0).coord_at(h.server.player(player_id).unwrap().translation);
+    // Lo que quedó atrás ya no lo necesita nadie: se descarga pasado `keep_loaded`
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        let coords = loaded(h);
+        coords.len() == 9 && coords.iter().all(|coord| coord.distance(center) <= 1)
+    }));
+    assert!(!loaded(&h).contains(&ChunkCoord::new(0, 0)));
+}
+
+#[test]
+fn client_streams_chunks_within_frame_budget() {
+    let mut h = chunk_harness();
+    let a = h.connect("ana");
+    h.client(a).app.world_mut().spawn(FallbackGround);
+    let budget = h.clients[a].app.world().resource::<ChunkStreamingSettings>().spawn_budget;
+
+    let mut previous = 0;
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        let count = h.clients[a].chunks().spawned().count();
+        assert!(count - previous <= budget, "{} chunks en un frame", count - previous);
+        previous = count;
+        count == 9
+    }));
+    assert_eq!(spawned(&h, a), around_spawn());
+    let info = h.clients[a].chunks().world().unwrap().clone();
+    assert_eq!((info.world_size, info.chunk_size, info.view_radius, info.seed), (200.0, 10.0, 1, 7));
+
+    // Los contenedores de la escena son los que generó el servidor
+    h.step_for(2);
+    let grid = info.grid();
+    let mut expected: Vec<u64> = around_spawn()
+        .into_iter()
+        .flat_map(|coord| generate_chunk(&grid, 7, coord).containers)
+        .map(|container| container.id)
+        .collect();
+    let mut shown: Vec<u64> = h.client(a).containers().iter().map(|container| container.id).collect();
+    expected.sort();
+    shown.sort();
+    assert_eq!(shown, expected);
+    let world = h.client(a).app.world_mut();
+    assert_eq!(world.query::<&FallbackGround>().iter(world).count(), 0);
+
+    // Al alejarse se construyen los nuevos y se tiran los viejos
+    walk_north(&mut h, a, -35.0);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        let coords = spawned(h, a);
+        coords.contains(&ChunkCoord::new(0, -4)) && !coords.contains(&ChunkCoord::new(0, 0))
+    }));
+    assert!(spawned(&h, a).iter().all(|coord| coord.distance(ChunkCoord::new(0, -4)) <= 2));
+}
+
+#[test]
+fn requests_outside_view_are_ignored() {
+    let mut h = chunk_harness();
+    let mut raw = RawClient::connect(h.server.address).unwrap();
+    raw.hello("curioso", vec![WireCodec::Json]).unwrap();
+    let mut received = Vec::new();
+    let mut world_info = None;
+    let mut requested = false;
+
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        for message in raw.poll().unwrap() {
+            match message {
+                ServerMessage::Welcome { .. } => raw
+                    .send(&ClientMessage::Login { username: "curioso".to_string(), password: "secret".to_string() })
+                    .unwrap(),
+                ServerMessage::WorldInfo(info) => world_info = Some(info),
+                ServerMessage::Chunk(chunk) => received.push(chunk.coord),
+                _ => {}
+            }
+        }
+        if world_info.is_some() && !requested {
+            // Uno lejano, uno fuera del mundo y el margen de un anillo sí se acepta
+            let coords = vec![ChunkCoord::new(8, 8), ChunkCoord::new(50, 0), ChunkCoord::new(2, 0), ChunkCoord::new(0, 0)];
+            raw.send(&ClientMessage::RequestChunks { coords }).unwrap();
+            requested = true;
+        }
+        received.len() >= 2
+    }));
+
+    h.step_for(30);
+    received.extend(raw.poll().unwrap().into_iter().filter_map(|message| match message {
+        ServerMessage::Chunk(chunk) => Some(chunk.coord),
+        _ => None,
+    }));
+    assert_eq!(received, vec![ChunkCoord::new(2, 0), ChunkCoord::new(0, 0)]);
+}
@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+use tn1_client::config::ClientSettings;
+use tn1_server::tls::ServerTls;
+use tn1_shared::components::PlayerController;
+use tn1_shared::conditioner::*;
+use tn1_tests::*;
+
+fn conditioner(conditions: LinkConditions) -> (LinkConditioner<u32>, Receiver<u32>, NetworkConditions) {
+    let shared = NetworkConditions::new(conditions);
+    let (sender, receiver) = mpsc::channel();
+    let link = LinkConditioner::new(shared.clone(), move |item| {
+        sender.send(item).map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    });
+    (link, receiver, shared)
+}
+
+/// Todo lo que llega antes de que la línea quede en silencio `idle`
+fn collect(receiver: &Receiver<u32>, idle: Duration) -> Vec<u32> {
+    let mut items = Vec::new();
+    while let Ok(item) = receiver.recv_timeout(idle) {
+        items.push(item);
+    }
+    items
+}
+
+#[test]
+fn perfect_link_delivers_immediately() {
+    let (link, receiver, _) = conditioner(LinkConditions::default());
+    for i in 0..10 {
+        link.send(i, 100, true).unwrap();
+        // Sin thread intermedio: ya está entregado al volver de `send`
+        assert_eq!(receiver.try_recv(), Ok(i));
+    }
+    assert_eq!(link.in_flight(), 0);
+}
+
+#[test]
+fn latency_delays_delivery() {
+    let (link, receiver, _) = conditioner(LinkConditions { latency_ms: 100, ..default() });
+    let start = Instant::now();
+    link.send(1, 100, true).unwrap();
+    assert!(receiver.try_recv().is_err());
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(2)), Ok(1));
+    assert!(start.elapsed() >= Duration::from_millis(100), "{:?}", start.elapsed());
+}
+
+#[test]
+fn loss_spares_reliable_messages() {
+    let (link, receiver, _) = conditioner(LinkConditions { loss_percent: 100.0, ..default() });
+    for i in 0..20 {
+        // Pares descartables (snapshots), impares fiables (eventos)
+        link.send(i, 100, i % 2 == 1).unwrap();
+    }
+    assert_eq!(collect(&receiver, Duration::from_millis(200)), (0..20).step_by(2).collect::<Vec<_>>());
+}
+
+#[test]
+fn duplication_delivers_twice() {
+    let (link, receiver, _) = conditioner(LinkConditions { duplicate_percent: 100.0, ..default() });
+    for i in 0..5 {
+        link.send(i, 100, true).unwrap();
+    }
+    link.send(99, 100, false).unwrap();
+
+    // La copia puede llegar después de mensajes posteriores, el original no
+    let mut received = collect(&receiver, Duration::from_millis(200));
+    let mut originals = Vec::new();
+    for item in &received {
+        if !originals.contains(item) {
+            originals.push(*item);
+        }
+    }
+    assert_eq!(originals, vec![0, 1, 2, 3, 4, 99]);
+    received.sort();
+    assert_eq!(received, vec![0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 99]);
+}
+
+#[test]
+fn jitter_keeps_order_unless_reordering() {
+    let (link, receiver, shared) = conditioner(LinkConditions { latency_ms: 20, jitter_ms: 20, ..default() });
+    for i in 0..200 {
+        link.send(i, 100, true).unwrap();
+    }
+    assert_eq!(collect(&receiver, Duration::from_millis(300)), (0..200).collect::<Vec<_>>());
+
+    // Con desorden no se pierde nada, pero algunos llegan después de los siguientes
+    shared.set(LinkConditions { latency_ms: 20, jitter_ms: 20, reorder_percent: 50.0, ..default() });
+    for i in 0..200 {
+        link.send(i, 100, true).unwrap();
+    }
+    let received = collect(&receiver, Duration::from_millis(300));
+    assert_ne!(received, (0..200).collect::<Vec<_>>());
+    let mut sorted = received.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..200).collect::<Vec<_>>());
+}
+
+#[test]
+fn bandwidth_cap_serializes_messages() {
+    // 10 mensajes de 400 bytes a 64 kbit/s: medio segundo en el cable
+    let (link, receiver, _) = conditioner(LinkConditions { bandwidth_kbps: 64, ..default() });
+    let start = Instant::now();
+    for i in 0..10 {
+        link.send(i, 400, true).unwrap();
+    }
+    assert_eq!(collect(&receiver, Duration::from_secs(1)).len(), 10);
+    let elapsed = start.elapsed() - Duration::from_secs(1);
+    assert!(elapsed >= Duration::from_millis(450), "{:?}", elapsed);
+}
+
+#[test]
+fn dropping_the_link_flushes_messages_in_flight() {
+    let (link, receiver, _) = conditioner(LinkConditions { latency_ms: 50, ..default() });
+    for i in 0..5 {
+        link.send(i, 100, false).unwrap();
+    }
+    drop(link);
+    assert_eq!(collect(&receiver, Duration::from_millis(300)), vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn closing_the_link_waits_for_messages_in_flight() {
+    let (link, receiver, _) = conditioner(LinkConditions { latency_ms: 50, ..default() });
+    for i in 0..3 {
+        link.send(i, 100, false).unwrap();
+    }
+    let (closed, after) = mpsc::channel();
+    link.close(move || closed.send(Instant::now()).unwrap());
+    assert_eq!(collect(&receiver, Duration::from_millis(300)), vec![0, 1, 2]);
+    let delivered = Instant::now();
+    assert!(after.recv_timeout(Duration::from_secs(1)).unwrap() <= delivered);
+
+    // Sin nada en vuelo se cierra al momento
+    let (link, _receiver, _) = conditioner(LinkConditions::default());
+    link.send(0, 100, false).unwrap();
+    let (closed, after) = mpsc::channel();
+    link.close(move || closed.send(()).unwrap());
+    assert_eq!(after.try_recv(), Ok(()));
+}
+
+#[test]
+fn conditions_are_parsed_from_arguments() {
+    let args: Vec<String> = ["tn1", "--latency", "120", "--jitter", "15", "--loss", "2.5", "--bandwidth", "256"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let conditions = LinkConditions::from_args(&args).unwrap();
+    assert_eq!(
+        conditions,
+        LinkConditions {
+            latency_ms: 120,
+            jitter_ms: 15,
+            loss_percent: 2.5,
+            bandwidth_kbps: 256,
+            ..default()
+        }
+    );
+    assert!(conditions.is_active());
+    assert!(!LinkConditions::from_args(&[]).unwrap().is_active());
+
+    let invalid = |args: &[&str]| LinkConditions::from_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>()).is_err();
+    assert!(invalid(&["--latency"]));
+    assert!(invalid(&["--latency", "mucho"]));
+    assert!(invalid(&["--loss", "150"]));
+}
+
+#[test]
+fn session_survives_a_bad_network() {
+    let bad = LinkConditions {
+        latency_ms: 40,
+        jitter_ms: 20,
+        loss_percent: 20.0,
+        duplicate_percent: 5.0,
+        reorder_percent: 5.0,
+        bandwidth_kbps: 0,
+    };
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.insert_resource(NetworkConditions::new(bad));
+    });
+    let mut h = Harness { server, clients: Vec::new() };
+
+    let settings = ClientSettings {
+        server_address: h.server.address.to_string(),
+        tls_enabled: false,
+        tls_pins: Vec::new(),
+    };
+    let client_conditions = NetworkConditions::new(bad);
+    let shared = client_conditions.clone();
+    h.clients.push(TestClient::connect_configured(settings, "bad_network", move |app| {
+        app.insert_resource(shared);
+    }));
+
+    // Handshake, login y Connected son fiables: llegan aunque se pierdan snapshots
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[0].is_connected()));
+    let id = h.clients[0].player_id().unwrap();
+    let start = h.server.player(id).unwrap().translation;
+
+    h.client(0).hold(KeyCode::KeyW);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.player(id).unwrap().translation.distance(start) > 2.0));
+    h.client(0).release(KeyCode::KeyW);
+
+    // Al cambiar las condiciones en caliente la réplica converge con el servidor
+    client_conditions.set(LinkConditions::default());
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.component::<PlayerController>(id).unwrap().velocity.length() < 0.01));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        let server = h.server.player(id).unwrap().translation;
+        h.clients[0].known_position(id).is_some_and(|position| position.distance(server) < 0.01)
+    }));
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use tn1_server::tls::ServerTls;
+use tn1_server::world::{WorldPlugin, WorldSettings};
+use tn1_shared::daylight::*;
+use tn1_tests::*;
+
+/// Servidor con el reloj parado a la hora dada (o corriendo con `time_scale`)
+fn daylight_harness(hour: f32, time_scale: f32) -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins(WorldPlugin).insert_resource(WorldSettings {
+            current_time: hour,
+            time_scale,
+            seed: 7,
+            ..default()
+        });
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+fn server_hour(h: &Harness) -> f32 {
+    h.server.app.world().resource::<WorldSettings>().current_time
+}
+
+/// Aleja al jugador hacia -Z hasta pasar `z`
+fn walk_away(h: &mut Harness, client: usize, z: f32) {
+    let player_id = h.clients[client].player_id().unwrap();
+    h.client(client).hold(KeyCode::KeyW);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.player(player_id).unwrap().translation.z < z));
+    h.client(client).release(KeyCode::KeyW);
+    h.step_for(30);
+}
+
+#[test]
+fn clock_drives_daylight() {
+    assert_eq!(WorldClock::new(12.0, 1.0).daylight(), 1.0);
+    assert_eq!(WorldClock::new(0.0, 1.0).darkness(), 1.0);
+    assert_eq!(WorldClock::new(DUSK.0 + (DUSK.1 - DUSK.0) / 2.0, 1.0).daylight(), 0.5);
+    assert!(WorldClock::new(6.5, 1.0).daylight() > 0.5 && !WorldClock::new(6.5, 1.0).is_night());
+    assert!(WorldClock::new(23.0, 1.0).is_night());
+
+    // 24 horas del mundo por hora real: un minuto real son 24 minutos
+    let mut clock = WorldClock::new(23.5, 24.0);
+    clock.advance(60.0);
+    assert!((clock.hour - 23.9).abs() < 1e-4);
+    clock.advance(60.0);
+    assert!(clock.hour < 1.0);
+    assert_eq!(WorldClock::new(19.5, 1.0).to_string(), "19:30");
+
+    // El sol está arriba a mediodía y bajo tierra a medianoche; la luna al revés
+    assert!(WorldClock::new(12.0, 1.0).sun_direction().y > 0.9);
+    assert!(WorldClock::new(0.0, 1.0).sun_direction().y < -0.9);
+    assert!(WorldClock::new(0.0, 1.0).moon_direction().y > 0.9);
+    assert!(WorldClock::new(6.0, 1.0).sun_direction().x > 0.9);
+}
+
+#[test]
+fn darkness_limits_how_far_players_are_spotted() {
+    let naked = Vision::default();
+    let day = spotting_range(0.0, naked, false);
+    let night = spotting_range(1.0, naked, false);
+    assert!(night < day / 10.0);
+    assert!(spotting_range(0.5, naked, false) < day && spotting_range(0.5, naked, false) > night);
+
+    // La linterna propia ayuda poco; la ajena delata desde lejos; las gafas devuelven la vista
+    let flashlight = spotting_range(1.0, Vision { flashlight: true, night_vision: false }, false);
+    let night_vision = spotting_range(1.0, Vision { flashlight: false, night_vision: true }, false);
+    let beacon = spotting_range(1.0, naked, true);
+    assert!(night < flashlight && flashlight < night_vision && night_vision < beacon);
+    // De día nada de eso cambia nada
+    assert_eq!(spotting_range(0.0, Vision { flashlight: true, night_vision: true }, true), day);
+}
+
+#[test]
+fn clients_follow_the_server_clock() {
+    let mut h = daylight_harness(19.5, 60.0);
+    let a = h.join("ana");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].clock().is_some()));
+
+    // Misma hora y misma escala: a un minuto del mundo por segundo, la deriva es mínima
+    let clock = h.clients[a].clock().unwrap();
+    assert_eq!(clock.time_scale, 60.0);
+    assert!((clock.hour - server_hour(&h)).abs() < 0.05, "{} vs {}", clock.hour, server_hour(&h));
+    assert_eq!(clock.is_night(), WorldClock::new(server_hour(&h), 60.0).is_night());
+
+    // Al cambiar la hora en el servidor, la resincronización periódica la corrige
+    h.server.app.world_mut().resource_mut::<WorldSettings>().current_time = 3.0;
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        let hour = h.clients[a].clock().unwrap().hour;
+        (hour - server_hour(h)).abs() < 0.05
+    }));
+    assert!(h.clients[a].clock().unwrap().is_night());
+}
+
+#[test]
+fn darkness_hides_players_unless_lit() {
+    let mut h = daylight_harness(0.0, 0.0);
+    let a = h.join("ana");
+    let b = h.join("bea");
+    let bea = h.clients[b].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].clock().is_some() && h.clients[a].known_position(bea).is_some()));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.client(a).sees(bea)));
+    // Una entidad por jugador aunque lleguen varios snapshots en el mismo frame
+    let world = h.client(a).app.world_mut();
+    assert_eq!(world.query::<&tn1_shared::components::PlayerId>().iter(world).count(), 2);
+
+    // A 40 m en plena noche no se distingue a nadie
+    walk_away(&mut h, b, -40.0);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| !h.client(a).sees(bea)));
+
+    // La linterna se replica: todos ven el haz y a quien la lleva
+    h.client(b).tap(KeyCode::KeyL);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.client(a).sees_flashlight(bea)));
+    assert!(h.client(a).sees(bea));
+    assert!(h.client(b).sees_flashlight(bea));
+
+    // Apagada, vuelve a la oscuridad... salvo para quien lleva visión nocturna
+    h.client(b).tap(KeyCode::KeyL);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| !h.client(a).sees_flashlight(bea) && !h.client(a).sees(bea)));
+    h.client(a).tap(KeyCode::KeyN);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.client(a).sees(bea)));
+}
+
+#[test]
+fn daylight_shows_everyone() {
+    let mut h = daylight_harness(12.0, 0.0);
+    let a = h.join("ana");
+    let b = h.join("bea");
+    let bea = h.clients[b].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].clock().is_some() && h.client(a).sees(bea)));
+    walk_away(&mut h, b, -40.0);
+    assert!(h.client(a).sees(bea));
+}
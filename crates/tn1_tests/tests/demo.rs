@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+use tn1_server::demo::*;
+use tn1_server::inventory::InventoryPlugin;
+use tn1_server::items::ItemRegistryPlugin;
+use tn1_server::tls::ServerTls;
+use tn1_shared::inventory::EquipmentSlot;
+use tn1_shared::protocol::ClientMessage;
+use tn1_tests::*;
+
+fn demo_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tn1-{}-{}.{}", name, uuid::Uuid::new_v4(), DEMO_EXTENSION))
+}
+
+/// Sesión corta con movimiento, saltos y una desconexión
+fn record_session(path: &Path) {
+    let settings = DemoRecordSettings {
+        path: Some(path.to_path_buf()),
+        keyframe_interval: 10,
+    };
+    let mut h = Harness {
+        server: TestServer::start_with(test_config(), ServerTls(None), |app| {
+            app.add_plugins(DemoRecordPlugin { settings });
+        }),
+        clients: Vec::new(),
+    };
+
+    let a = h.join("demo_a");
+    let b = h.join("demo_b");
+
+    h.client(a).set_yaw(0.7);
+    h.client(a).hold(KeyCode::KeyW);
+    h.client(b).hold(KeyCode::KeyD);
+    h.client(b).hold(KeyCode::Space);
+    h.step_for(90);
+    h.client(a).release(KeyCode::KeyW);
+    h.client(b).release(KeyCode::Space);
+    h.step_for(30);
+
+    h.client(b).disconnect();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.connected_players() == 1));
+    h.step_for(30);
+    // Al soltar el App se vacía el buffer del archivo
+}
+
+fn replay(path: &Path, stop_at: Option<u32>) -> App {
+    let mut app = build_replay_app(DemoPlaybackSettings {
+        path: path.to_path_buf(),
+        stop_at,
+    });
+
+    for _ in 0..100_000 {
+        app.update();
+        if app.world().resource::<DemoPlayback>().finished {
+            break;
+        }
+    }
+    assert!(app.world().resource::<DemoPlayback>().finished, "la reproducción no terminó");
+    app
+}
+
+#[test]
+fn recorded_session_replays_without_divergence() {
+    let path = demo_path("replay");
+    record_session(&path);
+
+    let (header, frames) = read_demo(&path).unwrap();
+    assert_eq!(header.format_version, DEMO_FORMAT_VERSION);
+    assert_eq!(header.keyframe_interval, 10);
+    assert!(frames.len() > 150);
+    assert!(frames.iter().flat_map(|frame| &frame.messages).any(|(_, message)| matches!(message, ClientMessage::Login { .. })));
+
+    // Hay keyframes con ambos jugadores y con uno solo tras la desconexión
+    let keyframe_sizes: Vec<usize> = frames.iter().filter_map(|frame| frame.keyframe.as_ref().map(Vec::len)).collect();
+    assert!(keyframe_sizes.contains(&2));
+    assert_eq!(keyframe_sizes.last(), Some(&1));
+
+    let app = replay(&path, None);
+    let playback = app.world().resource::<DemoPlayback>();
+    assert_eq!(playback.frame_count(), frames.len());
+    assert_eq!(playback.keyframes_checked as usize, keyframe_sizes.len());
+    assert!(playback.divergences.is_empty(), "divergencias: {:?}", playback.divergences.first());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn edited_demo_reports_divergence() {
+    let path = demo_path("original");
+    record_session(&path);
+
+    // Sin los inputs nadie se mueve: la re-simulación debe notarlo
+    let (header, mut frames) = read_demo(&path).unwrap();
+    for frame in &mut frames {
+        frame.messages.retain(|(_, message)| !matches!(message, ClientMessage::PlayerInput { .. }));
+    }
+    let edited = demo_path("edited");
+    write_demo(&edited, &header, &frames).unwrap();
+
+    let app = replay(&edited, None);
+    let playback = app.world().resource::<DemoPlayback>();
+    assert!(!playback.divergences.is_empty());
+    assert!(playback.divergences[0].frame > 0);
+
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(edited);
+}
+
+#[test]
+fn playback_can_stop_at_a_tick() {
+    let path = demo_path("stop");
+    record_session(&path);
+
+    let app = replay(&path, Some(50));
+    let playback = app.world().resource::<DemoPlayback>();
+    assert_eq!(playback.frame, 50);
+    assert!(playback.divergences.is_empty());
+
+    let _ = std::fs::remove_file(path);
+}
+
+/// Una jugadora con chaleco y otro sin nada corren a la vez
+fn record_loaded_session(path: &Path) {
+    let settings = DemoRecordSettings {
+        path: Some(path.to_path_buf()),
+        keyframe_interval: 10,
+    };
+    let mut h = Harness {
+        server: TestServer::start_with(test_config(), ServerTls(None), |app| {
+            app.add_plugins((ItemRegistryPlugin { settings: item_settings() }, InventoryPlugin, DemoRecordPlugin { settings }));
+        }),
+        clients: Vec::new(),
+    };
+
+    let (a, ana) = h.join_grounded("demo_loaded");
+    let (b, _) = h.join_grounded("demo_light");
+    // El chaleco no cabe en los bolsillos: primero la mochila
+    let backpack = h.give(a, ana, "backpack", 1);
+    h.equip(a, backpack.id, EquipmentSlot::Backpack);
+    let vest = h.give(a, ana, "armor_vest", 1);
+    h.equip(a, vest.id, EquipmentSlot::BodyArmor);
+
+    for client in [a, b] {
+        h.client(client).hold(KeyCode::KeyW);
+        h.client(client).hold(KeyCode::ShiftLeft);
+    }
+    h.step_for(60);
+    h.client(a).release(KeyCode::ShiftLeft);
+    h.step_for(30);
+    h.client(b).disconnect();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.connected_players() == 1));
+    h.step_for(20);
+}
+
+#[test]
+fn gear_slowdown_is_recorded_and_replays_without_divergence() {
+    let path = demo_path("loaded");
+    record_loaded_session(&path);
+
+    // Solo la del chaleco aparece frenada, y desde que se lo pone hasta el final
+    let (header, frames) = read_demo(&path).unwrap();
+    let limited: Vec<&MovementLimits> = frames.iter().flat_map(|frame| &frame.movement).collect();
+    assert!(!limited.is_empty());
+    assert!(limited.iter().all(|limits| limits.client_id == limited[0].client_id && limits.can_sprint));
+    assert!(limited.iter().all(|limits| (limits.speed_multiplier - 0.92).abs() < 1e-4), "{:?}", limited[0]);
+    let loaded = limited[0].client_id;
+    assert_eq!(frames.last().unwrap().movement.len(), 1);
+
+    let app = replay(&path, None);
+    let playback = app.world().resource::<DemoPlayback>();
+    assert!(playback.keyframes_checked > 10);
+    assert!(playback.divergences.is_empty(), "divergencias: {:?}", playback.divergences.first());
+
+    // Sin lo grabado la re-simulación la haría correr como a los demás
+    let mut frames = frames.clone();
+    for frame in &mut frames {
+        frame.movement.clear();
+    }
+    let edited = demo_path("unloaded");
+    write_demo(&edited, &header, &frames).unwrap();
+    let app = replay(&edited, None);
+    let playback = app.world().resource::<DemoPlayback>();
+    assert!(playback.divergences.iter().any(|divergence| divergence.client_id == loaded));
+
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(edited);
+}
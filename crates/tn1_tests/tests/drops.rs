@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+use tn1_server::drops::{Dead, DropSettings, DropsPlugin, WorldItem, WorldItemContents};
+use tn1_server::inventory::{InventoryPlugin, ItemDropped};
+use tn1_server::items::ItemRegistryPlugin;
+use tn1_server::tls::ServerTls;
+use tn1_shared::chunk::{PropKind, StaticProp};
+use tn1_shared::components::Health;
+use tn1_shared::drops::*;
+use tn1_shared::inventory::*;
+use tn1_shared::items::ItemDefs;
+use tn1_tests::*;
+
+fn prop(kind: PropKind, x: f32, z: f32) -> StaticProp {
+    StaticProp { kind, position: Vec3::new(x, 0.0, z), rotation: 0.0, scale: 1.0 }
+}
+
+#[test]
+fn line_of_sight_is_blocked_by_terrain_and_props() {
+    let eye = Vec3::new(0.0, EYE_HEIGHT, 0.0);
+    let item = Vec3::new(2.0, ITEM_CLEARANCE, 0.0);
+    let flat = |_: f32, _: f32| 0.0;
+    assert!(line_of_sight(eye, item, flat, &[]));
+
+    // Un montículo entre los dos tapa; uno detrás no
+    let mound = |center: f32| move |x: f32, _: f32| if (x - center).abs() < 0.4 { 1.5 } else { 0.0 };
+    assert!(!line_of_sight(eye, item, mound(1.0), &[]));
+    assert!(line_of_sight(eye, item, mound(3.0), &[]));
+
+    // Árboles y rocas tapan, los arbustos no; un tronco a un lado tampoco
+    assert!(!line_of_sight(eye, item, flat, &[prop(PropKind::Tree, 1.0, 0.1)]));
+    assert!(!line_of_sight(eye, item, flat, &[prop(PropKind::Rock, 1.5, 0.0)]));
+    assert!(line_of_sight(eye, item, flat, &[prop(PropKind::Bush, 1.0, 0.0)]));
+    assert!(line_of_sight(eye, item, flat, &[prop(PropKind::Tree, 1.0, 1.0)]));
+    // Una roca baja no tapa lo que se mira por encima
+    let high = Vec3::new(2.0, 1.5, 0.0);
+    assert!(line_of_sight(eye, high, flat, &[prop(PropKind::Rock, 1.0, 0.0)]));
+}
+
+#[test]
+fn corpses_take_pockets_equipment_and_what_is_inside() {
+    let defs = ItemDefs::load(&item_settings().path).unwrap();
+    let mut inventory = Inventory::new();
+    let mut next = 0;
+    let mut give = |inventory: &mut Inventory, key: &str| {
+        next += 1;
+        inventory.insert(&defs, ItemStack::new(ItemId(next), defs.get(key).unwrap(), 1)).unwrap();
+        ItemId(next)
+    };
+    give(&mut inventory, "bandage");
+    let helmet = give(&mut inventory, "helmet");
+    inventory.apply(&defs, &InventoryAction::Equip { item: helmet, slot: EquipmentSlot::Head }, || ItemId(100)).unwrap();
+    let pouch = give(&mut inventory, "pouch");
+    let to = Slot { container: ContainerId::Item(pouch), x: 0, y: 0, rotated: false };
+    let inner = give(&mut inventory, "canned_food");
+    inventory.apply(&defs, &InventoryAction::Move { item: inner, to }, || ItemId(100)).unwrap();
+
+    let items = corpse_items(&inventory);
+    let mut ids: Vec<ItemId> = items.iter().map(|stack| stack.id).collect();
+    ids.sort();
+    assert_eq!(ids, vec![ItemId(1), helmet, pouch]);
+    let pouch = items.iter().find(|stack| stack.id == pouch).unwrap();
+    assert_eq!(pouch.contents.as_ref().unwrap().items[0].stack.id, inner);
+}
+
+fn drops_harness(settings: DropSettings) -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((ItemRegistryPlugin { settings: item_settings() }, InventoryPlugin, DropsPlugin { settings }));
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+fn drop_settings() -> DropSettings {
+    DropSettings { reach: 2.5, view_distance: 30.0, item_lifetime: 60.0, corpse_lifetime: 60.0, max_items: 100 }
+}
+
+fn world_items(h: &mut Harness) -> Vec<(WorldItem, Vec3)> {
+    let world = h.server.app.world_mut();
+    let mut items: Vec<_> = world.query::<(&WorldItem, &Transform)>().iter(world).map(|(item, transform)| (item.clone(), transform.translation)).collect();
+    items.sort_by_key(|(item, _)| item.id);
+    items
+}
+
+fn rejected(h: &mut Harness, client: usize, sequence: u32) -> String {
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[client].inventory_rejection().is_some_and(|(seq, _)| *seq == sequence)));
+    h.clients[client].inventory_rejection().unwrap().1.clone()
+}
+
+#[test]
+fn dropped_items_settle_replicate_and_get_picked_up() {
+    let mut h = drops_harness(drop_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, _) = h.join_grounded("bea");
+    let bandage = h.give(a, ana, "bandage", 3).id;
+    let uuid = h.clients[a].inventory().unwrap().find(bandage).unwrap().stack.uuid;
+
+    // Sale lanzado, cae y se para en el suelo cerca de los pies
+    h.clients[a].inventory_action(InventoryAction::Drop { item: bandage });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| world_items(h).first().is_some_and(|(item, _)| item.resting)));
+    let (item, position) = world_items(&mut h).remove(0);
+    assert!(position.y.abs() < 1e-3 && position.xz().length() < 2.0, "{position}");
+    assert!(matches!(&item.contents, WorldItemContents::Stack(stack) if stack.uuid == uuid && stack.quantity == 3));
+
+    // Los dos lo ven ya quieto
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| (0..2).all(|c| h.clients[c].world_items().get(item.id).is_some_and(|state| state.resting))));
+    let state = h.clients[b].world_items().get(item.id).unwrap();
+    assert_eq!(state.kind, WorldItemKind::Item { item: "bandage".to_string(), quantity: 3 });
+    assert_eq!(state.position, position);
+
+    // Fuera de alcance no se coge
+    h.server.app.world_mut().resource_mut::<DropSettings>().reach = 0.5;
+    let sequence = h.clients[b].pickup(PickupAction::Item { id: item.id });
+    assert_eq!(rejected(&mut h, b, sequence), PickupError::OutOfReach.to_string());
+    h.server.app.world_mut().resource_mut::<DropSettings>().reach = 2.5;
+
+    // Bea lo recoge: pasa a su inventario y desaparece para los dos
+    h.clients[b].pickup(PickupAction::Item { id: item.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[b].inventory().unwrap().items().iter().any(|(_, placed)| placed.stack.uuid == uuid)
+    }));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| (0..2).all(|c| h.clients[c].world_items().is_empty())));
+    assert!(world_items(&mut h).is_empty());
+
+    // Ya no está; y un cadáver no se puede pedir como item
+    let sequence = h.clients[a].pickup(PickupAction::Item { id: item.id });
+    assert_eq!(rejected(&mut h, a, sequence), PickupError::Gone.to_string());
+    let sequence = h.clients[a].pickup(PickupAction::OpenCorpse { corpse: item.id });
+    assert_eq!(rejected(&mut h, a, sequence), PickupError::Gone.to_string());
+}
+
+#[test]
+fn only_nearby_clients_see_dropped_items() {
+    let mut h = drops_harness(DropSettings { view_distance: 10.0, ..drop_settings() });
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    h.teleport(bea, Vec3::new(20.0, 0.0, 20.0));
+    let bandage = h.give(a, ana, "bandage", 1).id;
+    h.clients[a].inventory_action(InventoryAction::Drop { item: bandage });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].world_items().iter().any(|state| state.resting)));
+    h.step_for(10);
+    assert!(h.clients[b].world_items().is_empty());
+
+    // Al acercarse le llega; al alejarse otra vez se le quita
+    h.teleport(bea, Vec3::new(3.0, 0.0, 0.0));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].world_items().len() == 1));
+    h.teleport(bea, Vec3::new(-20.0, 0.0, 20.0));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].world_items().is_empty()));
+    assert_eq!(h.clients[a].world_items().len(), 1);
+}
+
+#[test]
+fn dropped_items_expire_and_the_oldest_are_cleaned_up() {
+    let mut h = drops_harness(DropSettings { item_lifetime: 1.0, max_items: 2, ..drop_settings() });
+    let (a, ana) = h.join_grounded("ana");
+    let defs = h.server.app.world().resource::<ItemDefs>().clone();
+    for id in 1..=3 {
+        let stack = ItemStack::new(ItemId(1000 + id), defs.get("bandage").unwrap(), 1);
+        h.server.app.world_mut().send_event(ItemDropped { player_id: ana, position: Vec3::ZERO, stack });
+        h.step();
+    }
+    // Con tope de dos se va el primero
+    h.step();
+    let items = world_items(&mut h);
+    let kept: Vec<ItemId> = items
+        .iter()
+        .map(|(item, _)| match &item.contents {
+            WorldItemContents::Stack(stack) => stack.id,
+            WorldItemContents::Corpse { .. } => panic!("no hay cadáveres"),
+        })
+        .collect();
+    assert_eq!(kept, vec![ItemId(1002), ItemId(1003)]);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].world_items().len() == 2));
+
+    // Al segundo desaparecen también para el cliente
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| world_items(h).is_empty() && h.clients[a].world_items().is_empty()));
+
+    // Lo que ya no existe en el catálogo no llega al suelo
+    let ghost = ItemStack { item: "ghost".to_string(), ..ItemStack::new(ItemId(2000), defs.get("bandage").unwrap(), 1) };
+    h.server.app.world_mut().send_event(ItemDropped { player_id: ana, position: Vec3::ZERO, stack: ghost });
+    h.step_for(3);
+    assert!(world_items(&mut h).is_empty());
+}
+
+#[test]
+fn dead_players_leave_a_corpse_with_everything() {
+    let mut h = drops_harness(drop_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, _) = h.join_grounded("bea");
+    h.give(a, ana, "bandage", 2);
+    let helmet = h.give(a, ana, "helmet", 1).id;
+    h.clients[a].inventory_action(InventoryAction::Equip { item: helmet, slot: EquipmentSlot::Head });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().unwrap().equipment.get(EquipmentSlot::Head).is_some()));
+    let carried: Vec<uuid::Uuid> = {
+        let inventory = h.clients[a].inventory().unwrap();
+        let mut uuids: Vec<_> = corpse_items(inventory).iter().map(|stack| stack.uuid).collect();
+        uuids.sort();
+        uuids
+    };
+
+    // Muere: el cadáver se lleva todo y su inventario queda vacío
+    h.server.edit_component(ana, |health: &mut Health| health.current = 0.0);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| world_items(h).len() == 1 && h.clients[a].inventory().unwrap().items().is_empty()));
+    assert!(h.clients[a].inventory().unwrap().equipment.slots.is_empty());
+    let (corpse, _) = world_items(&mut h).remove(0);
+    let WorldItemContents::Corpse { name, items } = &corpse.contents else { panic!("{:?}", corpse.contents) };
+    assert_eq!(name, "ana");
+    let mut uuids: Vec<_> = items.iter().map(|stack| stack.uuid).collect();
+    uuids.sort();
+    assert_eq!(uuids, carried);
+    // Un solo cadáver por muerte
+    h.step_for(5);
+    assert_eq!(world_items(&mut h).len(), 1);
+
+    // Bea lo ve, lo abre y saca el casco
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].world_items().get(corpse.id).is_some()));
+    assert_eq!(h.clients[b].world_items().get(corpse.id).unwrap().kind, WorldItemKind::Corpse { name: "ana".to_string() });
+    h.clients[b].pickup(PickupAction::OpenCorpse { corpse: corpse.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].loot().is_some()));
+    assert_eq!(h.clients[b].loot().unwrap(), &(corpse.id, items.clone()));
+
+    let helmet = items.iter().find(|stack| stack.item == "helmet").unwrap().clone();
+    h.clients[b].pickup(PickupAction::TakeFromCorpse { corpse: corpse.id, item: helmet.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[b].inventory().unwrap().items().iter().any(|(_, placed)| placed.stack.uuid == helmet.uuid)
+    }));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].loot().is_some_and(|(_, left)| left.len() == items.len() - 1)));
+
+    // El cadáver no se coge entero y un item suelto no se registra
+    let sequence = h.clients[b].pickup(PickupAction::Item { id: corpse.id });
+    assert_eq!(rejected(&mut h, b, sequence), PickupError::Corpse.to_string());
+    let sequence = h.clients[b].pickup(PickupAction::TakeFromCorpse { corpse: corpse.id, item: helmet.id });
+    assert_eq!(rejected(&mut h, b, sequence), PickupError::Gone.to_string());
+
+    // Con vida otra vez deja de contar como muerto
+    h.server.edit_component(ana, |health: &mut Health| health.current = 50.0);
+    h.step_for(2);
+    let world = h.server.app.world_mut();
+    assert_eq!(world.query_filtered::<(), With<Dead>>().iter(world).count(), 0);
+}
@@ -0,0 +1,255 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use tn1_client::config::ClientSettings;
+use tn1_server::tls::{load_server_config, ServerTls, TlsSettings};
+use tn1_shared::codec::WireCodec;
+use tn1_shared::protocol::*;
+use tn1_shared::status::query_server_status;
+use tn1_shared::transport::{pinned_client_config, SecureStream};
+use tn1_tests::*;
+
+#[test]
+fn status_query_reports_server_info() {
+    let mut h = Harness::new();
+    h.join("status_a");
+
+    let info = query_server_status(h.server.address, Duration::from_secs(2), None)
+        .expect("el servidor no respondió a StatusQuery");
+
+    assert_eq!(info.server_name, "tn1-test");
+    assert_eq!(info.map_name, "test_plane");
+    assert_eq!(info.player_count, 1);
+    assert_eq!(info.max_players, 8);
+    assert_eq!(info.queue_length, 0);
+    assert!(info.accepts_protocol(PROTOCOL_VERSION));
+}
+
+#[test]
+fn unsupported_protocol_version_is_rejected() {
+    let mut h = Harness::new();
+    let mut raw = RawClient::connect(h.server.address).unwrap();
+    raw.send(&ClientMessage::Hello {
+        protocol_version: MIN_PROTOCOL_VERSION - 1,
+        client_name: "old_client".to_string(),
+        codecs: WireCodec::SUPPORTED.to_vec(),
+    })
+    .unwrap();
+
+    let mut messages = Vec::new();
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        messages.extend(raw.poll().unwrap());
+        !messages.is_empty()
+    }));
+
+    assert!(matches!(messages[0], ServerMessage::ConnectionError { .. }));
+    assert_eq!(h.server.connected_players(), 0);
+}
+
+#[test]
+fn first_message_must_be_hello() {
+    let mut h = Harness::new();
+    let mut raw = RawClient::connect(h.server.address).unwrap();
+    raw.send(&ClientMessage::Login {
+        username: "impatient".to_string(),
+        password: "secret".to_string(),
+    })
+    .unwrap();
+
+    let mut messages = Vec::new();
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        messages.extend(raw.poll().unwrap());
+        !messages.is_empty()
+    }));
+
+    assert!(matches!(messages[0], ServerMessage::ConnectionError { .. }));
+    assert_eq!(h.server.players().len(), 0);
+}
+
+#[test]
+fn codec_negotiation_switches_to_bincode() {
+    let mut h = Harness::new();
+    let mut raw = RawClient::connect(h.server.address).unwrap();
+    raw.hello("bincode_client", vec![WireCodec::Bincode]).unwrap();
+
+    let mut messages = Vec::new();
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        messages.extend(raw.poll().unwrap());
+        !messages.is_empty()
+    }));
+
+    let ServerMessage::Welcome { codec, server_info } = &messages[0] else {
+        panic!("se esperaba Welcome, llegó {:?}", messages[0]);
+    };
+    assert_eq!(*codec, WireCodec::Bincode);
+    assert_eq!(raw.codec(), WireCodec::Bincode);
+    assert_eq!(server_info.player_count, 0);
+
+    // Todo lo que sigue al Welcome viaja en bincode
+    raw.send(&ClientMessage::Login {
+        username: "bincode_client".to_string(),
+        password: "secret".to_string(),
+    })
+    .unwrap();
+
+    let mut connected = None;
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        for message in raw.poll().unwrap() {
+            if let ServerMessage::Connected { player_id, .. } = message {
+                connected = Some(player_id);
+            }
+        }
+        connected.is_some()
+    }));
+    assert!(h.server.player(connected.unwrap()).is_some());
+}
+
+#[test]
+fn tls_connection_with_pinned_certificate() {
+    let dir = std::env::temp_dir().join(format!("tn1-tls-{}", uuid::Uuid::new_v4()));
+    let settings = TlsSettings {
+        enabled: true,
+        cert_path: dir.join("server_cert.pem"),
+        key_path: dir.join("server_key.pem"),
+    };
+    let tls_config = load_server_config(&settings).expect("no se pudo generar el certificado de pruebas");
+    let pin = std::fs::read_to_string(dir.join("server_cert.sha256")).unwrap();
+
+    let mut h = Harness {
+        server: TestServer::start_with_tls(test_config(), ServerTls(Some(tls_config))),
+        clients: Vec::new(),
+    };
+
+    let client_settings = |pin: &str| ClientSettings {
+        server_address: h.server.address.to_string(),
+        tls_enabled: true,
+        tls_pins: vec![pin.trim().to_string()],
+    };
+    let trusted = client_settings(&pin);
+    let wrong_pin = client_settings(&"00".repeat(32));
+
+    h.clients.push(TestClient::connect_with(trusted, "tls_ok"));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[0].is_connected()));
+
+    // Un certificado que no coincide con el pin corta la conexión antes de enviar credenciales
+    h.clients.push(TestClient::connect_with(wrong_pin, "tls_mitm"));
+    h.step_for(30);
+    assert!(!h.clients[1].is_connected());
+    assert!(h.clients[1].network().stream.is_none());
+    assert_eq!(h.server.connected_players(), 1);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/// Byte `offset` del flujo de prueba, para detectar huecos y repeticiones
+fn pattern(offset: usize) -> u8 {
+    (offset % 251) as u8
+}
+
+/// Cliente y servidor TLS conectados por loopback, y el directorio del certificado
+fn tls_pair() -> (SecureStream, SecureStream, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("tn1-tls-{}", uuid::Uuid::new_v4()));
+    let settings = TlsSettings {
+        enabled: true,
+        cert_path: dir.join("server_cert.pem"),
+        key_path: dir.join("server_key.pem"),
+    };
+    let tls_config = load_server_config(&settings).expect("no se pudo generar el certificado de pruebas");
+    let pin = std::fs::read_to_string(dir.join("server_cert.sha256")).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let accepting = thread::spawn(move || SecureStream::accept(listener.accept().unwrap().0, tls_config).unwrap());
+    let socket = TcpStream::connect(address).unwrap();
+    let client = SecureStream::connect(socket, pinned_client_config(vec![pin.trim().to_string()])).unwrap();
+    (client, accepting.join().unwrap(), dir)
+}
+
+/// Lee el flujo de prueba hasta que el otro extremo cierra y devuelve cuánto llegó
+fn read_pattern(mut stream: SecureStream) -> usize {
+    let mut received = 0;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = stream.read(&mut buf).unwrap();
+        if n == 0 {
+            return received;
+        }
+        assert!(buf[..n].iter().enumerate().all(|(i, byte)| *byte == pattern(received + i)), "bytes repetidos o perdidos tras {received}");
+        received += n;
+    }
+}
+
+#[test]
+fn tls_writes_to_a_full_socket_are_not_sent_twice() {
+    let (mut client, server, dir) = tls_pair();
+
+    // Nadie lee al otro lado: el socket se llena y los registros se quedan a
+    // medias, pero lo que la sesión aceptó cuenta como escrito
+    client.socket().set_write_timeout(Some(Duration::from_millis(20))).unwrap();
+    let mut sent = 0;
+    while sent < 32 << 20 {
+        let chunk: Vec<u8> = (sent..sent + 16 * 1024).map(pattern).collect();
+        let n = client.write(&chunk).expect("lo ya cifrado no debe devolverse como error");
+        if n == 0 {
+            break;
+        }
+        sent += n;
+    }
+
+    let reading = thread::spawn(move || read_pattern(server));
+    loop {
+        match client.flush() {
+            Ok(()) => break,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => panic!("{e}"),
+        }
+    }
+    client.socket().set_write_timeout(None).unwrap();
+    client.shutdown(Shutdown::Write).unwrap();
+    assert_eq!(reading.join().unwrap(), sent);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn tls_peers_writing_to_each_other_do_not_deadlock() {
+    const LENGTH: usize = 8 << 20;
+    let (client, server, dir) = tls_pair();
+
+    // Los dos escriben más de lo que cabe en los sockets mientras sus lectores
+    // van vaciando lo que les llega: escribir no puede dejar sin lock al lector
+    let (done, finished) = std::sync::mpsc::channel();
+    for stream in [client, server] {
+        let reader = stream.try_clone().unwrap();
+        let done = done.clone();
+        thread::spawn(move || done.send(read_pattern(reader)).unwrap());
+        thread::spawn(move || {
+            let mut stream = stream;
+            let data: Vec<u8> = (0..LENGTH).map(pattern).collect();
+            stream.write_all(&data).unwrap();
+            stream.flush().unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+        });
+    }
+    for _ in 0..2 {
+        let received = finished.recv_timeout(Duration::from_secs(30)).expect("los extremos se bloquearon escribiendo");
+        assert_eq!(received, LENGTH);
+    }
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn tls_write_errors_are_reported() {
+    let (mut client, server, dir) = tls_pair();
+    drop(server);
+
+    // Con el otro extremo cerrado el socket acaba fallando y el error llega a quien escribe
+    let chunk = vec![0u8; 16 * 1024];
+    let error = (0..1000).find_map(|_| client.write_all(&chunk).err()).expect("escribir a un socket cerrado no dio error");
+    assert!(matches!(error.kind(), ErrorKind::BrokenPipe | ErrorKind::ConnectionReset), "{error}");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
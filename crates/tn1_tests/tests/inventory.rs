@@ -0,0 +1,788 @@
+use bevy::prelude::*;
+use tn1_server::database::{InMemoryStore, ItemPlacement, ItemRecord, PlayerStore};
+use tn1_server::inventory::{inventory_records, restore_inventory, GiveItem, InventoryPlugin, ItemIds, LoadInventory};
+use tn1_server::items::ItemRegistryPlugin;
+use tn1_server::tls::ServerTls;
+use tn1_shared::chunk::WorldRng;
+use tn1_shared::components::{BodyZone, PlayerController, PlayerId};
+use tn1_shared::items::ArmorStats;
+use tn1_shared::inventory::*;
+use tn1_tests::*;
+
+fn def(key: &str, (width, height): (u8, u8), weight: f32, max_stack: u32) -> ItemDef {
+    ItemDef {
+        key: key.to_string(),
+        name: key.to_string(),
+        width,
+        height,
+        weight,
+        max_stack,
+        rotatable: width != height,
+        ..default()
+    }
+}
+
+fn container(key: &str, size: (u8, u8), grid: (u8, u8), max_weight: f32) -> ItemDef {
+    ItemDef {
+        grid: Some(GridSpec { width: grid.0, height: grid.1, max_weight: Some(max_weight) }),
+        ..def(key, size, 0.5, 1)
+    }
+}
+
+fn defs() -> ItemDefs {
+    ItemDefs::new([
+        def("bandage", (1, 1), 0.05, 5),
+        def("pistol", (2, 1), 0.9, 1),
+        def("water", (1, 2), 0.6, 1),
+        def("brick", (1, 1), 2.0, 1),
+        def("rifle", (5, 2), 3.6, 1),
+        container("pouch", (1, 2), (2, 2), 3.0),
+        ItemDef { slot: Some(EquipmentSlot::Backpack), ..container("backpack", (2, 2), (5, 5), 30.0) },
+        ItemDef { slot: Some(EquipmentSlot::Rig), carry_bonus: 10.0, ..container("rig", (2, 2), (3, 2), 10.0) },
+        ItemDef {
+            slot: Some(EquipmentSlot::Head),
+            armor: Some(ArmorStats { class: 3, coverage: vec![BodyZone::Head] }),
+            speed_penalty: 0.1,
+            ..def("helmet", (2, 2), 1.5, 1)
+        },
+    ])
+}
+
+/// Ids como los reparte el servidor
+struct Ids(u64);
+
+impl Ids {
+    fn next(&mut self) -> ItemId {
+        self.0 += 1;
+        ItemId(self.0)
+    }
+}
+
+fn give(inventory: &mut Inventory, defs: &ItemDefs, ids: &mut Ids, key: &str, quantity: u32) -> ItemId {
+    let id = ids.next();
+    let diffs = inventory.insert(defs, ItemStack::new(id, defs.get(key).unwrap(), quantity)).unwrap();
+    match diffs.last() {
+        Some(InventoryDiff::Placed { item, .. }) => item.stack.id,
+        // Todo entró en montones que ya había
+        _ => id,
+    }
+}
+
+fn slot(container: ContainerId, x: u8, y: u8, rotated: bool) -> Slot {
+    Slot { container, x, y, rotated }
+}
+
+fn act(inventory: &mut Inventory, defs: &ItemDefs, ids: &mut Ids, action: InventoryAction) -> Result<InventoryOutcome, InventoryError> {
+    inventory.apply(defs, &action, || ids.next())
+}
+
+/// Ningún item se sale de su rejilla ni pisa a otro
+fn assert_consistent(inventory: &Inventory, defs: &ItemDefs) {
+    for id in inventory.containers() {
+        let grid = inventory.container(id).unwrap();
+        for placed in &grid.items {
+            let size = defs.get(&placed.stack.item).unwrap().size(placed.rotated);
+            assert_eq!(grid.fits(defs, placed.x, placed.y, size, Some(placed.stack.id)), Ok(()), "{id}: {placed:?}");
+        }
+        if let Some(max) = grid.max_weight {
+            assert!(grid.weight(defs) <= max + 1e-4, "{id} pesa demasiado");
+        }
+    }
+    for id in inventory.containers().into_iter().filter(|id| matches!(id, ContainerId::Item(_))) {
+        assert!(inventory.depth(id) <= MAX_NESTING, "{id} demasiado hondo");
+    }
+}
+
+#[test]
+fn placement_bounds_overlap_and_rotation() {
+    let defs = defs();
+    let mut ids = Ids(0);
+    let mut inventory = Inventory::new();
+    assert_eq!(inventory.pockets.len(), POCKET_COUNT as usize);
+
+    // La pistola entra derecha arriba del primer bolsillo; el agua ya no cabe
+    // derecha debajo y entra girada
+    let pistol = give(&mut inventory, &defs, &mut ids, "pistol", 1);
+    let water = give(&mut inventory, &defs, &mut ids, "water", 1);
+    assert_eq!(inventory.parent(pistol), Some(ContainerId::Pocket(0)));
+    let placed = inventory.find(water).unwrap();
+    assert_eq!((placed.x, placed.y, placed.rotated), (0, 1, true));
+    assert_eq!(inventory.parent(water), Some(ContainerId::Pocket(0)));
+
+    let before = inventory.clone();
+    let pocket = ContainerId::Pocket(1);
+    let moved = |to| InventoryAction::Move { item: pistol, to };
+    assert_eq!(act(&mut inventory, &defs, &mut ids, moved(slot(pocket, 1, 0, false))), Err(InventoryError::OutOfBounds));
+    assert_eq!(act(&mut inventory, &defs, &mut ids, moved(slot(pocket, 0, 2, false))), Err(InventoryError::OutOfBounds));
+    assert_eq!(
+        act(&mut inventory, &defs, &mut ids, moved(slot(ContainerId::Pocket(9), 0, 0, false))),
+        Err(InventoryError::UnknownContainer(ContainerId::Pocket(9)))
+    );
+    assert_eq!(
+        act(&mut inventory, &defs, &mut ids, InventoryAction::Move { item: ItemId(99), to: slot(pocket, 0, 0, false) }),
+        Err(InventoryError::UnknownItem(ItemId(99)))
+    );
+    // Moverse sobre su propio sitio no es pisarse a sí mismo, pero al agua sí
+    assert_eq!(
+        act(&mut inventory, &defs, &mut ids, moved(slot(ContainerId::Pocket(0), 0, 1, false))),
+        Err(InventoryError::Overlaps(water))
+    );
+    assert_eq!(inventory, before);
+
+    // Girada cabe en la columna derecha del bolsillo
+    let outcome = act(&mut inventory, &defs, &mut ids, moved(slot(pocket, 1, 0, true))).unwrap();
+    let placed = inventory.find(pistol).unwrap().clone();
+    assert_eq!((placed.x, placed.y, placed.rotated), (1, 0, true));
+    assert_eq!(outcome.diffs, vec![InventoryDiff::Placed { container: pocket, item: placed }]);
+    assert_eq!(inventory.container(ContainerId::Pocket(0)).unwrap().items.len(), 1);
+
+    // Lo cuadrado no se gira
+    let bandage = give(&mut inventory, &defs, &mut ids, "bandage", 1);
+    assert_eq!(
+        act(&mut inventory, &defs, &mut ids, InventoryAction::Move { item: bandage, to: slot(pocket, 0, 0, true) }),
+        Err(InventoryError::NotRotatable)
+    );
+    assert_eq!(Container::new(2, 2).fits(&defs, 1, 1, (1, 1), None), Ok(()));
+    assert_eq!(Container::new(2, 2).fits(&defs, 255, 0, (2, 1), None), Err(InventoryError::OutOfBounds));
+
+    // El fusil no cabe en ningún bolsillo
+    let before = inventory.clone();
+    let rifle = ItemStack::new(ids.next(), defs.get("rifle").unwrap(), 1);
+    assert_eq!(inventory.insert(&defs, rifle), Err(InventoryError::NoSpace));
+    assert_eq!(inventory, before);
+    assert_consistent(&inventory, &defs);
+}
+
+#[test]
+fn stacks_split_and_merge() {
+    let defs = defs();
+    let mut ids = Ids(0);
+    let mut inventory = Inventory::new();
+
+    let a = give(&mut inventory, &defs, &mut ids, "bandage", 3);
+    // Primero se completa el montón que hay y el resto va a un hueco nuevo
+    let diffs = inventory.insert(&defs, ItemStack::new(ids.next(), defs.get("bandage").unwrap(), 4)).unwrap();
+    assert_eq!(diffs[0], InventoryDiff::Quantity { item: a, quantity: 5 });
+    let InventoryDiff::Placed { item: ref b, .. } = diffs[1] else { panic!("{diffs:?}") };
+    let b = b.stack.id;
+    assert_eq!(inventory.find(b).unwrap().stack.quantity, 2);
+    assert_eq!(
+        inventory.insert(&defs, ItemStack::new(ids.next(), defs.get("bandage").unwrap(), 6)),
+        Err(InventoryError::InvalidQuantity(6))
+    );
+
+    let to = slot(ContainerId::Pocket(1), 0, 0, false);
+    let split = |quantity| InventoryAction::Split { item: a, quantity, to };
+    assert_eq!(act(&mut inventory, &defs, &mut ids, split(0)), Err(InventoryError::InvalidQuantity(0)));
+    assert_eq!(act(&mut inventory, &defs, &mut ids, split(5)), Err(InventoryError::InvalidQuantity(5)));
+    let outcome = act(&mut inventory, &defs, &mut ids, split(2)).unwrap();
+    let InventoryDiff::Placed { item: ref c, .. } = outcome.diffs[1] else { panic!("{outcome:?}") };
+    let c = c.stack.id;
+    assert_eq!(outcome.diffs[0], InventoryDiff::Quantity { item: a, quantity: 3 });
+    assert_eq!(inventory.find(c).unwrap().stack.quantity, 2);
+    assert_eq!(inventory.parent(c), Some(ContainerId::Pocket(1)));
+    // El hueco ya está ocupado por el montón nuevo
+    assert_eq!(act(&mut inventory, &defs, &mut ids, split(1)), Err(InventoryError::Overlaps(c)));
+
+    let pistol = give(&mut inventory, &defs, &mut ids, "pistol", 1);
+    assert_eq!(
+        act(&mut inventory, &defs, &mut ids, InventoryAction::Split { item: pistol, quantity: 1, to }),
+        Err(InventoryError::NotStackable)
+    );
+
+    // Se funde entero: el origen desaparece
+    let merge = |item, into| InventoryAction::Merge { item, into };
+    let outcome = act(&mut inventory, &defs, &mut ids, merge(c, b)).unwrap();
+    assert_eq!(
+        outcome.diffs,
+        vec![InventoryDiff::Removed { item: c }, InventoryDiff::Quantity { item: b, quantity: 4 }]
+    );
+    assert!(inventory.find(c).is_none());
+    // Solo pasa lo que cabe
+    act(&mut inventory, &defs, &mut ids, merge(a, b)).unwrap();
+    assert_eq!(inventory.find(a).unwrap().stack.quantity, 2);
+    assert_eq!(inventory.find(b).unwrap().stack.quantity, 5);
+
+    assert_eq!(act(&mut inventory, &defs, &mut ids, merge(a, b)), Err(InventoryError::StackFull));
+    assert_eq!(act(&mut inventory, &defs, &mut ids, merge(a, a)), Err(InventoryError::IntoItself));
+    assert_eq!(act(&mut inventory, &defs, &mut ids, merge(a, pistol)), Err(InventoryError::DifferentItems));
+    let other = give(&mut inventory, &defs, &mut ids, "pistol", 1);
+    assert_eq!(act(&mut inventory, &defs, &mut ids, merge(other, pistol)), Err(InventoryError::NotStackable));
+    assert!((inventory.weight(&defs) - (7.0 * 0.05 + 2.0 * 0.9)).abs() < 1e-4);
+    assert_consistent(&inventory, &defs);
+}
+
+#[test]
+fn nested_containers() {
+    let defs = defs();
+    let mut ids = Ids(0);
+    let mut inventory = Inventory::new();
+
+    // La mochila llena el primer bolsillo; lo siguiente entra en ella
+    let backpack = give(&mut inventory, &defs, &mut ids, "backpack", 1);
+    let pouch = give(&mut inventory, &defs, &mut ids, "pouch", 1);
+    let rifle = give(&mut inventory, &defs, &mut ids, "rifle", 1);
+    assert_eq!(inventory.parent(pouch), Some(ContainerId::Item(backpack)));
+    assert_eq!(inventory.parent(rifle), Some(ContainerId::Item(backpack)));
+    assert_eq!(inventory.depth(ContainerId::Item(backpack)), 1);
+    assert_eq!(inventory.depth(ContainerId::Item(pouch)), 2);
+    assert_eq!(
+        inventory.containers(),
+        vec![
+            ContainerId::Pocket(0),
+            ContainerId::Item(backpack),
+            ContainerId::Item(pouch),
+            ContainerId::Pocket(1),
+            ContainerId::Pocket(2),
+            ContainerId::Pocket(3),
+        ]
+    );
+
+    let before = inventory.clone();
+    let into = |item, container| InventoryAction::Move { item, to: slot(container, 0, 0, false) };
+    assert_eq!(
+        act(&mut inventory, &defs, &mut ids, into(backpack, ContainerId::Item(backpack))),
+        Err(InventoryError::IntoItself)
+    );
+    assert_eq!(
+        act(&mut inventory, &defs, &mut ids, into(backpack, ContainerId::Item(pouch))),
+        Err(InventoryError::IntoItself)
+    );
+    // Una riñonera dentro de otra que ya está en la mochila sería un tercer nivel
+    let spare = give(&mut inventory, &defs, &mut ids, "pouch", 1);
+    assert_eq!(inventory.parent(spare), Some(ContainerId::Item(backpack)));
+    assert_eq!(act(&mut inventory, &defs, &mut ids, into(spare, ContainerId::Item(pouch))), Err(InventoryError::TooDeep));
+    act(&mut inventory, &defs, &mut ids, InventoryAction::Drop { item: spare }).unwrap();
+    assert_eq!(inventory, before);
+
+    // Sacada a un bolsillo, la riñonera sí acepta cosas
+    act(&mut inventory, &defs, &mut ids, into(pouch, ContainerId::Pocket(1))).unwrap();
+    assert_eq!(inventory.depth(ContainerId::Item(pouch)), 1);
+    let bandage = give(&mut inventory, &defs, &mut ids, "bandage", 5);
+    act(&mut inventory, &defs, &mut ids, into(bandage, ContainerId::Item(pouch))).unwrap();
+    // Pero la mochila con la riñonera dentro ya no puede entrar en otra mochila
+    act(&mut inventory, &defs, &mut ids, into(pouch, ContainerId::Item(backpack))).unwrap();
+    let second = give(&mut inventory, &defs, &mut ids, "backpack", 1);
+    act(&mut inventory, &defs, &mut ids, into(second, ContainerId::Pocket(1))).unwrap();
+    assert_eq!(act(&mut inventory, &defs, &mut ids, into(backpack, ContainerId::Item(second))), Err(InventoryError::TooDeep));
+
+    // Tirar la mochila se lleva todo lo de dentro
+    let outcome = act(&mut inventory, &defs, &mut ids, InventoryAction::Drop { item: backpack }).unwrap();
+    let dropped = outcome.dropped.unwrap();
+    assert_eq!(outcome.diffs, vec![InventoryDiff::Removed { item: backpack }]);
+    assert_eq!(dropped.contents.as_ref().unwrap().items.len(), 2);
+    assert!((dropped.weight(&defs) - (0.5 + 3.6 + 0.5 + 5.0 * 0.05)).abs() < 1e-4);
+    for item in [backpack, pouch, rifle, bandage] {
+        assert!(inventory.find(item).is_none());
+    }
+    assert_eq!(inventory.items().len(), 1);
+    assert_consistent(&inventory, &defs);
+}
+
+#[test]
+fn weight_limits_are_atomic() {
+    let defs = defs();
+    let mut ids = Ids(0);
+    let mut inventory = Inventory::new();
+
+    let pouch = give(&mut inventory, &defs, &mut ids, "pouch", 1);
+    let first = give(&mut inventory, &defs, &mut ids, "brick", 1);
+    let second = give(&mut inventory, &defs, &mut ids, "brick", 1);
+    let into = |item, x| InventoryAction::Move { item, to: slot(ContainerId::Item(pouch), x, 0, false) };
+    act(&mut inventory, &defs, &mut ids, into(first, 0)).unwrap();
+    let before = inventory.clone();
+    assert_eq!(act(&mut inventory, &defs, &mut ids, into(second, 1)), Err(InventoryError::Overweight));
+    assert_eq!(inventory, before);
+
+    // Tampoco entra por `insert` si el único hueco que queda es la riñonera
+    let mut full = Inventory::new();
+    let pouch = give(&mut full, &defs, &mut ids, "pouch", 1);
+    let brick = give(&mut full, &defs, &mut ids, "brick", 1);
+    full.apply(&defs, &InventoryAction::Move { item: brick, to: slot(ContainerId::Item(pouch), 0, 0, false) }, || ids.next())
+        .unwrap();
+    while full.insert(&defs, ItemStack::new(ids.next(), defs.get("bandage").unwrap(), 5)).is_ok() {}
+    let inside: Vec<ItemId> = full.container(ContainerId::Item(pouch)).unwrap().items.iter().map(|placed| placed.stack.id).collect();
+    for item in inside.into_iter().filter(|item| *item != brick) {
+        full.apply(&defs, &InventoryAction::Drop { item }, || ids.next()).unwrap();
+    }
+    let before = full.clone();
+    let diffs = full.insert(&defs, ItemStack::new(ids.next(), defs.get("brick").unwrap(), 1));
+    assert_eq!(diffs, Err(InventoryError::Overweight));
+    assert_eq!(full, before);
+    assert_consistent(&full, &defs);
+}
+
+/// El cliente que aplica los diffs del servidor acaba con el mismo inventario,
+/// y ninguna secuencia de acciones (válidas o no) rompe las reglas
+#[test]
+fn diffs_converge() {
+    let defs = defs();
+    let mut keys: Vec<String> = defs.iter().map(|def| def.key.clone()).collect();
+    keys.sort();
+    let mut ids = Ids(0);
+    let mut rng = WorldRng::new(41);
+    let mut server = Inventory::new();
+    let mut client = server.clone();
+    let (mut accepted, mut rejected) = (0, 0);
+
+    for _ in 0..3000 {
+        let mut pick = |len: usize| ((rng.next_f32() * len as f32) as usize).min(len - 1);
+        let items: Vec<ItemId> = server.items().iter().map(|(_, placed)| placed.stack.id).collect();
+        let containers = server.containers();
+        let to = Slot {
+            container: containers[pick(containers.len())],
+            x: pick(5) as u8,
+            y: pick(5) as u8,
+            rotated: pick(2) == 1,
+        };
+        let kind = pick(10);
+        if items.is_empty() || kind < 3 {
+            let key = &keys[pick(keys.len())];
+            let def = defs.get(key).unwrap();
+            let quantity = 1 + pick(def.max_stack as usize) as u32;
+            if let Ok(diffs) = server.insert(&defs, ItemStack::new(ids.next(), def, quantity)) {
+                diffs.into_iter().for_each(|diff| client.apply_diff(diff));
+            }
+        } else {
+            let item = items[pick(items.len())];
+            let action = match kind {
+                3..=5 => InventoryAction::Move { item, to },
+                6 => InventoryAction::Split { item, quantity: 1 + pick(4) as u32, to },
+                7 | 8 => InventoryAction::Merge { item, into: items[pick(items.len())] },
+                _ => InventoryAction::Drop { item },
+            };
+            match server.apply(&defs, &action, || ids.next()) {
+                Ok(outcome) => {
+                    accepted += 1;
+                    outcome.diffs.into_iter().for_each(|diff| client.apply_diff(diff));
+                }
+                Err(_) => rejected += 1,
+            }
+        }
+        assert_eq!(client, server);
+        assert_consistent(&server, &defs);
+    }
+    assert!(accepted > 300 && rejected > 300, "{accepted} aceptadas, {rejected} rechazadas");
+}
+
+fn inventory_harness() -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((ItemRegistryPlugin { settings: item_settings() }, InventoryPlugin))
+            .init_resource::<Dropped>()
+            .add_systems(Update, collect_dropped);
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+fn find_item(h: &Harness, client: usize, key: &str) -> Option<ItemId> {
+    let inventory = h.clients[client].inventory()?;
+    inventory.items().iter().find(|(_, placed)| placed.stack.item == key).map(|(_, placed)| placed.stack.id)
+}
+
+#[test]
+fn server_validates_client_actions() {
+    let mut h = inventory_harness();
+    let a = h.join("ana");
+    let player_id = h.clients[a].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some()));
+    assert!(h.clients[a].inventory().unwrap().items().is_empty());
+
+    for (item, quantity) in [("bandage", 4), ("pistol", 1), ("no_such_item", 1)] {
+        h.server.app.world_mut().send_event(GiveItem { player_id, item: item.to_string(), quantity });
+    }
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().unwrap().items().len() == 2));
+    let bandage = find_item(&h, a, "bandage").unwrap();
+    let pistol = find_item(&h, a, "pistol").unwrap();
+
+    // Separar dos vendas a otro bolsillo
+    let to = Slot { container: ContainerId::Pocket(2), x: 1, y: 1, rotated: false };
+    h.client(a).inventory_action(InventoryAction::Split { item: bandage, quantity: 2, to });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().unwrap().items().len() == 3));
+    assert_eq!(h.clients[a].inventory().unwrap().find(bandage).unwrap().stack.quantity, 2);
+    assert_eq!(h.clients[a].inventory().unwrap().container(ContainerId::Pocket(2)).unwrap().items[0].stack.quantity, 2);
+
+    // El servidor rechaza lo imposible y el cliente no cambia nada
+    let before = h.clients[a].inventory().unwrap().clone();
+    let bad = h.client(a).inventory_action(InventoryAction::Move { item: pistol, to });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory_rejection().is_some()));
+    let (sequence, reason) = h.clients[a].inventory_rejection().unwrap().clone();
+    assert_eq!(sequence, bad);
+    assert_eq!(reason, InventoryError::OutOfBounds.to_string());
+    assert!(h.client(a).chat_lines().iter().any(|line| line.contains(&reason)));
+    assert_eq!(h.clients[a].inventory().unwrap(), &before);
+
+    // Tirar la pistola la saca del inventario y la deja donde está el jugador
+    h.client(a).inventory_action(InventoryAction::Drop { item: pistol });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().unwrap().find(pistol).is_none()));
+    let position = h.server.player(player_id).unwrap().translation;
+    let dropped = &h.server.app.world().resource::<Dropped>().0;
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].player_id, player_id);
+    assert_eq!(dropped[0].stack.id, pistol);
+    assert!(dropped[0].position.xz().distance(position.xz()) < 1.0);
+
+    assert_eq!(&h.server.component::<Inventory>(player_id).unwrap(), h.clients[a].inventory().unwrap());
+}
+
+#[test]
+fn inventories_are_private() {
+    let mut h = inventory_harness();
+    let a = h.join("ana");
+    let b = h.join("bea");
+    let ana = h.clients[a].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some() && h.clients[b].inventory().is_some()));
+
+    h.server.app.world_mut().send_event(GiveItem { player_id: ana, item: "rifle".to_string(), quantity: 1 });
+    h.server.app.world_mut().send_event(GiveItem { player_id: ana, item: "backpack".to_string(), quantity: 1 });
+    h.server.app.world_mut().send_event(GiveItem { player_id: ana, item: "rifle".to_string(), quantity: 1 });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| find_item(h, a, "rifle").is_some()));
+    // El primer fusil no cabía en ningún bolsillo; el segundo entra en la mochila
+    let rifle = find_item(&h, a, "rifle").unwrap();
+    let backpack = find_item(&h, a, "backpack").unwrap();
+    assert_eq!(h.clients[a].inventory().unwrap().parent(rifle), Some(ContainerId::Item(backpack)));
+    assert_eq!(h.clients[a].inventory().unwrap().items().len(), 2);
+
+    // Bea no puede tocar lo de Ana
+    h.client(b).inventory_action(InventoryAction::Drop { item: rifle });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].inventory_rejection().is_some()));
+    assert!(h.clients[b].inventory().unwrap().items().is_empty());
+    h.step_for(10);
+    assert!(h.clients[a].inventory().unwrap().find(rifle).is_some());
+    assert_eq!(&h.server.component::<Inventory>(ana).unwrap(), h.clients[a].inventory().unwrap());
+}
+
+/// Filas en un orden fijo, para comparar inventarios sin mirar los `ItemId`
+fn sorted_records(inventory: &Inventory) -> Vec<ItemRecord> {
+    let mut records = inventory_records(inventory);
+    records.sort_by_key(|record| record.id);
+    records
+}
+
+fn record(key: &str, quantity: u32, placement: ItemPlacement, (x, y): (u8, u8)) -> ItemRecord {
+    ItemRecord {
+        id: uuid::Uuid::new_v4(),
+        item: key.to_string(),
+        quantity,
+        durability: None,
+        ammo: None,
+        placement,
+        x,
+        y,
+        rotated: false,
+    }
+}
+
+#[test]
+fn equipped_items_can_swap_slots_between_saves() {
+    let store = InMemoryStore::new();
+    let player = uuid::Uuid::new_v4();
+    let equipped = |slot: EquipmentSlot| ItemPlacement::Equipment(slot.key().to_string());
+    let helmet = record("helmet", 1, equipped(EquipmentSlot::Head), (0, 0));
+    let backpack = record("backpack", 1, equipped(EquipmentSlot::Backpack), (0, 0));
+    store.save_player_state(player, Vec3::ZERO, Quat::IDENTITY, Some(&[helmet.clone(), backpack.clone()])).unwrap();
+
+    // El casco pasa a la mochila y se equipa otro, en el orden de `inventory_records`:
+    // el casco nuevo llega a "head" antes de que el viejo lo deje libre
+    let stored_helmet = ItemRecord { placement: ItemPlacement::Container(backpack.id), ..helmet.clone() };
+    let new_helmet = record("helmet", 1, equipped(EquipmentSlot::Head), (0, 0));
+    let items = vec![new_helmet.clone(), backpack.clone(), stored_helmet.clone()];
+    let mut expected = items.clone();
+    expected.sort_by_key(|record| record.id);
+    for _ in 0..2 {
+        store.save_player_state(player, Vec3::ZERO, Quat::IDENTITY, Some(&items)).unwrap();
+        let mut saved = store.load_inventory(player).unwrap();
+        saved.sort_by_key(|record| record.id);
+        assert_eq!(saved, expected);
+    }
+
+    // Dos items en el mismo slot al terminar no se guardan, ni a medias
+    let clash = vec![new_helmet, backpack, ItemRecord { placement: equipped(EquipmentSlot::Head), ..stored_helmet }];
+    assert!(store.save_player_state(player, Vec3::ZERO, Quat::IDENTITY, Some(&clash)).is_err());
+    let mut saved = store.load_inventory(player).unwrap();
+    saved.sort_by_key(|record| record.id);
+    assert_eq!(saved, expected);
+}
+
+#[test]
+fn saved_records_rebuild_the_inventory() {
+    let defs = defs();
+    let mut ids = Ids(0);
+    let mut inventory = Inventory::new();
+    let backpack = give(&mut inventory, &defs, &mut ids, "backpack", 1);
+    let pouch = give(&mut inventory, &defs, &mut ids, "pouch", 1);
+    let rifle = give(&mut inventory, &defs, &mut ids, "rifle", 1);
+    give(&mut inventory, &defs, &mut ids, "bandage", 3);
+    act(&mut inventory, &defs, &mut ids, InventoryAction::Move { item: pouch, to: slot(ContainerId::Item(backpack), 4, 0, false) }).unwrap();
+    let bandage = give(&mut inventory, &defs, &mut ids, "bandage", 5);
+    act(&mut inventory, &defs, &mut ids, InventoryAction::Move { item: bandage, to: slot(ContainerId::Item(pouch), 1, 1, false) }).unwrap();
+
+    // Un fusil gastado, con munición y un accesorio montado
+    {
+        let placed = inventory.pockets.iter_mut().flat_map(|pocket| pocket.items.iter_mut()).find(|placed| placed.stack.id == backpack).unwrap();
+        let contents = placed.stack.contents.as_mut().unwrap();
+        let rifle = &mut contents.items.iter_mut().find(|placed| placed.stack.id == rifle).unwrap().stack;
+        rifle.durability = Some(0.4);
+        rifle.ammo = Some(17);
+        rifle.attachments.push(ItemStack::new(ids.next(), defs.get("pistol").unwrap(), 1));
+    }
+    act(&mut inventory, &defs, &mut ids, InventoryAction::Equip { item: backpack, slot: EquipmentSlot::Backpack }).unwrap();
+
+    let records = inventory_records(&inventory);
+    assert_eq!(records.len(), 6);
+    for (index, record) in records.iter().enumerate() {
+        if let ItemPlacement::Container(parent) | ItemPlacement::Attachment(parent) = record.placement {
+            assert!(records[..index].iter().any(|other| other.id == parent), "los padres van primero: {record:?}");
+        }
+    }
+    let unique: std::collections::HashSet<_> = records.iter().map(|record| record.id).collect();
+    assert_eq!(unique.len(), records.len());
+
+    // Cargado en otro orden y con otros `ItemId`, es el mismo inventario
+    let mut shuffled = records.clone();
+    shuffled.reverse();
+    let mut server_ids = ItemIds::default();
+    let (restored, migration) = restore_inventory(&shuffled, &defs, &mut server_ids);
+    assert!(migration.is_empty(), "{migration:?}");
+    assert_eq!(sorted_records(&restored), sorted_records(&inventory));
+    assert_consistent(&restored, &defs);
+    assert_eq!(restored.equipment.get(EquipmentSlot::Backpack).unwrap().stack.uuid, inventory.equipment.get(EquipmentSlot::Backpack).unwrap().stack.uuid);
+    let saved_rifle = restored.items().into_iter().find(|(_, placed)| placed.stack.item == "rifle").unwrap().1.stack.clone();
+    assert_eq!((saved_rifle.durability, saved_rifle.ammo, saved_rifle.attachments.len()), (Some(0.4), Some(17), 1));
+}
+
+#[test]
+fn broken_saves_are_repaired_on_load() {
+    let defs = defs();
+    let water = record("water", 1, ItemPlacement::Pocket(0), (0, 0));
+    let records = vec![
+        water.clone(),
+        // La misma fila dos veces no da dos botellas
+        water.clone(),
+        // Su contenedor ya no existe: busca hueco
+        record("bandage", 2, ItemPlacement::Container(uuid::Uuid::new_v4()), (0, 0)),
+        // Esta pistola no va en la pistolera: va a los bolsillos
+        record("pistol", 1, ItemPlacement::Equipment("holster".to_string()), (0, 0)),
+        // Tipo borrado del catálogo
+        record("laser", 1, ItemPlacement::Pocket(1), (0, 0)),
+        // Bolsillo que no existe
+        record("brick", 1, ItemPlacement::Pocket(9), (0, 0)),
+    ];
+
+    let (inventory, migration) = restore_inventory(&records, &defs, &mut ItemIds::default());
+    assert_consistent(&inventory, &defs);
+    let mut keys: Vec<_> = inventory.items().iter().map(|(_, placed)| placed.stack.item.clone()).collect();
+    keys.sort();
+    assert_eq!(keys, ["bandage", "brick", "pistol", "water"]);
+    assert_eq!(migration.moved, 3);
+    assert_eq!(migration.lost.len(), 1);
+    assert_eq!(migration.lost[0].item, "laser");
+    assert_eq!(inventory.container(ContainerId::Pocket(0)).unwrap().items[0].stack.uuid, water.id);
+}
+
+fn wait_for_saved_inventory(h: &mut Harness, player_id: PlayerId) -> Vec<ItemRecord> {
+    let store = h.server.store.clone();
+    let expected = sorted_records(&h.server.component::<Inventory>(player_id).unwrap());
+    assert!(
+        h.step_until(NETWORK_TIMEOUT, |_| {
+            let mut saved = store.load_inventory(player_id.0).unwrap();
+            saved.sort_by_key(|record| record.id);
+            saved == expected
+        }),
+        "no se guardó el inventario {expected:?}"
+    );
+    expected
+}
+
+#[test]
+fn inventories_are_saved_and_loaded() {
+    let mut h = inventory_harness();
+    let a = h.join("ana");
+    let player_id = h.clients[a].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some()));
+
+    for (item, quantity) in [("backpack", 1), ("pistol", 1), ("bandage", 3)] {
+        h.server.app.world_mut().send_event(GiveItem { player_id, item: item.to_string(), quantity });
+    }
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().unwrap().items().len() == 3));
+
+    // Se guarda con la posición, en la misma pasada de 5 s
+    let saved = wait_for_saved_inventory(&mut h, player_id);
+    assert_eq!(saved.len(), 3);
+    assert!(h.server.store.load_player_state(player_id.0).unwrap().is_some());
+
+    // Lo que haya en la base de datos manda al cargar: nuevos `ItemId`, mismos UUID
+    let store = h.server.store.clone();
+    let water = record("water_bottle", 1, ItemPlacement::Pocket(3), (1, 0));
+    let kept: Vec<ItemRecord> = saved.iter().filter(|record| record.item != "bandage").cloned().chain([water.clone()]).collect();
+    store.save_player_state(player_id.0, Vec3::ZERO, Quat::IDENTITY, Some(&kept)).unwrap();
+    h.server.app.world_mut().send_event(LoadInventory { player_id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| find_item(h, a, "water_bottle").is_some()));
+    let inventory = h.clients[a].inventory().unwrap().clone();
+    assert!(find_item(&h, a, "bandage").is_none());
+    assert_eq!(inventory.find(find_item(&h, a, "water_bottle").unwrap()).unwrap().stack.uuid, water.id);
+    assert_eq!(sorted_records(&inventory), sorted_records(&h.server.component::<Inventory>(player_id).unwrap()));
+
+    // Al irse se guarda lo último
+    let pistol = find_item(&h, a, "pistol").unwrap();
+    h.client(a).inventory_action(InventoryAction::Drop { item: pistol });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| find_item(h, a, "pistol").is_none()));
+    h.client(a).disconnect();
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| store.load_inventory(player_id.0).unwrap().len() == 2));
+}
+
+#[test]
+fn returning_players_get_their_items_back() {
+    let mut h = inventory_harness();
+    let a = h.join("ana");
+    let player_id = h.clients[a].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some()));
+    for (item, quantity) in [("backpack", 1), ("bandage", 3)] {
+        h.server.app.world_mut().send_event(GiveItem { player_id, item: item.to_string(), quantity });
+    }
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().unwrap().items().len() == 2));
+    let saved = wait_for_saved_inventory(&mut h, player_id);
+
+    h.client(a).disconnect();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.connected_players() == 0));
+
+    // Misma cuenta, mismo `PlayerId` y los mismos items
+    let b = h.join("ana");
+    assert_eq!(h.clients[b].player_id(), Some(player_id));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].inventory().is_some_and(|inventory| inventory.items().len() == 2)));
+    assert_eq!(sorted_records(h.clients[b].inventory().unwrap()), saved);
+    assert_eq!(sorted_records(&h.server.component::<Inventory>(player_id).unwrap()), saved);
+
+    // Otro nombre es otra cuenta
+    let c = h.join("bea");
+    assert_ne!(h.clients[c].player_id(), Some(player_id));
+}
+
+#[test]
+fn items_cannot_be_loaded_twice() {
+    let mut h = inventory_harness();
+    let a = h.join("ana");
+    let b = h.join("bea");
+    let ana = h.clients[a].player_id().unwrap();
+    let bea = h.clients[b].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some() && h.clients[b].inventory().is_some()));
+
+    h.server.app.world_mut().send_event(GiveItem { player_id: ana, item: "backpack".to_string(), quantity: 1 });
+    h.server.app.world_mut().send_event(GiveItem { player_id: ana, item: "rifle".to_string(), quantity: 1 });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| find_item(h, a, "rifle").is_some()));
+    let saved = wait_for_saved_inventory(&mut h, ana);
+
+    // Una copia de las filas de Ana para Bea: el fusil solo puede estar en un sitio
+    let store = h.server.store.clone();
+    let bandage = record("bandage", 2, ItemPlacement::Pocket(0), (0, 0));
+    let copied: Vec<ItemRecord> = saved.iter().cloned().chain([bandage.clone()]).collect();
+    store.save_player_state(bea.0, Vec3::ZERO, Quat::IDENTITY, Some(&copied)).unwrap();
+    h.server.app.world_mut().send_event(LoadInventory { player_id: bea });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| find_item(h, b, "bandage").is_some()));
+    assert_eq!(h.clients[b].inventory().unwrap().items().len(), 1);
+    assert!(find_item(&h, a, "rifle").is_some());
+
+    // Copiarlas en la base de datos se las quitó a Ana, pero con los siguientes
+    // guardados cada UUID vuelve a tener un solo dueño: quien lo lleva
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        let mut ana_saved = store.load_inventory(ana.0).unwrap();
+        ana_saved.sort_by_key(|record| record.id);
+        ana_saved == saved && store.load_inventory(bea.0).unwrap() == vec![bandage.clone()]
+    }));
+}
+
+#[test]
+fn equipment_slots_and_modifiers() {
+    let defs = defs();
+    let mut ids = Ids(0);
+    let mut inventory = Inventory::new();
+    let helmet = give(&mut inventory, &defs, &mut ids, "helmet", 1);
+    let rig = give(&mut inventory, &defs, &mut ids, "rig", 1);
+    let brick = give(&mut inventory, &defs, &mut ids, "brick", 1);
+
+    // El cliente solo ve los diffs
+    let mut mirror = inventory.clone();
+    let mut equip = |inventory: &mut Inventory, ids: &mut Ids, item, slot| {
+        let outcome = act(inventory, &defs, ids, InventoryAction::Equip { item, slot })?;
+        outcome.diffs.into_iter().for_each(|diff| mirror.apply_diff(diff));
+        Ok::<_, InventoryError>(())
+    };
+    assert_eq!(equip(&mut inventory, &mut ids, helmet, EquipmentSlot::Face), Err(InventoryError::WrongSlot(EquipmentSlot::Face)));
+    assert_eq!(equip(&mut inventory, &mut ids, brick, EquipmentSlot::Head), Err(InventoryError::WrongSlot(EquipmentSlot::Head)));
+    assert_eq!(equip(&mut inventory, &mut ids, helmet, EquipmentSlot::Head), Ok(()));
+    assert_eq!(equip(&mut inventory, &mut ids, rig, EquipmentSlot::Rig), Ok(()));
+    assert_eq!(inventory.equipment.slot_of(helmet), Some(EquipmentSlot::Head));
+    assert_eq!(inventory.parent(helmet), None);
+    assert!(inventory.container(ContainerId::Pocket(0)).unwrap().items.is_empty());
+    let spare = give(&mut inventory, &defs, &mut ids, "helmet", 1);
+    assert_eq!(equip(&mut inventory, &mut ids, spare, EquipmentSlot::Head), Err(InventoryError::SlotOccupied(EquipmentSlot::Head)));
+    assert_eq!(&mirror.equipment, &inventory.equipment);
+
+    // La rejilla del rig puesto se usa como un bolsillo más
+    assert!(inventory.containers().contains(&ContainerId::Item(rig)));
+    assert_eq!(inventory.depth(ContainerId::Item(rig)), 0);
+    act(&mut inventory, &defs, &mut ids, InventoryAction::Move { item: brick, to: slot(ContainerId::Item(rig), 2, 1, false) }).unwrap();
+    assert_eq!(inventory.parent(brick), Some(ContainerId::Item(rig)));
+
+    let modifiers = inventory.modifiers(&defs);
+    assert_eq!(modifiers.carry_capacity, BASE_CARRY_CAPACITY + 10.0);
+    assert_eq!(modifiers.armor.get(&BodyZone::Head), Some(&3));
+    assert_eq!(modifiers.armor.get(&BodyZone::Thorax), None);
+    assert!((modifiers.speed_multiplier - 0.9).abs() < 1e-4, "{modifiers:?}");
+    assert!(modifiers.can_sprint);
+
+    // Quitárselo es moverlo a una rejilla, pero no a la suya
+    assert_eq!(
+        act(&mut inventory, &defs, &mut ids, InventoryAction::Move { item: rig, to: slot(ContainerId::Item(rig), 0, 0, false) }),
+        Err(InventoryError::IntoItself)
+    );
+    act(&mut inventory, &defs, &mut ids, InventoryAction::Move { item: helmet, to: slot(ContainerId::Pocket(1), 0, 0, false) }).unwrap();
+    assert!(inventory.equipment.get(EquipmentSlot::Head).is_none());
+    assert_eq!(inventory.modifiers(&defs).armor.len(), 0);
+
+    assert_consistent(&inventory, &defs);
+
+    // Cada múltiplo de la capacidad de carga es un nivel más lento; el último sin correr
+    let heavy = ItemDefs::new(defs.iter().cloned().chain([def("anvil", (1, 1), 25.0, 1)]));
+    let mut loaded = Inventory::new();
+    for expected in [(0.9, true), (0.75, true), (0.5, false)] {
+        give(&mut loaded, &heavy, &mut ids, "anvil", 1);
+        let modifiers = loaded.modifiers(&heavy);
+        assert_eq!((modifiers.speed_multiplier, modifiers.can_sprint), expected, "{} kg", modifiers.weight);
+    }
+}
+
+#[test]
+fn worn_gear_slows_the_player() {
+    let mut h = inventory_harness();
+    let a = h.join("ana");
+    let player_id = h.clients[a].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some()));
+
+    // El chaleco no cabe en un bolsillo: entra en la mochila
+    for item in ["backpack", "armor_vest"] {
+        h.server.app.world_mut().send_event(GiveItem { player_id, item: item.to_string(), quantity: 1 });
+    }
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| find_item(h, a, "armor_vest").is_some()));
+    let vest = find_item(&h, a, "armor_vest").unwrap();
+    let bad = h.client(a).inventory_action(InventoryAction::Equip { item: vest, slot: EquipmentSlot::Head });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory_rejection().is_some_and(|(sequence, _)| *sequence == bad)));
+    h.client(a).inventory_action(InventoryAction::Equip { item: vest, slot: EquipmentSlot::BodyArmor });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[a].inventory().unwrap().equipment.slot_of(vest) == Some(EquipmentSlot::BodyArmor)
+    }));
+
+    let defs = h.server.app.world().resource::<ItemDefs>().clone();
+    let penalty = defs.get("armor_vest").unwrap().speed_penalty;
+    let controller = h.server.component::<PlayerController>(player_id).unwrap();
+    let modifiers = h.server.component::<GearModifiers>(player_id).unwrap();
+    assert!((controller.speed_multiplier - (1.0 - penalty)).abs() < 1e-4);
+    assert_eq!(modifiers.armor.get(&BodyZone::Thorax), Some(&4));
+    assert_eq!(modifiers, h.clients[a].inventory().unwrap().modifiers(&defs));
+
+    // Andando con el chaleco se va más despacio que los 7 m/s de siempre
+    h.client(a).hold(KeyCode::KeyW);
+    h.step_for(30);
+    let speed = h.server.component::<PlayerController>(player_id).unwrap().velocity.xz().length();
+    h.client(a).release(KeyCode::KeyW);
+    assert!((speed - 7.0 * (1.0 - penalty)).abs() < 0.05, "{speed} m/s");
+}
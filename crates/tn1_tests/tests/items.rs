@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use std::path::Path;
+use std::time::Duration;
+use tn1_server::inventory::{GiveItem, InventoryPlugin};
+use tn1_server::items::{ItemRegistryPlugin, ItemSettings};
+use tn1_server::tls::ServerTls;
+use tn1_shared::inventory::*;
+use tn1_shared::items::*;
+use tn1_tests::*;
+
+fn load_error(name: &str, files: &[(&str, &str)]) -> ItemDefsError {
+    load_data(name, files, |dir| ItemDefs::load(dir).unwrap_err())
+}
+
+const BANDAGE: &str = r#"#![enable(implicit_some)]
+(
+    version: 1,
+    items: [
+        (key: "bandage", name: "Venda", weight: 0.05, max_stack: 5, category: Medical, medical: (heal: 5.0)),
+    ],
+)"#;
+
+#[test]
+fn shipped_catalog_is_valid() {
+    let defs = ItemDefs::load(&item_settings().path).unwrap();
+    assert!(defs.len() >= 9, "{}", defs.len());
+    assert!(defs.version() >= 1);
+
+    let rifle = defs.get("rifle").unwrap();
+    assert_eq!((rifle.size(false), rifle.category), ((5, 2), ItemCategory::Weapon));
+    assert!(rifle.rotatable);
+    let weapon = rifle.weapon.as_ref().unwrap();
+    assert_eq!(defs.get(&weapon.ammo).unwrap().category, ItemCategory::Ammo);
+
+    // Lo que falta toma el valor por defecto y lo cuadrado no se gira
+    let bandage = defs.get("bandage").unwrap();
+    assert_eq!((bandage.width, bandage.height, bandage.rarity), (1, 1, Rarity::Common));
+    assert!(!bandage.rotatable);
+    assert!(bandage.medical.as_ref().unwrap().stops_bleeding);
+    assert!(defs.get("water_bottle").unwrap().nutrition.as_ref().unwrap().hydration > 0.0);
+    let backpack = defs.get("backpack").unwrap();
+    assert_eq!(backpack.grid.unwrap().max_weight, Some(30.0));
+
+    for def in defs.iter() {
+        assert_eq!(def.validate(), Ok(()), "{}", def.key);
+    }
+    // Un fichero suelto también vale
+    let medical = ItemDefs::load(&item_settings().path.join("medical.ron")).unwrap();
+    assert!(medical.get("bandage").is_some() && medical.get("rifle").is_none());
+}
+
+#[test]
+fn bad_catalogs_are_rejected_with_clear_errors() {
+    // Sintaxis: fichero, línea y columna
+    let error = load_error("syntax", &[("broken.ron", "(version: 1, items: [(key: \"x\",, )])")]);
+    assert!(matches!(error, ItemDefsError::Parse { .. }));
+    assert!(error.to_string().contains("broken.ron:1:"), "{error}");
+
+    let invalid = |name: &str, item: &str| {
+        let source = format!("#![enable(implicit_some)]\n(version: 1, items: [{}])", item);
+        load_error(name, &[("items.ron", &source)])
+    };
+    let cases = [
+        ("(key: \"Bad Key\", name: \"x\")", "minúsculas"),
+        ("(key: \"x\")", "nombre"),
+        ("(key: \"x\", name: \"x\", width: 0)", "tamaño"),
+        ("(key: \"x\", name: \"x\", weight: -1.0)", "peso"),
+        ("(key: \"x\", name: \"x\", max_stack: 0)", "max_stack"),
+        ("(key: \"x\", name: \"x\", category: Weapon)", "weapon"),
+        ("(key: \"x\", name: \"x\", medical: (heal: 1.0))", "medical"),
+        ("(key: \"x\", name: \"x\", category: Food)", "nutrition"),
+        ("(key: \"x\", name: \"x\", grid: (width: 2, height: 2))", "Container"),
+        ("(key: \"x\", name: \"x\", max_stack: 2, category: Container, grid: (width: 2, height: 2))", "apilar"),
+        ("(key: \"x\", name: \"x\", aliases: [\"Old\"])", "alias"),
+    ];
+    for (index, (item, expected)) in cases.iter().enumerate() {
+        let error = invalid(&format!("invalid_{index}"), item);
+        let ItemDefsError::Invalid { key, problem, path } = &error else { panic!("{item}: {error}") };
+        assert_eq!(path.file_name().unwrap(), "items.ron");
+        assert!(problem.contains(expected), "{item}: {problem}");
+        assert!(error.to_string().contains(&format!("item '{key}'")), "{error}");
+    }
+
+    // Un arma con munición que no existe o que no es munición
+    let weapon = "(key: \"gun\", name: \"Arma\", category: Weapon, weapon: (ammo: \"{}\", damage: 1.0, fire_rate: 1.0, muzzle_velocity: 1.0, magazine: 1))";
+    let error = invalid("no_ammo", &weapon.replace("{}", "nope"));
+    assert!(matches!(error, ItemDefsError::BadReference { ref key, .. } if key == "gun"), "{error}");
+    let with_bandage = format!("{}, (key: \"bandage\", name: \"Venda\", category: Medical, medical: ())", weapon.replace("{}", "bandage"));
+    assert!(matches!(invalid("not_ammo", &with_bandage), ItemDefsError::BadReference { .. }));
+
+    // Claves repetidas entre ficheros, también por alias
+    let error = load_error("duplicate", &[("a.ron", BANDAGE), ("b.ron", BANDAGE)]);
+    assert!(matches!(error, ItemDefsError::Duplicate { ref key, .. } if key == "bandage"), "{error}");
+    assert!(error.to_string().contains("a.ron") && error.to_string().contains("b.ron"));
+    let alias = "#![enable(implicit_some)]\n(version: 1, items: [(key: \"gauze\", name: \"Gasa\", aliases: [\"bandage\"])])";
+    assert!(matches!(load_error("alias", &[("a.ron", BANDAGE), ("b.ron", alias)]), ItemDefsError::Duplicate { .. }));
+
+    assert!(matches!(load_error("empty", &[("notes.txt", "nada")]), ItemDefsError::Empty(_)));
+    assert!(matches!(ItemDefs::load(Path::new("/no/such/items")), Err(ItemDefsError::Io { .. })));
+}
+
+fn def(key: &str, size: (u8, u8), max_stack: u32) -> ItemDef {
+    ItemDef { key: key.to_string(), name: key.to_string(), width: size.0, height: size.1, max_stack, weight: 0.1, ..default() }
+}
+
+fn stack(id: u64, key: &str, quantity: u32) -> ItemStack {
+    ItemStack {
+        id: ItemId(id),
+        uuid: uuid::Uuid::new_v4(),
+        item: key.to_string(),
+        quantity,
+        durability: None,
+        ammo: None,
+        attachments: Vec::new(),
+        contents: None,
+    }
+}
+
+fn placed(stack: ItemStack, x: u8, y: u8) -> PlacedItem {
+    PlacedItem { stack, x, y, rotated: false }
+}
+
+#[test]
+fn saved_inventories_survive_catalog_changes() {
+    let crate_def = |size| ItemDef {
+        category: ItemCategory::Container,
+        grid: Some(GridSpec { width: size, height: size, max_weight: None }),
+        ..def("crate", (2, 2), 1)
+    };
+    let old = ItemDefs::new([def("bandage", (1, 1), 10), def("map", (1, 1), 1), def("gun", (2, 1), 1), crate_def(2), def("junk", (1, 1), 1)]);
+    let mut inventory = Inventory::new();
+    inventory.pockets[0].items.push(placed(stack(1, "bandage", 8), 0, 0));
+    inventory.pockets[0].items.push(placed(stack(2, "map", 1), 1, 0));
+    inventory.pockets[0].items.push(placed(stack(3, "gun", 1), 0, 1));
+    inventory.pockets[1].items.push(placed(stack(6, "junk", 1), 0, 0));
+    let mut crate_stack = ItemStack::new(ItemId(4), old.get("crate").unwrap(), 1);
+    crate_stack.contents.as_mut().unwrap().items.push(placed(stack(5, "gun", 1), 0, 1));
+    inventory.pockets[2].items.push(placed(crate_stack, 0, 0));
+    let saved = inventory.clone();
+
+    // Con el mismo catálogo no cambia nada
+    assert!(inventory.migrate(&old).is_empty());
+    assert_eq!(inventory, saved);
+
+    // Catálogo nuevo: la venda se renombra y apila menos, la pistola crece,
+    // la caja encoge y la chatarra desaparece
+    let new = ItemDefs::new([
+        ItemDef { aliases: vec!["bandage".to_string()], ..def("field_dressing", (1, 1), 5) },
+        def("map", (1, 1), 1),
+        def("gun", (3, 1), 1),
+        crate_def(1),
+    ]);
+    let migration = inventory.migrate(&new);
+    assert_eq!((migration.renamed, migration.clamped), (1, 1));
+    assert_eq!(migration.lost.iter().map(|stack| stack.id).collect::<Vec<_>>(), vec![ItemId(6), ItemId(3), ItemId(5)]);
+    assert_eq!(migration.moved, 0);
+
+    let dressing = inventory.find(ItemId(1)).unwrap();
+    assert_eq!((dressing.stack.item.as_str(), dressing.stack.quantity), ("field_dressing", 5));
+    assert_eq!(inventory.container(ContainerId::Item(ItemId(4))).unwrap().width, 1);
+    assert!(inventory.migrate(&new).is_empty());
+
+    // Lo que no cabe donde estaba busca otro hueco
+    let mut inventory = saved.clone();
+    let wider = ItemDefs::new([def("bandage", (1, 1), 10), def("map", (2, 1), 1), def("gun", (2, 1), 1), crate_def(2), def("junk", (1, 1), 1)]);
+    let migration = inventory.migrate(&wider);
+    assert_eq!((migration.moved, migration.lost.len()), (1, 0));
+    assert_ne!(inventory.parent(ItemId(2)), Some(ContainerId::Pocket(0)));
+    for id in inventory.containers() {
+        let grid = inventory.container(id).unwrap();
+        for item in &grid.items {
+            let size = wider.get(&item.stack.item).unwrap().size(item.rotated);
+            assert_eq!(grid.fits(&wider, item.x, item.y, size, Some(item.stack.id)), Ok(()));
+        }
+    }
+}
+
+#[test]
+fn dev_server_hot_reloads_definitions() {
+    let dir = data_dir("reload", &[("medical.ron", BANDAGE)]);
+    let settings = ItemSettings { path: dir.clone(), hot_reload: true, poll_interval: Duration::ZERO };
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((ItemRegistryPlugin { settings }, InventoryPlugin))
+            .init_resource::<Dropped>()
+            .add_systems(Update, collect_dropped);
+    });
+    let mut h = Harness { server, clients: Vec::new() };
+    let a = h.join("ana");
+    let player_id = h.clients[a].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some()));
+    h.server.app.world_mut().send_event(GiveItem { player_id, item: "bandage".to_string(), quantity: 5 });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().unwrap().items().len() == 1));
+
+    // Un fichero roto no tumba el servidor: sigue con el catálogo anterior
+    std::fs::write(dir.join("medical.ron"), "(version: 2, items: [").unwrap();
+    h.step_for(5);
+    assert!(h.server.app.world().resource::<ItemDefs>().get("bandage").is_some());
+
+    // La venda pasa a llamarse de otra forma y se apila de 3 en 3: el
+    // inventario se adapta y el cliente lo recibe entero de nuevo
+    let renamed = BANDAGE
+        .replace("version: 1", "version: 2")
+        .replace("key: \"bandage\"", "key: \"field_dressing\", aliases: [\"bandage\"]")
+        .replace("max_stack: 5", "max_stack: 3");
+    std::fs::write(dir.join("medical.ron"), renamed).unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[a].inventory().unwrap().items().first().is_some_and(|(_, placed)| placed.stack.item == "field_dressing")
+    }));
+    let defs = h.server.app.world().resource::<ItemDefs>();
+    assert_eq!(defs.version(), 2);
+    assert!(defs.get("bandage").is_none() && defs.resolve("bandage").is_some());
+    assert_eq!(h.clients[a].inventory().unwrap().items()[0].1.stack.quantity, 3);
+    assert!(h.server.app.world().resource::<Dropped>().0.is_empty());
+
+    // Si el tipo desaparece, el item cae al suelo
+    let other = BANDAGE.replace("version: 1", "version: 3").replace("key: \"bandage\"", "key: \"gauze\"");
+    std::fs::write(dir.join("medical.ron"), other).unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().unwrap().items().is_empty()));
+    let dropped = &h.server.app.world().resource::<Dropped>().0;
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].stack.item, "field_dressing");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use tn1_server::lag_compensation::*;
+use tn1_shared::components::PlayerId;
+use tn1_shared::constants::{LAG_COMPENSATION_MAX_REWIND_SECS, PLAYER_RADIUS};
+use tn1_shared::protocol::TICK_RATE;
+
+const DT: f64 = 1.0 / TICK_RATE as f64;
+const MAX_REWIND: f64 = LAG_COMPENSATION_MAX_REWIND_SECS as f64;
+
+fn snapshot(index: u32, player_id: PlayerId, position: Vec3, rotation: Quat) -> HitboxSnapshot {
+    HitboxSnapshot { entity: Entity::from_raw(index), player_id, position, rotation, hitbox: Hitbox::default() }
+}
+
+/// Un segundo de historia: `runner` avanza 1 m por tick girando sobre sí mismo y
+/// `late` solo aparece a partir del tick 50
+fn history() -> (HitboxHistory, PlayerId, PlayerId) {
+    let (runner, late) = (PlayerId(uuid::Uuid::new_v4()), PlayerId(uuid::Uuid::new_v4()));
+    let mut history = HitboxHistory::new(1.0);
+    for tick in 0..TICK_RATE {
+        let mut players = vec![snapshot(1, runner, Vec3::new(tick as f32, 0.0, 0.0), Quat::from_rotation_y(tick as f32 * 0.01))];
+        if tick >= 50 {
+            players.push(snapshot(2, late, Vec3::new(0.0, 0.0, -5.0), Quat::IDENTITY));
+        }
+        history.record(tick, tick as f64 * DT, players);
+    }
+    (history, runner, late)
+}
+
+fn position(world: &RewoundWorld, player_id: PlayerId) -> Option<Vec3> {
+    world.players.iter().find(|snapshot| snapshot.player_id == player_id).map(|snapshot| snapshot.position)
+}
+
+#[test]
+fn view_time_is_half_the_rtt_plus_interpolation_up_to_the_limit() {
+    let now = 100.0;
+    let view = |rtt: f32, delay: f32| now - HitboxHistory::estimate_view_time(now, rtt, delay);
+    assert!((view(0.1, 0.05) - 0.1).abs() < 1e-6);
+    assert!((view(0.0, 0.1) - 0.1).abs() < 1e-6);
+
+    // Con mucho ping no se retrocede más del límite, y nunca hacia el futuro
+    assert!((view(2.0, 0.1) - MAX_REWIND).abs() < 1e-6);
+    assert_eq!(view(0.0, -1.0), 0.0);
+}
+
+#[test]
+fn rewind_lands_on_recorded_ticks_and_interpolates_between_them() {
+    let (history, runner, _) = history();
+    let newest = 59.0 * DT;
+    assert_eq!(history.latest_tick(), Some(59));
+
+    // Justo en un tick: ese registro tal cual
+    let world = history.rewind(55.0 * DT).unwrap();
+    assert_eq!((world.tick, position(&world, runner)), (55, Some(Vec3::new(55.0, 0.0, 0.0))));
+
+    // Entre dos ticks se interpolan posición y giro, y el tick es el más cercano
+    let world = history.rewind(55.25 * DT).unwrap();
+    assert_eq!(world.tick, 55);
+    assert!(position(&world, runner).unwrap().distance(Vec3::new(55.25, 0.0, 0.0)) < 1e-3);
+    let rotation = world.players.iter().find(|snapshot| snapshot.player_id == runner).unwrap().rotation;
+    assert!(rotation.angle_between(Quat::from_rotation_y(0.5525)) < 1e-3);
+    let world = history.rewind(55.75 * DT).unwrap();
+    assert_eq!(world.tick, 56);
+    assert!(position(&world, runner).unwrap().distance(Vec3::new(55.75, 0.0, 0.0)) < 1e-3);
+
+    // Lo del futuro se queda en el último tick
+    let world = history.rewind(newest + 1.0).unwrap();
+    assert_eq!((world.tick, world.time), (59, newest));
+}
+
+#[test]
+fn rewind_is_limited_and_skips_players_missing_from_a_tick() {
+    let (history, runner, late) = history();
+    let newest = 59.0 * DT;
+
+    // Más atrás del límite se queda en el límite, aunque haya historia
+    let world = history.rewind(0.0).unwrap();
+    assert!((world.time - (newest - MAX_REWIND)).abs() < 1e-9);
+    let expected = 59.0 - (MAX_REWIND * TICK_RATE as f64) as f32;
+    assert!((position(&world, runner).unwrap().x - expected).abs() < 1e-3);
+
+    // Quien aún no estaba en el tick anterior no se inventa
+    let world = history.rewind(49.5 * DT).unwrap();
+    assert!(position(&world, runner).is_some());
+    assert_eq!(position(&world, late), None);
+    let world = history.rewind(50.0 * DT).unwrap();
+    assert_eq!(position(&world, late), Some(Vec3::new(0.0, 0.0, -5.0)));
+
+    assert!(HitboxHistory::new(1.0).rewind(0.0).is_none());
+}
+
+#[test]
+fn rewound_raycast_hits_the_player_where_they_were() {
+    let (history, runner, _) = history();
+    let world = history.rewind(55.5 * DT).unwrap();
+    let origin = Vec3::new(55.5, 1.0, 10.0);
+
+    let hit = world.raycast(origin, Vec3::NEG_Z, 100.0, None).unwrap();
+    assert_eq!(hit.player_id, runner);
+    assert!((hit.distance - (10.0 - PLAYER_RADIUS)).abs() < 1e-3, "{hit:?}");
+
+    // Un metro más allá (donde está ahora) ya no hay nadie, y al tirador se le ignora
+    assert!(world.raycast(origin + Vec3::X, Vec3::NEG_Z, 100.0, None).is_none());
+    assert!(world.raycast(origin, Vec3::NEG_Z, 100.0, Some(runner)).is_none());
+}
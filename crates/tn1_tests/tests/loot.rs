@@ -0,0 +1,300 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tn1_server::chunks::{ChunkPlugin, ChunkSettings};
+use tn1_server::database::{InMemoryStore, PlayerStorage};
+use tn1_server::inventory::InventoryPlugin;
+use tn1_server::items::ItemRegistryPlugin;
+use tn1_server::loot::{loot_statistics, LootContainers, LootPlugin, LootSettings, LootSpot};
+use tn1_server::tls::ServerTls;
+use tn1_server::world::WorldPlugin;
+use tn1_shared::chunk::*;
+use tn1_shared::items::ItemDefs;
+use tn1_shared::loot::*;
+use tn1_shared::terrain::Biome;
+use tn1_tests::*;
+
+fn shipped_defs() -> ItemDefs {
+    ItemDefs::load(&item_settings().path).unwrap()
+}
+
+fn load_error(name: &str, files: &[(&str, &str)]) -> LootTablesError {
+    load_data(name, files, |dir| LootTables::load(dir, &shipped_defs()).unwrap_err())
+}
+
+fn table(key: &str, containers: Vec<ContainerKind>, biomes: Vec<Biome>, entries: &[(&str, u32)]) -> LootTable {
+    LootTable {
+        key: key.to_string(),
+        containers,
+        biomes,
+        rolls: (1, 1),
+        restock_secs: 60.0,
+        entries: entries.iter().map(|(item, weight)| LootEntry { item: item.to_string(), weight: *weight, quantity: (1, 1) }).collect(),
+    }
+}
+
+/// Vendas y pistolas a partes iguales; pistolas con tope de 2 en todo el mundo
+const CAPPED: &str = r#"(
+    caps: { "pistol": 2 },
+    tables: [
+        (key: "everything", rolls: (3, 3), restock_secs: 1.0,
+         entries: [(item: "bandage", weight: 1), (item: "pistol", weight: 1)]),
+    ],
+)"#;
+
+#[test]
+fn shipped_loot_tables_are_valid() {
+    let tables = LootTables::load(&loot_settings().path, &shipped_defs()).unwrap();
+    assert!(tables.len() >= 4, "{}", tables.len());
+    for kind in [ContainerKind::Crate, ContainerKind::Toolbox, ContainerKind::MedicalBox] {
+        assert!(tables.table_for(kind, Biome::Plains).is_some(), "{kind:?}");
+    }
+    assert_eq!(tables.table_for(ContainerKind::Crate, Biome::Snow).unwrap().key, "military");
+    assert_eq!(tables.table_for(ContainerKind::Crate, Biome::Plains).unwrap().key, "crate");
+    assert!(tables.cap("rifle").is_some() && tables.cap("bandage").is_none());
+}
+
+#[test]
+fn bad_loot_tables_are_rejected() {
+    let invalid = |name: &str, table: &str| {
+        let source = format!("(tables: [{}])", table);
+        load_error(name, &[("loot.ron", &source)])
+    };
+    let cases = [
+        ("(key: \"Bad\", rolls: (1, 1), restock_secs: 1.0, entries: [(item: \"bandage\", weight: 1)])", "minúsculas"),
+        ("(key: \"x\", rolls: (2, 1), restock_secs: 1.0, entries: [(item: \"bandage\", weight: 1)])", "rolls"),
+        ("(key: \"x\", rolls: (0, 0), restock_secs: 1.0, entries: [(item: \"bandage\", weight: 1)])", "rolls"),
+        ("(key: \"x\", rolls: (1, 99), restock_secs: 1.0, entries: [(item: \"bandage\", weight: 1)])", "rolls"),
+        ("(key: \"x\", rolls: (1, 1), restock_secs: 0.0, entries: [(item: \"bandage\", weight: 1)])", "restock_secs"),
+        ("(key: \"x\", rolls: (1, 1), restock_secs: 1.0, entries: [])", "entradas"),
+        ("(key: \"x\", rolls: (1, 1), restock_secs: 1.0, entries: [(item: \"nope\", weight: 1)])", "no existe"),
+        ("(key: \"x\", rolls: (1, 1), restock_secs: 1.0, entries: [(item: \"bandage\", weight: 0)])", "peso 0"),
+        ("(key: \"x\", rolls: (1, 1), restock_secs: 1.0, entries: [(item: \"pistol\", weight: 1, quantity: (1, 2))])", "montón"),
+        ("(key: \"x\", rolls: (1, 1), restock_secs: 1.0, entries: [(item: \"bandage\", weight: 1, quantity: (0, 1))])", "cantidad"),
+    ];
+    for (index, (table, expected)) in cases.iter().enumerate() {
+        let error = invalid(&format!("invalid_{index}"), table);
+        let LootTablesError::Invalid { problem, path, .. } = &error else { panic!("{table}: {error}") };
+        assert_eq!(path.file_name().unwrap(), "loot.ron");
+        assert!(problem.contains(expected), "{table}: {problem}");
+    }
+
+    // Topes de items que no existen
+    let error = load_error("cap", &[("loot.ron", "(caps: { \"nope\": 1 }, tables: [])")]);
+    assert!(matches!(error, LootTablesError::Invalid { ref key, .. } if key == "caps"), "{error}");
+
+    // Tablas y topes repetidos entre ficheros
+    let error = load_error("duplicate", &[("a.ron", CAPPED), ("b.ron", CAPPED)]);
+    assert!(matches!(error, LootTablesError::Duplicate { .. }), "{error}");
+    assert!(error.to_string().contains("a.ron") && error.to_string().contains("b.ron"));
+
+    assert!(matches!(load_error("syntax", &[("loot.ron", "(tables: [")]), LootTablesError::Parse { .. }));
+    assert!(matches!(load_error("empty", &[("loot.ron", "(tables: [])")]), LootTablesError::Empty(_)));
+    assert!(matches!(LootTables::load(Path::new("/no/such/loot"), &shipped_defs()), Err(LootTablesError::Files(_))));
+}
+
+#[test]
+fn rolls_follow_weights_and_caps() {
+    let common = table("common", Vec::new(), Vec::new(), &[("bandage", 9), ("pistol", 1)]);
+    let mut rng = WorldRng::new(42);
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for _ in 0..10_000 {
+        for (item, quantity) in common.roll(&mut rng, |_| u32::MAX) {
+            *counts.entry(item).or_default() += quantity;
+        }
+    }
+    let share = counts["pistol"] as f32 / 10_000.0;
+    assert!((0.07..0.13).contains(&share), "{share}");
+
+    // Sin hueco bajo el tope la entrada no sale; con hueco para uno, sale uno
+    for _ in 0..100 {
+        assert!(common.roll(&mut rng, |item| if item == "pistol" { 0 } else { u32::MAX }).iter().all(|(item, _)| item == "bandage"));
+    }
+    let greedy = LootTable { rolls: (10, 10), ..table("greedy", Vec::new(), Vec::new(), &[("pistol", 1)]) };
+    assert_eq!(greedy.roll(&mut rng, |_| 1), vec![("pistol".to_string(), 1)]);
+    assert!(greedy.roll(&mut rng, |_| 0).is_empty());
+}
+
+#[test]
+fn most_specific_table_wins() {
+    let tables = LootTables::new(
+        [
+            table("anything", Vec::new(), Vec::new(), &[("bandage", 1)]),
+            table("crates", vec![ContainerKind::Crate], Vec::new(), &[("bandage", 1)]),
+            table("snow", Vec::new(), vec![Biome::Snow], &[("bandage", 1)]),
+            table("snowy_crates", vec![ContainerKind::Crate], vec![Biome::Snow], &[("bandage", 1)]),
+        ],
+        HashMap::new(),
+    );
+    let key = |kind, biome| tables.table_for(kind, biome).unwrap().key.as_str();
+    assert_eq!(key(ContainerKind::Crate, Biome::Snow), "snowy_crates");
+    assert_eq!(key(ContainerKind::Crate, Biome::Plains), "crates");
+    // Entre igual de concretas gana la primera
+    assert_eq!(key(ContainerKind::Toolbox, Biome::Snow), "snow");
+    assert_eq!(key(ContainerKind::Toolbox, Biome::Plains), "anything");
+    assert!(LootTables::default().table_for(ContainerKind::Crate, Biome::Snow).is_none());
+}
+
+#[test]
+fn loot_statistics_cover_the_whole_map() {
+    let tables = LootTables::load(&loot_settings().path, &shipped_defs()).unwrap();
+    let stats = loot_statistics(&tables, &ChunkGrid::new(1000.0, 100.0), 7, 200);
+    assert_eq!((stats.chunks, stats.seed, stats.samples), (100, 7, 200));
+    assert!(stats.containers > 0);
+    assert_eq!(stats.tables.iter().map(|(_, count)| count).sum::<usize>() + stats.untabled, stats.containers);
+    // Cada item sale en una parte de los contenedores; varios pueden compartir uno
+    assert!(stats.items.iter().all(|item| item.expected > 0.0 && item.share > 0.0 && item.share <= 1.0));
+    assert!(stats.items.iter().map(|item| item.share).sum::<f32>() > 1.0);
+    let report = stats.to_string();
+    assert!(report.contains("bandage") && report.contains("rifle"), "{report}");
+}
+
+/// Servidor con chunks de 100 m, catálogo del repo y las tablas de `settings`
+fn loot_harness(settings: LootSettings, store: Option<Arc<InMemoryStore>>) -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((
+            WorldPlugin,
+            ChunkPlugin { settings: ChunkSettings { view_radius: 1, ..default() } },
+            ItemRegistryPlugin { settings: item_settings() },
+            InventoryPlugin,
+        ))
+        .add_plugins(LootPlugin { settings });
+        if let Some(store) = store {
+            app.insert_resource(PlayerStorage(store));
+        }
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+fn spots(h: &Harness) -> Vec<(u64, LootSpot)> {
+    let mut spots: Vec<_> = h.server.app.world().resource::<LootContainers>().iter().map(|(id, spot)| (id, spot.clone())).collect();
+    spots.sort_by_key(|(id, _)| *id);
+    spots
+}
+
+fn count(h: &Harness, client: usize, item: &str) -> u32 {
+    let in_spots: u32 = spots(h).iter().flat_map(|(_, spot)| &spot.items).filter(|stack| stack.item == item).map(|stack| stack.quantity).sum();
+    let carried: u32 = h.clients[client]
+        .inventory()
+        .map(|inventory| inventory.items().iter().filter(|(_, placed)| placed.stack.item == item).map(|(_, placed)| placed.stack.quantity).sum())
+        .unwrap_or(0);
+    in_spots + carried
+}
+
+#[test]
+fn containers_fill_restock_and_respect_caps() {
+    let dir = data_dir("restock", &[("loot.ron", CAPPED)]);
+    let mut h = loot_harness(LootSettings { path: dir.clone(), ..loot_settings() }, None);
+    let a = h.join("ana");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some() && !spots(h).is_empty()));
+    // Todo a mano: aquí solo se prueban las tablas
+    h.server.app.world_mut().resource_mut::<LootSettings>().reach = 1.0e6;
+
+    // Tres tiradas por contenedor, pero nunca más de dos pistolas en el mundo
+    assert!(spots(&h).iter().all(|(_, spot)| spot.table == "everything" && spot.items.len() <= 3));
+    assert_eq!(count(&h, a, "pistol"), 2);
+
+    let (id, spot) = spots(&h).into_iter().find(|(_, spot)| spot.items.iter().any(|stack| stack.item == "bandage")).unwrap();
+    let sequence = h.clients[a].loot_action(LootAction::Open { container: id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].loot().is_some()));
+    assert_eq!(h.clients[a].loot().unwrap(), &(id, spot.items.clone()));
+    assert!(h.clients[a].inventory_rejection().is_none(), "{sequence}");
+
+    // Sacar una venda la pasa al inventario y arranca la cuenta atrás
+    let bandage = spot.items.iter().find(|stack| stack.item == "bandage").unwrap().clone();
+    h.clients[a].loot_action(LootAction::Take { container: id, item: bandage.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[a].inventory().unwrap().items().iter().any(|(_, placed)| placed.stack.uuid == bandage.uuid)
+    }));
+    let left = h.server.app.world().resource::<LootContainers>().get(id).unwrap().clone();
+    assert_eq!(left.items.len(), spot.items.len() - 1);
+    assert!(left.restock_at.is_some());
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].loot().is_some_and(|(_, items)| items == &left.items)));
+
+    // Lo que ya no está se rechaza
+    let sequence = h.clients[a].loot_action(LootAction::Take { container: id, item: bandage.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory_rejection().is_some_and(|(seq, _)| *seq == sequence)));
+    assert_eq!(h.clients[a].inventory_rejection().unwrap().1, LootError::ItemGone.to_string());
+
+    // Al segundo se rellena con items nuevos, sin pasarse del tope
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.server.app.world().resource::<LootContainers>().get(id).unwrap().restock_at.is_none()
+    }));
+    let refilled = h.server.app.world().resource::<LootContainers>().get(id).unwrap().clone();
+    assert!(!refilled.items.is_empty());
+    assert!(refilled.items.iter().all(|stack| left.items.iter().all(|old| old.uuid != stack.uuid)));
+    assert!(count(&h, a, "pistol") <= 2);
+
+    // Fuera de alcance o inexistente
+    h.server.app.world_mut().resource_mut::<LootSettings>().reach = 0.0;
+    let sequence = h.clients[a].loot_action(LootAction::Open { container: id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory_rejection().is_some_and(|(seq, _)| *seq == sequence)));
+    assert_eq!(h.clients[a].inventory_rejection().unwrap().1, LootError::OutOfReach.to_string());
+    let sequence = h.clients[a].loot_action(LootAction::Open { container: u64::MAX });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory_rejection().is_some_and(|(seq, _)| *seq == sequence)));
+    assert_eq!(h.clients[a].inventory_rejection().unwrap().1, LootError::UnknownContainer.to_string());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Tabla e items (clave y cantidad) de cada contenedor
+type LootSummary = Vec<(u64, String, Vec<(String, u32)>)>;
+
+/// Lo que sale en cada contenedor alrededor del punto de aparición
+fn spawned_loot() -> LootSummary {
+    let mut h = loot_harness(loot_settings(), None);
+    let a = h.join("ana");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some() && !spots(h).is_empty()));
+    spots(&h)
+        .into_iter()
+        .map(|(id, spot)| (id, spot.table, spot.items.into_iter().map(|stack| (stack.item, stack.quantity)).collect()))
+        .collect()
+}
+
+#[test]
+fn the_world_seed_decides_the_loot() {
+    let loot = spawned_loot();
+    assert!(loot.iter().any(|(_, _, items)| !items.is_empty()));
+    assert_eq!(spawned_loot(), loot);
+}
+
+#[test]
+fn looted_containers_survive_restarts() {
+    let store = Arc::new(InMemoryStore::new());
+    let mut h = loot_harness(loot_settings(), Some(store.clone()));
+    let a = h.join("ana");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].inventory().is_some() && !spots(h).is_empty()));
+    h.server.app.world_mut().resource_mut::<LootSettings>().reach = 1.0e6;
+
+    // Se guarda todo lo que ha aparecido
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| store.loot_containers().len() == spots(h).iter().filter(|(_, spot)| !spot.table.is_empty()).count()));
+
+    // Algo que quepa en los bolsillos (un rifle o una mochila no caben)
+    let defs = shipped_defs();
+    let fits = |stack: &tn1_shared::inventory::ItemStack| defs.get(&stack.item).is_some_and(|def| def.width <= 2 && def.height <= 2);
+    let (id, spot, item) = spots(&h)
+        .into_iter()
+        .find_map(|(id, spot)| Some((id, spot.clone(), spot.items.iter().find(|stack| fits(stack))?.id)))
+        .unwrap();
+    h.clients[a].loot_action(LootAction::Take { container: id, item });
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        store.loot_containers().iter().any(|record| record.id == id && record.items.len() + 1 == spot.items.len() && record.restock_at.is_some())
+    }));
+    let before = spots(&h);
+    h.client(a).disconnect();
+
+    // El servidor nuevo encuentra los contenedores tal como quedaron
+    let h = {
+        let mut h = loot_harness(loot_settings(), Some(store.clone()));
+        let b = h.join("bea");
+        assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].inventory().is_some() && spots(h).len() >= before.len()));
+        h
+    };
+    for (id, old) in before.iter().filter(|(_, spot)| !spot.table.is_empty()) {
+        let new = h.server.app.world().resource::<LootContainers>().get(*id).unwrap().clone();
+        let uuids = |spot: &LootSpot| spot.items.iter().map(|stack| stack.uuid).collect::<Vec<_>>();
+        assert_eq!(uuids(&new), uuids(old), "{id}");
+        assert_eq!(new.table, old.table);
+        assert_eq!(new.restock_at.is_some(), old.restock_at.is_some());
+    }
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+use tn1_client::config::ClientSettings;
+use tn1_client::replay::*;
+use tn1_shared::components::{PlayerController, PlayerId};
+use tn1_shared::protocol::*;
+use tn1_tests::*;
+
+fn match_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("tn1-{}-{}.{}", name, uuid::Uuid::new_v4(), MATCH_EXTENSION))
+}
+
+fn header() -> MatchHeader {
+    MatchHeader {
+        format_version: MATCH_FORMAT_VERSION,
+        protocol_version: PROTOCOL_VERSION,
+        server_address: "127.0.0.1:7777".to_string(),
+        username: "tester".to_string(),
+        recorded_at: 0,
+    }
+}
+
+fn state(player_id: PlayerId, position: Vec3) -> PlayerState {
+    PlayerState {
+        player_id,
+        position,
+        velocity: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        health: 100.0,
+        is_grounded: true,
+        last_input_sequence: 0,
+        flashlight: false,
+    }
+}
+
+fn world_state(time: f64, players: Vec<PlayerState>) -> MatchRecord {
+    MatchRecord {
+        time,
+        messages: vec![ServerMessage::WorldState {
+            tick: 0,
+            players,
+            timestamp: time,
+        }],
+    }
+}
+
+#[test]
+fn recorded_match_rebuilds_the_session() {
+    let path = match_path("session");
+    let mut h = Harness::new();
+
+    let settings = ClientSettings {
+        server_address: h.server.address.to_string(),
+        tls_enabled: false,
+        tls_pins: Vec::new(),
+    };
+    let record = MatchRecordSettings { path: Some(path.clone()) };
+    h.clients.push(TestClient::connect_configured(settings, "recorder", |app| {
+        app.add_plugins(MatchRecordPlugin { settings: record });
+    }));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[0].is_connected()));
+    let b = h.join("other");
+    let a_id = h.clients[0].player_id().unwrap();
+    let b_id = h.clients[b].player_id().unwrap();
+
+    h.client(0).hold(KeyCode::KeyW);
+    h.client(b).set_yaw(std::f32::consts::PI);
+    h.client(b).hold(KeyCode::KeyW);
+    h.step_for(40);
+    h.client(0).release(KeyCode::KeyW);
+    h.client(b).release(KeyCode::KeyW);
+
+    // Quietos y con el último snapshot ya recibido
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        [a_id, b_id].iter().all(|id| h.server.component::<PlayerController>(*id).unwrap().velocity.length() < 0.01)
+    }));
+    h.step_for(30);
+    let a_final = h.server.player(a_id).unwrap().translation;
+    let b_final = h.server.player(b_id).unwrap().translation;
+    drop(h);
+
+    let timeline = MatchTimeline::load(&path).unwrap();
+    assert_eq!(timeline.header.username, "recorder");
+    assert_eq!(timeline.local_player, Some(a_id));
+    assert_eq!(timeline.players(), &[a_id, b_id]);
+    assert!(timeline.duration() > 0.0);
+    assert!(timeline.snapshot_count() > 10);
+
+    // El primer snapshot ya tiene a quien grabó; al final coincide con el servidor
+    assert!(timeline.sample(0.0).iter().any(|state| state.player_id == a_id));
+    let end = timeline.sample(timeline.duration());
+    let position = |id: PlayerId| end.iter().find(|state| state.player_id == id).unwrap().position;
+    assert!(position(a_id).distance(a_final) < 0.01, "{:?} != {:?}", position(a_id), a_final);
+    assert!(position(b_id).distance(b_final) < 0.01, "{:?} != {:?}", position(b_id), b_final);
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn timeline_interpolates_between_snapshots() {
+    let a = PlayerId(uuid::Uuid::new_v4());
+    let b = PlayerId(uuid::Uuid::new_v4());
+    let records = vec![
+        MatchRecord {
+            time: 0.5,
+            messages: vec![ServerMessage::Connected {
+                player_id: a,
+                tick_rate: 60,
+                session_token: String::new(),
+                spawn_position: Vec3::ZERO,
+            }],
+        },
+        world_state(1.0, vec![state(a, Vec3::ZERO), state(b, Vec3::ONE)]),
+        world_state(2.0, vec![state(a, Vec3::new(10.0, 0.0, 0.0))]),
+        // Dos snapshots procesados en el mismo frame: vale el último
+        world_state(3.0, vec![state(a, Vec3::ZERO)]),
+        world_state(3.0, vec![state(a, Vec3::new(20.0, 0.0, 0.0))]),
+    ];
+
+    let timeline = MatchTimeline::new(header(), &records);
+    assert_eq!(timeline.local_player, Some(a));
+    assert_eq!(timeline.players(), &[a, b]);
+    assert_eq!(timeline.snapshot_count(), 3);
+    assert_eq!(timeline.duration(), 2.0);
+
+    let middle = timeline.sample(0.5);
+    assert_eq!(middle.len(), 2);
+    assert_eq!(middle[0].position, Vec3::new(5.0, 0.0, 0.0));
+    // `b` desaparece en el siguiente snapshot: se mantiene quieto hasta entonces
+    assert_eq!(middle[1].position, Vec3::ONE);
+
+    assert_eq!(timeline.sample(1.5)[0].position, Vec3::new(15.0, 0.0, 0.0));
+    assert_eq!(timeline.sample(-1.0)[0].position, Vec3::ZERO);
+    assert_eq!(timeline.sample(99.0)[0].position, Vec3::new(20.0, 0.0, 0.0));
+    assert_eq!(timeline.sample(1.0).len(), 1);
+}
+
+#[test]
+fn truncated_recording_keeps_complete_records() {
+    let path = match_path("truncated");
+    let id = PlayerId(uuid::Uuid::new_v4());
+    {
+        let mut recorder = MatchRecorder::create(&path, &header()).unwrap();
+        for i in 0..5 {
+            let message = ServerMessage::WorldState {
+                tick: i,
+                players: vec![state(id, Vec3::splat(i as f32))],
+                timestamp: 0.0,
+            };
+            recorder.record(&[message]).unwrap();
+        }
+        // Frames sin mensajes no se graban
+        recorder.record(&[]).unwrap();
+        assert_eq!(recorder.records(), 5);
+    }
+
+    let (_, records) = read_match(&path).unwrap();
+    assert_eq!(records.len(), 5);
+
+    // Cliente cerrado a la fuerza a mitad de un registro
+    let data = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &data[..data.len() - 3]).unwrap();
+    let (_, records) = read_match(&path).unwrap();
+    assert_eq!(records.len(), 4);
+
+    std::fs::write(&path, b"not a match file").unwrap();
+    assert!(read_match(&path).is_err());
+
+    let _ = std::fs::remove_file(path);
+}
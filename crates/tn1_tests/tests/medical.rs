@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use tn1_shared::components::{BodyZone, Health, PlayerController, PlayerId};
+use tn1_shared::inventory::*;
+use tn1_shared::medical::*;
+use tn1_tests::*;
+
+fn hurt(h: &mut Harness, player_id: PlayerId, zone: BodyZone, health: f32) {
+    h.server.edit_component(player_id, |body: &mut BodyHealth| body.set(zone, health));
+}
+
+/// Lo que deja correr el controlador de movimiento: multiplicador y si puede esprintar
+fn movement(h: &mut Harness, player_id: PlayerId) -> (f32, bool) {
+    let controller = h.server.component::<PlayerController>(player_id).unwrap();
+    (controller.speed_multiplier, controller.can_sprint)
+}
+
+#[test]
+fn zones_start_full_and_critical_zones_kill() {
+    let mut body = BodyHealth::default();
+    let expected = [(BodyZone::Head, 100.0), (BodyZone::Thorax, 150.0), (BodyZone::Stomach, 120.0), (BodyZone::LeftArm, 80.0), (BodyZone::RightLeg, 90.0)];
+    for (zone, health) in expected {
+        assert_eq!(body.get(zone), health, "{zone:?}");
+    }
+    assert_eq!(BodyHealth::max_total(), 710.0);
+    assert_eq!(body.health().current, 100.0);
+
+    // Lo que pasa de 0 se pierde; una pierna a 0 no mata
+    assert_eq!(body.damage(BodyZone::LeftLeg, 200.0), 90.0);
+    assert!(body.is_destroyed(BodyZone::LeftLeg) && !body.is_dead());
+    assert!(body.health().current > 0.0);
+
+    body.damage(BodyZone::Thorax, 150.0);
+    assert!(body.is_dead());
+    assert_eq!(body.health().current, 0.0);
+    assert_eq!(body.damage(BodyZone::Head, 10.0), 0.0, "a un muerto ya no se le quita nada");
+
+    let mut body = BodyHealth::default();
+    body.damage(BodyZone::Head, 100.0);
+    assert!(body.is_dead());
+}
+
+#[test]
+fn damage_to_a_destroyed_limb_spreads_over_the_rest() {
+    let mut body = BodyHealth::default();
+    body.set(BodyZone::LeftArm, 0.0);
+    assert_eq!(body.damage(BodyZone::LeftArm, 60.0), 60.0);
+    assert_eq!(body.get(BodyZone::LeftArm), 0.0);
+    for zone in BodyZone::ALL.into_iter().filter(|zone| *zone != BodyZone::LeftArm) {
+        assert_eq!(body.get(zone), zone.max_health() - 10.0, "{zone:?}");
+    }
+
+    // Disparando a las extremidades rotas también se acaba muriendo
+    for zone in [BodyZone::RightArm, BodyZone::LeftLeg, BodyZone::RightLeg] {
+        body.set(zone, 0.0);
+    }
+    while !body.is_dead() {
+        body.damage(BodyZone::LeftLeg, 30.0);
+    }
+    assert!(body.get(BodyZone::Head) <= 0.0 || body.get(BodyZone::Thorax) <= 0.0);
+}
+
+#[test]
+fn broken_legs_slow_and_hurt_arms_sway() {
+    let mut body = BodyHealth::default();
+    assert_eq!((body.speed_multiplier(), body.can_sprint(), body.sway()), (1.0, true, 0.0));
+
+    // Una pierna herida pero entera no frena
+    body.set(BodyZone::LeftLeg, 10.0);
+    assert_eq!((body.speed_multiplier(), body.can_sprint()), (1.0, true));
+    body.set(BodyZone::LeftLeg, 0.0);
+    assert_eq!((body.speed_multiplier(), body.can_sprint()), (BROKEN_LEG_SPEED, false));
+    body.set(BodyZone::RightLeg, 0.0);
+    assert_eq!(body.speed_multiplier(), BROKEN_LEGS_SPEED);
+
+    body.set(BodyZone::RightArm, 40.0);
+    assert_eq!(body.sway(), ARM_SWAY_DEG / 2.0);
+    body.set(BodyZone::LeftArm, 0.0);
+    assert_eq!(body.sway(), ARM_SWAY_DEG * 1.5);
+}
+
+#[test]
+fn zone_multipliers_favour_the_head() {
+    assert!(BodyZone::Head.damage_multiplier() > BodyZone::Thorax.damage_multiplier());
+    assert!(BodyZone::LeftLeg.damage_multiplier() < BodyZone::Stomach.damage_multiplier());
+    assert!(BodyZone::ALL.iter().filter(|zone| zone.is_critical()).eq([BodyZone::Head, BodyZone::Thorax].iter()));
+    // Una hitbox por zona
+    assert!(BodyZone::ALL.iter().all(|zone| ZONE_HITBOXES.iter().filter(|hitbox| hitbox.zone == *zone).count() == 1));
+}
+
+#[test]
+fn shots_hurt_the_zone_they_hit_and_the_owner_sees_it() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].body() == Some(&BodyHealth::default())));
+
+    let pistol = h.give(a, ana, "pistol", 1);
+    h.give(a, ana, "ammo_9mm", 15);
+    h.clients[a].inventory_action(InventoryAction::Equip { item: pistol.id, slot: EquipmentSlot::Holster });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].weapon().status().is_some()));
+    h.clients[a].tap(KeyCode::KeyR);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].weapon().status().is_some_and(|status| status.can_fire())));
+
+    // Ana, un poco a la derecha, apunta a la pierna derecha de Bea a 5 m
+    h.teleport(ana, Vec3::new(0.1, 0.0, 0.0));
+    h.teleport(bea, Vec3::new(0.0, 0.0, -5.0));
+    h.step_for(20);
+    h.clients[a].set_yaw(0.0);
+    h.clients[a].set_pitch(-(1.2f32 / 5.0).atan());
+    h.step_for(12);
+    h.clients[a].hold_mouse(MouseButton::Left);
+    h.step_for(2);
+    h.clients[a].release_mouse(MouseButton::Left);
+
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].weapon().last_hit().is_some()));
+    let damage = h.clients[b].weapon().last_hit().unwrap().2;
+    // 30 de la pistola por 0.7 de la pierna
+    assert!(damage > 29.0 * 0.7 && damage <= 30.0 * 0.7, "{damage}");
+    let server_body = h.server.component::<BodyHealth>(bea).unwrap();
+    assert_eq!(server_body.get(BodyZone::RightLeg), 90.0 - damage);
+    assert!(BodyZone::ALL.iter().filter(|zone| **zone != BodyZone::RightLeg).all(|zone| server_body.get(*zone) == zone.max_health()));
+    assert_eq!(h.server.component::<Health>(bea).unwrap().current, server_body.health().current);
+
+    // El HUD de Bea lo ve; el de Ana sigue entero
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].body().is_some_and(|body| body.get(BodyZone::RightLeg) < 90.0)));
+    assert_eq!(h.clients[b].body(), Some(&server_body));
+    assert_eq!(h.clients[a].body(), Some(&BodyHealth::default()));
+}
+
+#[test]
+fn broken_legs_slow_the_player_down() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (_, ana) = h.join_grounded("ana");
+    h.step_for(2);
+    let (speed, sprint) = movement(&mut h, ana);
+    assert!(sprint);
+
+    hurt(&mut h, ana, BodyZone::LeftLeg, 0.0);
+    h.step_for(2);
+    assert_eq!(movement(&mut h, ana), (speed * BROKEN_LEG_SPEED, false));
+
+    // Lo que deja el equipo no cambia, se multiplica
+    hurt(&mut h, ana, BodyZone::RightLeg, 0.0);
+    h.step_for(2);
+    assert_eq!(movement(&mut h, ana), (speed * BROKEN_LEGS_SPEED, false));
+    assert_eq!(h.server.component::<GearModifiers>(ana).unwrap().speed_multiplier, speed);
+}
+
+#[test]
+fn destroying_the_thorax_kills() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (a, ana) = h.join_grounded("ana");
+
+    hurt(&mut h, ana, BodyZone::Stomach, 0.0);
+    h.step_for(2);
+    assert!(h.server.component::<Health>(ana).unwrap().current > 0.0, "el estómago no es crítico");
+
+    hurt(&mut h, ana, BodyZone::Thorax, 0.0);
+    h.step_for(2);
+    assert_eq!(h.server.component::<Health>(ana).unwrap().current, 0.0);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].body().is_some_and(|body| body.is_dead())));
+}
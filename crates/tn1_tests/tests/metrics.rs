@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use tn1_server::metrics::{MetricsAddress, MetricsPlugin, MetricsSettings};
+use tn1_server::tls::ServerTls;
+use tn1_shared::codec::WireCodec;
+use tn1_shared::protocol::*;
+use tn1_tests::*;
+
+fn metrics_harness() -> (Harness, SocketAddr) {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins(MetricsPlugin {
+            settings: MetricsSettings {
+                address: Some(SocketAddr::from(([127, 0, 0, 1], 0))),
+            },
+        });
+    });
+    let address = server.app.world().resource::<MetricsAddress>().0;
+    (Harness { server, clients: Vec::new() }, address)
+}
+
+/// Respuesta HTTP completa (cabeceras y cuerpo)
+fn http_get(address: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+/// Valor de una serie exacta (`nombre{etiquetas}`) en el texto de Prometheus
+fn metric(body: &str, series: &str) -> Option<f64> {
+    body.lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' ')?.parse().ok())
+}
+
+#[test]
+fn metrics_endpoint_reports_session_activity() {
+    let (mut h, address) = metrics_harness();
+    let a = h.join("metrics_a");
+    h.join("metrics_b");
+
+    h.client(a).hold(KeyCode::KeyW);
+    h.step_for(20);
+    h.client(a).release(KeyCode::KeyW);
+
+    // El guardado periódico pasa por el worker de base de datos
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| !h.server.store.saved_players().is_empty()));
+    h.step();
+
+    let response = http_get(address, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+    let body = response.split("\r\n\r\n").nth(1).unwrap();
+
+    assert_eq!(metric(body, "tn1_players_connected"), Some(2.0));
+    assert_eq!(metric(body, "tn1_join_queue_length"), Some(0.0));
+    assert_eq!(metric(body, "tn1_auth_total{result=\"success\"}"), Some(2.0));
+    assert_eq!(metric(body, "tn1_auth_total{result=\"failure\"}"), Some(0.0));
+
+    assert!(metric(body, "tn1_tick_duration_seconds_count").unwrap() as u32 >= h.server.tick());
+    assert_eq!(
+        metric(body, "tn1_tick_duration_seconds_bucket{le=\"+Inf\"}"),
+        metric(body, "tn1_tick_duration_seconds_count")
+    );
+
+    assert_eq!(metric(body, "tn1_messages_total{direction=\"in\",message=\"hello\"}"), Some(2.0));
+    assert_eq!(metric(body, "tn1_messages_total{direction=\"in\",message=\"login\"}"), Some(2.0));
+    assert!(metric(body, "tn1_messages_total{direction=\"in\",message=\"player_input\"}").unwrap() > 0.0);
+    assert!(metric(body, "tn1_message_bytes_total{direction=\"out\",message=\"world_state\"}").unwrap() > 0.0);
+    assert_eq!(metric(body, "tn1_messages_total{direction=\"out\",message=\"connected\"}"), Some(2.0));
+
+    assert!(metric(body, "tn1_db_operation_duration_seconds_count{operation=\"save_player_state\"}").unwrap() >= 1.0);
+    // Cuentas nuevas: el worker busca la cuenta y, al no existir, la crea
+    assert_eq!(metric(body, "tn1_db_operation_duration_seconds_count{operation=\"authenticate_player\"}"), Some(2.0));
+    assert_eq!(metric(body, "tn1_db_operation_duration_seconds_count{operation=\"create_player\"}"), Some(2.0));
+    assert!(metric(body, "tn1_db_queue_depth").is_some());
+}
+
+#[test]
+fn rejected_handshakes_are_counted() {
+    let (mut h, address) = metrics_harness();
+
+    let mut old = RawClient::connect(h.server.address).unwrap();
+    old.send(&ClientMessage::Hello {
+        protocol_version: MIN_PROTOCOL_VERSION - 1,
+        client_name: "old_client".to_string(),
+        codecs: WireCodec::SUPPORTED.to_vec(),
+    })
+    .unwrap();
+    let mut rude = RawClient::connect(h.server.address).unwrap();
+    rude.send(&ClientMessage::Ping { timestamp: 0.0 }).unwrap();
+
+    let counted = |reason: &str| {
+        let body = http_get(address, "/metrics");
+        metric(&body, &format!("tn1_connections_rejected_total{{reason=\"{}\"}}", reason))
+    };
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        counted("protocol_version") == Some(1.0) && counted("expected_hello") == Some(1.0)
+    }));
+}
+
+#[test]
+fn unknown_paths_return_not_found() {
+    let (_h, address) = metrics_harness();
+    assert!(http_get(address, "/").starts_with("HTTP/1.1 404"));
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(b"POST /metrics HTTP/1.1\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 405"));
+}
+
+#[test]
+fn refused_logins_are_counted() {
+    let (mut h, address) = metrics_harness();
+    h.join("metrics_a");
+
+    // Cuenta existente, contraseña equivocada
+    let mut intruder = RawClient::connect(h.server.address).unwrap();
+    intruder.hello("intruder", WireCodec::SUPPORTED.to_vec()).unwrap();
+    let mut received = Vec::new();
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        received.extend(intruder.poll().unwrap());
+        received.iter().any(|message| matches!(message, ServerMessage::Welcome { .. }))
+    }));
+    intruder.send(&ClientMessage::Login { username: "metrics_a".to_string(), password: "wrong".to_string() }).unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        received.extend(intruder.poll().unwrap_or_default());
+        received.iter().any(|message| matches!(message, ServerMessage::AuthError { .. }))
+    }));
+    assert!(!received.iter().any(|message| matches!(message, ServerMessage::Connected { .. })));
+
+    let body = http_get(address, "/metrics");
+    assert_eq!(metric(&body, "tn1_auth_total{result=\"success\"}"), Some(1.0));
+    assert_eq!(metric(&body, "tn1_auth_total{result=\"failure\"}"), Some(1.0));
+    assert_eq!(metric(&body, "tn1_db_operation_duration_seconds_count{operation=\"authenticate_player\"}"), Some(2.0));
+    assert_eq!(h.server.connected_players(), 1);
+}
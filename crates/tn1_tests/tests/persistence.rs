@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use tn1_server::database::PlayerStore;
+use tn1_shared::components::PlayerController;
+use tn1_tests::*;
+
+#[test]
+fn player_positions_are_saved_periodically() {
+    let mut h = Harness::new();
+    let a = h.join("saver");
+    let id = h.clients[a].player_id().unwrap();
+
+    h.client(a).set_yaw(std::f32::consts::FRAC_PI_2);
+    h.client(a).hold(KeyCode::KeyW);
+    h.step_for(30);
+    h.client(a).release(KeyCode::KeyW);
+
+    // Quieto y en el suelo antes de comparar con lo guardado
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.server.component::<PlayerController>(id).unwrap().velocity.length() < 0.01
+            && h.server.player(id).unwrap().translation.y < 0.01
+    }));
+    let transform = h.server.player(id).unwrap();
+    assert!(transform.translation.x < -1.0, "yaw de 90° debería mover hacia -X: {:?}", transform.translation);
+
+    // El guardado corre cada 5 s de juego en el worker de persistencia
+    let store = h.server.store.clone();
+    assert!(
+        h.step_until(NETWORK_TIMEOUT, |_| {
+            store.load_player_state(id.0).unwrap().is_some_and(|state| {
+                Vec3::new(state.position_x, state.position_y, state.position_z).distance(transform.translation) < 0.01
+            })
+        }),
+        "no se guardó la posición {:?}",
+        transform.translation
+    );
+
+    let state = store.load_player_state(id.0).unwrap().unwrap();
+    let rotation = Quat::from_xyzw(state.rotation_x, state.rotation_y, state.rotation_z, state.rotation_w);
+    assert!(rotation.angle_between(transform.rotation) < 0.01);
+    assert_eq!(store.saved_players(), vec![id.0]);
+}
+
+#[test]
+fn nothing_is_saved_without_players() {
+    let mut h = Harness::new();
+    h.step_for(6 * 60);
+    assert!(h.server.store.saved_players().is_empty());
+}
@@ -0,0 +1,109 @@
+use bevy::prelude::*;
+use tn1_shared::codec::WireCodec;
+use tn1_shared::components::PlayerController;
+use tn1_shared::constants::PLAYER_MAX_HEALTH;
+use tn1_shared::protocol::*;
+use tn1_tests::*;
+
+#[test]
+fn movement_input_is_replicated() {
+    let mut h = Harness::new();
+    let a = h.join("mover");
+    let observer = h.join("observer");
+    let id = h.clients[a].player_id().unwrap();
+    let start = h.server.player(id).unwrap().translation;
+
+    // Yaw 0: "adelante" es -Z
+    h.client(a).set_yaw(0.0);
+    h.client(a).hold(KeyCode::KeyW);
+    assert!(
+        h.step_until(NETWORK_TIMEOUT, |h| h.server.player(id).unwrap().translation.z < start.z - 2.0),
+        "el servidor no aplicó el input de movimiento"
+    );
+    h.client(a).release(KeyCode::KeyW);
+
+    let moved = h.server.player(id).unwrap().translation;
+    assert!((moved.x - start.x).abs() < 0.01, "se movió de lado: {:?} -> {:?}", start, moved);
+
+    // Esperar a que el jugador se detenga y toque suelo
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.server.component::<PlayerController>(id).unwrap().velocity.length() < 0.01
+    }));
+
+    let server_position = h.server.player(id).unwrap().translation;
+    assert!(server_position.y.abs() < 0.01, "el jugador no aterrizó: {:?}", server_position);
+
+    // El cliente que se mueve y un tercero convergen a la posición del servidor
+    assert!(
+        h.step_until(NETWORK_TIMEOUT, |h| {
+            let local = h.clients[a].local_position();
+            let remote = h.clients[observer].known_position(id);
+            local.is_some_and(|p| p.distance(server_position) < 0.05)
+                && remote.is_some_and(|p| p.distance(server_position) < 0.05)
+        }),
+        "los clientes no convergieron a {:?}",
+        server_position
+    );
+}
+
+#[test]
+fn jump_only_leaves_the_ground_once_landed() {
+    let mut h = Harness::new();
+    let a = h.join("jumper");
+    let id = h.clients[a].player_id().unwrap();
+
+    // Spawn en el aire: aterrizar primero
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.player(id).unwrap().translation.y < 0.01));
+
+    h.client(a).hold(KeyCode::Space);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.player(id).unwrap().translation.y > 0.5));
+    h.client(a).release(KeyCode::Space);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.player(id).unwrap().translation.y < 0.01));
+}
+
+#[test]
+fn world_state_contains_every_player() {
+    let mut h = Harness::new();
+    let a = h.join("state_a");
+    let b = h.join("state_b");
+    let ids = [h.clients[a].player_id().unwrap(), h.clients[b].player_id().unwrap()];
+
+    let mut raw = RawClient::connect(h.server.address).unwrap();
+    raw.hello("state_raw", vec![WireCodec::Json]).unwrap();
+    let mut raw_id = None;
+    let mut snapshots = Vec::new();
+
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        for message in raw.poll().unwrap() {
+            match message {
+                ServerMessage::Welcome { .. } => raw
+                    .send(&ClientMessage::Login {
+                        username: "state_raw".to_string(),
+                        password: "secret".to_string(),
+                    })
+                    .unwrap(),
+                ServerMessage::Connected { player_id, .. } => raw_id = Some(player_id),
+                ServerMessage::WorldState { tick, players, timestamp } => snapshots.push((tick, players, timestamp)),
+                _ => {}
+            }
+        }
+        snapshots.len() >= 10
+    }));
+
+    // Un snapshot cada 2 ticks, en orden y con el reloj del servidor avanzando
+    for pair in snapshots.windows(2) {
+        let ((tick_a, _, time_a), (tick_b, _, time_b)) = (&pair[0], &pair[1]);
+        assert_eq!(tick_b - tick_a, 2, "ticks {} -> {}", tick_a, tick_b);
+        assert!(tick_b % 2 == 0);
+        assert!(time_b > time_a);
+    }
+
+    let (tick, players, _) = snapshots.last().unwrap();
+    assert!(*tick <= h.server.tick());
+    assert_eq!(players.len(), 3);
+    for id in ids.iter().chain(raw_id.iter()) {
+        let state = players.iter().find(|state| state.player_id == *id).expect("falta un jugador en el WorldState");
+        assert_eq!(state.health, PLAYER_MAX_HEALTH);
+        assert!(state.position.x.abs() <= 25.0 && state.position.z.abs() <= 25.0);
+    }
+}
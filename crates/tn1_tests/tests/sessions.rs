@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use tn1_shared::codec::WireCodec;
+use tn1_shared::conditioner::{LinkConditions, NetworkConditions};
+use tn1_shared::protocol::{ClientMessage, ServerMessage};
+use tn1_tests::*;
+
+/// Saluda y pide entrar como `username`; devuelve lo recibido hasta que llega lo que busca `done`
+fn login_until(h: &mut Harness, username: &str, password: &str, done: impl Fn(&ServerMessage) -> bool) -> Vec<ServerMessage> {
+    let mut raw = RawClient::connect(h.server.address).unwrap();
+    raw.hello(username, WireCodec::SUPPORTED.to_vec()).unwrap();
+    let mut received = Vec::new();
+    // El login ya va con el codec que elige el `Welcome`
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        received.extend(raw.poll().unwrap());
+        received.iter().any(|message| matches!(message, ServerMessage::Welcome { .. }))
+    }));
+    raw.send(&ClientMessage::Login { username: username.to_string(), password: password.to_string() }).unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        received.extend(raw.poll().unwrap_or_default());
+        received.iter().any(&done)
+    }), "{received:?}");
+    received
+}
+
+#[test]
+fn players_join_and_see_each_other() {
+    let mut h = Harness::new();
+    let a = h.join("join_a");
+    let b = h.join("join_b");
+
+    assert_eq!(h.server.connected_players(), 2);
+    assert_eq!(h.server.players().len(), 2);
+
+    let id_a = h.clients[a].player_id().unwrap();
+    let id_b = h.clients[b].player_id().unwrap();
+    assert_ne!(id_a, id_b);
+
+    // Cada cliente recibe al otro por WorldState
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[a].known_position(id_b).is_some() && h.clients[b].known_position(id_a).is_some()
+    }));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].local_position().is_some()));
+}
+
+#[test]
+fn leaving_player_is_removed_everywhere() {
+    let mut h = Harness::new();
+    let a = h.join("leave_a");
+    let b = h.join("leave_b");
+    let id_a = h.clients[a].player_id().unwrap();
+
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].known_position(id_a).is_some()));
+
+    h.client(a).disconnect();
+    assert!(!h.clients[a].is_connected());
+
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.connected_players() == 1));
+    assert!(h.server.player(id_a).is_none(), "la entidad del jugador sigue en el servidor");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].known_position(id_a).is_none()));
+}
+
+#[test]
+fn full_server_queues_and_admits_on_leave() {
+    let mut config = test_config();
+    config.max_players = 1;
+    let mut h = Harness::with_config(config);
+    let a = h.join("queue_a");
+
+    let b = h.connect("queue_b");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].network().queue_position == Some(1)));
+    assert!(!h.clients[b].is_connected());
+    assert_eq!(h.server.queue_length(), 1);
+    assert_eq!(h.server.connected_players(), 1);
+
+    // Al liberarse el hueco entra el primero de la cola
+    h.client(a).disconnect();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].is_connected()));
+    assert_eq!(h.clients[b].network().queue_position, None);
+    assert_eq!(h.server.queue_length(), 0);
+    assert_eq!(h.server.connected_players(), 1);
+}
+
+#[test]
+fn full_queue_rejects_new_players() {
+    let mut config = test_config();
+    config.max_players = 1;
+    config.max_queue = 0;
+    let mut h = Harness::with_config(config);
+    h.join("reject_a");
+
+    let b = h.connect("reject_b");
+    h.step_for(60);
+    assert!(!h.clients[b].is_connected());
+    assert_eq!(h.server.queue_length(), 0);
+    assert_eq!(h.server.connected_players(), 1);
+}
+
+#[test]
+fn refusals_arrive_before_the_connection_closes_on_a_laggy_link() {
+    let mut config = test_config();
+    config.max_players = 1;
+    config.max_queue = 0;
+    let mut h = Harness::with_config(config);
+    h.join("laggy_a");
+
+    // Con latencia el rechazo sale del servidor después de haber pedido cerrar
+    h.server.app.world().resource::<NetworkConditions>().set(LinkConditions { latency_ms: 100, ..default() });
+    login_until(&mut h, "laggy_b", "secret", |message| matches!(message, ServerMessage::ConnectionError { .. }));
+    assert_eq!(h.server.connected_players(), 1);
+}
+
+#[test]
+fn auth_errors_arrive_before_the_connection_closes_on_a_laggy_link() {
+    let mut h = Harness::new();
+    h.join("laggy_owner");
+
+    h.server.app.world().resource::<NetworkConditions>().set(LinkConditions { latency_ms: 100, ..default() });
+    let received = login_until(&mut h, "laggy_owner", "wrong", |message| matches!(message, ServerMessage::AuthError { .. }));
+    assert!(!received.iter().any(|message| matches!(message, ServerMessage::Connected { .. })));
+    assert_eq!(h.server.connected_players(), 1);
+}
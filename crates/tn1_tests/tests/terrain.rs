@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use tn1_client::chunks::{ChunkTerrain, WorldChunk};
+use tn1_server::chunks::{ChunkPlugin, ChunkSettings};
+use tn1_server::tls::ServerTls;
+use tn1_server::world::{WorldPlugin, WorldSettings, WorldTerrain};
+use tn1_shared::chunk::*;
+use tn1_shared::terrain::*;
+use tn1_tests::*;
+
+const SEED: u64 = 7;
+
+fn terrain_harness(view_radius: u32) -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((
+            WorldPlugin,
+            ChunkPlugin { settings: ChunkSettings { view_radius, keep_loaded: 0.5, send_budget: 8 } },
+        ))
+        .insert_resource(WorldSettings { world_size: 200.0, chunk_size: 10.0, seed: SEED, ..default() });
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+/// Altura del collider del servidor en la posición
+fn collider_height(grid: &ChunkGrid, position: Vec3) -> f32 {
+    let coord = grid.coord_at(position);
+    let local = position - grid.origin(coord);
+    TerrainGenerator::new(SEED).heightfield(grid, coord, COLLIDER_RESOLUTION).height_at(local.x, local.z)
+}
+
+#[test]
+fn terrain_is_deterministic_and_varied() {
+    let terrain = TerrainGenerator::new(1);
+    let points: Vec<(f32, f32)> = (-25..25)
+        .flat_map(|x| (-25..25).map(move |z| (x as f32 * 100.0 + 13.0, z as f32 * 100.0 - 7.0)))
+        .collect();
+    let samples: Vec<TerrainSample> = points.iter().map(|(x, z)| terrain.sample(*x, *z)).collect();
+
+    // Misma semilla, mismo terreno; otra semilla, otro mapa
+    assert!(points.iter().zip(&samples).all(|((x, z), sample)| TerrainGenerator::new(1).sample(*x, *z) == *sample));
+    let other = TerrainGenerator::new(2);
+    assert!(points.iter().zip(&samples).any(|((x, z), sample)| other.height(*x, *z) != sample.height));
+
+    let (min, max) = samples
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), sample| (min.min(sample.height), max.max(sample.height)));
+    assert!(max - min > 30.0, "terreno demasiado plano: {min}..{max}");
+
+    let mut biomes: Vec<Biome> = samples.iter().map(|sample| sample.biome).collect();
+    biomes.sort_by_key(|biome| *biome as u8);
+    biomes.dedup();
+    assert!(biomes.len() >= 4, "pocos biomas: {biomes:?}");
+    assert!(biomes.contains(&Biome::River));
+    // Los ríos excavan el terreno: van por debajo de la media
+    let mean = |river: bool| {
+        let heights: Vec<f32> =
+            samples.iter().filter(|sample| (sample.biome == Biome::River) == river).map(|sample| sample.height).collect();
+        heights.iter().sum::<f32>() / heights.len() as f32
+    };
+    assert!(mean(true) < mean(false));
+    assert!(samples.iter().all(|sample| (sample.biome == Biome::River) == (sample.river > 0.5)));
+}
+
+#[test]
+fn heightfield_matches_generator_and_neighbours() {
+    let grid = ChunkGrid::new(5000.0, 100.0);
+    let terrain = TerrainGenerator::new(3);
+    let coord = ChunkCoord::new(2, -1);
+    let field = terrain.heightfield(&grid, coord, COLLIDER_RESOLUTION);
+    let origin = grid.origin(coord);
+    assert_eq!(field.heights.len(), (COLLIDER_RESOLUTION * COLLIDER_RESOLUTION) as usize);
+
+    // En los vértices, la altura exacta del generador
+    for (column, row) in [(0, 0), (5, 7), (32, 32), (32, 0)] {
+        let (x, z) = (column as f32 * field.step(), row as f32 * field.step());
+        assert_eq!(field.vertex(column, row), terrain.height(origin.x + x, origin.z + z));
+        assert!((field.height_at(x, z) - field.vertex(column, row)).abs() < 1e-3);
+    }
+
+    // Entre vértices, interpolado dentro de la celda
+    let (x, z) = (field.step() * 3.25, field.step() * 8.5);
+    let corners = [field.vertex(3, 8), field.vertex(4, 8), field.vertex(3, 9), field.vertex(4, 9)];
+    let height = field.height_at(x, z);
+    assert!(corners.iter().any(|corner| *corner <= height + 1e-3) && corners.iter().any(|corner| *corner >= height - 1e-3));
+
+    // El borde es el mismo que el del vecino: no hay escalones entre chunks
+    let east = terrain.heightfield(&grid, ChunkCoord::new(3, -1), COLLIDER_RESOLUTION);
+    for row in 0..COLLIDER_RESOLUTION {
+        assert_eq!(field.vertex(COLLIDER_RESOLUTION - 1, row), east.vertex(0, row));
+    }
+}
+
+#[test]
+fn lod_drops_with_distance() {
+    assert_eq!(lod_for_distance(0), 0);
+    assert_eq!(lod_for_distance(1), 0);
+    assert_eq!(lod_for_distance(2), 1);
+    assert_eq!(lod_for_distance(3), 2);
+    assert_eq!(lod_for_distance(10), 2);
+    assert!(LOD_RESOLUTIONS.windows(2).all(|pair| pair[0] > pair[1]));
+    // Las resoluciones menores caen sobre vértices de la mayor
+    assert!(LOD_RESOLUTIONS.iter().all(|resolution| (COLLIDER_RESOLUTION - 1).is_multiple_of(resolution - 1)));
+}
+
+#[test]
+fn players_stand_on_the_terrain() {
+    let mut h = terrain_harness(1);
+    let a = h.join("ana");
+    let player_id = h.clients[a].player_id().unwrap();
+    let grid = ChunkGrid::new(200.0, 10.0);
+
+    let on_ground = |h: &mut Harness| {
+        let position = h.server.player(player_id).unwrap().translation;
+        (position.y - collider_height(&grid, position)).abs() < 1e-3
+    };
+    assert!(h.step_until(NETWORK_TIMEOUT, on_ground));
+
+    // Andando se sigue el relieve, sin hundirse ni flotar
+    h.client(a).hold(KeyCode::KeyW);
+    let start = h.server.player(player_id).unwrap().translation;
+    for _ in 0..120 {
+        h.step();
+        let position = h.server.player(player_id).unwrap().translation;
+        assert!(position.y >= collider_height(&grid, position) - 1e-3, "bajo el suelo en {position}");
+    }
+    h.client(a).release(KeyCode::KeyW);
+    assert!(h.server.player(player_id).unwrap().translation.z < start.z - 5.0);
+    assert!(h.step_until(NETWORK_TIMEOUT, on_ground));
+
+    // Solo quedan colliders donde hay jugadores
+    let position = h.server.player(player_id).unwrap().translation;
+    let colliders: Vec<ChunkCoord> = h.server.app.world().resource::<WorldTerrain>().colliders().collect();
+    assert!(colliders.contains(&grid.coord_at(position)));
+    assert!(colliders.iter().all(|coord| coord.distance(grid.coord_at(position)) <= 1));
+}
+
+#[test]
+fn client_builds_terrain_with_lod_by_distance() {
+    let mut h = terrain_harness(3);
+    let a = h.connect("ana");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].chunks().spawned().count() == 49));
+    // Un par de frames más para que se apliquen los cambios de LOD pendientes
+    h.step_for(5);
+
+    let center = ChunkCoord::new(0, 0);
+    let chunks = h.clients[a].chunks();
+    assert_eq!(chunks.terrain(), Some(TerrainGenerator::new(SEED)));
+    for coord in chunks.spawned() {
+        assert_eq!(chunks.lod(coord), Some(lod_for_distance(coord.distance(center))), "chunk {coord}");
+    }
+
+    let world = h.client(a).app.world_mut();
+    let mut lods: Vec<usize> = world.query::<&ChunkTerrain>().iter(world).map(|terrain| terrain.lod).collect();
+    lods.sort();
+    // 9 de detalle máximo, el anillo de 16 y el de 24
+    assert_eq!(lods.iter().filter(|lod| **lod == 0).count(), 9);
+    assert_eq!(lods.iter().filter(|lod| **lod == 1).count(), 16);
+    assert_eq!(lods.iter().filter(|lod| **lod == 2).count(), 24);
+
+    // El mesh de cerca es exactamente el collider del servidor
+    let grid = ChunkGrid::new(200.0, 10.0);
+    let field = TerrainGenerator::new(SEED).heightfield(&grid, center, COLLIDER_RESOLUTION);
+    let mut terrains = world.query::<(&ChunkTerrain, &Mesh3d, &Parent)>();
+    let world = &*world;
+    let mesh = terrains
+        .iter(world)
+        .find(|(_, _, parent)| world.get::<WorldChunk>(parent.get()).unwrap().coord == center)
+        .map(|(terrain, mesh, _)| (terrain.lod, mesh.0.clone()))
+        .unwrap();
+    assert_eq!(mesh.0, 0);
+    let meshes = world.resource::<Assets<Mesh>>();
+    let positions = meshes.get(&mesh.1).unwrap().attribute(Mesh::ATTRIBUTE_POSITION).unwrap().as_float3().unwrap();
+    assert!(field.heights.iter().zip(positions).all(|(height, position)| *height == position[1]));
+}
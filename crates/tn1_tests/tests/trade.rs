@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+use tn1_server::database::PlayerStore;
+use tn1_server::inventory::InventoryPlugin;
+use tn1_server::items::ItemRegistryPlugin;
+use tn1_server::tls::ServerTls;
+use tn1_server::trade::{TradePlugin, TradeSettings};
+use tn1_shared::components::PlayerId;
+use tn1_shared::inventory::*;
+use tn1_shared::trade::*;
+use tn1_tests::*;
+
+fn trade_settings() -> TradeSettings {
+    TradeSettings { distance: 3.0, countdown: 0.5, request_timeout: 30.0 }
+}
+
+fn trade_harness(settings: TradeSettings) -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((ItemRegistryPlugin { settings: item_settings() }, InventoryPlugin, TradePlugin { settings }));
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+fn rejected(h: &mut Harness, client: usize, sequence: u32) -> String {
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[client].inventory_rejection().is_some_and(|(seq, _)| *seq == sequence)));
+    h.clients[client].inventory_rejection().unwrap().1.clone()
+}
+
+fn has_uuid(h: &Harness, client: usize, uuid: uuid::Uuid) -> bool {
+    h.clients[client].inventory().unwrap().items().iter().any(|(_, placed)| placed.stack.uuid == uuid)
+}
+
+/// Ana propone, Bea acepta y las dos ven la ventana abierta
+fn open_trade(h: &mut Harness, (a, b): (usize, usize), bea: PlayerId) -> u64 {
+    h.clients[a].trade(TradeAction::Request { player: bea });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].trade_window().is_some()));
+    let window = h.clients[b].trade_window().unwrap().clone();
+    assert_eq!((window.phase, window.requested, window.partner_name.as_str()), (TradePhase::Pending, false, "ana"));
+    h.clients[b].trade(TradeAction::Accept { trade: window.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        [a, b].iter().all(|c| h.clients[*c].trade_window().is_some_and(|window| window.phase == TradePhase::Open))
+    }));
+    window.id
+}
+
+fn phase(h: &Harness, client: usize) -> Option<TradePhase> {
+    h.clients[client].trade_window().map(|window| window.phase)
+}
+
+#[test]
+fn confirmed_trades_swap_items_atomically_and_get_logged() {
+    let mut h = trade_harness(trade_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    let pistol = h.give(a, ana, "pistol", 1);
+    let bandage = h.give(a, ana, "bandage", 2);
+    let food = h.give(b, bea, "canned_food", 1);
+
+    // Nada se ofrece antes de que el otro acepte
+    h.clients[a].trade(TradeAction::Request { player: bea });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].trade_window().is_some_and(|window| window.requested)));
+    let sequence = h.clients[a].trade(TradeAction::Offer { item: pistol.id });
+    assert_eq!(rejected(&mut h, a, sequence), TradeError::NotAccepted.to_string());
+    let trade = h.clients[b].trade_window().unwrap().id;
+    h.clients[b].trade(TradeAction::Accept { trade });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| phase(h, a) == Some(TradePhase::Open)));
+
+    // Cada una pone lo suyo y la otra lo ve
+    h.clients[a].trade(TradeAction::Offer { item: pistol.id });
+    h.clients[b].trade(TradeAction::Offer { item: food.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        let window = h.clients[b].trade_window().unwrap();
+        window.theirs.items.iter().any(|stack| stack.uuid == pistol.uuid) && window.mine.items.iter().any(|stack| stack.uuid == food.uuid)
+    }));
+    let sequence = h.clients[a].trade(TradeAction::Offer { item: pistol.id });
+    assert_eq!(rejected(&mut h, a, sequence), TradeError::AlreadyOffered.to_string());
+
+    // Con las dos confirmaciones se bloquea: ya no se puede añadir nada
+    h.clients[b].trade(TradeAction::Confirm);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].trade_window().unwrap().theirs.confirmed));
+    h.clients[a].trade(TradeAction::Confirm);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| [a, b].iter().all(|c| matches!(phase(h, *c), Some(TradePhase::Locked { .. })))));
+    let sequence = h.clients[a].trade(TradeAction::Offer { item: bandage.id });
+    assert_eq!(rejected(&mut h, a, sequence), TradeError::Locked.to_string());
+    assert!(has_uuid(&h, a, pistol.uuid), "hasta el final nada sale del inventario");
+
+    // Al acabar la cuenta atrás cada una tiene lo de la otra
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| [a, b].iter().all(|c| h.clients[*c].last_trade_closed().is_some_and(|closed| closed.1))));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| has_uuid(h, b, pistol.uuid) && has_uuid(h, a, food.uuid)));
+    assert!(!has_uuid(&h, a, pistol.uuid) && !has_uuid(&h, b, food.uuid));
+    assert!(has_uuid(&h, a, bandage.uuid));
+    assert!(h.clients[a].trade_window().is_none() && h.clients[b].trade_window().is_none());
+
+    // El registro y los dos inventarios se guardan juntos
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.store.trade_log().len() == 1));
+    let record = h.server.store.trade_log().remove(0);
+    assert_eq!(record.players, [ana.0, bea.0]);
+    assert_eq!(record.usernames, ["ana".to_string(), "bea".to_string()]);
+    let moved: Vec<_> = record.items.iter().map(|item| (item.id, item.from, item.to)).collect();
+    assert_eq!(moved, vec![(pistol.uuid, ana.0, bea.0), (food.uuid, bea.0, ana.0)]);
+    assert!(record.inventories.iter().all(Option::is_some));
+    let saved = h.server.store.load_inventory(bea.0).unwrap();
+    assert!(saved.iter().any(|item| item.id == pistol.uuid));
+    assert!(!h.server.store.load_inventory(ana.0).unwrap().iter().any(|item| item.id == pistol.uuid));
+}
+
+#[test]
+fn changing_an_offered_item_unlocks_the_trade() {
+    let mut h = trade_harness(TradeSettings { countdown: 30.0, ..trade_settings() });
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    let bandage = h.give(a, ana, "bandage", 3);
+    open_trade(&mut h, (a, b), bea);
+
+    h.clients[a].trade(TradeAction::Offer { item: bandage.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| !h.clients[b].trade_window().unwrap().theirs.items.is_empty()));
+    h.clients[a].trade(TradeAction::Confirm);
+    h.clients[b].trade(TradeAction::Confirm);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| matches!(phase(h, b), Some(TradePhase::Locked { .. }))));
+
+    // Partir el montón en el último momento cambia la oferta y quita las confirmaciones
+    let to = Slot { container: ContainerId::Pocket(1), x: 0, y: 0, rotated: false };
+    h.clients[a].inventory_action(InventoryAction::Split { item: bandage.id, quantity: 1, to });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| phase(h, b) == Some(TradePhase::Open)));
+    let window = h.clients[b].trade_window().unwrap();
+    assert_eq!(window.theirs.items[0].quantity, 2);
+    assert!(!window.mine.confirmed && !window.theirs.confirmed);
+
+    // Si desaparece del inventario sale de la oferta
+    h.clients[a].inventory_action(InventoryAction::Drop { item: bandage.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].trade_window().unwrap().theirs.items.is_empty()));
+    let sequence = h.clients[b].trade(TradeAction::Confirm);
+    assert_eq!(rejected(&mut h, b, sequence), TradeError::Empty.to_string());
+}
+
+#[test]
+fn trades_are_validated_and_cancelled() {
+    let mut h = trade_harness(trade_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    let (c, _) = h.join_grounded("cris");
+
+    let sequence = h.clients[a].trade(TradeAction::Request { player: ana });
+    assert_eq!(rejected(&mut h, a, sequence), TradeError::WithYourself.to_string());
+    let sequence = h.clients[a].trade(TradeAction::Request { player: PlayerId(uuid::Uuid::new_v4()) });
+    assert_eq!(rejected(&mut h, a, sequence), TradeError::UnknownPlayer.to_string());
+    let sequence = h.clients[a].trade(TradeAction::Confirm);
+    assert_eq!(rejected(&mut h, a, sequence), TradeError::NoTrade.to_string());
+    h.teleport(bea, Vec3::new(10.0, 0.0, 0.0));
+    let sequence = h.clients[a].trade(TradeAction::Request { player: bea });
+    assert_eq!(rejected(&mut h, a, sequence), TradeError::TooFar.to_string());
+    h.teleport(bea, Vec3::new(1.0, 0.0, 0.0));
+
+    // Solo acepta a quien se lo han propuesto, y nadie está en dos a la vez
+    h.clients[a].trade(TradeAction::Request { player: bea });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].trade_window().is_some()));
+    let trade = h.clients[b].trade_window().unwrap().id;
+    let sequence = h.clients[c].trade(TradeAction::Accept { trade });
+    assert_eq!(rejected(&mut h, c, sequence), TradeError::NotInvited.to_string());
+    let sequence = h.clients[c].trade(TradeAction::Request { player: bea });
+    assert_eq!(rejected(&mut h, c, sequence), TradeError::Busy.to_string());
+
+    // Bea rechaza
+    h.clients[b].trade(TradeAction::Cancel);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| [a, b].iter().all(|c| h.clients[*c].last_trade_closed().is_some())));
+    let (_, completed, reason) = h.clients[a].last_trade_closed().unwrap();
+    assert!(!completed && reason.contains("bea"), "{reason}");
+    assert!(h.clients[a].trade_window().is_none());
+
+    // Alejarse lo cierra
+    let trade = open_trade(&mut h, (a, b), bea);
+    h.teleport(bea, Vec3::new(10.0, 0.0, 0.0));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| [a, b].iter().all(|c| h.clients[*c].last_trade_closed().is_some_and(|closed| closed.0 == trade))));
+    assert_eq!(h.clients[b].last_trade_closed().unwrap().2, TradeError::TooFar.to_string());
+    h.teleport(bea, Vec3::new(1.0, 0.0, 0.0));
+
+    // Y que el otro se vaya también
+    let trade = open_trade(&mut h, (a, b), bea);
+    h.client(a).disconnect();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].last_trade_closed().is_some_and(|closed| closed.0 == trade)));
+    assert!(h.clients[b].last_trade_closed().unwrap().2.contains("ana"));
+    assert!(h.server.store.trade_log().is_empty());
+}
+
+#[test]
+fn unanswered_requests_expire() {
+    let mut h = trade_harness(TradeSettings { request_timeout: 0.5, ..trade_settings() });
+    let (a, _) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    h.clients[a].trade(TradeAction::Request { player: bea });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].trade_window().is_some()));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].trade_window().is_none() && h.clients[a].last_trade_closed().is_some()));
+    assert!(h.clients[a].last_trade_closed().unwrap().2.contains("no ha respondido"));
+}
+
+#[test]
+fn a_trade_that_does_not_fit_changes_nothing() {
+    let mut h = trade_harness(trade_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    let bandage = h.give(a, ana, "bandage", 1);
+    // Cuatro cascos llenan los cuatro bolsillos de Bea
+    let helmets: Vec<ItemStack> = (0..4).map(|_| h.give(b, bea, "helmet", 1)).collect();
+    open_trade(&mut h, (a, b), bea);
+
+    // Bea no da nada a cambio, así que la venda no tiene dónde ir
+    h.clients[a].trade(TradeAction::Offer { item: bandage.id });
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| !h.clients[b].trade_window().unwrap().theirs.items.is_empty()));
+    h.clients[a].trade(TradeAction::Confirm);
+    h.clients[b].trade(TradeAction::Confirm);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| [a, b].iter().all(|c| h.clients[*c].last_trade_closed().is_some())));
+    let (_, completed, reason) = h.clients[a].last_trade_closed().unwrap().clone();
+    assert!(!completed && reason.contains("no se pudo hacer el cambio (bea)"), "{reason}");
+    h.step_for(5);
+    assert!(has_uuid(&h, a, bandage.uuid));
+    assert!(helmets.iter().all(|helmet| has_uuid(&h, b, helmet.uuid)));
+    assert!(h.server.store.trade_log().is_empty());
+}
@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+use tn1_client::voice::{JitterBuffer, JITTER_FRAMES};
+use tn1_server::chat::{ChatModeration, ChatPlugin};
+use tn1_server::tls::ServerTls;
+use tn1_server::voice::{VoicePlugin, VoiceSettings};
+use tn1_shared::voice::*;
+use tn1_tests::*;
+
+fn voice_harness() -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((
+            ChatPlugin::default(),
+            VoicePlugin { settings: VoiceSettings { range: 5.0, max_frames_per_second: 60 } },
+        ));
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+/// Tono de 440 Hz, 100 ms
+fn tone() -> Vec<i16> {
+    (0..SAMPLE_RATE as usize / 10)
+        .map(|i| ((i as f32 * 440.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin() * 8000.0) as i16)
+        .collect()
+}
+
+fn has_line(h: &Harness, client: usize, line: &str) -> bool {
+    h.clients[client].chat_lines().iter().any(|shown| shown == line)
+}
+
+/// Aleja a un cliente más allá del alcance de la voz de proximidad (5 m)
+fn walk_away(h: &mut Harness, client: usize) {
+    let player_id = h.clients[client].player_id().unwrap();
+    h.client(client).hold(KeyCode::KeyW);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.server.player(player_id).unwrap().translation.xz().length() > 8.0
+    }));
+    h.client(client).release(KeyCode::KeyW);
+}
+
+#[test]
+fn proximity_voice_only_reaches_nearby_players() {
+    let mut h = voice_harness();
+    let a = h.join("ana");
+    let b = h.join("bruno");
+    let c = h.join("carla");
+    let a_id = h.clients[a].player_id().unwrap();
+    walk_away(&mut h, b);
+
+    h.client(a).voice_input(tone());
+    h.client(a).hold(KeyCode::KeyV);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[c].hearing().iter().any(|(id, status)| *id == a_id && status.frames_played >= 10)
+    }));
+    h.client(a).release(KeyCode::KeyV);
+
+    let (_, status) = h.clients[c].hearing().into_iter().find(|(id, _)| *id == a_id).unwrap();
+    assert_eq!(status.channel, VoiceChannel::Proximity);
+    assert_eq!(status.gain, 1.0); // Carla está junto a Ana
+    assert!(status.position.is_some());
+    assert!(h.clients[b].hearing().is_empty());
+    assert!(h.clients[a].hearing().is_empty()); // Nadie se oye a sí mismo
+}
+
+#[test]
+fn radio_reaches_tuned_players_at_any_distance() {
+    let mut h = voice_harness();
+    let a = h.join("ana");
+    let b = h.join("bruno");
+    let c = h.join("carla");
+    let a_id = h.clients[a].player_id().unwrap();
+    walk_away(&mut h, b);
+
+    h.client(a).chat("/radio 7");
+    h.client(b).chat("/radio 7");
+    h.client(c).chat("/radio 1000");
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        has_line(h, a, "* Radio sintonizada en 7 (mantén B para hablar)")
+            && has_line(h, b, "* Radio sintonizada en 7 (mantén B para hablar)")
+            && has_line(h, c, "* Frecuencia inválida (1-999)")
+    }));
+
+    h.client(a).voice_input(tone());
+    h.client(a).hold(KeyCode::KeyB);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[b].hearing().iter().any(|(id, status)| *id == a_id && status.frames_played >= 10)
+    }));
+    h.client(a).release(KeyCode::KeyB);
+
+    let (_, status) = h.clients[b].hearing().into_iter().find(|(id, _)| *id == a_id).unwrap();
+    assert_eq!(status.channel, VoiceChannel::Radio { frequency: 7 });
+    assert_eq!(status.gain, 1.0);
+    assert_eq!(status.position, None);
+    // Carla está al lado pero la radio no se oye en proximidad
+    assert!(h.clients[c].hearing().is_empty());
+}
+
+#[test]
+fn muted_players_cannot_talk() {
+    let mut h = voice_harness();
+    let a = h.join("ana");
+    let b = h.join("bruno");
+    h.server.app.world_mut().resource_mut::<ChatModeration>().mute("ana", 0.0, 3600.0);
+
+    h.client(a).voice_input(tone());
+    h.client(a).hold(KeyCode::KeyV);
+    h.step_for(120);
+    assert!(h.clients[b].hearing().is_empty());
+}
+
+#[test]
+fn jitter_buffer_reorders_and_fills_gaps() {
+    let mut buffer = JitterBuffer::default();
+    buffer.push(1, vec![1]);
+    buffer.push(0, vec![0]);
+    assert_eq!(buffer.pop(), None); // Aún no hay colchón
+    buffer.push(2, vec![2]);
+    assert_eq!(buffer.len(), JITTER_FRAMES);
+
+    assert_eq!(buffer.pop(), Some(Some(vec![0])));
+    buffer.push(4, vec![4]); // El 3 se pierde
+    assert_eq!(buffer.pop(), Some(Some(vec![1])));
+    assert_eq!(buffer.pop(), Some(Some(vec![2])));
+    assert_eq!(buffer.pop(), Some(None));
+    assert_eq!(buffer.pop(), Some(Some(vec![4])));
+
+    buffer.push(3, vec![3]); // Demasiado tarde
+    assert!(buffer.is_empty());
+    assert_eq!(buffer.pop(), None);
+}
+
+#[test]
+fn wav_files_feed_the_voice_input() {
+    let path = std::env::temp_dir().join(format!("tn1-voice-{}.wav", uuid::Uuid::new_v4()));
+    // WAV mono a 16 kHz: se remuestrea a 48 kHz al cargarlo
+    let samples: Vec<i16> = (0..1600).map(|i| (i % 100) as i16 * 100).collect();
+    write_wav(&path, &samples, 16_000).unwrap();
+
+    let loaded = read_wav(&path).unwrap();
+    assert_eq!(loaded.len(), samples.len() * 3);
+
+    let mut input = WavInput::open(&path, false).unwrap();
+    let frames: Vec<_> = std::iter::from_fn(|| input.read_frame()).collect();
+    assert_eq!(frames.len(), 5); // 100 ms en frames de 20 ms
+    assert!(frames.iter().all(|frame| frame.len() == FRAME_SAMPLES));
+    std::fs::remove_file(&path).ok();
+
+    // PCM ida y vuelta, y silencio para los paquetes perdidos
+    let mut codec = Pcm16Codec;
+    let encoded = codec.encode(&frames[1]).unwrap();
+    assert_eq!(codec.decode(Some(&encoded)).unwrap(), frames[1]);
+    assert_eq!(codec.decode(None).unwrap(), vec![0; FRAME_SAMPLES]);
+    assert!(read_wav(std::env::temp_dir().join("tn1-no-existe.wav")).is_err());
+
+    // Una cabecera con frecuencia 0 se rechaza en vez de remuestrear dividiendo por cero
+    write_wav(&path, &samples, 0).unwrap();
+    assert!(matches!(read_wav(&path), Err(VoiceError::Wav(_))));
+    assert!(WavInput::open(&path, false).is_err());
+    std::fs::remove_file(&path).ok();
+}
+
+#[test]
+fn attenuation_falls_off_with_distance() {
+    assert_eq!(attenuation(0.0, 40.0), 1.0);
+    assert_eq!(attenuation(FULL_VOLUME_DISTANCE, 40.0), 1.0);
+    assert!(attenuation(10.0, 40.0) > attenuation(20.0, 40.0));
+    assert!(attenuation(39.0, 40.0) > 0.0);
+    assert_eq!(attenuation(40.0, 40.0), 0.0);
+    assert_eq!(attenuation(1.0, 0.5), 0.0);
+}
@@ -0,0 +1,433 @@
+use bevy::prelude::*;
+use std::thread;
+use std::time::Instant;
+use tn1_server::lag_compensation::Hitbox;
+use tn1_server::networking::ServerState;
+use tn1_server::weapons::{PlayerHit, Shot, WeaponSettings};
+use tn1_shared::codec::WireCodec;
+use tn1_shared::components::{BodyZone, Health, PlayerId};
+use tn1_shared::conditioner::{LinkConditions, NetworkConditions};
+use tn1_shared::inventory::*;
+use tn1_shared::items::FireMode;
+use tn1_shared::medical::BodyHealth;
+use tn1_shared::protocol::ServerMessage;
+use tn1_shared::weapons::{aim_direction, WeaponPhase, WeaponStatus};
+use tn1_tests::*;
+
+fn status(h: &Harness, client: usize) -> Option<WeaponStatus> {
+    h.clients[client].weapon().status().cloned()
+}
+
+/// Pulsa y suelta una tecla y espera a que el servidor lo vea
+fn tap(h: &mut Harness, client: usize, key: KeyCode) {
+    h.clients[client].tap(key);
+    h.step_for(2);
+}
+
+/// Un disparo (clic), dejando antes que la cadencia permita otro
+fn click(h: &mut Harness, client: usize) {
+    h.step_for(12);
+    h.clients[client].hold_mouse(MouseButton::Left);
+    h.step_for(2);
+    h.clients[client].release_mouse(MouseButton::Left);
+    h.step_for(2);
+}
+
+/// Pistola en la funda con la recámara montada y el resto de munición de reserva
+fn armed_with_pistol(h: &mut Harness, client: usize, player_id: PlayerId, rounds: u32) -> ItemStack {
+    let pistol = h.give(client, player_id, "pistol", 1);
+    h.give(client, player_id, "ammo_9mm", rounds);
+    h.equip(client, pistol.id, EquipmentSlot::Holster);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, client).is_some()));
+    tap(h, client, KeyCode::KeyR);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, client).is_some_and(|status| status.can_fire())));
+    pistol
+}
+
+/// Fusil en la espalda (con mochila para la munición) y cargado
+fn armed_with_rifle(h: &mut Harness, client: usize, player_id: PlayerId, rounds: u32) {
+    let backpack = h.give(client, player_id, "backpack", 1);
+    h.equip(client, backpack.id, EquipmentSlot::Backpack);
+    let rifle = h.give(client, player_id, "rifle", 1);
+    h.give(client, player_id, "ammo_545", rounds);
+    h.equip(client, rifle.id, EquipmentSlot::Primary);
+    tap(h, client, KeyCode::KeyR);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, client).is_some_and(|status| status.can_fire())));
+}
+
+#[test]
+fn reloading_an_empty_pistol_takes_ammo_and_chambers_it() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let pistol = h.give(a, ana, "pistol", 1);
+    h.give(a, ana, "ammo_9mm", 40);
+    assert!(status(&h, a).is_none(), "sin arma equipada no hay nada en la mano");
+
+    h.equip(a, pistol.id, EquipmentSlot::Holster);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).is_some()));
+    let empty = status(&h, a).unwrap();
+    assert_eq!((empty.key.as_str(), empty.mode, empty.ammo, empty.reserve), ("pistol", FireMode::Semi, 0, 40));
+    assert!(!empty.can_fire());
+
+    // Vacía: cambia el cargador y después hay que montarla
+    tap(&mut h, a, KeyCode::KeyR);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| matches!(status(h, a).unwrap().phase, WeaponPhase::Reloading { .. })));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| matches!(status(h, a).unwrap().phase, WeaponPhase::Chambering { .. })));
+    let chambering = status(&h, a).unwrap();
+    assert_eq!((chambering.ammo, chambering.reserve, chambering.chambered), (15, 25, false));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().can_fire()));
+
+    // Las balas viven en el propio item y la munición sale de los bolsillos
+    let inventory = h.clients[a].inventory().unwrap();
+    assert_eq!(inventory.equipment.get(EquipmentSlot::Holster).unwrap().stack.ammo, Some(15));
+    assert_eq!(inventory.count("ammo_9mm"), 25);
+
+    // Con una en la recámara cabe un cargador entero detrás, sin montar
+    click(&mut h, a);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().ammo == 14));
+    tap(&mut h, a, KeyCode::KeyR);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().ammo == 16));
+    let full = status(&h, a).unwrap();
+    assert_eq!((full.phase, full.reserve), (WeaponPhase::Ready, 23));
+
+    // Llena no se recarga
+    tap(&mut h, a, KeyCode::KeyR);
+    h.step_for(5);
+    assert_eq!(status(&h, a).unwrap().phase, WeaponPhase::Ready);
+}
+
+#[test]
+fn shots_hit_what_the_shooter_aims_at() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    armed_with_pistol(&mut h, a, ana, 20);
+
+    // Bea 5 m delante de Ana (yaw 0 mira hacia -Z); la historia de hitboxes
+    // tiene que haberla visto ahí
+    h.teleport(ana, Vec3::ZERO);
+    h.teleport(bea, Vec3::new(0.0, 0.0, -5.0));
+    h.step_for(20);
+    h.clients[a].set_yaw(0.0);
+    h.clients[a].set_pitch(0.0);
+
+    // El cliente predice el disparo antes de que conteste el servidor
+    h.clients[a].hold_mouse(MouseButton::Left);
+    h.clients[a].update();
+    assert_eq!((h.clients[a].weapon().predicted_shots(), h.clients[a].weapon().predicted_ammo()), (1, 14));
+    h.clients[a].release_mouse(MouseButton::Left);
+
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].weapon().last_hit().is_some()));
+    // A la altura de los ojos da en la cabeza: 30 de la pistola, menos lo poco
+    // que frena el aire en 5 m, por el multiplicador de la cabeza
+    let &(shooter, target, damage) = h.clients[b].weapon().last_hit().unwrap();
+    assert_eq!((shooter, target), (ana, bea));
+    assert!(damage > 29.0 * 1.2 && damage <= 30.0 * 1.2, "{damage}");
+    let hurt = h.server.component::<BodyHealth>(bea).unwrap();
+    assert_eq!(hurt.get(BodyZone::Head), 100.0 - damage);
+    assert_eq!(hurt.get(BodyZone::Thorax), 150.0);
+    assert_eq!(h.server.component::<Health>(bea).unwrap().current, hurt.health().current);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().ammo == 14));
+    assert_eq!(h.clients[a].weapon().predicted_ammo(), 14);
+
+    // Mirando a otro lado no le da
+    h.clients[a].set_yaw(std::f32::consts::PI);
+    h.step_for(2);
+    click(&mut h, a);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().ammo == 13));
+    h.step_for(5);
+    assert_eq!(h.server.component::<BodyHealth>(bea).unwrap(), hurt);
+}
+
+#[test]
+fn combat_messages_only_reach_players_in_the_world() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    armed_with_pistol(&mut h, a, ana, 20);
+    h.teleport(ana, Vec3::ZERO);
+    h.teleport(bea, Vec3::new(0.0, 0.0, -5.0));
+    h.step_for(20);
+    h.clients[a].set_yaw(0.0);
+    h.clients[a].set_pitch(0.0);
+
+    // Un socket que solo ha hecho el handshake no está en el mundo
+    let mut lurker = RawClient::connect(h.server.address).unwrap();
+    lurker.hello("lurker", WireCodec::SUPPORTED.to_vec()).unwrap();
+    let mut received = Vec::new();
+    assert!(h.step_until(NETWORK_TIMEOUT, |_| {
+        received.extend(lurker.poll().unwrap());
+        received.iter().any(|message| matches!(message, ServerMessage::Welcome { .. }))
+    }));
+
+    click(&mut h, a);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].weapon().last_hit().is_some()));
+    h.step_for(5);
+    received.extend(lurker.poll().unwrap());
+    assert!(
+        !received.iter().any(|message| matches!(message, ServerMessage::ShotFired { .. } | ServerMessage::Hit { .. } | ServerMessage::Kill { .. })),
+        "{received:?}"
+    );
+}
+
+#[test]
+fn fire_modes_follow_the_selector() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (a, ana) = h.join_grounded("ana");
+    armed_with_rifle(&mut h, a, ana, 60);
+    assert_eq!(status(&h, a).unwrap().mode, FireMode::Auto);
+    assert_eq!(status(&h, a).unwrap().ammo, 30);
+
+    // Automático: dispara mientras se mantenga el gatillo
+    h.clients[a].hold_mouse(MouseButton::Left);
+    h.step_for(60);
+    h.clients[a].release_mouse(MouseButton::Left);
+    h.step_for(5);
+    let after_auto = status(&h, a).unwrap().ammo;
+    assert!(after_auto <= 22, "650 disparos por minuto durante un segundo: quedan {}", after_auto);
+
+    // Ráfaga: tres por pulsación
+    tap(&mut h, a, KeyCode::KeyB);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().mode == FireMode::Burst));
+    click(&mut h, a);
+    h.step_for(20);
+    assert_eq!(status(&h, a).unwrap().ammo, after_auto - 3);
+
+    // Semiautomático: uno por pulsación aunque se mantenga
+    tap(&mut h, a, KeyCode::KeyB);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().mode == FireMode::Semi));
+    h.clients[a].hold_mouse(MouseButton::Left);
+    h.step_for(30);
+    h.clients[a].release_mouse(MouseButton::Left);
+    h.step_for(5);
+    assert_eq!(status(&h, a).unwrap().ammo, after_auto - 4);
+
+    // Y vuelve al primero
+    tap(&mut h, a, KeyCode::KeyB);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().mode == FireMode::Auto));
+}
+
+#[test]
+fn kills_are_announced_and_the_dead_cannot_shoot() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    let (c, cal) = h.join_grounded("cal");
+    armed_with_pistol(&mut h, a, ana, 20);
+    armed_with_pistol(&mut h, b, bea, 20);
+
+    // Cal mira desde un lado, fuera de la línea de tiro
+    h.teleport(ana, Vec3::ZERO);
+    h.teleport(bea, Vec3::new(0.0, 0.0, -5.0));
+    h.teleport(cal, Vec3::new(5.0, 0.0, 5.0));
+    h.step_for(20);
+    h.clients[a].set_yaw(0.0);
+    h.clients[a].set_pitch(0.0);
+    for _ in 0..4 {
+        click(&mut h, a);
+    }
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[c].weapon().last_kill().is_some()));
+    assert_eq!(h.clients[c].weapon().last_kill(), Some(&(ana, bea)));
+    assert!(h.clients[c].chat_lines().iter().any(|line| line.contains("ana ha matado a bea")));
+    assert_eq!(h.server.component::<Health>(bea).unwrap().current, 0.0);
+
+    // A un muerto no se le vuelve a matar ni se le cuentan más muertes
+    click(&mut h, a);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().ammo == 10));
+    h.step_for(5);
+    assert_eq!(h.clients[c].chat_lines().iter().filter(|line| line.contains("ha matado")).count(), 1);
+
+    // Bea, muerta, aprieta el gatillo apuntando a Ana
+    h.clients[b].set_yaw(std::f32::consts::PI);
+    h.step_for(2);
+    click(&mut h, b);
+    h.step_for(10);
+    assert_eq!(status(&h, b).unwrap().ammo, 15);
+    assert_eq!(h.server.component::<Health>(ana).unwrap().current, 100.0);
+}
+
+#[test]
+fn shots_beyond_hitscan_range_fly_as_projectiles() {
+    let mut h = Harness::with_weapons(WeaponSettings { hitscan_range: 10.0, ..weapon_settings() });
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    armed_with_pistol(&mut h, a, ana, 20);
+
+    h.teleport(ana, Vec3::ZERO);
+    h.teleport(bea, Vec3::new(0.0, 0.0, -40.0));
+    h.step_for(20);
+    h.clients[a].set_yaw(0.0);
+    h.clients[a].set_pitch(0.0);
+    click(&mut h, a);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].weapon().last_hit().is_some()));
+    // A 40 m la bala llega más lenta y hace algo menos de daño
+    let &(shooter, target, damage) = h.clients[b].weapon().last_hit().unwrap();
+    assert_eq!((shooter, target), (ana, bea));
+    assert!(damage > 20.0 * 1.2 && damage < 29.0 * 1.2, "{damage}");
+    assert_eq!(h.server.component::<BodyHealth>(bea).unwrap().get(BodyZone::Head), 100.0 - damage);
+}
+
+#[test]
+fn rifle_rounds_go_through_a_body_and_pistol_rounds_do_not() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    let (c, cal) = h.join_grounded("cal");
+    armed_with_rifle(&mut h, a, ana, 30);
+    armed_with_pistol(&mut h, c, cal, 20);
+    tap(&mut h, a, KeyCode::KeyB);
+    tap(&mut h, a, KeyCode::KeyB);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| status(h, a).unwrap().mode == FireMode::Semi));
+
+    // Los tres en fila: Ana, Bea a 5 m y Cal detrás de Bea
+    h.teleport(ana, Vec3::ZERO);
+    h.teleport(bea, Vec3::new(0.0, 0.0, -5.0));
+    h.teleport(cal, Vec3::new(0.0, 0.0, -10.0));
+    h.step_for(20);
+    h.clients[a].set_yaw(0.0);
+    h.clients[a].set_pitch(0.0);
+    click(&mut h, a);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[c].weapon().last_hit().is_some_and(|hit| hit.1 == cal)));
+    let through = h.clients[c].weapon().last_hit().unwrap().2;
+    let first = 100.0 - h.server.component::<BodyHealth>(bea).unwrap().get(BodyZone::Head);
+    assert!(first > 40.0 * 1.2 && first <= 45.0 * 1.2, "{first}");
+    // Sale de la cabeza con bastante menos energía
+    assert!(through > 5.0 && through < first * 0.8, "{through}");
+    assert_eq!(h.server.component::<BodyHealth>(cal).unwrap().get(BodyZone::Head), 100.0 - through);
+
+    // La 9 mm de Cal se queda en Bea y no llega a Ana
+    h.clients[c].set_yaw(std::f32::consts::PI);
+    h.clients[c].set_pitch(0.0);
+    h.step_for(2);
+    click(&mut h, c);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[b].weapon().last_hit().is_some_and(|hit| hit.0 == cal)));
+    h.step_for(10);
+    assert!(100.0 - first - h.server.component::<BodyHealth>(bea).unwrap().get(BodyZone::Head) > 29.0);
+    assert_eq!(h.server.component::<BodyHealth>(ana).unwrap(), BodyHealth::default());
+}
+
+/// Direcciones de los disparos, en orden
+#[derive(Resource, Default)]
+struct ShotDirections(Vec<Vec3>);
+
+fn collect_shots(mut shots: EventReader<Shot>, mut directions: ResMut<ShotDirections>) {
+    directions.0.extend(shots.read().map(|shot| shot.direction));
+}
+
+/// Doce disparos desde la cadera al cielo; devuelve hacia dónde salieron
+fn hip_fire(spread: f32) -> Vec<Vec3> {
+    let mut h = Harness::with_weapons(WeaponSettings { hip_spread: spread, ..weapon_settings() });
+    h.server.app.init_resource::<ShotDirections>().add_systems(Update, collect_shots);
+    let (a, ana) = h.join_grounded("ana");
+    armed_with_pistol(&mut h, a, ana, 20);
+    h.clients[a].set_yaw(0.0);
+    h.clients[a].set_pitch(0.5);
+    for _ in 0..12 {
+        click(&mut h, a);
+    }
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.app.world().resource::<ShotDirections>().0.len() == 12));
+    h.server.app.world().resource::<ShotDirections>().0.clone()
+}
+
+#[test]
+fn spread_stays_inside_the_cone_and_follows_the_world_seed() {
+    let aim = aim_direction(0.0, 0.5);
+    let directions = hip_fire(3.0);
+    let angles: Vec<f32> = directions.iter().map(|direction| direction.angle_between(aim).to_degrees()).collect();
+    assert!(angles.iter().all(|angle| *angle <= 3.0 + 1e-3), "{angles:?}");
+    assert!(angles.iter().any(|angle| *angle > 0.5), "sin dispersión: {angles:?}");
+    assert!(directions.windows(2).all(|pair| pair[0] != pair[1]));
+
+    // Misma semilla del mundo, mismos disparos
+    assert_eq!(hip_fire(3.0), directions);
+}
+
+/// Cada disparo: si habría dado a alguien sin retroceder en el tiempo, y cuánto se retrocedió
+#[derive(Resource, Default)]
+struct LaggedShots {
+    shots: Vec<(bool, f64)>,
+    hits: Vec<PlayerHit>,
+}
+
+fn collect_lagged_shots(
+    mut shots: EventReader<Shot>,
+    mut hits: EventReader<PlayerHit>,
+    players: Query<(&PlayerId, &Transform)>,
+    time: Res<Time>,
+    mut lagged: ResMut<LaggedShots>,
+) {
+    for shot in shots.read() {
+        let live_hit = players
+            .iter()
+            .filter(|(id, _)| **id != shot.player_id)
+            .any(|(_, transform)| Hitbox::default().ray_intersection(transform.translation, shot.origin, shot.direction, 100.0).is_some());
+        lagged.shots.push((live_hit, time.elapsed_secs_f64() - shot.view_time));
+    }
+    lagged.hits.extend(hits.read().cloned());
+}
+
+/// Ticks al ritmo real: la latencia simulada es de reloj de pared
+fn paced(h: &mut Harness, ticks: u32) {
+    for _ in 0..ticks {
+        let started = Instant::now();
+        h.step();
+        thread::sleep(TICK.saturating_sub(started.elapsed()));
+    }
+}
+
+#[test]
+fn lagged_shooter_hits_a_target_that_starts_running_after_they_fire() {
+    let mut h = Harness::with_weapons(weapon_settings());
+    h.server.app.init_resource::<LaggedShots>().add_systems(PostUpdate, collect_lagged_shots);
+    let (a, ana) = h.join_grounded("ana");
+    let (b, bea) = h.join_grounded("bea");
+    armed_with_pistol(&mut h, a, ana, 20);
+    h.teleport(ana, Vec3::ZERO);
+    h.teleport(bea, Vec3::new(0.0, 0.0, -10.0));
+    h.clients[a].set_yaw(0.0);
+    h.clients[a].set_pitch(0.0);
+    h.clients[b].set_yaw(0.0);
+
+    // Solo Ana va con lag: 150 ms por sentido en su conexión
+    let lag = LinkConditions { latency_ms: 150, ..default() };
+    h.clients[a].app.world().resource::<NetworkConditions>().set(lag);
+    let entity = {
+        let world = h.server.app.world_mut();
+        world.query::<(Entity, &PlayerId)>().iter(world).find(|(_, id)| **id == ana).unwrap().0
+    };
+    let rtt = |h: &Harness| h.server.app.world().resource::<ServerState>().rtt_for_entity(entity).unwrap();
+    // La media del RTT tardaría muchos pings en olvidar la red sin lag: el primer
+    // ping con lag la vuelve a fijar
+    for client in h.server.app.world().resource::<ServerState>().clients.lock().unwrap().values_mut() {
+        client.rtt = 0.0;
+    }
+    for _ in 0..10 {
+        if rtt(&h) > 0.0 {
+            break;
+        }
+        paced(&mut h, 30);
+    }
+    assert!(rtt(&h) >= 0.15, "rtt {}", rtt(&h));
+    assert!(h.clients[a].known_position(bea).unwrap().distance(Vec3::new(0.0, 0.0, -10.0)) < 0.01);
+
+    // Bea echa a correr mientras el disparo de Ana viaja: cuando llega ya se ha
+    // apartado, pero Ana la veía quieta en la mira. El cliente pinta el último
+    // estado que recibe, así que basta con retroceder medio RTT
+    let half_rtt = rtt(&h) as f64 / 2.0;
+    h.clients[a].hold_mouse(MouseButton::Left);
+    paced(&mut h, 2);
+    h.clients[a].release_mouse(MouseButton::Left);
+    paced(&mut h, 2);
+    h.clients[b].hold(KeyCode::KeyD);
+    h.clients[b].hold(KeyCode::ShiftLeft);
+    paced(&mut h, 30);
+
+    let lagged = h.server.app.world().resource::<LaggedShots>();
+    assert_eq!(lagged.shots.len(), 1);
+    let (live_hit, rewind) = lagged.shots[0];
+    assert!(!live_hit, "al resolverlo Bea ya no estaba en la línea de tiro");
+    assert!((rewind - half_rtt).abs() < 0.03, "{rewind} vs {half_rtt}");
+    assert_eq!(lagged.hits.len(), 1);
+    assert_eq!((lagged.hits[0].shooter, lagged.hits[0].target, lagged.hits[0].zone), (ana, bea, BodyZone::Head));
+    assert!(h.server.component::<BodyHealth>(bea).unwrap().get(BodyZone::Head) < 100.0);
+}
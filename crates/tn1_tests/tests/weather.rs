@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+use tn1_server::tls::ServerTls;
+use tn1_server::weather::{BodyTemperature, WeatherPlugin, WeatherSettings, WorldWeather};
+use tn1_server::world::{WorldPlugin, WorldSettings};
+use tn1_shared::weather::*;
+use tn1_tests::*;
+
+/// Frentes que no acaban solos: el clima solo cambia cuando el test lo fuerza
+fn frozen_settings(start: WeatherKind, base_temperature: f32) -> WeatherSettings {
+    WeatherSettings { start: Some(start), front_duration: (1e9, 1e9), transition: 5.0, base_temperature }
+}
+
+/// Servidor con el reloj parado a la hora dada y el clima fijo
+fn weather_harness(start: WeatherKind, base_temperature: f32, hour: f32) -> Harness {
+    let server = TestServer::start_with(test_config(), ServerTls(None), |app| {
+        app.add_plugins((WorldPlugin, WeatherPlugin { settings: frozen_settings(start, base_temperature) }))
+            .insert_resource(WorldSettings { current_time: hour, time_scale: 0.0, seed: 7, ..default() });
+    });
+    Harness { server, clients: Vec::new() }
+}
+
+fn set_weather(h: &mut Harness, kind: WeatherKind) {
+    h.server.app.world_mut().resource_scope(|world, mut weather: Mut<WorldWeather>| {
+        weather.set(kind, world.resource::<WeatherSettings>());
+    });
+}
+
+fn weather(kind: WeatherKind, wind: Vec2, temperature: f32) -> Weather {
+    Weather { kind, intensity: 1.0, wind, temperature }
+}
+
+#[test]
+fn weather_rules() {
+    let clear = weather(WeatherKind::Clear, Vec2::ZERO, 20.0);
+    let rain = weather(WeatherKind::Rain, Vec2::new(3.0, 0.0), 10.0);
+    let fog = weather(WeatherKind::Fog, Vec2::ZERO, 8.0);
+    let storm = weather(WeatherKind::Storm, Vec2::new(0.0, -15.0), 5.0);
+
+    // La niebla es lo que más tapa; un frente a medias tapa la mitad
+    assert_eq!(clear.visibility(), 1.0);
+    assert!(fog.visibility() < storm.visibility() && storm.visibility() < rain.visibility());
+    let half_fog = Weather { intensity: 0.5, ..fog };
+    assert!((half_fog.visibility() - (1.0 + fog.visibility()) / 2.0).abs() < 1e-6);
+
+    // Los pasos: quieto no suena, corriendo se oye más lejos y la tormenta lo tapa casi todo
+    assert_eq!(footstep_range(0.5, 0.0), 0.0);
+    assert!(footstep_range(10.0, 0.0) > footstep_range(7.0, 0.0));
+    assert!(footstep_range(7.0, storm.footstep_masking()) < footstep_range(7.0, rain.footstep_masking()));
+    assert!(footstep_range(7.0, rain.footstep_masking()) < footstep_range(7.0, clear.footstep_masking()));
+
+    // Viento y ropa mojada enfrían; con buen tiempo el cuerpo se recupera
+    assert!(storm.feels_like() < storm.temperature - 10.0);
+    assert!(body_heat_rate(&storm, NORMAL_BODY_TEMPERATURE, 0.0) < 0.0);
+    assert!(body_heat_rate(&storm, 36.0, 1.0) > body_heat_rate(&storm, 36.0, 0.0));
+    assert!(body_heat_rate(&clear, 36.0, 0.0) > 0.0);
+    assert_eq!(update_body_temperature(&clear, NORMAL_BODY_TEMPERATURE, 1.0, 60.0), NORMAL_BODY_TEMPERATURE);
+    let mut body = NORMAL_BODY_TEMPERATURE;
+    for _ in 0..600 {
+        body = update_body_temperature(&storm, body, 0.0, 1.0);
+    }
+    assert!((30.0..HYPOTHERMIA).contains(&body), "{body}");
+
+    // Didion: sin pérdida de velocidad no hay desvío; con ella, en la dirección del viento
+    assert_eq!(storm.wind_drift(300.0, 900.0, 300.0 / 900.0), Vec3::ZERO);
+    let drift = storm.wind_drift(300.0, 900.0, 0.4);
+    assert!((drift - Vec3::new(0.0, 0.0, -15.0) * (0.4 - 300.0 / 900.0)).length() < 1e-4);
+    assert!(weather(WeatherKind::Snow, Vec2::ZERO, -10.0).air_density() > clear.air_density());
+
+    assert_eq!("Storm".parse::<WeatherKind>(), Ok(WeatherKind::Storm));
+    assert!("hail".parse::<WeatherKind>().is_err());
+    assert_eq!(WeatherKind::Rain.for_temperature(-5.0), WeatherKind::Snow);
+    assert_eq!(WeatherKind::Snow.for_temperature(10.0), WeatherKind::Rain);
+}
+
+#[test]
+fn fronts_come_and_go_smoothly() {
+    let settings = WeatherSettings { start: None, front_duration: (20.0, 40.0), transition: 5.0, base_temperature: 15.0 };
+    let run = |seed: u64, settings: &WeatherSettings| {
+        let mut weather = WorldWeather::new(seed, WeatherKind::Clear, settings);
+        (0..20_000)
+            .map(|tick| {
+                weather.advance(0.1, (tick as f32 * 0.01) % 24.0, settings);
+                weather.weather()
+            })
+            .collect::<Vec<Weather>>()
+    };
+
+    // Misma semilla, mismo clima
+    let history = run(3, &settings);
+    assert_eq!(history, run(3, &settings));
+    assert_ne!(history, run(4, &settings));
+
+    let kinds: HashSet<WeatherKind> = history.iter().map(|weather| weather.kind).collect();
+    assert!(kinds.len() >= 4, "pocos frentes: {kinds:?}");
+    // Sin frío no nieva
+    assert!(!kinds.contains(&WeatherKind::Snow));
+
+    for pair in history.windows(2) {
+        // La intensidad entra y sale poco a poco; el frente solo cambia a intensidad 0
+        assert!((pair[1].intensity - pair[0].intensity).abs() <= 0.1 / 5.0 + 1e-5);
+        if pair[0].kind != pair[1].kind {
+            assert!(pair[0].intensity < 0.1 / 5.0 + 1e-5 && pair[1].intensity < 1e-5);
+        }
+        assert!((pair[1].visibility() - pair[0].visibility()).abs() < 0.02);
+        assert!((pair[1].wind - pair[0].wind).length() <= 0.5 * 0.1 + 1e-4);
+        assert!(pair[1].wind_speed() <= 20.0);
+    }
+
+    // Con frío la lluvia es nieve
+    let cold = WeatherSettings { base_temperature: -15.0, ..settings };
+    let kinds: HashSet<WeatherKind> = run(3, &cold).iter().map(|weather| weather.kind).collect();
+    assert!(kinds.contains(&WeatherKind::Snow) && !kinds.contains(&WeatherKind::Rain), "{kinds:?}");
+}
+
+#[test]
+fn clients_feel_the_storm() {
+    let mut h = weather_harness(WeatherKind::Storm, -5.0, 3.0);
+    let a = h.join("ana");
+    let player_id = h.clients[a].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].weather().is_some()));
+
+    let weather = h.clients[a].weather().unwrap();
+    assert_eq!(weather.kind, WeatherKind::Storm);
+    assert!(weather.temperature < -5.0);
+    assert!(weather.wind_speed() >= 10.0);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.client(a).precipitation() > 0));
+
+    // El frío va bajando la temperatura corporal y el servidor se la cuenta al jugador
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].body_temperature().is_some()));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].body_temperature().unwrap() < 36.8));
+    let body = h.server.component::<BodyTemperature>(player_id).unwrap().0;
+    assert!((body - h.clients[a].body_temperature().unwrap()).abs() < 0.2);
+
+    // Al escampar deja de llover y el cuerpo se recupera
+    h.server.app.world_mut().resource_mut::<WeatherSettings>().base_temperature = 25.0;
+    set_weather(&mut h, WeatherKind::Clear);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].weather().unwrap().kind == WeatherKind::Clear));
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.client(a).precipitation() == 0));
+    let cold = h.clients[a].body_temperature().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].body_temperature().unwrap() > cold));
+}
+
+#[test]
+fn bad_weather_hides_players() {
+    let mut h = weather_harness(WeatherKind::Clear, 20.0, 12.0);
+    let a = h.join("ana");
+    let b = h.join("bea");
+    let bea = h.clients[b].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].weather().is_some() && h.client(a).sees(bea)));
+
+    h.client(b).hold(KeyCode::KeyW);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.server.player(bea).unwrap().translation.z < -80.0));
+    h.client(b).release(KeyCode::KeyW);
+    h.step_for(30);
+    // A 80 m de día se ve a cualquiera; con niebla no, con tormenta aún sí
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.client(a).sees(bea)));
+    set_weather(&mut h, WeatherKind::Fog);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| !h.client(a).sees(bea)));
+    set_weather(&mut h, WeatherKind::Storm);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.client(a).sees(bea)));
+    // Con buen tiempo nadie pasa frío
+    assert_eq!(h.clients[a].body_temperature(), Some(NORMAL_BODY_TEMPERATURE));
+}
+
+#[test]
+fn rain_masks_footsteps() {
+    let mut h = weather_harness(WeatherKind::Clear, 20.0, 12.0);
+    let a = h.join("ana");
+    let b = h.join("bea");
+    let bea = h.clients[b].player_id().unwrap();
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| h.clients[a].weather().is_some() && h.client(a).sees(bea)));
+    // Quieta no se la oye
+    h.step_for(10);
+    assert!(!h.clients[a].hears(bea));
+
+    // Andando se la oye a unos metros...
+    h.client(b).hold(KeyCode::KeyW);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| {
+        h.clients[a].hears(bea) && h.server.player(bea).unwrap().translation.z < -8.0
+    }));
+    // ...pero no con la tormenta encima
+    set_weather(&mut h, WeatherKind::Storm);
+    assert!(h.step_until(NETWORK_TIMEOUT, |h| !h.clients[a].hears(bea)));
+    assert!(h.server.player(bea).unwrap().translation.z > -20.0);
+    h.client(b).release(KeyCode::KeyW);
+}
@@ -62,12 +62,106 @@ CREATE TABLE IF NOT EXISTS player_sessions (
     is_active BOOLEAN DEFAULT TRUE
 );
 
+-- Chat log: every message as typed, kept for moderation.
+-- player_id has no FK: guest players get an in-memory id that never reaches players
+CREATE TABLE IF NOT EXISTS chat_messages (
+    id BIGSERIAL PRIMARY KEY,
+    player_id UUID NOT NULL,
+    username VARCHAR(32) NOT NULL,
+    channel VARCHAR(16) NOT NULL, -- global, proximity, team, whisper
+    target VARCHAR(32), -- whisper recipient or team name
+    message TEXT NOT NULL,
+    filtered BOOLEAN NOT NULL DEFAULT FALSE,
+    blocked_reason VARCHAR(32), -- NULL if delivered
+    sent_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Item instances: every item a player carries, one row per item.
+-- The id is the item's permanent UUID, so the same item can never be stored twice;
+-- saving it for another player moves the row instead of copying it.
+-- owner_id has no FK for the same reason as chat_messages.
+-- Exactly one placement column is set: a pocket, another item's grid, mounted
+-- on another item as an attachment, or an equipment slot.
+CREATE TABLE IF NOT EXISTS item_instances (
+    id UUID PRIMARY KEY,
+    owner_id UUID NOT NULL,
+    item_key VARCHAR(64) NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    durability REAL CHECK (durability BETWEEN 0 AND 1),
+    ammo INTEGER CHECK (ammo >= 0),
+    pocket SMALLINT CHECK (pocket >= 0),
+    parent_id UUID REFERENCES item_instances(id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+    attached_to UUID REFERENCES item_instances(id) ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED,
+    slot VARCHAR(32),
+    grid_x SMALLINT NOT NULL DEFAULT 0 CHECK (grid_x >= 0),
+    grid_y SMALLINT NOT NULL DEFAULT 0 CHECK (grid_y >= 0),
+    rotated BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CHECK (num_nonnulls(pocket, parent_id, attached_to, slot) = 1),
+    CHECK (parent_id <> id AND attached_to <> id),
+    -- One item per equipment slot (NULL slots are distinct). Checked at commit:
+    -- the upsert goes row by row and a slot may change hands mid-save.
+    UNIQUE (owner_id, slot) DEFERRABLE INITIALLY DEFERRED
+);
+
+-- Loot containers: state of world containers (id from the chunk grid, stable per seed)
+CREATE TABLE IF NOT EXISTS loot_containers (
+    id BIGINT PRIMARY KEY,
+    table_key VARCHAR(64) NOT NULL,
+    restock_at TIMESTAMP WITH TIME ZONE,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Loot items: loose items waiting in a loot container
+CREATE TABLE IF NOT EXISTS loot_items (
+    id UUID PRIMARY KEY,
+    container_id BIGINT NOT NULL REFERENCES loot_containers(id) ON DELETE CASCADE,
+    item_key VARCHAR(64) NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    durability REAL CHECK (durability BETWEEN 0 AND 1),
+    ammo INTEGER CHECK (ammo >= 0),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Trade log: every completed player-to-player trade, for dispute resolution.
+-- Written in the same transaction that moves the items between inventories.
+CREATE TABLE IF NOT EXISTS trade_log (
+    id BIGSERIAL PRIMARY KEY,
+    player_a UUID NOT NULL, -- who proposed the trade
+    player_b UUID NOT NULL,
+    username_a VARCHAR(32) NOT NULL,
+    username_b VARCHAR(32) NOT NULL,
+    completed_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Trade log items: what changed hands, including items inside other items.
+-- item_id has no FK: the row in item_instances may be gone by the time of a dispute.
+CREATE TABLE IF NOT EXISTS trade_log_items (
+    trade_id BIGINT NOT NULL REFERENCES trade_log(id) ON DELETE CASCADE,
+    item_id UUID NOT NULL,
+    item_key VARCHAR(64) NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    from_player UUID NOT NULL,
+    to_player UUID NOT NULL
+);
+
 -- Indexes for performance
 CREATE INDEX idx_players_username ON players(username);
 CREATE INDEX idx_players_email ON players(email);
 CREATE INDEX idx_player_sessions_token ON player_sessions(session_token);
 CREATE INDEX idx_player_sessions_active ON player_sessions(player_id, is_active);
 CREATE INDEX idx_player_states_online ON player_states(is_online);
+CREATE INDEX idx_chat_messages_player ON chat_messages(player_id, sent_at);
+CREATE INDEX idx_chat_messages_sent_at ON chat_messages(sent_at);
+CREATE INDEX idx_item_instances_owner ON item_instances(owner_id);
+CREATE INDEX idx_item_instances_parent ON item_instances(parent_id);
+CREATE INDEX idx_item_instances_attached ON item_instances(attached_to);
+CREATE INDEX idx_loot_items_container ON loot_items(container_id);
+CREATE INDEX idx_trade_log_player_a ON trade_log(player_a, completed_at);
+CREATE INDEX idx_trade_log_player_b ON trade_log(player_b, completed_at);
+CREATE INDEX idx_trade_log_items_trade ON trade_log_items(trade_id);
+CREATE INDEX idx_trade_log_items_item ON trade_log_items(item_id);
 
 -- Function to update timestamps
 CREATE OR REPLACE FUNCTION update_updated_at_column()
@@ -85,6 +179,12 @@ CREATE TRIGGER update_player_profiles_updated_at BEFORE UPDATE
 CREATE TRIGGER update_player_states_updated_at BEFORE UPDATE
     ON player_states FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
 
+CREATE TRIGGER update_item_instances_updated_at BEFORE UPDATE
+    ON item_instances FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER update_loot_containers_updated_at BEFORE UPDATE
+    ON loot_containers FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
 -- Initial data for testing (optional)
 -- INSERT INTO players (username, password_hash) VALUES 
 -- ('test_player', crypt('password123', gen_salt('bf')));
\ No newline at end of file
@@ -0,0 +1,46 @@
+#!/bin/bash
+
+# Prueba de carga: levanta un servidor y lanza bots headless contra él
+# Uso: ./run_bots.sh [bots] [segundos]
+
+BOTS=${1:-20}
+DURATION=${2:-60}
+
+echo "🤖 PRUEBA DE CARGA TRUST-NO-1 ($BOTS bots, ${DURATION}s)"
+echo "=================================================="
+
+echo "📦 Compilando servidor y bots..."
+cargo build --bin tn1-server --bin tn1-bot
+if [ $? -ne 0 ]; then
+    echo "❌ Error al compilar"
+    exit 1
+fi
+
+cleanup() {
+    echo ""
+    echo "🧹 Deteniendo servidor..."
+    kill $SERVER_PID 2>/dev/null
+}
+trap cleanup EXIT
+
+echo "🌐 Iniciando servidor..."
+RUST_LOG=warn cargo run --bin tn1-server > /tmp/tn1_load_server.log 2>&1 &
+SERVER_PID=$!
+
+# Esperar a que el servidor acepte conexiones
+for i in $(seq 1 60); do
+    if grep -q "Servidor autoritativo iniciado" /tmp/tn1_load_server.log 2>/dev/null; then
+        break
+    fi
+    sleep 1
+done
+
+cargo run --bin tn1-bot -- --bots "$BOTS" --duration "$DURATION" --min-connected "$BOTS"
+STATUS=$?
+
+if [ $STATUS -eq 0 ]; then
+    echo "✅ Prueba de carga superada"
+else
+    echo "❌ Prueba de carga fallida (logs del servidor en /tmp/tn1_load_server.log)"
+fi
+exit $STATUS